@@ -0,0 +1,223 @@
+use reqwest::header::{self, HeaderValue};
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Upgraded};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::WebSocketStream;
+use url::Url;
+
+/// Cookie the web server sets once signed in, its value is the session token.
+const SESSION_COOKIE_NAME: &str = "privaxy_session";
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("invalid URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("unable to read the CA certificate: {0}")]
+    CaCertificate(std::io::Error),
+    #[error("unable to reach privaxy: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("signing in is required, run `privaxy-cli login` and pass the token with --token")]
+    Unauthorized,
+    #[error("{message} ({status})")]
+    Api { status: StatusCode, message: String },
+    #[error("{0}")]
+    NotFound(String),
+    #[error("invalid password")]
+    InvalidPassword,
+    #[error("privaxy did not return a session token")]
+    MissingToken,
+    #[error("privaxy refused to open the event stream ({0})")]
+    WebSocketRefused(StatusCode),
+    #[error("privaxy rejected the subscription to requests: {0}")]
+    InvalidSubscription(String),
+    #[error("event stream error: {0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    #[error("unexpected response: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for ClientError {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        ClientError::WebSocket(Box::new(err))
+    }
+}
+
+/// Error returned by the API, along with a non-success status.
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    error: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SignInRequest<'a> {
+    password: &'a str,
+}
+
+/// Client of the API of a running privaxy, authenticated with a session token when
+/// web authentication is enabled.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    http_client: reqwest::Client,
+    /// URL of `/api/v1/`, under the base path of the web server.
+    api_url: Url,
+    token: Option<String>,
+}
+
+impl ApiClient {
+    pub fn new(
+        url: &Url,
+        token: Option<String>,
+        ca_certificate: Option<&Path>,
+        insecure: bool,
+    ) -> Result<Self, ClientError> {
+        let mut http_client = reqwest::Client::builder()
+            .user_agent(concat!("privaxy-cli/", env!("CARGO_PKG_VERSION")))
+            .danger_accept_invalid_certs(insecure);
+
+        if let Some(ca_certificate) = ca_certificate {
+            let pem = std::fs::read(ca_certificate).map_err(ClientError::CaCertificate)?;
+            http_client = http_client.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+        }
+
+        // `join` replaces the last segment of base paths not ending with a slash.
+        let mut url = url.clone();
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+
+        Ok(Self {
+            http_client: http_client.build()?,
+            api_url: url.join("api/v1/")?,
+            token: token.filter(|token| !token.is_empty()),
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder, ClientError> {
+        let request = self.http_client.request(method, self.api_url.join(path)?);
+
+        Ok(match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        })
+    }
+
+    /// Sends the request, turning statuses other than success into errors.
+    async fn send(request: RequestBuilder) -> Result<Response, ClientError> {
+        let response = request.send().await?;
+        let status = response.status();
+
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        if status == StatusCode::UNAUTHORIZED {
+            return Err(ClientError::Unauthorized);
+        }
+
+        let body = response.text().await?;
+        let message = match serde_json::from_str::<ApiError>(&body) {
+            Ok(api_error) => api_error.error,
+            Err(_) if body.trim().is_empty() => status
+                .canonical_reason()
+                .unwrap_or("Request failed")
+                .to_string(),
+            Err(_) => body.trim().to_string(),
+        };
+
+        Err(ClientError::Api { status, message })
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        Ok(Self::send(self.request(Method::GET, path)?)
+            .await?
+            .json()
+            .await?)
+    }
+
+    pub async fn get_with_query<T: DeserializeOwned, Q: Serialize>(
+        &self,
+        path: &str,
+        query: &Q,
+    ) -> Result<T, ClientError> {
+        Ok(Self::send(self.request(Method::GET, path)?.query(query))
+            .await?
+            .json()
+            .await?)
+    }
+
+    /// Sends a JSON body and returns the JSON response, if there is one.
+    pub async fn send_json<B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: &B,
+    ) -> Result<Option<serde_json::Value>, ClientError> {
+        let response = Self::send(self.request(method, path)?.json(body)).await?;
+        let body = response.bytes().await?;
+
+        if body.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_slice(&body)?))
+    }
+
+    pub async fn delete(&self, path: &str) -> Result<(), ClientError> {
+        Self::send(self.request(Method::DELETE, path)?).await?;
+
+        Ok(())
+    }
+
+    /// Signs in and returns the token of the new session.
+    pub async fn sign_in(&self, password: &str) -> Result<String, ClientError> {
+        let request = self
+            .request(Method::POST, "session")?
+            .json(&SignInRequest { password });
+        let response = match Self::send(request).await {
+            Err(ClientError::Unauthorized) => return Err(ClientError::InvalidPassword),
+            response => response?,
+        };
+
+        response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|cookie| cookie.to_str().ok())
+            .find_map(|cookie| {
+                let (name, value) = cookie.split(';').next()?.split_once('=')?;
+                (name.trim() == SESSION_COOKIE_NAME).then(|| value.trim().to_string())
+            })
+            .filter(|token| !token.is_empty())
+            .ok_or(ClientError::MissingToken)
+    }
+
+    /// Opens a WebSocket, such as `events`. The upgrade goes through the HTTP client so
+    /// that it is made with the same certificates and credentials as other requests.
+    pub async fn connect_websocket(
+        &self,
+        path: &str,
+    ) -> Result<WebSocketStream<Upgraded>, ClientError> {
+        let request = self
+            .request(Method::GET, path)?
+            .header(header::CONNECTION, HeaderValue::from_static("Upgrade"))
+            .header(header::UPGRADE, HeaderValue::from_static("websocket"))
+            .header(
+                header::SEC_WEBSOCKET_VERSION,
+                HeaderValue::from_static("13"),
+            )
+            .header(header::SEC_WEBSOCKET_KEY, generate_key());
+        let response = request.send().await?;
+
+        match response.status() {
+            StatusCode::SWITCHING_PROTOCOLS => {}
+            StatusCode::UNAUTHORIZED => return Err(ClientError::Unauthorized),
+            status => return Err(ClientError::WebSocketRefused(status)),
+        }
+
+        Ok(WebSocketStream::from_raw_socket(response.upgrade().await?, Role::Client, None).await)
+    }
+}
@@ -0,0 +1,540 @@
+use crate::client::{ApiClient, ClientError};
+use crate::{
+    BlockingCommand, Command, ExclusionsCommand, FilterGroup, FiltersCommand, LoginArgs,
+    RequestsCommand, StatsCommand,
+};
+use chrono::{DateTime, Local, Utc};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write};
+use tokio_tungstenite::tungstenite::Message;
+use url::Url;
+
+#[derive(Debug, Deserialize)]
+struct SessionStatus {
+    authentication_required: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Filter {
+    id: String,
+    enabled: bool,
+    title: String,
+    group: String,
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FilterRequest {
+    enabled: bool,
+    title: String,
+    group: FilterGroup,
+    url: Url,
+    headers: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct TemporaryExclusion {
+    host: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+struct TemporaryExclusionRequest<'a> {
+    host: &'a str,
+    /// Seconds until the exclusion expires.
+    duration: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct BlockingStatus {
+    enabled: bool,
+    /// When blocking is enabled again, if it is paused.
+    resume_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockingPause {
+    resume_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+struct TopQuery<'a> {
+    kind: crate::TopKind,
+    period: &'a str,
+    limit: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopEntry {
+    name: String,
+    requests: u64,
+    entity: Option<String>,
+}
+
+/// Sent over the event stream to only receive proxied requests.
+#[derive(Debug, Serialize)]
+struct Subscription<'a> {
+    types: [&'static str; 1],
+    hosts: &'a [String],
+    min_severity: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+struct RequestEvent {
+    now: DateTime<Utc>,
+    method: String,
+    url: String,
+    is_request_blocked: bool,
+    #[serde(default)]
+    client: String,
+    filter: Option<MatchedFilter>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatchedFilter {
+    rule: String,
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+pub(crate) async fn run(
+    client: &ApiClient,
+    command: Command,
+    json: bool,
+) -> Result<(), ClientError> {
+    match command {
+        Command::Login(login_args) => login(client, login_args, json).await,
+        Command::Logout => client.delete("session").await,
+        Command::Filters(command) => filters(client, command, json).await,
+        Command::Exclusions(command) => exclusions(client, command, json).await,
+        Command::Blocking(command) => blocking(client, command, json).await,
+        Command::Stats(command) => stats(client, command, json).await,
+        Command::Requests(command) => requests(client, command, json).await,
+    }
+}
+
+async fn login(client: &ApiClient, login_args: LoginArgs, json: bool) -> Result<(), ClientError> {
+    let session_status: SessionStatus = client.get("session").await?;
+
+    if !session_status.authentication_required {
+        eprintln!("Web authentication is disabled, requests don't need a token");
+        return Ok(());
+    }
+
+    let password = match login_args.password {
+        Some(password) => password,
+        None => {
+            let stdin = std::io::stdin();
+
+            if stdin.is_terminal() {
+                eprint!("Password: ");
+                let _ = std::io::stderr().flush();
+            }
+
+            let mut password = String::new();
+            let _ = stdin.lock().read_line(&mut password);
+            password.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    let token = client.sign_in(&password).await?;
+
+    if json {
+        print_json(&json!({ "token": token }));
+    } else {
+        println!("{}", token);
+    }
+
+    Ok(())
+}
+
+async fn filters(
+    client: &ApiClient,
+    command: FiltersCommand,
+    json: bool,
+) -> Result<(), ClientError> {
+    match command {
+        FiltersCommand::List => {
+            let filters: Value = client.get("filters").await?;
+
+            if json {
+                print_json(&filters);
+                return Ok(());
+            }
+
+            for filter in serde_json::from_value::<Vec<Filter>>(filters)? {
+                println!(
+                    "[{}] {}  {:<8}  {}  <{}>",
+                    if filter.enabled { "x" } else { " " },
+                    filter.id,
+                    filter.group,
+                    filter.title,
+                    filter.url
+                );
+            }
+        }
+        FiltersCommand::Add {
+            list_url,
+            title,
+            group,
+            disabled,
+        } => {
+            let filter_request = FilterRequest {
+                enabled: !disabled,
+                title: title.unwrap_or_else(|| list_url.to_string()),
+                group,
+                url: list_url,
+                headers: BTreeMap::new(),
+            };
+            let filter = client
+                .send_json(Method::POST, "filters", &filter_request)
+                .await?
+                .unwrap_or(Value::Null);
+
+            if json {
+                print_json(&filter);
+            } else {
+                let filter: Filter = serde_json::from_value(filter)?;
+                println!("Added {} ({})", filter.title, filter.id);
+            }
+        }
+        FiltersCommand::Rm { filter } => {
+            let filters: Vec<Value> = client.get("filters").await?;
+            let removed_filter = filters
+                .into_iter()
+                .find(|candidate| {
+                    candidate["id"].as_str() == Some(filter.as_str())
+                        || candidate["url"].as_str() == Some(filter.as_str())
+                })
+                .ok_or_else(|| {
+                    ClientError::NotFound(format!(
+                        "no filter list has the identifier or URL {}",
+                        filter
+                    ))
+                })?;
+            let id = removed_filter["id"].as_str().unwrap_or_default();
+
+            client.delete(&format!("filters/{}", id)).await?;
+
+            if json {
+                print_json(&removed_filter);
+            } else {
+                println!("Removed {}", removed_filter["title"].as_str().unwrap_or(id));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn get_exclusions(client: &ApiClient) -> Result<Vec<String>, ClientError> {
+    let exclusions: String = client.get("exclusions").await?;
+
+    Ok(exclusions
+        .lines()
+        .map(str::trim)
+        .filter(|exclusion| !exclusion.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+async fn put_exclusions(client: &ApiClient, exclusions: &[String]) -> Result<(), ClientError> {
+    client
+        .send_json(Method::PUT, "exclusions", &exclusions.join("\n"))
+        .await?;
+
+    Ok(())
+}
+
+async fn exclusions(
+    client: &ApiClient,
+    command: ExclusionsCommand,
+    json: bool,
+) -> Result<(), ClientError> {
+    match command {
+        ExclusionsCommand::List => {
+            let exclusions = get_exclusions(client).await?;
+            let temporary_exclusions: Vec<TemporaryExclusion> =
+                client.get("exclusions/temporary").await?;
+
+            if json {
+                print_json(&json!({
+                    "exclusions": exclusions,
+                    "temporary": temporary_exclusions,
+                }));
+                return Ok(());
+            }
+
+            for exclusion in exclusions {
+                println!("{}", exclusion);
+            }
+
+            for temporary_exclusion in temporary_exclusions {
+                println!(
+                    "{} (until {})",
+                    temporary_exclusion.host,
+                    format_time(temporary_exclusion.expires_at)
+                );
+            }
+        }
+        ExclusionsCommand::Add {
+            host,
+            duration: Some(duration),
+        } => {
+            let temporary_exclusions = client
+                .send_json(
+                    Method::POST,
+                    "exclusions/temporary",
+                    &TemporaryExclusionRequest {
+                        host: &host,
+                        duration: duration.as_secs(),
+                    },
+                )
+                .await?
+                .unwrap_or(Value::Null);
+
+            if json {
+                print_json(&temporary_exclusions);
+                return Ok(());
+            }
+
+            let expires_at =
+                serde_json::from_value::<Vec<TemporaryExclusion>>(temporary_exclusions)?
+                    .into_iter()
+                    .find(|temporary_exclusion| temporary_exclusion.host == host.trim())
+                    .map(|temporary_exclusion| temporary_exclusion.expires_at);
+
+            match expires_at {
+                Some(expires_at) => println!("Excluded {} until {}", host, format_time(expires_at)),
+                None => println!("Excluded {}", host),
+            }
+        }
+        ExclusionsCommand::Add {
+            host,
+            duration: None,
+        } => {
+            let host = host.trim().to_string();
+            let mut exclusions = get_exclusions(client).await?;
+
+            if !exclusions.contains(&host) {
+                exclusions.push(host.clone());
+                put_exclusions(client, &exclusions).await?;
+            }
+
+            if json {
+                print_json(&json!({ "exclusions": exclusions }));
+            } else {
+                println!("Excluded {}", host);
+            }
+        }
+        ExclusionsCommand::Rm { host } => {
+            let host = host.trim().to_string();
+            let mut exclusions = get_exclusions(client).await?;
+
+            if exclusions.contains(&host) {
+                exclusions.retain(|exclusion| *exclusion != host);
+                put_exclusions(client, &exclusions).await?;
+            } else {
+                let temporary_exclusions: Vec<TemporaryExclusion> =
+                    client.get("exclusions/temporary").await?;
+
+                if !temporary_exclusions
+                    .iter()
+                    .any(|temporary_exclusion| temporary_exclusion.host == host)
+                {
+                    return Err(ClientError::NotFound(format!("{} is not excluded", host)));
+                }
+
+                client
+                    .delete(&format!("exclusions/temporary/{}", host))
+                    .await?;
+            }
+
+            if json {
+                print_json(&json!({ "exclusions": exclusions }));
+            } else {
+                println!("Removed the exclusion of {}", host);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn get_blocking_status(client: &ApiClient) -> Result<BlockingStatus, ClientError> {
+    let enabled: bool = client.get("blocking-enabled").await?;
+    let blocking_pause: BlockingPause = client.get("blocking-enabled/pause").await?;
+
+    Ok(BlockingStatus {
+        enabled,
+        resume_at: blocking_pause.resume_at,
+    })
+}
+
+async fn blocking(
+    client: &ApiClient,
+    command: BlockingCommand,
+    json: bool,
+) -> Result<(), ClientError> {
+    match command {
+        BlockingCommand::Status => {}
+        BlockingCommand::Enable | BlockingCommand::Resume => {
+            client
+                .send_json(Method::PUT, "blocking-enabled", &true)
+                .await?;
+        }
+        BlockingCommand::Disable => {
+            client
+                .send_json(Method::PUT, "blocking-enabled", &false)
+                .await?;
+        }
+        BlockingCommand::Pause { duration } => {
+            client
+                .send_json(
+                    Method::POST,
+                    "blocking-enabled/pause",
+                    &json!({ "duration": duration.as_secs() }),
+                )
+                .await?;
+        }
+    }
+
+    let blocking_status = get_blocking_status(client).await?;
+
+    if json {
+        print_json(&blocking_status);
+    } else if let Some(resume_at) = blocking_status.resume_at {
+        println!("Blocking is paused until {}", format_time(resume_at));
+    } else if blocking_status.enabled {
+        println!("Blocking is enabled");
+    } else {
+        println!("Blocking is disabled");
+    }
+
+    Ok(())
+}
+
+async fn stats(client: &ApiClient, command: StatsCommand, json: bool) -> Result<(), ClientError> {
+    match command {
+        StatsCommand::Top {
+            kind,
+            period,
+            limit,
+        } => {
+            let top: Value = client
+                .get_with_query(
+                    "statistics/top",
+                    &TopQuery {
+                        kind,
+                        period: &period,
+                        limit,
+                    },
+                )
+                .await?;
+
+            if json {
+                print_json(&top);
+                return Ok(());
+            }
+
+            for entry in serde_json::from_value::<Vec<TopEntry>>(top)? {
+                match entry.entity {
+                    Some(entity) => println!("{:>8}  {} ({})", entry.requests, entry.name, entity),
+                    None => println!("{:>8}  {}", entry.requests, entry.name),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn requests(
+    client: &ApiClient,
+    command: RequestsCommand,
+    json: bool,
+) -> Result<(), ClientError> {
+    let RequestsCommand::Tail { blocked, hosts } = command;
+
+    let mut events = client.connect_websocket("events").await?;
+
+    // Blocked requests are reported with the `info` severity, allowed ones with `debug`.
+    let subscription = Subscription {
+        types: ["request"],
+        hosts: &hosts,
+        min_severity: if blocked { "info" } else { "debug" },
+    };
+    events
+        .send(Message::text(serde_json::to_string(&subscription)?))
+        .await?;
+
+    // Every event is sent until the subscription is applied.
+    let mut is_subscribed = false;
+
+    while let Some(message) = events.next().await {
+        let message = match message? {
+            Message::Text(message) => message,
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let event: Value = serde_json::from_str(&message)?;
+
+        match event["kind"].as_str() {
+            Some("subscribed") => {
+                is_subscribed = true;
+                continue;
+            }
+            Some("invalid_subscription") => {
+                return Err(ClientError::InvalidSubscription(
+                    event["error"].as_str().unwrap_or_default().to_string(),
+                ));
+            }
+            Some("lagged") => {
+                eprintln!("Skipped {} requests", event["skipped"]);
+                continue;
+            }
+            Some(_) => continue,
+            None if !is_subscribed || event["type"] != "request" => continue,
+            None => {}
+        }
+
+        if json {
+            println!("{}", event);
+            continue;
+        }
+
+        let event: RequestEvent = serde_json::from_value(event)?;
+
+        print!(
+            "{} {:<7} {:<15} {} {}",
+            event.now.with_timezone(&Local).format("%H:%M:%S"),
+            if event.is_request_blocked {
+                "blocked"
+            } else {
+                "allowed"
+            },
+            event.client,
+            event.method,
+            event.url
+        );
+
+        match event.filter {
+            Some(filter) => println!("  {}", filter.rule),
+            None => println!(),
+        }
+    }
+
+    Ok(())
+}
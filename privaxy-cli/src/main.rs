@@ -0,0 +1,231 @@
+//! Command line client of the API of a running privaxy, for headless servers and
+//! scripts.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use client::ApiClient;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use url::Url;
+
+mod client;
+mod commands;
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "privaxy-cli",
+    version,
+    about = "Manage a running privaxy from the command line"
+)]
+struct Cli {
+    /// URL of the web interface of privaxy, including its base path.
+    #[arg(
+        long,
+        env = "PRIVAXY_URL",
+        default_value = "http://127.0.0.1:8200/",
+        global = true
+    )]
+    url: Url,
+    /// Session token printed by `privaxy-cli login`, required when web authentication
+    /// is enabled.
+    #[arg(long, env = "PRIVAXY_TOKEN", hide_env_values = true, global = true)]
+    token: Option<String>,
+    /// Print the responses of the API as JSON, one document per line when following
+    /// requests.
+    #[arg(long, global = true)]
+    json: bool,
+    /// PEM certificate to trust when the web server uses TLS with a certificate that
+    /// isn't publicly trusted.
+    #[arg(long, env = "PRIVAXY_CA_CERTIFICATE", global = true)]
+    ca_certificate: Option<PathBuf>,
+    /// Accept any certificate from the web server.
+    #[arg(long, global = true)]
+    insecure: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Sign in and print a session token to pass with --token or PRIVAXY_TOKEN.
+    Login(LoginArgs),
+    /// Sign the session of the token out.
+    Logout,
+    /// Manage the filter lists.
+    #[command(subcommand)]
+    Filters(FiltersCommand),
+    /// Manage the hosts whose requests are not intercepted.
+    #[command(subcommand)]
+    Exclusions(ExclusionsCommand),
+    /// Enable, disable or pause blocking.
+    #[command(subcommand)]
+    Blocking(BlockingCommand),
+    /// Show statistics.
+    #[command(subcommand)]
+    Stats(StatsCommand),
+    /// Follow proxied requests.
+    #[command(subcommand)]
+    Requests(RequestsCommand),
+}
+
+#[derive(Debug, Args)]
+struct LoginArgs {
+    /// Password of the web interface, read from the standard input when missing.
+    #[arg(long, env = "PRIVAXY_PASSWORD", hide_env_values = true)]
+    password: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum FiltersCommand {
+    /// List the filter lists.
+    List,
+    /// Subscribe to a filter list.
+    Add {
+        /// URL of the list.
+        #[arg(value_name = "URL")]
+        list_url: Url,
+        /// Title of the list, its URL by default.
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long, value_enum, default_value_t = FilterGroup::Default)]
+        group: FilterGroup,
+        /// Add the list without enabling it.
+        #[arg(long)]
+        disabled: bool,
+    },
+    /// Remove a filter list.
+    Rm {
+        /// Identifier or URL of the list.
+        filter: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize)]
+enum FilterGroup {
+    Default,
+    Regional,
+    Ads,
+    Privacy,
+    Malware,
+    Social,
+}
+
+#[derive(Debug, Subcommand)]
+enum ExclusionsCommand {
+    /// List the exclusions, and the temporary ones along with when they expire.
+    List,
+    /// Exclude a host, such as `example.com` or `*.example.com`.
+    Add {
+        host: String,
+        /// Exclude the host for a while instead, such as `30m` or `2h`. Temporary
+        /// exclusions are not saved to the configuration file.
+        #[arg(long = "for", value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+    /// Remove the exclusion, or the temporary exclusion, of a host.
+    Rm { host: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum BlockingCommand {
+    /// Show whether blocking is enabled.
+    Status,
+    Enable,
+    Disable,
+    /// Disable blocking for a while, such as `30m` or `2h`.
+    Pause {
+        /// How long blocking is disabled for.
+        #[arg(value_parser = parse_duration)]
+        duration: Duration,
+    },
+    /// Enable blocking again, ending a pause.
+    Resume,
+}
+
+#[derive(Debug, Subcommand)]
+enum StatsCommand {
+    /// Show the most requested domains or companies.
+    Top {
+        #[arg(long, value_enum, default_value_t = TopKind::Blocked)]
+        kind: TopKind,
+        /// Period ending now, in hours or days such as `24h` or `7d`.
+        #[arg(long, default_value = "24h")]
+        period: String,
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TopKind {
+    /// Domains of blocked requests.
+    Blocked,
+    /// Domains of proxied requests.
+    Allowed,
+    /// Companies owning the trackers of blocked requests.
+    Companies,
+}
+
+#[derive(Debug, Subcommand)]
+enum RequestsCommand {
+    /// Print requests as they are proxied, until interrupted.
+    Tail {
+        /// Only print blocked requests.
+        #[arg(long)]
+        blocked: bool,
+        /// Only print the requests of these hosts, along with their subdomains.
+        #[arg(long = "host")]
+        hosts: Vec<String>,
+    },
+}
+
+/// Parses durations such as `90s`, `30m`, `2h` or `1d`. Numbers without a unit are
+/// seconds.
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let duration = duration.trim();
+    let unit_start = duration
+        .find(|character: char| !character.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (value, unit) = duration.split_at(unit_start);
+
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration `{}`", duration))?;
+    let seconds_per_unit = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown unit `{}`, use s, m, h or d", unit)),
+    };
+
+    match value.checked_mul(seconds_per_unit) {
+        Some(0) => Err("the duration must be positive".to_string()),
+        Some(seconds) => Ok(Duration::from_secs(seconds)),
+        None => Err(format!("duration `{}` is too long", duration)),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    let client = match ApiClient::new(
+        &cli.url,
+        cli.token,
+        cli.ca_certificate.as_deref(),
+        cli.insecure,
+    ) {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("privaxy-cli: {}", err);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(err) = commands::run(&client, cli.command, cli.json).await {
+        eprintln!("privaxy-cli: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,25 @@
+use std::process::Command;
+
+/// Exposes the commit Privaxy is built from as `PRIVAXY_BUILD_HASH`. Builds outside of a
+/// git checkout, such as from a source archive, can set it in their environment.
+fn main() {
+    println!("cargo:rerun-if-env-changed=PRIVAXY_BUILD_HASH");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/index");
+
+    if std::env::var_os("PRIVAXY_BUILD_HASH").is_some() {
+        return;
+    }
+
+    let output = match Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return,
+    };
+
+    let build_hash = String::from_utf8_lossy(&output.stdout);
+
+    println!("cargo:rustc-env=PRIVAXY_BUILD_HASH={}", build_hash.trim());
+}
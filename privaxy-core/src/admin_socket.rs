@@ -0,0 +1,80 @@
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use warp::filters::BoxedFilter;
+
+/// Serves the web GUI and the API on a Unix socket until Privaxy reloads, for scripts
+/// to manage Privaxy with `curl --unix-socket` on hosts where it doesn't listen on the
+/// network.
+///
+/// Requests made through the socket don't require signing in. Only the users its
+/// permissions let open it, `mode`, can make them.
+#[cfg(unix)]
+pub(crate) async fn serve<R>(
+    frontend: BoxedFilter<(R,)>,
+    socket_path: &Path,
+    mode: u32,
+    notify_reload: Arc<Notify>,
+) -> std::io::Result<()>
+where
+    R: warp::Reply + 'static,
+{
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use tokio::net::UnixListener;
+
+    // The socket of a previous run is left behind when Privaxy doesn't stop cleanly.
+    if let Ok(metadata) = tokio::fs::symlink_metadata(socket_path).await {
+        if !metadata.file_type().is_socket() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", socket_path.display()),
+            ));
+        }
+        tokio::fs::remove_file(socket_path).await?;
+    }
+
+    let listener = UnixListener::bind(socket_path)?;
+
+    if let Err(err) =
+        tokio::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(mode)).await
+    {
+        let _ = tokio::fs::remove_file(socket_path).await;
+        return Err(err);
+    }
+
+    let incoming = futures_util::stream::unfold(listener, |listener| async move {
+        let stream = listener.accept().await.map(|(stream, _)| stream);
+        Some((stream, listener))
+    });
+
+    log::info!(
+        "Admin API available on unix socket {}",
+        socket_path.display()
+    );
+
+    warp::serve(frontend)
+        .serve_incoming_with_graceful_shutdown(incoming, async move {
+            notify_reload.notified().await;
+        })
+        .await;
+
+    let _ = tokio::fs::remove_file(socket_path).await;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) async fn serve<R>(
+    _frontend: BoxedFilter<(R,)>,
+    _socket_path: &Path,
+    _mode: u32,
+    _notify_reload: Arc<Notify>,
+) -> std::io::Result<()>
+where
+    R: warp::Reply + 'static,
+{
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "unix sockets are not supported on this platform",
+    ))
+}
@@ -0,0 +1,527 @@
+//! `privaxy bench`, replaying a corpus of requests against the blocking engine and then
+//! through the whole proxy, for performance regressions to be caught between releases.
+//!
+//! The proxy runs in process against a copy of the base directory, on ports of its own,
+//! so that a running Privaxy and its statistics are left alone. Requests it forwards all
+//! reach a local server standing in for the internet: the hosts of the corpus are kept,
+//! only the port changes and names resolve to the local server.
+
+use crate::configuration::{
+    self, Configuration, ConfigurationError, ProxyAuthentication, ResourceType,
+    CONFIGURATION_DIRECTORY_NAME, RESPONSE_CACHE_DIRECTORY_NAME,
+};
+use crate::health::HealthStore;
+use crate::startup::{set_startup_options, StartupOptions};
+use crate::{ProxyBuilder, StartError};
+use adblock::lists::{FilterSet, ParseOptions};
+use adblock::request::Request as AdblockRequest;
+use adblock::Engine;
+use clap::Args;
+use futures::StreamExt;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Added to the responses of the local server, responses without it were answered by
+/// the proxy itself.
+const UPSTREAM_HEADER: &str = "x-privaxy-bench";
+/// The blocking engine is built from the filters before the proxy serves requests.
+const READY_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Args)]
+pub struct BenchOptions {
+    /// File of the requests to replay, one per line: a URL, optionally followed by its
+    /// resource type (`document`, `script`, `image`, `xhr`...) and the URL of the page
+    /// making it. Empty lines and lines starting with `#` are skipped.
+    pub corpus: PathBuf,
+    /// Number of times the corpus is replayed.
+    #[arg(long, default_value_t = 1)]
+    pub iterations: usize,
+    /// Requests sent through the proxy at once.
+    #[arg(long, default_value_t = 16)]
+    pub concurrency: usize,
+    /// Only measure the blocking engine, without starting the proxy.
+    #[arg(long)]
+    pub engine_only: bool,
+    /// Print the report as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum BenchError {
+    #[error("unable to read corpus: {0}")]
+    Corpus(io::Error),
+    #[error("corpus has no requests")]
+    EmptyCorpus,
+    #[error("base directory {0} was not found")]
+    BaseDirectoryNotFound(PathBuf),
+    #[error("unable to copy base directory: {0}")]
+    BaseDirectoryCopy(io::Error),
+    #[error("configuration error: {0}")]
+    Configuration(#[from] ConfigurationError),
+    #[error("unable to start local server: {0}")]
+    Server(io::Error),
+    #[error("proxy was not ready after {0:?}")]
+    NotReady(Duration),
+    #[error("unable to build client: {0}")]
+    Client(#[from] reqwest::Error),
+    #[error("unable to start privaxy: {0}")]
+    Start(#[from] StartError),
+}
+
+#[derive(Debug, Clone)]
+struct CorpusRequest {
+    url: String,
+    resource_type: ResourceType,
+    referer: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Latency {
+    pub p50_us: u64,
+    pub p90_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhaseReport {
+    pub requests: usize,
+    /// Requests blocked by a filter, answered by the proxy rather than the local server.
+    pub blocked: usize,
+    /// Invalid URLs for the engine, and requests the proxy failed to answer.
+    pub failed: usize,
+    pub duration_ms: u64,
+    pub requests_per_second: f64,
+    pub latency: Latency,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub filter_lists: usize,
+    pub engine_compile_ms: u64,
+    pub engine: PhaseReport,
+    pub proxy: Option<PhaseReport>,
+}
+
+impl PhaseReport {
+    fn new(
+        mut latencies: Vec<Duration>,
+        blocked: usize,
+        failed: usize,
+        duration: Duration,
+    ) -> Self {
+        latencies.sort_unstable();
+        let seconds = duration.as_secs_f64().max(f64::EPSILON);
+
+        let percentile = |percentile: f64| {
+            if latencies.is_empty() {
+                return 0;
+            }
+
+            let index = ((latencies.len() - 1) as f64 * percentile).round() as usize;
+            latencies[index].as_micros() as u64
+        };
+
+        Self {
+            requests: latencies.len() + failed,
+            blocked,
+            failed,
+            duration_ms: duration.as_millis() as u64,
+            requests_per_second: latencies.len() as f64 / seconds,
+            latency: Latency {
+                p50_us: percentile(0.5),
+                p90_us: percentile(0.9),
+                p99_us: percentile(0.99),
+                max_us: percentile(1.),
+            },
+        }
+    }
+
+    fn print(&self, name: &str) {
+        println!(
+            "{}: {} requests in {} ms, {:.0} requests/s, {} blocked, {} failed",
+            name,
+            self.requests,
+            self.duration_ms,
+            self.requests_per_second,
+            self.blocked,
+            self.failed
+        );
+        println!(
+            "  latency p50 {} µs, p90 {} µs, p99 {} µs, max {} µs",
+            self.latency.p50_us, self.latency.p90_us, self.latency.p99_us, self.latency.max_us
+        );
+    }
+}
+
+impl BenchReport {
+    fn print(&self) {
+        println!(
+            "Blocking engine compiled from {} filter lists in {} ms",
+            self.filter_lists, self.engine_compile_ms
+        );
+        self.engine.print("Engine");
+        if let Some(proxy) = &self.proxy {
+            proxy.print("Proxy");
+        }
+    }
+}
+
+/// Runs the benchmark, in place of the proxy. `startup_options` are those privaxy was
+/// started with, their base path is copied and their ports replaced.
+pub async fn run(
+    startup_options: StartupOptions,
+    bench_options: BenchOptions,
+) -> Result<(), BenchError> {
+    let base_directory = startup_options
+        .base_path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(CONFIGURATION_DIRECTORY_NAME));
+    if !base_directory.exists() {
+        return Err(BenchError::BaseDirectoryNotFound(base_directory));
+    }
+
+    let bench_directory =
+        std::env::temp_dir().join(format!("privaxy-bench-{}", std::process::id()));
+    // Cached responses may take a lot of space, and the benchmark doesn't need them.
+    copy_directory(
+        &base_directory,
+        &bench_directory,
+        &[RESPONSE_CACHE_DIRECTORY_NAME],
+    )
+    .map_err(BenchError::BaseDirectoryCopy)?;
+
+    let proxy_port = get_free_port().map_err(BenchError::Server)?;
+    set_startup_options(StartupOptions {
+        base_path: Some(bench_directory.clone()),
+        ip_address: Some(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        proxy_port: Some(proxy_port),
+        web_port: Some(get_free_port().map_err(BenchError::Server)?),
+        admin_socket: None,
+        log_level: startup_options
+            .log_level
+            .or_else(|| Some("warn".to_string())),
+        no_configuration_watch: true,
+        disable_blocking: false,
+        command: None,
+        ..startup_options
+    });
+    crate::logging::init();
+
+    let report = bench(&bench_options, proxy_port).await;
+
+    if let Err(err) = std::fs::remove_dir_all(&bench_directory) {
+        log::warn!("Unable to remove {}: {}", bench_directory.display(), err);
+    }
+
+    let report = report?;
+    if bench_options.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        report.print();
+    }
+
+    Ok(())
+}
+
+async fn bench(bench_options: &BenchOptions, proxy_port: u16) -> Result<BenchReport, BenchError> {
+    let corpus = read_corpus(&bench_options.corpus).await?;
+
+    let mut configuration = Configuration::read_from_home().await?;
+    // Requests of the benchmark are sent without credentials.
+    configuration.proxy_authentication = ProxyAuthentication::default();
+    configuration.save().await?;
+
+    let filters =
+        configuration::get_filters_content(&mut configuration, &reqwest::Client::new()).await;
+    let filter_lists = filters.len();
+
+    let custom_filters = configuration.custom_filters.clone();
+    let engine_corpus = corpus.clone();
+    let iterations = bench_options.iterations;
+    let (engine_compile_duration, engine) = tokio::task::spawn_blocking(move || {
+        let started_at = Instant::now();
+
+        let mut filter_set = FilterSet::new(false);
+        for filter in &filters {
+            filter_set.add_filter_list(&filter.content, ParseOptions::default());
+        }
+        filter_set.add_filters(&custom_filters, ParseOptions::default());
+        let engine = Engine::from_filter_set(filter_set, true);
+
+        (
+            started_at.elapsed(),
+            bench_engine(&engine, &engine_corpus, iterations),
+        )
+    })
+    .await
+    .unwrap();
+
+    let proxy = if bench_options.engine_only {
+        None
+    } else {
+        Some(bench_proxy(&corpus, bench_options, proxy_port).await?)
+    };
+
+    Ok(BenchReport {
+        filter_lists,
+        engine_compile_ms: engine_compile_duration.as_millis() as u64,
+        engine,
+        proxy,
+    })
+}
+
+fn bench_engine(engine: &Engine, corpus: &[CorpusRequest], iterations: usize) -> PhaseReport {
+    let requests = corpus
+        .iter()
+        .filter_map(|corpus_request| {
+            AdblockRequest::new(
+                &corpus_request.url,
+                &corpus_request.referer,
+                corpus_request.resource_type.as_adblock_str(),
+            )
+            .ok()
+        })
+        .collect::<Vec<_>>();
+    let failed = (corpus.len() - requests.len()) * iterations;
+
+    let mut latencies = Vec::with_capacity(requests.len() * iterations);
+    let mut blocked = 0;
+
+    let started_at = Instant::now();
+    for _ in 0..iterations {
+        for request in &requests {
+            let request_started_at = Instant::now();
+            let blocker_result = engine.check_network_request(request);
+            latencies.push(request_started_at.elapsed());
+
+            if blocker_result.matched {
+                blocked += 1;
+            }
+        }
+    }
+
+    PhaseReport::new(latencies, blocked, failed, started_at.elapsed())
+}
+
+async fn bench_proxy(
+    corpus: &[CorpusRequest],
+    bench_options: &BenchOptions,
+    proxy_port: u16,
+) -> Result<PhaseReport, BenchError> {
+    let upstream_address = start_upstream_server().map_err(BenchError::Server)?;
+
+    let privaxy_server = ProxyBuilder::new()
+        .resolved_address_override(upstream_address.ip())
+        .start_with_current_options()
+        .await?;
+    wait_until_ready(&privaxy_server.health_store).await?;
+
+    let client = reqwest::Client::builder()
+        .proxy(reqwest::Proxy::http(format!(
+            "http://{}",
+            SocketAddr::from((Ipv4Addr::LOCALHOST, proxy_port))
+        ))?)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+
+    let requests = (0..bench_options.iterations)
+        .flat_map(|_| corpus.iter())
+        .filter_map(|corpus_request| {
+            let mut url = url::Url::parse(&corpus_request.url).ok()?;
+            // The local server doesn't speak TLS.
+            url.set_scheme("http").ok()?;
+            url.set_port(Some(upstream_address.port())).ok()?;
+
+            let mut request = client
+                .get(url)
+                .header(hyper::header::REFERER, &corpus_request.referer);
+            if let Some(destination) = get_fetch_destination(corpus_request.resource_type) {
+                request = request.header("sec-fetch-dest", destination);
+            }
+
+            Some(request)
+        })
+        .collect::<Vec<_>>();
+    let invalid = corpus.len() * bench_options.iterations - requests.len();
+
+    let started_at = Instant::now();
+    let results = futures::stream::iter(requests)
+        .map(|request| async move {
+            let request_started_at = Instant::now();
+            let response = request.send().await;
+
+            let is_blocked = match response {
+                Ok(response) => {
+                    let is_blocked = !response.headers().contains_key(UPSTREAM_HEADER);
+                    // Reading the body completes the request.
+                    response.bytes().await.ok().map(|_body| is_blocked)
+                }
+                Err(err) => {
+                    log::debug!("Request failed: {}", err);
+                    None
+                }
+            };
+
+            (request_started_at.elapsed(), is_blocked)
+        })
+        .buffer_unordered(bench_options.concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    let duration = started_at.elapsed();
+
+    let mut latencies = Vec::with_capacity(results.len());
+    let mut blocked = 0;
+    let mut failed = invalid;
+    for (latency, is_blocked) in results {
+        match is_blocked {
+            Some(is_blocked) => {
+                latencies.push(latency);
+                if is_blocked {
+                    blocked += 1;
+                }
+            }
+            None => failed += 1,
+        }
+    }
+
+    Ok(PhaseReport::new(latencies, blocked, failed, duration))
+}
+
+/// Reads requests of the corpus, skipping lines of unknown resource types.
+async fn read_corpus(path: &Path) -> Result<Vec<CorpusRequest>, BenchError> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(BenchError::Corpus)?;
+
+    let corpus = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let url = fields.next()?.to_string();
+            let resource_type = match fields.next() {
+                Some(resource_type) => {
+                    match ResourceType::deserialize(
+                        IntoDeserializer::<serde::de::value::Error>::into_deserializer(
+                            resource_type,
+                        ),
+                    ) {
+                        Ok(resource_type) => resource_type,
+                        Err(err) => {
+                            log::warn!("Skipping corpus line {:?}: {}", line, err);
+                            return None;
+                        }
+                    }
+                }
+                None => ResourceType::Other,
+            };
+            // Requests without a page are those of pages themselves.
+            let referer = fields.next().unwrap_or(&url).to_string();
+
+            Some(CorpusRequest {
+                url,
+                resource_type,
+                referer,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if corpus.is_empty() {
+        return Err(BenchError::EmptyCorpus);
+    }
+
+    Ok(corpus)
+}
+
+/// Value of `Sec-Fetch-Dest` browsers send for a resource type, which the proxy
+/// guesses the resource type from.
+fn get_fetch_destination(resource_type: ResourceType) -> Option<&'static str> {
+    match resource_type {
+        ResourceType::Document => Some("document"),
+        ResourceType::Subdocument => Some("iframe"),
+        ResourceType::Script => Some("script"),
+        ResourceType::Stylesheet => Some("style"),
+        ResourceType::Image => Some("image"),
+        ResourceType::Media => Some("video"),
+        ResourceType::Font => Some("font"),
+        ResourceType::Xhr => Some("empty"),
+        ResourceType::Other => None,
+    }
+}
+
+/// Answers every request with a small response, in place of the servers of the corpus.
+fn start_upstream_server() -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let address = listener.local_addr()?;
+
+    let make_service = make_service_fn(|_conn| async {
+        Ok::<_, Infallible>(service_fn(|_request| async {
+            Ok::<_, Infallible>(
+                Response::builder()
+                    .header(UPSTREAM_HEADER, "upstream")
+                    .body(Body::from("privaxy bench"))
+                    .unwrap(),
+            )
+        }))
+    });
+    let server = Server::from_tcp(listener)
+        .map_err(io::Error::other)?
+        .serve(make_service);
+    tokio::spawn(server);
+
+    Ok(address)
+}
+
+async fn wait_until_ready(health_store: &HealthStore) -> Result<(), BenchError> {
+    let started_at = Instant::now();
+
+    while !health_store.get_readiness().ready {
+        if started_at.elapsed() > READY_TIMEOUT {
+            return Err(BenchError::NotReady(READY_TIMEOUT));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    Ok(())
+}
+
+fn get_free_port() -> io::Result<u16> {
+    Ok(TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+        .local_addr()?
+        .port())
+}
+
+/// Copies files and directories, following links, other entries such as sockets are
+/// left out.
+fn copy_directory(source: &Path, destination: &Path, skipped: &[&str]) -> io::Result<()> {
+    std::fs::create_dir_all(destination)?;
+
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        if skipped.iter().any(|skipped| entry.file_name() == *skipped) {
+            continue;
+        }
+
+        let destination = destination.join(entry.file_name());
+        let metadata = std::fs::metadata(entry.path())?;
+
+        if metadata.is_dir() {
+            copy_directory(&entry.path(), &destination, &[])?;
+        } else if metadata.is_file() {
+            std::fs::copy(entry.path(), destination)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1286 @@
+use crate::blocker_utils::{
+    build_resource_from_file_contents, read_redirectable_resource_mapping, read_template_resources,
+};
+use crate::configuration::{
+    get_engine_cache_file, FilterContent, ResourceType, SitePolicyAction, CUSTOM_FILTERS_SOURCE,
+    SITE_POLICIES_SOURCE,
+};
+use crate::health::HealthStore;
+use crate::proxy::content_rewrite::ContentRewriteStore;
+use crate::proxy::html_filters::HtmlFilterStore;
+use crate::proxy::site_policies::{SitePolicyMatch, SitePolicyStore};
+use crate::rule_profiling::{ProfiledRequest, RuleProfiler};
+pub use crate::rule_profiling::{RuleCost, RulePerformance};
+use crate::statistics::Statistics;
+use crate::web_gui::events::StatusEvent;
+use crate::AppState;
+use adblock::blocker::BlockerResult as AdblockerBlockerResult;
+use adblock::cosmetic_filter_cache::UrlSpecificResources;
+use adblock::lists::FilterSet;
+use adblock::request::{Request, RequestError};
+use adblock::resources::Resource;
+use adblock::Engine;
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use crossbeam_channel::{Receiver, Sender};
+use include_dir::{include_dir, Dir};
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+use tokio::sync::{broadcast, oneshot};
+
+pub type AdblockRequestChannel = Sender<BlockerRequest>;
+
+#[derive(Debug)]
+struct BlockingState {
+    disabled: bool,
+    /// When blocking was paused rather than disabled, when it is enabled again.
+    resume_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockingDisabledStore(Arc<RwLock<BlockingState>>);
+
+impl BlockingDisabledStore {
+    pub fn new(enabled: bool) -> Self {
+        Self(Arc::new(RwLock::new(BlockingState {
+            disabled: !enabled,
+            resume_at: None,
+        })))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        let state = self.0.read().unwrap();
+
+        !state.disabled
+            || state
+                .resume_at
+                .is_some_and(|resume_at| resume_at <= Utc::now())
+    }
+
+    /// Enables or disables blocking until it is set again, ending any pause.
+    pub fn set(&self, enabled: bool) {
+        *self.0.write().unwrap() = BlockingState {
+            disabled: !enabled,
+            resume_at: None,
+        }
+    }
+
+    /// Disables blocking until `resume_at`.
+    pub fn pause_until(&self, resume_at: DateTime<Utc>) {
+        *self.0.write().unwrap() = BlockingState {
+            disabled: true,
+            resume_at: Some(resume_at),
+        }
+    }
+
+    /// When blocking is enabled again, if it is paused.
+    pub fn get_resume_at(&self) -> Option<DateTime<Utc>> {
+        let state = self.0.read().unwrap();
+
+        state
+            .resume_at
+            .filter(|resume_at| state.disabled && *resume_at > Utc::now())
+    }
+}
+
+#[derive(Debug)]
+pub struct CosmeticRequest {
+    pub(crate) url: String,
+    pub(crate) ids: Vec<String>,
+    pub(crate) classes: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct NetworkUrl {
+    url: String,
+    referer: String,
+    resource_type: ResourceType,
+}
+
+#[derive(Debug)]
+pub enum RequestKind {
+    Url(NetworkUrl),
+    Cosmetic(CosmeticRequest),
+    /// Checks a request against the blocking engine even when blocking is disabled,
+    /// without it being proxied.
+    Test(NetworkUrl),
+    ReplaceEngine(Vec<FilterContent>),
+    /// Custom filters are compiled into a small engine of their own so that editing
+    /// them doesn't require recompiling the filter lists.
+    ReplaceCustomEngine(Vec<String>),
+    Status,
+    /// Starts or stops profiling the time rules take to match requests.
+    SetRuleProfiling(bool),
+    RulePerformance,
+}
+
+#[derive(Debug)]
+pub enum BlockerResult {
+    Network(adblock::blocker::BlockerResult, RequestDecision),
+    Cosmetic(CosmeticBlockerResult),
+    Test(Result<RequestDecision, String>),
+    Status(EngineStatus),
+    RulePerformance(RulePerformance),
+}
+
+/// A filter rule that matched a request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct MatchedFilter {
+    pub rule: String,
+    /// Title of the filter list the rule comes from, when it could be found.
+    pub list: Option<String>,
+}
+
+/// Explains why a network request was blocked or allowed.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct RequestDecision {
+    pub blocked: bool,
+    /// Whether the matching rule is marked `$important`, overriding exceptions.
+    pub important: bool,
+    /// Name of the resource the request is answered with, as requested by a `$redirect` rule.
+    pub redirect: Option<String>,
+    /// Blocking rule that matched the request, even when an exception allowed it.
+    pub filter: Option<MatchedFilter>,
+    /// Exception rule that allowed the request.
+    pub exception: Option<MatchedFilter>,
+    /// Policy added to the response by `$csp` rules, for page requests that aren't blocked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csp: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct CosmeticBlockerResult {
+    pub hidden_selectors: Vec<String>,
+    pub style_selectors: HashMap<String, Vec<String>>,
+    pub injected_script: Option<String>,
+}
+
+/// Rules of filters, counted by type from their syntax. Rules adblock fails to parse are
+/// counted too.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct RuleCounts {
+    pub network: usize,
+    /// `@@` rules.
+    pub network_exceptions: usize,
+    /// Element hiding rules, such as `##.ad`.
+    pub cosmetic: usize,
+    /// `#@#` rules.
+    pub cosmetic_exceptions: usize,
+    /// Scriptlet injections, such as `##+js(...)`.
+    pub scriptlets: usize,
+    /// HTML filters (`##^`), applied by the HTML rewriter rather than the engine.
+    pub html: usize,
+}
+
+impl RuleCounts {
+    const COSMETIC_SEPARATORS: [&'static str; 8] =
+        ["##", "#?#", "#$#", "#%#", "#@#", "#@?#", "#@$#", "#@%#"];
+
+    fn count(filters: &[FilterContent]) -> Self {
+        let mut rule_counts = Self::default();
+
+        for line in filters.iter().flat_map(|filter| filter.content.lines()) {
+            rule_counts.add(line.trim());
+        }
+
+        rule_counts
+    }
+
+    fn add(&mut self, rule: &str) {
+        if rule.is_empty() || rule.starts_with('!') || rule.starts_with('[') {
+            return;
+        }
+
+        let cosmetic_separator = Self::COSMETIC_SEPARATORS
+            .iter()
+            .filter_map(|separator| Some((rule.find(separator)?, *separator)))
+            .min_by_key(|(position, _separator)| *position);
+
+        match cosmetic_separator {
+            Some((position, separator)) => {
+                let selector = &rule[position + separator.len()..];
+
+                if separator.contains('@') {
+                    self.cosmetic_exceptions += 1;
+                } else if separator == "##" && selector.starts_with("+js(") {
+                    self.scriptlets += 1;
+                } else if separator == "##" && selector.starts_with('^') {
+                    self.html += 1;
+                } else {
+                    self.cosmetic += 1;
+                }
+            }
+            // Comments of hosts files.
+            None if rule.starts_with('#') => {}
+            None if rule.starts_with("@@") => self.network_exceptions += 1,
+            None => self.network += 1,
+        }
+    }
+}
+
+/// State of the blocking engines.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct EngineStatus {
+    /// Replicas of the engines, one per shard.
+    pub shards: usize,
+    pub filter_lists: usize,
+    pub rules: RuleCounts,
+    pub custom_rules: RuleCounts,
+    /// Estimated from the size of the serialized engine of each replica, and of the
+    /// filters kept to find where matching rules come from.
+    pub memory_estimate_bytes: u64,
+}
+
+pub struct BlockerRequest {
+    pub(crate) kind: RequestKind,
+    pub(crate) respond_to: oneshot::Sender<BlockerResult>,
+}
+
+pub struct Blocker {
+    receiver: Receiver<BlockerRequest>,
+    /// Index of the engine replicas this blocker matches requests against.
+    shard: usize,
+    engine: Arc<EngineSlot>,
+    /// Consulted after `engine`: custom exceptions apply to rules of the filter lists
+    /// and custom rules block requests the filter lists allow.
+    custom_engine: Arc<EngineSlot>,
+    blocking_disabled: BlockingDisabledStore,
+    /// Consulted before the engines.
+    site_policy_store: SitePolicyStore,
+    statistics: Statistics,
+    engine_builder: EngineBuilder,
+}
+
+/// Engine built from a set of filters, replaced as a whole when they change.
+struct FilterEngine {
+    engine: Engine,
+    /// Filters the engine was built from, used to find where matching rules come from.
+    /// Shared by the replicas of the engine.
+    filters: Arc<Vec<FilterContent>>,
+    rule_counts: RuleCounts,
+    /// Size of the engine once serialized, when it was.
+    serialized_size: Option<usize>,
+    /// Lists in which rules were found, looked up lazily as rules match.
+    filter_sources: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl FilterEngine {
+    fn new(
+        engine: Engine,
+        filters: Arc<Vec<FilterContent>>,
+        rule_counts: RuleCounts,
+        serialized_size: Option<usize>,
+    ) -> Self {
+        Self {
+            engine,
+            filters,
+            rule_counts,
+            serialized_size,
+            filter_sources: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn empty() -> Self {
+        Self::new(
+            Engine::new(true),
+            Arc::default(),
+            RuleCounts::default(),
+            None,
+        )
+    }
+
+    fn get_filters_size(&self) -> usize {
+        self.filters.iter().map(|filter| filter.content.len()).sum()
+    }
+
+    /// Returns the title of the list containing `rule`.
+    fn find_filter_source(&self, rule: &str) -> Option<String> {
+        let mut filter_sources = self.filter_sources.lock().unwrap();
+
+        if let Some(source) = filter_sources.get(rule) {
+            return source.clone();
+        }
+
+        let source = self
+            .filters
+            .iter()
+            .find(|filter| filter.content.lines().any(|line| line.trim() == rule))
+            .map(|filter| filter.source.clone());
+
+        filter_sources.insert(rule.to_string(), source.clone());
+
+        source
+    }
+}
+
+/// Holds the engine requests are checked against. Engines are built on threads of
+/// their own and swapped in once ready, so that requests never wait for filters to
+/// compile.
+struct EngineSlot {
+    /// Replicas of the engine, one per shard, so that shards don't contend for the
+    /// regex cache of a single engine.
+    engines: Vec<ArcSwap<FilterEngine>>,
+    /// Latest build requested. Builds finishing after a more recent one was requested
+    /// are discarded, their filters being outdated.
+    requested_build: Mutex<u64>,
+}
+
+impl EngineSlot {
+    fn new(shards: usize) -> Self {
+        Self {
+            engines: (0..shards)
+                .map(|_| ArcSwap::from_pointee(FilterEngine::empty()))
+                .collect(),
+            requested_build: Mutex::new(0),
+        }
+    }
+
+    fn shards(&self) -> usize {
+        self.engines.len()
+    }
+
+    fn load(&self, shard: usize) -> Arc<FilterEngine> {
+        self.engines[shard].load_full()
+    }
+
+    fn request_build(&self) -> u64 {
+        let mut requested_build = self.requested_build.lock().unwrap();
+        *requested_build += 1;
+
+        *requested_build
+    }
+
+    fn is_latest_build(&self, build: u64) -> bool {
+        *self.requested_build.lock().unwrap() == build
+    }
+
+    /// Calls `on_swap` then swaps the replicas of `build` in, one per shard, unless a
+    /// more recent build was requested meanwhile. Both happen under the lock so that
+    /// stores updated by `on_swap` always match the engines in use.
+    fn swap(&self, build: u64, replicas: Vec<Arc<FilterEngine>>, on_swap: impl FnOnce()) -> bool {
+        let requested_build = self.requested_build.lock().unwrap();
+
+        if *requested_build != build {
+            log::debug!(
+                "Discarded blocking engine of build {}, build {} was requested since.",
+                build,
+                requested_build
+            );
+            return false;
+        }
+
+        on_swap();
+        for (engine, replica) in self.engines.iter().zip(replicas) {
+            engine.store(replica);
+        }
+
+        true
+    }
+}
+
+/// Engines of the blocker as they were when a request started being handled.
+struct LoadedEngines {
+    engine: Arc<FilterEngine>,
+    custom_engine: Arc<FilterEngine>,
+}
+
+impl LoadedEngines {
+    fn check_network_request(&self, req: &Request) -> AdblockerBlockerResult {
+        let blocker_result = self.engine.engine.check_network_request(req);
+
+        // Important rules can't be excepted, not even by custom filters.
+        if blocker_result.important {
+            return blocker_result;
+        }
+
+        let custom_blocker_result = self.custom_engine.engine.check_network_request_subset(
+            req,
+            blocker_result.matched,
+            blocker_result.matched,
+        );
+
+        if custom_blocker_result.filter.is_none() && custom_blocker_result.exception.is_none() {
+            return blocker_result;
+        }
+
+        let matched = custom_blocker_result.matched;
+
+        AdblockerBlockerResult {
+            matched,
+            important: custom_blocker_result.important,
+            redirect: if matched {
+                custom_blocker_result.redirect.or(blocker_result.redirect)
+            } else {
+                None
+            },
+            exception: custom_blocker_result.exception,
+            filter: custom_blocker_result.filter.or(blocker_result.filter),
+            rewritten_url: custom_blocker_result
+                .rewritten_url
+                .or(blocker_result.rewritten_url),
+        }
+    }
+
+    /// Directives of the `$csp` rules of both engines matching a page request.
+    fn get_csp_directives(&self, req: &Request, resource_type: ResourceType) -> Option<String> {
+        // Policies only apply to the documents they are served with.
+        if !matches!(
+            resource_type,
+            ResourceType::Document | ResourceType::Subdocument
+        ) {
+            return None;
+        }
+
+        let directives = [&self.engine, &self.custom_engine]
+            .into_iter()
+            .filter_map(|engine| engine.engine.get_csp_directives(req))
+            .collect::<Vec<_>>();
+
+        if directives.is_empty() {
+            None
+        } else {
+            // Separate policies of a single header are all enforced.
+            Some(directives.join(", "))
+        }
+    }
+
+    /// Cosmetic resources of both engines, custom exceptions applying to the filter lists.
+    fn url_cosmetic_resources(&self, url: &str) -> UrlSpecificResources {
+        let mut url_specific_resources = self.engine.engine.url_cosmetic_resources(url);
+        let custom_url_specific_resources = self.custom_engine.engine.url_cosmetic_resources(url);
+
+        url_specific_resources
+            .hide_selectors
+            .extend(custom_url_specific_resources.hide_selectors);
+        url_specific_resources
+            .hide_selectors
+            .retain(|selector| !custom_url_specific_resources.exceptions.contains(selector));
+        url_specific_resources
+            .style_selectors
+            .extend(custom_url_specific_resources.style_selectors);
+        url_specific_resources
+            .exceptions
+            .extend(custom_url_specific_resources.exceptions);
+        url_specific_resources.generichide |= custom_url_specific_resources.generichide;
+
+        if !custom_url_specific_resources.injected_script.is_empty() {
+            if !url_specific_resources.injected_script.is_empty() {
+                url_specific_resources.injected_script.push('\n');
+            }
+            url_specific_resources
+                .injected_script
+                .push_str(&custom_url_specific_resources.injected_script);
+        }
+
+        url_specific_resources
+    }
+
+    fn get_matched_filter(&self, rule: Option<&String>) -> Option<MatchedFilter> {
+        let rule = rule?;
+
+        Some(MatchedFilter {
+            rule: rule.clone(),
+            list: self
+                .engine
+                .find_filter_source(rule)
+                .or_else(|| self.custom_engine.find_filter_source(rule)),
+        })
+    }
+}
+
+/// Builds engines off the thread handling requests, cloned into each build.
+#[derive(Clone)]
+struct EngineBuilder {
+    engine: Arc<EngineSlot>,
+    custom_engine: Arc<EngineSlot>,
+    /// Kept up to date with the `$replace` rules of the filters, which the engines ignore.
+    content_rewrite_store: ContentRewriteStore,
+    /// Same for HTML filters (`##^`), which the HTML rewriter applies.
+    html_filter_store: HtmlFilterStore,
+    engine_cache_path: PathBuf,
+    status_sender: broadcast::Sender<StatusEvent>,
+    health_store: HealthStore,
+    statistics: Statistics,
+    rule_profiler: RuleProfiler,
+}
+
+impl EngineBuilder {
+    /// Filters of the engines in use, custom filters last.
+    fn get_profiled_filters(&self) -> Vec<FilterContent> {
+        let mut filters = self.engine.load(0).filters.to_vec();
+        filters.extend_from_slice(&self.custom_engine.load(0).filters);
+
+        filters
+    }
+
+    /// Profiling starts over with the new filters once an engine is swapped in.
+    fn restart_rule_profiling(&self) {
+        if self.rule_profiler.is_enabled() {
+            self.rule_profiler.start(self.get_profiled_filters());
+        }
+    }
+
+    /// Restores replicas of a previously compiled engine, provided it was built from
+    /// the same filters.
+    fn read_cached_engines(&self, filters_hash: &str) -> Option<(Vec<Engine>, usize)> {
+        let cached = std::fs::read(&self.engine_cache_path).ok()?;
+
+        if cached.len() < filters_hash.len()
+            || &cached[..filters_hash.len()] != filters_hash.as_bytes()
+        {
+            log::debug!("Filters changed since the blocking engine was cached.");
+            return None;
+        }
+
+        let engines = (0..self.engine.shards())
+            .map_while(|_| deserialize_engine(&cached[filters_hash.len()..]))
+            .collect::<Vec<_>>();
+
+        if engines.is_empty() {
+            None
+        } else {
+            Some((engines, cached.len() - filters_hash.len()))
+        }
+    }
+
+    fn write_cached_engine(&self, serialized: &[u8], filters_hash: &str) {
+        let mut cached = Vec::with_capacity(filters_hash.len() + serialized.len());
+        cached.extend_from_slice(filters_hash.as_bytes());
+        cached.extend_from_slice(serialized);
+
+        if let Err(err) = std::fs::write(&self.engine_cache_path, cached) {
+            log::warn!("Unable to write blocking engine cache: {}", err);
+        }
+    }
+
+    fn build_engine(&self, build: u64, filters: Vec<FilterContent>) {
+        log::debug!("Building blocking engine.");
+
+        let started_at = Instant::now();
+        let _ = self.status_sender.send(StatusEvent::EngineLoading {
+            now: chrono::Utc::now(),
+        });
+
+        let filters_hash = hash_filters(&filters);
+
+        let (mut adblock_engines, serialized_size, from_cache) = match self
+            .read_cached_engines(&filters_hash)
+        {
+            Some((adblock_engines, serialized_size)) => {
+                (adblock_engines, Some(serialized_size), true)
+            }
+            None => {
+                let mut filter_set = FilterSet::new(true);
+
+                for (parsed_lists, filter) in filters.iter().enumerate() {
+                    filter_set
+                        .add_filter_list(&filter.content, adblock::lists::ParseOptions::default());
+
+                    let _ = self.status_sender.send(StatusEvent::EngineProgress {
+                        now: chrono::Utc::now(),
+                        parsed_lists: parsed_lists + 1,
+                        lists: filters.len(),
+                    });
+                }
+
+                let adblock_engine = Engine::from_filter_set(filter_set, true);
+                let mut adblock_engines = Vec::with_capacity(self.engine.shards());
+                let mut serialized_size = None;
+
+                match adblock_engine.serialize_raw() {
+                    Ok(serialized) => {
+                        serialized_size = Some(serialized.len());
+
+                        // The more recent build will cache its own engine.
+                        if self.engine.is_latest_build(build) {
+                            self.write_cached_engine(&serialized, &filters_hash);
+                        }
+
+                        // Restoring replicas is much faster than compiling the filters again.
+                        adblock_engines.extend(
+                            (1..self.engine.shards())
+                                .map_while(|_| deserialize_engine(&serialized)),
+                        );
+                    }
+                    Err(err) => log::warn!("Unable to serialize blocking engine: {:?}", err),
+                }
+                adblock_engines.insert(0, adblock_engine);
+
+                (adblock_engines, serialized_size, false)
+            }
+        };
+
+        let rule_counts = RuleCounts::count(&filters);
+        let filters = Arc::new(filters);
+        let mut replicas = adblock_engines
+            .drain(..)
+            .map(|mut adblock_engine| {
+                // Resources are not part of the serialized engine.
+                adblock_engine.use_resources(ADBLOCKING_RESOURCES.clone());
+
+                Arc::new(FilterEngine::new(
+                    adblock_engine,
+                    filters.clone(),
+                    rule_counts.clone(),
+                    serialized_size,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        // Shards left without a replica of their own share the first one.
+        while replicas.len() < self.engine.shards() {
+            replicas.push(replicas[0].clone());
+        }
+
+        let is_swapped = self.engine.swap(build, replicas, || {
+            self.content_rewrite_store.replace_filter_rules(&filters);
+            self.html_filter_store.replace_filter_rules(&filters);
+
+            self.health_store.set_engine_ready(filters.len());
+        });
+
+        if !is_swapped {
+            return;
+        }
+
+        self.restart_rule_profiling();
+
+        let duration = started_at.elapsed();
+        self.statistics.record_engine_build(duration);
+
+        log::debug!(
+            "Blocking engine ready in {:?}, from cache: {}",
+            duration,
+            from_cache
+        );
+        let _ = self.status_sender.send(StatusEvent::EngineReady {
+            now: chrono::Utc::now(),
+            from_cache,
+            duration_ms: duration.as_millis() as u64,
+        });
+    }
+
+    fn build_custom_engine(&self, build: u64, custom_filters: Vec<String>) {
+        let started_at = Instant::now();
+
+        let filters = Arc::new(vec![FilterContent {
+            source: CUSTOM_FILTERS_SOURCE.to_string(),
+            content: custom_filters.join("\n"),
+        }]);
+        let rule_counts = RuleCounts::count(&filters);
+
+        // Custom filters are few, compiling them for each shard is cheap.
+        let replicas = (0..self.custom_engine.shards())
+            .map(|_| {
+                let mut filter_set = FilterSet::new(true);
+                filter_set.add_filters(&custom_filters, adblock::lists::ParseOptions::default());
+
+                let mut custom_engine = Engine::from_filter_set(filter_set, true);
+                custom_engine.use_resources(ADBLOCKING_RESOURCES.clone());
+
+                Arc::new(FilterEngine::new(
+                    custom_engine,
+                    filters.clone(),
+                    rule_counts.clone(),
+                    None,
+                ))
+            })
+            .collect();
+
+        let is_swapped = self.custom_engine.swap(build, replicas, || {
+            self.content_rewrite_store
+                .replace_custom_filter_rules(&custom_filters);
+            self.html_filter_store
+                .replace_custom_filter_rules(&custom_filters);
+
+            self.health_store.set_custom_filters(custom_filters.len());
+        });
+
+        if is_swapped {
+            self.restart_rule_profiling();
+
+            log::debug!("Custom filters engine ready in {:?}", started_at.elapsed());
+        }
+    }
+}
+
+lazy_static! {
+    static ref ADBLOCKING_RESOURCES: Vec<Resource> = {
+        let mut resources =
+            read_template_resources(include_str!("../resources/vendor/ublock/scriptlets.js"));
+
+        static WEB_ACCESSIBLE_RESOURCES: Dir =
+            include_dir!("$CARGO_MANIFEST_DIR/resources/vendor/ublock/web_accessible_resources/");
+
+        let resource_properties = read_redirectable_resource_mapping(include_str!(
+            "../resources/vendor/ublock/redirect-resources.js"
+        ));
+
+        resources.extend(resource_properties.iter().filter_map(|resource_info| {
+            WEB_ACCESSIBLE_RESOURCES
+                .get_file(&resource_info.name)
+                .map(|resource| {
+                    build_resource_from_file_contents(resource.contents(), resource_info)
+                })
+        }));
+
+        resources
+    };
+}
+
+impl Blocker {
+    /// Creates a blocker per receiver, each matching requests against replicas of the
+    /// engines of its own.
+    pub(crate) fn new_shards(
+        receivers: Vec<Receiver<BlockerRequest>>,
+        state: &AppState,
+    ) -> Vec<Self> {
+        let engine = Arc::new(EngineSlot::new(receivers.len()));
+        let custom_engine = Arc::new(EngineSlot::new(receivers.len()));
+
+        let engine_builder = EngineBuilder {
+            engine: engine.clone(),
+            custom_engine: custom_engine.clone(),
+            content_rewrite_store: state.content_rewrite_store.clone(),
+            html_filter_store: state.html_filter_store.clone(),
+            engine_cache_path: get_engine_cache_file(),
+            status_sender: state.status_sender.clone(),
+            health_store: state.health_store.clone(),
+            statistics: state.statistics.clone(),
+            rule_profiler: RuleProfiler::default(),
+        };
+
+        receivers
+            .into_iter()
+            .enumerate()
+            .map(|(shard, receiver)| Self {
+                receiver,
+                shard,
+                engine: engine.clone(),
+                custom_engine: custom_engine.clone(),
+                blocking_disabled: state.blocking_disabled_store.clone(),
+                site_policy_store: state.site_policy_store.clone(),
+                statistics: state.statistics.clone(),
+                engine_builder: engine_builder.clone(),
+            })
+            .collect()
+    }
+
+    fn load_engines(&self) -> LoadedEngines {
+        LoadedEngines {
+            engine: self.engine.load(self.shard),
+            custom_engine: self.custom_engine.load(self.shard),
+        }
+    }
+
+    fn check_network_url(
+        &self,
+        network_url: &NetworkUrl,
+    ) -> Result<(AdblockerBlockerResult, RequestDecision), RequestError> {
+        let req = Request::new(
+            network_url.url.as_str(),
+            network_url.referer.as_str(),
+            network_url.resource_type.as_adblock_str(),
+        )?;
+
+        if let Some(site_policy_match) = self.get_site_policy_match(network_url) {
+            return Ok(get_site_policy_result(site_policy_match));
+        }
+
+        let engines = self.load_engines();
+        let blocker_result = engines.check_network_request(&req);
+
+        let csp = if blocker_result.matched {
+            None
+        } else {
+            engines.get_csp_directives(&req, network_url.resource_type)
+        };
+
+        let decision = RequestDecision {
+            blocked: blocker_result.matched,
+            important: blocker_result.important,
+            redirect: blocker_result
+                .redirect
+                .as_deref()
+                .map(get_redirect_resource_name),
+            filter: engines.get_matched_filter(blocker_result.filter.as_ref()),
+            exception: engines.get_matched_filter(blocker_result.exception.as_ref()),
+            csp,
+        };
+
+        Ok((blocker_result, decision))
+    }
+
+    /// Returns the site policy deciding what happens to a request, based on the site of
+    /// the page making it.
+    fn get_site_policy_match(&self, network_url: &NetworkUrl) -> Option<SitePolicyMatch> {
+        let referer = url::Url::parse(&network_url.referer).ok()?;
+
+        self.site_policy_store
+            .get_match(referer.host_str()?, network_url.resource_type)
+    }
+
+    fn get_engine_status(&self) -> EngineStatus {
+        let engines = (0..self.engine.shards())
+            .map(|shard| self.engine.load(shard))
+            .collect::<Vec<_>>();
+        let custom_engines = (0..self.custom_engine.shards())
+            .map(|shard| self.custom_engine.load(shard))
+            .collect::<Vec<_>>();
+
+        // Shards may share a replica.
+        let mut replicas = engines.iter().chain(&custom_engines).collect::<Vec<_>>();
+        replicas.dedup_by(|a, b| Arc::ptr_eq(a, b));
+
+        let memory_estimate_bytes = replicas
+            .iter()
+            .map(|replica| {
+                replica
+                    .serialized_size
+                    .unwrap_or(replica.get_filters_size())
+            })
+            .chain([engines[0].get_filters_size()])
+            .sum::<usize>();
+
+        EngineStatus {
+            shards: engines.len(),
+            filter_lists: engines[0].filters.len(),
+            rules: engines[0].rule_counts.clone(),
+            custom_rules: custom_engines[0].rule_counts.clone(),
+            memory_estimate_bytes: memory_estimate_bytes as u64,
+        }
+    }
+
+    pub fn handle_requests(self) {
+        while let Ok(request) = self.receiver.recv() {
+            match request.kind {
+                RequestKind::Cosmetic(cosmetic_request) => {
+                    if !self.blocking_disabled.is_enabled() {
+                        let _ = request.respond_to.send(BlockerResult::Cosmetic(
+                            CosmeticBlockerResult {
+                                hidden_selectors: Vec::new(),
+                                style_selectors: HashMap::new(),
+                                injected_script: None,
+                            },
+                        ));
+                        continue;
+                    }
+
+                    let started_at = Instant::now();
+                    let engines = self.load_engines();
+                    let mut hidden_selectors = Vec::new();
+                    let url_specific_resources =
+                        engines.url_cosmetic_resources(cosmetic_request.url.as_str());
+
+                    if !url_specific_resources.generichide {
+                        for engine in [&engines.engine, &engines.custom_engine] {
+                            let generic_selectors = engine.engine.hidden_class_id_selectors(
+                                &cosmetic_request.classes,
+                                &cosmetic_request.ids,
+                                &url_specific_resources.exceptions,
+                            );
+
+                            hidden_selectors.extend(generic_selectors);
+                        }
+                    }
+
+                    hidden_selectors.extend(url_specific_resources.hide_selectors);
+
+                    let injected_script = if !url_specific_resources.injected_script.is_empty() {
+                        Some(url_specific_resources.injected_script)
+                    } else {
+                        None
+                    };
+
+                    self.statistics
+                        .record_engine_shard_match(self.shard, started_at.elapsed());
+                    self.engine_builder
+                        .rule_profiler
+                        .profile(ProfiledRequest::Cosmetic {
+                            url: cosmetic_request.url,
+                        });
+
+                    let _ =
+                        request
+                            .respond_to
+                            .send(BlockerResult::Cosmetic(CosmeticBlockerResult {
+                                hidden_selectors,
+                                style_selectors: url_specific_resources.style_selectors,
+                                injected_script,
+                            }));
+                }
+                RequestKind::Url(network_url) => {
+                    if !self.blocking_disabled.is_enabled() {
+                        let _ = request.respond_to.send(BlockerResult::Network(
+                            AdblockerBlockerResult {
+                                matched: false,
+                                important: false,
+                                redirect: None,
+                                exception: None,
+                                filter: None,
+                                rewritten_url: None,
+                            },
+                            RequestDecision::default(),
+                        ));
+                        continue;
+                    }
+
+                    let started_at = Instant::now();
+                    let (blocker_result, decision) = self.check_network_url(&network_url).unwrap();
+                    self.statistics
+                        .record_engine_shard_match(self.shard, started_at.elapsed());
+                    self.engine_builder
+                        .rule_profiler
+                        .profile(ProfiledRequest::Network {
+                            url: network_url.url,
+                            referer: network_url.referer,
+                            resource_type: network_url.resource_type,
+                        });
+
+                    let _ = request
+                        .respond_to
+                        .send(BlockerResult::Network(blocker_result, decision));
+                }
+                RequestKind::Test(network_url) => {
+                    let result = self
+                        .check_network_url(&network_url)
+                        .map(|(_blocker_result, decision)| decision)
+                        .map_err(|err| format!("Invalid request: {:?}", err));
+
+                    let _ = request.respond_to.send(BlockerResult::Test(result));
+                }
+                RequestKind::Status => {
+                    let _ = request
+                        .respond_to
+                        .send(BlockerResult::Status(self.get_engine_status()));
+                }
+                RequestKind::SetRuleProfiling(enabled) => {
+                    let rule_profiler = &self.engine_builder.rule_profiler;
+
+                    if enabled {
+                        rule_profiler.start(self.engine_builder.get_profiled_filters());
+                    } else {
+                        rule_profiler.stop();
+                    }
+                }
+                RequestKind::RulePerformance => {
+                    let _ = request.respond_to.send(BlockerResult::RulePerformance(
+                        self.engine_builder.rule_profiler.get_performance(),
+                    ));
+                }
+                RequestKind::ReplaceEngine(filters) => {
+                    let build = self.engine.request_build();
+                    let engine_builder = self.engine_builder.clone();
+
+                    std::thread::spawn(move || engine_builder.build_engine(build, filters));
+                }
+                RequestKind::ReplaceCustomEngine(custom_filters) => {
+                    let build = self.custom_engine.request_build();
+                    let engine_builder = self.engine_builder.clone();
+
+                    std::thread::spawn(move || {
+                        engine_builder.build_custom_engine(build, custom_filters)
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Site policies take the place of filter rules, matching requests regardless of
+/// the filters.
+fn get_site_policy_result(
+    site_policy_match: SitePolicyMatch,
+) -> (AdblockerBlockerResult, RequestDecision) {
+    let rule = site_policy_match.rule();
+    let matched_filter = Some(MatchedFilter {
+        rule: rule.clone(),
+        list: Some(SITE_POLICIES_SOURCE.to_string()),
+    });
+
+    match site_policy_match.action {
+        SitePolicyAction::Block => (
+            AdblockerBlockerResult {
+                matched: true,
+                filter: Some(rule),
+                ..Default::default()
+            },
+            RequestDecision {
+                blocked: true,
+                filter: matched_filter,
+                ..Default::default()
+            },
+        ),
+        SitePolicyAction::Allow | SitePolicyAction::Default => (
+            AdblockerBlockerResult {
+                exception: Some(rule),
+                ..Default::default()
+            },
+            RequestDecision {
+                exception: matched_filter,
+                ..Default::default()
+            },
+        ),
+    }
+}
+
+/// Finds which resource a redirect `data:` url was built from, falling back to the
+/// media type of the url for resources we don't know of.
+fn get_redirect_resource_name(redirect: &str) -> String {
+    let (metadata, data) = redirect.split_once(',').unwrap_or((redirect, ""));
+
+    match ADBLOCKING_RESOURCES
+        .iter()
+        .find(|resource| !resource.content.is_empty() && resource.content == data)
+    {
+        Some(resource) => resource.name.clone(),
+        None => metadata.to_string(),
+    }
+}
+
+/// Hex encoded SHA-256 digest identifying a set of filters.
+fn hash_filters(filters: &[FilterContent]) -> String {
+    let mut hasher = Sha256::new();
+
+    for filter in filters {
+        hasher.update(&filter.content);
+        // Separates filters so that moving lines between lists changes the digest.
+        hasher.update([0]);
+    }
+
+    hex::encode(hasher.finalize())
+}
+
+fn deserialize_engine(serialized: &[u8]) -> Option<Engine> {
+    let mut engine = Engine::new(true);
+
+    if let Err(err) = engine.deserialize(serialized) {
+        log::warn!("Unable to deserialize blocking engine: {:?}", err);
+        return None;
+    }
+
+    Some(engine)
+}
+
+#[derive(Debug, Clone)]
+pub struct AdblockRequester {
+    /// Channels of the blockers, one per shard.
+    adblock_request_channels: Vec<AdblockRequestChannel>,
+}
+
+impl AdblockRequester {
+    pub(crate) fn new(adblock_request_channels: Vec<AdblockRequestChannel>) -> Self {
+        Self {
+            adblock_request_channels,
+        }
+    }
+
+    /// Returns the channel of the shard requests to `url` are matched by. Requests of a
+    /// host always go to the same shard, whose regex cache then holds the rules of the
+    /// host.
+    fn get_shard_channel(&self, url: &str) -> &AdblockRequestChannel {
+        if self.adblock_request_channels.len() == 1 {
+            return &self.adblock_request_channels[0];
+        }
+
+        let mut hasher = DefaultHasher::new();
+        match url::Url::parse(url) {
+            Ok(url) => url.host_str().hash(&mut hasher),
+            Err(_err) => url.hash(&mut hasher),
+        }
+
+        &self.adblock_request_channels
+            [(hasher.finish() % self.adblock_request_channels.len() as u64) as usize]
+    }
+
+    /// Any blocker can build engines, which replace those of every shard.
+    fn get_build_channel(&self) -> &AdblockRequestChannel {
+        &self.adblock_request_channels[0]
+    }
+
+    pub async fn replace_engine(&self, filters: Vec<FilterContent>) {
+        let (sender, _receiver) = oneshot::channel();
+
+        self.get_build_channel()
+            .send(BlockerRequest {
+                respond_to: sender,
+                kind: RequestKind::ReplaceEngine(filters),
+            })
+            .unwrap();
+    }
+
+    pub async fn replace_custom_engine(&self, custom_filters: Vec<String>) {
+        let (sender, _receiver) = oneshot::channel();
+
+        self.get_build_channel()
+            .send(BlockerRequest {
+                respond_to: sender,
+                kind: RequestKind::ReplaceCustomEngine(custom_filters),
+            })
+            .unwrap();
+    }
+
+    pub async fn get_cosmetic_response(
+        &self,
+        url: String,
+        ids: Vec<String>,
+        classes: Vec<String>,
+    ) -> CosmeticBlockerResult {
+        let (sender, receiver) = oneshot::channel();
+
+        self.get_shard_channel(&url)
+            .send(BlockerRequest {
+                respond_to: sender,
+                kind: RequestKind::Cosmetic(CosmeticRequest { url, ids, classes }),
+            })
+            .unwrap();
+
+        match receiver.await {
+            Ok(blocker_result) => match blocker_result {
+                BlockerResult::Cosmetic(blocker_result) => blocker_result,
+                BlockerResult::Network(..)
+                | BlockerResult::Test(_)
+                | BlockerResult::Status(_)
+                | BlockerResult::RulePerformance(_) => {
+                    unreachable!()
+                }
+            },
+            Err(_err) => unreachable!(),
+        }
+    }
+
+    pub async fn is_network_url_blocked(
+        &self,
+        network_url: String,
+        referer: String,
+        resource_type: ResourceType,
+    ) -> (adblock::blocker::BlockerResult, RequestDecision) {
+        let (sender, receiver) = oneshot::channel();
+
+        self.get_shard_channel(&network_url)
+            .send(BlockerRequest {
+                respond_to: sender,
+                kind: RequestKind::Url(NetworkUrl {
+                    url: network_url,
+                    referer,
+                    resource_type,
+                }),
+            })
+            .unwrap();
+
+        match receiver.await {
+            Ok(blocker_result) => match blocker_result {
+                BlockerResult::Network(blocker_result, decision) => (blocker_result, decision),
+                BlockerResult::Cosmetic(_)
+                | BlockerResult::Test(_)
+                | BlockerResult::Status(_)
+                | BlockerResult::RulePerformance(_) => {
+                    unreachable!()
+                }
+            },
+            Err(_err) => unreachable!(),
+        }
+    }
+
+    /// Returns the decision the blocking engine would take for a request, whether
+    /// blocking is enabled or not.
+    pub async fn test_network_url(
+        &self,
+        network_url: String,
+        referer: String,
+        resource_type: ResourceType,
+    ) -> Result<RequestDecision, String> {
+        let (sender, receiver) = oneshot::channel();
+
+        self.get_shard_channel(&network_url)
+            .send(BlockerRequest {
+                respond_to: sender,
+                kind: RequestKind::Test(NetworkUrl {
+                    url: network_url,
+                    referer,
+                    resource_type,
+                }),
+            })
+            .unwrap();
+
+        match receiver.await {
+            Ok(blocker_result) => match blocker_result {
+                BlockerResult::Test(result) => result,
+                BlockerResult::Network(..)
+                | BlockerResult::Cosmetic(_)
+                | BlockerResult::Status(_)
+                | BlockerResult::RulePerformance(_) => {
+                    unreachable!()
+                }
+            },
+            Err(_err) => unreachable!(),
+        }
+    }
+
+    pub async fn get_engine_status(&self) -> EngineStatus {
+        let (sender, receiver) = oneshot::channel();
+
+        self.get_build_channel()
+            .send(BlockerRequest {
+                respond_to: sender,
+                kind: RequestKind::Status,
+            })
+            .unwrap();
+
+        match receiver.await {
+            Ok(blocker_result) => match blocker_result {
+                BlockerResult::Status(engine_status) => engine_status,
+                BlockerResult::Network(..)
+                | BlockerResult::Cosmetic(_)
+                | BlockerResult::Test(_)
+                | BlockerResult::RulePerformance(_) => {
+                    unreachable!()
+                }
+            },
+            Err(_err) => unreachable!(),
+        }
+    }
+
+    pub async fn set_rule_profiling(&self, enabled: bool) {
+        let (sender, _receiver) = oneshot::channel();
+
+        self.get_build_channel()
+            .send(BlockerRequest {
+                respond_to: sender,
+                kind: RequestKind::SetRuleProfiling(enabled),
+            })
+            .unwrap();
+    }
+
+    pub async fn get_rule_performance(&self) -> RulePerformance {
+        let (sender, receiver) = oneshot::channel();
+
+        self.get_build_channel()
+            .send(BlockerRequest {
+                respond_to: sender,
+                kind: RequestKind::RulePerformance,
+            })
+            .unwrap();
+
+        match receiver.await {
+            Ok(blocker_result) => match blocker_result {
+                BlockerResult::RulePerformance(rule_performance) => rule_performance,
+                BlockerResult::Network(..)
+                | BlockerResult::Cosmetic(_)
+                | BlockerResult::Test(_)
+                | BlockerResult::Status(_) => {
+                    unreachable!()
+                }
+            },
+            Err(_err) => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Starting Privaxy from other programs.
+
+use crate::configuration::ConfigurationError;
+use crate::startup::{try_set_startup_options, StartupOptions};
+use crate::PrivaxyServer;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum StartError {
+    #[error("startup options were already set, privaxy can only be started once per process")]
+    AlreadyStarted,
+    #[error("unable to process the configuration file: {0}")]
+    Configuration(ConfigurationError),
+    #[error("unable to decode the CA certificate: {0}")]
+    CaCertificate(ConfigurationError),
+    #[error("unable to decode the CA private key: {0}")]
+    CaPrivateKey(ConfigurationError),
+    #[error("invalid CA: {0}")]
+    InvalidCa(ConfigurationError),
+}
+
+/// Starts the proxy, its blocking engine and the web server within a Tokio runtime.
+///
+/// Settings left unset are read from the configuration file of the base path, which is
+/// created with defaults when missing. Once started, the stores of [`PrivaxyServer`]
+/// change what the proxy does, and the configuration can be replaced through its
+/// `configuration_updater_sender`.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), privaxy_core::StartError> {
+/// let server = privaxy_core::ProxyBuilder::new()
+///     .base_path("/var/lib/my-app/privaxy")
+///     .proxy_port(8100)
+///     .web_server(false)
+///     .start()
+///     .await?;
+///
+/// // Lets requests through until blocking is enabled again.
+/// server.blocking_disabled_store.set(false);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProxyBuilder {
+    startup_options: StartupOptions,
+    handle_signals: bool,
+    init_logging: bool,
+    resolved_address_override: Option<IpAddr>,
+}
+
+impl ProxyBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from options parsed from the command line or the environment, as the
+    /// `privaxy` binary does.
+    pub fn from_startup_options(startup_options: StartupOptions) -> Self {
+        Self {
+            startup_options,
+            ..Self::default()
+        }
+    }
+
+    /// Directory of the configuration file, the filters and the other files privaxy
+    /// keeps.
+    pub fn base_path(mut self, base_path: impl Into<PathBuf>) -> Self {
+        self.startup_options.base_path = Some(base_path.into());
+        self
+    }
+
+    /// Address the proxy and the web server listen on.
+    pub fn ip_address(mut self, ip_address: IpAddr) -> Self {
+        self.startup_options.ip_address = Some(ip_address);
+        self
+    }
+
+    pub fn proxy_port(mut self, proxy_port: u16) -> Self {
+        self.startup_options.proxy_port = Some(proxy_port);
+        self
+    }
+
+    pub fn web_port(mut self, web_port: u16) -> Self {
+        self.startup_options.web_port = Some(web_port);
+        self
+    }
+
+    /// Path the web interface and the API are served under, such as `/privaxy/`.
+    pub fn web_base_path(mut self, web_base_path: impl Into<String>) -> Self {
+        self.startup_options.web_base_path = Some(web_base_path.into());
+        self
+    }
+
+    /// Unix socket the web interface and the API are also served on.
+    pub fn admin_socket(mut self, admin_socket: impl Into<PathBuf>) -> Self {
+        self.startup_options.admin_socket = Some(admin_socket.into());
+        self
+    }
+
+    /// Whether the web server is started, it is by default.
+    pub fn web_server(mut self, enabled: bool) -> Self {
+        self.startup_options.no_web_server = !enabled;
+        self
+    }
+
+    /// Whether the web server serves the web interface besides the API, it does by
+    /// default when the `web-frontend` feature is enabled.
+    pub fn web_interface(mut self, enabled: bool) -> Self {
+        self.startup_options.no_web_interface = !enabled;
+        self
+    }
+
+    /// Whether requests are blocked from the start, they are by default.
+    pub fn blocking(mut self, enabled: bool) -> Self {
+        self.startup_options.disable_blocking = !enabled;
+        self
+    }
+
+    /// Whether edits made to the configuration file while privaxy runs are applied,
+    /// they are by default.
+    pub fn watch_configuration(mut self, enabled: bool) -> Self {
+        self.startup_options.no_configuration_watch = !enabled;
+        self
+    }
+
+    /// Reload on SIGHUP and complete [`PrivaxyServer::shutdown_requested`] on SIGTERM,
+    /// as the `privaxy` binary does. Programs embedding privaxy usually handle signals
+    /// themselves and call [`PrivaxyServer::reload`].
+    pub fn handle_signals(mut self, enabled: bool) -> Self {
+        self.handle_signals = enabled;
+        self
+    }
+
+    /// Set up logging with [`crate::logging::init`] and the log options, instead of
+    /// leaving it to the embedding program.
+    pub fn init_logging(mut self, enabled: bool) -> Self {
+        self.init_logging = enabled;
+        self
+    }
+
+    /// Makes every name the proxy connects to resolve to `address`, for `privaxy bench`
+    /// to reach its local server.
+    pub(crate) fn resolved_address_override(mut self, address: IpAddr) -> Self {
+        self.resolved_address_override = Some(address);
+        self
+    }
+
+    /// Startup options are global to the process, so this fails when privaxy was
+    /// already started, or when they were read before, such as by
+    /// [`crate::logging::init`].
+    pub async fn start(mut self) -> Result<PrivaxyServer, StartError> {
+        if !try_set_startup_options(std::mem::take(&mut self.startup_options)) {
+            return Err(StartError::AlreadyStarted);
+        }
+
+        if self.init_logging {
+            crate::logging::init();
+        }
+
+        self.start_with_current_options().await
+    }
+
+    /// Starts with the startup options that were already set, leaving out those of the
+    /// builder. `privaxy bench` sets them to read the configuration before starting the
+    /// proxy.
+    pub(crate) async fn start_with_current_options(self) -> Result<PrivaxyServer, StartError> {
+        crate::try_start_privaxy(self.handle_signals, self.resolved_address_override).await
+    }
+}
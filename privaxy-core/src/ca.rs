@@ -1,3 +1,6 @@
+use crate::cert::CertCache;
+use crate::web_gui::events::StatusEvent;
+use chrono::{DateTime, Utc};
 use openssl::asn1::Asn1Time;
 use openssl::bn::{BigNum, MsbOption};
 use openssl::hash::MessageDigest;
@@ -6,10 +9,17 @@ use openssl::pkey::Private;
 use openssl::rsa::Rsa;
 use openssl::x509::extension::{BasicConstraints, KeyUsage, SubjectKeyIdentifier};
 use openssl::x509::X509NameBuilder;
-use openssl::x509::X509;
+use openssl::x509::{X509Ref, X509};
+use std::time::Duration;
+use tokio::sync::broadcast;
 
 const ORGANIZATION_NAME: &str = "Privaxy";
 
+/// The CA is reported as expiring when it expires in less than this many days.
+const CA_EXPIRY_WARNING_DAYS: i64 = 30;
+
+const CA_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60 * 12);
+
 pub fn make_ca_certificate() -> (X509, PKey<Private>) {
     let rsa: Rsa<Private> = Rsa::generate(2048).unwrap();
     let key_pair = PKey::from_rsa(rsa).unwrap();
@@ -74,3 +84,40 @@ pub fn make_ca_certificate() -> (X509, PKey<Private>) {
 
     (cert, key_pair)
 }
+
+fn get_expiry(ca_certificate: &X509Ref) -> Option<DateTime<Utc>> {
+    let time_left = Asn1Time::days_from_now(0)
+        .ok()?
+        .diff(ca_certificate.not_after())
+        .ok()?;
+
+    Some(
+        Utc::now()
+            + chrono::Duration::days(time_left.days.into())
+            + chrono::Duration::seconds(time_left.secs.into()),
+    )
+}
+
+/// Reports on the events stream when the CA is about to expire, for it to be rotated
+/// before clients stop trusting the certificates it issues.
+pub(crate) async fn warn_before_ca_expiry(
+    cert_cache: CertCache,
+    status_sender: broadcast::Sender<StatusEvent>,
+) {
+    loop {
+        let ca_certificate = cert_cache.get_ca_certificate().await;
+
+        if let Some(expires_at) = get_expiry(&ca_certificate) {
+            if expires_at - Utc::now() < chrono::Duration::days(CA_EXPIRY_WARNING_DAYS) {
+                log::warn!("The CA certificate expires on {expires_at}, it should be rotated");
+
+                let _ = status_sender.send(StatusEvent::CaExpiring {
+                    now: Utc::now(),
+                    expires_at,
+                });
+            }
+        }
+
+        tokio::time::sleep(CA_EXPIRY_CHECK_INTERVAL).await;
+    }
+}
@@ -0,0 +1,244 @@
+use crate::configuration::{ContentCategories, CronRule};
+use crate::threat_intelligence::{is_host_listed, parse_hosts};
+use chrono::{DateTime, Local, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use url::Url;
+
+/// How often lists are checked for being due for an update.
+const LISTS_UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Download state of the list of a category.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct CategoryStatus {
+    pub name: String,
+    pub enabled: bool,
+    /// Whether the category is blocked right now, for the clients it applies to.
+    pub active: bool,
+    /// Listed hosts.
+    pub entries: usize,
+    /// When the list was last downloaded successfully.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Why the last download failed, the hosts of the previous one are kept.
+    pub error: Option<String>,
+}
+
+/// Hosts of a category, as last downloaded.
+#[derive(Debug, Default)]
+struct DownloadedList {
+    hosts: HashSet<String>,
+    updated_at: Option<DateTime<Utc>>,
+    checked_at: Option<Instant>,
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Categories {
+    configuration: ContentCategories,
+    /// Parsed schedules, keyed by category name.
+    schedules: HashMap<String, CronRule>,
+    /// Keyed by list URL.
+    downloaded: HashMap<Url, DownloadedList>,
+}
+
+impl Categories {
+    fn is_active(&self, name: &str, now: &DateTime<Local>) -> bool {
+        self.schedules
+            .get(name)
+            .is_none_or(|schedule| schedule.matches(now))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CategoryStore {
+    categories: Arc<RwLock<Categories>>,
+    configuration_changed: Arc<Notify>,
+}
+
+impl CategoryStore {
+    pub fn new(categories: ContentCategories) -> Self {
+        let store = Self {
+            categories: Arc::new(RwLock::new(Categories {
+                configuration: ContentCategories::default(),
+                schedules: HashMap::new(),
+                downloaded: HashMap::new(),
+            })),
+            configuration_changed: Arc::new(Notify::new()),
+        };
+        store.replace(categories);
+
+        store
+    }
+
+    /// Lists of categories that were added or enabled are downloaded right away.
+    pub fn replace(&self, categories: ContentCategories) {
+        let mut current_categories = self.categories.write().unwrap();
+
+        let urls = categories
+            .categories
+            .iter()
+            .map(|category| category.url.clone())
+            .collect::<HashSet<_>>();
+        current_categories
+            .downloaded
+            .retain(|url, _list| urls.contains(url));
+
+        // Schedules are validated before being saved, one edited by hand may not be.
+        current_categories.schedules = categories
+            .categories
+            .iter()
+            .filter_map(|category| {
+                let schedule = CronRule::from_str(category.schedule.as_deref()?).ok()?;
+                Some((category.name.clone(), schedule))
+            })
+            .collect();
+        current_categories.configuration = categories;
+
+        self.configuration_changed.notify_one();
+    }
+
+    /// Returns the first enabled and scheduled category listing `host`, or one of its
+    /// parent domains, for the given client or proxy user.
+    pub(crate) fn get_match(
+        &self,
+        host: &str,
+        client_identity: &str,
+        user: Option<&str>,
+    ) -> Option<String> {
+        let categories = self.categories.read().unwrap();
+        let host = host.to_lowercase();
+        let now = Local::now();
+
+        categories
+            .configuration
+            .categories
+            .iter()
+            .filter(|category| {
+                category.enabled
+                    && (category.applies_to(client_identity)
+                        || user.is_some_and(|user| category.applies_to(user)))
+            })
+            .filter(|category| categories.is_active(&category.name, &now))
+            .find(|category| match categories.downloaded.get(&category.url) {
+                Some(list) => is_host_listed(&list.hosts, &host),
+                None => false,
+            })
+            .map(|category| category.name.clone())
+    }
+
+    pub(crate) fn get_statuses(&self) -> Vec<CategoryStatus> {
+        let categories = self.categories.read().unwrap();
+        let now = Local::now();
+
+        categories
+            .configuration
+            .categories
+            .iter()
+            .map(|category| {
+                let list = categories.downloaded.get(&category.url);
+
+                CategoryStatus {
+                    name: category.name.clone(),
+                    enabled: category.enabled,
+                    active: category.enabled && categories.is_active(&category.name, &now),
+                    entries: list.map_or(0, |list| list.hosts.len()),
+                    updated_at: list.and_then(|list| list.updated_at),
+                    error: list.and_then(|list| list.error.clone()),
+                }
+            })
+            .collect()
+    }
+
+    /// Downloads the lists of enabled categories that are due for an update, or all of
+    /// them when `force` is set.
+    pub(crate) async fn update(&self, http_client: &reqwest::Client, force: bool) {
+        let due_categories = {
+            let categories = self.categories.read().unwrap();
+            let update_interval = categories.configuration.update_interval();
+
+            categories
+                .configuration
+                .categories
+                .iter()
+                .filter(|category| category.enabled)
+                .filter(|category| {
+                    force
+                        || categories
+                            .downloaded
+                            .get(&category.url)
+                            .and_then(|list| list.checked_at)
+                            .is_none_or(|checked_at| checked_at.elapsed() >= update_interval)
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        for category in due_categories {
+            let result = download_list(http_client, &category.url).await;
+
+            let mut categories = self.categories.write().unwrap();
+            let list = categories
+                .downloaded
+                .entry(category.url.clone())
+                .or_default();
+            list.checked_at = Some(Instant::now());
+
+            match result {
+                Ok(hosts) => {
+                    list.hosts = hosts;
+                    list.updated_at = Some(Utc::now());
+                    list.error = None;
+
+                    log::info!(
+                        "Updated category {} with {} hosts",
+                        category.name,
+                        list.hosts.len()
+                    );
+                }
+                Err(err) => {
+                    log::error!("Unable to update category {}: {}", category.name, err);
+
+                    list.error = Some(err);
+                }
+            }
+        }
+    }
+}
+
+async fn download_list(
+    http_client: &reqwest::Client,
+    url: &Url,
+) -> Result<HashSet<String>, String> {
+    let content = http_client
+        .get(url.clone())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?
+        .text()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    Ok(parse_hosts(&content))
+}
+
+/// Downloads the lists as they become due, and as soon as categories are added or
+/// enabled.
+pub(crate) async fn update_lists_periodically(
+    category_store: CategoryStore,
+    http_client: reqwest::Client,
+) {
+    loop {
+        category_store.update(&http_client, false).await;
+
+        tokio::select! {
+            _ = tokio::time::sleep(LISTS_UPDATE_CHECK_INTERVAL) => {}
+            _ = category_store.configuration_changed.notified() => {}
+        }
+    }
+}
@@ -0,0 +1,557 @@
+use crate::configuration::{LeafCertificates, LeafKeyType, SubjectAlternativeNames};
+use http::uri::Authority;
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    error::ErrorStack,
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{Id, PKey, PKeyRef, Private},
+    rsa::Rsa,
+    stack::Stack,
+    x509::{
+        extension::{
+            AuthorityKeyIdentifier, BasicConstraints, KeyUsage, SubjectAlternativeName,
+            SubjectKeyIdentifier,
+        },
+        store::X509StoreBuilder,
+        verify::X509VerifyFlags,
+        X509NameBuilder, X509Ref, X509Req, X509ReqBuilder, X509StoreContext, X509VerifyResult,
+        X509,
+    },
+};
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{str::FromStr, sync::Arc};
+use tokio::sync::Mutex;
+use uluru::LRUCache;
+
+const MAX_CACHED_CERTIFICATES: usize = 1_000;
+
+/// Filename of the key certificates are issued for, within the directory of the CA and
+/// settings they are issued with.
+const PRIVATE_KEY_FILE_NAME: &str = "key.pem";
+
+#[derive(Clone)]
+pub struct SignedWithCaCert {
+    authority: Authority,
+    subject_alternative_names: SubjectAlternativeNames,
+    pub server_configuration: ServerConfig,
+}
+
+impl SignedWithCaCert {
+    pub(super) fn new(
+        authority: Authority,
+        private_key: PKey<Private>,
+        ca_certificate: X509,
+        ca_private_key: PKey<Private>,
+        leaf_certificates: &LeafCertificates,
+        generation_directory: &Path,
+    ) -> Self {
+        let certificate_path = generation_directory.join(get_certificate_file_name(&authority));
+
+        let x509 = match read_persisted_certificate(&certificate_path, &private_key) {
+            Some(x509) => x509,
+            None => {
+                let x509 = Self::build_ca_signed_cert(
+                    &ca_certificate,
+                    &ca_private_key,
+                    &authority,
+                    &private_key,
+                    leaf_certificates,
+                );
+                persist(&certificate_path, &x509.to_pem().unwrap());
+                x509
+            }
+        };
+
+        let certs = vec![
+            Certificate(x509.to_der().unwrap()),
+            Certificate(ca_certificate.to_der().unwrap()),
+        ];
+
+        let server_configuration = ServerConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                certs,
+                PrivateKey(private_key.private_key_to_pkcs8().unwrap()),
+            )
+            .unwrap();
+
+        Self {
+            authority,
+            subject_alternative_names: leaf_certificates.subject_alternative_names,
+            server_configuration,
+        }
+    }
+
+    /// Whether the certificate is valid for the authority, either because it was issued
+    /// for it or because it covers its parent's subdomains.
+    fn covers(&self, authority: &Authority) -> bool {
+        if self.authority == *authority {
+            return true;
+        }
+
+        if self.subject_alternative_names != SubjectAlternativeNames::HostAndSubdomains
+            || self.authority.port_u16() != authority.port_u16()
+        {
+            return false;
+        }
+
+        // A wildcard only matches a single label.
+        authority
+            .host()
+            .strip_suffix(self.authority.host())
+            .and_then(|label| label.strip_suffix('.'))
+            .is_some_and(|label| !label.is_empty() && !label.contains('.'))
+    }
+
+    fn build_certificate_request(key_pair: &PKey<Private>, authority: &Authority) -> X509Req {
+        let mut request_builder = X509ReqBuilder::new().unwrap();
+        request_builder.set_pubkey(key_pair).unwrap();
+
+        let mut x509_name = X509NameBuilder::new().unwrap();
+
+        // Only 64 characters are allowed in the CN field.
+        // (ub-common-name INTEGER ::= 64), browsers are not using CN anymore but uses SANs instead.
+        // Let's use a shorter entry.
+        // RFC 3280.
+        let authority_host = authority.host();
+        let common_name = if authority_host.len() > 64 {
+            "privaxy_cn_too_long.local"
+        } else {
+            authority_host
+        };
+
+        x509_name.append_entry_by_text("CN", common_name).unwrap();
+        let x509_name = x509_name.build();
+        request_builder.set_subject_name(&x509_name).unwrap();
+
+        request_builder
+            .sign(key_pair, MessageDigest::sha256())
+            .unwrap();
+
+        request_builder.build()
+    }
+
+    fn build_ca_signed_cert(
+        ca_cert: &X509Ref,
+        ca_key_pair: &PKeyRef<Private>,
+        authority: &Authority,
+        private_key: &PKey<Private>,
+        leaf_certificates: &LeafCertificates,
+    ) -> X509 {
+        let req = Self::build_certificate_request(private_key, authority);
+
+        let mut cert_builder = X509::builder().unwrap();
+        cert_builder.set_version(2).unwrap();
+
+        let serial_number = {
+            let mut serial = BigNum::new().unwrap();
+            serial.rand(159, MsbOption::MAYBE_ZERO, false).unwrap();
+            serial.to_asn1_integer().unwrap()
+        };
+
+        cert_builder.set_serial_number(&serial_number).unwrap();
+        cert_builder.set_subject_name(req.subject_name()).unwrap();
+        cert_builder
+            .set_issuer_name(ca_cert.subject_name())
+            .unwrap();
+        cert_builder.set_pubkey(private_key).unwrap();
+
+        let not_before = {
+            let current_time = SystemTime::now();
+            let since_epoch = current_time
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards");
+            // patch NotValidBefore
+            Asn1Time::from_unix(since_epoch.as_secs() as i64 - 60).unwrap()
+        };
+        cert_builder.set_not_before(&not_before).unwrap();
+
+        let not_after = Asn1Time::days_from_now(leaf_certificates.validity_days()).unwrap();
+        cert_builder.set_not_after(&not_after).unwrap();
+
+        cert_builder
+            .append_extension(BasicConstraints::new().build().unwrap())
+            .unwrap();
+
+        let mut key_usage = KeyUsage::new();
+        key_usage.critical().non_repudiation().digital_signature();
+        // Keys are only encrypted to with RSA.
+        if private_key.id() == Id::RSA {
+            key_usage.key_encipherment();
+        }
+        cert_builder
+            .append_extension(key_usage.build().unwrap())
+            .unwrap();
+
+        let subject_alternative_name = match std::net::IpAddr::from_str(authority.host()) {
+            Ok(_ip_addr) => {
+                let mut san = SubjectAlternativeName::new();
+                san.ip(authority.host());
+
+                san
+            }
+            Err(_err) => {
+                let mut san = SubjectAlternativeName::new();
+                san.dns(authority.host());
+                if leaf_certificates.subject_alternative_names
+                    == SubjectAlternativeNames::HostAndSubdomains
+                {
+                    san.dns(&format!("*.{}", authority.host()));
+                }
+                san
+            }
+        }
+        .build(&cert_builder.x509v3_context(Some(ca_cert), None))
+        .unwrap();
+
+        cert_builder
+            .append_extension(subject_alternative_name)
+            .unwrap();
+
+        let subject_key_identifier = SubjectKeyIdentifier::new()
+            .build(&cert_builder.x509v3_context(Some(ca_cert), None))
+            .unwrap();
+        cert_builder
+            .append_extension(subject_key_identifier)
+            .unwrap();
+
+        let auth_key_identifier = AuthorityKeyIdentifier::new()
+            .keyid(false)
+            .issuer(false)
+            .build(&cert_builder.x509v3_context(Some(ca_cert), None))
+            .unwrap();
+        cert_builder.append_extension(auth_key_identifier).unwrap();
+
+        cert_builder
+            .sign(ca_key_pair, MessageDigest::sha256())
+            .unwrap();
+
+        cert_builder.build()
+    }
+}
+
+/// Checks that a certificate issued by the CA is trusted by clients trusting the CA, which
+/// requires it to be allowed to sign certificates and to be valid.
+pub(crate) fn verify_ca(
+    ca_certificate: &X509,
+    ca_private_key: &PKey<Private>,
+) -> Result<(), String> {
+    let authority = Authority::from_static("privaxy.test");
+    let certificate = SignedWithCaCert::build_ca_signed_cert(
+        ca_certificate,
+        ca_private_key,
+        &authority,
+        ca_private_key,
+        &LeafCertificates::default(),
+    );
+
+    let verify = || -> Result<X509VerifyResult, ErrorStack> {
+        let mut store = X509StoreBuilder::new()?;
+        store.add_cert(ca_certificate.clone())?;
+        // The CA may be an intermediate one, whose root clients trust as well.
+        store.set_flags(X509VerifyFlags::PARTIAL_CHAIN)?;
+        let store = store.build();
+
+        let chain = Stack::new()?;
+        let mut context = X509StoreContext::new()?;
+        context.init(&store, &certificate, &chain, |context| {
+            context.verify_cert()?;
+            Ok(context.error())
+        })
+    };
+
+    match verify() {
+        Ok(result) if result == X509VerifyResult::OK => Ok(()),
+        Ok(result) => Err(result.error_string().to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn generate_private_key(key_type: LeafKeyType) -> PKey<Private> {
+    match key_type {
+        LeafKeyType::EcdsaP256 => {
+            let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+            PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+        }
+        LeafKeyType::Rsa2048 => {
+            let rsa: Rsa<Private> = Rsa::generate(2048).unwrap();
+            PKey::from_rsa(rsa).unwrap()
+        }
+    }
+}
+
+/// Certificates are persisted in a directory per CA and settings they are issued with, so
+/// that replacing either invalidates them.
+fn get_generation_directory(
+    certificates_directory: &Path,
+    ca_certificate: &X509,
+    leaf_certificates: &LeafCertificates,
+) -> PathBuf {
+    let mut hasher = Sha256::new();
+    hasher.update(ca_certificate.to_der().unwrap());
+    hasher.update(serde_json::to_vec(leaf_certificates).unwrap());
+
+    certificates_directory.join(hex::encode(hasher.finalize()))
+}
+
+/// Hosts are hashed as they may contain characters file names can't.
+fn get_certificate_file_name(authority: &Authority) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(authority.as_str());
+
+    format!("{}.pem", hex::encode(hasher.finalize()))
+}
+
+fn persist(path: &Path, contents: &[u8]) {
+    let result = match path.parent() {
+        Some(directory) => std::fs::create_dir_all(directory),
+        None => Ok(()),
+    }
+    .and_then(|()| std::fs::write(path, contents));
+
+    if let Err(err) = result {
+        log::warn!("Unable to persist {}: {}", path.display(), err);
+    }
+}
+
+/// Returns a previously issued certificate, unless it's about to expire.
+fn read_persisted_certificate(path: &Path, private_key: &PKey<Private>) -> Option<X509> {
+    let certificate = X509::from_pem(&std::fs::read(path).ok()?).ok()?;
+
+    let valid_tomorrow = Asn1Time::days_from_now(1)
+        .and_then(|tomorrow| certificate.not_after().compare(&tomorrow))
+        .is_ok_and(|ordering| ordering == Ordering::Greater);
+    let issued_for_key = certificate
+        .public_key()
+        .is_ok_and(|public_key| public_key.public_eq(private_key));
+
+    if !valid_tomorrow || !issued_for_key {
+        return None;
+    }
+
+    Some(certificate)
+}
+
+/// Reads the key certificates were issued for with the same CA and settings, generating
+/// one when there is none.
+fn read_or_generate_private_key(
+    generation_directory: &Path,
+    key_type: LeafKeyType,
+) -> PKey<Private> {
+    let path = generation_directory.join(PRIVATE_KEY_FILE_NAME);
+
+    if let Some(private_key) = std::fs::read(&path)
+        .ok()
+        .and_then(|pem| PKey::private_key_from_pem(&pem).ok())
+    {
+        return private_key;
+    }
+
+    let private_key = generate_private_key(key_type);
+    persist(&path, &private_key.private_key_to_pem_pkcs8().unwrap());
+
+    private_key
+}
+
+/// Removes certificates issued with other CAs or settings than the current ones.
+fn remove_stale_generations(certificates_directory: &Path, generation_directory: &Path) {
+    let entries = match std::fs::read_dir(certificates_directory) {
+        Ok(entries) => entries,
+        Err(_err) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path != generation_directory && path.is_dir() {
+            if let Err(err) = std::fs::remove_dir_all(&path) {
+                log::warn!(
+                    "Unable to remove stale certificates {}: {}",
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+}
+
+/// Certificates issued for hosts, along with the CA and settings they were issued with.
+struct IssuedCertificates {
+    certificates: LRUCache<SignedWithCaCert, MAX_CACHED_CERTIFICATES>,
+    ca_certificate: X509,
+    ca_private_key: PKey<Private>,
+    leaf_certificates: LeafCertificates,
+    // We use a single key for all certificates.
+    private_key: PKey<Private>,
+    generation_directory: PathBuf,
+}
+
+#[derive(Clone)]
+pub struct CertCache {
+    cache: Arc<Mutex<IssuedCertificates>>,
+    /// Issued certificates are persisted there, so that they don't all have to be issued
+    /// again after a restart.
+    certificates_directory: PathBuf,
+}
+
+impl CertCache {
+    pub fn new(
+        ca_certificate: X509,
+        ca_private_key: PKey<Private>,
+        leaf_certificates: LeafCertificates,
+        certificates_directory: PathBuf,
+    ) -> Self {
+        let generation_directory =
+            get_generation_directory(&certificates_directory, &ca_certificate, &leaf_certificates);
+        let private_key =
+            read_or_generate_private_key(&generation_directory, leaf_certificates.key_type);
+        remove_stale_generations(&certificates_directory, &generation_directory);
+
+        Self {
+            cache: Arc::new(Mutex::new(IssuedCertificates {
+                certificates: LRUCache::default(),
+                ca_certificate,
+                ca_private_key,
+                leaf_certificates,
+                private_key,
+                generation_directory,
+            })),
+            certificates_directory,
+        }
+    }
+
+    /// Issues the next certificates with another CA or other settings, dropping those
+    /// issued until now. Does nothing when neither changed.
+    async fn switch_generation(
+        &self,
+        ca_certificate: X509,
+        ca_private_key: PKey<Private>,
+        leaf_certificates: LeafCertificates,
+    ) {
+        let generation_directory = get_generation_directory(
+            &self.certificates_directory,
+            &ca_certificate,
+            &leaf_certificates,
+        );
+
+        {
+            let cache = self.cache.lock().await;
+
+            if cache.generation_directory == generation_directory
+                && cache.ca_private_key.public_eq(&ca_private_key)
+            {
+                return;
+            }
+        }
+
+        let certificates_directory = self.certificates_directory.clone();
+        let key_generation_directory = generation_directory.clone();
+        let key_type = leaf_certificates.key_type;
+
+        let private_key = tokio::task::spawn_blocking(move || {
+            let private_key = read_or_generate_private_key(&key_generation_directory, key_type);
+            remove_stale_generations(&certificates_directory, &key_generation_directory);
+
+            private_key
+        })
+        .await
+        .unwrap();
+
+        let mut cache = self.cache.lock().await;
+
+        cache.certificates.clear();
+        cache.ca_certificate = ca_certificate;
+        cache.ca_private_key = ca_private_key;
+        cache.leaf_certificates = leaf_certificates;
+        cache.private_key = private_key;
+        cache.generation_directory = generation_directory;
+    }
+
+    /// Issues the next certificates with other settings, dropping those issued with the
+    /// current ones. Does nothing when the settings didn't change.
+    pub async fn set_leaf_certificates(&self, leaf_certificates: LeafCertificates) {
+        let (ca_certificate, ca_private_key) = {
+            let cache = self.cache.lock().await;
+
+            (cache.ca_certificate.clone(), cache.ca_private_key.clone())
+        };
+
+        self.switch_generation(ca_certificate, ca_private_key, leaf_certificates)
+            .await;
+    }
+
+    /// Issues the next certificates with another CA, dropping those issued by the current
+    /// one. Does nothing when the CA didn't change.
+    pub async fn replace_ca(&self, ca_certificate: X509, ca_private_key: PKey<Private>) {
+        let leaf_certificates = self.cache.lock().await.leaf_certificates.clone();
+
+        self.switch_generation(ca_certificate, ca_private_key, leaf_certificates)
+            .await;
+    }
+
+    /// The CA certificates are currently issued with.
+    pub async fn get_ca_certificate(&self) -> X509 {
+        self.cache.lock().await.ca_certificate.clone()
+    }
+
+    async fn insert(&self, certificate: SignedWithCaCert, generation_directory: &Path) {
+        let mut cache = self.cache.lock().await;
+
+        // The CA or the settings may have been replaced while the certificate was being
+        // issued.
+        if cache.generation_directory == generation_directory {
+            cache.certificates.insert(certificate);
+        }
+    }
+
+    pub async fn get(&self, authority: Authority) -> SignedWithCaCert {
+        let mut cache = self.cache.lock().await;
+
+        match cache.certificates.find(|cert| cert.covers(&authority)) {
+            Some(certificate) => certificate.clone(),
+            None => {
+                let ca_certificate = cache.ca_certificate.clone();
+                let ca_private_key = cache.ca_private_key.clone();
+                let private_key = cache.private_key.clone();
+                let leaf_certificates = cache.leaf_certificates.clone();
+                let generation_directory = cache.generation_directory.clone();
+
+                // We release the previously acquired lock early as `insert`, which we will call just
+                // afterwards also waits to acquire a lock.
+                std::mem::drop(cache);
+
+                let issuer_generation_directory = generation_directory.clone();
+
+                // This operation is somewhat CPU intensive and on some lower powered machines,
+                // not running it inside of a thread pool may cause it to block the executor for too long.
+                let certificate = tokio::task::spawn_blocking(move || {
+                    SignedWithCaCert::new(
+                        authority,
+                        private_key,
+                        ca_certificate,
+                        ca_private_key,
+                        &leaf_certificates,
+                        &generation_directory,
+                    )
+                })
+                .await
+                .unwrap();
+
+                self.insert(certificate.clone(), &issuer_generation_directory)
+                    .await;
+                certificate
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::{ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+
+/// Status codes `forbidden` block responses can be sent with.
+pub const BLOCK_PAGE_STATUS_CODES: [u16; 3] = [403, 204, 451];
+
+/// Kind of resource a request is fetching, as understood by filter `$`-options.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceType {
+    Document,
+    Subdocument,
+    Script,
+    Stylesheet,
+    Image,
+    Media,
+    Font,
+    Xhr,
+    Other,
+}
+
+impl ResourceType {
+    /// Name of the resource type as expected by the blocking engine.
+    pub fn as_adblock_str(&self) -> &'static str {
+        match self {
+            Self::Document => "document",
+            Self::Subdocument => "subdocument",
+            Self::Script => "script",
+            Self::Stylesheet => "stylesheet",
+            Self::Image => "image",
+            Self::Media => "media",
+            Self::Font => "font",
+            Self::Xhr => "xmlhttprequest",
+            Self::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// How a blocked request is answered
+pub enum BlockResponse {
+    /// A page explaining which filter matched, sent with the configured status code.
+    #[default]
+    Forbidden,
+    /// `200 OK` with an empty body.
+    Empty,
+    /// Drop the connection without answering.
+    CloseConnection,
+    /// `200 OK` with a harmless resource of the same kind, such as a
+    /// transparent pixel for images or a noop script for scripts.
+    Resource,
+    /// A page explaining the block with a button to visit the page anyway. Only
+    /// page navigations get one, other requests are answered as with `forbidden`.
+    Interstitial,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Answer given to blocked requests, per resource type
+pub struct BlockResponses {
+    #[serde(default)]
+    pub document: BlockResponse,
+    #[serde(default)]
+    pub subdocument: BlockResponse,
+    #[serde(default)]
+    pub script: BlockResponse,
+    #[serde(default)]
+    pub stylesheet: BlockResponse,
+    #[serde(default)]
+    pub image: BlockResponse,
+    #[serde(default)]
+    pub media: BlockResponse,
+    #[serde(default)]
+    pub font: BlockResponse,
+    #[serde(default)]
+    pub xhr: BlockResponse,
+    #[serde(default)]
+    pub other: BlockResponse,
+    /// Status code of `forbidden` responses, one of [`BLOCK_PAGE_STATUS_CODES`].
+    /// No page is sent with `204 No Content`.
+    #[serde(default = "default_status_code")]
+    pub status_code: u16,
+    /// Page answering blocked documents with a `forbidden` response, in place of
+    /// the default one. `{{url}}`, `{{rule}}` and `{{list}}` are replaced with the
+    /// blocked url, the rule that matched and the list it comes from.
+    #[serde(default)]
+    pub block_page_template: Option<String>,
+}
+
+fn default_status_code() -> u16 {
+    403
+}
+
+impl Default for BlockResponses {
+    fn default() -> Self {
+        Self {
+            document: BlockResponse::default(),
+            subdocument: BlockResponse::default(),
+            script: BlockResponse::default(),
+            stylesheet: BlockResponse::default(),
+            image: BlockResponse::default(),
+            media: BlockResponse::default(),
+            font: BlockResponse::default(),
+            xhr: BlockResponse::default(),
+            other: BlockResponse::default(),
+            status_code: default_status_code(),
+            block_page_template: None,
+        }
+    }
+}
+
+impl BlockResponses {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if !BLOCK_PAGE_STATUS_CODES.contains(&self.status_code) {
+            return Err(ConfigurationError::BlockResponsesError(format!(
+                "unsupported status code: {}",
+                self.status_code
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, resource_type: ResourceType) -> BlockResponse {
+        match resource_type {
+            ResourceType::Document => self.document,
+            ResourceType::Subdocument => self.subdocument,
+            ResourceType::Script => self.script,
+            ResourceType::Stylesheet => self.stylesheet,
+            ResourceType::Image => self.image,
+            ResourceType::Media => self.media,
+            ResourceType::Font => self.font,
+            ResourceType::Xhr => self.xhr,
+            ResourceType::Other => self.other,
+        }
+    }
+}
@@ -28,6 +28,8 @@ pub enum CaError {
     CaPrivateKeyError(String),
     #[error("private key does not match the certificate")]
     PrivateKeyMismatch,
+    #[error("certificate can't be used as a CA: {0}")]
+    NotCaCertificate(String),
 }
 
 impl Ca {
@@ -59,10 +61,21 @@ impl Ca {
                 .into())
             }
         };
-        if ca_pkey.public_eq(&ca_pub_key) {
-            Ok(())
-        } else {
-            Err(CaError::PrivateKeyMismatch.into())
+        if !ca_pkey.public_eq(&ca_pub_key) {
+            return Err(CaError::PrivateKeyMismatch.into());
+        }
+
+        crate::cert::verify_ca(&ca_cert, &ca_pkey)
+            .map_err(|err| CaError::NotCaCertificate(err).into())
+    }
+
+    /// A CA kept in the configuration file rather than in separate files.
+    pub fn from_pem(ca_certificate: String, ca_private_key: String) -> Self {
+        Self {
+            ca_certificate: Some(ca_certificate),
+            ca_private_key: Some(ca_private_key),
+            ca_certificate_path: None,
+            ca_private_key_path: None,
         }
     }
 
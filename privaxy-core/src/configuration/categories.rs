@@ -0,0 +1,194 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult, CronRule};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+
+/// Category lists change slowly, updating them more often is a waste.
+const MIN_CATEGORIES_UPDATE_INTERVAL_SECS: u64 = 5 * 60;
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Sites of a kind, such as adult or gambling ones, blocked from a list of their hosts.
+pub struct ContentCategory {
+    /// Identifies the category, such as `adult`.
+    pub name: String,
+    /// List of hosts, one per line or as hosts file entries. Subdomains of the listed
+    /// hosts are blocked as well.
+    #[serde_as(as = "DisplayFromStr")]
+    pub url: Url,
+    #[serde(default)]
+    pub enabled: bool,
+    /// IP addresses, networks or names of clients as set in the client identification
+    /// settings, or authenticated proxy users. The category applies to every client
+    /// when empty.
+    #[serde(default)]
+    pub clients: Vec<String>,
+    /// Five field cron expression matching the minutes during which the category is
+    /// blocked, in local time. For instance `* 21-23,0-6 * * *` for nights. The category
+    /// is blocked at all times without one.
+    #[serde(default)]
+    pub schedule: Option<String>,
+}
+
+impl ContentCategory {
+    /// Whether the category applies to a client, as identified by the client
+    /// identification settings, or to an authenticated proxy user.
+    pub fn applies_to(&self, client_identity: &str) -> bool {
+        self.clients.is_empty() || is_client_listed(&self.clients, client_identity)
+    }
+}
+
+/// Whether a client, as identified by the client identification settings, or an
+/// authenticated proxy user is one of `clients`, by name or by IP address or network.
+pub(crate) fn is_client_listed(clients: &[String], client_identity: &str) -> bool {
+    let ip_address = client_identity.parse::<IpAddr>().ok();
+
+    clients.iter().any(|client| {
+        if client == client_identity {
+            return true;
+        }
+
+        match (client.parse::<IpNet>(), ip_address) {
+            (Ok(network), Some(ip_address)) => network.contains(&ip_address),
+            _ => false,
+        }
+    })
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Blocking of whole categories of sites, for parental controls. Unlike filter lists,
+/// categories apply whether blocking is enabled or not.
+pub struct ContentCategories {
+    /// Seconds between downloads of the lists of enabled categories.
+    #[serde(default = "default_categories_update_interval_secs")]
+    pub update_interval_secs: u64,
+    #[serde(default = "default_categories")]
+    pub categories: Vec<ContentCategory>,
+}
+
+fn default_categories_update_interval_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_categories() -> Vec<ContentCategory> {
+    [
+        ("adult", "porn-only"),
+        ("gambling", "gambling-only"),
+        ("social", "social-only"),
+    ]
+    .into_iter()
+    .map(|(name, list)| ContentCategory {
+        name: name.to_string(),
+        url: Url::parse(&format!(
+            "https://raw.githubusercontent.com/StevenBlack/hosts/master/alternates/{}/hosts",
+            list
+        ))
+        .unwrap(),
+        enabled: false,
+        clients: Vec::new(),
+        schedule: None,
+    })
+    .collect()
+}
+
+impl Default for ContentCategories {
+    fn default() -> Self {
+        Self {
+            update_interval_secs: default_categories_update_interval_secs(),
+            categories: default_categories(),
+        }
+    }
+}
+
+impl ContentCategories {
+    pub fn update_interval(&self) -> Duration {
+        Duration::from_secs(self.update_interval_secs)
+    }
+
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.update_interval_secs < MIN_CATEGORIES_UPDATE_INTERVAL_SECS {
+            return Err(ConfigurationError::CategoryError(format!(
+                "lists can't be updated more often than every {} seconds",
+                MIN_CATEGORIES_UPDATE_INTERVAL_SECS
+            )));
+        }
+
+        let mut names = HashSet::new();
+
+        for category in &self.categories {
+            let is_valid_name = !category.name.is_empty()
+                && category
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+            if !is_valid_name {
+                return Err(ConfigurationError::CategoryError(format!(
+                    "invalid name: {:?}",
+                    category.name
+                )));
+            }
+
+            if !names.insert(category.name.as_str()) {
+                return Err(ConfigurationError::CategoryError(format!(
+                    "category {} is defined more than once",
+                    category.name
+                )));
+            }
+
+            if !matches!(category.url.scheme(), "http" | "https") {
+                return Err(ConfigurationError::CategoryError(format!(
+                    "invalid url for category {}: {}",
+                    category.name, category.url
+                )));
+            }
+
+            if category.clients.iter().any(String::is_empty) {
+                return Err(ConfigurationError::CategoryError(format!(
+                    "category {} applies to a client without a name",
+                    category.name
+                )));
+            }
+
+            if let Some(schedule) = &category.schedule {
+                CronRule::from_str(schedule)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Configuration {
+    pub async fn set_categories(
+        &mut self,
+        mut categories: ContentCategories,
+        category_store: crate::categories::CategoryStore,
+    ) -> ConfigurationResult<()> {
+        for category in &mut categories.categories {
+            category.name = category.name.trim().to_lowercase();
+            for client in &mut category.clients {
+                *client = client.trim().to_string();
+            }
+            category.schedule = category
+                .schedule
+                .take()
+                .map(|schedule| schedule.trim().to_string())
+                .filter(|schedule| !schedule.is_empty());
+        }
+        categories.validate()?;
+
+        self.categories = categories;
+
+        self.save().await?;
+
+        category_store.replace(self.categories.clone());
+
+        Ok(())
+    }
+}
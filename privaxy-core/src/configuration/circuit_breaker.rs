@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Stops waiting on upstream servers that don't answer, so that a dead third-party
+/// host doesn't slow down every page that references it.
+pub struct CircuitBreaker {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Seconds to wait for an upstream server to start answering.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Timeouts, in seconds, of specific domains and their subdomains.
+    #[serde(default)]
+    pub domain_timeouts: BTreeMap<String, u64>,
+    /// Consecutive failures after which requests to a domain are short-circuited.
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Seconds during which requests to a failing domain are short-circuited.
+    #[serde(default = "default_cooldown_secs")]
+    pub cooldown_secs: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_cooldown_secs() -> u64 {
+    60
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            timeout_secs: default_timeout_secs(),
+            domain_timeouts: BTreeMap::new(),
+            failure_threshold: default_failure_threshold(),
+            cooldown_secs: default_cooldown_secs(),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Returns the timeout of `host`, which is the one of its closest configured
+    /// parent domain when it has none of its own.
+    pub fn timeout(&self, host: &str) -> Duration {
+        let mut domain = host;
+
+        let timeout_secs = loop {
+            if let Some(timeout_secs) = self.domain_timeouts.get(domain) {
+                break *timeout_secs;
+            }
+
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => break self.timeout_secs,
+            }
+        };
+
+        Duration::from_secs(timeout_secs.max(1))
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown_secs)
+    }
+}
@@ -0,0 +1,113 @@
+use super::{ConfigurationError, ConfigurationResult};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::net::IpAddr;
+
+/// Devices rotate their IPv6 privacy addresses within their network's /64.
+const DEFAULT_IPV6_PREFIX_LENGTH: u8 = 64;
+
+/// Defines how client addresses are grouped into logical clients, so that statistics
+/// don't fragment across the addresses a single device uses.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ClientIdentification {
+    /// IPv4 addresses sharing this prefix are considered to be the same client.
+    #[serde(default = "default_ipv4_prefix_length")]
+    pub ipv4_prefix_length: u8,
+    /// IPv6 addresses sharing this prefix are considered to be the same client.
+    #[serde(default = "default_ipv6_prefix_length")]
+    pub ipv6_prefix_length: u8,
+    /// Clients explicitly identified by the networks they use. They take precedence
+    /// over prefix grouping, which allows aggregating IPv4 and IPv6 addresses.
+    #[serde(default)]
+    pub clients: Vec<NamedClient>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NamedClient {
+    pub name: String,
+    /// Networks in CIDR notation. A single address may be used as well.
+    #[serde_as(as = "Vec<DisplayFromStr>")]
+    pub networks: Vec<IpNet>,
+}
+
+fn default_ipv4_prefix_length() -> u8 {
+    32
+}
+
+fn default_ipv6_prefix_length() -> u8 {
+    DEFAULT_IPV6_PREFIX_LENGTH
+}
+
+impl Default for ClientIdentification {
+    fn default() -> Self {
+        Self {
+            ipv4_prefix_length: default_ipv4_prefix_length(),
+            ipv6_prefix_length: default_ipv6_prefix_length(),
+            clients: Vec::new(),
+        }
+    }
+}
+
+impl ClientIdentification {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.ipv4_prefix_length > 32 {
+            return Err(ConfigurationError::ClientIdentificationError(format!(
+                "invalid IPv4 prefix length: {}",
+                self.ipv4_prefix_length
+            )));
+        }
+
+        if self.ipv6_prefix_length > 128 {
+            return Err(ConfigurationError::ClientIdentificationError(format!(
+                "invalid IPv6 prefix length: {}",
+                self.ipv6_prefix_length
+            )));
+        }
+
+        if let Some(client) = self.clients.iter().find(|client| client.name.is_empty()) {
+            return Err(ConfigurationError::ClientIdentificationError(format!(
+                "a client using {:?} has no name",
+                client.networks
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the logical client an address belongs to: the name of a configured client,
+    /// the network the address belongs to, or the address itself when it is not grouped.
+    pub fn identify(&self, ip_address: IpAddr) -> String {
+        // IPv4 clients connecting through a dual stack socket show up as IPv4-mapped
+        // IPv6 addresses.
+        let ip_address = match ip_address {
+            IpAddr::V6(ipv6_address) => match ipv6_address.to_ipv4_mapped() {
+                Some(ipv4_address) => IpAddr::V4(ipv4_address),
+                None => ip_address,
+            },
+            IpAddr::V4(_) => ip_address,
+        };
+
+        if let Some(client) = self.clients.iter().find(|client| {
+            client
+                .networks
+                .iter()
+                .any(|network| network.contains(&ip_address))
+        }) {
+            return client.name.clone();
+        }
+
+        let prefix_length = match ip_address {
+            IpAddr::V4(_) => self.ipv4_prefix_length,
+            IpAddr::V6(_) => self.ipv6_prefix_length,
+        };
+
+        match IpNet::new(ip_address, prefix_length) {
+            Ok(network) if network.prefix_len() < network.max_prefix_len() => {
+                network.trunc().to_string()
+            }
+            _ => ip_address.to_string(),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Idle connections are dropped by the proxy's HTTP client after 90 seconds by default,
+/// warming them up more often keeps them open.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Keeps connections to the most requested origins open, so that requests to
+/// them don't wait for DNS resolution and TLS handshakes.
+pub struct ConnectionPrewarming {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Number of most requested origins to keep connections to.
+    #[serde(default = "default_origins_count")]
+    pub origins_count: usize,
+    /// Seconds between two warm ups.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_origins_count() -> usize {
+    10
+}
+
+fn default_interval_secs() -> u64 {
+    DEFAULT_INTERVAL.as_secs()
+}
+
+impl Default for ConnectionPrewarming {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            origins_count: default_origins_count(),
+            interval_secs: default_interval_secs(),
+        }
+    }
+}
+
+impl ConnectionPrewarming {
+    pub fn interval(&self) -> Duration {
+        // Don't hammer origins when misconfigured.
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+}
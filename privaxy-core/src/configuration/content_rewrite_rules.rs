@@ -0,0 +1,55 @@
+use super::{ConfigurationError, ConfigurationResult, ANY_HOST};
+use serde::{Deserialize, Serialize};
+
+/// Compiled size regular expressions of rewrite rules are limited to, so that a rule
+/// can't make the proxy allocate without bounds.
+pub const CONTENT_REWRITE_REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Replacement made in the text responses of a host and its subdomains, like
+/// AdGuard's `$replace` rules.
+pub struct ContentRewriteRule {
+    /// Host of the rewritten responses, or `*` for every host.
+    pub host: String,
+    /// Regular expression matched against response bodies.
+    pub pattern: String,
+    /// Replaces every match, `$1` or `${name}` standing for the groups of the pattern.
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default)]
+    pub case_insensitive: bool,
+}
+
+impl ContentRewriteRule {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        let is_valid_host = !self.host.is_empty()
+            && self
+                .host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+        if self.host != ANY_HOST && !is_valid_host {
+            return Err(ConfigurationError::ContentRewriteRuleError(format!(
+                "invalid host: {:?}",
+                self.host
+            )));
+        }
+
+        self.build_regex().map_err(|err| {
+            ConfigurationError::ContentRewriteRuleError(format!(
+                "invalid pattern {:?}: {}",
+                self.pattern, err
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    pub fn build_regex(&self) -> Result<regex::Regex, regex::Error> {
+        regex::RegexBuilder::new(&self.pattern)
+            .case_insensitive(self.case_insensitive)
+            .size_limit(CONTENT_REWRITE_REGEX_SIZE_LIMIT)
+            .dfa_size_limit(CONTENT_REWRITE_REGEX_SIZE_LIMIT)
+            .build()
+    }
+}
@@ -0,0 +1,55 @@
+use super::{ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// Whether a host may set and receive cookies
+pub enum CookieRuleAction {
+    /// Keep cookies, even when they would otherwise be blocked.
+    Allow,
+    /// Remove every cookie sent to, or set by, the host.
+    Deny,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Cookie action of a host, which also applies to its subdomains unless they have
+/// a rule of their own.
+pub struct CookieRule {
+    pub host: String,
+    pub action: CookieRuleAction,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// What happens to the `Cookie` and `Set-Cookie` headers of proxied requests
+pub struct CookieRules {
+    /// Remove cookies of requests made by pages of another site.
+    #[serde(default)]
+    pub block_third_party: bool,
+    /// Remove `Set-Cookie` from responses of known tracking hosts.
+    #[serde(default)]
+    pub strip_tracker_cookies: bool,
+    /// Per host rules, taking precedence over the above.
+    #[serde(default)]
+    pub rules: Vec<CookieRule>,
+}
+
+impl CookieRules {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        for rule in &self.rules {
+            let is_valid_host = !rule.host.is_empty()
+                && rule
+                    .host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+            if !is_valid_host {
+                return Err(ConfigurationError::CookieRuleError(format!(
+                    "invalid host: {:?}",
+                    rule.host
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Version of the export document, increased when its layout changes.
+pub const CONFIGURATION_EXPORT_VERSION: u32 = 1;
+
+/// The whole configuration as a single document, to back it up or move it to another
+/// machine.
+///
+/// The document contains the CA private key and the credentials of the proxy and the
+/// web GUI, it has to be kept as safe as the configuration file itself.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConfigurationExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub configuration: Configuration,
+}
+
+impl Configuration {
+    /// Exports the configuration file, without the managed configuration applied on top
+    /// of it.
+    pub async fn export() -> ConfigurationResult<ConfigurationExport> {
+        Ok(ConfigurationExport {
+            version: CONFIGURATION_EXPORT_VERSION,
+            exported_at: Utc::now(),
+            configuration: Self::read_local_from_home().await?,
+        })
+    }
+
+    /// Replaces the configuration file with an exported configuration, returning the
+    /// configuration with the managed configuration applied on top of it.
+    pub async fn import(export: ConfigurationExport) -> ConfigurationResult<Self> {
+        if export.version > CONFIGURATION_EXPORT_VERSION {
+            return Err(ConfigurationError::ImportError(format!(
+                "unsupported export version {}, this version of privaxy supports up to {}",
+                export.version, CONFIGURATION_EXPORT_VERSION
+            )));
+        }
+
+        let mut configuration = export.configuration;
+        configuration.managed_exclusions = BTreeSet::new();
+        for filter in configuration.filters.iter_mut() {
+            filter.managed = false;
+        }
+
+        configuration.validate().await?;
+        configuration.save().await?;
+
+        Self::read_from_home().await
+    }
+
+    /// Checks the settings that are otherwise validated when edited one at a time.
+    async fn validate(&self) -> ConfigurationResult<()> {
+        self.ca.validate().await?;
+        self.network.validate().await?;
+        self.block_responses.validate()?;
+        self.client_identification.validate()?;
+        self.cookie_rules.validate()?;
+        self.referer_trimming.validate()?;
+        self.telemetry.validate()?;
+        self.statistics_retention.validate()?;
+        self.threat_intelligence.validate()?;
+        self.categories.validate()?;
+
+        for filter in &self.filters {
+            filter.get_request_headers()?;
+        }
+        for site_policy in &self.site_policies {
+            site_policy.validate()?;
+        }
+        for header_rule in &self.header_rules {
+            header_rule.validate()?;
+        }
+        for user_agent_rule in &self.user_agent_rules {
+            user_agent_rule.validate()?;
+        }
+        for ip_rule in &self.ip_rules {
+            ip_rule.validate()?;
+        }
+        for content_rewrite_rule in &self.content_rewrite_rules {
+            content_rewrite_rule.validate()?;
+        }
+        for url_rewrite in &self.url_rewrites {
+            url_rewrite.validate()?;
+        }
+        self.plugins.validate()?;
+        self.scripts.validate()?;
+        self.response_cache.validate()?;
+        self.traffic_shaping.validate()?;
+        self.upstream_connections.validate()?;
+        for injection in &self.injections {
+            injection.validate()?;
+        }
+        self.validate_profiles()?;
+        self.validate_schedules()?;
+        self.validate_statistics_reset()?;
+        self.validate_notifications()?;
+
+        Ok(())
+    }
+}
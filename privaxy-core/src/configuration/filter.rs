@@ -1,14 +1,23 @@
+use crate::startup::get_startup_options;
+use chrono::{DateTime, Utc};
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::StatusCode;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::env;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::fs;
 use url::Url;
+use uuid::Uuid;
 
 use serde_with::{serde_as, DisplayFromStr};
 pub(crate) const FILTERS_DIRECTORY_NAME: &str = "filters";
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
 pub enum FilterGroup {
     Default,
     Regional,
@@ -44,8 +53,13 @@ pub struct DefaultFilter {
 }
 
 #[serde_as]
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
 pub struct Filter {
+    /// Stable identifier of the filter, kept when it is renamed or its URL changes.
+    /// Assigned when reading configurations written before filters had one.
+    #[serde(default)]
+    #[schemars(skip_serializing_if = "Uuid::is_nil")]
+    pub id: Uuid,
     /// If the filter is enabled
     pub enabled: bool,
     /// Title of the filter
@@ -55,8 +69,160 @@ pub struct Filter {
     /// Local file name of the filter
     pub file_name: String,
     #[serde_as(as = "DisplayFromStr")]
-    /// Remote URL of the filter
+    /// Remote URL of the filter, or `file://` URL of a list maintained locally
     pub url: Url,
+    /// Seconds between automatic updates of this filter, overrides
+    /// the global `filters_update_interval_secs` when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_interval_secs: Option<u64>,
+    /// Headers sent when downloading the filter, such as `Authorization` for private lists.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Credentials sent when downloading the filter.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub basic_auth: Option<FilterBasicAuth>,
+    /// Whether the filter is part of the managed configuration, in which case it
+    /// can't be disabled nor removed. Derived from the managed configuration when
+    /// the configuration is read.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub managed: bool,
+}
+
+/// HTTP basic authentication credentials of a private filter list.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct FilterBasicAuth {
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// HTTP validators of the last downloaded version of a filter, so that it is only
+/// downloaded again when it changed.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+struct FilterMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+}
+
+impl FilterMetadata {
+    fn get_path(filters_directory: &Path, file_name: &str) -> PathBuf {
+        filters_directory.join(format!("{}.meta.json", file_name))
+    }
+
+    async fn read(filters_directory: &Path, file_name: &str) -> Self {
+        match fs::read(Self::get_path(filters_directory, file_name)).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    async fn write(
+        &self,
+        filters_directory: &Path,
+        file_name: &str,
+    ) -> super::ConfigurationResult<()> {
+        let path = Self::get_path(filters_directory, file_name);
+
+        if self == &Self::default() {
+            // The server doesn't support conditional requests, don't keep stale validators.
+            match fs::remove_file(path).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+                _ => return Ok(()),
+            }
+        }
+
+        fs::write(path, serde_json::to_vec(self).unwrap()).await?;
+
+        Ok(())
+    }
+}
+
+/// Outcome of the last updates of a filter, kept to report the health of the list.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub(super) struct FilterUpdateRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) last_checked_at: Option<DateTime<Utc>>,
+    /// Last time the list was successfully checked for updates, whether it changed or not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) last_success_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) last_modified_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) last_http_status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) last_error: Option<String>,
+}
+
+impl FilterUpdateRecord {
+    fn get_path(filters_directory: &Path, file_name: &str) -> PathBuf {
+        filters_directory.join(format!("{}.status.json", file_name))
+    }
+
+    pub(super) async fn read(file_name: &str) -> Self {
+        match fs::read(Self::get_path(&get_filter_directory(), file_name)).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => Self::default(),
+        }
+    }
+
+    async fn record(
+        filters_directory: &Path,
+        file_name: &str,
+        update: &super::ConfigurationResult<FilterUpdate>,
+        http_status: Option<StatusCode>,
+    ) {
+        let path = Self::get_path(filters_directory, file_name);
+        let mut record = match fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => Self::default(),
+        };
+
+        let now = Utc::now();
+        record.last_checked_at = Some(now);
+        record.last_http_status = http_status.map(|status| status.as_u16());
+
+        match update {
+            Ok(update) => {
+                record.last_success_at = Some(now);
+                record.last_error = None;
+                if matches!(update, FilterUpdate::Modified(_)) {
+                    record.last_modified_at = Some(now);
+                }
+            }
+            Err(err) => record.last_error = Some(err.to_string()),
+        }
+
+        if let Err(err) = fs::write(&path, serde_json::to_vec(&record).unwrap()).await {
+            log::error!(
+                "Unable to record the update of filter {}: {:?}",
+                file_name,
+                err
+            );
+        }
+    }
+}
+
+/// Outcome of a filter update.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterUpdate {
+    /// A new version of the filter was downloaded.
+    Modified(String),
+    /// The remote filter didn't change since it was last downloaded.
+    NotModified,
+}
+
+/// Name under which custom filters are reported as the source of a rule. Custom filters
+/// are not part of [`get_filters_content`], they are compiled separately.
+pub const CUSTOM_FILTERS_SOURCE: &str = "Custom filters";
+
+/// Rules of a filter list, along with the list they come from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FilterContent {
+    /// Title of the filter list, or [`CUSTOM_FILTERS_SOURCE`].
+    pub source: String,
+    pub content: String,
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
@@ -247,21 +413,127 @@ pub(crate) fn calc_filter_filename(filename: &str) -> String {
 }
 
 impl Filter {
+    /// Headers sent when downloading the filter, failing when one of them isn't a valid
+    /// HTTP header.
+    pub fn get_request_headers(&self) -> super::ConfigurationResult<HeaderMap> {
+        let mut headers = HeaderMap::new();
+
+        for (name, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                super::ConfigurationError::FilterError(format!("Invalid header name: {:?}", name))
+            })?;
+            let header_value = HeaderValue::from_str(value).map_err(|_| {
+                super::ConfigurationError::FilterError(format!("Invalid value of header {}", name))
+            })?;
+
+            headers.insert(header_name, header_value);
+        }
+
+        Ok(headers)
+    }
+
+    /// Path of the list when it is a local file, read from disk rather than downloaded.
+    pub fn get_local_path(&self) -> Option<PathBuf> {
+        if self.url.scheme() == "file" {
+            self.url.to_file_path().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Time to wait between automatic updates of this filter.
+    pub fn update_interval(&self, default_interval: Duration) -> Duration {
+        self.update_interval_secs
+            .map(Duration::from_secs)
+            .unwrap_or(default_interval)
+    }
+
     pub(super) async fn update(
         &mut self,
         http_client: &reqwest::Client,
-    ) -> super::ConfigurationResult<String> {
+    ) -> super::ConfigurationResult<FilterUpdate> {
         log::debug!("Updating filter: {}", self.title);
 
         let filters_directory = get_filter_directory();
         fs::create_dir_all(&filters_directory).await?;
 
-        let filter = get_filter(self, http_client).await?;
+        let mut http_status = None;
+        let update = self
+            .fetch_update(&filters_directory, http_client, &mut http_status)
+            .await;
+
+        FilterUpdateRecord::record(&filters_directory, &self.file_name, &update, http_status).await;
+
+        update
+    }
 
+    async fn fetch_update(
+        &mut self,
+        filters_directory: &Path,
+        http_client: &reqwest::Client,
+        http_status: &mut Option<StatusCode>,
+    ) -> super::ConfigurationResult<FilterUpdate> {
         let filter_path = filters_directory.join(&self.file_name);
+
+        if let Some(local_path) = self.get_local_path() {
+            let filter = fs::read_to_string(&local_path).await?;
+
+            // There are no validators for local files, the copy in use tells whether it changed.
+            if fs::read_to_string(&filter_path).await.ok().as_deref() == Some(filter.as_str()) {
+                log::debug!("Filter not modified: {}", self.title);
+                return Ok(FilterUpdate::NotModified);
+            }
+
+            fs::write(&filter_path, &filter).await?;
+            return Ok(FilterUpdate::Modified(filter));
+        }
+
+        // Validators are only relevant as long as we still have the filter they describe.
+        let metadata = if fs::metadata(&filter_path).await.is_ok() {
+            FilterMetadata::read(filters_directory, &self.file_name).await
+        } else {
+            FilterMetadata::default()
+        };
+
+        let downloaded = get_filter(self, http_client, &metadata, http_status).await?;
+        let (filter, metadata) = match downloaded {
+            Some(downloaded) => downloaded,
+            None => {
+                log::debug!("Filter not modified: {}", self.title);
+                return Ok(FilterUpdate::NotModified);
+            }
+        };
+
         fs::write(&filter_path, &filter).await?;
+        metadata.write(filters_directory, &self.file_name).await?;
+
+        Ok(FilterUpdate::Modified(filter))
+    }
+
+    /// Current version of the list, fetched without being stored or recorded as an update.
+    pub(super) async fn fetch_contents(
+        &self,
+        http_client: &reqwest::Client,
+    ) -> super::ConfigurationResult<String> {
+        if let Some(local_path) = self.get_local_path() {
+            return Ok(fs::read_to_string(&local_path).await?);
+        }
+
+        match get_filter(self, http_client, &FilterMetadata::default(), &mut None).await? {
+            Some((filter, _)) => Ok(filter),
+            // Without validators, the server has no reason to answer `304 Not Modified`.
+            None => Err(super::ConfigurationError::FilterError(format!(
+                "Filter {} is missing",
+                self.title
+            ))),
+        }
+    }
 
-        Ok(filter)
+    /// Last downloaded version of the list, if any.
+    pub(super) async fn read_cached_contents(&self) -> Option<String> {
+        fs::read_to_string(get_filter_directory().join(&self.file_name))
+            .await
+            .ok()
     }
 
     pub async fn get_contents(
@@ -272,7 +544,12 @@ impl Filter {
         match fs::read(&filter_path).await {
             Err(err) => {
                 if err.kind() == std::io::ErrorKind::NotFound {
-                    self.update(http_client).await
+                    match self.update(http_client).await? {
+                        FilterUpdate::Modified(filter) => Ok(filter),
+                        FilterUpdate::NotModified => Err(super::ConfigurationError::FilterError(
+                            format!("Filter {} is missing", self.title),
+                        )),
+                    }
                 } else {
                     Err(super::ConfigurationError::FileSystemError(err))
                 }
@@ -285,6 +562,7 @@ impl Filter {
 impl From<DefaultFilter> for Filter {
     fn from(default_filter: DefaultFilter) -> Self {
         Self {
+            id: Uuid::new_v4(),
             enabled: default_filter.enabled_by_default,
             title: default_filter.title,
             group: match default_filter.group.as_str() {
@@ -298,18 +576,54 @@ impl From<DefaultFilter> for Filter {
             },
             file_name: default_filter.file_name,
             url: default_filter.url,
+            update_interval_secs: None,
+            headers: BTreeMap::new(),
+            basic_auth: None,
+            managed: false,
         }
     }
 }
 
-pub(crate) async fn get_filter(
-    filter: &mut Filter,
+/// Downloads a filter, unless it didn't change since the version described by `metadata`.
+async fn get_filter(
+    filter: &Filter,
     http_client: &reqwest::Client,
-) -> super::ConfigurationResult<String> {
-    let response = http_client.get(filter.url.as_str()).send().await?;
-    if response.status().is_success() {
+    metadata: &FilterMetadata,
+    http_status: &mut Option<StatusCode>,
+) -> super::ConfigurationResult<Option<(String, FilterMetadata)>> {
+    let mut request = http_client
+        .get(filter.url.as_str())
+        .headers(filter.get_request_headers()?);
+    if let Some(basic_auth) = &filter.basic_auth {
+        request = request.basic_auth(&basic_auth.username, basic_auth.password.as_ref());
+    }
+    if let Some(etag) = &metadata.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &metadata.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().await?;
+    *http_status = Some(response.status());
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        Ok(None)
+    } else if response.status().is_success() {
+        let header = |name: HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let metadata = FilterMetadata {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+
         let content = response.text().await?;
-        Ok(content)
+        Ok(Some((content, metadata)))
     } else {
         log::error!("Failed to fetch filter content: {}", response.status());
         Err(super::ConfigurationError::FilterError(format!(
@@ -320,10 +634,10 @@ pub(crate) async fn get_filter(
 }
 
 fn get_filter_directory() -> PathBuf {
-    let filter_dir: PathBuf = match env::var("PRIVAXY_FILTER_PATH") {
-        Ok(val) => PathBuf::from(&val),
+    let filter_dir: PathBuf = match &get_startup_options().filter_path {
+        Some(filter_path) => filter_path.clone(),
         // Assume home directory
-        Err(_) => PathBuf::from(FILTERS_DIRECTORY_NAME),
+        None => PathBuf::from(FILTERS_DIRECTORY_NAME),
     };
     return super::get_base_directory().unwrap().join(filter_dir);
 }
@@ -331,12 +645,18 @@ fn get_filter_directory() -> PathBuf {
 pub(crate) async fn get_filters_content(
     configuration: &mut super::Configuration,
     http_client: &reqwest::Client,
-) -> Vec<String> {
+) -> Vec<FilterContent> {
     let mut filters = Vec::new();
     let mut futures = vec![];
 
     for filter in configuration.get_enabled_filters() {
-        let future = filter.get_contents(http_client);
+        let source = filter.title.clone();
+        let future = async move {
+            filter
+                .get_contents(http_client)
+                .await
+                .map(|content| FilterContent { source, content })
+        };
         futures.push(future);
     }
 
@@ -350,9 +670,58 @@ pub(crate) async fn get_filters_content(
         }
     }
 
-    filters.append(&mut configuration.custom_filters);
+    if let Some(local_filters_directory) = &configuration.local_filters_directory {
+        filters.extend(get_local_directory_filters_content(local_filters_directory).await);
+    }
+
     filters.sort_unstable();
-    // Filter out duplicate lines, if present
+    // Filter out duplicate lists, if present
     filters.dedup();
     filters
 }
+
+/// Tells whether a file of the local filters directory is a filter list.
+pub(crate) fn is_local_directory_filter(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "txt")
+}
+
+/// Reads the lists of the local filters directory, in which every `.txt` file is a
+/// filter list named after its file.
+async fn get_local_directory_filters_content(directory: &Path) -> Vec<FilterContent> {
+    let mut entries = match fs::read_dir(directory).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            log::error!(
+                "Unable to read local filters directory {}: {:?}",
+                directory.display(),
+                err
+            );
+            return Vec::new();
+        }
+    };
+
+    let mut filters = Vec::new();
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if !is_local_directory_filter(&path) {
+            continue;
+        }
+
+        match fs::read_to_string(&path).await {
+            Ok(content) => filters.push(FilterContent {
+                source: entry.file_name().to_string_lossy().to_string(),
+                content,
+            }),
+            Err(err) => {
+                log::error!(
+                    "Unable to read local filter {}: {:?}, skipping.",
+                    path.display(),
+                    err
+                )
+            }
+        }
+    }
+
+    filters
+}
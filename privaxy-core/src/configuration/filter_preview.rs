@@ -0,0 +1,186 @@
+use super::filter_status::{is_comment_line, parse_list};
+use super::{ConfigurationError, ConfigurationResult, Filter};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+/// Number of rules of the list returned as a sample of its content.
+const MAX_SAMPLE_RULES: usize = 20;
+
+/// Rules only uBlock Origin understands.
+const UBLOCK_ORIGIN_MARKERS: [&str; 6] = [
+    "##+js(",
+    "#@#+js(",
+    "##^",
+    "$redirect=",
+    "$redirect-rule=",
+    ":remove()",
+];
+
+/// Rules only AdGuard understands.
+const ADGUARD_MARKERS: [&str; 6] = ["#%#", "#@%#", "#$#", "#@$#", "$$", "$removeheader="];
+
+/// Syntax a filter list is written in.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterDialect {
+    /// Hosts file, `0.0.0.0 example.com`.
+    Hosts,
+    /// One domain per line.
+    Domains,
+    AdblockPlus,
+    UblockOrigin,
+    Adguard,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct FilterRuleCounts {
+    pub network: usize,
+    pub network_exception: usize,
+    pub cosmetic: usize,
+    pub cosmetic_exception: usize,
+    /// Scriptlets and scripts injected in pages.
+    pub scriptlet: usize,
+    /// HTML filters, removing elements before pages reach the browser.
+    pub html: usize,
+}
+
+/// What a list contains, to see what subscribing to it would change.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct FilterPreview {
+    /// Title of the list, from its header.
+    pub title: Option<String>,
+    pub homepage: Option<String>,
+    pub expires: Option<String>,
+    pub dialect: FilterDialect,
+    /// Number of rules of the list, comments excluded.
+    pub rule_count: usize,
+    pub rule_counts: FilterRuleCounts,
+    /// Number of rules the blocking engine can't parse.
+    pub parse_error_count: usize,
+    /// First rules the blocking engine can't parse.
+    pub parse_errors: Vec<String>,
+    /// First rules of the list.
+    pub sample_rules: Vec<String>,
+}
+
+impl Filter {
+    /// Downloads the list and describes its content, without subscribing to it.
+    pub async fn get_preview(
+        &self,
+        http_client: &reqwest::Client,
+    ) -> ConfigurationResult<FilterPreview> {
+        let content = self.fetch_contents(http_client).await?;
+
+        // Lists can have hundreds of thousands of rules.
+        tokio::task::spawn_blocking(move || preview_list(&content))
+            .await
+            .map_err(|err| {
+                ConfigurationError::FilterError(format!("unable to preview filter: {err}"))
+            })
+    }
+}
+
+fn is_hosts_rule(line: &str) -> bool {
+    let mut parts = line.split_whitespace();
+
+    matches!(
+        parts.next(),
+        Some("0.0.0.0" | "127.0.0.1" | "::" | "::1" | "0")
+    ) && parts.next().is_some()
+}
+
+fn is_domain_rule(line: &str) -> bool {
+    line.contains('.')
+        && line
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_')
+}
+
+fn count_rule(rule_counts: &mut FilterRuleCounts, line: &str) {
+    let counter = if line.contains("+js(") || line.contains("#%#") || line.contains("#@%#") {
+        &mut rule_counts.scriptlet
+    } else if line.contains("##^") || line.contains("#@#^") {
+        &mut rule_counts.html
+    } else if ["#@#", "#@?#", "#@$#"].iter().any(|s| line.contains(s)) {
+        &mut rule_counts.cosmetic_exception
+    } else if ["##", "#?#", "#$#"].iter().any(|s| line.contains(s)) {
+        &mut rule_counts.cosmetic
+    } else if line.starts_with("@@") {
+        &mut rule_counts.network_exception
+    } else {
+        &mut rule_counts.network
+    };
+
+    *counter += 1;
+}
+
+fn preview_list(content: &str) -> FilterPreview {
+    let parsed_list = parse_list(content);
+
+    let mut rule_counts = FilterRuleCounts::default();
+    let mut sample_rules = Vec::new();
+    let mut hosts_rule_count = 0;
+    let mut domain_rule_count = 0;
+    let mut ublock_origin_rule_count = 0;
+    let mut adguard_rule_count = 0;
+
+    for line in content.lines().map(str::trim) {
+        if is_comment_line(line) {
+            continue;
+        }
+
+        count_rule(&mut rule_counts, line);
+
+        if is_hosts_rule(line) {
+            hosts_rule_count += 1;
+        } else if is_domain_rule(line) {
+            domain_rule_count += 1;
+        }
+        if UBLOCK_ORIGIN_MARKERS.iter().any(|m| line.contains(m)) {
+            ublock_origin_rule_count += 1;
+        }
+        if ADGUARD_MARKERS.iter().any(|m| line.contains(m)) {
+            adguard_rule_count += 1;
+        }
+
+        if sample_rules.len() < MAX_SAMPLE_RULES {
+            sample_rules.push(line.to_string());
+        }
+    }
+
+    let rule_count = parsed_list.rule_count;
+    let is_adblock_list = content
+        .lines()
+        .next()
+        .is_some_and(|line| line.trim().starts_with("[Adblock"))
+        || rule_count > hosts_rule_count + domain_rule_count;
+
+    let dialect = if rule_count == 0 {
+        FilterDialect::Unknown
+    } else if hosts_rule_count * 2 > rule_count {
+        FilterDialect::Hosts
+    } else if domain_rule_count * 2 > rule_count {
+        FilterDialect::Domains
+    } else if adguard_rule_count > 0 && adguard_rule_count >= ublock_origin_rule_count {
+        FilterDialect::Adguard
+    } else if ublock_origin_rule_count > 0 {
+        FilterDialect::UblockOrigin
+    } else if is_adblock_list {
+        FilterDialect::AdblockPlus
+    } else {
+        FilterDialect::Unknown
+    };
+
+    FilterPreview {
+        title: parsed_list.title,
+        homepage: parsed_list.homepage,
+        expires: parsed_list.expires,
+        dialect,
+        rule_count,
+        rule_counts,
+        parse_error_count: parsed_list.parse_error_count,
+        parse_errors: parsed_list.parse_errors,
+        sample_rules,
+    }
+}
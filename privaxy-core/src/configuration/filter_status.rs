@@ -0,0 +1,132 @@
+use super::{Filter, FilterUpdateRecord};
+use adblock::lists::{FilterParseError, ParseOptions};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Lists that couldn't be checked for updates for this long are reported as stale.
+const FILTER_STALE_AFTER: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Number of rules that failed to parse reported along with their count.
+const MAX_REPORTED_PARSE_ERRORS: usize = 10;
+
+/// Health of a filter list, so that broken subscriptions can be spotted.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, JsonSchema)]
+pub struct FilterStatus {
+    pub id: Uuid,
+    /// Number of rules of the list, comments excluded.
+    pub rule_count: usize,
+    /// Number of rules the blocking engine can't parse.
+    pub parse_error_count: usize,
+    /// First rules the blocking engine can't parse.
+    pub parse_errors: Vec<String>,
+    /// Homepage of the list, from its header.
+    pub homepage: Option<String>,
+    /// Update frequency of the list, as announced by its header.
+    pub expires: Option<String>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_modified_at: Option<DateTime<Utc>>,
+    pub last_http_status: Option<u16>,
+    pub last_error: Option<String>,
+    /// Whether the list is gone or couldn't be checked for updates for a while.
+    pub stale: bool,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct ParsedList {
+    pub(super) rule_count: usize,
+    pub(super) parse_error_count: usize,
+    pub(super) parse_errors: Vec<String>,
+    pub(super) title: Option<String>,
+    pub(super) homepage: Option<String>,
+    pub(super) expires: Option<String>,
+}
+
+impl Filter {
+    pub async fn get_status(&self) -> FilterStatus {
+        let record = FilterUpdateRecord::read(&self.file_name).await;
+
+        let parsed_list = match self.read_cached_contents().await {
+            // Lists can have hundreds of thousands of rules.
+            Some(content) => tokio::task::spawn_blocking(move || parse_list(&content))
+                .await
+                .unwrap_or_default(),
+            None => ParsedList::default(),
+        };
+
+        let is_gone = matches!(record.last_http_status, Some(404 | 410));
+        let is_outdated = match record.last_success_at {
+            Some(last_success_at) => Utc::now()
+                .signed_duration_since(last_success_at)
+                .to_std()
+                .is_ok_and(|elapsed| elapsed > FILTER_STALE_AFTER),
+            None => record.last_checked_at.is_some(),
+        };
+
+        FilterStatus {
+            id: self.id,
+            rule_count: parsed_list.rule_count,
+            parse_error_count: parsed_list.parse_error_count,
+            parse_errors: parsed_list.parse_errors,
+            homepage: parsed_list.homepage,
+            expires: parsed_list.expires,
+            last_checked_at: record.last_checked_at,
+            last_success_at: record.last_success_at,
+            last_modified_at: record.last_modified_at,
+            last_http_status: record.last_http_status,
+            last_error: record.last_error,
+            stale: is_gone || is_outdated,
+        }
+    }
+}
+
+/// Whether the line of a list is blank, a comment or a header rather than a rule.
+pub(super) fn is_comment_line(line: &str) -> bool {
+    line.is_empty()
+        || line.starts_with('[')
+        || line.starts_with('!')
+        || line == "#"
+        || line.starts_with("# ")
+}
+
+pub(super) fn parse_list(content: &str) -> ParsedList {
+    let mut parsed_list = ParsedList::default();
+
+    for line in content.lines().map(str::trim) {
+        if let Some(comment) = line.strip_prefix('!') {
+            if let Some((key, value)) = comment.split_once(':') {
+                let value = Some(value.trim().to_string());
+
+                match key.trim().to_lowercase().as_str() {
+                    "title" => parsed_list.title = value,
+                    "homepage" => parsed_list.homepage = value,
+                    "expires" => parsed_list.expires = value,
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        if is_comment_line(line) {
+            continue;
+        }
+
+        parsed_list.rule_count += 1;
+
+        // Rules the engine recognizes but doesn't apply aren't errors of the list.
+        match adblock::lists::parse_filter(line, false, ParseOptions::default()) {
+            Ok(_) | Err(FilterParseError::Unsupported) | Err(FilterParseError::Empty) => {}
+            Err(_) => {
+                parsed_list.parse_error_count += 1;
+                if parsed_list.parse_errors.len() < MAX_REPORTED_PARSE_ERRORS {
+                    parsed_list.parse_errors.push(line.to_string());
+                }
+            }
+        }
+    }
+
+    parsed_list
+}
@@ -0,0 +1,154 @@
+use super::{ConfigurationError, ConfigurationResult};
+use http::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Matches every host.
+pub const ANY_HOST: &str = "*";
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// Headers a header rule applies to
+pub enum HeaderRuleDirection {
+    /// Headers sent to upstream servers.
+    Request,
+    /// Headers sent back to clients.
+    Response,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// What a header rule does to the header it names
+pub enum HeaderRuleAction {
+    /// Appends a value, keeping the existing ones.
+    Add,
+    /// Removes every value.
+    Remove,
+    /// Replaces the values of a header that is present, without adding it otherwise.
+    Replace,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Change made to the headers of requests to, or responses from, matching hosts.
+///
+/// Rules apply in order, a rule of a host also applies to its subdomains.
+pub struct HeaderRule {
+    /// Host of the requested urls, or `*` for every host.
+    pub host: String,
+    pub direction: HeaderRuleDirection,
+    pub action: HeaderRuleAction,
+    pub name: String,
+    /// Required when adding or replacing a header.
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+impl HeaderRule {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        let is_valid_host = !self.host.is_empty()
+            && self
+                .host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+        if self.host != ANY_HOST && !is_valid_host {
+            return Err(ConfigurationError::HeaderRuleError(format!(
+                "invalid host: {:?}",
+                self.host
+            )));
+        }
+
+        if HeaderName::from_bytes(self.name.as_bytes()).is_err() {
+            return Err(ConfigurationError::HeaderRuleError(format!(
+                "invalid header name: {:?}",
+                self.name
+            )));
+        }
+
+        match (&self.action, &self.value) {
+            (HeaderRuleAction::Remove, _) => {}
+            (_, None) => {
+                return Err(ConfigurationError::HeaderRuleError(format!(
+                    "a value is required to add or replace {}",
+                    self.name
+                )))
+            }
+            (_, Some(value)) => {
+                if HeaderValue::from_str(value).is_err() {
+                    return Err(ConfigurationError::HeaderRuleError(format!(
+                        "invalid value for {}: {:?}",
+                        self.name, value
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// How much of the `Referer` header upstream servers get
+pub struct RefererTrimming {
+    /// Only send the origin of the referring page to other sites.
+    #[serde(default)]
+    pub cross_site: bool,
+    /// Never send a referer to these hosts and their subdomains.
+    #[serde(default)]
+    pub strip_domains: BTreeSet<String>,
+}
+
+impl RefererTrimming {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        for domain in &self.strip_domains {
+            let is_valid_domain = !domain.is_empty()
+                && domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+            if !is_valid_domain {
+                return Err(ConfigurationError::HeaderRuleError(format!(
+                    "invalid domain: {:?}",
+                    domain
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Generic `User-Agent` sent to a host and its subdomains in place of the client's
+/// own, with client hints removed so that they don't give it away.
+pub struct UserAgentRule {
+    /// Host of the requested urls, or `*` for every host.
+    pub host: String,
+    pub user_agent: String,
+}
+
+impl UserAgentRule {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        let is_valid_host = !self.host.is_empty()
+            && self
+                .host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+        if self.host != ANY_HOST && !is_valid_host {
+            return Err(ConfigurationError::HeaderRuleError(format!(
+                "invalid host: {:?}",
+                self.host
+            )));
+        }
+
+        if self.user_agent.trim().is_empty() || HeaderValue::from_str(&self.user_agent).is_err() {
+            return Err(ConfigurationError::HeaderRuleError(format!(
+                "invalid user agent: {:?}",
+                self.user_agent
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+use super::{calc_filter_filename, Configuration, ConfigurationError, ConfigurationResult};
+use super::{Filter, FilterGroup};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+use uuid::Uuid;
+
+/// AdGuard Home modifiers that only make sense for a DNS server.
+const ADGUARD_HOME_DNS_MODIFIERS: [&str; 5] =
+    ["dnsrewrite", "dnstype", "client", "ctag", "denyallow"];
+
+/// Configuration exported by another blocker.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// `adlists.list`, or `adlist.json` and `domainlist.json` from a Teleporter export.
+    Pihole,
+    /// `AdGuardHome.yaml`.
+    Adguardhome,
+}
+
+/// An entry of the imported configuration with no equivalent in privaxy.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SkippedImportEntry {
+    pub entry: String,
+    pub reason: String,
+}
+
+/// Changes an import makes to the configuration.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct ImportDiff {
+    pub filters: Vec<Filter>,
+    pub custom_filters: Vec<String>,
+    pub exclusions: Vec<String>,
+    pub skipped: Vec<SkippedImportEntry>,
+}
+
+/// Entry of the `adlist.json` file of a Pi-hole Teleporter export.
+#[derive(Debug, Deserialize)]
+struct PiholeAdlist {
+    address: String,
+    #[serde(
+        default = "default_enabled",
+        deserialize_with = "deserialize_pihole_bool"
+    )]
+    enabled: bool,
+    #[serde(default)]
+    comment: Option<String>,
+}
+
+/// Entry of the `domainlist.json` file of a Pi-hole Teleporter export.
+#[derive(Debug, Deserialize)]
+struct PiholeDomain {
+    /// 0: exact allow, 1: exact deny, 2: regex allow, 3: regex deny.
+    #[serde(rename = "type")]
+    kind: u8,
+    domain: String,
+    #[serde(
+        default = "default_enabled",
+        deserialize_with = "deserialize_pihole_bool"
+    )]
+    enabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PiholeExport {
+    Domains(Vec<PiholeDomain>),
+    Adlists(Vec<PiholeAdlist>),
+}
+
+#[derive(Debug, Deserialize)]
+struct AdguardHomeFilter {
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    url: String,
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdguardHomeConfiguration {
+    #[serde(default)]
+    filters: Vec<AdguardHomeFilter>,
+    #[serde(default)]
+    whitelist_filters: Vec<AdguardHomeFilter>,
+    #[serde(default)]
+    user_rules: Vec<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Pi-hole stores booleans as integers.
+fn deserialize_pihole_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PiholeBool {
+        Bool(bool),
+        Integer(u8),
+    }
+
+    Ok(match PiholeBool::deserialize(deserializer)? {
+        PiholeBool::Bool(value) => value,
+        PiholeBool::Integer(value) => value != 0,
+    })
+}
+
+impl ImportDiff {
+    fn skip(&mut self, entry: &str, reason: &str) {
+        self.skipped.push(SkippedImportEntry {
+            entry: entry.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    fn add_filter(&mut self, configuration: &Configuration, title: &str, url: &str, enabled: bool) {
+        let url = match Url::parse(url.trim()) {
+            Ok(url) if ["http", "https"].contains(&url.scheme()) => url,
+            _ => return self.skip(url, "not an HTTP URL"),
+        };
+
+        if configuration
+            .filters
+            .iter()
+            .chain(self.filters.iter())
+            .any(|filter| filter.url == url)
+        {
+            return self.skip(url.as_str(), "already subscribed");
+        }
+
+        let title = match title.trim() {
+            "" => url.host_str().unwrap_or(url.as_str()).to_string(),
+            title => title.to_string(),
+        };
+
+        self.filters.push(Filter {
+            id: Uuid::new_v4(),
+            enabled,
+            title,
+            // DNS blockers lists are overwhelmingly about ads and trackers.
+            group: FilterGroup::Ads,
+            file_name: calc_filter_filename(url.as_str()),
+            url,
+            update_interval_secs: None,
+            headers: BTreeMap::new(),
+            basic_auth: None,
+            managed: false,
+        });
+    }
+
+    fn add_custom_filter(&mut self, configuration: &Configuration, rule: String) {
+        if configuration.custom_filters.contains(&rule) || self.custom_filters.contains(&rule) {
+            return self.skip(&rule, "already a custom filter");
+        }
+
+        self.custom_filters.push(rule);
+    }
+
+    fn add_exclusion(&mut self, configuration: &Configuration, exclusion: String) {
+        if configuration.exclusions.contains(&exclusion) || self.exclusions.contains(&exclusion) {
+            return self.skip(&exclusion, "already excluded");
+        }
+
+        self.exclusions.push(exclusion);
+    }
+
+    fn add_pihole_domain(&mut self, configuration: &Configuration, domain: PiholeDomain) {
+        if !domain.enabled {
+            return self.skip(&domain.domain, "disabled");
+        }
+
+        match domain.kind {
+            0 => self.add_exclusion(configuration, domain.domain.to_lowercase()),
+            1 => self.add_custom_filter(
+                configuration,
+                format!("||{}^", domain.domain.to_lowercase()),
+            ),
+            // Pi-hole matches these against domains only, filters against whole URLs.
+            2 | 3 => self.skip(&domain.domain, "regular expressions can't be converted"),
+            _ => self.skip(&domain.domain, "unknown domain type"),
+        }
+    }
+
+    /// Converts a user rule of AdGuard Home, a mix of adblock rules and hosts entries.
+    fn add_adguard_home_rule(&mut self, configuration: &Configuration, rule: &str) {
+        let rule = rule.trim();
+
+        if rule.is_empty() || rule.starts_with('!') || rule.starts_with('#') {
+            return;
+        }
+
+        let mut parts = rule.split_whitespace();
+        if let (Some(address), Some(host)) = (parts.next(), parts.next()) {
+            if address.parse::<std::net::IpAddr>().is_ok() {
+                return self
+                    .add_custom_filter(configuration, format!("||{}^", host.to_lowercase()));
+            }
+        }
+
+        let modifiers = rule.rsplit_once('$').map_or("", |(_, modifiers)| modifiers);
+        if modifiers.split(',').any(|modifier| {
+            let name = modifier.split('=').next().unwrap_or_default();
+            ADGUARD_HOME_DNS_MODIFIERS.contains(&name.trim_start_matches('~'))
+        }) {
+            return self.skip(rule, "DNS specific modifier");
+        }
+
+        // Allowing a whole domain amounts to not filtering it at all.
+        if let Some(domain) = rule
+            .strip_prefix("@@||")
+            .and_then(|domain| domain.strip_suffix('^'))
+            .filter(|domain| {
+                domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+            })
+        {
+            let domain = domain.to_lowercase();
+            self.add_exclusion(configuration, format!("*.{}", domain));
+            return self.add_exclusion(configuration, domain);
+        }
+
+        self.add_custom_filter(configuration, rule.to_string());
+    }
+}
+
+impl Configuration {
+    /// Converts the configuration of another blocker into changes to this one, without
+    /// applying them.
+    pub fn get_import_diff(
+        &self,
+        format: ImportFormat,
+        content: &str,
+    ) -> ConfigurationResult<ImportDiff> {
+        let mut diff = ImportDiff::default();
+
+        match format {
+            ImportFormat::Pihole => match serde_json::from_str::<PiholeExport>(content) {
+                Ok(PiholeExport::Adlists(adlists)) => {
+                    for adlist in adlists {
+                        diff.add_filter(
+                            self,
+                            adlist.comment.as_deref().unwrap_or_default(),
+                            &adlist.address,
+                            adlist.enabled,
+                        );
+                    }
+                }
+                Ok(PiholeExport::Domains(domains)) => {
+                    for domain in domains {
+                        diff.add_pihole_domain(self, domain);
+                    }
+                }
+                Err(_) if content.trim_start().starts_with('[') => {
+                    return Err(ConfigurationError::ImportError(
+                        "unrecognized Pi-hole export".to_string(),
+                    ));
+                }
+                // `adlists.list`, one URL per line.
+                Err(_) => {
+                    for line in content.lines().map(str::trim) {
+                        if !line.is_empty() && !line.starts_with('#') {
+                            diff.add_filter(self, "", line, true);
+                        }
+                    }
+                }
+            },
+            ImportFormat::Adguardhome => {
+                let adguard_home_configuration =
+                    serde_yaml::from_str::<AdguardHomeConfiguration>(content).map_err(|err| {
+                        ConfigurationError::ImportError(format!(
+                            "unable to parse AdGuard Home configuration: {err}"
+                        ))
+                    })?;
+
+                for filter in adguard_home_configuration.filters {
+                    diff.add_filter(self, &filter.name, &filter.url, filter.enabled);
+                }
+                for filter in adguard_home_configuration.whitelist_filters {
+                    diff.skip(&filter.url, "allowlists can't be subscribed to");
+                }
+                for rule in adguard_home_configuration.user_rules {
+                    diff.add_adguard_home_rule(self, &rule);
+                }
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Applies an import, downloading its filters first so that nothing changes if one
+    /// of them is unavailable.
+    pub async fn apply_import_diff(
+        &mut self,
+        diff: &ImportDiff,
+        http_client: &reqwest::Client,
+    ) -> ConfigurationResult<()> {
+        self.add_filters(diff.filters.clone(), http_client).await?;
+        self.custom_filters
+            .extend(diff.custom_filters.iter().cloned());
+        self.exclusions.extend(diff.exclusions.iter().cloned());
+
+        self.save().await
+    }
+}
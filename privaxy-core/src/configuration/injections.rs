@@ -0,0 +1,54 @@
+use super::{ConfigurationError, ConfigurationResult, ANY_HOST};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Styles and scripts added to the pages of a host and its subdomains, such as dark
+/// themes or fixes for broken sites.
+pub struct Injection {
+    /// Host of the pages, or `*` for every host.
+    pub host: String,
+    #[serde(default = "default_injection_enabled")]
+    pub enabled: bool,
+    /// Inserted in a `<style>` element.
+    #[serde(default)]
+    pub css: String,
+    /// Inserted in a `<script>` element.
+    #[serde(default)]
+    pub js: String,
+}
+
+fn default_injection_enabled() -> bool {
+    true
+}
+
+impl Injection {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        let is_valid_host = !self.host.is_empty()
+            && self
+                .host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+        if self.host != ANY_HOST && !is_valid_host {
+            return Err(ConfigurationError::InjectionError(format!(
+                "invalid host: {:?}",
+                self.host
+            )));
+        }
+
+        // Either would end the element the snippet is inserted in.
+        if self.css.to_lowercase().contains("</style") {
+            return Err(ConfigurationError::InjectionError(
+                "styles can't contain `</style`".to_string(),
+            ));
+        }
+
+        if self.js.to_lowercase().contains("</script") {
+            return Err(ConfigurationError::InjectionError(
+                "scripts can't contain `</script`".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
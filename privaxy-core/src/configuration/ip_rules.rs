@@ -0,0 +1,42 @@
+use super::{ConfigurationError, ConfigurationResult};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Destination addresses connections are refused to, whatever the host they were made
+/// for, such as the ranges of a known botnet.
+pub struct IpRule {
+    /// Address or CIDR range, such as `203.0.113.7` or `198.51.100.0/24`.
+    pub network: String,
+    /// Why the addresses are blocked.
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[serde(default = "default_ip_rule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_ip_rule_enabled() -> bool {
+    true
+}
+
+impl IpRule {
+    /// A single address is a network of its own.
+    pub fn get_network(&self) -> Option<IpNet> {
+        self.network
+            .parse::<IpNet>()
+            .or_else(|_| self.network.parse::<IpAddr>().map(IpNet::from))
+            .ok()
+    }
+
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.get_network().is_none() {
+            return Err(ConfigurationError::IpRuleError(format!(
+                "invalid address or CIDR range: {:?}",
+                self.network
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// Key the certificates issued for hosts are signed for
+pub enum LeafKeyType {
+    /// ECDSA on the P-256 curve, much cheaper to handshake with than RSA.
+    #[default]
+    EcdsaP256,
+    /// 2048 bits RSA, for clients which don't support ECDSA.
+    Rsa2048,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// Names certificates issued for hosts are valid for
+pub enum SubjectAlternativeNames {
+    /// Only the host the certificate is issued for.
+    #[default]
+    Host,
+    /// The host and its direct subdomains, through a wildcard. A certificate issued for a
+    /// host is reused for its subdomains.
+    HostAndSubdomains,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Certificates issued on the fly for the hosts requests are intercepted for.
+pub struct LeafCertificates {
+    #[serde(default)]
+    pub key_type: LeafKeyType,
+    /// Days certificates are valid for once issued.
+    #[serde(default = "default_validity_days")]
+    pub validity_days: u32,
+    #[serde(default)]
+    pub subject_alternative_names: SubjectAlternativeNames,
+}
+
+fn default_validity_days() -> u32 {
+    365
+}
+
+impl Default for LeafCertificates {
+    fn default() -> Self {
+        Self {
+            key_type: LeafKeyType::default(),
+            validity_days: default_validity_days(),
+            subject_alternative_names: SubjectAlternativeNames::default(),
+        }
+    }
+}
+
+impl LeafCertificates {
+    pub fn validity_days(&self) -> u32 {
+        // Certificates valid from the time they are issued only would be rejected.
+        self.validity_days.max(1)
+    }
+}
@@ -0,0 +1,182 @@
+use super::{
+    calc_filter_filename, get_base_directory, Configuration, ConfigurationError,
+    ConfigurationResult, Filter, FilterBasicAuth, FilterGroup,
+};
+use crate::proxy::exclusions::LocalExclusionStore;
+use crate::startup::get_startup_options;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::fs;
+use tokio::sync::mpsc::Sender;
+use url::Url;
+use uuid::Uuid;
+
+const MANAGED_CONFIGURATION_FILE_NAME: &str = "managed.toml";
+
+const MANAGED_CONFIGURATION_REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+lazy_static! {
+    static ref MANAGED_CONFIGURATION: RwLock<ManagedConfiguration> =
+        RwLock::new(ManagedConfiguration::default());
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ManagedFilter {
+    pub title: String,
+    #[serde(default = "default_managed_filter_group")]
+    pub group: FilterGroup,
+    #[serde_as(as = "DisplayFromStr")]
+    pub url: Url,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub basic_auth: Option<FilterBasicAuth>,
+}
+
+fn default_managed_filter_group() -> FilterGroup {
+    FilterGroup::Default
+}
+
+impl From<ManagedFilter> for Filter {
+    fn from(managed_filter: ManagedFilter) -> Self {
+        Self {
+            // Derived from the URL so that the filter keeps its identifier across reads.
+            id: Uuid::new_v5(&Uuid::NAMESPACE_URL, managed_filter.url.as_str().as_bytes()),
+            enabled: true,
+            title: managed_filter.title,
+            group: managed_filter.group,
+            file_name: calc_filter_filename(managed_filter.url.as_str()),
+            url: managed_filter.url,
+            update_interval_secs: None,
+            headers: managed_filter.headers,
+            basic_auth: managed_filter.basic_auth,
+            managed: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// Baseline policy pushed by an administrator. Its filter lists and exclusions apply
+/// on top of the local configuration and can't be removed through the API.
+pub struct ManagedConfiguration {
+    #[serde(default)]
+    pub filters: Vec<ManagedFilter>,
+    #[serde(default)]
+    pub exclusions: BTreeSet<String>,
+}
+
+enum ManagedConfigurationSource {
+    File(PathBuf),
+    Url(Url),
+}
+
+fn get_managed_configuration_source() -> Option<ManagedConfigurationSource> {
+    match &get_startup_options().managed_configuration {
+        Some(value) => match Url::parse(value) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {
+                Some(ManagedConfigurationSource::Url(url))
+            }
+            _ => Some(ManagedConfigurationSource::File(PathBuf::from(value))),
+        },
+        None => get_base_directory()
+            .ok()
+            .map(|base_directory| base_directory.join(MANAGED_CONFIGURATION_FILE_NAME))
+            .map(ManagedConfigurationSource::File),
+    }
+}
+
+async fn read_managed_configuration(
+    http_client: &reqwest::Client,
+) -> ConfigurationResult<ManagedConfiguration> {
+    let content = match get_managed_configuration_source() {
+        Some(ManagedConfigurationSource::File(path)) => match fs::read_to_string(&path).await {
+            Ok(content) => content,
+            // Deployments that aren't managed don't have one.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ManagedConfiguration::default())
+            }
+            Err(err) => return Err(ConfigurationError::FileSystemError(err)),
+        },
+        Some(ManagedConfigurationSource::Url(url)) => http_client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| ConfigurationError::ManagedConfigurationError(err.to_string()))?
+            .text()
+            .await
+            .map_err(|err| ConfigurationError::ManagedConfigurationError(err.to_string()))?,
+        None => return Ok(ManagedConfiguration::default()),
+    };
+
+    toml::from_str(&content)
+        .map_err(|err| ConfigurationError::ManagedConfigurationError(err.to_string()))
+}
+
+/// Reads the managed configuration again, returning whether it changed. The previous
+/// one is kept when it can't be read.
+pub(crate) async fn load_managed_configuration(
+    http_client: &reqwest::Client,
+) -> ConfigurationResult<bool> {
+    let managed_configuration = read_managed_configuration(http_client).await?;
+
+    let mut current = MANAGED_CONFIGURATION.write().unwrap();
+    if *current == managed_configuration {
+        return Ok(false);
+    }
+
+    log::info!(
+        "Loaded managed configuration with {} filters and {} exclusions",
+        managed_configuration.filters.len(),
+        managed_configuration.exclusions.len()
+    );
+
+    *current = managed_configuration;
+
+    Ok(true)
+}
+
+pub(crate) fn get_managed_configuration() -> ManagedConfiguration {
+    MANAGED_CONFIGURATION.read().unwrap().clone()
+}
+
+/// Applies changes of the managed configuration as they are published.
+pub(crate) async fn refresh_managed_configuration_periodically(
+    http_client: reqwest::Client,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    mut local_exclusion_store: LocalExclusionStore,
+) {
+    loop {
+        tokio::time::sleep(MANAGED_CONFIGURATION_REFRESH_INTERVAL).await;
+
+        match load_managed_configuration(&http_client).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(err) => {
+                log::error!("Unable to refresh managed configuration: {}", err);
+                continue;
+            }
+        }
+
+        let _guard = configuration_save_lock.lock().await;
+
+        let configuration = match Configuration::read_from_home().await {
+            Ok(configuration) => configuration,
+            Err(err) => {
+                log::error!("Unable to apply managed configuration: {}", err);
+                continue;
+            }
+        };
+
+        local_exclusion_store.replace_exclusions(configuration.get_exclusions());
+
+        let _result = configuration_updater_sender.send(configuration).await;
+    }
+}
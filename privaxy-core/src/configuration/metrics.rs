@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Prometheus metrics exposed at `/api/metrics`
+pub struct Metrics {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Number of most active clients getting series of their own, the others are
+    /// aggregated so that large networks don't create countless series.
+    #[serde(default = "default_max_labeled_clients")]
+    pub max_labeled_clients: usize,
+    /// Number of most active proxy users getting series of their own.
+    #[serde(default = "default_max_labeled_users")]
+    pub max_labeled_users: usize,
+    /// Lets scrapers authenticate with an `Authorization: Bearer` header rather than
+    /// a web GUI session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+}
+
+fn default_max_labeled_clients() -> usize {
+    20
+}
+
+fn default_max_labeled_users() -> usize {
+    20
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_labeled_clients: default_max_labeled_clients(),
+            max_labeled_users: default_max_labeled_users(),
+            bearer_token: None,
+        }
+    }
+}
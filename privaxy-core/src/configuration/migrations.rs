@@ -0,0 +1,84 @@
+use super::{ConfigurationError, ConfigurationResult};
+use toml::Value;
+use uuid::Uuid;
+
+/// Version of the configuration file layout written by this version of privaxy.
+pub const CONFIGURATION_VERSION: u32 = 1;
+
+type Migration = fn(&mut toml::Table) -> ConfigurationResult<()>;
+
+/// Upgrades the configuration file one version at a time, the migration at index `n`
+/// upgrades version `n` to `n + 1`.
+///
+/// Migrations work on the TOML document rather than on [`super::Configuration`] so that
+/// they can read settings that were renamed or removed since.
+const MIGRATIONS: [Migration; CONFIGURATION_VERSION as usize] = [assign_filter_ids];
+
+fn get_version(configuration: &toml::Table) -> ConfigurationResult<u32> {
+    match configuration.get("version") {
+        // Configuration files were not versioned at first.
+        None => Ok(0),
+        Some(Value::Integer(version)) => u32::try_from(*version)
+            .map_err(|_| ConfigurationError::MigrationError(format!("invalid version {version}"))),
+        Some(version) => Err(ConfigurationError::MigrationError(format!(
+            "invalid version {version}"
+        ))),
+    }
+}
+
+/// Upgrades a configuration file to [`CONFIGURATION_VERSION`], returning the version it
+/// was upgraded from if it was outdated.
+pub(super) fn migrate(configuration: &mut toml::Table) -> ConfigurationResult<Option<u32>> {
+    let version = get_version(configuration)?;
+
+    // Settings written by a newer version would be lost the next time the file is saved.
+    if version > CONFIGURATION_VERSION {
+        return Err(ConfigurationError::MigrationError(format!(
+            "configuration file version {version} was written by a newer version of privaxy, \
+             this version supports up to {CONFIGURATION_VERSION}"
+        )));
+    }
+
+    if version == CONFIGURATION_VERSION {
+        return Ok(None);
+    }
+
+    for (from_version, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        log::info!(
+            "Migrating configuration file from version {} to {}",
+            from_version,
+            from_version + 1
+        );
+
+        migration(configuration)?;
+    }
+
+    configuration.insert(
+        "version".to_string(),
+        Value::Integer(CONFIGURATION_VERSION.into()),
+    );
+
+    Ok(Some(version))
+}
+
+/// Filters of configurations written by earlier versions have no identifier.
+fn assign_filter_ids(configuration: &mut toml::Table) -> ConfigurationResult<()> {
+    let filters = match configuration.get_mut("filters") {
+        Some(Value::Array(filters)) => filters,
+        _ => return Ok(()),
+    };
+
+    for filter in filters.iter_mut().filter_map(Value::as_table_mut) {
+        let has_id = filter
+            .get("id")
+            .and_then(Value::as_str)
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .is_some_and(|id| !id.is_nil());
+
+        if !has_id {
+            filter.insert("id".to_string(), Value::String(Uuid::new_v4().to_string()));
+        }
+    }
+
+    Ok(())
+}
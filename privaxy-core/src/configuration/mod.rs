@@ -0,0 +1,1095 @@
+use crate::startup::get_startup_options;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, time::Duration};
+use thiserror::Error;
+use tokio::fs;
+use toml::Value;
+mod block_responses;
+mod ca;
+mod categories;
+mod circuit_breaker;
+mod client_identification;
+mod connection_prewarming;
+mod content_rewrite_rules;
+mod cookie_rules;
+mod export;
+mod filter;
+mod filter_preview;
+mod filter_status;
+mod header_rules;
+mod import;
+mod injections;
+mod ip_rules;
+mod leaf_certificates;
+mod managed;
+mod metrics;
+mod migrations;
+mod network;
+mod notifications;
+mod plugins;
+mod profiles;
+mod proxy_authentication;
+mod response_cache;
+mod safe_search;
+mod schedules;
+mod scripts;
+mod site_policies;
+mod statistics_retention;
+mod telemetry;
+mod threat_intelligence;
+mod traffic_shaping;
+mod updater;
+mod upstream_connections;
+mod upstream_tls;
+mod url_rewrites;
+mod watcher;
+mod web_authentication;
+pub use block_responses::*;
+pub use ca::*;
+pub use categories::*;
+pub use circuit_breaker::*;
+pub use client_identification::*;
+pub use connection_prewarming::*;
+pub use content_rewrite_rules::*;
+pub use cookie_rules::*;
+pub use export::*;
+pub use filter::*;
+pub use filter_preview::*;
+pub use filter_status::*;
+use futures::future::{join_all, try_join_all};
+pub use header_rules::*;
+pub use import::*;
+pub use injections::*;
+pub use ip_rules::*;
+pub use leaf_certificates::*;
+pub use managed::*;
+pub use metrics::*;
+pub use migrations::CONFIGURATION_VERSION;
+pub use network::*;
+pub use notifications::*;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::X509;
+pub use plugins::*;
+pub use profiles::*;
+pub use proxy_authentication::*;
+pub use response_cache::*;
+pub use safe_search::*;
+pub(crate) use schedules::{apply_schedules, reset_statistics_as_scheduled};
+pub use schedules::{CronRule, Schedule};
+pub use scripts::*;
+pub use site_policies::*;
+pub use statistics_retention::*;
+use std::path::{Path, PathBuf};
+pub use telemetry::*;
+pub use threat_intelligence::*;
+pub use traffic_shaping::*;
+pub use updater::*;
+pub use upstream_connections::*;
+pub use upstream_tls::*;
+use url::Url;
+pub use url_rewrites::*;
+use uuid::Uuid;
+pub(crate) use watcher::watch_configuration_file;
+pub use web_authentication::*;
+pub(crate) type ConfigurationResult<T> = Result<T, ConfigurationError>;
+/// Default time between automatic filter updates.
+pub(crate) const FILTERS_UPDATE_AFTER: Duration = Duration::from_secs(60 * 10);
+
+/// Filename of the configuration file.
+pub(crate) const CONFIGURATION_FILE_NAME: &str = "config";
+
+/// Filename of the compiled blocking engine cache.
+pub(crate) const ENGINE_CACHE_FILE_NAME: &str = "engine.dat";
+
+/// Filename of the saved statistics history.
+pub(crate) const STATISTICS_HISTORY_FILE_NAME: &str = "statistics_history.json";
+
+/// Filename of the saved statistics time series.
+pub(crate) const STATISTICS_TIMESERIES_FILE_NAME: &str = "statistics_timeseries.json";
+
+/// Filename of the saved companies of tracker domains.
+pub(crate) const TRACKER_ENTITIES_FILE_NAME: &str = "tracker_entities.json";
+
+/// Filename of the saved event history.
+pub(crate) const EVENT_HISTORY_FILE_NAME: &str = "event_history.json";
+
+/// Filename of the log of configuration changes, one JSON entry per line.
+pub(crate) const AUDIT_LOG_FILE_NAME: &str = "audit_log.jsonl";
+
+/// Filename of the log written by the `file` log sink.
+pub(crate) const LOG_FILE_NAME: &str = "privaxy.log";
+
+/// Name of the directory certificates issued for hosts are persisted in.
+pub(crate) const CERTIFICATES_DIRECTORY_NAME: &str = "certificates";
+
+/// Filenames of the certificate and key the web GUI is served with over TLS, when they
+/// are generated rather than specified.
+pub(crate) const WEB_TLS_CERTIFICATE_FILE_NAME: &str = "web_certificate.pem";
+pub(crate) const WEB_TLS_KEY_FILE_NAME: &str = "web_key.pem";
+
+/// Name of the directory the modules of plugins are installed in.
+pub(crate) const PLUGINS_DIRECTORY_NAME: &str = "plugins";
+
+/// Name of the directory Lua scripts are loaded from.
+pub(crate) const SCRIPTS_DIRECTORY_NAME: &str = "scripts";
+
+/// Name of the directory cached responses are kept in.
+pub(crate) const RESPONSE_CACHE_DIRECTORY_NAME: &str = "cache";
+
+/// Default configuration directory name.
+pub(crate) const CONFIGURATION_DIRECTORY_NAME: &str = "/etc/privaxy";
+
+#[derive(Error, Debug)]
+pub enum ConfigurationError {
+    #[error("NetworkConfigError error: {0}")]
+    NetworkConfigError(#[from] NetworkConfigError),
+    #[error("CaError error: {0}")]
+    CaError(#[from] CaError),
+    #[error("an error occured while trying to deserialize configuration file")]
+    DeserializeError(#[from] toml::de::Error),
+    #[error("this directory was not found")]
+    DirectoryNotFound,
+    #[error("file system error")]
+    FileSystemError(#[from] std::io::Error),
+    #[error("data store disconnected")]
+    UnableToRetrieveDefaultFilters(#[from] reqwest::Error),
+    #[error("unable to decode filter bytes, bad utf8 data")]
+    UnableToDecodeFilterbytes(#[from] std::str::Utf8Error),
+    #[error("unable to decode pem data")]
+    UnableToDecodePem(#[from] openssl::error::ErrorStack),
+    #[error("filter error: {0}")]
+    FilterError(String),
+    #[error("client identification error: {0}")]
+    ClientIdentificationError(String),
+    #[error("site policy error: {0}")]
+    SitePolicyError(String),
+    #[error("block responses error: {0}")]
+    BlockResponsesError(String),
+    #[error("header rule error: {0}")]
+    HeaderRuleError(String),
+    #[error("cookie rule error: {0}")]
+    CookieRuleError(String),
+    #[error("IP rule error: {0}")]
+    IpRuleError(String),
+    #[error("content rewrite rule error: {0}")]
+    ContentRewriteRuleError(String),
+    #[error("injection error: {0}")]
+    InjectionError(String),
+    #[error("import error: {0}")]
+    ImportError(String),
+    #[error("managed configuration error: {0}")]
+    ManagedConfigurationError(String),
+    #[error("{0} is part of the managed configuration")]
+    Managed(String),
+    #[error("filter {0} does not exist")]
+    FilterNotFound(Uuid),
+    #[error("unable to migrate configuration file: {0}")]
+    MigrationError(String),
+    #[error("profile error: {0}")]
+    ProfileError(String),
+    #[error("schedule error: {0}")]
+    ScheduleError(String),
+    #[error("notification error: {0}")]
+    NotificationError(String),
+    #[error("telemetry error: {0}")]
+    TelemetryError(String),
+    #[error("statistics retention error: {0}")]
+    StatisticsRetentionError(String),
+    #[error("TLS override error: {0}")]
+    TlsOverrideError(String),
+    #[error("threat intelligence error: {0}")]
+    ThreatIntelligenceError(String),
+    #[error("category error: {0}")]
+    CategoryError(String),
+    #[error("URL rewrite error: {0}")]
+    UrlRewriteError(String),
+    #[error("plugin error: {0}")]
+    PluginError(String),
+    #[error("script error: {0}")]
+    ScriptError(String),
+    #[error("response cache error: {0}")]
+    ResponseCacheError(String),
+    #[error("traffic shaping error: {0}")]
+    TrafficShapingError(String),
+    #[error("upstream connections error: {0}")]
+    UpstreamConnectionsError(String),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Configuration {
+    /// Layout version of the configuration file, older files are migrated when read.
+    #[serde(default)]
+    pub version: u32,
+    pub exclusions: BTreeSet<String>,
+    pub custom_filters: Vec<String>,
+    pub ca: Ca,
+    pub network: NetworkConfig,
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub proxy_authentication: ProxyAuthentication,
+    #[serde(default)]
+    pub block_responses: BlockResponses,
+    #[serde(default)]
+    pub client_identification: ClientIdentification,
+    #[serde(default)]
+    pub web_authentication: WebAuthentication,
+    #[serde(default)]
+    pub connection_prewarming: ConnectionPrewarming,
+    #[serde(default)]
+    pub leaf_certificates: LeafCertificates,
+    #[serde(default)]
+    pub upstream_tls: UpstreamTls,
+    #[serde(default)]
+    pub upstream_connections: UpstreamConnections,
+    #[serde(default)]
+    pub metrics: Metrics,
+    #[serde(default)]
+    pub telemetry: Telemetry,
+    #[serde(default)]
+    pub statistics_retention: StatisticsRetention,
+    #[serde(default)]
+    pub site_policies: Vec<SitePolicy>,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreaker,
+    #[serde(default)]
+    pub header_rules: Vec<HeaderRule>,
+    #[serde(default)]
+    pub referer_trimming: RefererTrimming,
+    #[serde(default)]
+    pub user_agent_rules: Vec<UserAgentRule>,
+    #[serde(default)]
+    pub cookie_rules: CookieRules,
+    #[serde(default)]
+    pub ip_rules: Vec<IpRule>,
+    #[serde(default)]
+    pub threat_intelligence: ThreatIntelligence,
+    #[serde(default)]
+    pub safe_search: SafeSearch,
+    #[serde(default)]
+    pub categories: ContentCategories,
+    #[serde(default)]
+    pub content_rewrite_rules: Vec<ContentRewriteRule>,
+    #[serde(default)]
+    pub url_rewrites: Vec<UrlRewrite>,
+    #[serde(default)]
+    pub plugins: Plugins,
+    #[serde(default)]
+    pub scripts: Scripts,
+    #[serde(default)]
+    pub response_cache: ResponseCache,
+    #[serde(default)]
+    pub traffic_shaping: TrafficShaping,
+    #[serde(default)]
+    pub injections: Vec<Injection>,
+    /// Seconds between automatic filter updates, unless a filter sets its own interval.
+    #[serde(default = "default_filters_update_interval_secs")]
+    pub filters_update_interval_secs: u64,
+    /// Directory in which every `.txt` file is a filter list, reloaded when edited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_filters_directory: Option<PathBuf>,
+    /// Responses announcing a larger body, in bytes, are relayed without being inspected
+    /// nor rewritten, pages excepted. `0` inspects responses of any size.
+    #[serde(default = "default_max_inspected_body_size")]
+    pub max_inspected_body_size: u64,
+    /// Replicas of the blocking engine requests are spread over, each matching requests
+    /// on a thread of its own. Every replica takes as much memory as the engine. Takes
+    /// effect once Privaxy restarts.
+    #[serde(default = "default_engine_shards")]
+    pub engine_shards: usize,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// Name of the profile the filters, exclusions and custom filters belong to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    /// Switch between profiles at set times.
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+    /// Cron rule statistics are reset at, such as `0 0 1 * *` to start over every month.
+    #[serde(default)]
+    pub statistics_reset: Option<String>,
+    /// Webhooks events are posted to.
+    #[serde(default)]
+    pub notifications: Vec<NotificationTarget>,
+    /// Exclusions of the managed configuration, never saved to the configuration file.
+    #[serde(skip)]
+    pub managed_exclusions: BTreeSet<String>,
+}
+
+fn default_filters_update_interval_secs() -> u64 {
+    FILTERS_UPDATE_AFTER.as_secs()
+}
+
+fn default_max_inspected_body_size() -> u64 {
+    8 * 1024 * 1024
+}
+
+fn default_engine_shards() -> usize {
+    1
+}
+
+#[derive(Error, Debug)]
+pub enum PrivaxyError {
+    #[error("ConfigurationError: {0}")]
+    ConfigurationError(#[from] ConfigurationError),
+}
+
+impl Configuration {
+    /// Reads the configuration file, with the managed configuration applied on top of it.
+    pub async fn read_from_home() -> ConfigurationResult<Self> {
+        let mut configuration = Self::read_local_from_home().await?;
+        configuration.apply_managed_configuration(get_managed_configuration());
+
+        Ok(configuration)
+    }
+
+    fn apply_managed_configuration(&mut self, managed_configuration: ManagedConfiguration) {
+        for filter in self.filters.iter_mut() {
+            filter.managed = false;
+        }
+
+        for managed_filter in managed_configuration.filters {
+            match self
+                .filters
+                .iter_mut()
+                .find(|filter| filter.url == managed_filter.url)
+            {
+                Some(filter) => {
+                    filter.enabled = true;
+                    filter.managed = true;
+                }
+                None => self.filters.push(managed_filter.into()),
+            }
+        }
+
+        self.managed_exclusions = managed_configuration.exclusions;
+    }
+
+    async fn read_local_from_home() -> ConfigurationResult<Self> {
+        let configuration_directory = get_base_directory().unwrap();
+        let configuration_file_path = get_config_file();
+
+        if let Err(err) = fs::metadata(&configuration_directory).await {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                log::debug!("Configuration directory not found, creating one");
+
+                fs::create_dir(&configuration_directory).await?;
+
+                let configuration = Self::new_default().await?;
+                configuration.save().await?;
+
+                return Ok(configuration);
+            } else {
+                return Err(ConfigurationError::FileSystemError(err));
+            }
+        };
+
+        match fs::read(&configuration_file_path).await {
+            Ok(bytes) => {
+                let mut document: toml::Table = toml::from_str(std::str::from_utf8(&bytes)?)?;
+                let previous_version = migrations::migrate(&mut document)?;
+                let configuration: Self = Value::Table(document).try_into()?;
+
+                if let Some(previous_version) = previous_version {
+                    // Older versions of privaxy can't read migrated files.
+                    let backup_file_path =
+                        configuration_file_path.with_extension(format!("v{previous_version}.bak"));
+                    fs::copy(&configuration_file_path, &backup_file_path).await?;
+
+                    log::info!(
+                        "Migrated configuration file, the previous one was saved to {}",
+                        backup_file_path.display()
+                    );
+                    configuration.save().await?;
+                }
+
+                Ok(configuration)
+            }
+            Err(err) => {
+                log::debug!("Configuration file not found, creating one");
+
+                if err.kind() == std::io::ErrorKind::NotFound {
+                    let configuration = Self::new_default().await?;
+                    configuration.save().await?;
+
+                    Ok(configuration)
+                } else {
+                    Err(ConfigurationError::FileSystemError(err))
+                }
+            }
+        }
+    }
+
+    pub async fn save(&self) -> ConfigurationResult<()> {
+        let configuration_file_path = get_config_file();
+
+        let configuration_serialized = toml::to_string_pretty(&self).unwrap();
+        watcher::set_saved_configuration(configuration_serialized.clone());
+
+        fs::write(configuration_file_path, configuration_serialized).await?;
+
+        Ok(())
+    }
+
+    pub async fn set_custom_filters(&mut self, custom_filters: &str) -> ConfigurationResult<()> {
+        self.custom_filters = Self::deserialize_lines(custom_filters);
+
+        self.save().await?;
+
+        Ok(())
+    }
+
+    fn deserialize_lines<T>(lines: &str) -> T
+    where
+        T: FromIterator<String>,
+    {
+        lines
+            .lines()
+            .filter_map(|s_| {
+                let s_ = s_.trim();
+
+                // Removing empty lines
+                if s_.is_empty() {
+                    None
+                } else {
+                    Some(s_.to_string())
+                }
+            })
+            .collect::<T>()
+    }
+
+    pub async fn set_exclusions(
+        &mut self,
+        exclusions: &str,
+        mut local_exclusion_store: crate::exclusions::LocalExclusionStore,
+    ) -> ConfigurationResult<()> {
+        self.exclusions = Self::deserialize_lines(exclusions);
+
+        self.save().await?;
+
+        local_exclusion_store.replace_exclusions(self.get_exclusions());
+
+        Ok(())
+    }
+
+    pub async fn add_exclusion(
+        &mut self,
+        exclusion: &str,
+        mut local_exclusion_store: crate::exclusions::LocalExclusionStore,
+    ) -> ConfigurationResult<()> {
+        self.exclusions.insert(exclusion.to_string());
+
+        self.save().await?;
+
+        local_exclusion_store.replace_exclusions(self.get_exclusions());
+
+        Ok(())
+    }
+
+    /// Returns local and managed exclusions.
+    pub fn get_exclusions(&self) -> Vec<String> {
+        self.exclusions
+            .union(&self.managed_exclusions)
+            .cloned()
+            .collect()
+    }
+
+    pub async fn set_proxy_authentication(
+        &mut self,
+        proxy_authentication: ProxyAuthentication,
+        proxy_authentication_store: crate::proxy::authentication::ProxyAuthenticationStore,
+    ) -> ConfigurationResult<()> {
+        self.proxy_authentication = proxy_authentication;
+
+        self.save().await?;
+
+        proxy_authentication_store.replace(self.proxy_authentication.clone());
+
+        Ok(())
+    }
+
+    pub async fn set_web_authentication(
+        &mut self,
+        web_authentication: WebAuthentication,
+        session_store: crate::web_gui::sessions::SessionStore,
+    ) -> ConfigurationResult<()> {
+        self.web_authentication = web_authentication;
+
+        self.save().await?;
+
+        session_store.replace(self.web_authentication.clone());
+
+        Ok(())
+    }
+
+    pub async fn set_block_responses(
+        &mut self,
+        block_responses: BlockResponses,
+        block_responses_store: crate::proxy::block_responses::BlockResponsesStore,
+    ) -> ConfigurationResult<()> {
+        block_responses.validate()?;
+
+        // An empty template falls back to the default page.
+        let mut block_responses = block_responses;
+        if let Some(template) = &block_responses.block_page_template {
+            if template.trim().is_empty() {
+                block_responses.block_page_template = None;
+            }
+        }
+
+        self.block_responses = block_responses;
+
+        self.save().await?;
+
+        block_responses_store.replace(self.block_responses.clone());
+
+        Ok(())
+    }
+
+    pub async fn set_client_identification(
+        &mut self,
+        client_identification: ClientIdentification,
+        client_identification_store: crate::proxy::client_identification::ClientIdentificationStore,
+    ) -> ConfigurationResult<()> {
+        client_identification.validate()?;
+
+        self.client_identification = client_identification;
+
+        self.save().await?;
+
+        client_identification_store.replace(self.client_identification.clone());
+
+        Ok(())
+    }
+
+    /// Adds the policy of a site, or replaces it when the site already has one.
+    pub async fn set_site_policy(
+        &mut self,
+        mut site_policy: SitePolicy,
+        site_policy_store: crate::proxy::site_policies::SitePolicyStore,
+    ) -> ConfigurationResult<()> {
+        site_policy.site = site_policy.site.trim().to_lowercase();
+        site_policy.validate()?;
+
+        match self
+            .site_policies
+            .iter_mut()
+            .find(|policy| policy.site == site_policy.site)
+        {
+            Some(policy) => *policy = site_policy,
+            None => self.site_policies.push(site_policy),
+        }
+
+        self.save().await?;
+
+        site_policy_store.replace(self.site_policies.clone());
+
+        Ok(())
+    }
+
+    /// Returns whether the site had a policy.
+    pub async fn remove_site_policy(
+        &mut self,
+        site: &str,
+        site_policy_store: crate::proxy::site_policies::SitePolicyStore,
+    ) -> ConfigurationResult<bool> {
+        let site = site.to_lowercase();
+        let policies_count = self.site_policies.len();
+
+        self.site_policies.retain(|policy| policy.site != site);
+
+        if self.site_policies.len() == policies_count {
+            return Ok(false);
+        }
+
+        self.save().await?;
+
+        site_policy_store.replace(self.site_policies.clone());
+
+        Ok(true)
+    }
+
+    /// Replaces every header rule, keeping their order.
+    pub async fn set_header_rules(
+        &mut self,
+        mut header_rules: Vec<HeaderRule>,
+        header_rules_store: crate::proxy::header_rules::HeaderRulesStore,
+    ) -> ConfigurationResult<()> {
+        for header_rule in &mut header_rules {
+            header_rule.host = header_rule.host.trim().to_lowercase();
+            header_rule.name = header_rule.name.trim().to_lowercase();
+            header_rule.validate()?;
+        }
+
+        self.header_rules = header_rules;
+
+        self.save().await?;
+
+        header_rules_store.replace(self.header_rules.clone());
+
+        Ok(())
+    }
+
+    pub async fn set_referer_trimming(
+        &mut self,
+        mut referer_trimming: RefererTrimming,
+        header_rules_store: crate::proxy::header_rules::HeaderRulesStore,
+    ) -> ConfigurationResult<()> {
+        referer_trimming.strip_domains = referer_trimming
+            .strip_domains
+            .iter()
+            .map(|domain| domain.trim().to_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+        referer_trimming.validate()?;
+
+        self.referer_trimming = referer_trimming;
+
+        self.save().await?;
+
+        header_rules_store.replace_referer_trimming(self.referer_trimming.clone());
+
+        Ok(())
+    }
+
+    /// Replaces every user agent rule.
+    pub async fn set_user_agent_rules(
+        &mut self,
+        mut user_agent_rules: Vec<UserAgentRule>,
+        header_rules_store: crate::proxy::header_rules::HeaderRulesStore,
+    ) -> ConfigurationResult<()> {
+        for user_agent_rule in &mut user_agent_rules {
+            user_agent_rule.host = user_agent_rule.host.trim().to_lowercase();
+            user_agent_rule.user_agent = user_agent_rule.user_agent.trim().to_string();
+            user_agent_rule.validate()?;
+        }
+
+        self.user_agent_rules = user_agent_rules;
+
+        self.save().await?;
+
+        header_rules_store.replace_user_agent_rules(self.user_agent_rules.clone());
+
+        Ok(())
+    }
+
+    pub async fn set_cookie_rules(
+        &mut self,
+        mut cookie_rules: CookieRules,
+        cookie_rules_store: crate::proxy::cookie_rules::CookieRulesStore,
+    ) -> ConfigurationResult<()> {
+        for rule in &mut cookie_rules.rules {
+            rule.host = rule.host.trim().to_lowercase();
+        }
+        cookie_rules.validate()?;
+
+        self.cookie_rules = cookie_rules;
+
+        self.save().await?;
+
+        cookie_rules_store.replace(self.cookie_rules.clone());
+
+        Ok(())
+    }
+
+    /// Replaces every IP rule.
+    pub async fn set_ip_rules(
+        &mut self,
+        mut ip_rules: Vec<IpRule>,
+        ip_rule_store: crate::proxy::ip_rules::IpRuleStore,
+    ) -> ConfigurationResult<()> {
+        for ip_rule in &mut ip_rules {
+            ip_rule.network = ip_rule.network.trim().to_string();
+            ip_rule.validate()?;
+        }
+
+        self.ip_rules = ip_rules;
+
+        self.save().await?;
+
+        ip_rule_store.replace(self.ip_rules.clone());
+
+        Ok(())
+    }
+
+    /// Replaces every content rewrite rule.
+    pub async fn set_content_rewrite_rules(
+        &mut self,
+        mut content_rewrite_rules: Vec<ContentRewriteRule>,
+        content_rewrite_store: crate::proxy::content_rewrite::ContentRewriteStore,
+    ) -> ConfigurationResult<()> {
+        for content_rewrite_rule in &mut content_rewrite_rules {
+            content_rewrite_rule.host = content_rewrite_rule.host.trim().to_lowercase();
+            content_rewrite_rule.validate()?;
+        }
+
+        self.content_rewrite_rules = content_rewrite_rules;
+
+        self.save().await?;
+
+        content_rewrite_store.replace(self.content_rewrite_rules.clone());
+
+        Ok(())
+    }
+
+    pub async fn set_injections(
+        &mut self,
+        mut injections: Vec<Injection>,
+        injection_store: crate::proxy::injections::InjectionStore,
+    ) -> ConfigurationResult<()> {
+        for injection in &mut injections {
+            injection.host = injection.host.trim().to_lowercase();
+            injection.validate()?;
+        }
+
+        self.injections = injections;
+
+        self.save().await?;
+
+        injection_store.replace(self.injections.clone());
+
+        Ok(())
+    }
+
+    pub fn get_filter_mut(&mut self, id: Uuid) -> ConfigurationResult<&mut Filter> {
+        self.filters
+            .iter_mut()
+            .find(|filter| filter.id == id)
+            .ok_or(ConfigurationError::FilterNotFound(id))
+    }
+
+    pub async fn set_filter_enabled_status(
+        &mut self,
+        id: Uuid,
+        enabled: bool,
+    ) -> ConfigurationResult<()> {
+        let filter = self.get_filter_mut(id)?;
+
+        if filter.managed && !enabled {
+            return Err(ConfigurationError::Managed(filter.title.clone()));
+        }
+
+        filter.enabled = enabled;
+
+        self.save().await?;
+        Ok(())
+    }
+
+    /// Renames a filter or changes its URL, downloading the list again when the URL
+    /// changed.
+    pub async fn edit_filter(
+        &mut self,
+        id: Uuid,
+        title: Option<String>,
+        url: Option<Url>,
+        http_client: &reqwest::Client,
+    ) -> ConfigurationResult<()> {
+        let filter = self.get_filter_mut(id)?;
+
+        if filter.managed {
+            return Err(ConfigurationError::Managed(filter.title.clone()));
+        }
+
+        if let Some(title) = title {
+            filter.title = title;
+        }
+
+        if let Some(url) = url.filter(|url| url != &filter.url) {
+            let mut edited_filter = filter.clone();
+            edited_filter.file_name = calc_filter_filename(url.as_str());
+            edited_filter.url = url;
+
+            // The previous list is kept when the new URL can't be downloaded.
+            edited_filter.update(http_client).await?;
+            *filter = edited_filter;
+        }
+
+        self.save().await?;
+        Ok(())
+    }
+
+    pub fn get_enabled_filters(&mut self) -> impl Iterator<Item = &mut Filter> {
+        self.filters.iter_mut().filter(|f| f.enabled)
+    }
+
+    pub fn filters_update_interval(&self) -> Duration {
+        Duration::from_secs(self.filters_update_interval_secs)
+    }
+
+    pub async fn update_filters(
+        &mut self,
+        http_client: reqwest::Client,
+    ) -> ConfigurationResult<()> {
+        log::debug!("Updating filters");
+
+        let futures = self.filters.iter_mut().filter_map(|filter| {
+            if filter.enabled {
+                Some(filter.update(&http_client))
+            } else {
+                None
+            }
+        });
+
+        try_join_all(futures).await?;
+
+        Ok(())
+    }
+
+    pub async fn add_filter(
+        &mut self,
+        filter: &mut Filter,
+        http_client: &reqwest::Client,
+    ) -> ConfigurationResult<()> {
+        filter.get_request_headers()?;
+
+        match filter.update(http_client).await {
+            Ok(_) => {
+                self.filters.push(filter.clone());
+                Ok(())
+            }
+            Err(err) => {
+                log::error!("Failed to add filter: {err}");
+                filter.enabled = false;
+                Err(ConfigurationError::FilterError(
+                    "Unable to add filter".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Adds filters, downloading them concurrently. None of them is added when one
+    /// can't be downloaded.
+    pub async fn add_filters(
+        &mut self,
+        mut filters: Vec<Filter>,
+        http_client: &reqwest::Client,
+    ) -> ConfigurationResult<()> {
+        for filter in &filters {
+            filter.get_request_headers()?;
+        }
+
+        let results = join_all(filters.iter_mut().map(|filter| filter.update(http_client))).await;
+
+        for (filter, result) in filters.iter().zip(results) {
+            if let Err(err) = result {
+                log::error!("Failed to add filter {}: {err}", filter.title);
+                return Err(ConfigurationError::FilterError(format!(
+                    "Unable to add filter {}",
+                    filter.title
+                )));
+            }
+        }
+
+        self.filters.extend(filters);
+        Ok(())
+    }
+
+    pub async fn set_network_settings(
+        &mut self,
+        network_config: &NetworkConfig,
+    ) -> ConfigurationResult<()> {
+        if let Err(err) = network_config.validate().await {
+            log::error!("Failed to validate network settings: {err}");
+            return Err(err);
+        };
+        self.network = network_config.clone();
+        Ok(())
+    }
+
+    pub async fn set_ca_settings(&mut self, ca_config: &Ca) -> ConfigurationResult<()> {
+        if let Err(err) = ca_config.validate().await {
+            log::error!("Failed to validate ca settings: {err}");
+            return Err(err);
+        };
+        self.ca = ca_config.clone();
+        Ok(())
+    }
+
+    /// Replaces the CA, which is written to its files when they are configured.
+    pub async fn set_ca(
+        &mut self,
+        ca_certificate: &X509,
+        ca_private_key: &PKey<Private>,
+    ) -> ConfigurationResult<()> {
+        let ca_certificate_pem = ca_certificate.to_pem()?;
+        let ca_private_key_pem = ca_private_key.private_key_to_pem_pkcs8()?;
+
+        self.ca
+            .set_ca_certificate(std::str::from_utf8(&ca_certificate_pem)?)
+            .await?;
+        self.ca
+            .set_ca_private_key(std::str::from_utf8(&ca_private_key_pem)?)
+            .await?;
+
+        self.save().await
+    }
+
+    async fn new_default() -> ConfigurationResult<Self> {
+        let (x509, private_key) = crate::ca::make_ca_certificate();
+
+        let x509_pem = std::str::from_utf8(&x509.to_pem().unwrap())
+            .unwrap()
+            .to_string();
+
+        let private_key_pem = std::str::from_utf8(&private_key.private_key_to_pem_pkcs8().unwrap())
+            .unwrap()
+            .to_string();
+
+        let default_filters = DefaultFilters::new();
+        Ok(Configuration {
+            version: CONFIGURATION_VERSION,
+            filters: default_filters
+                .list()
+                .into_iter()
+                .map(|filter| filter.into())
+                .collect(),
+            ca: Ca {
+                ca_certificate: Some(x509_pem),
+                ca_certificate_path: None,
+                ca_private_key: Some(private_key_pem),
+                ca_private_key_path: None,
+            },
+            network: NetworkConfig {
+                bind_addr: "127.0.0.1".to_string(),
+                proxy_port: 8100,
+                web_port: 8200,
+                tls: false,
+                tls_cert_path: None,
+                tls_key_path: None,
+                listen_url: None,
+                web_base_path: None,
+                allowed_origins: Vec::new(),
+                allowed_hosts: Vec::new(),
+                admin_socket_path: None,
+                admin_socket_mode: None,
+                compress_api_responses: true,
+                frontend_directory: None,
+            },
+            exclusions: BTreeSet::new(),
+            custom_filters: Vec::new(),
+            proxy_authentication: ProxyAuthentication::default(),
+            block_responses: BlockResponses::default(),
+            client_identification: ClientIdentification::default(),
+            web_authentication: WebAuthentication::default(),
+            connection_prewarming: ConnectionPrewarming::default(),
+            leaf_certificates: LeafCertificates::default(),
+            upstream_tls: UpstreamTls::default(),
+            upstream_connections: UpstreamConnections::default(),
+            metrics: Metrics::default(),
+            telemetry: Telemetry::default(),
+            statistics_retention: StatisticsRetention::default(),
+            site_policies: Vec::new(),
+            circuit_breaker: CircuitBreaker::default(),
+            header_rules: Vec::new(),
+            referer_trimming: RefererTrimming::default(),
+            user_agent_rules: Vec::new(),
+            cookie_rules: CookieRules::default(),
+            ip_rules: Vec::new(),
+            threat_intelligence: ThreatIntelligence::default(),
+            safe_search: SafeSearch::default(),
+            categories: ContentCategories::default(),
+            content_rewrite_rules: Vec::new(),
+            url_rewrites: Vec::new(),
+            plugins: Plugins::default(),
+            scripts: Scripts::default(),
+            response_cache: ResponseCache::default(),
+            traffic_shaping: TrafficShaping::default(),
+            injections: Vec::new(),
+            filters_update_interval_secs: default_filters_update_interval_secs(),
+            local_filters_directory: None,
+            max_inspected_body_size: default_max_inspected_body_size(),
+            engine_shards: default_engine_shards(),
+            profiles: Vec::new(),
+            active_profile: None,
+            schedules: Vec::new(),
+            statistics_reset: None,
+            notifications: Vec::new(),
+            managed_exclusions: BTreeSet::new(),
+        })
+    }
+}
+
+pub(crate) fn get_config_file() -> PathBuf {
+    get_base_directory().unwrap().join(CONFIGURATION_FILE_NAME)
+}
+
+pub(crate) fn get_engine_cache_file() -> PathBuf {
+    get_base_directory().unwrap().join(ENGINE_CACHE_FILE_NAME)
+}
+
+pub(crate) fn get_statistics_history_file() -> PathBuf {
+    get_base_directory()
+        .unwrap()
+        .join(STATISTICS_HISTORY_FILE_NAME)
+}
+
+pub(crate) fn get_statistics_timeseries_file() -> PathBuf {
+    get_base_directory()
+        .unwrap()
+        .join(STATISTICS_TIMESERIES_FILE_NAME)
+}
+
+pub(crate) fn get_tracker_entities_file() -> PathBuf {
+    get_base_directory()
+        .unwrap()
+        .join(TRACKER_ENTITIES_FILE_NAME)
+}
+
+pub(crate) fn get_event_history_file() -> PathBuf {
+    get_base_directory().unwrap().join(EVENT_HISTORY_FILE_NAME)
+}
+
+pub(crate) fn get_audit_log_file() -> PathBuf {
+    get_base_directory().unwrap().join(AUDIT_LOG_FILE_NAME)
+}
+
+/// Logging starts before the base directory is created, the log file may have to create
+/// it.
+pub(crate) fn get_log_file() -> PathBuf {
+    get_startup_options()
+        .base_path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(CONFIGURATION_DIRECTORY_NAME))
+        .join(LOG_FILE_NAME)
+}
+
+pub(crate) fn get_certificates_directory() -> PathBuf {
+    get_base_directory()
+        .unwrap()
+        .join(CERTIFICATES_DIRECTORY_NAME)
+}
+
+pub(crate) fn get_plugins_directory() -> PathBuf {
+    get_base_directory().unwrap().join(PLUGINS_DIRECTORY_NAME)
+}
+
+pub(crate) fn get_scripts_directory() -> PathBuf {
+    get_base_directory().unwrap().join(SCRIPTS_DIRECTORY_NAME)
+}
+
+pub(crate) fn get_response_cache_directory() -> PathBuf {
+    get_base_directory()
+        .unwrap()
+        .join(RESPONSE_CACHE_DIRECTORY_NAME)
+}
+
+pub(crate) fn get_web_tls_certificate_file() -> PathBuf {
+    get_base_directory()
+        .unwrap()
+        .join(WEB_TLS_CERTIFICATE_FILE_NAME)
+}
+
+pub(crate) fn get_web_tls_key_file() -> PathBuf {
+    get_base_directory().unwrap().join(WEB_TLS_KEY_FILE_NAME)
+}
+
+fn get_base_directory() -> ConfigurationResult<PathBuf> {
+    let base_directory: PathBuf = match &get_startup_options().base_path {
+        Some(base_path) => base_path.clone(),
+        // Assume home directory
+        None => PathBuf::from(CONFIGURATION_DIRECTORY_NAME),
+    };
+    match Path::exists(&base_directory) {
+        true => Ok(base_directory),
+        false => Err(ConfigurationError::DirectoryNotFound),
+    }
+}
@@ -0,0 +1,103 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use crate::web_gui::events::{Category, Severity};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// How the body of a notification is laid out, depending on the service receiving it.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationFormat {
+    /// The event as it is sent over `/events`.
+    Json,
+    /// A Slack incoming webhook.
+    Slack,
+    /// A Discord webhook.
+    Discord,
+    /// A ntfy topic, such as `https://ntfy.sh/my-topic`.
+    Ntfy,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// A webhook events are posted to as they happen.
+pub struct NotificationTarget {
+    pub name: String,
+    #[serde(default = "default_notification_target_enabled")]
+    pub enabled: bool,
+    pub format: NotificationFormat,
+    pub url: String,
+    /// `request` for blocked requests, or the `kind` of status events. Every type of
+    /// event is notified when empty.
+    #[serde(default)]
+    pub types: Vec<String>,
+    /// Every category of event is notified when empty.
+    #[serde(default)]
+    pub categories: Vec<Category>,
+    #[serde(default = "default_notification_min_severity")]
+    pub min_severity: Severity,
+}
+
+fn default_notification_target_enabled() -> bool {
+    true
+}
+
+fn default_notification_min_severity() -> Severity {
+    Severity::Warning
+}
+
+impl NotificationTarget {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.name.is_empty() {
+            return Err(ConfigurationError::NotificationError(
+                "targets must have a name".to_string(),
+            ));
+        }
+
+        match Url::parse(&self.url) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(()),
+            _ => Err(ConfigurationError::NotificationError(format!(
+                "invalid url for target {}: {:?}",
+                self.name, self.url
+            ))),
+        }
+    }
+}
+
+impl Configuration {
+    pub async fn set_notifications(
+        &mut self,
+        mut notifications: Vec<NotificationTarget>,
+        notification_store: crate::notifications::NotificationStore,
+    ) -> ConfigurationResult<()> {
+        for target in &mut notifications {
+            target.name = target.name.trim().to_string();
+            target.url = target.url.trim().to_string();
+        }
+
+        self.notifications = notifications;
+        self.validate_notifications()?;
+
+        self.save().await?;
+
+        notification_store.replace(self.notifications.clone());
+
+        Ok(())
+    }
+
+    pub(super) fn validate_notifications(&self) -> ConfigurationResult<()> {
+        for (i, target) in self.notifications.iter().enumerate() {
+            target.validate()?;
+
+            if self.notifications[..i]
+                .iter()
+                .any(|other| other.name == target.name)
+            {
+                return Err(ConfigurationError::NotificationError(format!(
+                    "target {} is listed more than once",
+                    target.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Memory can only grow by pages of 64 KiB, a plugin needs at least one.
+const MIN_PLUGIN_MEMORY_BYTES: u64 = 64 * 1024;
+
+const MAX_PLUGIN_MEMORY_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// WebAssembly module installed in the plugins directory, as `<name>.wasm`.
+pub struct Plugin {
+    pub name: String,
+    #[serde(default = "default_plugin_enabled")]
+    pub enabled: bool,
+}
+
+fn default_plugin_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Plugins hooking into requests and responses, called in order, along with the limits
+/// each of their calls runs within.
+pub struct Plugins {
+    #[serde(default)]
+    pub plugins: Vec<Plugin>,
+    /// Instructions a hook can run, roughly, before it is aborted.
+    #[serde(default = "default_plugin_max_fuel")]
+    pub max_fuel: u64,
+    /// Memory a hook can use.
+    #[serde(default = "default_plugin_max_memory_bytes")]
+    pub max_memory_bytes: u64,
+}
+
+fn default_plugin_max_fuel() -> u64 {
+    10_000_000
+}
+
+fn default_plugin_max_memory_bytes() -> u64 {
+    16 * 1024 * 1024
+}
+
+impl Default for Plugins {
+    fn default() -> Self {
+        Self {
+            plugins: Vec::new(),
+            max_fuel: default_plugin_max_fuel(),
+            max_memory_bytes: default_plugin_max_memory_bytes(),
+        }
+    }
+}
+
+impl Plugins {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.max_fuel == 0 {
+            return Err(ConfigurationError::PluginError(
+                "hooks must be allowed some fuel".to_string(),
+            ));
+        }
+
+        if !(MIN_PLUGIN_MEMORY_BYTES..=MAX_PLUGIN_MEMORY_BYTES).contains(&self.max_memory_bytes) {
+            return Err(ConfigurationError::PluginError(format!(
+                "the memory limit must be between {} and {} bytes",
+                MIN_PLUGIN_MEMORY_BYTES, MAX_PLUGIN_MEMORY_BYTES
+            )));
+        }
+
+        let mut names = HashSet::new();
+
+        for plugin in &self.plugins {
+            if !is_valid_plugin_name(&plugin.name) {
+                return Err(ConfigurationError::PluginError(format!(
+                    "invalid name: {:?}",
+                    plugin.name
+                )));
+            }
+
+            if !names.insert(plugin.name.as_str()) {
+                return Err(ConfigurationError::PluginError(format!(
+                    "plugin {} is listed more than once",
+                    plugin.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Names are used as filenames, they are kept to characters that are safe in paths.
+pub fn is_valid_plugin_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl Configuration {
+    /// Modules of enabled plugins are loaded again, so that ones that were replaced
+    /// are picked up.
+    pub async fn set_plugins(
+        &mut self,
+        mut plugins: Plugins,
+        plugin_store: crate::proxy::plugins::PluginStore,
+    ) -> ConfigurationResult<()> {
+        for plugin in &mut plugins.plugins {
+            plugin.name = plugin.name.trim().to_string();
+        }
+        plugins.validate()?;
+
+        self.plugins = plugins;
+
+        self.save().await?;
+
+        plugin_store.replace(self.plugins.clone());
+
+        Ok(())
+    }
+}
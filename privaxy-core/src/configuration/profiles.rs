@@ -0,0 +1,170 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+/// Named set of filters, exclusions and custom filters, such as "work" or "kids", that
+/// can be switched to at runtime.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    /// Identifiers of the filters the profile enables, the others are disabled.
+    #[serde(default)]
+    pub enabled_filters: BTreeSet<Uuid>,
+    #[serde(default)]
+    pub exclusions: BTreeSet<String>,
+    #[serde(default)]
+    pub custom_filters: Vec<String>,
+}
+
+impl Configuration {
+    /// The current filters, exclusions and custom filters, as a profile.
+    fn get_current_profile(&self, name: String) -> Profile {
+        Profile {
+            name,
+            enabled_filters: self
+                .filters
+                .iter()
+                .filter(|filter| filter.enabled && !filter.managed)
+                .map(|filter| filter.id)
+                .collect(),
+            exclusions: self.exclusions.clone(),
+            custom_filters: self.custom_filters.clone(),
+        }
+    }
+
+    /// Returns the profiles, the active one reflecting the settings edited since it was
+    /// switched to.
+    pub fn get_profiles(&self) -> Vec<Profile> {
+        self.profiles
+            .iter()
+            .map(|profile| {
+                if Some(&profile.name) == self.active_profile.as_ref() {
+                    self.get_current_profile(profile.name.clone())
+                } else {
+                    profile.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Adds a profile made of the current filters, exclusions and custom filters.
+    pub async fn add_profile(&mut self, name: &str) -> ConfigurationResult<Profile> {
+        let name = name.trim();
+
+        if name.is_empty() {
+            return Err(ConfigurationError::ProfileError(
+                "a name is required".to_string(),
+            ));
+        }
+        if self.profiles.iter().any(|profile| profile.name == name) {
+            return Err(ConfigurationError::ProfileError(format!(
+                "profile {name} already exists"
+            )));
+        }
+
+        let profile = self.get_current_profile(name.to_string());
+        self.profiles.push(profile.clone());
+
+        self.save().await?;
+
+        Ok(profile)
+    }
+
+    /// Returns whether a profile with the given name existed.
+    pub async fn remove_profile(&mut self, name: &str) -> ConfigurationResult<bool> {
+        if self.active_profile.as_deref() == Some(name) {
+            return Err(ConfigurationError::ProfileError(format!(
+                "profile {name} is active"
+            )));
+        }
+
+        if self
+            .schedules
+            .iter()
+            .any(|schedule| schedule.profile == name)
+        {
+            return Err(ConfigurationError::ProfileError(format!(
+                "profile {name} is used by a schedule"
+            )));
+        }
+
+        let profiles_count = self.profiles.len();
+        self.profiles.retain(|profile| profile.name != name);
+
+        if self.profiles.len() == profiles_count {
+            return Ok(false);
+        }
+
+        self.save().await?;
+
+        Ok(true)
+    }
+
+    /// Switches to another profile, keeping the changes made to the current one. `None`
+    /// keeps the current settings without any profile being active.
+    ///
+    /// The blocking engine and the exclusions have to be updated by the caller.
+    pub async fn set_active_profile(&mut self, name: Option<&str>) -> ConfigurationResult<()> {
+        if let Some(name) = name {
+            if !self.profiles.iter().any(|profile| profile.name == name) {
+                return Err(ConfigurationError::ProfileError(format!(
+                    "profile {name} does not exist"
+                )));
+            }
+        }
+
+        if let Some(active_profile) = self.active_profile.take() {
+            let current_profile = self.get_current_profile(active_profile);
+
+            if let Some(profile) = self
+                .profiles
+                .iter_mut()
+                .find(|profile| profile.name == current_profile.name)
+            {
+                *profile = current_profile;
+            }
+        }
+
+        // Looked up once the active profile was saved, which may be the same one.
+        let profile = name.and_then(|name| {
+            self.profiles
+                .iter()
+                .find(|profile| profile.name == name)
+                .cloned()
+        });
+
+        if let Some(profile) = profile {
+            for filter in self.filters.iter_mut().filter(|filter| !filter.managed) {
+                filter.enabled = profile.enabled_filters.contains(&filter.id);
+            }
+            self.exclusions = profile.exclusions;
+            self.custom_filters = profile.custom_filters;
+            self.active_profile = Some(profile.name);
+        }
+
+        self.save().await
+    }
+
+    pub(super) fn validate_profiles(&self) -> ConfigurationResult<()> {
+        let mut names = BTreeSet::new();
+
+        for profile in &self.profiles {
+            if !names.insert(profile.name.as_str()) {
+                return Err(ConfigurationError::ProfileError(format!(
+                    "profile {} is defined more than once",
+                    profile.name
+                )));
+            }
+        }
+
+        match &self.active_profile {
+            Some(active_profile) if !names.contains(active_profile.as_str()) => {
+                Err(ConfigurationError::ProfileError(format!(
+                    "active profile {active_profile} does not exist"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
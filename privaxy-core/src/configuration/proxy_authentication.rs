@@ -0,0 +1,67 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// Authentication required from clients using the proxy
+pub struct ProxyAuthentication {
+    /// Require clients to send valid `Proxy-Authorization` credentials.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Users allowed to use the proxy.
+    #[serde(default)]
+    pub users: Vec<ProxyUser>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ProxyUser {
+    /// Name the user authenticates with.
+    pub username: String,
+    /// Argon2 hash of the user's password, as a PHC string.
+    pub password_hash: String,
+}
+
+impl ProxyUser {
+    pub fn new(username: String, password: &str) -> Self {
+        Self {
+            username,
+            password_hash: hash_password(password),
+        }
+    }
+
+    pub fn verify_password(&self, password: &str) -> bool {
+        verify_password(&self.password_hash, password)
+    }
+}
+
+impl ProxyAuthentication {
+    /// Returns the name of the user matching the given credentials, if any.
+    pub fn authenticate(&self, username: &str, password: &str) -> Option<&str> {
+        self.users
+            .iter()
+            .find(|user| user.username == username && user.verify_password(password))
+            .map(|user| user.username.as_str())
+    }
+}
+
+/// Hashes a password with Argon2 and a random salt, into a PHC string.
+pub(super) fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+/// Compares a password to a PHC string in constant time. Hashes that can't be parsed
+/// match no password.
+pub(super) fn verify_password(password_hash: &str, password: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(password_hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,70 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// HTTP cache of upstream responses, kept in the cache directory.
+pub struct ResponseCache {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Size of the cache on disk, least recently used responses are evicted past it.
+    #[serde(default = "default_response_cache_max_size_bytes")]
+    pub max_size_bytes: u64,
+    /// Larger responses are not stored.
+    #[serde(default = "default_response_cache_max_entry_size_bytes")]
+    pub max_entry_size_bytes: u64,
+}
+
+fn default_response_cache_max_size_bytes() -> u64 {
+    512 * 1024 * 1024
+}
+
+fn default_response_cache_max_entry_size_bytes() -> u64 {
+    16 * 1024 * 1024
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size_bytes: default_response_cache_max_size_bytes(),
+            max_entry_size_bytes: default_response_cache_max_entry_size_bytes(),
+        }
+    }
+}
+
+impl ResponseCache {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.max_entry_size_bytes == 0 {
+            return Err(ConfigurationError::ResponseCacheError(
+                "responses must be allowed some size".to_string(),
+            ));
+        }
+
+        if self.max_entry_size_bytes > self.max_size_bytes {
+            return Err(ConfigurationError::ResponseCacheError(
+                "responses can't be larger than the cache".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Configuration {
+    /// Responses are evicted right away when the cache is made smaller.
+    pub async fn set_response_cache(
+        &mut self,
+        response_cache: ResponseCache,
+        response_cache_store: crate::proxy::response_cache::ResponseCacheStore,
+    ) -> ConfigurationResult<()> {
+        response_cache.validate()?;
+
+        self.response_cache = response_cache;
+
+        self.save().await?;
+
+        response_cache_store.replace(self.response_cache.clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use super::{Configuration, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+/// Search engines whose SafeSearch can be enforced
+pub enum SearchEngine {
+    Google,
+    Bing,
+    #[serde(rename = "duckduckgo")]
+    DuckDuckGo,
+    /// Restricted mode, which hides mature videos and comments.
+    #[serde(rename = "youtube")]
+    YouTube,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// Requests to search engines are rewritten so that they return their SafeSearch
+/// results, whatever the preferences of their users.
+pub struct SafeSearch {
+    /// Enforce SafeSearch on every supported search engine.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Engines on which SafeSearch is, or isn't, enforced regardless of `enabled`.
+    #[serde(default)]
+    pub overrides: BTreeMap<SearchEngine, bool>,
+}
+
+impl SafeSearch {
+    pub fn is_enforced(&self, search_engine: SearchEngine) -> bool {
+        self.overrides
+            .get(&search_engine)
+            .copied()
+            .unwrap_or(self.enabled)
+    }
+}
+
+impl Configuration {
+    pub async fn set_safe_search(
+        &mut self,
+        safe_search: SafeSearch,
+        safe_search_store: crate::proxy::safe_search::SafeSearchStore,
+    ) -> ConfigurationResult<()> {
+        self.safe_search = safe_search;
+
+        self.save().await?;
+
+        safe_search_store.replace(self.safe_search.clone());
+
+        Ok(())
+    }
+}
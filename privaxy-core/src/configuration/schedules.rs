@@ -0,0 +1,336 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use crate::proxy::exclusions::LocalExclusionStore;
+use crate::statistics::Statistics;
+use crate::web_gui::events::StatusEvent;
+use chrono::{DateTime, Datelike, Local, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::Sender;
+
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Switches to a profile at the times matched by a cron rule, such as a stricter profile
+/// on weekdays at 9:00 and back to the usual one at 17:00.
+///
+/// Profiles switched to by hand are kept until the next matching time.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Schedule {
+    /// Five field cron expression, `minute hour day-of-month month day-of-week`, in local
+    /// time. For instance `0 9 * * mon-fri`.
+    pub rule: String,
+    /// Name of the profile switched to.
+    pub profile: String,
+    #[serde(default = "default_schedule_enabled")]
+    pub enabled: bool,
+}
+
+fn default_schedule_enabled() -> bool {
+    true
+}
+
+/// Parsed cron expression, each field being a bit set of the values it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CronRule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// As in cron, a day matches when either the day of the month or the day of the week
+    /// does, unless one of them is `*`.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+/// Parses a comma separated list of values, `a-b` ranges, `*` and `/step` suffixes.
+fn parse_cron_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let parse_value = |value: &str| -> Result<u32, String> {
+        let value = value.to_lowercase();
+
+        let parsed = match names.iter().position(|name| *name == value) {
+            Some(position) => position as u32 + min,
+            None => value
+                .parse::<u32>()
+                .map_err(|_| format!("invalid value {value:?}"))?,
+        };
+
+        if parsed < min || parsed > max {
+            return Err(format!("{parsed} is not between {min} and {max}"));
+        }
+
+        Ok(parsed)
+    };
+
+    let mut bits = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("invalid step {step:?}")),
+            },
+            None => (part, None),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start)?, parse_value(end)?),
+                // `5/15` is every 15 from 5 onwards.
+                None if step.is_some() => (parse_value(range)?, max),
+                None => {
+                    let value = parse_value(range)?;
+                    (value, value)
+                }
+            },
+        };
+
+        if start > end {
+            return Err(format!("invalid range {range:?}"));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+impl FromStr for CronRule {
+    type Err = ConfigurationError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let parse = || -> Result<Self, String> {
+            let fields = rule.split_whitespace().collect::<Vec<_>>();
+
+            let [minutes, hours, days, months, weekdays] = fields[..] else {
+                return Err(format!("expected 5 fields, found {}", fields.len()));
+            };
+
+            // Both 0 and 7 are sundays.
+            let mut weekdays_bits = parse_cron_field(weekdays, 0, 7, &WEEKDAY_NAMES)?;
+            if weekdays_bits & 1 << 7 != 0 {
+                weekdays_bits = (weekdays_bits | 1) & !(1 << 7);
+            }
+
+            Ok(Self {
+                minutes: parse_cron_field(minutes, 0, 59, &[])?,
+                hours: parse_cron_field(hours, 0, 23, &[])?,
+                days: parse_cron_field(days, 1, 31, &[])?,
+                months: parse_cron_field(months, 1, 12, &MONTH_NAMES)?,
+                weekdays: weekdays_bits,
+                any_day: days == "*",
+                any_weekday: weekdays == "*",
+            })
+        };
+
+        parse().map_err(|err| ConfigurationError::ScheduleError(format!("rule {rule:?}: {err}")))
+    }
+}
+
+impl CronRule {
+    /// Whether the minute `date_time` is in matches the rule.
+    pub fn matches<Tz: TimeZone>(&self, date_time: &DateTime<Tz>) -> bool {
+        let is_set = |bits: u64, value: u32| bits & 1 << value != 0;
+
+        let day_matches = is_set(self.days, date_time.day());
+        let weekday_matches = is_set(self.weekdays, date_time.weekday().num_days_from_sunday());
+
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday_matches,
+            (false, true) => day_matches,
+            (false, false) => day_matches || weekday_matches,
+        };
+
+        is_set(self.minutes, date_time.minute())
+            && is_set(self.hours, date_time.hour())
+            && is_set(self.months, date_time.month())
+            && day_matches
+    }
+}
+
+impl Configuration {
+    pub async fn set_schedules(&mut self, mut schedules: Vec<Schedule>) -> ConfigurationResult<()> {
+        for schedule in &mut schedules {
+            schedule.rule = schedule.rule.trim().to_string();
+        }
+
+        self.schedules = schedules;
+        self.validate_schedules()?;
+
+        self.save().await
+    }
+
+    pub(super) fn validate_schedules(&self) -> ConfigurationResult<()> {
+        for schedule in &self.schedules {
+            CronRule::from_str(&schedule.rule)?;
+
+            if !self
+                .profiles
+                .iter()
+                .any(|profile| profile.name == schedule.profile)
+            {
+                return Err(ConfigurationError::ScheduleError(format!(
+                    "profile {} does not exist",
+                    schedule.profile
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn validate_statistics_reset(&self) -> ConfigurationResult<()> {
+        if let Some(rule) = &self.statistics_reset {
+            CronRule::from_str(rule)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the enabled schedule matching `date_time`, the last one listed when several
+    /// do.
+    pub fn get_due_schedule<Tz: TimeZone>(&self, date_time: &DateTime<Tz>) -> Option<&Schedule> {
+        self.schedules
+            .iter()
+            .filter(|schedule| schedule.enabled)
+            .filter(|schedule| {
+                CronRule::from_str(&schedule.rule).is_ok_and(|rule| rule.matches(date_time))
+            })
+            .last()
+    }
+
+    /// Whether statistics are to be reset at `date_time`.
+    pub fn is_statistics_reset_due<Tz: TimeZone>(&self, date_time: &DateTime<Tz>) -> bool {
+        self.statistics_reset
+            .as_deref()
+            .and_then(|rule| CronRule::from_str(rule).ok())
+            .is_some_and(|rule| rule.matches(date_time))
+    }
+}
+
+/// Waits for the start of the next minute, skipping `last_checked_minute` when sleeping
+/// ends slightly before the minute changes.
+async fn wait_for_next_minute(last_checked_minute: &mut Option<i64>) -> DateTime<Local> {
+    loop {
+        let now = Local::now();
+        let elapsed_millis =
+            u64::from(now.second()) * 1000 + u64::from(now.timestamp_subsec_millis());
+        tokio::time::sleep(Duration::from_millis(
+            60_000u64.saturating_sub(elapsed_millis),
+        ))
+        .await;
+
+        let now = Local::now();
+
+        let minute = now.timestamp().div_euclid(60);
+        if *last_checked_minute != Some(minute) {
+            *last_checked_minute = Some(minute);
+
+            return now;
+        }
+    }
+}
+
+/// Switches profiles as scheduled, checking the schedules at the start of every minute.
+pub(crate) async fn apply_schedules(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    mut local_exclusion_store: LocalExclusionStore,
+    status_sender: broadcast::Sender<StatusEvent>,
+) {
+    let mut last_checked_minute = None;
+
+    loop {
+        let now = wait_for_next_minute(&mut last_checked_minute).await;
+
+        let is_due = |configuration: &Configuration| {
+            configuration
+                .get_due_schedule(&now)
+                .filter(|schedule| configuration.active_profile.as_ref() != Some(&schedule.profile))
+                .cloned()
+        };
+
+        match Configuration::read_from_home().await {
+            Ok(configuration) if is_due(&configuration).is_some() => {}
+            Ok(_) => continue,
+            Err(err) => {
+                log::error!("Unable to apply schedules: {}", err);
+                continue;
+            }
+        }
+
+        let _guard = configuration_save_lock.lock().await;
+
+        let mut configuration = match Configuration::read_from_home().await {
+            Ok(configuration) => configuration,
+            Err(err) => {
+                log::error!("Unable to apply schedules: {}", err);
+                continue;
+            }
+        };
+
+        let schedule = match is_due(&configuration) {
+            Some(schedule) => schedule,
+            None => continue,
+        };
+
+        if let Err(err) = configuration
+            .set_active_profile(Some(&schedule.profile))
+            .await
+        {
+            log::error!("Unable to apply schedule {}: {}", schedule.rule, err);
+            continue;
+        }
+
+        local_exclusion_store.replace_exclusions(configuration.get_exclusions());
+
+        if let Err(err) = configuration_updater_sender.send(configuration).await {
+            log::error!("Unable to apply schedule {}: {}", schedule.rule, err);
+            continue;
+        }
+
+        log::info!(
+            "Switched to profile {} as scheduled by {}",
+            schedule.profile,
+            schedule.rule
+        );
+
+        let _ = status_sender.send(StatusEvent::ScheduleApplied {
+            now: chrono::Utc::now(),
+            rule: schedule.rule,
+            profile: schedule.profile,
+        });
+    }
+}
+
+/// Resets statistics at the times matched by `statistics_reset`.
+pub(crate) async fn reset_statistics_as_scheduled(statistics: Statistics) {
+    let mut last_checked_minute = None;
+
+    loop {
+        let now = wait_for_next_minute(&mut last_checked_minute).await;
+
+        match Configuration::read_from_home().await {
+            Ok(configuration) if configuration.is_statistics_reset_due(&now) => {
+                crate::statistics::reset(&statistics).await;
+
+                log::info!(
+                    "Reset statistics as scheduled by {}",
+                    configuration.statistics_reset.unwrap_or_default()
+                );
+            }
+            Ok(_) => {}
+            Err(err) => log::error!("Unable to reset statistics as scheduled: {}", err),
+        }
+    }
+}
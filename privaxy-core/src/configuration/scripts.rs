@@ -0,0 +1,81 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+
+/// Lua needs some memory of its own before a script is even loaded.
+const MIN_SCRIPT_MEMORY_BYTES: u64 = 1024 * 1024;
+
+const MAX_SCRIPT_MEMORY_BYTES: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Lua scripts of the scripts directory, called for requests before they are matched
+/// against filters, along with the limits each of their calls runs within.
+pub struct Scripts {
+    #[serde(default = "default_scripts_enabled")]
+    pub enabled: bool,
+    /// Instructions a call can run before it is aborted.
+    #[serde(default = "default_script_max_instructions")]
+    pub max_instructions: u64,
+    /// Memory each Lua state of a script can use, across its calls.
+    #[serde(default = "default_script_max_memory_bytes")]
+    pub max_memory_bytes: u64,
+}
+
+fn default_scripts_enabled() -> bool {
+    true
+}
+
+fn default_script_max_instructions() -> u64 {
+    1_000_000
+}
+
+fn default_script_max_memory_bytes() -> u64 {
+    8 * 1024 * 1024
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self {
+            enabled: default_scripts_enabled(),
+            max_instructions: default_script_max_instructions(),
+            max_memory_bytes: default_script_max_memory_bytes(),
+        }
+    }
+}
+
+impl Scripts {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.max_instructions == 0 {
+            return Err(ConfigurationError::ScriptError(
+                "calls must be allowed some instructions".to_string(),
+            ));
+        }
+
+        if !(MIN_SCRIPT_MEMORY_BYTES..=MAX_SCRIPT_MEMORY_BYTES).contains(&self.max_memory_bytes) {
+            return Err(ConfigurationError::ScriptError(format!(
+                "the memory limit must be between {} and {} bytes",
+                MIN_SCRIPT_MEMORY_BYTES, MAX_SCRIPT_MEMORY_BYTES
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Configuration {
+    /// Scripts are loaded again, so that the new limits apply to them.
+    pub async fn set_scripts(
+        &mut self,
+        scripts: Scripts,
+        script_store: crate::proxy::scripts::ScriptStore,
+    ) -> ConfigurationResult<()> {
+        scripts.validate()?;
+
+        self.scripts = scripts;
+
+        self.save().await?;
+
+        script_store.replace(self.scripts.clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use super::{ConfigurationError, ConfigurationResult, ResourceType};
+use serde::{Deserialize, Serialize};
+
+/// Matches every site, with a lower precedence than policies of actual sites.
+pub const ANY_SITE: &str = "*";
+
+/// Shown in place of a filter list for requests decided by a site policy.
+pub const SITE_POLICIES_SOURCE: &str = "Site policies";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// What happens to requests a site policy applies to
+pub enum SitePolicyAction {
+    /// Leave the decision to less specific policies, then to the filters.
+    #[default]
+    Default,
+    /// Block requests, whatever the filters say.
+    Block,
+    /// Allow requests, whatever the filters say.
+    Allow,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// Actions applied to requests made by the pages of a site, per resource type,
+/// similar to uBlock Origin's dynamic filtering.
+///
+/// Policies of a site also apply to its subdomains, unless they have policies of
+/// their own.
+pub struct SitePolicy {
+    /// Host of the pages making requests, or `*` for every site.
+    pub site: String,
+    /// Applies to resource types set to `default`.
+    #[serde(default)]
+    pub all: SitePolicyAction,
+    #[serde(default)]
+    pub document: SitePolicyAction,
+    #[serde(default)]
+    pub subdocument: SitePolicyAction,
+    #[serde(default)]
+    pub script: SitePolicyAction,
+    #[serde(default)]
+    pub stylesheet: SitePolicyAction,
+    #[serde(default)]
+    pub image: SitePolicyAction,
+    #[serde(default)]
+    pub media: SitePolicyAction,
+    #[serde(default)]
+    pub font: SitePolicyAction,
+    #[serde(default)]
+    pub xhr: SitePolicyAction,
+    #[serde(default)]
+    pub other: SitePolicyAction,
+}
+
+impl SitePolicy {
+    pub fn get(&self, resource_type: ResourceType) -> SitePolicyAction {
+        match resource_type {
+            ResourceType::Document => self.document,
+            ResourceType::Subdocument => self.subdocument,
+            ResourceType::Script => self.script,
+            ResourceType::Stylesheet => self.stylesheet,
+            ResourceType::Image => self.image,
+            ResourceType::Media => self.media,
+            ResourceType::Font => self.font,
+            ResourceType::Xhr => self.xhr,
+            ResourceType::Other => self.other,
+        }
+    }
+
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        let is_valid_host = !self.site.is_empty()
+            && self
+                .site
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+        if self.site != ANY_SITE && !is_valid_host {
+            return Err(ConfigurationError::SitePolicyError(format!(
+                "invalid site: {:?}",
+                self.site
+            )));
+        }
+
+        Ok(())
+    }
+}
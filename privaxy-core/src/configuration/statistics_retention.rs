@@ -0,0 +1,62 @@
+use super::{ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+
+const MAX_MINUTES: u32 = 7 * 24 * 60;
+const MAX_HOURS: u32 = 365 * 24;
+const MAX_DAYS: u32 = 10 * 365;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// How long the time series of `/statistics/timeseries` are kept, at each resolution.
+/// Coarser resolutions can be kept longer for the same space.
+pub struct StatisticsRetention {
+    /// Number of minutes per-minute statistics are kept for.
+    #[serde(default = "default_minutes")]
+    pub minutes: u32,
+    /// Number of hours hourly statistics are kept for.
+    #[serde(default = "default_hours")]
+    pub hours: u32,
+    /// Number of days daily statistics are kept for.
+    #[serde(default = "default_days")]
+    pub days: u32,
+}
+
+fn default_minutes() -> u32 {
+    24 * 60
+}
+
+fn default_hours() -> u32 {
+    30 * 24
+}
+
+fn default_days() -> u32 {
+    365
+}
+
+impl Default for StatisticsRetention {
+    fn default() -> Self {
+        Self {
+            minutes: default_minutes(),
+            hours: default_hours(),
+            days: default_days(),
+        }
+    }
+}
+
+impl StatisticsRetention {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        for (name, value, max) in [
+            ("minutes", self.minutes, MAX_MINUTES),
+            ("hours", self.hours, MAX_HOURS),
+            ("days", self.days, MAX_DAYS),
+        ] {
+            if value == 0 || value > max {
+                return Err(ConfigurationError::StatisticsRetentionError(format!(
+                    "{} must be between 1 and {}",
+                    name, max
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
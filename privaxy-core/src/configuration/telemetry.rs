@@ -0,0 +1,67 @@
+use super::{ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Traces of the requests going through the proxy, exported to an OpenTelemetry
+/// collector to see where time is spent.
+pub struct Telemetry {
+    /// Base URL of an OTLP/HTTP collector, such as `http://localhost:4318`. Requests are
+    /// not traced when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+    /// Percentage of requests and connections traced.
+    #[serde(default = "default_sample_percent")]
+    pub sample_percent: u8,
+}
+
+fn default_service_name() -> String {
+    "privaxy".to_string()
+}
+
+fn default_sample_percent() -> u8 {
+    100
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: default_service_name(),
+            sample_percent: default_sample_percent(),
+        }
+    }
+}
+
+impl Telemetry {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if let Some(otlp_endpoint) = &self.otlp_endpoint {
+            match Url::parse(otlp_endpoint) {
+                Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+                _ => {
+                    return Err(ConfigurationError::TelemetryError(format!(
+                        "invalid OTLP endpoint: {:?}",
+                        otlp_endpoint
+                    )))
+                }
+            }
+        }
+
+        if self.sample_percent > 100 {
+            return Err(ConfigurationError::TelemetryError(
+                "sample_percent can't be more than 100".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// URL spans are posted to, following the OTLP/HTTP conventions.
+    pub(crate) fn get_traces_url(&self) -> Option<String> {
+        self.otlp_endpoint
+            .as_ref()
+            .map(|otlp_endpoint| format!("{}/v1/traces", otlp_endpoint.trim_end_matches('/')))
+    }
+}
@@ -0,0 +1,156 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::HashSet;
+use std::time::Duration;
+use url::Url;
+
+/// Feeds are published every few minutes at most, updating them more often is a waste.
+const MIN_THREAT_FEEDS_UPDATE_INTERVAL_SECS: u64 = 5 * 60;
+
+/// How dangerous the addresses of a feed are.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreatSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+/// How the entries of a feed are laid out.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreatFeedFormat {
+    /// One URL per line, such as the plain text feeds of URLhaus and OpenPhish. Only
+    /// requests for these exact URLs are blocked.
+    Urls,
+    /// One host per line, or hosts file entries. Requests to these hosts and their
+    /// subdomains are blocked.
+    Hosts,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// A list of malware or phishing addresses, downloaded separately from filter lists.
+pub struct ThreatFeed {
+    pub title: String,
+    #[serde_as(as = "DisplayFromStr")]
+    pub url: Url,
+    pub format: ThreatFeedFormat,
+    pub severity: ThreatSeverity,
+    #[serde(default = "default_threat_feed_enabled")]
+    pub enabled: bool,
+}
+
+fn default_threat_feed_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Blocking of requests to known malware and phishing addresses. Unlike filter lists,
+/// it applies whether blocking is enabled or not.
+pub struct ThreatIntelligence {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seconds between downloads of the feeds.
+    #[serde(default = "default_threat_feeds_update_interval_secs")]
+    pub update_interval_secs: u64,
+    #[serde(default = "default_threat_feeds")]
+    pub feeds: Vec<ThreatFeed>,
+}
+
+fn default_threat_feeds_update_interval_secs() -> u64 {
+    60 * 60
+}
+
+fn default_threat_feeds() -> Vec<ThreatFeed> {
+    vec![
+        ThreatFeed {
+            title: "URLhaus".to_string(),
+            url: Url::parse("https://urlhaus.abuse.ch/downloads/text_online/").unwrap(),
+            format: ThreatFeedFormat::Urls,
+            severity: ThreatSeverity::High,
+            enabled: true,
+        },
+        ThreatFeed {
+            title: "OpenPhish".to_string(),
+            url: Url::parse("https://openphish.com/feed.txt").unwrap(),
+            format: ThreatFeedFormat::Urls,
+            severity: ThreatSeverity::High,
+            enabled: true,
+        },
+    ]
+}
+
+impl Default for ThreatIntelligence {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            update_interval_secs: default_threat_feeds_update_interval_secs(),
+            feeds: default_threat_feeds(),
+        }
+    }
+}
+
+impl ThreatIntelligence {
+    pub fn update_interval(&self) -> Duration {
+        Duration::from_secs(self.update_interval_secs)
+    }
+
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.update_interval_secs < MIN_THREAT_FEEDS_UPDATE_INTERVAL_SECS {
+            return Err(ConfigurationError::ThreatIntelligenceError(format!(
+                "feeds can't be updated more often than every {} seconds",
+                MIN_THREAT_FEEDS_UPDATE_INTERVAL_SECS
+            )));
+        }
+
+        let mut titles = HashSet::new();
+
+        for feed in &self.feeds {
+            if feed.title.is_empty() {
+                return Err(ConfigurationError::ThreatIntelligenceError(
+                    "feeds must have a title".to_string(),
+                ));
+            }
+
+            if !titles.insert(feed.title.as_str()) {
+                return Err(ConfigurationError::ThreatIntelligenceError(format!(
+                    "feed titles must be unique: {}",
+                    feed.title
+                )));
+            }
+
+            if !matches!(feed.url.scheme(), "http" | "https") {
+                return Err(ConfigurationError::ThreatIntelligenceError(format!(
+                    "invalid url for feed {}: {}",
+                    feed.title, feed.url
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Configuration {
+    pub async fn set_threat_intelligence(
+        &mut self,
+        mut threat_intelligence: ThreatIntelligence,
+        threat_store: crate::threat_intelligence::ThreatStore,
+    ) -> ConfigurationResult<()> {
+        for feed in &mut threat_intelligence.feeds {
+            feed.title = feed.title.trim().to_string();
+        }
+        threat_intelligence.validate()?;
+
+        self.threat_intelligence = threat_intelligence;
+
+        self.save().await?;
+
+        threat_store.replace(self.threat_intelligence.clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use super::{is_client_listed, Configuration, ConfigurationError, ConfigurationResult, ANY_HOST};
+use serde::{Deserialize, Serialize};
+
+/// Lower rates would hold a single read of a body for minutes.
+pub const MIN_SHAPING_BYTES_PER_SECOND: u64 = 1024;
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Rate limit of the traffic of matching hosts and clients.
+///
+/// A rule of a host also applies to its subdomains. Traffic matching several rules is
+/// held to the lowest of their rates.
+pub struct ShapingRule {
+    /// Host of the requested urls and tunnels, or `*` for every host.
+    pub host: String,
+    /// IP addresses, networks or names of clients as set in the client identification
+    /// settings, or authenticated proxy users. The rule applies to every client when
+    /// empty.
+    #[serde(default)]
+    pub clients: Vec<String>,
+    pub bytes_per_second: u64,
+    /// Gives every client the rule applies to a rate of its own, rather than having
+    /// them share it.
+    #[serde(default)]
+    pub per_client: bool,
+}
+
+impl ShapingRule {
+    pub fn applies_to(&self, host: &str, client_identity: &str, user: Option<&str>) -> bool {
+        let is_matching_host = self.host == ANY_HOST
+            || host == self.host
+            || host.ends_with(&format!(".{}", self.host));
+
+        is_matching_host
+            && (self.clients.is_empty()
+                || is_client_listed(&self.clients, client_identity)
+                || user.is_some_and(|user| is_client_listed(&self.clients, user)))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// Bandwidth limits of tunnels and of the bodies of proxied requests and responses, to
+/// simulate slow networks or keep large downloads from taking the whole link.
+pub struct TrafficShaping {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Rate shared by all traffic, unlimited when not set.
+    #[serde(default)]
+    pub global_bytes_per_second: Option<u64>,
+    #[serde(default)]
+    pub rules: Vec<ShapingRule>,
+}
+
+impl TrafficShaping {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        let rates = self
+            .global_bytes_per_second
+            .iter()
+            .chain(self.rules.iter().map(|rule| &rule.bytes_per_second));
+        for rate in rates {
+            if *rate < MIN_SHAPING_BYTES_PER_SECOND {
+                return Err(ConfigurationError::TrafficShapingError(format!(
+                    "rates can't be lower than {} bytes per second",
+                    MIN_SHAPING_BYTES_PER_SECOND
+                )));
+            }
+        }
+
+        for rule in &self.rules {
+            let is_valid_host = !rule.host.is_empty()
+                && rule
+                    .host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+            if rule.host != ANY_HOST && !is_valid_host {
+                return Err(ConfigurationError::TrafficShapingError(format!(
+                    "invalid host: {:?}",
+                    rule.host
+                )));
+            }
+
+            if rule.clients.iter().any(String::is_empty) {
+                return Err(ConfigurationError::TrafficShapingError(format!(
+                    "rule of {} applies to a client without a name",
+                    rule.host
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Configuration {
+    /// Rates and what was shaped so far start over.
+    pub async fn set_traffic_shaping(
+        &mut self,
+        mut traffic_shaping: TrafficShaping,
+        traffic_shaping_store: crate::proxy::traffic_shaping::TrafficShapingStore,
+    ) -> ConfigurationResult<()> {
+        for rule in &mut traffic_shaping.rules {
+            rule.host = rule.host.trim().to_lowercase();
+            for client in &mut rule.clients {
+                *client = client.trim().to_string();
+            }
+        }
+        traffic_shaping.validate()?;
+
+        self.traffic_shaping = traffic_shaping;
+
+        self.save().await?;
+
+        traffic_shaping_store.replace(self.traffic_shaping.clone());
+
+        Ok(())
+    }
+}
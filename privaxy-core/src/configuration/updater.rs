@@ -0,0 +1,349 @@
+use super::{Filter, FilterUpdate};
+use crate::blocker::AdblockRequester;
+use crate::web_gui::events::StatusEvent;
+use futures::future::{join_all, AbortHandle, Abortable};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::Receiver;
+use tokio::sync::{self, mpsc::Sender};
+
+/// How often filters are checked for being due for an update. This is the
+/// lowest effective update interval.
+const FILTERS_UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Editors usually write a file in several steps, changes to local filters are only
+/// applied once they have settled for this long.
+const LOCAL_FILTERS_DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub struct ConfigurationUpdater {
+    filters_updater_abort_handle: AbortHandle,
+    /// Dropping the watcher stops watching local filters.
+    local_filters_watcher: Option<RecommendedWatcher>,
+    rx: Receiver<super::Configuration>,
+    pub tx: Sender<super::Configuration>,
+    http_client: reqwest::Client,
+    adblock_requester: AdblockRequester,
+    status_sender: broadcast::Sender<StatusEvent>,
+}
+
+impl ConfigurationUpdater {
+    pub(crate) async fn new(
+        configuration: super::Configuration,
+        http_client: reqwest::Client,
+        adblock_requester: AdblockRequester,
+        status_sender: broadcast::Sender<StatusEvent>,
+        tx_rx: Option<(
+            sync::mpsc::Sender<super::Configuration>,
+            sync::mpsc::Receiver<super::Configuration>,
+        )>,
+    ) -> Self {
+        let (tx, rx) = match tx_rx {
+            Some((tx, rx)) => (tx, rx),
+            None => sync::mpsc::channel(1),
+        };
+
+        let local_filters_watcher = Self::spawn_local_filters_watcher(
+            configuration.clone(),
+            adblock_requester.clone(),
+            http_client.clone(),
+            status_sender.clone(),
+        );
+
+        let filters_updater_abort_handle = Self::spawn_filters_updater(
+            configuration,
+            adblock_requester.clone(),
+            http_client.clone(),
+            status_sender.clone(),
+        );
+
+        Self {
+            filters_updater_abort_handle,
+            local_filters_watcher,
+            rx,
+            tx,
+            http_client,
+            adblock_requester,
+            status_sender,
+        }
+    }
+
+    pub(crate) fn start(mut self: Self) {
+        tokio::spawn(async move {
+            loop {
+                let mut configuration = self.rx.recv().await.unwrap();
+                self.filters_updater_abort_handle.abort();
+                // Stops watching the local lists of the previous configuration.
+                self.local_filters_watcher.take();
+
+                let filters =
+                    super::filter::get_filters_content(&mut configuration, &self.http_client).await;
+                self.adblock_requester.replace_engine(filters).await;
+                self.adblock_requester
+                    .replace_custom_engine(configuration.custom_filters.clone())
+                    .await;
+
+                self.local_filters_watcher = Self::spawn_local_filters_watcher(
+                    configuration.clone(),
+                    self.adblock_requester.clone(),
+                    self.http_client.clone(),
+                    self.status_sender.clone(),
+                );
+
+                self.filters_updater_abort_handle = Self::spawn_filters_updater(
+                    configuration,
+                    self.adblock_requester.clone(),
+                    self.http_client.clone(),
+                    self.status_sender.clone(),
+                );
+
+                log::info!("Applied new configuration");
+            }
+        });
+    }
+
+    fn spawn_filters_updater(
+        configuration: super::Configuration,
+        adblock_requester: AdblockRequester,
+        http_client: reqwest::Client,
+        status_sender: broadcast::Sender<StatusEvent>,
+    ) -> AbortHandle {
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+
+        let filters_updater = Abortable::new(
+            Self::filters_updater(configuration, adblock_requester, http_client, status_sender),
+            abort_registration,
+        );
+
+        tokio::spawn(filters_updater);
+
+        abort_handle
+    }
+
+    /// Watches the local filters and the local filters directory, recompiling the
+    /// engine when they are edited on disk.
+    ///
+    /// Returns `None` when there is nothing to watch or the watcher can't be created.
+    fn spawn_local_filters_watcher(
+        configuration: super::Configuration,
+        adblock_requester: AdblockRequester,
+        http_client: reqwest::Client,
+        status_sender: broadcast::Sender<StatusEvent>,
+    ) -> Option<RecommendedWatcher> {
+        let local_filters = configuration
+            .filters
+            .iter()
+            .filter(|filter| filter.enabled)
+            .filter_map(|filter| filter.get_local_path())
+            .collect::<HashSet<_>>();
+        let local_filters_directory = configuration.local_filters_directory.clone();
+
+        // Files are replaced rather than modified by many editors, which only the
+        // directory they are in sees.
+        let watched_directories = local_filters
+            .iter()
+            .filter_map(|path| path.parent().map(PathBuf::from))
+            .chain(local_filters_directory.clone())
+            .collect::<HashSet<_>>();
+
+        if watched_directories.is_empty() {
+            return None;
+        }
+
+        let (paths_sender, mut paths_receiver) = sync::mpsc::unbounded_channel::<PathBuf>();
+
+        let mut watcher =
+            match notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                match event {
+                    Ok(event) if event.kind.is_access() => {}
+                    Ok(event) => {
+                        for path in event.paths {
+                            let _ = paths_sender.send(path);
+                        }
+                    }
+                    Err(err) => log::error!("Unable to watch local filters: {:?}", err),
+                }
+            }) {
+                Ok(watcher) => watcher,
+                Err(err) => {
+                    log::error!("Unable to watch local filters: {:?}", err);
+                    return None;
+                }
+            };
+
+        for directory in &watched_directories {
+            if let Err(err) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+                log::error!("Unable to watch {}: {:?}", directory.display(), err);
+            }
+        }
+
+        let mut configuration = configuration;
+
+        tokio::spawn(async move {
+            while let Some(path) = paths_receiver.recv().await {
+                let is_relevant = |path: &PathBuf| {
+                    local_filters.contains(path)
+                        || (path.parent() == local_filters_directory.as_deref()
+                            && super::filter::is_local_directory_filter(path))
+                };
+
+                if !is_relevant(&path) {
+                    continue;
+                }
+
+                tokio::time::sleep(LOCAL_FILTERS_DEBOUNCE).await;
+
+                let mut changed_paths = HashSet::from([path]);
+                while let Ok(path) = paths_receiver.try_recv() {
+                    if is_relevant(&path) {
+                        changed_paths.insert(path);
+                    }
+                }
+
+                let has_directory_changed = changed_paths
+                    .iter()
+                    .any(|path| !local_filters.contains(path));
+
+                let changed_filters = configuration
+                    .get_enabled_filters()
+                    .filter(|filter| {
+                        filter
+                            .get_local_path()
+                            .is_some_and(|path| changed_paths.contains(&path))
+                    })
+                    .collect::<Vec<_>>();
+
+                let outcome = refresh_filters(changed_filters, &http_client, &status_sender).await;
+
+                if outcome.modified == 0 && !has_directory_changed {
+                    continue;
+                }
+
+                let filters =
+                    super::filter::get_filters_content(&mut configuration, &http_client).await;
+                adblock_requester.replace_engine(filters).await;
+
+                log::info!("Reloaded local filters");
+            }
+        });
+
+        Some(watcher)
+    }
+
+    async fn filters_updater(
+        mut configuration: super::Configuration,
+        adblock_requester: AdblockRequester,
+        http_client: reqwest::Client,
+        status_sender: broadcast::Sender<StatusEvent>,
+    ) {
+        let default_interval = configuration.filters_update_interval();
+        let started_at = Instant::now();
+        let mut last_updates: HashMap<String, Instant> = HashMap::new();
+
+        loop {
+            tokio::time::sleep(FILTERS_UPDATE_CHECK_INTERVAL).await;
+
+            let now = Instant::now();
+            let due_filters = configuration
+                .get_enabled_filters()
+                .filter(|filter| {
+                    let last_update = last_updates
+                        .get(&filter.file_name)
+                        .copied()
+                        .unwrap_or(started_at);
+
+                    now.duration_since(last_update) >= filter.update_interval(default_interval)
+                })
+                .collect::<Vec<_>>();
+
+            if due_filters.is_empty() {
+                continue;
+            }
+
+            // Failed updates are retried on the next interval rather than on every check.
+            for filter in &due_filters {
+                last_updates.insert(filter.file_name.clone(), now);
+            }
+
+            let outcome = refresh_filters(due_filters, &http_client, &status_sender).await;
+
+            if outcome.modified == 0 {
+                log::debug!("Filters not modified, keeping the current engine");
+                continue;
+            }
+
+            let filters =
+                super::filter::get_filters_content(&mut configuration, &http_client).await;
+            adblock_requester.replace_engine(filters).await;
+
+            log::info!("Updated filters");
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct FiltersRefreshOutcome {
+    /// Number of filters for which a new version was downloaded.
+    pub(crate) modified: usize,
+    /// Number of filters that failed to update.
+    pub(crate) failed: usize,
+}
+
+/// Downloads the latest version of the given filters, reporting progress as status events.
+pub(crate) async fn refresh_filters(
+    filters: Vec<&mut Filter>,
+    http_client: &reqwest::Client,
+    status_sender: &broadcast::Sender<StatusEvent>,
+) -> FiltersRefreshOutcome {
+    let futures = filters.into_iter().map(|filter| async move {
+        let _ = status_sender.send(StatusEvent::FilterUpdateStarted {
+            now: chrono::Utc::now(),
+            id: filter.id,
+            file_name: filter.file_name.clone(),
+            title: filter.title.clone(),
+        });
+
+        match filter.update(http_client).await {
+            Ok(update) => {
+                let modified = matches!(update, FilterUpdate::Modified(_));
+
+                let _ = status_sender.send(StatusEvent::FilterUpdateFinished {
+                    now: chrono::Utc::now(),
+                    id: filter.id,
+                    file_name: filter.file_name.clone(),
+                    title: filter.title.clone(),
+                    modified,
+                });
+
+                Ok(modified)
+            }
+            Err(err) => {
+                log::error!("Unable to update filter {}: {:?}", filter.title, err);
+
+                let _ = status_sender.send(StatusEvent::FilterUpdateFailed {
+                    now: chrono::Utc::now(),
+                    id: filter.id,
+                    file_name: filter.file_name.clone(),
+                    title: filter.title.clone(),
+                    error: err.to_string(),
+                });
+
+                Err(err)
+            }
+        }
+    });
+
+    join_all(futures).await.into_iter().fold(
+        FiltersRefreshOutcome::default(),
+        |mut outcome, result| {
+            match result {
+                Ok(true) => outcome.modified += 1,
+                Ok(false) => {}
+                Err(_) => outcome.failed += 1,
+            }
+            outcome
+        },
+    )
+}
@@ -0,0 +1,132 @@
+use super::{ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+/// Address family connections to hosts having both IPv4 and IPv6 addresses are
+/// attempted with first. The other one is raced shortly after, should the first not
+/// connect right away.
+pub enum AddressFamily {
+    /// In the order addresses are resolved in.
+    #[default]
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Local address connections to a host and its subdomains are made from.
+pub struct LocalAddressRule {
+    pub host: String,
+    pub local_address: IpAddr,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// How connections to upstream servers are made and kept, to be tuned on flaky or
+/// high latency networks. Applied when the proxy starts.
+pub struct UpstreamConnections {
+    /// Seconds to wait for connections to be established.
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Seconds to wait for the response of a server, and then for each chunk of its
+    /// body. Unbounded when not set, unless the circuit breaker bounds requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_timeout_secs: Option<u64>,
+    /// Seconds idle connections are kept open for, to be reused by later requests.
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+    /// Idle connections kept open to each host, unlimited when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_idle_connections_per_host: Option<usize>,
+    /// Seconds between TCP keepalive probes of connections, not sent when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_secs: Option<u64>,
+    #[serde(default)]
+    pub preferred_address_family: AddressFamily,
+    /// Local address connections are made from, on hosts having several addresses or
+    /// routing traffic according to its source. Addresses of the other family are
+    /// connected to from any address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_address: Option<IpAddr>,
+    /// Local addresses of connections to some hosts, the first matching rule applies.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_address_rules: Vec<LocalAddressRule>,
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    30
+}
+
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+impl Default for UpstreamConnections {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: default_connect_timeout_secs(),
+            read_timeout_secs: None,
+            pool_idle_timeout_secs: default_pool_idle_timeout_secs(),
+            max_idle_connections_per_host: None,
+            tcp_keepalive_secs: None,
+            preferred_address_family: AddressFamily::default(),
+            local_address: None,
+            local_address_rules: Vec::new(),
+        }
+    }
+}
+
+impl UpstreamConnections {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        for rule in &self.local_address_rules {
+            let is_valid_host = !rule.host.is_empty()
+                && rule
+                    .host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+            if !is_valid_host {
+                return Err(ConfigurationError::UpstreamConnectionsError(format!(
+                    "invalid host: {:?}",
+                    rule.host
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the local address connections to `host` are made from, if set.
+    pub fn get_local_address(&self, host: &str) -> Option<IpAddr> {
+        let host = host.to_lowercase();
+
+        self.local_address_rules
+            .iter()
+            .find(|rule| {
+                let rule_host = rule.host.to_lowercase();
+                host == rule_host || host.ends_with(&format!(".{}", rule_host))
+            })
+            .map(|rule| rule.local_address)
+            .or(self.local_address)
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        // Connections could never be made otherwise.
+        Duration::from_secs(self.connect_timeout_secs.max(1))
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout_secs
+            .map(|read_timeout_secs| Duration::from_secs(read_timeout_secs.max(1)))
+    }
+
+    pub fn pool_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.pool_idle_timeout_secs)
+    }
+
+    pub fn tcp_keepalive(&self) -> Option<Duration> {
+        self.tcp_keepalive_secs.map(Duration::from_secs)
+    }
+}
@@ -0,0 +1,59 @@
+use super::{Configuration, ConfigurationError, ConfigurationResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// What happens when the certificate of a server can't be verified
+pub enum UpstreamCertificatePolicy {
+    /// The request fails and the client is shown why.
+    #[default]
+    Strict,
+    /// The request goes on and the error is logged.
+    Warn,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+/// Verification of the certificates of the servers requests are forwarded to.
+pub struct UpstreamTls {
+    #[serde(default)]
+    pub policy: UpstreamCertificatePolicy,
+    /// Hosts whose certificates are accepted even when invalid, such as devices of the
+    /// local network with self-signed certificates.
+    #[serde(default)]
+    pub overrides: BTreeSet<String>,
+}
+
+impl Configuration {
+    pub async fn set_tls_overrides(
+        &mut self,
+        overrides: Vec<String>,
+        upstream_tls_store: crate::proxy::upstream_tls::UpstreamTlsStore,
+    ) -> ConfigurationResult<()> {
+        let mut normalized_overrides = BTreeSet::new();
+
+        for host in overrides {
+            let host = host.trim().to_lowercase();
+
+            if host.is_empty() {
+                continue;
+            }
+
+            if host.contains(|c: char| c.is_whitespace() || matches!(c, '/' | '@')) {
+                return Err(ConfigurationError::TlsOverrideError(format!(
+                    "\"{host}\" is not a host"
+                )));
+            }
+
+            normalized_overrides.insert(host);
+        }
+
+        self.upstream_tls.overrides = normalized_overrides;
+
+        self.save().await?;
+
+        upstream_tls_store.replace(self.upstream_tls.clone());
+
+        Ok(())
+    }
+}
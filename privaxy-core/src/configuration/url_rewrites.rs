@@ -0,0 +1,110 @@
+use super::{
+    Configuration, ConfigurationError, ConfigurationResult, CONTENT_REWRITE_REGEX_SIZE_LIMIT,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// How the URL a request is rewritten to is requested
+pub enum UrlRewriteAction {
+    /// Clients are redirected to it, such as from `reddit.com` to `old.reddit.com`.
+    #[default]
+    Redirect,
+    /// It is fetched in place of the requested one, without clients noticing.
+    Rewrite,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Replacement of the URL of matching requests, before they are sent upstream.
+///
+/// The first enabled rule matching a request applies.
+pub struct UrlRewrite {
+    /// Requested URL, or a regular expression matched against requested URLs when
+    /// `regex` is set.
+    pub pattern: String,
+    #[serde(default)]
+    pub regex: bool,
+    /// URL of matching requests. With a regular expression, only the part of the URL
+    /// it matches is replaced, `$1` or `${name}` standing for its groups.
+    pub replacement: String,
+    #[serde(default)]
+    pub action: UrlRewriteAction,
+    #[serde(default = "default_url_rewrite_enabled")]
+    pub enabled: bool,
+}
+
+fn default_url_rewrite_enabled() -> bool {
+    true
+}
+
+impl UrlRewrite {
+    pub fn validate(&self) -> ConfigurationResult<()> {
+        if self.regex {
+            self.build_regex().map_err(|err| {
+                ConfigurationError::UrlRewriteError(format!(
+                    "invalid pattern {:?}: {}",
+                    self.pattern, err
+                ))
+            })?;
+        } else {
+            parse_http_url(&self.pattern).ok_or_else(|| {
+                ConfigurationError::UrlRewriteError(format!("invalid url: {:?}", self.pattern))
+            })?;
+
+            // Replacements of regular expressions are only known once they matched.
+            parse_http_url(&self.replacement).ok_or_else(|| {
+                ConfigurationError::UrlRewriteError(format!(
+                    "invalid replacement url: {:?}",
+                    self.replacement
+                ))
+            })?;
+        }
+
+        if self.replacement.is_empty() {
+            return Err(ConfigurationError::UrlRewriteError(format!(
+                "a replacement is required for {}",
+                self.pattern
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn build_regex(&self) -> Result<regex::Regex, regex::Error> {
+        regex::RegexBuilder::new(&self.pattern)
+            .size_limit(CONTENT_REWRITE_REGEX_SIZE_LIMIT)
+            .dfa_size_limit(CONTENT_REWRITE_REGEX_SIZE_LIMIT)
+            .build()
+    }
+}
+
+impl Configuration {
+    /// Replaces every URL rewrite.
+    pub async fn set_url_rewrites(
+        &mut self,
+        mut url_rewrites: Vec<UrlRewrite>,
+        url_rewrite_store: crate::proxy::url_rewrites::UrlRewriteStore,
+    ) -> ConfigurationResult<()> {
+        for url_rewrite in &mut url_rewrites {
+            url_rewrite.pattern = url_rewrite.pattern.trim().to_string();
+            url_rewrite.replacement = url_rewrite.replacement.trim().to_string();
+            url_rewrite.validate()?;
+        }
+
+        self.url_rewrites = url_rewrites;
+
+        self.save().await?;
+
+        url_rewrite_store.replace(self.url_rewrites.clone());
+
+        Ok(())
+    }
+}
+
+/// Parses an absolute `http` or `https` URL.
+fn parse_http_url(url: &str) -> Option<Url> {
+    Url::parse(url)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+}
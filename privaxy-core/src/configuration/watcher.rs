@@ -0,0 +1,130 @@
+use super::{get_base_directory, get_config_file, Configuration, CONFIGURATION_FILE_NAME};
+use crate::proxy::exclusions::LocalExclusionStore;
+use crate::web_gui::events::StatusEvent;
+use lazy_static::lazy_static;
+use notify::{RecursiveMode, Watcher};
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::{broadcast, Notify};
+
+/// Editors and provisioning tools usually write a file in several steps, edits are only
+/// applied once they have settled for this long.
+const CONFIGURATION_FILE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+lazy_static! {
+    /// Content of the configuration file as privaxy last wrote it, to tell its own saves
+    /// apart from external edits.
+    static ref SAVED_CONFIGURATION: Mutex<Option<String>> = Mutex::new(None);
+}
+
+pub(super) fn set_saved_configuration(content: String) {
+    *SAVED_CONFIGURATION.lock().unwrap() = Some(content);
+}
+
+fn is_saved_configuration(content: &str) -> bool {
+    SAVED_CONFIGURATION.lock().unwrap().as_deref() == Some(content)
+}
+
+/// Applies edits made to the configuration file by other programs, without restarting.
+pub(crate) async fn watch_configuration_file(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    mut local_exclusion_store: LocalExclusionStore,
+    status_sender: broadcast::Sender<StatusEvent>,
+    notify_reload: Arc<Notify>,
+) {
+    let base_directory = match get_base_directory() {
+        Ok(base_directory) => base_directory,
+        Err(err) => {
+            log::error!("Unable to watch configuration file: {}", err);
+            return;
+        }
+    };
+
+    let (paths_sender, mut paths_receiver) = tokio::sync::mpsc::unbounded_channel::<PathBuf>();
+
+    let mut watcher =
+        match notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if event.kind.is_access() => {}
+            Ok(event) => {
+                for path in event.paths {
+                    let _ = paths_sender.send(path);
+                }
+            }
+            Err(err) => log::error!("Unable to watch configuration file: {:?}", err),
+        }) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::error!("Unable to watch configuration file: {:?}", err);
+                return;
+            }
+        };
+
+    // The file is replaced rather than modified by many editors, which only the directory
+    // it is in sees.
+    if let Err(err) = watcher.watch(&base_directory, RecursiveMode::NonRecursive) {
+        log::error!("Unable to watch {}: {:?}", base_directory.display(), err);
+        return;
+    }
+
+    let is_configuration_file =
+        |path: &PathBuf| path.file_name() == Some(OsStr::new(CONFIGURATION_FILE_NAME));
+
+    // Content of the last external edit, editors may write the same content several times.
+    let mut last_edit = None;
+
+    while let Some(path) = paths_receiver.recv().await {
+        if !is_configuration_file(&path) {
+            continue;
+        }
+
+        tokio::time::sleep(CONFIGURATION_FILE_DEBOUNCE).await;
+        while paths_receiver.try_recv().is_ok() {}
+
+        let guard = configuration_save_lock.lock().await;
+
+        let content = match tokio::fs::read_to_string(get_config_file()).await {
+            Ok(content) => content,
+            // The file is being replaced, the next event reloads it.
+            Err(_) => continue,
+        };
+
+        if is_saved_configuration(&content) || last_edit.as_ref() == Some(&content) {
+            continue;
+        }
+        last_edit = Some(content);
+
+        let configuration = match Configuration::read_from_home().await {
+            Ok(configuration) => configuration,
+            Err(err) => {
+                log::error!("Ignoring invalid configuration file edit: {}", err);
+
+                let _ = status_sender.send(StatusEvent::ConfigurationReloadFailed {
+                    now: chrono::Utc::now(),
+                    error: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        local_exclusion_store.replace_exclusions(configuration.get_exclusions());
+
+        if let Err(err) = configuration_updater_sender.send(configuration).await {
+            log::error!("Unable to apply configuration file edit: {}", err);
+            continue;
+        }
+        drop(guard);
+
+        // Most settings are only read when the servers start.
+        notify_reload.notify_waiters();
+
+        let _ = status_sender.send(StatusEvent::ConfigurationReloaded {
+            now: chrono::Utc::now(),
+        });
+
+        log::info!("Applied configuration file edit");
+    }
+}
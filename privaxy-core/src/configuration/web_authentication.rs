@@ -0,0 +1,53 @@
+use super::proxy_authentication::{hash_password, verify_password};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Sign-ins last a week unless configured otherwise.
+const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+/// Longer lifetimes are clamped, sign-ins are meant to expire eventually.
+pub const MAX_SESSION_TTL: Duration = Duration::from_secs(60 * 60 * 24 * 365);
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+/// Authentication required to access the web GUI and its API
+pub struct WebAuthentication {
+    /// Require signing in with a password to access the web GUI.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Argon2 hash of the web GUI password, as a PHC string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    /// Seconds after which a sign-in expires.
+    #[serde(default = "default_session_ttl_secs")]
+    pub session_ttl_secs: u64,
+}
+
+fn default_session_ttl_secs() -> u64 {
+    DEFAULT_SESSION_TTL.as_secs()
+}
+
+impl Default for WebAuthentication {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            password_hash: None,
+            session_ttl_secs: default_session_ttl_secs(),
+        }
+    }
+}
+
+impl WebAuthentication {
+    pub fn set_password(&mut self, password: &str) {
+        self.password_hash = Some(hash_password(password));
+    }
+
+    pub fn verify_password(&self, password: &str) -> bool {
+        match &self.password_hash {
+            Some(password_hash) => verify_password(password_hash, password),
+            None => false,
+        }
+    }
+
+    pub fn session_ttl(&self) -> Duration {
+        Duration::from_secs(self.session_ttl_secs).min(MAX_SESSION_TTL)
+    }
+}
@@ -0,0 +1,224 @@
+use crate::web_gui::events::{Category, Event, Severity, StatusEvent};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Number of events kept, the oldest are dropped first.
+const EVENT_HISTORY_CAPACITY: usize = 1_000;
+const EVENT_HISTORY_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Version of the fields events are sent to clients with, increased when they change
+/// in a way that breaks existing clients.
+pub(crate) const EVENT_VERSION: u32 = 1;
+
+/// An event as it is kept in the history, tagged with a `type` telling requests and
+/// status events apart.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    Request(Event),
+    Status(StatusEvent),
+}
+
+impl RecordedEvent {
+    fn get_now(&self) -> DateTime<Utc> {
+        match self {
+            RecordedEvent::Request(event) => event.now,
+            RecordedEvent::Status(status_event) => status_event.get_now(),
+        }
+    }
+
+    /// `request`, or the `kind` of status events.
+    pub(crate) fn get_type(&self) -> String {
+        match self {
+            RecordedEvent::Request(_event) => "request".to_string(),
+            RecordedEvent::Status(status_event) => status_event.get_kind(),
+        }
+    }
+
+    pub(crate) fn get_severity(&self) -> Severity {
+        match self {
+            RecordedEvent::Request(event) => event.get_severity(),
+            RecordedEvent::Status(status_event) => status_event.get_severity(),
+        }
+    }
+
+    pub(crate) fn get_category(&self) -> Category {
+        match self {
+            RecordedEvent::Request(event) => event.get_category(),
+            RecordedEvent::Status(status_event) => status_event.get_category(),
+        }
+    }
+
+    /// The host the event is about, if any.
+    pub(crate) fn get_host(&self) -> Option<String> {
+        match self {
+            RecordedEvent::Request(event) => event.get_host(),
+            RecordedEvent::Status(status_event) => status_event.get_host(),
+        }
+    }
+}
+
+/// An event as it is sent to clients, along with the version of its fields, its
+/// severity and its category.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TaggedEvent {
+    version: u32,
+    severity: Severity,
+    category: Category,
+    #[serde(flatten)]
+    event: RecordedEvent,
+}
+
+impl From<RecordedEvent> for TaggedEvent {
+    fn from(event: RecordedEvent) -> Self {
+        Self {
+            version: EVENT_VERSION,
+            severity: event.get_severity(),
+            category: event.get_category(),
+            event,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct EventHistoryState {
+    events: VecDeque<RecordedEvent>,
+    /// Whether events were recorded since the history was last saved.
+    is_modified: bool,
+}
+
+/// Recent events, kept across restarts so that they can be looked at after they
+/// happened.
+///
+/// Allowed requests are not kept, they would quickly push blocked requests and status
+/// events out of the history.
+#[derive(Debug, Clone, Default)]
+pub struct EventHistory(Arc<Mutex<EventHistoryState>>);
+
+impl EventHistory {
+    fn record(&self, event: RecordedEvent) {
+        let mut state = self.0.lock().unwrap();
+
+        if state.events.len() == EVENT_HISTORY_CAPACITY {
+            state.events.pop_front();
+        }
+
+        state.events.push_back(event);
+        state.is_modified = true;
+    }
+
+    /// Returns the events recorded after `since` that match `filter`, most recent
+    /// first.
+    pub(crate) fn query(
+        &self,
+        since: Option<DateTime<Utc>>,
+        filter: impl Fn(&RecordedEvent) -> bool,
+        limit: usize,
+    ) -> Vec<TaggedEvent> {
+        self.0
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .rev()
+            .filter(|event| since.is_none_or(|since| event.get_now() > since))
+            .filter(|event| filter(event))
+            .take(limit)
+            .cloned()
+            .map(TaggedEvent::from)
+            .collect()
+    }
+
+    /// Restores the history saved by a previous run, if any.
+    pub async fn restore(&self, path: &Path) {
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Unable to read event history: {}", err);
+                }
+                return;
+            }
+        };
+
+        match serde_json::from_slice::<VecDeque<RecordedEvent>>(&bytes) {
+            Ok(mut saved_events) => {
+                let mut state = self.0.lock().unwrap();
+
+                // Events may have been recorded before the history was restored.
+                saved_events.extend(state.events.drain(..));
+
+                while saved_events.len() > EVENT_HISTORY_CAPACITY {
+                    saved_events.pop_front();
+                }
+
+                state.events = saved_events;
+            }
+            Err(err) => log::warn!("Unable to decode event history: {}", err),
+        }
+    }
+
+    async fn save(&self, path: &Path) {
+        let serialized = {
+            let mut state = self.0.lock().unwrap();
+
+            if !state.is_modified {
+                return;
+            }
+            state.is_modified = false;
+
+            serde_json::to_vec(&state.events).unwrap()
+        };
+
+        if let Err(err) = tokio::fs::write(path, serialized).await {
+            log::warn!("Unable to save event history: {}", err);
+        }
+    }
+}
+
+/// Records blocked requests and status events as they are broadcast.
+pub(crate) async fn record_events(
+    event_history: EventHistory,
+    mut events_receiver: broadcast::Receiver<Event>,
+    mut status_receiver: broadcast::Receiver<StatusEvent>,
+) {
+    loop {
+        let recorded_event = tokio::select! {
+            event = events_receiver.recv() => match event {
+                Ok(event) if event.is_request_blocked => RecordedEvent::Request(event),
+                Ok(_event) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    log::debug!("Event history skipped {} requests", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            status_event = status_receiver.recv() => match status_event {
+                Ok(status_event) => RecordedEvent::Status(status_event),
+                Err(RecvError::Lagged(skipped)) => {
+                    log::debug!("Event history skipped {} status events", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        event_history.record(recorded_event);
+    }
+}
+
+/// Periodically saves the event history, so that it survives restarts.
+pub(crate) async fn save_periodically(event_history: EventHistory, path: PathBuf) {
+    loop {
+        tokio::time::sleep(EVENT_HISTORY_SAVE_INTERVAL).await;
+
+        event_history.save(&path).await;
+    }
+}
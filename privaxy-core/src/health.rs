@@ -0,0 +1,73 @@
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+
+/// Commit Privaxy was built from, when it was known at build time.
+const BUILD_HASH: Option<&str> = option_env!("PRIVAXY_BUILD_HASH");
+
+#[derive(Debug, Default)]
+struct HealthState {
+    /// Number of filter lists the blocking engine was built from, once it was built.
+    filter_lists: Option<usize>,
+    custom_filters: usize,
+    proxy_listening: bool,
+}
+
+/// Whether Privaxy is able to filter requests, for orchestrators to hold traffic back
+/// until it is.
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    /// The blocking engine was built from the filter lists.
+    pub engine_ready: bool,
+    pub proxy_listening: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Version {
+    pub version: &'static str,
+    pub build_hash: Option<&'static str>,
+    /// Filter lists the blocking engine was built from.
+    pub filter_lists: usize,
+    pub custom_filters: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HealthStore(Arc<RwLock<HealthState>>);
+
+impl HealthStore {
+    /// The engine keeps serving requests while it is rebuilt, it stays ready until
+    /// Privaxy stops.
+    pub(crate) fn set_engine_ready(&self, filter_lists: usize) {
+        self.0.write().unwrap().filter_lists = Some(filter_lists);
+    }
+
+    pub(crate) fn set_custom_filters(&self, custom_filters: usize) {
+        self.0.write().unwrap().custom_filters = custom_filters;
+    }
+
+    pub(crate) fn set_proxy_listening(&self, proxy_listening: bool) {
+        self.0.write().unwrap().proxy_listening = proxy_listening;
+    }
+
+    pub(crate) fn get_readiness(&self) -> Readiness {
+        let state = self.0.read().unwrap();
+        let engine_ready = state.filter_lists.is_some();
+
+        Readiness {
+            ready: engine_ready && state.proxy_listening,
+            engine_ready,
+            proxy_listening: state.proxy_listening,
+        }
+    }
+
+    pub(crate) fn get_version(&self) -> Version {
+        let state = self.0.read().unwrap();
+
+        Version {
+            version: env!("CARGO_PKG_VERSION"),
+            build_hash: BUILD_HASH,
+            filter_lists: state.filter_lists.unwrap_or_default(),
+            custom_filters: state.custom_filters,
+        }
+    }
+}
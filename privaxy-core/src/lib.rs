@@ -0,0 +1,870 @@
+//! The tracker and advertisement blocking proxy of Privaxy, with its blocking engine and
+//! web interface. The `privaxy` binary starts it with [`ProxyBuilder`], which other
+//! programs can embed it with too.
+
+use crate::blocker::AdblockRequester;
+use crate::categories::CategoryStore;
+use crate::configuration::NetworkConfig;
+use crate::event_history::EventHistory;
+use crate::health::HealthStore;
+use crate::notifications::NotificationStore;
+use crate::proxy::authentication::ProxyAuthenticationStore;
+use crate::proxy::block_responses::BlockResponsesStore;
+use crate::proxy::circuit_breaker::CircuitBreakerStore;
+use crate::proxy::client_grants::ClientGrantStore;
+use crate::proxy::client_identification::ClientIdentificationStore;
+use crate::proxy::content_rewrite::ContentRewriteStore;
+use crate::proxy::cookie_rules::CookieRulesStore;
+use crate::proxy::debug_headers::DebugHeadersStore;
+use crate::proxy::exclusions::LocalExclusionStore;
+use crate::proxy::handshake_failures::HandshakeFailureStore;
+use crate::proxy::header_rules::HeaderRulesStore;
+use crate::proxy::html_filters::HtmlFilterStore;
+use crate::proxy::injections::InjectionStore;
+use crate::proxy::ip_rules::IpRuleStore;
+use crate::proxy::plugins::PluginStore;
+use crate::proxy::proceed_tokens::ProceedTokenStore;
+use crate::proxy::response_cache::ResponseCacheStore;
+use crate::proxy::safe_search::SafeSearchStore;
+use crate::proxy::scripts::ScriptStore;
+use crate::proxy::site_policies::SitePolicyStore;
+use crate::proxy::traffic_shaping::TrafficShapingStore;
+use crate::proxy::upstream_clients::UpstreamClients;
+use crate::proxy::upstream_tls::UpstreamTlsStore;
+use crate::proxy::url_rewrites::UrlRewriteStore;
+use crate::startup::get_startup_options;
+use crate::threat_intelligence::ThreatStore;
+use crate::web_gui::sessions::SessionStore;
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::Server;
+#[cfg(feature = "web-frontend")]
+use include_dir::{include_dir, Dir};
+use proxy::exclusions;
+use reqwest::redirect::Policy;
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::broadcast;
+use tokio::sync::Notify;
+
+mod admin_socket;
+pub mod bench;
+pub mod blocker;
+mod blocker_utils;
+mod builder;
+mod ca;
+mod categories;
+mod cert;
+pub mod configuration;
+mod event_history;
+mod health;
+pub mod logging;
+mod notifications;
+mod proxy;
+mod rule_profiling;
+pub mod startup;
+pub mod statistics;
+mod system;
+mod telemetry;
+mod threat_intelligence;
+mod timeseries;
+mod tracker_entities;
+mod web_gui;
+
+pub use builder::{ProxyBuilder, StartError};
+pub use web_gui::events::{Event, StatusEvent};
+
+/// Stores of what the proxy does, kept up to date with the configuration and shared
+/// with the web interface. Replacing their contents takes effect on the next requests.
+pub mod stores {
+    pub use crate::categories::CategoryStore;
+    pub use crate::event_history::EventHistory;
+    pub use crate::health::HealthStore;
+    pub use crate::notifications::NotificationStore;
+    pub use crate::proxy::authentication::ProxyAuthenticationStore;
+    pub use crate::proxy::block_responses::BlockResponsesStore;
+    pub use crate::proxy::circuit_breaker::CircuitBreakerStore;
+    pub use crate::proxy::client_grants::ClientGrantStore;
+    pub use crate::proxy::client_identification::ClientIdentificationStore;
+    pub use crate::proxy::content_rewrite::ContentRewriteStore;
+    pub use crate::proxy::cookie_rules::CookieRulesStore;
+    pub use crate::proxy::debug_headers::DebugHeadersStore;
+    pub use crate::proxy::exclusions::LocalExclusionStore;
+    pub use crate::proxy::handshake_failures::HandshakeFailureStore;
+    pub use crate::proxy::header_rules::HeaderRulesStore;
+    pub use crate::proxy::html_filters::HtmlFilterStore;
+    pub use crate::proxy::injections::InjectionStore;
+    pub use crate::proxy::ip_rules::IpRuleStore;
+    pub use crate::proxy::plugins::PluginStore;
+    pub use crate::proxy::response_cache::ResponseCacheStore;
+    pub use crate::proxy::safe_search::SafeSearchStore;
+    pub use crate::proxy::scripts::ScriptStore;
+    pub use crate::proxy::site_policies::SitePolicyStore;
+    pub use crate::proxy::traffic_shaping::TrafficShapingStore;
+    pub use crate::proxy::upstream_tls::UpstreamTlsStore;
+    pub use crate::proxy::url_rewrites::UrlRewriteStore;
+    pub use crate::threat_intelligence::ThreatStore;
+    pub use crate::web_gui::sessions::SessionStore;
+}
+
+#[cfg(feature = "web-frontend")]
+pub const WEBAPP_FRONTEND_DIR: Dir<'_> = include_dir!("web_frontend/dist");
+
+#[derive(Debug)]
+pub struct PrivaxyServer {
+    pub ca_certificate_pem: String,
+    pub configuration_updater_sender: tokio::sync::mpsc::Sender<configuration::Configuration>,
+    pub configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    pub blocking_disabled_store: blocker::BlockingDisabledStore,
+    pub statistics: statistics::Statistics,
+    pub local_exclusion_store: exclusions::LocalExclusionStore,
+    pub proxy_authentication_store: ProxyAuthenticationStore,
+    pub block_responses_store: BlockResponsesStore,
+    pub client_identification_store: ClientIdentificationStore,
+    pub session_store: SessionStore,
+    pub debug_headers_store: DebugHeadersStore,
+    pub site_policy_store: SitePolicyStore,
+    pub client_grant_store: ClientGrantStore,
+    pub circuit_breaker_store: CircuitBreakerStore,
+    pub header_rules_store: HeaderRulesStore,
+    pub cookie_rules_store: CookieRulesStore,
+    pub ip_rule_store: IpRuleStore,
+    pub threat_store: ThreatStore,
+    pub category_store: CategoryStore,
+    pub safe_search_store: SafeSearchStore,
+    pub url_rewrite_store: UrlRewriteStore,
+    pub plugin_store: PluginStore,
+    pub script_store: ScriptStore,
+    pub response_cache_store: ResponseCacheStore,
+    pub traffic_shaping_store: TrafficShapingStore,
+    pub content_rewrite_store: ContentRewriteStore,
+    pub injection_store: InjectionStore,
+    pub notification_store: NotificationStore,
+    pub html_filter_store: HtmlFilterStore,
+    pub upstream_tls_store: UpstreamTlsStore,
+    pub handshake_failure_store: HandshakeFailureStore,
+    pub health_store: HealthStore,
+    pub event_history: EventHistory,
+    /// Matches requests against the blocking engine and replaces its filters.
+    pub adblock_requester: AdblockRequester,
+    // A Sender is required to subscribe to broadcasted messages
+    pub requests_broadcast_sender: broadcast::Sender<Event>,
+    pub status_broadcast_sender: broadcast::Sender<StatusEvent>,
+    notify_reload: Arc<Notify>,
+    notify_shutdown: Arc<Notify>,
+}
+
+impl PrivaxyServer {
+    /// Restarts the proxy and the web server with the configuration file, as SIGHUP
+    /// does.
+    pub fn reload(&self) {
+        self.notify_reload.notify_waiters();
+    }
+
+    /// Completes once SIGTERM is received, when privaxy was started to handle signals,
+    /// leaving it to the caller to stop the process.
+    pub async fn shutdown_requested(&self) {
+        self.notify_shutdown.notified().await;
+    }
+}
+
+/// What the proxy and the web server share, handed to them each time they start.
+/// Clones share the same stores.
+#[derive(Clone)]
+pub(crate) struct AppState {
+    pub(crate) cert_cache: cert::CertCache,
+    pub(crate) adblock_requester: AdblockRequester,
+    pub(crate) statistics: statistics::Statistics,
+    pub(crate) blocking_disabled_store: blocker::BlockingDisabledStore,
+    pub(crate) events_sender: broadcast::Sender<Event>,
+    pub(crate) status_sender: broadcast::Sender<StatusEvent>,
+    pub(crate) configuration_updater_sender:
+        tokio::sync::mpsc::Sender<configuration::Configuration>,
+    pub(crate) configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Notified to restart the proxy and the web server.
+    pub(crate) notify_reload: Arc<Notify>,
+    pub(crate) local_exclusion_store: LocalExclusionStore,
+    pub(crate) proxy_authentication_store: ProxyAuthenticationStore,
+    pub(crate) block_responses_store: BlockResponsesStore,
+    pub(crate) client_identification_store: ClientIdentificationStore,
+    pub(crate) session_store: SessionStore,
+    pub(crate) debug_headers_store: DebugHeadersStore,
+    pub(crate) site_policy_store: SitePolicyStore,
+    pub(crate) client_grant_store: ClientGrantStore,
+    pub(crate) circuit_breaker_store: CircuitBreakerStore,
+    pub(crate) header_rules_store: HeaderRulesStore,
+    pub(crate) cookie_rules_store: CookieRulesStore,
+    pub(crate) ip_rule_store: IpRuleStore,
+    pub(crate) threat_store: ThreatStore,
+    pub(crate) category_store: CategoryStore,
+    pub(crate) safe_search_store: SafeSearchStore,
+    pub(crate) url_rewrite_store: UrlRewriteStore,
+    pub(crate) plugin_store: PluginStore,
+    pub(crate) script_store: ScriptStore,
+    pub(crate) response_cache_store: ResponseCacheStore,
+    pub(crate) traffic_shaping_store: TrafficShapingStore,
+    pub(crate) content_rewrite_store: ContentRewriteStore,
+    pub(crate) injection_store: InjectionStore,
+    pub(crate) notification_store: NotificationStore,
+    pub(crate) html_filter_store: HtmlFilterStore,
+    pub(crate) proceed_token_store: ProceedTokenStore,
+    pub(crate) upstream_tls_store: UpstreamTlsStore,
+    pub(crate) handshake_failure_store: HandshakeFailureStore,
+    pub(crate) health_store: HealthStore,
+    pub(crate) event_history: EventHistory,
+}
+
+/// Reloads on SIGHUP and requests a shutdown on SIGTERM.
+fn handle_signals(notify_shutdown: Arc<Notify>, notify_reload: Arc<Notify>) {
+    tokio::spawn(async move {
+        let mut hup_signal =
+            signal(SignalKind::hangup()).expect("failed to set up SIGHUP signal handler");
+        let mut term_signal =
+            signal(SignalKind::terminate()).expect("failed to set up SIGTERM signal handler");
+
+        loop {
+            tokio::select! {
+                _ = hup_signal.recv() => {
+                    log::info!("Received SIGHUP signal, restarting child processes...");
+                    notify_reload.notify_waiters();
+                }
+                _ = term_signal.recv() => {
+                    log::info!("Received SIGTERM signal, shutting down gracefully...");
+                    notify_shutdown.notify_one();
+                }
+            }
+        }
+    });
+}
+
+async fn try_start_privaxy(
+    handles_signals: bool,
+    resolved_address_override: Option<std::net::IpAddr>,
+) -> Result<PrivaxyServer, StartError> {
+    system::set_started();
+
+    // We use reqwest instead of hyper's client to perform most of the proxying as it's more convenient
+    // to handle compression as well as offers a more convenient interface.
+    let client = reqwest::Client::builder()
+        .use_rustls_tls()
+        .redirect(Policy::none())
+        .no_proxy()
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .build()
+        .unwrap();
+
+    if let Err(err) = configuration::load_managed_configuration(&client).await {
+        log::error!("Unable to load managed configuration: {}", err);
+    }
+
+    let configuration = configuration::Configuration::read_from_home()
+        .await
+        .map_err(StartError::Configuration)?;
+
+    let local_exclusion_store = LocalExclusionStore::new(configuration.get_exclusions());
+
+    tokio::spawn(exclusions::expire_temporary_exclusions(
+        local_exclusion_store.clone(),
+    ));
+
+    let proxy_authentication_store =
+        ProxyAuthenticationStore::new(configuration.proxy_authentication.clone());
+
+    let block_responses_store = BlockResponsesStore::new(configuration.block_responses.clone());
+
+    let client_identification_store =
+        ClientIdentificationStore::new(configuration.client_identification.clone());
+
+    let session_store = SessionStore::new(configuration.web_authentication.clone());
+
+    let debug_headers_store = DebugHeadersStore::default();
+
+    let site_policy_store = SitePolicyStore::new(configuration.site_policies.clone());
+
+    let client_grant_store = ClientGrantStore::default();
+
+    tokio::spawn(proxy::client_grants::expire_client_grants(
+        client_grant_store.clone(),
+    ));
+
+    let circuit_breaker_store = CircuitBreakerStore::new(configuration.circuit_breaker.clone());
+
+    let header_rules_store = HeaderRulesStore::new(
+        configuration.header_rules.clone(),
+        configuration.referer_trimming.clone(),
+        configuration.user_agent_rules.clone(),
+    );
+
+    let cookie_rules_store = CookieRulesStore::new(configuration.cookie_rules.clone());
+
+    let ip_rule_store = IpRuleStore::new(configuration.ip_rules.clone())
+        .with_resolved_address_override(resolved_address_override);
+
+    let threat_store = ThreatStore::new(configuration.threat_intelligence.clone());
+
+    let category_store = CategoryStore::new(configuration.categories.clone());
+
+    let safe_search_store = SafeSearchStore::new(configuration.safe_search.clone());
+
+    let url_rewrite_store = UrlRewriteStore::new(configuration.url_rewrites.clone());
+
+    let plugin_store = PluginStore::new(configuration.plugins.clone());
+
+    let response_cache_store = ResponseCacheStore::new(configuration.response_cache.clone());
+
+    let traffic_shaping_store = TrafficShapingStore::new(configuration.traffic_shaping.clone());
+
+    let content_rewrite_store =
+        ContentRewriteStore::new(configuration.content_rewrite_rules.clone());
+
+    let injection_store = InjectionStore::new(configuration.injections.clone());
+
+    let notification_store = NotificationStore::new(configuration.notifications.clone());
+
+    let html_filter_store = HtmlFilterStore::default();
+
+    let proceed_token_store = ProceedTokenStore::default();
+
+    let upstream_tls_store = UpstreamTlsStore::new(configuration.upstream_tls.clone());
+
+    let ca_certificate = configuration
+        .ca
+        .get_ca_certificate()
+        .await
+        .map_err(StartError::CaCertificate)?;
+
+    let ca_certificate_pem = std::str::from_utf8(&ca_certificate.clone().to_pem().unwrap())
+        .unwrap()
+        .to_string();
+
+    let ca_private_key = configuration
+        .ca
+        .get_ca_private_key()
+        .await
+        .map_err(StartError::CaPrivateKey)?;
+
+    configuration
+        .ca
+        .validate()
+        .await
+        .map_err(StartError::InvalidCa)?;
+
+    let cert_cache = cert::CertCache::new(
+        ca_certificate,
+        ca_private_key,
+        configuration.leaf_certificates.clone(),
+        configuration::get_certificates_directory(),
+    );
+
+    let statistics = statistics::Statistics::new();
+
+    let statistics_history_path = configuration::get_statistics_history_file();
+    statistics.restore_history(&statistics_history_path).await;
+    tokio::spawn(statistics::save_history_periodically(
+        statistics.clone(),
+        statistics_history_path,
+    ));
+
+    let timeseries_path = configuration::get_statistics_timeseries_file();
+    statistics.timeseries.restore(&timeseries_path).await;
+    tokio::spawn(timeseries::save_periodically(
+        statistics.timeseries.clone(),
+        timeseries_path,
+    ));
+
+    let tracker_entities_path = configuration::get_tracker_entities_file();
+    statistics
+        .tracker_entities
+        .restore(&tracker_entities_path)
+        .await;
+    tokio::spawn(tracker_entities::update_periodically(
+        statistics.tracker_entities.clone(),
+        client.clone(),
+        tracker_entities_path,
+    ));
+
+    let (broadcast_tx, _broadcast_rx) = broadcast::channel(32);
+
+    let (status_tx, _status_rx) = broadcast::channel(32);
+
+    let handshake_failure_store = HandshakeFailureStore::new(status_tx.clone());
+
+    let health_store = HealthStore::default();
+
+    let event_history = EventHistory::default();
+
+    let event_history_path = configuration::get_event_history_file();
+    event_history.restore(&event_history_path).await;
+    tokio::spawn(event_history::record_events(
+        event_history.clone(),
+        broadcast_tx.subscribe(),
+        status_tx.subscribe(),
+    ));
+    tokio::spawn(event_history::save_periodically(
+        event_history.clone(),
+        event_history_path,
+    ));
+
+    tokio::spawn(notifications::send_notifications(
+        notification_store.clone(),
+        client.clone(),
+        broadcast_tx.subscribe(),
+        status_tx.subscribe(),
+    ));
+
+    tokio::spawn(threat_intelligence::update_feeds_periodically(
+        threat_store.clone(),
+        client.clone(),
+        status_tx.clone(),
+    ));
+
+    tokio::spawn(categories::update_lists_periodically(
+        category_store.clone(),
+        client.clone(),
+    ));
+
+    tokio::spawn(ca::warn_before_ca_expiry(
+        cert_cache.clone(),
+        status_tx.clone(),
+    ));
+
+    // Created once events are recorded, so that scripts failing to load are reported.
+    let script_store = ScriptStore::new(configuration.scripts.clone(), status_tx.clone());
+    tokio::spawn(proxy::scripts::watch_scripts_directory(
+        script_store.clone(),
+    ));
+
+    let blocking_disabled_store =
+        blocker::BlockingDisabledStore::new(!get_startup_options().disable_blocking);
+
+    let engine_shards = configuration.engine_shards.max(1);
+    let (blocker_senders, blocker_receivers): (Vec<_>, Vec<_>) = (0..engine_shards)
+        .map(|_| crossbeam_channel::unbounded())
+        .unzip();
+
+    let blocker_requester = AdblockRequester::new(blocker_senders);
+
+    let configuration_updater = configuration::ConfigurationUpdater::new(
+        configuration.clone(),
+        client.clone(),
+        blocker_requester.clone(),
+        status_tx.clone(),
+        None,
+    )
+    .await;
+
+    let configuration_updater_tx = configuration_updater.tx.clone();
+    configuration_updater_tx.send(configuration).await.unwrap();
+
+    configuration_updater.start();
+
+    let configuration_save_lock = Arc::new(tokio::sync::Mutex::new(()));
+
+    tokio::spawn(configuration::refresh_managed_configuration_periodically(
+        client.clone(),
+        configuration_updater_tx.clone(),
+        configuration_save_lock.clone(),
+        local_exclusion_store.clone(),
+    ));
+
+    tokio::spawn(configuration::apply_schedules(
+        configuration_updater_tx.clone(),
+        configuration_save_lock.clone(),
+        local_exclusion_store.clone(),
+        status_tx.clone(),
+    ));
+
+    tokio::spawn(configuration::reset_statistics_as_scheduled(
+        statistics.clone(),
+    ));
+
+    let notify_shutdown = Arc::new(Notify::new());
+    let notify_reload = Arc::new(Notify::new());
+    if handles_signals {
+        handle_signals(notify_shutdown.clone(), notify_reload.clone());
+    }
+
+    if !get_startup_options().no_configuration_watch {
+        tokio::spawn(configuration::watch_configuration_file(
+            configuration_updater_tx.clone(),
+            configuration_save_lock.clone(),
+            local_exclusion_store.clone(),
+            status_tx.clone(),
+            notify_reload.clone(),
+        ));
+    }
+
+    let state = AppState {
+        cert_cache,
+        adblock_requester: blocker_requester,
+        statistics,
+        blocking_disabled_store,
+        events_sender: broadcast_tx,
+        status_sender: status_tx,
+        configuration_updater_sender: configuration_updater_tx,
+        configuration_save_lock,
+        notify_reload,
+        local_exclusion_store,
+        proxy_authentication_store,
+        block_responses_store,
+        client_identification_store,
+        session_store,
+        debug_headers_store,
+        site_policy_store,
+        client_grant_store,
+        circuit_breaker_store,
+        header_rules_store,
+        cookie_rules_store,
+        ip_rule_store,
+        threat_store,
+        category_store,
+        safe_search_store,
+        url_rewrite_store,
+        plugin_store,
+        script_store,
+        response_cache_store,
+        traffic_shaping_store,
+        content_rewrite_store,
+        injection_store,
+        notification_store,
+        html_filter_store,
+        proceed_token_store,
+        upstream_tls_store,
+        handshake_failure_store,
+        health_store,
+        event_history,
+    };
+
+    let frontend_state = state.clone();
+
+    tokio::spawn(async move {
+        loop {
+            log::info!("Starting Privaxy frontend");
+            privaxy_frontend(frontend_state.clone()).await;
+            frontend_state.notify_reload.notified().await;
+            log::info!("Stopping Privaxy frontend");
+        }
+    });
+
+    let blockers = blocker::Blocker::new_shards(blocker_receivers, &state);
+    for blocker in blockers {
+        thread::spawn(move || blocker.handle_requests());
+    }
+
+    let backend_state = state.clone();
+
+    tokio::spawn(async move {
+        loop {
+            log::info!("Starting Privaxy proxy");
+            privaxy_backend(backend_state.clone()).await;
+            let cfg = read_configuration(&backend_state.configuration_save_lock).await;
+            let ca_cert = cfg.ca.get_ca_certificate().await.unwrap();
+            let ca_key = cfg.ca.get_ca_private_key().await.unwrap();
+            backend_state.cert_cache.replace_ca(ca_cert, ca_key).await;
+            backend_state
+                .cert_cache
+                .set_leaf_certificates(cfg.leaf_certificates.clone())
+                .await;
+        }
+    });
+    Ok(PrivaxyServer {
+        ca_certificate_pem,
+        configuration_updater_sender: state.configuration_updater_sender,
+        configuration_save_lock: state.configuration_save_lock,
+        blocking_disabled_store: state.blocking_disabled_store,
+        statistics: state.statistics,
+        local_exclusion_store: state.local_exclusion_store,
+        proxy_authentication_store: state.proxy_authentication_store,
+        block_responses_store: state.block_responses_store,
+        client_identification_store: state.client_identification_store,
+        session_store: state.session_store,
+        debug_headers_store: state.debug_headers_store,
+        site_policy_store: state.site_policy_store,
+        client_grant_store: state.client_grant_store,
+        circuit_breaker_store: state.circuit_breaker_store,
+        header_rules_store: state.header_rules_store,
+        cookie_rules_store: state.cookie_rules_store,
+        ip_rule_store: state.ip_rule_store,
+        threat_store: state.threat_store,
+        category_store: state.category_store,
+        safe_search_store: state.safe_search_store,
+        url_rewrite_store: state.url_rewrite_store,
+        plugin_store: state.plugin_store,
+        script_store: state.script_store,
+        response_cache_store: state.response_cache_store,
+        traffic_shaping_store: state.traffic_shaping_store,
+        content_rewrite_store: state.content_rewrite_store,
+        injection_store: state.injection_store,
+        notification_store: state.notification_store,
+        html_filter_store: state.html_filter_store,
+        upstream_tls_store: state.upstream_tls_store,
+        handshake_failure_store: state.handshake_failure_store,
+        health_store: state.health_store,
+        event_history: state.event_history,
+        adblock_requester: state.adblock_requester,
+        requests_broadcast_sender: state.events_sender,
+        status_broadcast_sender: state.status_sender,
+        notify_reload: state.notify_reload,
+        notify_shutdown,
+    })
+}
+
+async fn privaxy_frontend(state: AppState) {
+    let config = read_configuration(&state.configuration_save_lock).await;
+    let web_base_path = config.network.get_web_base_path();
+    let notify_reload = state.notify_reload.clone();
+    let frontend = web_gui::get_frontend(&state, &config.network);
+    if let Some(admin_socket_path) = config.network.get_admin_socket_path() {
+        let admin_socket_mode = config.network.get_admin_socket_mode();
+        let admin_frontend = frontend.clone();
+        let notify_reload = notify_reload.clone();
+        tokio::spawn(async move {
+            if let Err(err) = admin_socket::serve(
+                admin_frontend,
+                &admin_socket_path,
+                admin_socket_mode,
+                notify_reload,
+            )
+            .await
+            {
+                log::error!(
+                    "Failed to serve the admin API on {}: {err}",
+                    admin_socket_path.display()
+                );
+            }
+        });
+    }
+    let frontend_server = warp::serve(frontend);
+
+    // The password may have been edited on disk before a reload.
+    state
+        .session_store
+        .replace(config.web_authentication.clone());
+    state
+        .site_policy_store
+        .replace(config.site_policies.clone());
+    state
+        .statistics
+        .timeseries
+        .set_retention(config.statistics_retention.clone());
+
+    if get_startup_options().no_web_server {
+        log::info!("Web server disabled");
+        return;
+    }
+
+    let ip = get_ip_address(&config.network);
+    let web_port = get_startup_options()
+        .web_port
+        .unwrap_or(config.network.web_port);
+    let web_api_server_addr = SocketAddr::from((ip, web_port));
+    let tls_identity = if get_startup_options().tls.unwrap_or(config.network.tls) {
+        match read_web_tls_identity(&config, &state.configuration_save_lock).await {
+            Ok(tls_identity) => Some(tls_identity),
+            Err(err) => {
+                log::error!("Unable to set up TLS for the web server, serving it over HTTP: {err}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    state
+        .session_store
+        .set_secure_cookies(tls_identity.is_some());
+    if let Some((tls_cert, tls_key)) = tls_identity {
+        tokio::spawn(async move {
+            let (_, task) = frontend_server
+                .tls()
+                .cert(tls_cert)
+                .key(tls_key)
+                .bind_with_graceful_shutdown(web_api_server_addr, async move {
+                    notify_reload.clone().notified().await;
+                });
+            log::info!("Web server available at https://{web_api_server_addr}{web_base_path}");
+            log::info!("API server available at https://{web_api_server_addr}{web_base_path}api");
+
+            task.await;
+        });
+    } else {
+        tokio::spawn(async move {
+            let (_, task) =
+                frontend_server.bind_with_graceful_shutdown(web_api_server_addr, async move {
+                    let _ = notify_reload.clone().notified().await;
+                });
+            log::info!("Web server available at http://{web_api_server_addr}{web_base_path}");
+            log::info!("API server available at http://{web_api_server_addr}{web_base_path}api");
+            task.await
+        });
+    }
+}
+
+/// Reads the PEM certificate and private key of the web server, creating them from the
+/// CA when missing.
+async fn read_web_tls_identity(
+    config: &configuration::Configuration,
+    configuration_save_lock: &Arc<tokio::sync::Mutex<()>>,
+) -> configuration::ConfigurationResult<(Vec<u8>, Vec<u8>)> {
+    let lock = configuration_save_lock.lock().await;
+    let ca_certificate = config.ca.get_ca_certificate().await?;
+    let ca_private_key = config.ca.get_ca_private_key().await?;
+    drop(lock);
+    let tls_cert = config
+        .network
+        .read_or_create_tls_cert(ca_certificate, ca_private_key)
+        .await?;
+    let tls_key = config.network.get_tls_key().await?;
+
+    Ok((tls_cert.to_pem()?, tls_key.private_key_to_pem_pkcs8()?))
+}
+
+async fn read_configuration(
+    configuration_save_lock: &Arc<tokio::sync::Mutex<()>>,
+) -> configuration::Configuration {
+    let lock = configuration_save_lock.lock().await;
+    let config = configuration::Configuration::read_from_home()
+        .await
+        .unwrap();
+    drop(lock);
+    config
+}
+/// Startup options take precedence over the configuration file.
+fn get_ip_address(network_config: &NetworkConfig) -> IpAddr {
+    get_startup_options()
+        .ip_address
+        .unwrap_or_else(|| network_config.parsed_ip_address())
+}
+
+async fn privaxy_backend(state: AppState) {
+    let config = read_configuration(&state.configuration_save_lock).await;
+    let network_config = &config.network;
+    let max_inspected_body_size = config.max_inspected_body_size;
+    let upstream_connections = &config.upstream_connections;
+    let read_timeout = upstream_connections.read_timeout();
+
+    // Credentials may have been edited on disk before a reload.
+    state
+        .proxy_authentication_store
+        .replace(config.proxy_authentication.clone());
+    state
+        .block_responses_store
+        .replace(config.block_responses.clone());
+    state
+        .client_identification_store
+        .replace(config.client_identification.clone());
+    state
+        .circuit_breaker_store
+        .replace(config.circuit_breaker.clone());
+    state
+        .header_rules_store
+        .replace(config.header_rules.clone());
+    state
+        .header_rules_store
+        .replace_referer_trimming(config.referer_trimming.clone());
+    state
+        .header_rules_store
+        .replace_user_agent_rules(config.user_agent_rules.clone());
+    state
+        .cookie_rules_store
+        .replace(config.cookie_rules.clone());
+    state.ip_rule_store.replace(config.ip_rules.clone());
+    state
+        .threat_store
+        .replace(config.threat_intelligence.clone());
+    state.category_store.replace(config.categories.clone());
+    state.safe_search_store.replace(config.safe_search.clone());
+    state.url_rewrite_store.replace(config.url_rewrites.clone());
+    state.plugin_store.replace(config.plugins.clone());
+    state.script_store.replace(config.scripts.clone());
+    state
+        .response_cache_store
+        .replace(config.response_cache.clone());
+    state
+        .traffic_shaping_store
+        .replace(config.traffic_shaping.clone());
+    state
+        .content_rewrite_store
+        .replace(config.content_rewrite_rules.clone());
+    state.injection_store.replace(config.injections.clone());
+    state
+        .notification_store
+        .replace(config.notifications.clone());
+    state
+        .upstream_tls_store
+        .replace(config.upstream_tls.clone());
+    state
+        .ip_rule_store
+        .set_preferred_address_family(upstream_connections.preferred_address_family);
+    let upstream_clients = UpstreamClients::new(
+        &state.upstream_tls_store,
+        &state.ip_rule_store,
+        upstream_connections.clone(),
+    );
+    telemetry::configure(
+        &config.telemetry,
+        upstream_clients.get_default().client.clone(),
+    );
+
+    let prewarmer = tokio::spawn(proxy::prewarm::prewarm_connections(
+        upstream_clients.clone(),
+        state.statistics.clone(),
+        config.connection_prewarming.clone(),
+    ));
+
+    let health_store = state.health_store.clone();
+    let notify_reload = state.notify_reload.clone();
+
+    let context = Arc::new(proxy::ProxyContext {
+        state,
+        upstream_clients,
+        max_inspected_body_size,
+        read_timeout,
+    });
+
+    let make_service = make_service_fn(move |conn: &AddrStream| {
+        let client_ip_address = conn.remote_addr().ip();
+        let open_connection = context.state.statistics.open_connection();
+
+        let context = context.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                // Dropped along with the service once the connection closes or is upgraded.
+                let _open_connection = &open_connection;
+
+                proxy::serve_mitm_session(context.clone(), req, client_ip_address)
+            }))
+        }
+    });
+
+    let ip = get_ip_address(network_config);
+    let proxy_port = get_startup_options()
+        .proxy_port
+        .unwrap_or(network_config.proxy_port);
+    let proxy_server_addr = SocketAddr::from((ip, proxy_port));
+
+    let server = Server::bind(&proxy_server_addr)
+        .http1_preserve_header_case(true)
+        .http1_title_case_headers(true)
+        .tcp_keepalive(Some(Duration::from_secs(600)))
+        .serve(make_service)
+        .with_graceful_shutdown(async move {
+            log::info!("Proxy available at http://{}", proxy_server_addr);
+            let _ = notify_reload.clone().notified().await;
+            log::info!("Stopping Privaxy proxy");
+        });
+
+    health_store.set_proxy_listening(true);
+
+    let _ = server.await;
+
+    health_store.set_proxy_listening(false);
+    prewarmer.abort();
+}
@@ -0,0 +1,371 @@
+use crate::configuration;
+use crate::startup::get_startup_options;
+use chrono::{SecondsFormat, Utc};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::OnceCell;
+use serde_json::json;
+use std::cell::Cell;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+
+const RUST_LOG_ENV_KEY: &str = "RUST_LOG";
+const DEFAULT_LOG_LEVEL: &str = "privaxy=info";
+
+/// The log file is rotated when it grows past this size, in bytes.
+const MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// Number of rotated log files kept, `privaxy.log.1` being the most recent.
+const ROTATED_LOG_FILES: u32 = 5;
+
+#[cfg(unix)]
+const SYSLOG_SOCKET_PATH: &str = "/dev/log";
+/// Logs are sent to syslog with the `daemon` facility.
+#[cfg(unix)]
+const SYSLOG_FACILITY: u8 = 3;
+
+static LOGGER: OnceCell<Logger> = OnceCell::new();
+
+tokio::task_local! {
+    static REQUEST_ID: Cell<Option<u64>>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, for log shippers.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown log format {:?}, expected text or json", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogSink {
+    Stdout,
+    /// A file that is rotated as it grows.
+    File,
+    /// The local syslog socket, which journald also listens on.
+    Syslog,
+}
+
+impl FromStr for LogSink {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stdout" => Ok(Self::Stdout),
+            "file" => Ok(Self::File),
+            "syslog" => Ok(Self::Syslog),
+            _ => Err(format!(
+                "unknown log sink {:?}, expected stdout, file or syslog",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LogSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogSink::Stdout => "stdout",
+            LogSink::File => "file",
+            LogSink::Syslog => "syslog",
+        };
+
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid log level {0:?}")]
+pub struct InvalidLogLevel(String);
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self { path, file, size })
+    }
+
+    fn get_rotated_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+
+        path.into()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for index in (1..ROTATED_LOG_FILES).rev() {
+            let rotated_path = self.get_rotated_path(index);
+
+            if rotated_path.exists() {
+                std::fs::rename(rotated_path, self.get_rotated_path(index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.get_rotated_path(1))?;
+
+        *self = Self::open(self.path.clone())?;
+
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size + line.len() as u64 + 1 > MAX_LOG_FILE_SIZE && self.size > 0 {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+
+        Ok(())
+    }
+}
+
+enum Sink {
+    Stdout,
+    File(Mutex<RotatingFile>),
+    #[cfg(unix)]
+    Syslog(std::os::unix::net::UnixDatagram),
+}
+
+impl Sink {
+    fn open(log_sink: LogSink) -> std::io::Result<Self> {
+        match log_sink {
+            LogSink::Stdout => Ok(Sink::Stdout),
+            LogSink::File => {
+                let path = get_startup_options()
+                    .log_file
+                    .clone()
+                    .unwrap_or_else(configuration::get_log_file);
+
+                Ok(Sink::File(Mutex::new(RotatingFile::open(path)?)))
+            }
+            #[cfg(unix)]
+            LogSink::Syslog => {
+                let socket = std::os::unix::net::UnixDatagram::unbound()?;
+                socket.connect(SYSLOG_SOCKET_PATH)?;
+
+                Ok(Sink::Syslog(socket))
+            }
+            #[cfg(not(unix))]
+            LogSink::Syslog => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "syslog is only available on unix",
+            )),
+        }
+    }
+
+    fn write(&self, level: Level, line: &str) {
+        // Failing to log can't be logged, the line is dropped.
+        let _result = match self {
+            Sink::Stdout => writeln!(std::io::stdout().lock(), "{}", line),
+            Sink::File(file) => file.lock().unwrap().write_line(line),
+            #[cfg(unix)]
+            Sink::Syslog(socket) => {
+                let severity = match level {
+                    Level::Error => 3,
+                    Level::Warn => 4,
+                    Level::Info => 6,
+                    Level::Debug | Level::Trace => 7,
+                };
+
+                socket
+                    .send(
+                        format!(
+                            "<{}>privaxy[{}]: {}",
+                            SYSLOG_FACILITY * 8 + severity,
+                            std::process::id(),
+                            line
+                        )
+                        .as_bytes(),
+                    )
+                    .map(|_sent| ())
+            }
+        };
+    }
+}
+
+struct Logger {
+    /// The filter as it was given, such as `privaxy=debug`.
+    level: RwLock<String>,
+    filter: RwLock<env_filter::Filter>,
+    format: LogFormat,
+    sinks: Vec<Sink>,
+}
+
+impl Logger {
+    fn format(&self, record: &Record) -> String {
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let request_id = REQUEST_ID.try_with(Cell::get).ok().flatten();
+
+        match self.format {
+            LogFormat::Text => {
+                let request_id = request_id
+                    .map(|request_id| format!(" request={}", request_id))
+                    .unwrap_or_default();
+
+                format!(
+                    "[{} {:<5} {}{}] {}",
+                    timestamp,
+                    record.level(),
+                    record.target(),
+                    request_id,
+                    record.args()
+                )
+            }
+            LogFormat::Json => json!({
+                "timestamp": timestamp,
+                "level": record.level().as_str().to_lowercase(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+                "request_id": request_id,
+            })
+            .to_string(),
+        }
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.read().unwrap().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.read().unwrap().matches(record) {
+            return;
+        }
+
+        let line = self.format(record);
+
+        for sink in &self.sinks {
+            sink.write(record.level(), &line);
+        }
+    }
+
+    fn flush(&self) {
+        let _result = std::io::stdout().flush();
+    }
+}
+
+/// Builds a filter, such as `info` or `privaxy=debug,hyper=warn`, refusing levels that
+/// are not levels rather than ignoring them.
+fn get_filter(level: &str) -> Result<env_filter::Filter, InvalidLogLevel> {
+    let is_valid = level
+        .split(',')
+        .all(|directive| match directive.split_once('=') {
+            Some((_module, level)) => LevelFilter::from_str(level.trim()).is_ok(),
+            None => !directive.contains('/'),
+        });
+
+    if !is_valid {
+        return Err(InvalidLogLevel(level.to_string()));
+    }
+
+    Ok(env_filter::Builder::new().parse(level).build())
+}
+
+/// Sets up logging according to the startup options, which must be set beforehand.
+pub fn init() {
+    let startup_options = get_startup_options();
+
+    let level = startup_options
+        .log_level
+        .clone()
+        .or_else(|| std::env::var(RUST_LOG_ENV_KEY).ok())
+        .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string());
+    let (level, filter) = match get_filter(&level) {
+        Ok(filter) => (level, filter),
+        Err(err) => {
+            eprintln!("{}, using {}", err, DEFAULT_LOG_LEVEL);
+            (
+                DEFAULT_LOG_LEVEL.to_string(),
+                get_filter(DEFAULT_LOG_LEVEL).unwrap(),
+            )
+        }
+    };
+
+    let log_sinks = if startup_options.log_sinks.is_empty() {
+        vec![LogSink::Stdout]
+    } else {
+        startup_options.log_sinks.clone()
+    };
+    let sinks = log_sinks
+        .into_iter()
+        .filter_map(|log_sink| match Sink::open(log_sink) {
+            Ok(sink) => Some(sink),
+            Err(err) => {
+                eprintln!("Unable to log to {}: {}", log_sink, err);
+                None
+            }
+        })
+        .collect();
+
+    let max_level = filter.filter();
+    let logger = LOGGER.get_or_init(|| Logger {
+        level: RwLock::new(level),
+        filter: RwLock::new(filter),
+        format: startup_options.log_format.unwrap_or_default(),
+        sinks,
+    });
+
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Returns the filter logs are currently written with.
+pub(crate) fn get_log_level() -> String {
+    LOGGER
+        .get()
+        .map(|logger| logger.level.read().unwrap().clone())
+        .unwrap_or_default()
+}
+
+/// Replaces the filter logs are written with, until privaxy restarts.
+pub(crate) fn set_log_level(level: &str) -> Result<(), InvalidLogLevel> {
+    let filter = get_filter(level)?;
+    let max_level = filter.filter();
+
+    if let Some(logger) = LOGGER.get() {
+        *logger.filter.write().unwrap() = filter;
+        *logger.level.write().unwrap() = level.to_string();
+        log::set_max_level(max_level);
+    }
+
+    Ok(())
+}
+
+/// Runs `future` with the logs it writes tagged with the id of the request it serves,
+/// once the id is set with [`set_request_id`].
+pub(crate) async fn with_request_id<F: Future>(future: F) -> F::Output {
+    REQUEST_ID.scope(Cell::new(None), future).await
+}
+
+pub(crate) fn set_request_id(request_id: u64) {
+    let _result = REQUEST_ID.try_with(|id| id.set(Some(request_id)));
+}
@@ -0,0 +1,237 @@
+use crate::configuration::{NotificationFormat, NotificationTarget};
+use crate::event_history::{RecordedEvent, TaggedEvent};
+use crate::web_gui::events::{Event, Severity, StatusEvent};
+use reqwest::StatusCode;
+use serde_json::json;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Deliveries that keep failing are given up after this many attempts.
+const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+/// Delay before the first retry, doubled after every failed attempt.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct NotificationStore(Arc<RwLock<Vec<NotificationTarget>>>);
+
+impl NotificationStore {
+    pub fn new(notifications: Vec<NotificationTarget>) -> Self {
+        Self(Arc::new(RwLock::new(notifications)))
+    }
+
+    pub fn replace(&self, notifications: Vec<NotificationTarget>) {
+        *self.0.write().unwrap() = notifications;
+    }
+
+    /// Returns the enabled targets `event` has to be posted to.
+    fn get_targets(&self, event: &RecordedEvent) -> Vec<NotificationTarget> {
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|target| target.enabled)
+            .filter(|target| target.types.is_empty() || target.types.contains(&event.get_type()))
+            .filter(|target| {
+                target.categories.is_empty() || target.categories.contains(&event.get_category())
+            })
+            .filter(|target| event.get_severity() >= target.min_severity)
+            .cloned()
+            .collect()
+    }
+}
+
+/// A single line describing the event, for chat services.
+fn get_summary(event: &RecordedEvent) -> String {
+    match event {
+        RecordedEvent::Request(Event {
+            method,
+            url,
+            filter,
+            threat,
+            category,
+            script,
+            ..
+        }) => match (threat, category, script, filter) {
+            (Some(threat), _, _, _) => format!(
+                "Blocked {} {}, listed as a threat by {}",
+                method, url, threat.feed
+            ),
+            (None, Some(category), _, _) => {
+                format!("Blocked {} {}, in category {}", method, url, category)
+            }
+            (None, None, Some(script), _) => {
+                format!("Blocked {} {} with script {}", method, url, script.script)
+            }
+            (None, None, None, Some(filter)) => {
+                format!("Blocked {} {} with {}", method, url, filter.rule)
+            }
+            (None, None, None, None) => format!("Blocked {} {}", method, url),
+        },
+        RecordedEvent::Status(status_event) => match status_event {
+            StatusEvent::EngineLoading { .. } => "Blocking engine is loading".to_string(),
+            StatusEvent::EngineProgress {
+                parsed_lists,
+                lists,
+                ..
+            } => format!(
+                "Blocking engine parsed {} of {} filter lists",
+                parsed_lists, lists
+            ),
+            StatusEvent::EngineReady { duration_ms, .. } => {
+                format!("Blocking engine is ready after {} ms", duration_ms)
+            }
+            StatusEvent::FilterUpdateStarted { title, .. } => {
+                format!("Updating filter list {}", title)
+            }
+            StatusEvent::FilterUpdateFinished {
+                title, modified, ..
+            } => {
+                if *modified {
+                    format!("Filter list {} was updated", title)
+                } else {
+                    format!("Filter list {} is up to date", title)
+                }
+            }
+            StatusEvent::FilterUpdateFailed { title, error, .. } => {
+                format!("Unable to update filter list {}: {}", title, error)
+            }
+            StatusEvent::ConfigurationReloaded { .. } => "Configuration was reloaded".to_string(),
+            StatusEvent::ConfigurationReloadFailed { error, .. } => {
+                format!("Unable to reload the configuration: {}", error)
+            }
+            StatusEvent::CaExpiring { expires_at, .. } => {
+                format!(
+                    "CA certificate expires on {}",
+                    expires_at.format("%Y-%m-%d")
+                )
+            }
+            StatusEvent::ScheduleApplied { rule, profile, .. } => {
+                format!("Schedule {} switched to profile {}", rule, profile)
+            }
+            StatusEvent::ExclusionSuggested {
+                host,
+                client,
+                failures,
+                ..
+            } => format!(
+                "{} failed {} TLS handshakes with {}, it may have to be excluded",
+                client, failures, host
+            ),
+            StatusEvent::ThreatFeedUpdated { title, entries, .. } => {
+                format!("Threat feed {} was updated with {} entries", title, entries)
+            }
+            StatusEvent::ThreatFeedUpdateFailed { title, error, .. } => {
+                format!("Unable to update threat feed {}: {}", title, error)
+            }
+            StatusEvent::ScriptsReloaded { loaded, .. } => {
+                format!("Loaded {} scripts", loaded)
+            }
+            StatusEvent::ScriptFailed { name, error, .. } => {
+                format!("Script {} failed: {}", name, error)
+            }
+        },
+    }
+}
+
+fn get_request(
+    client: &reqwest::Client,
+    target: &NotificationTarget,
+    event: &RecordedEvent,
+) -> reqwest::RequestBuilder {
+    let request = client.post(&target.url).timeout(DELIVERY_TIMEOUT);
+
+    match target.format {
+        NotificationFormat::Json => request.json(&TaggedEvent::from(event.clone())),
+        NotificationFormat::Slack => request.json(&json!({ "text": get_summary(event) })),
+        NotificationFormat::Discord => request.json(&json!({ "content": get_summary(event) })),
+        NotificationFormat::Ntfy => {
+            let priority = match event.get_severity() {
+                Severity::Error => "high",
+                Severity::Warning => "default",
+                Severity::Debug | Severity::Info => "low",
+            };
+            let category = serde_json::to_value(event.get_category()).unwrap();
+
+            request
+                .header("Title", "Privaxy")
+                .header("Priority", priority)
+                .header("Tags", category.as_str().unwrap_or_default())
+                .body(get_summary(event))
+        }
+    }
+}
+
+/// Posts `event` to `target`, retrying with an increasing delay when it fails.
+async fn deliver(client: reqwest::Client, target: NotificationTarget, event: RecordedEvent) {
+    let mut retry_delay = FIRST_RETRY_DELAY;
+
+    for attempt in 1..=MAX_DELIVERY_ATTEMPTS {
+        let err = match get_request(&client, &target, &event)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(_response) => return,
+            Err(err) => err,
+        };
+
+        // Requests the target rejects would be rejected again.
+        let is_retryable = err.status().is_none_or(|status| {
+            status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+        });
+
+        if !is_retryable || attempt == MAX_DELIVERY_ATTEMPTS {
+            log::warn!(
+                "Giving up notifying {} after attempt {}: {}",
+                target.name,
+                attempt,
+                err
+            );
+            return;
+        }
+
+        log::debug!("Unable to notify {}, retrying: {}", target.name, err);
+
+        tokio::time::sleep(retry_delay).await;
+        retry_delay *= 2;
+    }
+}
+
+/// Posts blocked requests and status events to the targets that are interested in
+/// them, as they are broadcast.
+pub(crate) async fn send_notifications(
+    notification_store: NotificationStore,
+    client: reqwest::Client,
+    mut events_receiver: broadcast::Receiver<Event>,
+    mut status_receiver: broadcast::Receiver<StatusEvent>,
+) {
+    loop {
+        let event = tokio::select! {
+            event = events_receiver.recv() => match event {
+                Ok(event) if event.is_request_blocked => RecordedEvent::Request(event),
+                Ok(_event) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    log::debug!("Notifications skipped {} requests", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            status_event = status_receiver.recv() => match status_event {
+                Ok(status_event) => RecordedEvent::Status(status_event),
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Notifications skipped {} status events", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        // Deliveries are retried independently, a slow target doesn't hold up the others.
+        for target in notification_store.get_targets(&event) {
+            tokio::spawn(deliver(client.clone(), target, event.clone()));
+        }
+    }
+}
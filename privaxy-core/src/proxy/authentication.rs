@@ -0,0 +1,212 @@
+use crate::configuration::ProxyAuthentication;
+use base64::{engine::general_purpose, Engine};
+use hyper::{http, Body, Request, Response};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tokio::sync::Semaphore;
+
+const PROXY_AUTHENTICATION_REALM: &str = "Privaxy";
+/// Verified credentials remembered before starting over, hashing passwords is slow on
+/// purpose.
+const MAX_VERIFIED_CREDENTIALS: usize = 1_024;
+/// Rejected credentials remembered before starting over, so that a client repeating wrong
+/// credentials doesn't get a password hashed on every request.
+const MAX_REJECTED_CREDENTIALS: usize = 1_024;
+/// Passwords hashed at once. Verifications beyond that wait for a permit instead of
+/// taking over the blocking thread pool.
+const MAX_CONCURRENT_VERIFICATIONS: usize = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Authentication {
+    /// Proxy authentication is disabled, anyone may use the proxy.
+    NotRequired,
+    /// Credentials were valid for this user.
+    Authenticated(String),
+    /// Credentials were missing or invalid.
+    Rejected,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProxyAuthenticationStore {
+    proxy_authentication: Arc<RwLock<Arc<ProxyAuthentication>>>,
+    /// Users of the `Proxy-Authorization` values that were verified, keyed by their
+    /// SHA-256 digest, so that the password of a client is only hashed once.
+    verified_credentials: Arc<RwLock<HashMap<[u8; 32], String>>>,
+    /// Digests of the `Proxy-Authorization` values that were rejected.
+    rejected_credentials: Arc<RwLock<HashSet<[u8; 32]>>>,
+    verification_permits: Arc<Semaphore>,
+}
+
+impl ProxyAuthenticationStore {
+    pub fn new(proxy_authentication: ProxyAuthentication) -> Self {
+        Self {
+            proxy_authentication: Arc::new(RwLock::new(Arc::new(proxy_authentication))),
+            verified_credentials: Arc::new(RwLock::new(HashMap::new())),
+            rejected_credentials: Arc::new(RwLock::new(HashSet::new())),
+            verification_permits: Arc::new(Semaphore::new(MAX_CONCURRENT_VERIFICATIONS)),
+        }
+    }
+
+    pub fn replace(&self, proxy_authentication: ProxyAuthentication) {
+        let mut current_proxy_authentication = self.proxy_authentication.write().unwrap();
+
+        *current_proxy_authentication = Arc::new(proxy_authentication);
+        // Users may have been added, removed or their passwords changed.
+        self.verified_credentials.write().unwrap().clear();
+        self.rejected_credentials.write().unwrap().clear();
+    }
+
+    pub(crate) async fn authenticate(&self, request: &Request<Body>) -> Authentication {
+        let proxy_authentication = self.proxy_authentication.read().unwrap().clone();
+
+        if !proxy_authentication.enabled {
+            return Authentication::NotRequired;
+        }
+
+        let header_value = match request
+            .headers()
+            .get(http::header::PROXY_AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(header_value) => header_value,
+            None => return Authentication::Rejected,
+        };
+
+        let digest: [u8; 32] = Sha256::digest(header_value.as_bytes()).into();
+
+        if let Some(username) = self.verified_credentials.read().unwrap().get(&digest) {
+            return Authentication::Authenticated(username.clone());
+        }
+
+        if self.rejected_credentials.read().unwrap().contains(&digest) {
+            return Authentication::Rejected;
+        }
+
+        let (username, password) = match parse_basic_credentials(header_value) {
+            Some(credentials) => credentials,
+            None => return Authentication::Rejected,
+        };
+
+        // The semaphore is never closed.
+        let _permit = self.verification_permits.acquire().await.unwrap();
+
+        // Argon2 is slow on purpose, it must not hold up the other requests of a worker.
+        let authenticated_username = {
+            let proxy_authentication = proxy_authentication.clone();
+            let username = username.clone();
+
+            tokio::task::spawn_blocking(move || {
+                proxy_authentication
+                    .authenticate(&username, &password)
+                    .map(str::to_string)
+            })
+            .await
+            .unwrap()
+        };
+
+        // Remember the outcome only if the users weren't replaced in the meantime.
+        let current_proxy_authentication = self.proxy_authentication.read().unwrap();
+        let is_current = Arc::ptr_eq(&current_proxy_authentication, &proxy_authentication);
+
+        match authenticated_username {
+            Some(username) => {
+                if is_current {
+                    let mut verified_credentials = self.verified_credentials.write().unwrap();
+
+                    if verified_credentials.len() >= MAX_VERIFIED_CREDENTIALS {
+                        verified_credentials.clear();
+                    }
+                    verified_credentials.insert(digest, username.clone());
+                }
+
+                Authentication::Authenticated(username)
+            }
+            None => {
+                log::debug!("Rejected proxy credentials for user: {}", username);
+
+                if is_current {
+                    let mut rejected_credentials = self.rejected_credentials.write().unwrap();
+
+                    if rejected_credentials.len() >= MAX_REJECTED_CREDENTIALS {
+                        rejected_credentials.clear();
+                    }
+                    rejected_credentials.insert(digest);
+                }
+
+                Authentication::Rejected
+            }
+        }
+    }
+}
+
+/// Decodes the user and password out of a `Basic` authorization header value.
+fn parse_basic_credentials(header_value: &str) -> Option<(String, String)> {
+    let (scheme, encoded_credentials) = header_value.trim().split_once(' ')?;
+
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+
+    let decoded_credentials = general_purpose::STANDARD
+        .decode(encoded_credentials.trim())
+        .ok()?;
+    let decoded_credentials = String::from_utf8(decoded_credentials).ok()?;
+
+    let (username, password) = decoded_credentials.split_once(':')?;
+
+    Some((username.to_string(), password.to_string()))
+}
+
+pub(crate) fn get_proxy_authentication_required_response() -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = http::StatusCode::PROXY_AUTHENTICATION_REQUIRED;
+    response.headers_mut().insert(
+        http::header::PROXY_AUTHENTICATE,
+        http::HeaderValue::from_str(&format!(
+            "Basic realm=\"{}\", charset=\"UTF-8\"",
+            PROXY_AUTHENTICATION_REALM
+        ))
+        .unwrap(),
+    );
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(credentials: &str) -> String {
+        general_purpose::STANDARD.encode(credentials)
+    }
+
+    #[test]
+    fn basic_credentials_are_decoded() {
+        assert_eq!(
+            parse_basic_credentials(&format!("Basic {}", encode("alice:pass:word"))),
+            Some(("alice".to_string(), "pass:word".to_string()))
+        );
+    }
+
+    #[test]
+    fn malformed_base64_is_rejected() {
+        assert_eq!(parse_basic_credentials("Basic not*base64"), None);
+    }
+
+    #[test]
+    fn credentials_without_separator_are_rejected() {
+        assert_eq!(
+            parse_basic_credentials(&format!("Basic {}", encode("alice"))),
+            None
+        );
+    }
+
+    #[test]
+    fn other_schemes_are_rejected() {
+        assert_eq!(
+            parse_basic_credentials(&format!("Bearer {}", encode("alice:password"))),
+            None
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::configuration::{BlockResponse, BlockResponses, ResourceType};
+use hyper::{Body, Request};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone)]
+pub struct BlockResponsesStore(Arc<RwLock<BlockResponses>>);
+
+impl BlockResponsesStore {
+    pub fn new(block_responses: BlockResponses) -> Self {
+        Self(Arc::new(RwLock::new(block_responses)))
+    }
+
+    pub fn replace(&self, block_responses: BlockResponses) {
+        *self.0.write().unwrap() = block_responses;
+    }
+
+    pub(crate) fn get(&self, resource_type: ResourceType) -> BlockResponse {
+        self.0.read().unwrap().get(resource_type)
+    }
+
+    /// Returns the status code and template of `forbidden` responses.
+    pub(crate) fn get_block_page(&self) -> (u16, Option<String>) {
+        let block_responses = self.0.read().unwrap();
+
+        (
+            block_responses.status_code,
+            block_responses.block_page_template.clone(),
+        )
+    }
+}
+
+/// Guesses what kind of resource is being requested.
+///
+/// Fetch metadata headers are trusted first as browsers set them precisely. We otherwise
+/// fall back on the `Accept` header and finally on the extension of the requested path.
+pub(crate) fn get_resource_type(request: &Request<Body>) -> ResourceType {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_ascii_lowercase())
+    };
+
+    if let Some(destination) = header("sec-fetch-dest") {
+        match destination.as_str() {
+            "document" => return ResourceType::Document,
+            "iframe" | "frame" | "embed" | "object" => return ResourceType::Subdocument,
+            "script" | "worker" | "sharedworker" | "serviceworker" => return ResourceType::Script,
+            "style" => return ResourceType::Stylesheet,
+            "image" => return ResourceType::Image,
+            "audio" | "video" | "track" => return ResourceType::Media,
+            "font" => return ResourceType::Font,
+            // `fetch()` and `XMLHttpRequest` calls.
+            "empty" => return ResourceType::Xhr,
+            _ => {}
+        }
+    }
+
+    if let Some(accept) = header(hyper::header::ACCEPT.as_str()) {
+        if accept.starts_with("text/html") {
+            return ResourceType::Document;
+        } else if accept.starts_with("text/css") {
+            return ResourceType::Stylesheet;
+        } else if accept.starts_with("image/") {
+            return ResourceType::Image;
+        }
+    }
+
+    let extension = request
+        .uri()
+        .path()
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("js" | "mjs") => ResourceType::Script,
+        Some("css") => ResourceType::Stylesheet,
+        Some("png" | "jpg" | "jpeg" | "gif" | "webp" | "avif" | "svg" | "ico") => {
+            ResourceType::Image
+        }
+        Some("mp3" | "mp4" | "webm" | "ogg" | "m3u8") => ResourceType::Media,
+        Some("woff" | "woff2" | "ttf" | "otf" | "eot") => ResourceType::Font,
+        _ => ResourceType::Other,
+    }
+}
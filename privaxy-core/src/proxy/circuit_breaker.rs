@@ -0,0 +1,177 @@
+use crate::configuration::CircuitBreaker;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests are sent, failures are being counted.
+    Closed,
+    /// Requests are short-circuited until the cooldown ends.
+    Open,
+    /// The cooldown ended, the next request tells whether the domain recovered.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitStatus {
+    pub domain: String,
+    pub state: CircuitState,
+    /// Consecutive failures.
+    pub failures: u32,
+    pub last_error: String,
+    /// When requests will be sent again, while the circuit is open.
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct DomainState {
+    failures: u32,
+    last_error: String,
+    open_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct CircuitBreakerState {
+    configuration: CircuitBreaker,
+    // Only failing domains are tracked, they are forgotten as soon as they answer.
+    domains: HashMap<String, DomainState>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerStore(Arc<RwLock<CircuitBreakerState>>);
+
+impl CircuitBreakerStore {
+    pub fn new(configuration: CircuitBreaker) -> Self {
+        Self(Arc::new(RwLock::new(CircuitBreakerState {
+            configuration,
+            domains: HashMap::new(),
+        })))
+    }
+
+    pub fn replace(&self, configuration: CircuitBreaker) {
+        let mut state = self.0.write().unwrap();
+
+        if !configuration.enabled {
+            state.domains.clear();
+        }
+
+        state.configuration = configuration;
+    }
+
+    /// Returns how long to wait for `host` to answer, if requests to it are bounded.
+    pub(crate) fn get_timeout(&self, host: &str) -> Option<Duration> {
+        let state = self.0.read().unwrap();
+
+        state
+            .configuration
+            .enabled
+            .then(|| state.configuration.timeout(host))
+    }
+
+    /// Returns the error requests to `host` are answered with, while its circuit is open.
+    pub(crate) fn get_short_circuit_error(&self, host: &str) -> Option<String> {
+        let state = self.0.read().unwrap();
+        let domain_state = state.domains.get(host)?;
+
+        match domain_state.open_until {
+            Some(open_until) if open_until > Utc::now() => Some(format!(
+                "{} failed to answer {} times in a row and is not contacted until {}. Last error: {}",
+                host,
+                domain_state.failures,
+                open_until.to_rfc3339(),
+                domain_state.last_error
+            )),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn record_success(&self, host: &str) {
+        // Most requests succeed, don't take the write lock for them.
+        if !self.0.read().unwrap().domains.contains_key(host) {
+            return;
+        }
+
+        if self.0.write().unwrap().domains.remove(host).is_some() {
+            log::info!("{} answered again, closing its circuit", host);
+        }
+    }
+
+    pub(crate) fn record_failure(&self, host: &str, error: String) {
+        let mut state = self.0.write().unwrap();
+
+        if !state.configuration.enabled {
+            return;
+        }
+
+        let failure_threshold = state.configuration.failure_threshold.max(1);
+        let cooldown = chrono::Duration::from_std(state.configuration.cooldown())
+            .unwrap_or_else(|_| chrono::Duration::zero());
+
+        let domain_state = state
+            .domains
+            .entry(host.to_string())
+            .or_insert_with(|| DomainState {
+                failures: 0,
+                last_error: String::new(),
+                open_until: None,
+            });
+
+        domain_state.failures += 1;
+        domain_state.last_error = error;
+
+        // Once the threshold is reached, a single failure after a cooldown opens the
+        // circuit again.
+        if domain_state.failures >= failure_threshold {
+            let open_until = Utc::now() + cooldown;
+
+            log::warn!(
+                "{} failed to answer {} times in a row, short-circuiting requests to it until {}",
+                host,
+                domain_state.failures,
+                open_until
+            );
+
+            domain_state.open_until = Some(open_until);
+        }
+    }
+
+    pub fn get_status(&self) -> Vec<CircuitStatus> {
+        let now = Utc::now();
+
+        let mut status = self
+            .0
+            .read()
+            .unwrap()
+            .domains
+            .iter()
+            .map(|(domain, domain_state)| {
+                let (state, retry_at) = match domain_state.open_until {
+                    Some(open_until) if open_until > now => (CircuitState::Open, Some(open_until)),
+                    Some(_) => (CircuitState::HalfOpen, None),
+                    None => (CircuitState::Closed, None),
+                };
+
+                CircuitStatus {
+                    domain: domain.clone(),
+                    state,
+                    failures: domain_state.failures,
+                    last_error: domain_state.last_error.clone(),
+                    retry_at,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        status.sort_by(|a, b| b.failures.cmp(&a.failures).then(a.domain.cmp(&b.domain)));
+
+        status
+    }
+
+    /// Forgets the failures of `domain`, returns whether there were any.
+    pub fn reset(&self, domain: &str) -> bool {
+        self.0.write().unwrap().domains.remove(domain).is_some()
+    }
+}
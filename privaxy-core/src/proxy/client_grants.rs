@@ -0,0 +1,214 @@
+use chrono::{DateTime, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Grants are checked again when matching clients, this only bounds how long expired
+/// ones are listed as active.
+const CLIENT_GRANTS_EXPIRY_INTERVAL: Duration = Duration::from_secs(10);
+/// Number of audit trail entries kept.
+const AUDIT_TRAIL_ENTRIES: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+/// How much filtering a grant relaxes, from the least to the most permissive
+pub enum ClientGrantLevel {
+    /// HTTPS connections are tunneled instead of intercepted, so that the client
+    /// doesn't need to trust the Privaxy CA. Plain HTTP requests are still filtered.
+    Relaxed,
+    /// Nothing is intercepted, blocked or rewritten.
+    Unfiltered,
+}
+
+/// Relaxed filtering granted to a client for a limited time, such as a visitor's phone.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientGrant {
+    pub id: u64,
+    /// IP address, network or name of a client as set in the client identification settings.
+    pub client: String,
+    pub level: ClientGrantLevel,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ClientGrant {
+    fn applies_to(&self, ip_address: IpAddr, client_identity: &str) -> bool {
+        if let Ok(network) = self.client.parse::<IpNet>() {
+            network.contains(&ip_address)
+        } else if let Ok(grant_ip_address) = self.client.parse::<IpAddr>() {
+            grant_ip_address == ip_address
+        } else {
+            self.client == client_identity
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientGrantAuditAction {
+    Created,
+    Revoked,
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientGrantAuditEntry {
+    pub now: DateTime<Utc>,
+    pub action: ClientGrantAuditAction,
+    /// Address of the web GUI client that made the change, if any.
+    pub by: Option<String>,
+    pub grant: ClientGrant,
+}
+
+/// Grants only live in memory, restarting Privaxy revokes them.
+#[derive(Debug, Clone, Default)]
+pub struct ClientGrantStore {
+    grants: Arc<RwLock<Vec<ClientGrant>>>,
+    /// Most recent changes, oldest first.
+    audit_trail: Arc<RwLock<VecDeque<ClientGrantAuditEntry>>>,
+    last_grant_id: Arc<RwLock<u64>>,
+}
+
+impl ClientGrantStore {
+    fn audit(&self, action: ClientGrantAuditAction, by: Option<String>, grant: ClientGrant) {
+        log::info!(
+            "Client grant {} for {} ({:?}) {:?}",
+            grant.id,
+            grant.client,
+            grant.level,
+            action
+        );
+
+        let mut audit_trail = self.audit_trail.write().unwrap();
+        if audit_trail.len() == AUDIT_TRAIL_ENTRIES {
+            audit_trail.pop_front();
+        }
+        audit_trail.push_back(ClientGrantAuditEntry {
+            now: Utc::now(),
+            action,
+            by,
+            grant,
+        });
+    }
+
+    pub fn add(
+        &self,
+        client: String,
+        level: ClientGrantLevel,
+        note: Option<String>,
+        expires_at: DateTime<Utc>,
+        by: Option<String>,
+    ) -> ClientGrant {
+        let id = {
+            let mut last_grant_id = self.last_grant_id.write().unwrap();
+            *last_grant_id += 1;
+            *last_grant_id
+        };
+
+        let grant = ClientGrant {
+            id,
+            client,
+            level,
+            note,
+            created_at: Utc::now(),
+            expires_at,
+        };
+
+        self.grants.write().unwrap().push(grant.clone());
+        self.audit(ClientGrantAuditAction::Created, by, grant.clone());
+
+        grant
+    }
+
+    /// Returns whether there was an active grant with this id.
+    pub fn revoke(&self, id: u64, by: Option<String>) -> bool {
+        let grant = {
+            let mut grants = self.grants.write().unwrap();
+
+            match grants.iter().position(|grant| grant.id == id) {
+                Some(index) => grants.remove(index),
+                None => return false,
+            }
+        };
+
+        self.audit(ClientGrantAuditAction::Revoked, by, grant);
+
+        true
+    }
+
+    pub fn get_grants(&self) -> Vec<ClientGrant> {
+        let now = Utc::now();
+
+        self.grants
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|grant| grant.expires_at > now)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the audit trail, most recent first.
+    pub fn get_audit_trail(&self) -> Vec<ClientGrantAuditEntry> {
+        self.audit_trail
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the most permissive level granted to a client.
+    pub(crate) fn get_level(
+        &self,
+        ip_address: IpAddr,
+        client_identity: &str,
+    ) -> Option<ClientGrantLevel> {
+        let grants = self.grants.read().unwrap();
+
+        if grants.is_empty() {
+            return None;
+        }
+
+        let now = Utc::now();
+
+        grants
+            .iter()
+            .filter(|grant| grant.expires_at > now && grant.applies_to(ip_address, client_identity))
+            .map(|grant| grant.level)
+            .max()
+    }
+
+    fn remove_expired(&self) {
+        let now = Utc::now();
+
+        let expired_grants = {
+            let mut grants = self.grants.write().unwrap();
+
+            let (expired_grants, active_grants) = grants
+                .drain(..)
+                .partition::<Vec<_>, _>(|grant| grant.expires_at <= now);
+            *grants = active_grants;
+
+            expired_grants
+        };
+
+        for grant in expired_grants {
+            self.audit(ClientGrantAuditAction::Expired, None, grant);
+        }
+    }
+}
+
+/// Removes client grants once they expire, recording it in the audit trail.
+pub(crate) async fn expire_client_grants(client_grant_store: ClientGrantStore) {
+    loop {
+        tokio::time::sleep(CLIENT_GRANTS_EXPIRY_INTERVAL).await;
+
+        client_grant_store.remove_expired();
+    }
+}
@@ -0,0 +1,20 @@
+use crate::configuration::ClientIdentification;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone)]
+pub struct ClientIdentificationStore(Arc<RwLock<ClientIdentification>>);
+
+impl ClientIdentificationStore {
+    pub fn new(client_identification: ClientIdentification) -> Self {
+        Self(Arc::new(RwLock::new(client_identification)))
+    }
+
+    pub fn replace(&self, client_identification: ClientIdentification) {
+        *self.0.write().unwrap() = client_identification;
+    }
+
+    pub(crate) fn identify(&self, ip_address: IpAddr) -> String {
+        self.0.read().unwrap().identify(ip_address)
+    }
+}
@@ -0,0 +1,341 @@
+use crate::configuration::{ContentRewriteRule, FilterContent, ANY_HOST};
+use crate::statistics::{LatencyStage, Statistics};
+use hyper::body::Bytes;
+use hyper::header::HeaderMap;
+use hyper::http;
+use regex::Regex;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Responses with a larger body are relayed unmodified, as they would have to be held
+/// back entirely while being rewritten.
+const MAX_REWRITTEN_BODY_SIZE: usize = 4 * 1024 * 1024;
+
+/// Time after which a body is relayed unmodified if its rewrite hasn't finished.
+const REWRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Content types of the responses rewrite rules apply to, besides `text/*`.
+const REWRITABLE_CONTENT_TYPES: [&str; 4] = [
+    "application/json",
+    "application/javascript",
+    "application/xml",
+    "application/x-javascript",
+];
+
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledRewriteRule {
+    host: String,
+    regex: Regex,
+    replacement: String,
+}
+
+impl CompiledRewriteRule {
+    fn compile(content_rewrite_rule: ContentRewriteRule) -> Option<Self> {
+        match content_rewrite_rule.build_regex() {
+            Ok(regex) => Some(Self {
+                host: content_rewrite_rule.host,
+                regex,
+                replacement: content_rewrite_rule.replacement,
+            }),
+            Err(err) => {
+                log::warn!(
+                    "Ignoring content rewrite rule {:?}: {}",
+                    content_rewrite_rule.pattern,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    fn is_matching_host(&self, host: &str) -> bool {
+        self.host == ANY_HOST || host == self.host || host.ends_with(&format!(".{}", self.host))
+    }
+}
+
+/// Rewrite rules of the configuration, and `$replace` rules of the filter lists and
+/// custom filters.
+#[derive(Debug, Clone)]
+pub struct ContentRewriteStore {
+    configured_rules: Arc<RwLock<Vec<CompiledRewriteRule>>>,
+    filter_rules: Arc<RwLock<Vec<CompiledRewriteRule>>>,
+    custom_filter_rules: Arc<RwLock<Vec<CompiledRewriteRule>>>,
+}
+
+impl ContentRewriteStore {
+    pub fn new(content_rewrite_rules: Vec<ContentRewriteRule>) -> Self {
+        let store = Self {
+            configured_rules: Arc::new(RwLock::new(Vec::new())),
+            filter_rules: Arc::new(RwLock::new(Vec::new())),
+            custom_filter_rules: Arc::new(RwLock::new(Vec::new())),
+        };
+        store.replace(content_rewrite_rules);
+        store
+    }
+
+    pub fn replace(&self, content_rewrite_rules: Vec<ContentRewriteRule>) {
+        *self.configured_rules.write().unwrap() = compile_rules(content_rewrite_rules);
+    }
+
+    pub(crate) fn replace_filter_rules(&self, filters: &[FilterContent]) {
+        let content_rewrite_rules = filters
+            .iter()
+            .flat_map(|filter| filter.content.lines())
+            .filter_map(parse_replace_rule)
+            .collect();
+
+        *self.filter_rules.write().unwrap() = compile_rules(content_rewrite_rules);
+    }
+
+    pub(crate) fn replace_custom_filter_rules(&self, custom_filters: &[String]) {
+        let content_rewrite_rules = custom_filters
+            .iter()
+            .filter_map(|line| parse_replace_rule(line))
+            .collect();
+
+        *self.custom_filter_rules.write().unwrap() = compile_rules(content_rewrite_rules);
+    }
+
+    /// Returns the rules applying to the body of a response from `host`.
+    pub(crate) fn get_rules(&self, host: &str, headers: &HeaderMap) -> Vec<CompiledRewriteRule> {
+        if !is_rewritable(headers) {
+            return Vec::new();
+        }
+
+        let host = host.to_lowercase();
+
+        [
+            &self.configured_rules,
+            &self.filter_rules,
+            &self.custom_filter_rules,
+        ]
+        .into_iter()
+        .flat_map(|rules| {
+            rules
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|rule| rule.is_matching_host(&host))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .collect()
+    }
+}
+
+fn compile_rules(content_rewrite_rules: Vec<ContentRewriteRule>) -> Vec<CompiledRewriteRule> {
+    content_rewrite_rules
+        .into_iter()
+        .filter_map(CompiledRewriteRule::compile)
+        .collect()
+}
+
+fn is_rewritable(headers: &HeaderMap) -> bool {
+    let too_large = headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|content_length| content_length.to_str().ok())
+        .and_then(|content_length| content_length.parse::<usize>().ok())
+        .is_some_and(|content_length| content_length > MAX_REWRITTEN_BODY_SIZE);
+
+    if too_large {
+        return false;
+    }
+
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_ascii_lowercase())
+        .is_some_and(|content_type| {
+            content_type.starts_with("text/")
+                || content_type.contains("+json")
+                || content_type.contains("+xml")
+                || REWRITABLE_CONTENT_TYPES
+                    .iter()
+                    .any(|rewritable| content_type.starts_with(rewritable))
+        })
+}
+
+/// Reads the body of a response up to [`MAX_REWRITTEN_BODY_SIZE`] and applies rewrite
+/// rules to it, returning the bytes read and whether they were modified.
+///
+/// Past that size, the bytes read so far are returned as they are and the rest of
+/// the body is left to be read from `response`. Bodies that aren't valid UTF-8 or
+/// that take too long to rewrite are returned unmodified as well.
+pub(crate) async fn read_rewritten_body(
+    response: &mut reqwest::Response,
+    rules: Vec<CompiledRewriteRule>,
+    statistics: &Statistics,
+) -> (Bytes, bool) {
+    let mut body = Vec::new();
+
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                body.extend_from_slice(&chunk);
+
+                if body.len() > MAX_REWRITTEN_BODY_SIZE {
+                    log::debug!("Body too large to be rewritten: {}", response.url());
+                    return (Bytes::from(body), false);
+                }
+            }
+            Ok(None) => break,
+            // The rest of the body is lost either way, hand over what we have.
+            Err(_err) => return (Bytes::from(body), false),
+        }
+    }
+
+    let text = match String::from_utf8(body) {
+        Ok(text) => text,
+        Err(err) => return (Bytes::from(err.into_bytes()), false),
+    };
+
+    // Regular expressions run in linear time, but a body can still take a while to
+    // go through many rules. The original is kept around in case it does.
+    let original = text.clone();
+    let started_at = Instant::now();
+    let rewrite = tokio::task::spawn_blocking(move || rewrite(text, &rules));
+    let rewritten = tokio::time::timeout(REWRITE_TIMEOUT, rewrite).await;
+
+    statistics.record_latency(LatencyStage::Rewrite, started_at.elapsed());
+
+    match rewritten {
+        Ok(Ok(Some(rewritten))) => (Bytes::from(rewritten), true),
+        Ok(Ok(None)) | Ok(Err(_)) => (Bytes::from(original), false),
+        Err(_elapsed) => {
+            log::warn!("Gave up rewriting body of: {}", response.url());
+            (Bytes::from(original), false)
+        }
+    }
+}
+
+/// Returns the rewritten text, if any rule matched.
+fn rewrite(mut text: String, rules: &[CompiledRewriteRule]) -> Option<String> {
+    let mut is_modified = false;
+
+    for rule in rules {
+        if let std::borrow::Cow::Owned(rewritten) =
+            rule.regex.replace_all(&text, rule.replacement.as_str())
+        {
+            text = rewritten;
+            is_modified = true;
+        }
+    }
+
+    is_modified.then_some(text)
+}
+
+/// Converts an AdGuard `$replace` rule into a rewrite rule.
+///
+/// Only rules applying to every request or to the requests of a domain, written
+/// `||example.com^`, and without any other option are supported, as the blocking
+/// engine is not able to tell which requests the others apply to.
+fn parse_replace_rule(line: &str) -> Option<ContentRewriteRule> {
+    let line = line.trim();
+
+    // Comments, exceptions and cosmetic rules.
+    if line.starts_with('!') || line.starts_with("@@") || line.contains("##") {
+        return None;
+    }
+
+    let (pattern, options) = line.split_once('$')?;
+    let value = options.strip_prefix("replace=")?;
+
+    let host = match pattern {
+        "" | "*" => ANY_HOST.to_string(),
+        pattern => {
+            let host = pattern.strip_prefix("||")?.strip_suffix('^')?;
+            if host.is_empty() || host.contains(['/', '*', '^']) {
+                return None;
+            }
+            host.to_lowercase()
+        }
+    };
+
+    // `/regex/replacement/flags`, slashes and commas being escaped within them.
+    let parts = split_unescaped(value.strip_prefix('/')?, '/');
+    let (regex, replacement, flags) = match parts.as_slice() {
+        [regex, replacement, flags] => (regex, replacement, flags),
+        _ => return None,
+    };
+
+    // Another option follows the flags.
+    if flags.contains(',') || !flags.chars().all(|flag| matches!(flag, 'i' | 'g' | 's')) {
+        return None;
+    }
+
+    let mut pattern = unescape(regex);
+    if flags.contains('s') {
+        pattern = format!("(?s){}", pattern);
+    }
+
+    Some(ContentRewriteRule {
+        host,
+        pattern,
+        replacement: convert_replacement(replacement),
+        case_insensitive: flags.contains('i'),
+    })
+}
+
+/// Splits `value` on `separator`s that aren't escaped with a backslash.
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            parts.last_mut().unwrap().push(c);
+            if let Some(escaped) = chars.next() {
+                parts.last_mut().unwrap().push(escaped);
+            }
+        } else if c == separator {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+
+    parts
+}
+
+/// Removes the escaping of characters that are special in filter rules but not in
+/// regular expressions.
+fn unescape(value: &str) -> String {
+    value.replace("\\/", "/").replace("\\,", ",")
+}
+
+/// Converts a JavaScript replacement string into the syntax of the regex crate.
+fn convert_replacement(replacement: &str) -> String {
+    let mut converted = String::new();
+    let mut chars = replacement.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('$') => converted.push_str("$$"),
+                Some(escaped @ ('/' | ',')) => converted.push(escaped),
+                Some(escaped) => {
+                    converted.push('\\');
+                    converted.push(escaped);
+                }
+                None => converted.push('\\'),
+            },
+            '$' => {
+                let mut group = String::new();
+                while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    group.push(*digit);
+                    chars.next();
+                }
+
+                if group.is_empty() {
+                    converted.push_str("$$");
+                } else {
+                    // Braces keep the group apart from text following it.
+                    converted.push_str(&format!("${{{}}}", group));
+                }
+            }
+            c => converted.push(c),
+        }
+    }
+
+    converted
+}
@@ -0,0 +1,116 @@
+use super::header_rules::get_site;
+use crate::configuration::{CookieRuleAction, CookieRules};
+use hyper::header::{self, HeaderMap};
+use std::sync::{Arc, RwLock};
+
+/// Hosts of tracking services whose cookies are only ever used to follow users
+/// across sites.
+const TRACKER_HOSTS: [&str; 16] = [
+    "doubleclick.net",
+    "google-analytics.com",
+    "googletagmanager.com",
+    "googlesyndication.com",
+    "googleadservices.com",
+    "adnxs.com",
+    "criteo.com",
+    "scorecardresearch.com",
+    "quantserve.com",
+    "taboola.com",
+    "outbrain.com",
+    "rubiconproject.com",
+    "pubmatic.com",
+    "casalemedia.com",
+    "demdex.net",
+    "hotjar.com",
+];
+
+/// Which cookie headers of a request and its response are removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct CookieDecision {
+    pub(crate) strip_request_cookies: bool,
+    pub(crate) strip_response_cookies: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct CookieRulesStore(Arc<RwLock<CookieRules>>);
+
+impl CookieRulesStore {
+    pub fn new(cookie_rules: CookieRules) -> Self {
+        Self(Arc::new(RwLock::new(cookie_rules)))
+    }
+
+    pub fn replace(&self, cookie_rules: CookieRules) {
+        *self.0.write().unwrap() = cookie_rules;
+    }
+
+    /// Decides what happens to the cookies of a request to `host`, given the headers
+    /// the client sent.
+    ///
+    /// The rule of `host` comes first, then those of its parent domains. Without
+    /// one, third-party and tracker cookies are removed when enabled.
+    pub(crate) fn get_decision(&self, host: &str, request_headers: &HeaderMap) -> CookieDecision {
+        let cookie_rules = self.0.read().unwrap();
+        let host = host.to_lowercase();
+
+        let parent_domains = host.match_indices('.').map(|(index, _)| &host[index + 1..]);
+        let action = std::iter::once(host.as_str())
+            .chain(parent_domains)
+            .find_map(|domain| {
+                cookie_rules
+                    .rules
+                    .iter()
+                    .find(|rule| rule.host == domain)
+                    .map(|rule| rule.action)
+            });
+
+        match action {
+            Some(CookieRuleAction::Allow) => CookieDecision::default(),
+            Some(CookieRuleAction::Deny) => CookieDecision {
+                strip_request_cookies: true,
+                strip_response_cookies: true,
+            },
+            None => {
+                let is_third_party =
+                    cookie_rules.block_third_party && is_third_party(&host, request_headers);
+                let is_tracker = cookie_rules.strip_tracker_cookies
+                    && TRACKER_HOSTS.iter().any(|tracker_host| {
+                        host == *tracker_host || host.ends_with(&format!(".{}", tracker_host))
+                    });
+
+                CookieDecision {
+                    strip_request_cookies: is_third_party,
+                    strip_response_cookies: is_third_party || is_tracker,
+                }
+            }
+        }
+    }
+}
+
+impl CookieDecision {
+    pub(crate) fn apply_to_request(&self, headers: &mut HeaderMap) -> bool {
+        self.strip_request_cookies && headers.remove(header::COOKIE).is_some()
+    }
+
+    pub(crate) fn apply_to_response(&self, headers: &mut HeaderMap) -> bool {
+        self.strip_response_cookies && headers.remove(header::SET_COOKIE).is_some()
+    }
+}
+
+/// Tells whether a request was made by a page of another site, trusting fetch
+/// metadata first and falling back on the referer.
+fn is_third_party(host: &str, request_headers: &HeaderMap) -> bool {
+    let header = |name| {
+        request_headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+
+    if let Some(fetch_site) = header("sec-fetch-site") {
+        return fetch_site.eq_ignore_ascii_case("cross-site");
+    }
+
+    header(header::REFERER.as_str())
+        .and_then(|referer| url::Url::parse(referer).ok())
+        .and_then(|referer| referer.host_str().map(|host| host.to_lowercase()))
+        .is_some_and(|referer_host| get_site(&referer_host) != get_site(host))
+}
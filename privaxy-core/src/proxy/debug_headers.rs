@@ -0,0 +1,67 @@
+use crate::blocker::{MatchedFilter, RequestDecision};
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use std::sync::{Arc, RwLock};
+
+const DECISION_HEADER: &str = "x-privaxy-decision";
+const RULE_HEADER: &str = "x-privaxy-rule";
+const RULE_LIST_HEADER: &str = "x-privaxy-rule-list";
+const REQUEST_ID_HEADER: &str = "x-privaxy-request-id";
+
+/// Whether responses are annotated with the decision taken for their request, so that
+/// it can be inspected from browser developer tools.
+#[derive(Debug, Clone, Default)]
+pub struct DebugHeadersStore(Arc<RwLock<bool>>);
+
+impl DebugHeadersStore {
+    pub fn is_enabled(&self) -> bool {
+        *self.0.read().unwrap()
+    }
+
+    pub fn set(&self, enabled: bool) {
+        *self.0.write().unwrap() = enabled
+    }
+}
+
+/// Adds the debug headers describing `decision`. `rewritten` tells whether the
+/// response goes through the HTML rewriter.
+pub(crate) fn add_debug_headers(
+    headers: &mut HeaderMap,
+    request_id: u64,
+    decision: &RequestDecision,
+    rewritten: bool,
+) {
+    let (mut decision_value, rule) = if decision.blocked {
+        ("blocked".to_string(), decision.filter.as_ref())
+    } else if decision.exception.is_some() {
+        (
+            "allowed; exception".to_string(),
+            decision.exception.as_ref(),
+        )
+    } else {
+        ("allowed".to_string(), None)
+    };
+
+    if let Some(redirect) = &decision.redirect {
+        decision_value += &format!("; redirect={}", redirect);
+    }
+    if rewritten {
+        decision_value += "; rewritten";
+    }
+
+    insert_header(headers, DECISION_HEADER, &decision_value);
+    insert_header(headers, REQUEST_ID_HEADER, &request_id.to_string());
+
+    if let Some(MatchedFilter { rule, list }) = rule {
+        insert_header(headers, RULE_HEADER, rule);
+        if let Some(list) = list {
+            insert_header(headers, RULE_LIST_HEADER, list);
+        }
+    }
+}
+
+fn insert_header(headers: &mut HeaderMap, name: &'static str, value: &str) {
+    // Rules may contain characters that can't be sent in headers, they are left out.
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(HeaderName::from_static(name), value);
+    }
+}
@@ -1,7 +1,16 @@
+use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use wildmatch::WildMatch;
 
+/// Temporary exclusions are checked again when matching hosts, this only bounds how
+/// long expired ones are kept around.
+const TEMPORARY_EXCLUSIONS_EXPIRY_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone)]
 struct WildMatchCollection(Vec<WildMatch>);
 
@@ -78,25 +87,125 @@ lazy_static! {
 }
 
 #[derive(Debug, Clone)]
-pub struct LocalExclusionStore(Arc<RwLock<WildMatchCollection>>);
+struct TemporaryExclusion {
+    pattern: WildMatch,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TemporaryExclusionResponse {
+    pub host: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalExclusionStore {
+    exclusions: Arc<RwLock<WildMatchCollection>>,
+    /// Exclusions that are not saved to the configuration file, keyed by host.
+    temporary_exclusions: Arc<RwLock<HashMap<String, TemporaryExclusion>>>,
+}
 
 impl LocalExclusionStore {
     pub fn new(exclusions: Vec<String>) -> Self {
         let collection = WildMatchCollection::new(exclusions);
-        Self(Arc::new(RwLock::new(collection)))
+        Self {
+            exclusions: Arc::new(RwLock::new(collection)),
+            temporary_exclusions: Arc::new(RwLock::new(HashMap::new())),
+        }
     }
 
     pub fn replace_exclusions(&mut self, exclusions: Vec<String>) {
-        let new_exclusion_store = LocalExclusionStore::new(exclusions);
+        *self.exclusions.write().unwrap() = WildMatchCollection::new(exclusions);
+    }
+
+    /// Excludes `host` until `expires_at`, replacing any previous temporary exclusion
+    /// of the same host.
+    pub fn add_temporary_exclusion(&self, host: &str, expires_at: DateTime<Utc>) {
+        let host = host.to_lowercase();
+
+        self.temporary_exclusions.write().unwrap().insert(
+            host.clone(),
+            TemporaryExclusion {
+                pattern: WildMatch::new(&host),
+                expires_at,
+            },
+        );
+    }
 
-        *self.0.write().unwrap() = new_exclusion_store.0.read().unwrap().clone();
+    /// Returns whether there was a temporary exclusion of `host`.
+    pub fn remove_temporary_exclusion(&self, host: &str) -> bool {
+        self.temporary_exclusions
+            .write()
+            .unwrap()
+            .remove(&host.to_lowercase())
+            .is_some()
+    }
+
+    pub fn get_temporary_exclusions(&self) -> Vec<TemporaryExclusionResponse> {
+        let now = Utc::now();
+
+        let mut temporary_exclusions = self
+            .temporary_exclusions
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_host, exclusion)| exclusion.expires_at > now)
+            .map(|(host, exclusion)| TemporaryExclusionResponse {
+                host: host.clone(),
+                expires_at: exclusion.expires_at,
+            })
+            .collect::<Vec<_>>();
+
+        temporary_exclusions.sort_by_key(|exclusion| exclusion.expires_at);
+
+        temporary_exclusions
+    }
+
+    fn remove_expired_temporary_exclusions(&self) {
+        let now = Utc::now();
+
+        self.temporary_exclusions
+            .write()
+            .unwrap()
+            .retain(|host, exclusion| {
+                let expired = exclusion.expires_at <= now;
+
+                if expired {
+                    log::info!("Temporary exclusion of {} expired", host);
+                }
+
+                !expired
+            });
+    }
+
+    fn is_temporarily_excluded(&self, element: &str) -> bool {
+        let now = Utc::now();
+        let lowercase_element = element.to_lowercase();
+
+        self.temporary_exclusions
+            .read()
+            .unwrap()
+            .values()
+            .any(|exclusion| {
+                exclusion.expires_at > now && exclusion.pattern.matches(&lowercase_element)
+            })
     }
 
     pub fn contains(&self, element: &str) -> bool {
         if DEFAULT_EXCLUSIONS.is_match(element) {
             true
         } else {
-            self.0.read().unwrap().is_match(element)
+            self.exclusions.read().unwrap().is_match(element)
+                || self.is_temporarily_excluded(element)
         }
     }
 }
+
+/// Removes temporary exclusions once they expire.
+pub(crate) async fn expire_temporary_exclusions(local_exclusion_store: LocalExclusionStore) {
+    loop {
+        tokio::time::sleep(TEMPORARY_EXCLUSIONS_EXPIRY_INTERVAL).await;
+
+        local_exclusion_store.remove_expired_temporary_exclusions();
+    }
+}
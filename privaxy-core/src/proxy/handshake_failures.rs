@@ -0,0 +1,153 @@
+use crate::web_gui::events::StatusEvent;
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+/// Failed handshakes of a client with a host after which its exclusion is suggested.
+const SUGGESTION_THRESHOLD: u32 = 3;
+/// Failures older than this are forgotten, so that occasional network errors don't
+/// add up to a suggestion.
+const FAILURE_WINDOW_MINUTES: i64 = 10;
+const MAX_TRACKED_FAILURES: usize = 10_000;
+
+#[derive(Debug)]
+struct HandshakeFailures {
+    count: u32,
+    last_failure_at: DateTime<Utc>,
+    last_error: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, JsonSchema)]
+/// A host clients repeatedly failed to complete TLS handshakes with, because they pin
+/// certificates or don't speak HTTP over TLS. Excluding it tunnels its connections
+/// instead of intercepting them.
+pub struct SuggestedExclusion {
+    pub host: String,
+    pub clients: BTreeSet<IpAddr>,
+    pub failures: u32,
+    pub last_failure_at: DateTime<Utc>,
+    pub last_error: String,
+}
+
+#[derive(Debug, Default)]
+struct HandshakeFailureState {
+    failures: HashMap<(IpAddr, String), HandshakeFailures>,
+    /// Hosts exclusions were suggested for, until they are applied or dismissed.
+    suggested_hosts: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HandshakeFailureStore {
+    state: Arc<RwLock<HandshakeFailureState>>,
+    status_sender: broadcast::Sender<StatusEvent>,
+}
+
+impl HandshakeFailureStore {
+    pub fn new(status_sender: broadcast::Sender<StatusEvent>) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(HandshakeFailureState::default())),
+            status_sender,
+        }
+    }
+
+    /// Records that `client` couldn't complete a handshake for `host`, and suggests
+    /// excluding the host once this happens repeatedly.
+    pub(crate) fn record_failure(&self, client: IpAddr, host: &str, error: &std::io::Error) {
+        let host = host.to_lowercase();
+        let now = Utc::now();
+        let window_start = now - Duration::minutes(FAILURE_WINDOW_MINUTES);
+
+        let mut state = self.state.write().unwrap();
+        let HandshakeFailureState {
+            failures,
+            suggested_hosts,
+        } = &mut *state;
+
+        if failures.len() >= MAX_TRACKED_FAILURES {
+            failures.retain(|(_client, failed_host), failures| {
+                failures.last_failure_at > window_start || suggested_hosts.contains(failed_host)
+            });
+        }
+
+        let failures = failures
+            .entry((client, host.clone()))
+            .or_insert(HandshakeFailures {
+                count: 0,
+                last_failure_at: now,
+                last_error: String::new(),
+            });
+
+        if failures.last_failure_at <= window_start {
+            failures.count = 0;
+        }
+
+        failures.count += 1;
+        failures.last_failure_at = now;
+        failures.last_error = error.to_string();
+
+        let count = failures.count;
+
+        if count >= SUGGESTION_THRESHOLD && suggested_hosts.insert(host.clone()) {
+            log::warn!(
+                "{client} failed {count} handshakes with {host}, the host may not tolerate TLS interception. Suggesting to exclude it."
+            );
+
+            let _ = self.status_sender.send(StatusEvent::ExclusionSuggested {
+                now,
+                host,
+                client,
+                failures: count,
+            });
+        }
+    }
+
+    pub(crate) fn get_suggested_exclusions(&self) -> Vec<SuggestedExclusion> {
+        let state = self.state.read().unwrap();
+
+        let mut suggested_exclusions = BTreeMap::new();
+
+        for ((client, host), failures) in &state.failures {
+            if !state.suggested_hosts.contains(host) {
+                continue;
+            }
+
+            let suggested_exclusion =
+                suggested_exclusions
+                    .entry(host)
+                    .or_insert_with(|| SuggestedExclusion {
+                        host: host.clone(),
+                        clients: BTreeSet::new(),
+                        failures: 0,
+                        last_failure_at: failures.last_failure_at,
+                        last_error: failures.last_error.clone(),
+                    });
+
+            suggested_exclusion.clients.insert(*client);
+            suggested_exclusion.failures += failures.count;
+
+            if failures.last_failure_at > suggested_exclusion.last_failure_at {
+                suggested_exclusion.last_failure_at = failures.last_failure_at;
+                suggested_exclusion.last_error = failures.last_error.clone();
+            }
+        }
+
+        suggested_exclusions.into_values().collect()
+    }
+
+    /// Forgets the failures of `host`, once it was excluded or the suggestion dismissed.
+    /// Returns whether its exclusion was suggested.
+    pub(crate) fn remove_suggestion(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        let mut state = self.state.write().unwrap();
+
+        state
+            .failures
+            .retain(|(_client, failed_host), _failures| *failed_host != host);
+
+        state.suggested_hosts.remove(&host)
+    }
+}
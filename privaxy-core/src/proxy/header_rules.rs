@@ -0,0 +1,251 @@
+use crate::configuration::{
+    HeaderRule, HeaderRuleAction, HeaderRuleDirection, RefererTrimming, UserAgentRule, ANY_HOST,
+};
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use std::sync::{Arc, RwLock};
+
+/// Second level labels under which country code top level domains register sites,
+/// as in `example.co.uk`.
+const COUNTRY_SECOND_LEVEL_LABELS: [&str; 7] = ["co", "com", "net", "org", "gov", "edu", "ac"];
+
+/// Prefix of the client hint headers describing the user agent, such as
+/// `Sec-CH-UA-Platform`.
+const USER_AGENT_CLIENT_HINTS_PREFIX: &str = "sec-ch-ua";
+
+/// Response headers asking clients to send client hints.
+const CLIENT_HINTS_REQUEST_HEADERS: [&str; 2] = ["accept-ch", "critical-ch"];
+
+/// What was done to the `Referer` header of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RefererTrimmed {
+    /// Only the origin of the referring page was kept.
+    Origin,
+    Stripped,
+}
+
+/// Header rules, referer trimming and user agent settings, applied to the headers
+/// of every proxied request and response.
+#[derive(Debug, Clone)]
+pub struct HeaderRulesStore {
+    header_rules: Arc<RwLock<Vec<HeaderRule>>>,
+    referer_trimming: Arc<RwLock<RefererTrimming>>,
+    user_agent_rules: Arc<RwLock<Vec<UserAgentRule>>>,
+}
+
+impl HeaderRulesStore {
+    pub fn new(
+        header_rules: Vec<HeaderRule>,
+        referer_trimming: RefererTrimming,
+        user_agent_rules: Vec<UserAgentRule>,
+    ) -> Self {
+        Self {
+            header_rules: Arc::new(RwLock::new(header_rules)),
+            referer_trimming: Arc::new(RwLock::new(referer_trimming)),
+            user_agent_rules: Arc::new(RwLock::new(user_agent_rules)),
+        }
+    }
+
+    pub fn replace(&self, header_rules: Vec<HeaderRule>) {
+        *self.header_rules.write().unwrap() = header_rules;
+    }
+
+    pub fn replace_referer_trimming(&self, referer_trimming: RefererTrimming) {
+        *self.referer_trimming.write().unwrap() = referer_trimming;
+    }
+
+    pub fn replace_user_agent_rules(&self, user_agent_rules: Vec<UserAgentRule>) {
+        *self.user_agent_rules.write().unwrap() = user_agent_rules;
+    }
+
+    /// Returns the user agent sent to `host`, from its rule, then the rules of its
+    /// parent domains and finally the rule of every host.
+    fn get_user_agent(&self, host: &str) -> Option<HeaderValue> {
+        let user_agent_rules = self.user_agent_rules.read().unwrap();
+
+        if user_agent_rules.is_empty() {
+            return None;
+        }
+
+        let host = host.to_lowercase();
+        let parent_domains = host.match_indices('.').map(|(index, _)| &host[index + 1..]);
+
+        std::iter::once(host.as_str())
+            .chain(parent_domains)
+            .chain(std::iter::once(ANY_HOST))
+            .find_map(|domain| {
+                user_agent_rules
+                    .iter()
+                    .find(|user_agent_rule| user_agent_rule.host == domain)
+            })
+            .and_then(|user_agent_rule| HeaderValue::from_str(&user_agent_rule.user_agent).ok())
+    }
+
+    /// Replaces the user agent of requests to hosts with a user agent rule, and
+    /// removes the client hints that would otherwise describe the actual one.
+    pub(crate) fn normalize_user_agent(&self, host: &str, headers: &mut HeaderMap) {
+        let user_agent = match self.get_user_agent(host) {
+            Some(user_agent) => user_agent,
+            None => return,
+        };
+
+        headers.insert(header::USER_AGENT, user_agent);
+
+        let client_hints = headers
+            .keys()
+            .filter(|name| name.as_str().starts_with(USER_AGENT_CLIENT_HINTS_PREFIX))
+            .cloned()
+            .collect::<Vec<_>>();
+        for client_hint in client_hints {
+            headers.remove(client_hint);
+        }
+    }
+
+    /// Keeps hosts with a user agent rule from asking for client hints.
+    pub(crate) fn remove_client_hints_requests(&self, host: &str, headers: &mut HeaderMap) {
+        if self.get_user_agent(host).is_none() {
+            return;
+        }
+
+        for name in CLIENT_HINTS_REQUEST_HEADERS {
+            headers.remove(name);
+        }
+    }
+
+    /// Strips the referer of requests to `host` when it's one of the configured
+    /// domains, or keeps only its origin when it comes from another site.
+    pub(crate) fn trim_referer(
+        &self,
+        host: &str,
+        headers: &mut HeaderMap,
+    ) -> Option<RefererTrimmed> {
+        let referer_trimming = self.referer_trimming.read().unwrap();
+
+        let referer = headers.get(header::REFERER)?.clone();
+        let host = host.to_lowercase();
+
+        if referer_trimming
+            .strip_domains
+            .iter()
+            .any(|domain| is_matching_host(&host, domain))
+        {
+            headers.remove(header::REFERER);
+            return Some(RefererTrimmed::Stripped);
+        }
+
+        if !referer_trimming.cross_site {
+            return None;
+        }
+
+        // Referers that can't be understood can't be trimmed, they are not sent.
+        let referer_url = match referer
+            .to_str()
+            .ok()
+            .and_then(|referer| url::Url::parse(referer).ok())
+        {
+            Some(referer_url) => referer_url,
+            None => {
+                headers.remove(header::REFERER);
+                return Some(RefererTrimmed::Stripped);
+            }
+        };
+
+        let referer_host = referer_url.host_str()?.to_lowercase();
+        if get_site(&referer_host) == get_site(&host) {
+            return None;
+        }
+
+        let origin = format!("{}/", referer_url.origin().ascii_serialization());
+        if referer.as_bytes() == origin.as_bytes() {
+            return None;
+        }
+
+        match HeaderValue::from_str(&origin) {
+            Ok(origin) => {
+                headers.insert(header::REFERER, origin);
+            }
+            Err(_err) => {
+                headers.remove(header::REFERER);
+                return Some(RefererTrimmed::Stripped);
+            }
+        }
+
+        Some(RefererTrimmed::Origin)
+    }
+
+    /// Applies, in order, the rules of `host` and its parent domains to headers
+    /// going in the given direction.
+    pub(crate) fn apply(
+        &self,
+        host: &str,
+        direction: HeaderRuleDirection,
+        headers: &mut HeaderMap,
+    ) {
+        let header_rules = self.header_rules.read().unwrap();
+
+        if header_rules.is_empty() {
+            return;
+        }
+
+        let host = host.to_lowercase();
+
+        for header_rule in header_rules.iter().filter(|header_rule| {
+            header_rule.direction == direction && is_matching_host(&host, &header_rule.host)
+        }) {
+            // Rules are validated before being saved, one edited by hand may not be.
+            let name = match HeaderName::from_bytes(header_rule.name.as_bytes()) {
+                Ok(name) => name,
+                Err(_err) => continue,
+            };
+            let value = header_rule
+                .value
+                .as_deref()
+                .and_then(|value| HeaderValue::from_str(value).ok());
+
+            match (header_rule.action, value) {
+                (HeaderRuleAction::Remove, _) => {
+                    headers.remove(name);
+                }
+                (HeaderRuleAction::Add, Some(value)) => {
+                    headers.append(name, value);
+                }
+                (HeaderRuleAction::Replace, Some(value)) => {
+                    if headers.contains_key(&name) {
+                        headers.insert(name, value);
+                    }
+                }
+                (_, None) => {}
+            }
+        }
+    }
+}
+
+fn is_matching_host(host: &str, rule_host: &str) -> bool {
+    rule_host == ANY_HOST || host == rule_host || host.ends_with(&format!(".{}", rule_host))
+}
+
+/// Approximates the registrable domain of a host, which tells whether two hosts
+/// belong to the same site.
+pub(crate) fn get_site(host: &str) -> String {
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return host.to_string();
+    }
+
+    let labels = host.rsplit('.').collect::<Vec<_>>();
+
+    let labels_count = match labels.as_slice() {
+        [top_level, second_level, _, ..]
+            if top_level.len() == 2 && COUNTRY_SECOND_LEVEL_LABELS.contains(second_level) =>
+        {
+            3
+        }
+        _ => 2,
+    };
+
+    labels
+        .iter()
+        .take(labels_count)
+        .rev()
+        .copied()
+        .collect::<Vec<_>>()
+        .join(".")
+}
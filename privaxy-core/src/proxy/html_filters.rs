@@ -0,0 +1,221 @@
+use crate::configuration::{FilterContent, CONTENT_REWRITE_REGEX_SIZE_LIMIT};
+use regex::{Regex, RegexBuilder};
+use std::sync::{Arc, RwLock};
+
+/// Elements whose content is a single text node, the only ones `:has-text()` can be
+/// matched against while streaming.
+pub(crate) const RAW_TEXT_ELEMENTS: [&str; 4] = ["script", "style", "textarea", "title"];
+
+/// Procedural operators of uBO that the rewriter can't evaluate.
+const UNSUPPORTED_OPERATORS: [&str; 8] = [
+    ":has(",
+    ":if(",
+    ":if-not(",
+    ":min-text-length(",
+    ":upward(",
+    ":watch-attr(",
+    ":xpath(",
+    ":matches-css(",
+];
+
+#[derive(Debug, Clone)]
+pub(crate) enum TextMatcher {
+    Text(String),
+    Regex(Regex),
+}
+
+impl TextMatcher {
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Text(needle) => text.contains(needle.as_str()),
+            Self::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// An HTML filter, `example.com##^selector`, removing the elements it matches from
+/// pages before they reach the browser.
+#[derive(Debug, Clone)]
+pub(crate) struct HtmlFilter {
+    included_domains: Vec<String>,
+    excluded_domains: Vec<String>,
+    /// Selector as written in the rule, which exceptions refer to.
+    raw_selector: String,
+    pub(crate) selector: String,
+    /// Text the content of the element must contain to be removed.
+    pub(crate) has_text: Option<TextMatcher>,
+}
+
+impl HtmlFilter {
+    fn is_matching_host(&self, host: &str) -> bool {
+        let is_matching_domain =
+            |domain: &String| host == domain || host.ends_with(&format!(".{}", domain));
+
+        (self.included_domains.is_empty() || self.included_domains.iter().any(is_matching_domain))
+            && !self.excluded_domains.iter().any(is_matching_domain)
+    }
+}
+
+#[derive(Debug, Default)]
+struct HtmlFilters {
+    filters: Vec<HtmlFilter>,
+    exceptions: Vec<HtmlFilter>,
+}
+
+impl HtmlFilters {
+    fn from_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Self {
+        let mut html_filters = Self::default();
+
+        for line in lines {
+            match parse_html_filter(line) {
+                Some((html_filter, false)) => html_filters.filters.push(html_filter),
+                Some((html_filter, true)) => html_filters.exceptions.push(html_filter),
+                None => {}
+            }
+        }
+
+        html_filters
+    }
+}
+
+/// HTML filters of the filter lists and custom filters.
+///
+/// The blocking engine ignores these rules, they are applied by the HTML rewriter.
+#[derive(Debug, Clone, Default)]
+pub struct HtmlFilterStore {
+    filter_rules: Arc<RwLock<HtmlFilters>>,
+    custom_filter_rules: Arc<RwLock<HtmlFilters>>,
+}
+
+impl HtmlFilterStore {
+    pub(crate) fn replace_filter_rules(&self, filters: &[FilterContent]) {
+        let lines = filters.iter().flat_map(|filter| filter.content.lines());
+
+        *self.filter_rules.write().unwrap() = HtmlFilters::from_lines(lines);
+    }
+
+    pub(crate) fn replace_custom_filter_rules(&self, custom_filters: &[String]) {
+        let lines = custom_filters.iter().map(String::as_str);
+
+        *self.custom_filter_rules.write().unwrap() = HtmlFilters::from_lines(lines);
+    }
+
+    /// Returns the filters applying to the pages of `host`, once exceptions are
+    /// taken into account.
+    pub(crate) fn get_filters(&self, host: &str) -> Vec<HtmlFilter> {
+        let host = host.to_lowercase();
+
+        let filter_rules = self.filter_rules.read().unwrap();
+        let custom_filter_rules = self.custom_filter_rules.read().unwrap();
+
+        let exceptions = filter_rules
+            .exceptions
+            .iter()
+            .chain(custom_filter_rules.exceptions.iter())
+            .filter(|exception| exception.is_matching_host(&host))
+            .collect::<Vec<_>>();
+
+        filter_rules
+            .filters
+            .iter()
+            .chain(custom_filter_rules.filters.iter())
+            .filter(|html_filter| html_filter.is_matching_host(&host))
+            .filter(|html_filter| {
+                !exceptions
+                    .iter()
+                    .any(|exception| exception.raw_selector == html_filter.raw_selector)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Parses an HTML filter, returning it along with whether it is an exception.
+fn parse_html_filter(line: &str) -> Option<(HtmlFilter, bool)> {
+    let line = line.trim();
+
+    if line.starts_with('!') {
+        return None;
+    }
+
+    let (domains, raw_selector, is_exception) =
+        if let Some((domains, raw_selector)) = line.split_once("#@#^") {
+            (domains, raw_selector, true)
+        } else {
+            let (domains, raw_selector) = line.split_once("##^")?;
+            (domains, raw_selector, false)
+        };
+
+    let raw_selector = raw_selector.trim();
+
+    // Response header filters share the syntax but aren't about the document.
+    if raw_selector.is_empty() || raw_selector.starts_with("responseheader(") {
+        return None;
+    }
+
+    if UNSUPPORTED_OPERATORS
+        .iter()
+        .any(|operator| raw_selector.contains(operator))
+    {
+        return None;
+    }
+
+    let (selector, has_text) = match raw_selector.split_once(":has-text(") {
+        Some((selector, argument)) => {
+            let argument = argument.strip_suffix(')')?;
+            (selector.trim(), Some(parse_text_matcher(argument)?))
+        }
+        None => (raw_selector, None),
+    };
+
+    // The rewriter only accepts selectors it knows how to match.
+    if selector.is_empty() || selector.parse::<lol_html::Selector>().is_err() {
+        return None;
+    }
+
+    let mut included_domains = Vec::new();
+    let mut excluded_domains = Vec::new();
+
+    for domain in domains.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        match domain.strip_prefix('~') {
+            Some(domain) => excluded_domains.push(domain.to_lowercase()),
+            None => included_domains.push(domain.to_lowercase()),
+        }
+    }
+
+    Some((
+        HtmlFilter {
+            included_domains,
+            excluded_domains,
+            raw_selector: raw_selector.to_string(),
+            selector: selector.to_string(),
+            has_text,
+        },
+        is_exception,
+    ))
+}
+
+/// Parses the argument of `:has-text()`, either text or `/regex/` with an optional
+/// `i` flag.
+fn parse_text_matcher(argument: &str) -> Option<TextMatcher> {
+    if argument.is_empty() {
+        return None;
+    }
+
+    let regex = argument.strip_prefix('/').and_then(|argument| {
+        argument
+            .strip_suffix("/i")
+            .map(|pattern| (pattern, true))
+            .or_else(|| argument.strip_suffix('/').map(|pattern| (pattern, false)))
+    });
+
+    match regex {
+        Some((pattern, case_insensitive)) => RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .size_limit(CONTENT_REWRITE_REGEX_SIZE_LIMIT)
+            .build()
+            .ok()
+            .map(TextMatcher::Regex),
+        None => Some(TextMatcher::Text(argument.to_string())),
+    }
+}
@@ -0,0 +1,379 @@
+use super::html_filters::{HtmlFilter, TextMatcher, RAW_TEXT_ELEMENTS};
+use crate::blocker::AdblockRequester;
+use crate::configuration::Injection;
+use crate::statistics::{LatencyStage, Statistics};
+use crossbeam_channel::Receiver;
+use hyper::body::Bytes;
+use lol_html::html_content::{ContentType, Element};
+use lol_html::{element, text, HtmlRewriter, Settings};
+use regex::Regex;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+type InternalBodyChannel = (
+    mpsc::UnboundedSender<(Bytes, Option<AdblockProperties>)>,
+    mpsc::UnboundedReceiver<(Bytes, Option<AdblockProperties>)>,
+);
+
+struct AdblockProperties {
+    url: String,
+    ids: HashSet<String>,
+    classes: HashSet<String>,
+    has_removed_elements: bool,
+}
+
+/// Raw text element held back until its content is known, for `:has-text()` filters.
+struct CapturedElement {
+    start_tag: String,
+    text: String,
+    text_matchers: Vec<TextMatcher>,
+}
+
+pub struct Rewriter {
+    url: String,
+    adblock_requester: AdblockRequester,
+    receiver: Receiver<Bytes>,
+    body_sender: hyper::body::Sender,
+    statistics: Statistics,
+    html_filters: Vec<HtmlFilter>,
+    injections: Vec<Injection>,
+    internal_body_channel: InternalBodyChannel,
+}
+
+impl Rewriter {
+    pub(crate) fn new(
+        url: String,
+        adblock_requester: AdblockRequester,
+        receiver: Receiver<Bytes>,
+        body_sender: hyper::body::Sender,
+        statistics: Statistics,
+        html_filters: Vec<HtmlFilter>,
+        injections: Vec<Injection>,
+    ) -> Self {
+        Self {
+            url,
+            body_sender,
+            statistics,
+            html_filters,
+            injections,
+            adblock_requester,
+            receiver,
+            internal_body_channel: mpsc::unbounded_channel(),
+        }
+    }
+
+    pub(crate) fn rewrite(self) {
+        let (internal_body_sender, internal_body_receiver) = self.internal_body_channel;
+        let body_sender = self.body_sender;
+        let adblock_requester = self.adblock_requester.clone();
+        let statistics = self.statistics.clone();
+
+        let internal_body_sender = Arc::new(Mutex::new(internal_body_sender));
+
+        let classes = Arc::new(Mutex::new(HashSet::new()));
+        let ids = Arc::new(Mutex::new(HashSet::new()));
+
+        tokio::spawn(Self::write_body(
+            internal_body_receiver,
+            body_sender,
+            adblock_requester,
+            statistics,
+            self.injections,
+        ));
+
+        let re = Regex::new(r"\s+").unwrap();
+        let classes_clone = Arc::clone(&classes);
+        let ids_clone = Arc::clone(&ids);
+        let internal_body_sender_clone = Arc::clone(&internal_body_sender);
+
+        let has_removed_elements = Arc::new(Mutex::new(false));
+        let captured_element = Arc::new(Mutex::new(None));
+
+        // Elements are removed by filters without `:has-text()` first, so that the
+        // others don't capture an element that is gone.
+        let (text_filters, html_filters): (Vec<_>, Vec<_>) = self
+            .html_filters
+            .into_iter()
+            .partition(|html_filter| html_filter.has_text.is_some());
+        let has_text_filters = !text_filters.is_empty();
+
+        let mut html_filter_handlers = html_filters
+            .into_iter()
+            .chain(text_filters)
+            .map(|html_filter| {
+                let has_removed_elements = Arc::clone(&has_removed_elements);
+                let captured_element = Arc::clone(&captured_element);
+
+                element!(html_filter.selector.as_str(), move |element| {
+                    match &html_filter.has_text {
+                        Some(text_matcher) => capture_element(
+                            element,
+                            text_matcher,
+                            &captured_element,
+                            &has_removed_elements,
+                        ),
+                        None => {
+                            element.remove();
+                            *has_removed_elements.lock().unwrap() = true;
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if has_text_filters {
+            let captured_element = Arc::clone(&captured_element);
+
+            html_filter_handlers.push(text!(RAW_TEXT_ELEMENTS.join(", ").as_str(), move |chunk| {
+                if let Some(captured_element) = captured_element.lock().unwrap().as_mut() {
+                    captured_element.text.push_str(chunk.as_str());
+                    chunk.remove();
+                }
+                Ok(())
+            }));
+        }
+
+        let mut rewriter = HtmlRewriter::new(
+            Settings {
+                element_content_handlers: html_filter_handlers
+                    .into_iter()
+                    .chain(vec![
+                        element!("*", move |element| {
+                            if let Some(id) = element.get_attribute("id") {
+                                ids_clone.lock().unwrap().insert(id);
+                            }
+                            Ok(())
+                        }),
+                        element!("*", move |element| {
+                            if let Some(class) = element.get_attribute("class") {
+                                let classes_without_duplicate_spaces = re.replace_all(&class, " ");
+                                let class_set: HashSet<_> = classes_without_duplicate_spaces
+                                    .split_whitespace()
+                                    .map(String::from)
+                                    .collect();
+                                classes_clone.lock().unwrap().extend(class_set);
+                            }
+                            Ok(())
+                        }),
+                        element!("html, body", |element| {
+                            if let Some(handlers) = element.end_tag_handlers() {
+                                handlers.push(Box::new(move |end| {
+                                    end.remove();
+                                    Ok(())
+                                }))
+                            }
+                            Ok(())
+                        }),
+                    ])
+                    .collect(),
+                ..Settings::default()
+            },
+            move |c: &[u8]| {
+                let _ = internal_body_sender_clone
+                    .lock()
+                    .unwrap()
+                    .send((Bytes::copy_from_slice(c), None));
+            },
+        );
+
+        // Only the time spent rewriting counts, not the time spent waiting for the body.
+        let mut rewrite_duration = Duration::ZERO;
+        for message in self.receiver {
+            let started_at = Instant::now();
+            rewriter.write(&message).unwrap();
+            rewrite_duration += started_at.elapsed();
+        }
+        let started_at = Instant::now();
+        rewriter.end().unwrap();
+        rewrite_duration += started_at.elapsed();
+
+        self.statistics
+            .record_latency(LatencyStage::Rewrite, rewrite_duration);
+
+        let _ = internal_body_sender.lock().unwrap().send((
+            Bytes::new(),
+            Some(AdblockProperties {
+                ids: ids.lock().unwrap().clone(),
+                classes: classes.lock().unwrap().clone(),
+                url: self.url,
+                has_removed_elements: *has_removed_elements.lock().unwrap(),
+            }),
+        ));
+    }
+
+    async fn write_body(
+        mut receiver: mpsc::UnboundedReceiver<(Bytes, Option<AdblockProperties>)>,
+        mut body_sender: hyper::body::Sender,
+        adblock_requester: AdblockRequester,
+        statistics: Statistics,
+        injections: Vec<Injection>,
+    ) {
+        while let Some((bytes, adblock_properties)) = receiver.recv().await {
+            statistics.add_response_bytes(bytes.len() as u64);
+
+            if let Err(_err) = body_sender.send_data(bytes).await {
+                break;
+            }
+            if let Some(adblock_properties) = adblock_properties {
+                let mut response_has_been_modified = adblock_properties.has_removed_elements;
+
+                let blocker_result = adblock_requester
+                    .get_cosmetic_response(
+                        adblock_properties.url,
+                        adblock_properties.ids.into_iter().collect(),
+                        adblock_properties.classes.into_iter().collect(),
+                    )
+                    .await;
+
+                let hidden_selectors: String = blocker_result
+                    .hidden_selectors
+                    .into_iter()
+                    .map(|selector| format!("{} {{ display: none !important; }}", selector))
+                    .collect();
+
+                let style_selectors: String = blocker_result
+                    .style_selectors
+                    .into_iter()
+                    .map(|(selector, content)| {
+                        response_has_been_modified = true;
+                        format!("{} {{ {} }}", selector, content.join(";"))
+                    })
+                    .collect();
+
+                let mut to_append_to_response = format!(
+                    r#"
+<!-- privaxy proxy -->
+<style>{hidden_selectors}
+{style_selectors}
+</style>
+<!-- privaxy proxy -->"#
+                );
+
+                if let Some(injected_script) = blocker_result.injected_script {
+                    response_has_been_modified = true;
+                    write!(
+                        to_append_to_response,
+                        r#"
+<!-- Privaxy proxy -->
+<script type="application/javascript">{}</script>
+<!-- privaxy proxy -->
+"#,
+                        injected_script
+                    )
+                    .unwrap();
+                }
+
+                // User injections come last, so that they take precedence.
+                for injection in &injections {
+                    response_has_been_modified = true;
+
+                    if !injection.css.is_empty() {
+                        write!(
+                            to_append_to_response,
+                            r#"
+<!-- privaxy proxy -->
+<style>{}</style>
+<!-- privaxy proxy -->
+"#,
+                            injection.css
+                        )
+                        .unwrap();
+                    }
+
+                    if !injection.js.is_empty() {
+                        write!(
+                            to_append_to_response,
+                            r#"
+<!-- privaxy proxy -->
+<script type="application/javascript">{}</script>
+<!-- privaxy proxy -->
+"#,
+                            injection.js
+                        )
+                        .unwrap();
+                    }
+                }
+
+                if response_has_been_modified {
+                    statistics.increment_modified_responses();
+                }
+
+                let bytes = Bytes::copy_from_slice(to_append_to_response.as_bytes());
+                statistics.add_response_bytes(bytes.len() as u64);
+
+                if let Err(_err) = body_sender.send_data(bytes).await {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Holds back a raw text element matched by a `:has-text()` filter, which is then
+/// removed or written back once its end tag shows whether its text matches.
+fn capture_element(
+    element: &mut Element,
+    text_matcher: &TextMatcher,
+    captured_element: &Arc<Mutex<Option<CapturedElement>>>,
+    has_removed_elements: &Arc<Mutex<bool>>,
+) {
+    if element.removed() || !RAW_TEXT_ELEMENTS.contains(&element.tag_name().as_str()) {
+        return;
+    }
+
+    let mut captured = captured_element.lock().unwrap();
+
+    // Another filter already holds it back.
+    if let Some(captured) = captured.as_mut() {
+        captured.text_matchers.push(text_matcher.clone());
+        return;
+    }
+
+    let mut start_tag = format!("<{}", element.tag_name_preserve_case());
+    for attribute in element.attributes() {
+        write!(
+            start_tag,
+            r#" {}="{}""#,
+            attribute.name_preserve_case(),
+            attribute.value().replace('"', "&quot;")
+        )
+        .unwrap();
+    }
+    start_tag.push('>');
+
+    *captured = Some(CapturedElement {
+        start_tag,
+        text: String::new(),
+        text_matchers: vec![text_matcher.clone()],
+    });
+    element.start_tag().remove();
+
+    let captured_element = Arc::clone(captured_element);
+    let has_removed_elements = Arc::clone(has_removed_elements);
+
+    if let Some(handlers) = element.end_tag_handlers() {
+        handlers.push(Box::new(move |end| {
+            if let Some(captured) = captured_element.lock().unwrap().take() {
+                let is_matching = captured
+                    .text_matchers
+                    .iter()
+                    .any(|text_matcher| text_matcher.is_match(&captured.text));
+
+                if is_matching {
+                    end.remove();
+                    *has_removed_elements.lock().unwrap() = true;
+                } else {
+                    end.before(
+                        &format!("{}{}", captured.start_tag, captured.text),
+                        ContentType::Html,
+                    );
+                }
+            }
+            Ok(())
+        }));
+    }
+}
@@ -0,0 +1,34 @@
+use crate::configuration::{Injection, ANY_HOST};
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone)]
+pub struct InjectionStore(Arc<RwLock<Vec<Injection>>>);
+
+impl InjectionStore {
+    pub fn new(injections: Vec<Injection>) -> Self {
+        Self(Arc::new(RwLock::new(injections)))
+    }
+
+    pub fn replace(&self, injections: Vec<Injection>) {
+        *self.0.write().unwrap() = injections;
+    }
+
+    /// Returns the enabled injections applying to the pages of `host`, in the order
+    /// they were defined.
+    pub(crate) fn get_injections(&self, host: &str) -> Vec<Injection> {
+        let host = host.to_lowercase();
+
+        self.0
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|injection| injection.enabled)
+            .filter(|injection| {
+                injection.host == ANY_HOST
+                    || host == injection.host
+                    || host.ends_with(&format!(".{}", injection.host))
+            })
+            .cloned()
+            .collect()
+    }
+}
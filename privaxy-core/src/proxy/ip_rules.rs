@@ -0,0 +1,170 @@
+use crate::configuration::{AddressFamily, IpRule};
+use hyper::client::connect::dns::Name;
+use ipnet::IpNet;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+
+/// Enabled IP rules, along with their parsed network.
+#[derive(Debug, Clone, Default)]
+pub struct IpRuleStore {
+    ip_rules: Arc<RwLock<Vec<(IpNet, IpRule)>>>,
+    preferred_address_family: Arc<RwLock<AddressFamily>>,
+    /// Address every name resolves to instead, when benchmarking against a local server.
+    /// Only set when the store is created.
+    resolved_address_override: Option<IpAddr>,
+}
+
+impl IpRuleStore {
+    pub fn new(ip_rules: Vec<IpRule>) -> Self {
+        let ip_rule_store = Self::default();
+        ip_rule_store.replace(ip_rules);
+
+        ip_rule_store
+    }
+
+    pub fn replace(&self, ip_rules: Vec<IpRule>) {
+        *self.ip_rules.write().unwrap() = ip_rules
+            .into_iter()
+            .filter(|ip_rule| ip_rule.enabled)
+            .filter_map(|ip_rule| Some((ip_rule.get_network()?, ip_rule)))
+            .collect();
+    }
+
+    /// Returns the rule connections to `ip_address` are refused by, if any.
+    pub(crate) fn get_blocking_rule(&self, ip_address: IpAddr) -> Option<IpRule> {
+        // IPv4 addresses may come mapped into IPv6 ones.
+        let ip_address = match ip_address {
+            IpAddr::V6(ipv6_address) => {
+                ipv6_address.to_ipv4_mapped().map_or(ip_address, IpAddr::V4)
+            }
+            IpAddr::V4(_) => ip_address,
+        };
+
+        self.ip_rules
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(network, _ip_rule)| network.contains(&ip_address))
+            .map(|(_network, ip_rule)| ip_rule.clone())
+    }
+
+    /// Resolved addresses of this family are put first, connections are attempted with
+    /// them before the others.
+    pub fn set_preferred_address_family(&self, preferred_address_family: AddressFamily) {
+        *self.preferred_address_family.write().unwrap() = preferred_address_family;
+    }
+
+    /// Makes every name resolve to `address`, IP rules aside, so that upstream requests
+    /// reach a local server whatever their host.
+    pub(crate) fn with_resolved_address_override(mut self, address: Option<IpAddr>) -> Self {
+        self.resolved_address_override = address;
+        self
+    }
+
+    /// Explains why connections to `host` are refused, when it's an address blocked by a
+    /// rule. Names are checked as they are resolved instead.
+    pub(crate) fn get_address_error(&self, host: &str) -> Option<String> {
+        let ip_address = get_unbracketed_host(host).parse::<IpAddr>().ok()?;
+
+        self.get_blocking_rule(ip_address)
+            .map(|ip_rule| get_blocked_message(host, &ip_rule))
+    }
+
+    /// Resolves `host`, leaving out the addresses blocked by a rule. Fails when all of
+    /// them are.
+    pub(crate) async fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        if let Some(error) = self.get_address_error(host) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, error));
+        }
+
+        if let Some(address) = self.resolved_address_override {
+            return Ok(vec![SocketAddr::new(address, port)]);
+        }
+
+        let mut blocking_rule = None;
+        let mut socket_addresses = tokio::net::lookup_host((get_unbracketed_host(host), port))
+            .await?
+            .filter(
+                |socket_address| match self.get_blocking_rule(socket_address.ip()) {
+                    Some(ip_rule) => {
+                        blocking_rule = Some(ip_rule);
+                        false
+                    }
+                    None => true,
+                },
+            )
+            .collect::<Vec<_>>();
+
+        // Sorting is stable, the resolved order is kept within each family.
+        match *self.preferred_address_family.read().unwrap() {
+            AddressFamily::Any => {}
+            AddressFamily::Ipv4 => socket_addresses.sort_by_key(|address| !address.is_ipv4()),
+            AddressFamily::Ipv6 => socket_addresses.sort_by_key(|address| !address.is_ipv6()),
+        }
+
+        match blocking_rule {
+            Some(ip_rule) if socket_addresses.is_empty() => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                get_blocked_message(host, &ip_rule),
+            )),
+            _ => Ok(socket_addresses),
+        }
+    }
+}
+
+/// IPv6 addresses are bracketed in URIs.
+fn get_unbracketed_host(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+fn get_blocked_message(host: &str, ip_rule: &IpRule) -> String {
+    match &ip_rule.comment {
+        Some(comment) => format!(
+            "Connections to {} are blocked by IP rule {} ({})",
+            host, ip_rule.network, comment
+        ),
+        None => format!(
+            "Connections to {} are blocked by IP rule {}",
+            host, ip_rule.network
+        ),
+    }
+}
+
+/// Resolves the names of requests forwarded with reqwest.
+impl reqwest::dns::Resolve for IpRuleStore {
+    fn resolve(&self, name: Name) -> reqwest::dns::Resolving {
+        let ip_rule_store = self.clone();
+
+        Box::pin(async move {
+            // The port is set by reqwest.
+            let socket_addresses = ip_rule_store.resolve(name.as_str(), 0).await?;
+
+            Ok(Box::new(socket_addresses.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Resolves the names of upgraded requests, forwarded with hyper.
+impl hyper::service::Service<Name> for IpRuleStore {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _context: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let ip_rule_store = self.clone();
+
+        Box::pin(async move {
+            let socket_addresses = ip_rule_store.resolve(name.as_str(), 0).await?;
+
+            Ok(socket_addresses.into_iter())
+        })
+    }
+}
@@ -0,0 +1,350 @@
+use super::authentication::{get_proxy_authentication_required_response, Authentication};
+use super::client_grants::ClientGrantLevel;
+use super::ip_rules::IpRuleStore;
+use super::serve::{serve, ConnectionClosed};
+use super::sni;
+use super::traffic_shaping::Shaper;
+use super::upstream_clients::UpstreamClient;
+use super::ProxyContext;
+use crate::logging::with_request_id;
+use crate::statistics::{LatencyStage, Statistics};
+use crate::{blocker::RequestDecision, Event};
+use http::uri::{Authority, Scheme};
+use hyper::{
+    http, server::conn::Http, service::service_fn, upgrade::Upgraded, Body, Method, Request,
+    Response,
+};
+use std::{net::IpAddr, sync::Arc, time::Instant};
+use tokio::{io::AsyncWriteExt, sync::broadcast};
+use tokio_rustls::TlsAcceptor;
+use tracing::Instrument;
+
+pub(crate) async fn serve_mitm_session(
+    context: Arc<ProxyContext>,
+    req: Request<Body>,
+    client_ip_address: IpAddr,
+) -> Result<Response<Body>, ConnectionClosed> {
+    let state = &context.state;
+
+    let user = match state.proxy_authentication_store.authenticate(&req).await {
+        Authentication::NotRequired => None,
+        Authentication::Authenticated(user) => Some(user),
+        Authentication::Rejected => {
+            log::debug!(
+                "Rejected unauthenticated request from client: {}",
+                client_ip_address
+            );
+
+            return Ok(get_proxy_authentication_required_response());
+        }
+    };
+
+    let client_identity = state
+        .client_identification_store
+        .identify(client_ip_address);
+
+    let authority = match req.uri().authority().cloned() {
+        Some(authority) => authority,
+        None => {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = http::StatusCode::BAD_REQUEST;
+
+            log::warn!("Received a request without proper authority, sending bad request");
+
+            return Ok(response);
+        }
+    };
+
+    if Method::CONNECT == req.method() {
+        // Received an HTTP request like:
+        // ```
+        // CONNECT www.domain.com:443 HTTP/1.1
+        // Host: www.domain.com:443
+        // Proxy-Connection: Keep-Alive
+        // ```
+        //
+        // When HTTP method is CONNECT we should return an empty body
+        // then we can eventually upgrade the connection and talk a new protocol.
+        let accept_span = tracing::info_span!(
+            "accept",
+            client.address = %client_ip_address,
+            server.address = authority.host(),
+        );
+        let server_configuration = Arc::new(
+            state
+                .cert_cache
+                .get(authority.clone())
+                .instrument(accept_span.clone())
+                .await
+                .server_configuration,
+        );
+
+        tokio::task::spawn(async move {
+            let state = &context.state;
+
+            match hyper::upgrade::on(req).await {
+                Ok(mut upgraded) => {
+                    // Tunnels and intercepted sessions outlive the connection they were
+                    // upgraded from.
+                    let _open_connection = state.statistics.open_connection();
+
+                    let is_host_blacklisted =
+                        state.local_exclusion_store.contains(authority.host());
+                    // Granted clients may not trust our CA.
+                    let client_grant_level = state
+                        .client_grant_store
+                        .get_level(client_ip_address, &client_identity);
+
+                    if is_host_blacklisted || client_grant_level.is_some() {
+                        // Connections can still be blocked from the server name they are
+                        // for, as long as they are TLS ones.
+                        let client_hello = if client_grant_level
+                            != Some(ClientGrantLevel::Unfiltered)
+                            && authority.port_u16().unwrap_or(443) == 443
+                        {
+                            match sni::read_client_hello(&mut upgraded).await {
+                                Ok(client_hello) => Some(client_hello),
+                                Err(err) => {
+                                    log::debug!("Unable to read client hello: {authority}: {err}");
+                                    return;
+                                }
+                            }
+                        } else {
+                            None
+                        };
+
+                        if let Some(client_hello) = &client_hello {
+                            let server_name = client_hello
+                                .server_name
+                                .as_deref()
+                                .unwrap_or(authority.host());
+                            let decision =
+                                sni::get_decision(&state.adblock_requester, server_name).await;
+                            let category = state.category_store.get_match(
+                                server_name,
+                                &client_identity,
+                                user.as_deref(),
+                            );
+
+                            if decision.blocked || category.is_some() {
+                                log::debug!("Refused tunnel to host: {server_name}");
+
+                                record_blocked_tunnel(
+                                    &state.statistics,
+                                    &state.events_sender,
+                                    server_name,
+                                    client_identity,
+                                    user,
+                                    decision,
+                                    category,
+                                );
+
+                                return;
+                            }
+                        }
+
+                        let client_hello_bytes = client_hello
+                            .map(|client_hello| client_hello.bytes)
+                            .unwrap_or_default();
+
+                        let shaper = state.traffic_shaping_store.get_shaper(
+                            authority.host(),
+                            &client_identity,
+                            user.as_deref(),
+                        );
+
+                        if let Err(err) = tunnel(
+                            &mut upgraded,
+                            &authority,
+                            &state.ip_rule_store,
+                            context.upstream_clients.get(authority.host()),
+                            &client_hello_bytes,
+                            shaper,
+                        )
+                        .await
+                        {
+                            log::debug!("Unable to tunnel host: {authority}: {err}");
+                        }
+
+                        return;
+                    }
+
+                    let http = Http::new();
+
+                    let handshake_started_at = Instant::now();
+                    let tls_stream = TlsAcceptor::from(server_configuration)
+                        .accept(upgraded)
+                        .instrument(tracing::info_span!(parent: &accept_span, "tls_handshake"))
+                        .await;
+                    state
+                        .statistics
+                        .record_latency(LatencyStage::TlsHandshake, handshake_started_at.elapsed());
+
+                    match tls_stream {
+                        Ok(tls_stream) => {
+                            // Requests on the connection are traced on their own.
+                            drop(accept_span);
+
+                            let _result = http
+                                .serve_connection(
+                                    tls_stream,
+                                    service_fn(move |req| {
+                                        // Grants may be created while the connection is open.
+                                        let unfiltered = context
+                                            .state
+                                            .client_grant_store
+                                            .get_level(client_ip_address, &client_identity)
+                                            == Some(ClientGrantLevel::Unfiltered);
+
+                                        let request_span = get_request_span(&req, &authority);
+
+                                        // Logs written while serving the request are tagged with its id.
+                                        with_request_id(serve(
+                                            context.clone(),
+                                            req,
+                                            authority.clone(),
+                                            Scheme::HTTPS,
+                                            client_identity.clone(),
+                                            user.clone(),
+                                            unfiltered,
+                                        ))
+                                        .instrument(request_span)
+                                    }),
+                                )
+                                .with_upgrades()
+                                .await;
+                        }
+                        // Couldn't perform the tls handshake, they may only support TLS features that we don't or
+                        // make use of untrusted certificates. Repeated failures get the host suggested as an
+                        // exclusion so that it can be tunneled instead of trying to perform MITM.
+                        // No blocking will be able to be performed.
+                        Err(error) => {
+                            log::debug!(
+                                "Unable to perform handshake for host: {authority}: {error}"
+                            );
+
+                            state.handshake_failure_store.record_failure(
+                                client_ip_address,
+                                authority.host(),
+                                &error,
+                            );
+                        }
+                    }
+                }
+                Err(e) => log::error!("upgrade error: {}", e),
+            }
+        });
+
+        Ok(Response::new(Body::empty()))
+    } else {
+        // The request is not of method `CONNECT`. Therefore,
+        // this request is for an HTTP resource.
+        let unfiltered = state
+            .client_grant_store
+            .get_level(client_ip_address, &client_identity)
+            == Some(ClientGrantLevel::Unfiltered);
+        let request_span = get_request_span(&req, &authority);
+
+        with_request_id(serve(
+            context.clone(),
+            req,
+            authority,
+            Scheme::HTTP,
+            client_identity,
+            user,
+            unfiltered,
+        ))
+        .instrument(request_span)
+        .await
+    }
+}
+
+fn get_request_span(req: &Request<Body>, authority: &Authority) -> tracing::Span {
+    tracing::info_span!(
+        parent: None,
+        "request",
+        http.request.method = %req.method(),
+        server.address = authority.host(),
+        url.path = req.uri().path(),
+    )
+}
+
+/// Counts a tunnel refused from its server name as a blocked request, by a filter or
+/// a content category.
+fn record_blocked_tunnel(
+    statistics: &Statistics,
+    broadcast_tx: &broadcast::Sender<Event>,
+    server_name: &str,
+    client_identity: String,
+    user: Option<String>,
+    decision: RequestDecision,
+    category: Option<String>,
+) {
+    let url = sni::get_url(server_name);
+    // Categories take precedence over filters, as they do for intercepted requests.
+    let decision = match &category {
+        Some(_category) => RequestDecision {
+            blocked: true,
+            ..RequestDecision::default()
+        },
+        None => decision,
+    };
+    let blocking_filter = decision.filter.clone();
+    // Requests of authenticated proxy users are attributed to them.
+    let attributed_client = user.clone().unwrap_or_else(|| client_identity.clone());
+
+    let request_id = statistics.record_decision("CONNECT".to_string(), url.clone(), decision);
+
+    let _result = broadcast_tx.send(Event {
+        id: request_id,
+        now: chrono::Utc::now(),
+        method: "CONNECT".to_string(),
+        url,
+        is_request_blocked: true,
+        client: client_identity.clone(),
+        user,
+        filter: blocking_filter.clone(),
+        threat: None,
+        category: category.clone(),
+        script: None,
+    });
+
+    statistics.increment_client_requests(&attributed_client);
+    statistics.increment_blocked_requests();
+    if let Some(category) = &category {
+        statistics.increment_category_blocks(category);
+    }
+    statistics.increment_blocked_clients(client_identity);
+    statistics.increment_client_blocked_requests(&attributed_client, server_name);
+    statistics.increment_blocked_domains(server_name);
+    if let Some(blocking_filter) = &blocking_filter {
+        statistics.increment_blocking_filter(blocking_filter);
+    }
+}
+
+async fn tunnel(
+    mut upgraded: &mut Upgraded,
+    authority: &Authority,
+    ip_rule_store: &IpRuleStore,
+    upstream_client: &UpstreamClient,
+    client_hello_bytes: &[u8],
+    shaper: Option<Shaper>,
+) -> std::io::Result<()> {
+    let socket_addresses = ip_rule_store
+        .resolve(authority.host(), authority.port_u16().unwrap_or(443))
+        .await?;
+    let mut server = upstream_client.connect(&socket_addresses).await?;
+    // Bytes read to find the server name the client asks for.
+    server.write_all(client_hello_bytes).await?;
+
+    match shaper {
+        Some(shaper) => shaper.copy_bidirectional(upgraded, &mut server).await?,
+        None => {
+            tokio::io::copy_bidirectional(&mut upgraded, &mut server).await?;
+        }
+    }
+
+    log::debug!("Started tunneling host: {}", authority);
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+pub(crate) mod authentication;
+pub(crate) mod block_responses;
+pub(crate) mod circuit_breaker;
+pub(crate) mod client_grants;
+pub(crate) mod client_identification;
+pub(crate) mod content_rewrite;
+pub(crate) mod cookie_rules;
+pub(crate) mod debug_headers;
+pub(crate) mod header_rules;
+pub(crate) mod mitm;
+pub(crate) mod prewarm;
+pub(crate) mod proceed_tokens;
+pub(crate) mod response_cache;
+pub(crate) mod safe_search;
+pub(crate) mod scripts;
+pub(crate) mod serve;
+pub(crate) mod site_policies;
+pub(crate) mod sni;
+pub(crate) mod streaming;
+pub(crate) mod traffic_shaping;
+pub(crate) mod upstream_clients;
+pub(crate) mod upstream_tls;
+pub(crate) mod url_rewrites;
+pub(crate) use mitm::serve_mitm_session;
+pub(crate) mod exclusions;
+pub(crate) mod handshake_failures;
+pub(crate) mod html_filters;
+pub(crate) mod html_rewriter;
+pub(crate) mod injections;
+pub(crate) mod ip_rules;
+pub(crate) mod plugins;
+
+use crate::AppState;
+use std::time::Duration;
+use upstream_clients::UpstreamClients;
+
+/// What requests are served with until the proxy restarts: the stores it shares with the
+/// web server, along with the upstream clients and limits of the configuration it was
+/// started with.
+pub(crate) struct ProxyContext {
+    pub(crate) state: AppState,
+    pub(crate) upstream_clients: UpstreamClients,
+    pub(crate) max_inspected_body_size: u64,
+    /// Bounds waiting for the response and each chunk of its body.
+    pub(crate) read_timeout: Option<Duration>,
+}
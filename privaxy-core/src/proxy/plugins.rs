@@ -0,0 +1,535 @@
+//! WebAssembly plugins, hooking into requests before they are sent upstream and into
+//! responses before they are relayed to clients. See `docs/plugins.md` for their ABI.
+
+use crate::configuration::{get_plugins_directory, Plugins};
+use chrono::{DateTime, Utc};
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::{Body, Method, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use wasmtime::{
+    Caller, Config, Engine, Extern, InstancePre, Linker, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+const ON_REQUEST_HOOK: &str = "on_request";
+const ON_RESPONSE_HOOK: &str = "on_response";
+
+/// Bodies plugins respond with are held in memory.
+const MAX_PLUGIN_RESPONSE_BODY_SIZE: usize = 1024 * 1024;
+
+/// Stack hooks run with, so that deep recursion traps rather than taking the proxy
+/// down.
+const MAX_PLUGIN_STACK_SIZE: usize = 512 * 1024;
+
+/// A plugin, and how its hooks fared since Privaxy started.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PluginStatus {
+    pub name: String,
+    pub enabled: bool,
+    /// Hooks its module exports.
+    pub hooks: Vec<String>,
+    /// Why its module couldn't be loaded, in which case its hooks aren't called.
+    pub load_error: Option<String>,
+    pub calls: u64,
+    pub failures: u64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hook {
+    OnRequest,
+    OnResponse,
+}
+
+impl Hook {
+    fn name(self) -> &'static str {
+        match self {
+            Self::OnRequest => ON_REQUEST_HOOK,
+            Self::OnResponse => ON_RESPONSE_HOOK,
+        }
+    }
+}
+
+/// What a hook can see and change, and its limits.
+struct HookContext {
+    limits: StoreLimits,
+    plugin: String,
+    method: String,
+    url: String,
+    status: Option<StatusCode>,
+    headers: HeaderMap,
+    response: Option<(StatusCode, Vec<u8>)>,
+}
+
+struct LoadedPlugin {
+    name: String,
+    instance_pre: InstancePre<HookContext>,
+    hooks: Vec<Hook>,
+}
+
+impl fmt::Debug for LoadedPlugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadedPlugin")
+            .field("name", &self.name)
+            .field("hooks", &self.hooks)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+struct LoadedPlugins {
+    configuration: Plugins,
+    /// Enabled plugins whose module could be loaded, in the order they are called.
+    plugins: Vec<LoadedPlugin>,
+    /// Keyed by plugin name.
+    load_errors: HashMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+struct PluginCounters {
+    calls: u64,
+    failures: u64,
+    last_error: Option<String>,
+    last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginStore {
+    engine: Engine,
+    plugins: Arc<RwLock<LoadedPlugins>>,
+    /// Keyed by plugin name, so that they are kept when plugins are reloaded.
+    counters: Arc<Mutex<HashMap<String, PluginCounters>>>,
+}
+
+impl PluginStore {
+    pub fn new(plugins: Plugins) -> Self {
+        let mut config = Config::new();
+        config
+            .consume_fuel(true)
+            .max_wasm_stack(MAX_PLUGIN_STACK_SIZE);
+
+        let store = Self {
+            engine: Engine::new(&config).expect("invalid plugin engine configuration"),
+            plugins: Arc::new(RwLock::new(LoadedPlugins::default())),
+            counters: Arc::new(Mutex::new(HashMap::new())),
+        };
+        store.replace(plugins);
+
+        store
+    }
+
+    /// Loads the modules of enabled plugins from the plugins directory.
+    pub fn replace(&self, plugins: Plugins) {
+        let linker = create_linker(&self.engine);
+
+        let mut loaded_plugins = Vec::new();
+        let mut load_errors = HashMap::new();
+
+        for plugin in plugins.plugins.iter().filter(|plugin| plugin.enabled) {
+            match self.load(&linker, &plugin.name) {
+                Ok(loaded_plugin) => {
+                    log::info!(
+                        "Loaded plugin {} with hooks {:?}",
+                        plugin.name,
+                        loaded_plugin.hooks
+                    );
+                    loaded_plugins.push(loaded_plugin);
+                }
+                Err(err) => {
+                    log::error!("Unable to load plugin {}: {}", plugin.name, err);
+                    load_errors.insert(plugin.name.clone(), err);
+                }
+            }
+        }
+
+        self.counters
+            .lock()
+            .unwrap()
+            .retain(|name, _counters| plugins.plugins.iter().any(|plugin| &plugin.name == name));
+
+        *self.plugins.write().unwrap() = LoadedPlugins {
+            configuration: plugins,
+            plugins: loaded_plugins,
+            load_errors,
+        };
+    }
+
+    fn load(&self, linker: &Linker<HookContext>, name: &str) -> Result<LoadedPlugin, String> {
+        let module = Module::from_file(&self.engine, get_plugin_file(name))
+            .map_err(|err| format!("{:#}", err))?;
+
+        let hooks = [Hook::OnRequest, Hook::OnResponse]
+            .into_iter()
+            .filter(|hook| module.get_export(hook.name()).is_some())
+            .collect::<Vec<_>>();
+        if hooks.is_empty() {
+            return Err(format!(
+                "the module exports neither {} nor {}",
+                ON_REQUEST_HOOK, ON_RESPONSE_HOOK
+            ));
+        }
+
+        let instance_pre = linker
+            .instantiate_pre(&module)
+            .map_err(|err| format!("{:#}", err))?;
+
+        Ok(LoadedPlugin {
+            name: name.to_string(),
+            instance_pre,
+            hooks,
+        })
+    }
+
+    /// Checks that a module can be loaded, before it is installed.
+    pub(crate) fn validate_module(&self, module: &[u8]) -> Result<(), String> {
+        let module = Module::new(&self.engine, module).map_err(|err| format!("{:#}", err))?;
+
+        create_linker(&self.engine)
+            .instantiate_pre(&module)
+            .map(|_instance_pre| ())
+            .map_err(|err| format!("{:#}", err))
+    }
+
+    /// Calls the `on_request` hooks with the headers that are about to be sent upstream,
+    /// returning the response of the first plugin answering the request itself.
+    pub(crate) fn on_request(
+        &self,
+        method: &Method,
+        url: &str,
+        headers: &mut HeaderMap,
+    ) -> Option<Response<Body>> {
+        self.call_hooks(Hook::OnRequest, method, url, None, headers)
+    }
+
+    /// Calls the `on_response` hooks with the headers of the response that is about to
+    /// be relayed, returning the response of the first plugin replacing it.
+    pub(crate) fn on_response(
+        &self,
+        method: &Method,
+        url: &str,
+        status: StatusCode,
+        headers: &mut HeaderMap,
+    ) -> Option<Response<Body>> {
+        self.call_hooks(Hook::OnResponse, method, url, Some(status), headers)
+    }
+
+    fn call_hooks(
+        &self,
+        hook: Hook,
+        method: &Method,
+        url: &str,
+        status: Option<StatusCode>,
+        headers: &mut HeaderMap,
+    ) -> Option<Response<Body>> {
+        let plugins = self.plugins.read().unwrap();
+
+        for plugin in plugins
+            .plugins
+            .iter()
+            .filter(|plugin| plugin.hooks.contains(&hook))
+        {
+            let context = HookContext {
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(plugins.configuration.max_memory_bytes as usize)
+                    .instances(1)
+                    .build(),
+                plugin: plugin.name.clone(),
+                method: method.to_string(),
+                url: url.to_string(),
+                status,
+                headers: headers.clone(),
+                response: None,
+            };
+
+            let result = call_hook(plugin, hook, context, plugins.configuration.max_fuel);
+            self.record_call(&plugin.name, hook, result.as_ref().err());
+
+            // Changes of a failed hook are dropped, the request goes on as if the plugin
+            // wasn't there.
+            if let Ok(context) = result {
+                *headers = context.headers;
+
+                if let Some((status, body)) = context.response {
+                    log::debug!("Plugin {} responded to {}", plugin.name, url);
+                    return Some(get_plugin_response(status, body));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn record_call(&self, name: &str, hook: Hook, error: Option<&String>) {
+        let mut counters = self.counters.lock().unwrap();
+        let counters = counters.entry(name.to_string()).or_default();
+        counters.calls += 1;
+
+        if let Some(error) = error {
+            log::warn!("Plugin {} failed in {}: {}", name, hook.name(), error);
+
+            counters.failures += 1;
+            counters.last_error = Some(format!("{}: {}", hook.name(), error));
+            counters.last_error_at = Some(Utc::now());
+        }
+    }
+
+    pub(crate) fn get_statuses(&self) -> Vec<PluginStatus> {
+        let plugins = self.plugins.read().unwrap();
+        let counters = self.counters.lock().unwrap();
+
+        plugins
+            .configuration
+            .plugins
+            .iter()
+            .map(|plugin| {
+                let loaded_plugin = plugins
+                    .plugins
+                    .iter()
+                    .find(|loaded_plugin| loaded_plugin.name == plugin.name);
+                let plugin_counters = counters.get(&plugin.name);
+
+                PluginStatus {
+                    name: plugin.name.clone(),
+                    enabled: plugin.enabled,
+                    hooks: loaded_plugin.map_or_else(Vec::new, |loaded_plugin| {
+                        loaded_plugin
+                            .hooks
+                            .iter()
+                            .map(|hook| hook.name().to_string())
+                            .collect()
+                    }),
+                    load_error: plugins.load_errors.get(&plugin.name).cloned(),
+                    calls: plugin_counters.map_or(0, |counters| counters.calls),
+                    failures: plugin_counters.map_or(0, |counters| counters.failures),
+                    last_error: plugin_counters.and_then(|counters| counters.last_error.clone()),
+                    last_error_at: plugin_counters.and_then(|counters| counters.last_error_at),
+                }
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn get_plugin_file(name: &str) -> PathBuf {
+    get_plugins_directory().join(format!("{}.wasm", name))
+}
+
+fn call_hook(
+    plugin: &LoadedPlugin,
+    hook: Hook,
+    context: HookContext,
+    max_fuel: u64,
+) -> Result<HookContext, String> {
+    let mut store = Store::new(plugin.instance_pre.module().engine(), context);
+    store.limiter(|context| &mut context.limits);
+    store.set_fuel(max_fuel).map_err(|err| err.to_string())?;
+
+    let instance = plugin
+        .instance_pre
+        .instantiate(&mut store)
+        .map_err(|err| format!("{:#}", err))?;
+    instance
+        .get_typed_func::<(), ()>(&mut store, hook.name())
+        .and_then(|hook| hook.call(&mut store, ()))
+        .map_err(|err| format!("{:#}", err))?;
+
+    Ok(store.into_data())
+}
+
+fn get_plugin_response(status: StatusCode, body: Vec<u8>) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+
+    response
+}
+
+/// Functions plugins can import, the only way they have to reach the request or
+/// response they are called for.
+fn create_linker(engine: &Engine) -> Linker<HookContext> {
+    let mut linker = Linker::new(engine);
+
+    linker
+        .func_wrap(
+            "privaxy",
+            "get_method",
+            |mut caller: Caller<'_, HookContext>, ptr: u32, cap: u32| {
+                let method = caller.data().method.clone();
+                write_bytes(&mut caller, method.as_bytes(), ptr, cap)
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "privaxy",
+            "get_url",
+            |mut caller: Caller<'_, HookContext>, ptr: u32, cap: u32| {
+                let url = caller.data().url.clone();
+                write_bytes(&mut caller, url.as_bytes(), ptr, cap)
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "privaxy",
+            "get_status",
+            |caller: Caller<'_, HookContext>| -> i32 {
+                caller
+                    .data()
+                    .status
+                    .map_or(0, |status| status.as_u16().into())
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "privaxy",
+            "get_header",
+            |mut caller: Caller<'_, HookContext>,
+             name_ptr: u32,
+             name_len: u32,
+             ptr: u32,
+             cap: u32|
+             -> wasmtime::Result<i32> {
+                let name = read_string(&mut caller, name_ptr, name_len)?;
+                let values = caller
+                    .data()
+                    .headers
+                    .get_all(name.as_str())
+                    .iter()
+                    .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+                    .collect::<Vec<_>>();
+
+                if values.is_empty() {
+                    return Ok(-1);
+                }
+
+                write_bytes(&mut caller, values.join(", ").as_bytes(), ptr, cap)
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "privaxy",
+            "set_header",
+            |mut caller: Caller<'_, HookContext>,
+             name_ptr: u32,
+             name_len: u32,
+             value_ptr: u32,
+             value_len: u32|
+             -> wasmtime::Result<i32> {
+                let name = read_bytes(&mut caller, name_ptr, name_len)?;
+                let value = read_bytes(&mut caller, value_ptr, value_len)?;
+
+                match (
+                    HeaderName::from_bytes(&name),
+                    HeaderValue::from_bytes(&value),
+                ) {
+                    (Ok(name), Ok(value)) => {
+                        caller.data_mut().headers.insert(name, value);
+                        Ok(0)
+                    }
+                    _ => Ok(-1),
+                }
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "privaxy",
+            "remove_header",
+            |mut caller: Caller<'_, HookContext>,
+             name_ptr: u32,
+             name_len: u32|
+             -> wasmtime::Result<()> {
+                let name = read_string(&mut caller, name_ptr, name_len)?;
+                caller.data_mut().headers.remove(name.as_str());
+                Ok(())
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "privaxy",
+            "respond",
+            |mut caller: Caller<'_, HookContext>,
+             status: u32,
+             body_ptr: u32,
+             body_len: u32|
+             -> wasmtime::Result<i32> {
+                if body_len as usize > MAX_PLUGIN_RESPONSE_BODY_SIZE {
+                    return Err(wasmtime::Error::msg(format!(
+                        "responses can't be larger than {} bytes",
+                        MAX_PLUGIN_RESPONSE_BODY_SIZE
+                    )));
+                }
+
+                let status = match u16::try_from(status)
+                    .ok()
+                    .and_then(|status| StatusCode::from_u16(status).ok())
+                {
+                    Some(status) => status,
+                    None => return Ok(-1),
+                };
+                let body = read_bytes(&mut caller, body_ptr, body_len)?;
+                caller.data_mut().response = Some((status, body));
+
+                Ok(0)
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "privaxy",
+            "log",
+            |mut caller: Caller<'_, HookContext>, ptr: u32, len: u32| -> wasmtime::Result<()> {
+                let message = read_string(&mut caller, ptr, len)?;
+                log::info!("Plugin {}: {}", caller.data().plugin, message);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+    linker
+}
+
+fn read_bytes(
+    caller: &mut Caller<'_, HookContext>,
+    ptr: u32,
+    len: u32,
+) -> wasmtime::Result<Vec<u8>> {
+    let memory = get_memory(caller)?;
+    let mut bytes = vec![0; len as usize];
+    memory.read(&*caller, ptr as usize, &mut bytes)?;
+
+    Ok(bytes)
+}
+
+fn read_string(
+    caller: &mut Caller<'_, HookContext>,
+    ptr: u32,
+    len: u32,
+) -> wasmtime::Result<String> {
+    Ok(String::from_utf8_lossy(&read_bytes(caller, ptr, len)?).into_owned())
+}
+
+/// Copies as much of `bytes` as fits in `cap` bytes, returning their full length.
+fn write_bytes(
+    caller: &mut Caller<'_, HookContext>,
+    bytes: &[u8],
+    ptr: u32,
+    cap: u32,
+) -> wasmtime::Result<i32> {
+    let memory = get_memory(caller)?;
+    let written = bytes.len().min(cap as usize);
+    memory.write(&mut *caller, ptr as usize, &bytes[..written])?;
+
+    Ok(i32::try_from(bytes.len()).unwrap_or(i32::MAX))
+}
+
+fn get_memory(caller: &mut Caller<'_, HookContext>) -> wasmtime::Result<wasmtime::Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => Err(wasmtime::Error::msg("the module doesn't export its memory")),
+    }
+}
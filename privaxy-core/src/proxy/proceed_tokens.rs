@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Path of the links offered by interstitial pages, followed by a token.
+pub(crate) const PROCEED_PATH_PREFIX: &str = "/__privaxy/proceed/";
+
+/// How long the link of an interstitial page can be followed.
+const TOKEN_LIFETIME_SECS: i64 = 5 * 60;
+
+/// How long pages of a host are let through once a client chose to visit it anyway.
+const PROCEED_DURATION_SECS: i64 = 10 * 60;
+
+#[derive(Debug)]
+struct ProceedToken {
+    host: String,
+    url: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Tokens handed out by interstitial pages, and hosts whose pages clients chose to
+/// visit anyway.
+#[derive(Debug, Clone, Default)]
+pub struct ProceedTokenStore {
+    tokens: Arc<RwLock<HashMap<String, ProceedToken>>>,
+    hosts: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
+}
+
+impl ProceedTokenStore {
+    /// Returns a single use token letting pages of `host` through, then redirecting
+    /// to `url`.
+    pub(crate) fn issue(&self, host: &str, url: String) -> String {
+        let mut bytes = [0; 16];
+        openssl::rand::rand_bytes(&mut bytes).unwrap();
+        let token = hex::encode(bytes);
+
+        let now = Utc::now();
+
+        let mut tokens = self.tokens.write().unwrap();
+        // Most interstitials are never acted upon, don't let their tokens pile up.
+        tokens.retain(|_token, proceed_token| proceed_token.expires_at > now);
+        tokens.insert(
+            token.clone(),
+            ProceedToken {
+                host: host.to_string(),
+                url,
+                expires_at: now + chrono::Duration::seconds(TOKEN_LIFETIME_SECS),
+            },
+        );
+
+        token
+    }
+
+    /// Consumes a token issued for `host`, returning the url to redirect to.
+    pub(crate) fn redeem(&self, host: &str, token: &str) -> Option<String> {
+        let proceed_token = self.tokens.write().unwrap().remove(token)?;
+        let now = Utc::now();
+
+        if proceed_token.host != host || proceed_token.expires_at <= now {
+            return None;
+        }
+
+        let mut hosts = self.hosts.write().unwrap();
+        hosts.retain(|_host, expires_at| *expires_at > now);
+        hosts.insert(
+            proceed_token.host,
+            now + chrono::Duration::seconds(PROCEED_DURATION_SECS),
+        );
+
+        Some(proceed_token.url)
+    }
+
+    /// Whether a client recently chose to visit pages of `host` anyway.
+    pub(crate) fn is_proceeding(&self, host: &str) -> bool {
+        self.hosts
+            .read()
+            .unwrap()
+            .get(host)
+            .is_some_and(|expires_at| *expires_at > Utc::now())
+    }
+}
@@ -0,0 +1,1067 @@
+//! HTTP cache of upstream responses, kept on disk. As it is shared by every client of
+//! the proxy, it follows the rules of RFC 9111 for shared caches.
+//!
+//! Responses to `GET` requests are stored unless they are `no-store` or `private`, set
+//! cookies or vary on every header, as long as they have a freshness lifetime or a
+//! validator. Responses to requests with credentials are only stored when they are
+//! explicitly allowed to be shared. Fresh responses are served without contacting the
+//! server, stale ones are revalidated with `If-None-Match` or `If-Modified-Since`.
+//!
+//! Each response takes two files of the cache directory, its metadata and its body.
+//! Bodies get a new file whenever a response is stored, so that a response being served
+//! isn't swapped for another one halfway.
+
+use crate::configuration::{get_response_cache_directory, ResponseCache};
+use crate::statistics::Statistics;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{stream, StreamExt};
+use hyper::body::Bytes;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::{Body, Method, Response, StatusCode};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use url::Url;
+
+const METADATA_EXTENSION: &str = "json";
+const BODY_EXTENSION: &str = "body";
+
+/// Statuses responses are stored with, those RFC 9110 defines as heuristically
+/// cacheable.
+const CACHEABLE_STATUS_CODES: [u16; 11] = [200, 203, 204, 300, 301, 308, 404, 405, 410, 414, 501];
+
+/// Responses not saying how long they are fresh for are fresh for this fraction of the
+/// time since they were last modified, as RFC 9111 suggests.
+const HEURISTIC_FRESHNESS_DIVISOR: i32 = 10;
+
+const MAX_HEURISTIC_FRESHNESS_HOURS: i64 = 24;
+
+/// Headers of a `304 Not Modified` response that describe its own empty body rather than
+/// the stored response.
+const NOT_MODIFIED_IGNORED_HEADERS: [HeaderName; 3] = [
+    header::CONTENT_LENGTH,
+    header::CONTENT_ENCODING,
+    header::TRANSFER_ENCODING,
+];
+
+/// Request headers a client revalidates its own copy of a response with, or asks for
+/// part of a response with. Such requests are left to the server.
+const CONDITIONAL_REQUEST_HEADERS: [HeaderName; 6] = [
+    header::IF_NONE_MATCH,
+    header::IF_MODIFIED_SINCE,
+    header::IF_MATCH,
+    header::IF_UNMODIFIED_SINCE,
+    header::IF_RANGE,
+    header::RANGE,
+];
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ResponseCacheStatus {
+    pub enabled: bool,
+    pub entries: usize,
+    pub size_bytes: u64,
+    pub max_size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PurgedResponses {
+    pub purged: usize,
+}
+
+/// Directives of `Cache-Control` headers that matter to a shared cache.
+#[derive(Debug, Default)]
+struct CacheControl {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    public: bool,
+    must_revalidate: bool,
+    max_age: Option<i64>,
+    s_maxage: Option<i64>,
+}
+
+impl CacheControl {
+    fn parse<'a>(values: impl Iterator<Item = &'a str>) -> Self {
+        let mut cache_control = Self::default();
+
+        for directive in values.flat_map(|value| value.split(',')) {
+            let (name, argument) = match directive.split_once('=') {
+                Some((name, argument)) => (name, Some(argument.trim().trim_matches('"'))),
+                None => (directive, None),
+            };
+            let seconds = argument.and_then(|argument| argument.parse::<i64>().ok());
+
+            match name.trim().to_ascii_lowercase().as_str() {
+                "no-store" => cache_control.no_store = true,
+                "no-cache" => cache_control.no_cache = true,
+                "private" => cache_control.private = true,
+                "public" => cache_control.public = true,
+                "must-revalidate" | "proxy-revalidate" => cache_control.must_revalidate = true,
+                "max-age" => cache_control.max_age = seconds,
+                "s-maxage" => cache_control.s_maxage = seconds,
+                _ => {}
+            }
+        }
+
+        cache_control
+    }
+
+    fn from_request(headers: &HeaderMap) -> Self {
+        let mut cache_control = Self::parse(
+            headers
+                .get_all(header::CACHE_CONTROL)
+                .iter()
+                .filter_map(|value| value.to_str().ok()),
+        );
+
+        // `Pragma: no-cache` is only meaningful without `Cache-Control`.
+        if !headers.contains_key(header::CACHE_CONTROL) {
+            cache_control.no_cache = headers
+                .get_all(header::PRAGMA)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .any(|value| value.to_ascii_lowercase().contains("no-cache"));
+        }
+
+        cache_control
+    }
+}
+
+fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// A stored response, as saved in its metadata file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedResponse {
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// Request headers named by `Vary`, with the values the response was stored for.
+    vary: Vec<(String, Option<String>)>,
+    request_time: DateTime<Utc>,
+    response_time: DateTime<Utc>,
+    /// Name of the file of the body, in the cache directory.
+    body_file: String,
+    body_size: u64,
+}
+
+impl CachedResponse {
+    fn get_header_values<'a>(&'a self, name: &'a HeaderName) -> impl Iterator<Item = &'a str> {
+        self.headers
+            .iter()
+            .filter(move |(header_name, _value)| header_name == name.as_str())
+            .map(|(_name, value)| value.as_str())
+    }
+
+    fn get_header(&self, name: &HeaderName) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _value)| header_name == name.as_str())
+            .map(|(_name, value)| value.as_str())
+    }
+
+    fn get_cache_control(&self) -> CacheControl {
+        CacheControl::parse(self.get_header_values(&header::CACHE_CONTROL))
+    }
+
+    fn get_date(&self) -> DateTime<Utc> {
+        self.get_header(&header::DATE)
+            .and_then(parse_http_date)
+            .unwrap_or(self.response_time)
+    }
+
+    fn get_freshness_lifetime(&self, cache_control: &CacheControl) -> Duration {
+        if let Some(seconds) = cache_control.s_maxage.or(cache_control.max_age) {
+            return Duration::seconds(seconds);
+        }
+
+        // An invalid date means the response has already expired.
+        if let Some(expires) = self.get_header(&header::EXPIRES) {
+            return parse_http_date(expires)
+                .map_or_else(Duration::zero, |expires| expires - self.get_date());
+        }
+
+        match self
+            .get_header(&header::LAST_MODIFIED)
+            .and_then(parse_http_date)
+        {
+            Some(last_modified) => ((self.get_date() - last_modified)
+                / HEURISTIC_FRESHNESS_DIVISOR)
+                .min(Duration::hours(MAX_HEURISTIC_FRESHNESS_HOURS)),
+            None => Duration::zero(),
+        }
+    }
+
+    /// Age of the response, as RFC 9111 computes it.
+    fn get_age(&self, now: DateTime<Utc>) -> Duration {
+        let apparent_age = (self.response_time - self.get_date()).max(Duration::zero());
+        let response_delay = self.response_time - self.request_time;
+        let age_value = self
+            .get_header(&header::AGE)
+            .and_then(|age| age.trim().parse::<i64>().ok())
+            .map_or_else(Duration::zero, Duration::seconds);
+
+        apparent_age.max(age_value + response_delay) + (now - self.response_time)
+    }
+
+    fn matches_vary(&self, request_headers: &HeaderMap) -> bool {
+        self.vary.iter().all(|(name, value)| {
+            request_headers
+                .get(name.as_str())
+                .and_then(|request_value| request_value.to_str().ok())
+                == value.as_deref()
+        })
+    }
+
+    fn get_host(&self) -> Option<String> {
+        Url::parse(&self.url).ok()?.host_str().map(str::to_string)
+    }
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    response: Arc<CachedResponse>,
+    /// Value of the index clock when the response was last stored or served.
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Index {
+    /// Keyed by the hash of the URL responses were stored for.
+    entries: HashMap<String, IndexEntry>,
+    size: u64,
+    clock: u64,
+}
+
+impl Index {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn insert(&mut self, key: String, response: Arc<CachedResponse>) -> Option<IndexEntry> {
+        let last_used = self.tick();
+        self.size += response.body_size;
+
+        let replaced = self.entries.insert(
+            key,
+            IndexEntry {
+                response,
+                last_used,
+            },
+        );
+        if let Some(replaced) = &replaced {
+            self.size -= replaced.response.body_size;
+        }
+        replaced
+    }
+
+    fn remove(&mut self, key: &str) -> Option<IndexEntry> {
+        let removed = self.entries.remove(key)?;
+        self.size -= removed.response.body_size;
+        Some(removed)
+    }
+
+    /// Removes least recently used responses until the cache fits in `max_size`.
+    fn evict(&mut self, max_size: u64) -> Vec<(String, IndexEntry)> {
+        let mut evicted = Vec::new();
+
+        while self.size > max_size {
+            let key = match self
+                .entries
+                .iter()
+                .min_by_key(|(_key, entry)| entry.last_used)
+            {
+                Some((key, _entry)) => key.clone(),
+                None => break,
+            };
+            if let Some(entry) = self.remove(&key) {
+                evicted.push((key, entry));
+            }
+        }
+
+        evicted
+    }
+}
+
+/// Request the response of the server may be stored for.
+#[derive(Debug)]
+pub(crate) struct CacheRequest {
+    key: String,
+    url: String,
+    /// Headers as sent to the server.
+    headers: HeaderMap,
+    time: DateTime<Utc>,
+}
+
+/// What the cache has for a request.
+#[derive(Debug)]
+pub(crate) enum CacheLookup {
+    /// Fresh response, served without contacting the server.
+    Hit(reqwest::Response),
+    /// Stale response, whose validators were added to the request so that it is served
+    /// again if the server answers it didn't change.
+    Revalidating(CacheRequest, Arc<CachedResponse>),
+    Miss(CacheRequest),
+    /// Requests the cache doesn't apply to, such as other methods than `GET`.
+    Bypassed,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCacheStore {
+    response_cache: Arc<RwLock<ResponseCache>>,
+    index: Arc<Mutex<Index>>,
+    directory: PathBuf,
+}
+
+impl ResponseCacheStore {
+    /// Loads the responses stored by previous runs.
+    pub fn new(response_cache: ResponseCache) -> Self {
+        let store = Self {
+            response_cache: Arc::new(RwLock::new(response_cache)),
+            index: Arc::new(Mutex::new(Index::default())),
+            directory: get_response_cache_directory(),
+        };
+
+        store.load();
+        store
+    }
+
+    pub fn replace(&self, response_cache: ResponseCache) {
+        let max_size_bytes = response_cache.max_size_bytes;
+        *self.response_cache.write().unwrap() = response_cache;
+
+        let evicted = self.index.lock().unwrap().evict(max_size_bytes);
+        self.remove_files(evicted);
+    }
+
+    fn load(&self) {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return,
+            Err(err) => {
+                log::warn!("Unable to read the response cache: {}", err);
+                return;
+            }
+        };
+
+        let mut responses = Vec::new();
+        let mut files = Vec::new();
+        for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+            if path.extension().and_then(|extension| extension.to_str()) != Some(METADATA_EXTENSION)
+            {
+                files.push(path);
+                continue;
+            }
+
+            let key = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(key) => key.to_string(),
+                None => continue,
+            };
+            let response = std::fs::read(&path)
+                .ok()
+                .and_then(|metadata| serde_json::from_slice::<CachedResponse>(&metadata).ok())
+                .filter(|response| {
+                    std::fs::metadata(self.directory.join(&response.body_file))
+                        .is_ok_and(|metadata| metadata.len() == response.body_size)
+                });
+
+            match response {
+                Some(response) => responses.push((key, response)),
+                // Left by a run that stopped while storing it, or unreadable.
+                None => {
+                    let _result = std::fs::remove_file(&path);
+                }
+            }
+        }
+
+        // Bodies of responses that were replaced or evicted while files couldn't be
+        // removed, and files left halfway through being written.
+        let body_files = responses
+            .iter()
+            .map(|(_key, response)| response.body_file.as_str())
+            .collect::<HashSet<_>>();
+        for path in files {
+            let is_used = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .is_some_and(|file_name| body_files.contains(file_name));
+            if !is_used {
+                let _result = std::fs::remove_file(&path);
+            }
+        }
+
+        // Oldest first, so that they are the first ones evicted.
+        responses.sort_by_key(|(_key, response)| response.response_time);
+
+        let max_size_bytes = self.response_cache.read().unwrap().max_size_bytes;
+        let mut index = self.index.lock().unwrap();
+        for (key, response) in responses {
+            index.insert(key, Arc::new(response));
+        }
+        // The cache may have been made smaller since.
+        for (key, entry) in index.evict(max_size_bytes) {
+            let _result = std::fs::remove_file(get_metadata_path(&self.directory, &key));
+            let _result = std::fs::remove_file(self.directory.join(&entry.response.body_file));
+        }
+
+        log::info!(
+            "Loaded {} cached responses ({} bytes)",
+            index.entries.len(),
+            index.size
+        );
+    }
+
+    /// Looks up the response stored for a request about to be sent, adding validators
+    /// to its headers when the stored response has to be revalidated.
+    pub(crate) async fn lookup(
+        &self,
+        method: &Method,
+        url: &str,
+        request_headers: &mut HeaderMap,
+        statistics: &Statistics,
+    ) -> CacheLookup {
+        if !self.response_cache.read().unwrap().enabled || method != Method::GET {
+            return CacheLookup::Bypassed;
+        }
+
+        let request_cache_control = CacheControl::from_request(request_headers);
+        if request_cache_control.no_store
+            || CONDITIONAL_REQUEST_HEADERS
+                .iter()
+                .any(|name| request_headers.contains_key(name))
+        {
+            return CacheLookup::Bypassed;
+        }
+
+        let key = get_key(url);
+        let cached_response = {
+            let mut index = self.index.lock().unwrap();
+            let last_used = index.tick();
+
+            index.entries.get_mut(&key).and_then(|entry| {
+                if entry.response.url != url || !entry.response.matches_vary(request_headers) {
+                    return None;
+                }
+
+                entry.last_used = last_used;
+                Some(entry.response.clone())
+            })
+        };
+
+        let now = Utc::now();
+        let cached_response = match cached_response {
+            Some(cached_response) => cached_response,
+            None => return CacheLookup::Miss(self.get_cache_request(key, url, request_headers)),
+        };
+
+        let cache_control = cached_response.get_cache_control();
+        let age = cached_response.get_age(now);
+        let is_fresh = age < cached_response.get_freshness_lifetime(&cache_control)
+            && !cache_control.no_cache
+            && !request_cache_control.no_cache
+            && request_cache_control
+                .max_age
+                .is_none_or(|max_age| age <= Duration::seconds(max_age));
+
+        if is_fresh {
+            match self.read_response(&cached_response, age).await {
+                Ok(response) => {
+                    statistics.increment_cache_hits();
+                    return CacheLookup::Hit(response);
+                }
+                Err(err) => {
+                    log::warn!("Unable to read cached response of {}: {}", url, err);
+                    self.remove(&key);
+                    return CacheLookup::Miss(self.get_cache_request(key, url, request_headers));
+                }
+            }
+        }
+
+        let etag = cached_response
+            .get_header(&header::ETAG)
+            .and_then(|etag| HeaderValue::from_str(etag).ok());
+        let last_modified = cached_response
+            .get_header(&header::LAST_MODIFIED)
+            .and_then(|last_modified| HeaderValue::from_str(last_modified).ok());
+        if etag.is_none() && last_modified.is_none() {
+            return CacheLookup::Miss(self.get_cache_request(key, url, request_headers));
+        }
+
+        if let Some(etag) = etag {
+            request_headers.insert(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request_headers.insert(header::IF_MODIFIED_SINCE, last_modified);
+        }
+
+        CacheLookup::Revalidating(
+            self.get_cache_request(key, url, request_headers),
+            cached_response,
+        )
+    }
+
+    fn get_cache_request(&self, key: String, url: &str, headers: &HeaderMap) -> CacheRequest {
+        CacheRequest {
+            key,
+            url: url.to_string(),
+            headers: headers.clone(),
+            time: Utc::now(),
+        }
+    }
+
+    /// Stores the response of the server when it can be, serving the stored response
+    /// instead when the server answered it didn't change.
+    pub(crate) async fn store_response(
+        &self,
+        cache_lookup: CacheLookup,
+        method: &Method,
+        url: &str,
+        response: reqwest::Response,
+        statistics: &Statistics,
+    ) -> reqwest::Response {
+        // Unsafe methods succeeding change the resource, the stored response is stale.
+        if !method.is_safe()
+            && (response.status().is_success() || response.status().is_redirection())
+        {
+            self.remove(&get_key(url));
+            return response;
+        }
+
+        let (cache_request, revalidated_response) = match cache_lookup {
+            CacheLookup::Miss(cache_request) => (cache_request, None),
+            CacheLookup::Revalidating(cache_request, cached_response) => {
+                (cache_request, Some(cached_response))
+            }
+            CacheLookup::Hit(_) | CacheLookup::Bypassed => return response,
+        };
+
+        if let Some(cached_response) = revalidated_response {
+            if response.status() == StatusCode::NOT_MODIFIED {
+                match self
+                    .refresh(&cache_request, &cached_response, response.headers())
+                    .await
+                {
+                    Ok(response) => {
+                        statistics.increment_cache_revalidations();
+                        return response;
+                    }
+                    Err(err) => {
+                        log::warn!("Unable to refresh cached response of {}: {}", url, err);
+                        self.remove(&cache_request.key);
+                        return response;
+                    }
+                }
+            }
+        }
+
+        statistics.increment_cache_misses();
+
+        let max_entry_size_bytes = self.response_cache.read().unwrap().max_entry_size_bytes;
+        if !is_storable(&cache_request.headers, &response, max_entry_size_bytes) {
+            return response;
+        }
+
+        self.record(cache_request, response, max_entry_size_bytes)
+    }
+
+    /// Stores the body of a response as it is relayed, once it has been read whole.
+    fn record(
+        &self,
+        cache_request: CacheRequest,
+        response: reqwest::Response,
+        max_entry_size_bytes: u64,
+    ) -> reqwest::Response {
+        let cached_response = CachedResponse {
+            url: cache_request.url,
+            status: response.status().as_u16(),
+            headers: get_storable_headers(response.headers()),
+            vary: get_vary(response.headers(), &cache_request.headers),
+            request_time: cache_request.time,
+            response_time: Utc::now(),
+            body_file: format!(
+                "{}.{}.{}",
+                cache_request.key,
+                uuid::Uuid::new_v4(),
+                BODY_EXTENSION
+            ),
+            body_size: 0,
+        };
+
+        let mut relayed_response = Response::new(Body::empty());
+        *relayed_response.status_mut() = response.status();
+        *relayed_response.version_mut() = response.version();
+        *relayed_response.headers_mut() = response.headers().clone();
+
+        // Dropped as soon as the body turns out to be too large, or fails to be read.
+        let body = Arc::new(Mutex::new(Some(Vec::new())));
+        let recorded_body = body.clone();
+        let store = self.clone();
+        let key = cache_request.key;
+
+        let body_stream = response
+            .bytes_stream()
+            .map(move |chunk| {
+                let mut recorded_body = recorded_body.lock().unwrap();
+                match &chunk {
+                    Ok(chunk) => {
+                        let is_too_large = recorded_body.as_ref().is_some_and(|body| {
+                            (body.len() + chunk.len()) as u64 > max_entry_size_bytes
+                        });
+                        if is_too_large {
+                            *recorded_body = None;
+                        } else if let Some(body) = recorded_body.as_mut() {
+                            body.extend_from_slice(chunk);
+                        }
+                    }
+                    Err(_err) => *recorded_body = None,
+                }
+                chunk
+            })
+            // Only reached once the whole body was relayed.
+            .chain(
+                stream::once(async move {
+                    let body = body.lock().unwrap().take();
+                    if let Some(body) = body {
+                        tokio::spawn(async move { store.insert(key, cached_response, body).await });
+                    }
+                })
+                .filter_map(|()| async { None::<Result<Bytes, reqwest::Error>> }),
+            );
+        *relayed_response.body_mut() = Body::wrap_stream(body_stream);
+
+        reqwest::Response::from(relayed_response)
+    }
+
+    async fn insert(&self, key: String, mut cached_response: CachedResponse, body: Vec<u8>) {
+        cached_response.body_size = body.len() as u64;
+
+        let body_path = self.directory.join(&cached_response.body_file);
+        let result = async {
+            tokio::fs::create_dir_all(&self.directory).await?;
+            tokio::fs::write(&body_path, &body).await?;
+            self.write_metadata(&key, &cached_response).await
+        }
+        .await;
+        if let Err(err) = result {
+            log::warn!(
+                "Unable to store response of {}: {}",
+                cached_response.url,
+                err
+            );
+            let _result = tokio::fs::remove_file(&body_path).await;
+            return;
+        }
+
+        let max_size_bytes = self.response_cache.read().unwrap().max_size_bytes;
+        let (replaced, evicted) = {
+            let mut index = self.index.lock().unwrap();
+            let replaced = index.insert(key.clone(), Arc::new(cached_response));
+            (replaced, index.evict(max_size_bytes))
+        };
+
+        // The metadata file now belongs to the new response.
+        if let Some(replaced) = replaced {
+            let _result =
+                tokio::fs::remove_file(self.directory.join(&replaced.response.body_file)).await;
+        }
+        self.remove_files(evicted);
+    }
+
+    /// Serves a stored response again once the server answered it didn't change,
+    /// updating it with the headers of that answer.
+    async fn refresh(
+        &self,
+        cache_request: &CacheRequest,
+        cached_response: &CachedResponse,
+        not_modified_headers: &HeaderMap,
+    ) -> std::io::Result<reqwest::Response> {
+        let mut refreshed_response = cached_response.clone();
+        let updated_headers = get_storable_headers(not_modified_headers)
+            .into_iter()
+            .filter(|(name, _value)| {
+                !NOT_MODIFIED_IGNORED_HEADERS
+                    .iter()
+                    .any(|ignored| ignored.as_str() == name)
+            })
+            .collect::<Vec<_>>();
+        refreshed_response.headers.retain(|(name, _value)| {
+            !updated_headers
+                .iter()
+                .any(|(updated_name, _value)| updated_name == name)
+        });
+        refreshed_response.headers.extend(updated_headers);
+        refreshed_response.request_time = cache_request.time;
+        refreshed_response.response_time = Utc::now();
+
+        self.write_metadata(&cache_request.key, &refreshed_response)
+            .await?;
+
+        let refreshed_response = Arc::new(refreshed_response);
+        {
+            let mut index = self.index.lock().unwrap();
+            // Unless another response was stored meanwhile.
+            let is_current = index
+                .entries
+                .get(&cache_request.key)
+                .is_some_and(|entry| entry.response.body_file == refreshed_response.body_file);
+            if is_current {
+                index.insert(cache_request.key.clone(), refreshed_response.clone());
+            }
+        }
+
+        self.read_response(&refreshed_response, refreshed_response.get_age(Utc::now()))
+            .await
+    }
+
+    async fn write_metadata(
+        &self,
+        key: &str,
+        cached_response: &CachedResponse,
+    ) -> std::io::Result<()> {
+        let metadata = serde_json::to_vec(cached_response)?;
+
+        // Written aside first, so that a response is never read half written.
+        let temporary_path = self
+            .directory
+            .join(format!("{}.{}.tmp", key, uuid::Uuid::new_v4()));
+        tokio::fs::write(&temporary_path, metadata).await?;
+        if let Err(err) =
+            tokio::fs::rename(&temporary_path, get_metadata_path(&self.directory, key)).await
+        {
+            let _result = tokio::fs::remove_file(&temporary_path).await;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    async fn read_response(
+        &self,
+        cached_response: &CachedResponse,
+        age: Duration,
+    ) -> std::io::Result<reqwest::Response> {
+        let body = tokio::fs::read(self.directory.join(&cached_response.body_file)).await?;
+
+        let mut response = Response::new(Body::from(body));
+        *response.status_mut() = StatusCode::from_u16(cached_response.status)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        for (name, value) in &cached_response.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                response.headers_mut().append(name, value);
+            }
+        }
+        response
+            .headers_mut()
+            .insert(header::AGE, HeaderValue::from(age.num_seconds().max(0)));
+
+        Ok(reqwest::Response::from(response))
+    }
+
+    fn remove(&self, key: &str) {
+        let removed = self.index.lock().unwrap().remove(key);
+        if let Some(removed) = removed {
+            self.remove_files(vec![(key.to_string(), removed)]);
+        }
+    }
+
+    fn remove_files(&self, entries: Vec<(String, IndexEntry)>) {
+        if entries.is_empty() {
+            return;
+        }
+
+        let directory = self.directory.clone();
+        tokio::spawn(async move {
+            for (key, entry) in entries {
+                let _result = tokio::fs::remove_file(get_metadata_path(&directory, &key)).await;
+                let _result =
+                    tokio::fs::remove_file(directory.join(&entry.response.body_file)).await;
+            }
+        });
+    }
+
+    /// Removes the stored responses of `host`, or every one of them. Returns how many
+    /// were removed.
+    pub(crate) fn purge(&self, host: Option<&str>) -> usize {
+        let purged = {
+            let mut index = self.index.lock().unwrap();
+            let keys = index
+                .entries
+                .iter()
+                .filter(|(_key, entry)| {
+                    host.is_none_or(|host| entry.response.get_host().as_deref() == Some(host))
+                })
+                .map(|(key, _entry)| key.clone())
+                .collect::<Vec<_>>();
+
+            keys.into_iter()
+                .filter_map(|key| {
+                    let entry = index.remove(&key)?;
+                    Some((key, entry))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let count = purged.len();
+        self.remove_files(purged);
+        count
+    }
+
+    pub(crate) fn get_status(&self) -> ResponseCacheStatus {
+        let response_cache = self.response_cache.read().unwrap();
+        let index = self.index.lock().unwrap();
+
+        ResponseCacheStatus {
+            enabled: response_cache.enabled,
+            entries: index.entries.len(),
+            size_bytes: index.size,
+            max_size_bytes: response_cache.max_size_bytes,
+        }
+    }
+}
+
+fn get_key(url: &str) -> String {
+    hex::encode(Sha256::digest(url.as_bytes()))
+}
+
+fn get_metadata_path(directory: &Path, key: &str) -> PathBuf {
+    directory.join(format!("{}.{}", key, METADATA_EXTENSION))
+}
+
+/// Headers of a response that can be saved, those whose value is text.
+fn get_storable_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// Request headers named by the `Vary` headers of a response, with their values.
+fn get_vary(
+    response_headers: &HeaderMap,
+    request_headers: &HeaderMap,
+) -> Vec<(String, Option<String>)> {
+    response_headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let value = request_headers
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            (name, value)
+        })
+        .collect()
+}
+
+/// Whether a response to a `GET` request can be stored by a shared cache.
+fn is_storable(
+    request_headers: &HeaderMap,
+    response: &reqwest::Response,
+    max_entry_size_bytes: u64,
+) -> bool {
+    if !CACHEABLE_STATUS_CODES.contains(&response.status().as_u16()) {
+        return false;
+    }
+
+    let headers = response.headers();
+    let cache_control = CacheControl::parse(
+        headers
+            .get_all(header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok()),
+    );
+    if cache_control.no_store || cache_control.private {
+        return false;
+    }
+
+    // Responses to credentials are meant for their user, unless the server says
+    // otherwise.
+    if request_headers.contains_key(header::AUTHORIZATION)
+        && !(cache_control.public
+            || cache_control.s_maxage.is_some()
+            || cache_control.must_revalidate)
+    {
+        return false;
+    }
+
+    // Cookies of a client must not be handed to the others.
+    if headers.contains_key(header::SET_COOKIE) {
+        return false;
+    }
+
+    let varies_on_everything = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|name| name.trim() == "*");
+    if varies_on_everything {
+        return false;
+    }
+
+    if response
+        .content_length()
+        .is_some_and(|content_length| content_length > max_entry_size_bytes)
+    {
+        return false;
+    }
+
+    let has_freshness = cache_control.max_age.is_some()
+        || cache_control.s_maxage.is_some()
+        || headers.contains_key(header::EXPIRES);
+    let has_validator =
+        headers.contains_key(header::ETAG) || headers.contains_key(header::LAST_MODIFIED);
+
+    has_freshness || has_validator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_ENTRY_SIZE_BYTES: u64 = 1024 * 1024;
+
+    fn get_headers(headers: &[(&'static str, &'static str)]) -> HeaderMap {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    fn get_response(headers: &[(&'static str, &'static str)]) -> reqwest::Response {
+        let mut response = Response::new(Body::empty());
+        *response.headers_mut() = get_headers(headers);
+
+        reqwest::Response::from(response)
+    }
+
+    #[test]
+    fn cache_control_directives_are_parsed() {
+        let cache_control = CacheControl::parse(
+            ["Public, MAX-AGE=60", "s-maxage=\"120\", must-revalidate"].into_iter(),
+        );
+
+        assert!(cache_control.public);
+        assert!(cache_control.must_revalidate);
+        assert!(!cache_control.no_store);
+        assert_eq!(cache_control.max_age, Some(60));
+        assert_eq!(cache_control.s_maxage, Some(120));
+    }
+
+    #[test]
+    fn invalid_ages_are_ignored() {
+        let cache_control = CacheControl::parse(["max-age=soon, no-store"].into_iter());
+
+        assert!(cache_control.no_store);
+        assert_eq!(cache_control.max_age, None);
+    }
+
+    #[test]
+    fn pragma_only_applies_without_cache_control() {
+        let pragma = get_headers(&[("pragma", "no-cache")]);
+        assert!(CacheControl::from_request(&pragma).no_cache);
+
+        let cache_control = get_headers(&[("pragma", "no-cache"), ("cache-control", "max-age=0")]);
+        assert!(!CacheControl::from_request(&cache_control).no_cache);
+    }
+
+    #[test]
+    fn vary_records_the_request_headers() {
+        let response_headers = get_headers(&[("vary", "Accept-Encoding, , Accept-Language")]);
+        let request_headers = get_headers(&[("accept-encoding", "gzip")]);
+
+        assert_eq!(
+            get_vary(&response_headers, &request_headers),
+            vec![
+                ("accept-encoding".to_string(), Some("gzip".to_string())),
+                ("accept-language".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn responses_varying_on_everything_are_not_stored() {
+        let request_headers = HeaderMap::new();
+
+        assert!(is_storable(
+            &request_headers,
+            &get_response(&[("cache-control", "max-age=60"), ("vary", "accept-encoding")]),
+            MAX_ENTRY_SIZE_BYTES
+        ));
+        assert!(!is_storable(
+            &request_headers,
+            &get_response(&[
+                ("cache-control", "max-age=60"),
+                ("vary", "accept-encoding, *")
+            ]),
+            MAX_ENTRY_SIZE_BYTES
+        ));
+    }
+
+    #[test]
+    fn private_responses_are_not_stored() {
+        let request_headers = HeaderMap::new();
+
+        assert!(!is_storable(
+            &request_headers,
+            &get_response(&[("cache-control", "private, max-age=60")]),
+            MAX_ENTRY_SIZE_BYTES
+        ));
+        assert!(!is_storable(
+            &request_headers,
+            &get_response(&[("cache-control", "no-store")]),
+            MAX_ENTRY_SIZE_BYTES
+        ));
+    }
+
+    #[test]
+    fn authorized_responses_are_stored_when_public() {
+        let request_headers = get_headers(&[("authorization", "Bearer token")]);
+
+        assert!(!is_storable(
+            &request_headers,
+            &get_response(&[("cache-control", "max-age=60")]),
+            MAX_ENTRY_SIZE_BYTES
+        ));
+        assert!(is_storable(
+            &request_headers,
+            &get_response(&[("cache-control", "public, max-age=60")]),
+            MAX_ENTRY_SIZE_BYTES
+        ));
+    }
+
+    #[test]
+    fn responses_need_a_freshness_or_a_validator() {
+        let request_headers = HeaderMap::new();
+
+        assert!(!is_storable(
+            &request_headers,
+            &get_response(&[]),
+            MAX_ENTRY_SIZE_BYTES
+        ));
+        assert!(is_storable(
+            &request_headers,
+            &get_response(&[("etag", "\"1\"")]),
+            MAX_ENTRY_SIZE_BYTES
+        ));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::configuration::{SafeSearch, SearchEngine};
+use http::Uri;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use std::sync::{Arc, RwLock};
+use url::Url;
+
+/// Hosts YouTube serves its pages and API from, all of which honor the
+/// `YouTube-Restrict` header.
+const YOUTUBE_HOSTS: [&str; 6] = [
+    "youtube.com",
+    "www.youtube.com",
+    "m.youtube.com",
+    "youtubei.googleapis.com",
+    "youtube.googleapis.com",
+    "www.youtube-nocookie.com",
+];
+
+const YOUTUBE_RESTRICT_HEADER: &str = "youtube-restrict";
+
+/// Cookie in which DuckDuckGo keeps the SafeSearch setting, `1` being strict.
+const DUCKDUCKGO_SAFE_SEARCH_COOKIE: &str = "p";
+
+#[derive(Debug, Clone)]
+pub struct SafeSearchStore(Arc<RwLock<SafeSearch>>);
+
+impl SafeSearchStore {
+    pub fn new(safe_search: SafeSearch) -> Self {
+        Self(Arc::new(RwLock::new(safe_search)))
+    }
+
+    pub fn replace(&self, safe_search: SafeSearch) {
+        *self.0.write().unwrap() = safe_search;
+    }
+
+    /// Makes a request to a search engine ask for its SafeSearch results, by adding
+    /// the headers and cookies it honors and returning the URL to request in place of
+    /// `uri` when its query has to change.
+    pub(crate) fn apply(&self, uri: &Uri, headers: &mut HeaderMap) -> Option<Url> {
+        let host = uri.host()?.to_lowercase();
+        let search_engine = get_search_engine(&host)?;
+
+        if !self.0.read().unwrap().is_enforced(search_engine) {
+            return None;
+        }
+
+        match search_engine {
+            SearchEngine::Google if uri.path() == "/search" => {
+                set_query_parameter(uri, "safe", "active")
+            }
+            // Images and videos are searched from `/images/search` and `/videos/search`.
+            SearchEngine::Bing if uri.path().ends_with("/search") => {
+                set_query_parameter(uri, "adlt", "strict")
+            }
+            SearchEngine::DuckDuckGo => {
+                set_cookie(headers, DUCKDUCKGO_SAFE_SEARCH_COOKIE, "1");
+
+                // Results are also fetched by scripts, with the query of the page.
+                get_query_parameter(uri, "q").and_then(|_query| set_query_parameter(uri, "kp", "1"))
+            }
+            SearchEngine::YouTube => {
+                headers.insert(
+                    HeaderName::from_static(YOUTUBE_RESTRICT_HEADER),
+                    HeaderValue::from_static("Strict"),
+                );
+
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+fn get_search_engine(host: &str) -> Option<SearchEngine> {
+    if YOUTUBE_HOSTS.contains(&host) {
+        return Some(SearchEngine::YouTube);
+    }
+
+    if host == "duckduckgo.com" || host.ends_with(".duckduckgo.com") {
+        return Some(SearchEngine::DuckDuckGo);
+    }
+
+    match host.strip_prefix("www.").unwrap_or(host).split_once('.') {
+        // Google has a domain per country, such as `google.co.uk`.
+        Some(("google", top_level_domain)) if is_top_level_domain(top_level_domain) => {
+            Some(SearchEngine::Google)
+        }
+        Some(("bing", "com")) => Some(SearchEngine::Bing),
+        _ => None,
+    }
+}
+
+fn is_top_level_domain(domain: &str) -> bool {
+    domain.split('.').count() <= 2
+        && domain
+            .split('.')
+            .all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+fn get_query_parameter(uri: &Uri, name: &str) -> Option<String> {
+    url::form_urlencoded::parse(uri.query()?.as_bytes())
+        .find(|(key, _value)| key == name)
+        .map(|(_key, value)| value.into_owned())
+}
+
+/// Returns the URL of `uri` with `name` set to `value` in its query, unless it
+/// already was.
+fn set_query_parameter(uri: &Uri, name: &str, value: &str) -> Option<Url> {
+    let pairs = url::form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes())
+        .into_owned()
+        .collect::<Vec<_>>();
+
+    let mut values = pairs.iter().filter(|(key, _value)| key == name);
+    if let (Some((_key, current_value)), None) = (values.next(), values.next()) {
+        if current_value == value {
+            return None;
+        }
+    }
+
+    let mut url = Url::parse(&uri.to_string()).ok()?;
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs.iter().filter(|(key, _value)| key != name))
+        .append_pair(name, value);
+
+    Some(url)
+}
+
+/// Sets a cookie of the request, replacing the value the client sent for it.
+fn set_cookie(headers: &mut HeaderMap, name: &str, value: &str) {
+    let prefix = format!("{}=", name);
+
+    let mut cookies = headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|cookie| cookie.to_str().ok())
+        .flat_map(|cookie| cookie.split(';'))
+        .map(str::trim)
+        .filter(|cookie| !cookie.is_empty() && !cookie.starts_with(&prefix))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    cookies.push(format!("{}{}", prefix, value));
+
+    if let Ok(cookie) = HeaderValue::from_str(&cookies.join("; ")) {
+        headers.insert(header::COOKIE, cookie);
+    }
+}
@@ -0,0 +1,822 @@
+//! Lua scripts, a lighter alternative to plugins for quick customization, called for
+//! requests before they are matched against filters. See `docs/scripts.md` for how they
+//! are written.
+
+use crate::configuration::{get_scripts_directory, ResourceType, Scripts};
+use crate::web_gui::events::StatusEvent;
+use chrono::{DateTime, Utc};
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::{Body, Method, Response, StatusCode, Uri};
+use mlua::{Function, HookTriggers, Lua, LuaOptions, RegistryKey, StdLib, Table, Value};
+use notify::{RecursiveMode, Watcher};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+const SCRIPT_EXTENSION: &str = "lua";
+const ON_REQUEST_FUNCTION: &str = "on_request";
+
+/// Lua states kept for each script, as many requests can run a script at once.
+const STATES_PER_SCRIPT: usize = 4;
+
+/// Instructions run between checks of the instruction limit, checking after every one of
+/// them would slow scripts down a lot.
+const INSTRUCTIONS_PER_CHECK: u32 = 1000;
+
+/// Base library functions removed from scripts, they read files, load code or manage
+/// memory behind the back of the memory limit.
+const REMOVED_GLOBALS: [&str; 5] = ["dofile", "loadfile", "load", "require", "collectgarbage"];
+
+/// Editors usually write a file in several steps, scripts are only reloaded once changes
+/// have settled for this long.
+const SCRIPTS_DIRECTORY_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Helpers scripts build their verdicts with.
+const VERDICT_PRELUDE: &str = r#"
+verdict = {
+    allow = function()
+        return { action = "allow" }
+    end,
+    block = function(reason)
+        return { action = "block", reason = reason }
+    end,
+    redirect = function(url)
+        return { action = "redirect", url = url }
+    end,
+    respond = function(status, body, content_type)
+        return { action = "respond", status = status, body = body, content_type = content_type }
+    end,
+}
+"#;
+
+/// Script that blocked a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ScriptMatch {
+    pub script: String,
+    /// Given by the script, if any.
+    pub reason: Option<String>,
+}
+
+/// A script of the scripts directory, and how its calls fared since it was loaded.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ScriptStatus {
+    /// File name of the script, without its extension.
+    pub name: String,
+    pub loaded: bool,
+    /// Why the script could not be loaded.
+    pub load_error: Option<String>,
+    pub calls: u64,
+    pub failures: u64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+/// What a script decided for a request.
+#[derive(Debug)]
+pub(crate) enum ScriptVerdict {
+    Allow,
+    Block(ScriptMatch),
+    Redirect(String),
+    Respond {
+        status: StatusCode,
+        body: String,
+        content_type: String,
+    },
+}
+
+/// Request scripts are called for.
+pub(crate) struct ScriptRequest {
+    pub method: Method,
+    pub uri: Uri,
+    pub resource_type: ResourceType,
+    pub client: String,
+    pub user: Option<String>,
+    pub headers: HeaderMap,
+}
+
+struct ScriptState {
+    lua: Lua,
+    /// Main chunk of the script, run again in a new environment for every call.
+    chunk: RegistryKey,
+    /// Instructions run by the current call, counted by the hook of the Lua state.
+    instructions: Arc<AtomicU64>,
+}
+
+struct LoadedScript {
+    name: String,
+    /// Lua states can't be shared between threads, a call takes the first free state and
+    /// holds it for as long as the instruction limit lets it run.
+    states: Vec<Mutex<ScriptState>>,
+    /// State waited for when they are all busy.
+    next_state: AtomicUsize,
+}
+
+impl LoadedScript {
+    fn lock_state(&self) -> MutexGuard<'_, ScriptState> {
+        for state in &self.states {
+            if let Ok(state) = state.try_lock() {
+                return state;
+            }
+        }
+
+        let index = self.next_state.fetch_add(1, Ordering::Relaxed) % self.states.len();
+        self.states[index].lock().unwrap()
+    }
+}
+
+impl fmt::Debug for LoadedScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoadedScript")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+struct LoadedScripts {
+    configuration: Scripts,
+    scripts: Vec<LoadedScript>,
+    load_errors: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct ScriptCounters {
+    calls: u64,
+    failures: u64,
+    last_error: Option<String>,
+    last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScriptStore {
+    scripts: Arc<RwLock<LoadedScripts>>,
+    /// Keyed by script name, reset when scripts are reloaded.
+    counters: Arc<Mutex<HashMap<String, ScriptCounters>>>,
+    status_sender: broadcast::Sender<StatusEvent>,
+}
+
+impl ScriptStore {
+    pub fn new(scripts: Scripts, status_sender: broadcast::Sender<StatusEvent>) -> Self {
+        let store = Self {
+            scripts: Arc::new(RwLock::new(LoadedScripts::default())),
+            counters: Arc::new(Mutex::new(HashMap::new())),
+            status_sender,
+        };
+        store.replace(scripts);
+
+        store
+    }
+
+    pub fn replace(&self, scripts: Scripts) {
+        self.scripts.write().unwrap().configuration = scripts;
+        self.reload();
+    }
+
+    /// Loads the scripts of the scripts directory again, returning how many of them
+    /// were loaded.
+    pub(crate) fn reload(&self) -> usize {
+        let configuration = self.scripts.read().unwrap().configuration.clone();
+
+        let mut scripts = Vec::new();
+        let mut load_errors = Vec::new();
+
+        if configuration.enabled {
+            for (name, path) in get_script_files(&get_scripts_directory()) {
+                let script = std::fs::read_to_string(&path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|source| load_script(&name, &source, &configuration));
+
+                match script {
+                    Ok(script) => {
+                        log::info!("Loaded script {}", name);
+                        scripts.push(script);
+                    }
+                    Err(err) => {
+                        log::error!("Unable to load script {}: {}", name, err);
+
+                        let _result = self.status_sender.send(StatusEvent::ScriptFailed {
+                            now: Utc::now(),
+                            name: name.clone(),
+                            error: err.clone(),
+                        });
+                        load_errors.push((name, err));
+                    }
+                }
+            }
+        }
+
+        let loaded = scripts.len();
+
+        // Counters describe the scripts as they were loaded, a script that failed is
+        // reported again once it was edited.
+        self.counters.lock().unwrap().clear();
+
+        let mut loaded_scripts = self.scripts.write().unwrap();
+        loaded_scripts.scripts = scripts;
+        loaded_scripts.load_errors = load_errors;
+
+        loaded
+    }
+
+    /// Calls scripts in order on the blocking thread pool, until one of them returns a
+    /// verdict.
+    pub(crate) async fn on_request(&self, request: ScriptRequest) -> Option<ScriptVerdict> {
+        if self.scripts.read().unwrap().scripts.is_empty() {
+            return None;
+        }
+
+        let script_store = self.clone();
+
+        match tokio::task::spawn_blocking(move || script_store.call_scripts(&request)).await {
+            Ok(verdict) => verdict,
+            Err(err) => {
+                log::error!("Unable to call scripts: {}", err);
+                None
+            }
+        }
+    }
+
+    fn call_scripts(&self, request: &ScriptRequest) -> Option<ScriptVerdict> {
+        let scripts = self.scripts.read().unwrap();
+
+        for script in &scripts.scripts {
+            let result = call_script(script, request, scripts.configuration.max_instructions);
+            self.record_call(&script.name, result.as_ref().err());
+
+            if let Ok(Some(verdict)) = result {
+                log::debug!(
+                    "Script {} returned {:?} for {}",
+                    script.name,
+                    verdict,
+                    request.uri
+                );
+                return Some(verdict);
+            }
+        }
+
+        None
+    }
+
+    fn record_call(&self, name: &str, error: Option<&String>) {
+        let mut counters = self.counters.lock().unwrap();
+        let counters = counters.entry(name.to_string()).or_default();
+        counters.calls += 1;
+
+        if let Some(error) = error {
+            log::warn!("Script {} failed: {}", name, error);
+
+            counters.failures += 1;
+            counters.last_error_at = Some(Utc::now());
+
+            // A broken script fails for most requests, the events stream would be
+            // flooded with the same error.
+            if counters.last_error.as_ref() != Some(error) {
+                counters.last_error = Some(error.clone());
+
+                let _result = self.status_sender.send(StatusEvent::ScriptFailed {
+                    now: Utc::now(),
+                    name: name.to_string(),
+                    error: error.clone(),
+                });
+            }
+        }
+    }
+
+    pub(crate) fn get_statuses(&self) -> Vec<ScriptStatus> {
+        let scripts = self.scripts.read().unwrap();
+        let counters = self.counters.lock().unwrap();
+
+        let loaded_statuses = scripts.scripts.iter().map(|script| {
+            let script_counters = counters.get(&script.name).cloned().unwrap_or_default();
+
+            ScriptStatus {
+                name: script.name.clone(),
+                loaded: true,
+                load_error: None,
+                calls: script_counters.calls,
+                failures: script_counters.failures,
+                last_error: script_counters.last_error,
+                last_error_at: script_counters.last_error_at,
+            }
+        });
+        let failed_statuses = scripts
+            .load_errors
+            .iter()
+            .map(|(name, load_error)| ScriptStatus {
+                name: name.clone(),
+                loaded: false,
+                load_error: Some(load_error.clone()),
+                calls: 0,
+                failures: 0,
+                last_error: None,
+                last_error_at: None,
+            });
+
+        let mut statuses = loaded_statuses.chain(failed_statuses).collect::<Vec<_>>();
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+
+        statuses
+    }
+}
+
+/// Scripts of a directory along with their names, sorted by name.
+fn get_script_files(directory: &Path) -> Vec<(String, PathBuf)> {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            log::error!("Unable to read {}: {}", directory.display(), err);
+            return Vec::new();
+        }
+    };
+
+    let mut script_files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_script_file(path))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some((name, path))
+        })
+        .collect::<Vec<_>>();
+    script_files.sort();
+
+    script_files
+}
+
+fn is_script_file(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new(SCRIPT_EXTENSION))
+}
+
+fn load_script(name: &str, source: &str, configuration: &Scripts) -> Result<LoadedScript, String> {
+    let states = (0..STATES_PER_SCRIPT)
+        .map(|_index| create_state(name, source, configuration))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Scripts are run once to find out whether they load and define the function.
+    let state = &states[0];
+    let on_request = get_on_request(state).map_err(|err| get_error_message(&err))?;
+    if on_request.is_none() {
+        return Err(format!(
+            "the script doesn't define an {} function",
+            ON_REQUEST_FUNCTION
+        ));
+    }
+    drop(on_request);
+    let _result = state.lua.gc_collect();
+
+    Ok(LoadedScript {
+        name: name.to_string(),
+        states: states.into_iter().map(Mutex::new).collect(),
+        next_state: AtomicUsize::new(0),
+    })
+}
+
+fn create_state(name: &str, source: &str, configuration: &Scripts) -> Result<ScriptState, String> {
+    let lua = create_sandbox().map_err(|err| get_error_message(&err))?;
+    lua.set_memory_limit(configuration.max_memory_bytes as usize)
+        .map_err(|err| get_error_message(&err))?;
+
+    let instructions = Arc::new(AtomicU64::new(0));
+    let max_instructions = configuration.max_instructions;
+    let hook_instructions = instructions.clone();
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(INSTRUCTIONS_PER_CHECK),
+        move |_lua, _debug| {
+            let instructions = hook_instructions
+                .fetch_add(INSTRUCTIONS_PER_CHECK.into(), Ordering::Relaxed)
+                + u64::from(INSTRUCTIONS_PER_CHECK);
+
+            if instructions > max_instructions {
+                return Err(mlua::Error::runtime(format!(
+                    "exceeded the limit of {} instructions",
+                    max_instructions
+                )));
+            }
+
+            Ok(())
+        },
+    );
+
+    let log_name = name.to_string();
+    let log = lua
+        .create_function(move |_lua, message: String| {
+            log::info!("Script {}: {}", log_name, message);
+            Ok(())
+        })
+        .map_err(|err| get_error_message(&err))?;
+    lua.globals()
+        .set("log", log)
+        .map_err(|err| get_error_message(&err))?;
+
+    lua.load(VERDICT_PRELUDE)
+        .set_name("verdict")
+        .exec()
+        .map_err(|err| get_error_message(&err))?;
+
+    let chunk = lua
+        .load(source)
+        .set_name(format!("{}.{}", name, SCRIPT_EXTENSION))
+        .into_function()
+        .and_then(|chunk| lua.create_registry_value(chunk))
+        .map_err(|err| get_error_message(&err))?;
+
+    Ok(ScriptState {
+        lua,
+        chunk,
+        instructions,
+    })
+}
+
+/// Creates a Lua state with the libraries scripts are given. The base library is always
+/// opened, the functions of it scripts must not have are removed.
+fn create_sandbox() -> mlua::Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8,
+        LuaOptions::default(),
+    )?;
+
+    let globals = lua.globals();
+    for name in REMOVED_GLOBALS {
+        globals.set(name, Value::Nil)?;
+    }
+    drop(globals);
+
+    // The string library is reachable from the metatable of strings, which every call
+    // shares.
+    lua.load(r#"getmetatable("").__metatable = false"#)
+        .set_name("sandbox")
+        .exec()?;
+
+    Ok(lua)
+}
+
+/// Globals of a call, copies of those of the Lua state so that nothing a call stores is
+/// seen by the next ones. Libraries are copied as well, for scripts not to replace their
+/// functions for other calls.
+fn create_environment(lua: &Lua) -> mlua::Result<Table<'_>> {
+    let globals = lua.globals();
+    let environment = lua.create_table()?;
+
+    for pair in globals.clone().pairs::<Value, Value>() {
+        let (name, value) = pair?;
+        let value = match value {
+            Value::Table(table) if table == globals => continue,
+            Value::Table(library) => {
+                let library_copy = lua.create_table()?;
+                for pair in library.pairs::<Value, Value>() {
+                    let (name, value) = pair?;
+                    library_copy.raw_set(name, value)?;
+                }
+                Value::Table(library_copy)
+            }
+            value => value,
+        };
+        environment.raw_set(name, value)?;
+    }
+    environment.raw_set("_G", environment.clone())?;
+
+    Ok(environment)
+}
+
+/// Runs the main chunk of a script in a new environment, returning the function it
+/// defined.
+fn get_on_request(state: &ScriptState) -> mlua::Result<Option<Function<'_>>> {
+    let chunk = state.lua.registry_value::<Function>(&state.chunk)?;
+    let environment = create_environment(&state.lua)?;
+    chunk.set_environment(environment.clone())?;
+    chunk.call::<_, ()>(())?;
+
+    environment.get::<_, Option<Function>>(ON_REQUEST_FUNCTION)
+}
+
+fn call_script(
+    script: &LoadedScript,
+    request: &ScriptRequest,
+    max_instructions: u64,
+) -> Result<Option<ScriptVerdict>, String> {
+    let state = script.lock_state();
+    state.instructions.store(0, Ordering::Relaxed);
+
+    let result = get_on_request(&state).and_then(|on_request| match on_request {
+        Some(on_request) => {
+            let request = create_request_table(&state.lua, request)?;
+            on_request.call::<_, Value>(request)
+        }
+        None => Err(mlua::Error::runtime(format!(
+            "the script no longer defines an {} function",
+            ON_REQUEST_FUNCTION
+        ))),
+    });
+
+    // Memory held by the call is released right away rather than counted against the
+    // next ones.
+    let _result = state.lua.gc_collect();
+
+    match result {
+        Ok(verdict) => get_verdict(&script.name, verdict),
+        Err(_err) if state.instructions.load(Ordering::Relaxed) > max_instructions => Err(format!(
+            "exceeded the limit of {} instructions",
+            max_instructions
+        )),
+        Err(mlua::Error::MemoryError(_err)) => Err("exceeded its memory limit".to_string()),
+        Err(err) => Err(get_error_message(&err)),
+    }
+}
+
+/// Lua errors come with a stack traceback, which is left out of reported errors.
+fn get_error_message(err: &mlua::Error) -> String {
+    let message = err.to_string();
+
+    match message.split_once("\nstack traceback:") {
+        Some((message, _traceback)) => message.to_string(),
+        None => message,
+    }
+}
+
+fn create_request_table<'lua>(
+    lua: &'lua Lua,
+    request: &ScriptRequest,
+) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    table.set("method", request.method.as_str())?;
+    table.set("url", request.uri.to_string())?;
+    table.set("scheme", request.uri.scheme_str())?;
+    table.set("host", request.uri.host())?;
+    table.set("path", request.uri.path())?;
+    table.set("query", request.uri.query())?;
+    table.set("resource_type", request.resource_type.as_adblock_str())?;
+    table.set("client", request.client.as_str())?;
+    table.set("user", request.user.as_deref())?;
+
+    let headers = lua.create_table()?;
+    for name in request.headers.keys() {
+        let values = request
+            .headers
+            .get_all(name)
+            .iter()
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned())
+            .collect::<Vec<_>>();
+        headers.set(name.as_str(), values.join(", "))?;
+    }
+    table.set("headers", headers)?;
+
+    Ok(table)
+}
+
+fn get_verdict(script: &str, verdict: Value) -> Result<Option<ScriptVerdict>, String> {
+    let verdict = match verdict {
+        Value::Nil => return Ok(None),
+        Value::Table(verdict) => verdict,
+        _ => {
+            return Err(format!(
+                "{} returned a {} rather than a verdict",
+                ON_REQUEST_FUNCTION,
+                verdict.type_name()
+            ))
+        }
+    };
+
+    let action = verdict
+        .get::<_, String>("action")
+        .map_err(|_err| "verdicts must be built with the verdict table".to_string())?;
+
+    match action.as_str() {
+        "allow" => Ok(Some(ScriptVerdict::Allow)),
+        "block" => Ok(Some(ScriptVerdict::Block(ScriptMatch {
+            script: script.to_string(),
+            reason: verdict
+                .get::<_, Option<String>>("reason")
+                .map_err(|err| format!("invalid reason: {}", err))?,
+        }))),
+        "redirect" => {
+            let url = verdict
+                .get::<_, String>("url")
+                .map_err(|err| format!("invalid redirect url: {}", err))?;
+
+            match url::Url::parse(&url) {
+                Ok(parsed_url) if ["http", "https"].contains(&parsed_url.scheme()) => {
+                    Ok(Some(ScriptVerdict::Redirect(url)))
+                }
+                _ => Err(format!("invalid redirect url: {:?}", url)),
+            }
+        }
+        "respond" => {
+            let status = verdict
+                .get::<_, u16>("status")
+                .ok()
+                .and_then(|status| StatusCode::from_u16(status).ok())
+                .ok_or_else(|| "invalid response status".to_string())?;
+            let body = verdict
+                .get::<_, Option<String>>("body")
+                .map_err(|err| format!("invalid response body: {}", err))?
+                .unwrap_or_default();
+            let content_type = verdict
+                .get::<_, Option<String>>("content_type")
+                .map_err(|err| format!("invalid response content type: {}", err))?
+                .unwrap_or_else(|| "text/plain; charset=utf-8".to_string());
+
+            Ok(Some(ScriptVerdict::Respond {
+                status,
+                body,
+                content_type,
+            }))
+        }
+        _ => Err(format!("unknown verdict: {:?}", action)),
+    }
+}
+
+pub(crate) fn get_script_response(
+    status: StatusCode,
+    body: String,
+    content_type: &str,
+) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+
+    let content_type = HeaderValue::from_str(content_type)
+        .unwrap_or_else(|_err| HeaderValue::from_static("text/plain; charset=utf-8"));
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, content_type);
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+
+    response
+}
+
+/// Reloads scripts whenever a script of the scripts directory changes.
+pub(crate) async fn watch_scripts_directory(script_store: ScriptStore) {
+    let scripts_directory = get_scripts_directory();
+
+    // The directory has to exist to be watched.
+    if let Err(err) = tokio::fs::create_dir_all(&scripts_directory).await {
+        log::error!("Unable to create {}: {}", scripts_directory.display(), err);
+        return;
+    }
+
+    let (paths_sender, mut paths_receiver) = tokio::sync::mpsc::unbounded_channel::<PathBuf>();
+
+    let mut watcher =
+        match notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if event.kind.is_access() => {}
+            Ok(event) => {
+                for path in event.paths {
+                    let _ = paths_sender.send(path);
+                }
+            }
+            Err(err) => log::error!("Unable to watch scripts directory: {:?}", err),
+        }) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::error!("Unable to watch scripts directory: {:?}", err);
+                return;
+            }
+        };
+
+    if let Err(err) = watcher.watch(&scripts_directory, RecursiveMode::NonRecursive) {
+        log::error!("Unable to watch {}: {:?}", scripts_directory.display(), err);
+        return;
+    }
+
+    while let Some(path) = paths_receiver.recv().await {
+        if !is_script_file(&path) {
+            continue;
+        }
+
+        tokio::time::sleep(SCRIPTS_DIRECTORY_DEBOUNCE).await;
+        while paths_receiver.try_recv().is_ok() {}
+
+        let reload_store = script_store.clone();
+        let loaded = match tokio::task::spawn_blocking(move || reload_store.reload()).await {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                log::error!("Unable to reload scripts: {}", err);
+                continue;
+            }
+        };
+
+        let _result = script_store
+            .status_sender
+            .send(StatusEvent::ScriptsReloaded {
+                now: Utc::now(),
+                loaded,
+            });
+
+        log::info!("Reloaded scripts, {} of them loaded", loaded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_request() -> ScriptRequest {
+        ScriptRequest {
+            method: Method::GET,
+            uri: Uri::from_static("https://example.com/"),
+            resource_type: ResourceType::Document,
+            client: "127.0.0.1".to_string(),
+            user: None,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    fn is_blocked(script: &LoadedScript) -> bool {
+        matches!(
+            call_script(script, &get_request(), Scripts::default().max_instructions),
+            Ok(Some(ScriptVerdict::Block(_)))
+        )
+    }
+
+    #[test]
+    fn scripts_cannot_load_code_or_read_files() {
+        let lua = create_sandbox().unwrap();
+
+        for name in REMOVED_GLOBALS {
+            assert!(
+                matches!(lua.globals().get::<_, Value>(name).unwrap(), Value::Nil),
+                "{} is available to scripts",
+                name
+            );
+        }
+
+        assert!(lua.load(r#"dofile("/etc/passwd")"#).exec().is_err());
+        assert!(lua.load(r#"load("return 1")"#).exec().is_err());
+    }
+
+    #[test]
+    fn scripts_keep_their_libraries() {
+        let lua = create_sandbox().unwrap();
+
+        let result = lua
+            .load(r#"return string.upper("a") .. math.floor(1.5) .. tostring(#{ 1, 2 })"#)
+            .eval::<String>()
+            .unwrap();
+
+        assert_eq!(result, "A12");
+    }
+
+    #[test]
+    fn globals_are_not_kept_between_calls() {
+        let script = load_script(
+            "seen",
+            r#"
+            function on_request(request)
+                if seen then
+                    return verdict.block()
+                end
+                seen = true
+                _G.seen = true
+            end
+            "#,
+            &Scripts::default(),
+        )
+        .unwrap();
+
+        assert!(!is_blocked(&script));
+        assert!(!is_blocked(&script));
+    }
+
+    #[test]
+    fn libraries_are_not_shared_between_calls() {
+        let script = load_script(
+            "upper",
+            r#"
+            function on_request(request)
+                if string.upper("a") ~= "A" then
+                    return verdict.block()
+                end
+                string.upper = string.lower
+            end
+            "#,
+            &Scripts::default(),
+        )
+        .unwrap();
+
+        assert!(!is_blocked(&script));
+        assert!(!is_blocked(&script));
+    }
+
+    #[test]
+    fn busy_states_are_skipped() {
+        let script = load_script(
+            "busy",
+            "function on_request(request) return verdict.block() end",
+            &Scripts::default(),
+        )
+        .unwrap();
+
+        let _busy_state = script.states[0].lock().unwrap();
+
+        assert!(is_blocked(&script));
+    }
+}
@@ -0,0 +1,1207 @@
+use super::block_responses::get_resource_type;
+use super::content_rewrite::read_rewritten_body;
+use super::debug_headers::add_debug_headers;
+use super::header_rules::RefererTrimmed;
+use super::html_rewriter::Rewriter;
+use super::proceed_tokens::PROCEED_PATH_PREFIX;
+use super::response_cache::CacheLookup;
+use super::scripts::{get_script_response, ScriptMatch, ScriptRequest, ScriptVerdict};
+use super::streaming::{get_passthrough_kind, PassthroughKind};
+use super::upstream_clients::HyperClient;
+use super::url_rewrites::RewrittenUrl;
+use super::ProxyContext;
+use crate::blocker::{MatchedFilter, RequestDecision};
+use crate::configuration::{BlockResponse, HeaderRuleDirection, ResourceType};
+use crate::statistics::{LatencyStage, Statistics};
+use crate::threat_intelligence::ThreatMatch;
+use crate::web_gui::events::Event;
+use crate::AppState;
+use adblock::blocker::BlockerResult;
+use base64::{engine::general_purpose, Engine};
+use futures_util::StreamExt;
+use http::uri::{Authority, Scheme};
+use http::{StatusCode, Uri};
+use hyper::body::{Bytes, HttpBody};
+use hyper::{http, Body, Request, Response};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::Instrument;
+
+/// Encodings the proxy's HTTP client decodes responses from.
+const DECODABLE_CONTENT_ENCODINGS: [&str; 4] = ["gzip", "deflate", "br", "identity"];
+
+/// Returned to hyper in place of a response so that it drops the client connection.
+#[derive(Debug, thiserror::Error)]
+#[error("connection closed in response to a blocked request")]
+pub(crate) struct ConnectionClosed;
+
+pub(crate) async fn serve(
+    context: Arc<ProxyContext>,
+    request: Request<Body>,
+    authority: Authority,
+    scheme: Scheme,
+    client_identity: String,
+    user: Option<String>,
+    // Whether the client was granted unfiltered access.
+    unfiltered: bool,
+) -> Result<Response<Body>, ConnectionClosed> {
+    let ProxyContext {
+        state,
+        upstream_clients,
+        max_inspected_body_size,
+        read_timeout,
+    } = &*context;
+    let (max_inspected_body_size, read_timeout) = (*max_inspected_body_size, *read_timeout);
+    let AppState {
+        adblock_requester,
+        events_sender: broadcast_sender,
+        statistics,
+        block_responses_store,
+        debug_headers_store,
+        circuit_breaker_store,
+        header_rules_store,
+        cookie_rules_store,
+        ip_rule_store,
+        threat_store,
+        category_store,
+        safe_search_store,
+        url_rewrite_store,
+        plugin_store,
+        script_store,
+        response_cache_store,
+        traffic_shaping_store,
+        content_rewrite_store,
+        injection_store,
+        html_filter_store,
+        proceed_token_store,
+        upstream_tls_store,
+        ..
+    } = state;
+
+    let started_at = Instant::now();
+    let scheme_string = scheme.to_string();
+
+    let uri = match http::uri::Builder::new()
+        .scheme(scheme)
+        .authority(authority)
+        .path_and_query(match request.uri().path_and_query() {
+            Some(path_and_query) => path_and_query.as_str(),
+            None => "/",
+        })
+        .build()
+    {
+        Ok(uri) => uri,
+        Err(_err) => {
+            return Ok(get_empty_response(http::StatusCode::BAD_REQUEST));
+        }
+    };
+
+    // Names are checked as they are resolved, addresses aren't resolved.
+    if let Some(error) = ip_rule_store.get_address_error(uri.host().unwrap()) {
+        log::debug!("Blocked request to address: {}", uri);
+        return Ok(get_informative_error_response(&error));
+    }
+
+    if request.headers().contains_key(http::header::UPGRADE) {
+        let hyper_client = upstream_clients
+            .get(uri.host().unwrap())
+            .hyper_client
+            .clone();
+        return Ok(perform_two_ends_upgrade(request, uri, hyper_client).await);
+    }
+
+    // Link followed from an interstitial page.
+    if let Some(token) = uri.path().strip_prefix(PROCEED_PATH_PREFIX) {
+        if let Some(url) = proceed_token_store.redeem(uri.host().unwrap(), token) {
+            log::info!(
+                "Letting pages of {} through, as chosen from an interstitial page",
+                uri.host().unwrap()
+            );
+
+            return Ok(get_redirect_response(&url));
+        }
+    }
+
+    let (mut parts, body) = request.into_parts();
+    parts.uri = uri.clone();
+
+    let (sender, new_body) = Body::channel();
+
+    let req = Request::from_parts(parts, body);
+    let resource_type = get_resource_type(&req);
+
+    log::debug!("{} {}", req.method(), req.uri());
+
+    statistics.increment_top_clients(client_identity.clone());
+    if let Some(user) = &user {
+        statistics.increment_top_users(user.clone());
+    }
+    // Requests of authenticated proxy users are attributed to them rather than to the
+    // device they came from.
+    let attributed_client = user.clone().unwrap_or_else(|| client_identity.clone());
+    statistics.increment_client_requests(&attributed_client);
+
+    // Known malware and phishing addresses are blocked whether blocking is enabled or not.
+    if let Some(threat) = (!unfiltered)
+        .then(|| threat_store.get_match(&uri))
+        .flatten()
+    {
+        log::debug!(
+            "Blocked request listed as a threat by {}: {}",
+            threat.feed,
+            uri
+        );
+
+        record_block(
+            &req,
+            EarlyBlock::Threat(threat.clone()),
+            client_identity,
+            &attributed_client,
+            user,
+            statistics,
+            broadcast_sender,
+        );
+
+        return Ok(get_threat_response(&uri, &threat, resource_type));
+    }
+
+    // Categories are parental controls, they apply whether blocking is enabled or not.
+    if let Some(category) = (!unfiltered)
+        .then(|| category_store.get_match(uri.host().unwrap(), &client_identity, user.as_deref()))
+        .flatten()
+    {
+        log::debug!("Blocked request in category {}: {}", category, uri);
+
+        record_block(
+            &req,
+            EarlyBlock::Category(category.clone()),
+            client_identity,
+            &attributed_client,
+            user,
+            statistics,
+            broadcast_sender,
+        );
+
+        return Ok(get_category_response(&uri, &category, resource_type));
+    }
+
+    // Scripts come before filters, so that they can let requests through.
+    let script_verdict = if unfiltered {
+        None
+    } else {
+        script_store
+            .on_request(ScriptRequest {
+                method: req.method().clone(),
+                uri: uri.clone(),
+                resource_type,
+                client: client_identity.clone(),
+                user: user.clone(),
+                headers: req.headers().clone(),
+            })
+            .await
+    };
+
+    let allowed_by_script = match script_verdict {
+        Some(ScriptVerdict::Block(script_match)) => {
+            log::debug!(
+                "Blocked request with script {}: {}",
+                script_match.script,
+                uri
+            );
+
+            record_block(
+                &req,
+                EarlyBlock::Script(script_match),
+                client_identity,
+                &attributed_client,
+                user,
+                statistics,
+                broadcast_sender,
+            );
+
+            let (status_code, block_page_template) = block_responses_store.get_block_page();
+
+            return get_blocked_response(
+                BlockerResult::default(),
+                resource_type,
+                block_responses_store.get(resource_type),
+                BlockPage {
+                    status_code,
+                    template: block_page_template,
+                    url: &uri,
+                    filter: None,
+                    proceed_url: None,
+                },
+            );
+        }
+        Some(ScriptVerdict::Redirect(url)) => {
+            log::debug!("Redirected {} to {} with a script", uri, url);
+            return Ok(get_redirect_response(&url));
+        }
+        Some(ScriptVerdict::Respond {
+            status,
+            body,
+            content_type,
+        }) => {
+            log::debug!("Responded to {} with a script", uri);
+            return Ok(get_script_response(status, body, &content_type));
+        }
+        Some(ScriptVerdict::Allow) => true,
+        None => false,
+    };
+
+    let (blocker_result, decision) = if unfiltered || allowed_by_script {
+        (BlockerResult::default(), RequestDecision::default())
+    } else {
+        let filter_match_started_at = Instant::now();
+
+        let result = adblock_requester
+            .is_network_url_blocked(
+                uri.to_string(),
+                match req.headers().get(http::header::REFERER) {
+                    Some(referer) => referer.to_str().unwrap().to_string(),
+                    // When no referer, we default to `uri` as we otherwise may get many false
+                    // positives due to the blocker thinking it's third party requests.
+                    None => uri.to_string(),
+                },
+                resource_type,
+            )
+            .instrument(tracing::info_span!("filter_match"))
+            .await;
+
+        statistics.record_latency(LatencyStage::FilterMatch, filter_match_started_at.elapsed());
+
+        result
+    };
+
+    // Clients may have chosen to visit the pages of this host anyway.
+    let is_request_blocked = decision.blocked
+        && !(resource_type == ResourceType::Document
+            && proceed_token_store.is_proceeding(uri.host().unwrap()));
+    let blocking_filter = if is_request_blocked {
+        decision.filter.clone()
+    } else {
+        None
+    };
+
+    let csp = decision.csp.clone();
+
+    // Only kept around when responses are annotated with it.
+    let debug_decision = debug_headers_store.is_enabled().then(|| decision.clone());
+
+    let request_id =
+        statistics.record_decision(req.method().to_string(), req.uri().to_string(), decision);
+    crate::logging::set_request_id(request_id);
+
+    let _result = broadcast_sender.send(Event {
+        id: request_id,
+        now: chrono::Utc::now(),
+        method: req.method().to_string(),
+        url: req.uri().to_string(),
+        is_request_blocked,
+        client: client_identity.clone(),
+        user: user.clone(),
+        filter: blocking_filter.clone(),
+        threat: None,
+        category: None,
+        script: None,
+    });
+
+    if is_request_blocked {
+        statistics.increment_blocked_requests();
+        statistics.increment_blocked_clients(client_identity);
+        statistics.increment_client_blocked_requests(&attributed_client, uri.host().unwrap());
+        statistics.increment_blocked_domains(uri.host().unwrap());
+        statistics.increment_top_blocked_paths(
+            format!("{}://{}{}", scheme_string, uri.host().unwrap(), uri.path()),
+            blocking_filter.clone(),
+        );
+        if let Some(blocking_filter) = &blocking_filter {
+            statistics.increment_blocking_filter(blocking_filter);
+        }
+
+        log::debug!("Blocked request: {}", uri);
+
+        let block_response = block_responses_store.get(resource_type);
+        let (status_code, block_page_template) = block_responses_store.get_block_page();
+
+        // Only page navigations can be retried from a link.
+        let proceed_url = (block_response == BlockResponse::Interstitial
+            && resource_type == ResourceType::Document
+            && req.method() == http::Method::GET)
+            .then(|| {
+                format!(
+                    "{}://{}{}{}",
+                    scheme_string,
+                    uri.authority().unwrap(),
+                    PROCEED_PATH_PREFIX,
+                    proceed_token_store.issue(uri.host().unwrap(), uri.to_string())
+                )
+            });
+
+        return get_blocked_response(
+            blocker_result,
+            resource_type,
+            block_response,
+            BlockPage {
+                status_code,
+                template: block_page_template,
+                url: &uri,
+                filter: blocking_filter.as_ref(),
+                proceed_url,
+            },
+        )
+        .map(|mut response| {
+            if let Some(decision) = &debug_decision {
+                add_debug_headers(response.headers_mut(), request_id, decision, false);
+            }
+            response
+        });
+    }
+
+    let mut upstream_uri = uri.clone();
+    if !unfiltered {
+        match url_rewrite_store.apply(&uri.to_string()) {
+            Some(RewrittenUrl::Redirect(url)) => {
+                log::debug!("Redirected {} to {}", uri, url);
+                return Ok(get_redirect_response(&url));
+            }
+            Some(RewrittenUrl::Rewrite(url)) => match url.parse::<Uri>() {
+                Ok(rewritten_uri) => {
+                    log::debug!("Rewrote {} to {}", uri, url);
+                    upstream_uri = rewritten_uri;
+                }
+                Err(err) => log::warn!("Invalid rewritten url {:?}: {}", url, err),
+            },
+            None => {}
+        }
+    }
+
+    statistics.increment_top_origins(format!("{}://{}", scheme_string, uri.authority().unwrap()));
+
+    let mut new_response = Response::new(new_body);
+
+    let mut request_headers = req.headers().clone();
+    let cookie_decision = cookie_rules_store.get_decision(uri.host().unwrap(), &request_headers);
+    if cookie_decision.apply_to_request(&mut request_headers) {
+        statistics.increment_stripped_cookies();
+    }
+    request_headers.remove(http::header::CONNECTION);
+    request_headers.remove(http::header::HOST);
+    // Proxy credentials are meant for us, they must not leak to upstream servers.
+    request_headers.remove(http::header::PROXY_AUTHORIZATION);
+    header_rules_store.normalize_user_agent(uri.host().unwrap(), &mut request_headers);
+    match header_rules_store.trim_referer(uri.host().unwrap(), &mut request_headers) {
+        Some(RefererTrimmed::Origin) => {
+            statistics.increment_trimmed_referers();
+        }
+        Some(RefererTrimmed::Stripped) => {
+            statistics.increment_stripped_referers();
+        }
+        None => {}
+    }
+    header_rules_store.apply(
+        uri.host().unwrap(),
+        HeaderRuleDirection::Request,
+        &mut request_headers,
+    );
+    // Pages can only be rewritten when their body can be decoded.
+    if let Some(accept_encoding) = request_headers.remove(http::header::ACCEPT_ENCODING) {
+        if let Some(accept_encoding) = get_decodable_accept_encoding(&accept_encoding) {
+            request_headers.insert(http::header::ACCEPT_ENCODING, accept_encoding);
+        }
+    }
+
+    if !unfiltered {
+        if let Some(response) = plugin_store.on_request(
+            req.method(),
+            &upstream_uri.to_string(),
+            &mut request_headers,
+        ) {
+            return Ok(response);
+        }
+    }
+
+    let host = uri.host().unwrap().to_string();
+
+    if let Some(error) = circuit_breaker_store.get_short_circuit_error(&host) {
+        log::debug!("Short-circuited request: {}", uri);
+        return Ok(get_informative_error_response(&error));
+    }
+
+    let mut upstream_url = upstream_uri.to_string();
+    // Done last, so that header rules can't undo it.
+    if !unfiltered {
+        if let Some(safe_search_url) = safe_search_store.apply(&upstream_uri, &mut request_headers)
+        {
+            log::debug!("Enforced SafeSearch: {}", safe_search_url);
+            upstream_url = safe_search_url.to_string();
+        }
+    }
+
+    let method = req.method().clone();
+    // Fresh cached responses are served as they are, stale ones are revalidated.
+    let cache_lookup = response_cache_store
+        .lookup(&method, &upstream_url, &mut request_headers, statistics)
+        .await;
+    let shaper = traffic_shaping_store.get_shaper(&host, &client_identity, user.as_deref());
+    let mut request_body = req.into_body();
+    if let Some(shaper) = &shaper {
+        // Bodyless requests are left alone, so that they aren't sent chunked.
+        if !request_body.is_end_stream() {
+            request_body = Body::wrap_stream(shaper.clone().shape_body(request_body));
+        }
+    }
+    let upstream_client = upstream_clients.get(upstream_uri.host().unwrap_or(&host));
+    let request = upstream_client
+        .client
+        .request(method.clone(), upstream_url.as_str())
+        .headers(request_headers)
+        .body(request_body);
+
+    statistics.record_latency(LatencyStage::RequestProcessing, started_at.elapsed());
+
+    let mut response = match cache_lookup {
+        CacheLookup::Hit(response) => {
+            log::debug!("Served from cache: {}", upstream_url);
+            response
+        }
+        cache_lookup => {
+            let timeout = circuit_breaker_store.get_timeout(&host).or(read_timeout);
+            match send_upstream_request(request, timeout)
+                .instrument(tracing::info_span!("upstream_fetch"))
+                .await
+            {
+                Ok(mut response) => {
+                    circuit_breaker_store.record_success(&host);
+                    if let Some(read_timeout) = read_timeout {
+                        response = with_read_timeout(response, read_timeout);
+                    }
+                    response_cache_store
+                        .store_response(cache_lookup, &method, &upstream_url, response, statistics)
+                        .await
+                }
+                Err(err) => {
+                    log::error!("Failed to send request: {}", err);
+                    circuit_breaker_store.record_failure(&host, err.clone());
+                    if let Some(certificate_error) = upstream_tls_store.get_certificate_error(&host)
+                    {
+                        return Ok(get_upstream_certificate_error_response(
+                            &host,
+                            &certificate_error,
+                        ));
+                    }
+                    return Ok(get_informative_error_response(&err));
+                }
+            }
+        }
+    };
+    if let Some(shaper) = shaper {
+        response = shaper.shape_response(response);
+    }
+
+    statistics.increment_proxied_requests();
+    statistics.increment_allowed_domains(&host);
+
+    *new_response.headers_mut() = response.headers().clone();
+    if cookie_decision.apply_to_response(new_response.headers_mut()) {
+        statistics.increment_stripped_cookies();
+    }
+    if let Some(csp) = csp.and_then(|csp| http::HeaderValue::from_str(&csp).ok()) {
+        // Appended, so that the policies of the page still apply.
+        new_response
+            .headers_mut()
+            .append(http::header::CONTENT_SECURITY_POLICY, csp);
+    }
+    header_rules_store.remove_client_hints_requests(&host, new_response.headers_mut());
+    header_rules_store.apply(
+        &host,
+        HeaderRuleDirection::Response,
+        new_response.headers_mut(),
+    );
+    if !unfiltered {
+        if let Some(response) = plugin_store.on_response(
+            &method,
+            &upstream_uri.to_string(),
+            response.status(),
+            new_response.headers_mut(),
+        ) {
+            return Ok(response);
+        }
+    }
+
+    let (mut parts, new_new_body) = new_response.into_parts();
+    parts.status = response.status();
+
+    let passthrough_kind = get_passthrough_kind(
+        &uri,
+        resource_type,
+        response.status(),
+        response.headers(),
+        max_inspected_body_size,
+    );
+
+    let is_html = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("text/html"));
+    // The client decodes gzip, brotli and deflate bodies and strips their
+    // `Content-Encoding`, any remaining one is an encoding we can't read.
+    let is_encoded = response
+        .headers()
+        .contains_key(http::header::CONTENT_ENCODING);
+    let is_rewritten = is_html && !is_encoded && passthrough_kind.is_none() && !unfiltered;
+
+    let rewrite_rules = if !is_encoded && passthrough_kind.is_none() && !unfiltered {
+        content_rewrite_store.get_rules(&host, response.headers())
+    } else {
+        Vec::new()
+    };
+
+    if is_rewritten || !rewrite_rules.is_empty() {
+        // The rewritten body has a length of its own.
+        parts.headers.remove(http::header::CONTENT_LENGTH);
+    }
+
+    if let Some(decision) = &debug_decision {
+        add_debug_headers(&mut parts.headers, request_id, decision, is_rewritten);
+    }
+
+    // Media and other streams are handed to hyper as they come, without going through
+    // the body channel, so that playback isn't held back by the proxy.
+    if let Some(passthrough_kind) = passthrough_kind {
+        log::debug!("Relaying {:?} response: {}", passthrough_kind, uri);
+
+        match passthrough_kind {
+            PassthroughKind::EventStream => statistics.increment_event_stream_responses(),
+            PassthroughKind::Grpc => statistics.increment_grpc_responses(),
+            PassthroughKind::Media | PassthroughKind::LargeBody => {
+                statistics.increment_streamed_responses()
+            }
+        };
+
+        // Bodies relayed as they come aren't looked at, only their announced length is
+        // counted.
+        if let Some(content_length) = response.content_length() {
+            statistics.add_response_bytes(content_length);
+        }
+
+        return Ok(Response::from_parts(
+            parts,
+            Body::wrap_stream(response.bytes_stream()),
+        ));
+    }
+
+    let new_response = Response::from_parts(parts, new_new_body);
+
+    // Content rewrite rules need the whole body, which is read before the rest goes on.
+    let rewritten_body = if rewrite_rules.is_empty() {
+        None
+    } else {
+        let (body, is_modified) = read_rewritten_body(&mut response, rewrite_rules, statistics)
+            .instrument(tracing::info_span!("content_rewrite"))
+            .await;
+        if is_modified {
+            statistics.increment_modified_responses();
+        }
+        Some(body)
+    };
+
+    if is_rewritten {
+        let (sender_rewriter, receiver_rewriter) = crossbeam_channel::unbounded::<Bytes>();
+
+        let rewriter = Rewriter::new(
+            uri.to_string(),
+            adblock_requester.clone(),
+            receiver_rewriter,
+            sender,
+            statistics.clone(),
+            html_filter_store.get_filters(&host),
+            injection_store.get_injections(&host),
+        );
+
+        // The page is rewritten as it streams, after the request span has ended.
+        let rewrite_span = tracing::info_span!("rewrite");
+        tokio::task::spawn_blocking(move || rewrite_span.in_scope(|| rewriter.rewrite()));
+
+        if let Some(body) = rewritten_body {
+            let _result = sender_rewriter.send(body);
+        }
+
+        while let Ok(Some(chunk)) = response.chunk().await {
+            if let Err(_err) = sender_rewriter.send(chunk) {
+                break;
+            }
+        }
+
+        return Ok(new_response);
+    }
+
+    if is_html && is_encoded && !unfiltered {
+        log::debug!("Unable to decode page, not filtering it: {}", uri);
+    }
+
+    tokio::spawn(write_proxied_body(
+        rewritten_body,
+        response,
+        sender,
+        statistics.clone(),
+    ));
+
+    Ok(new_response)
+}
+
+/// Sends a request upstream, giving up when no response started within `timeout`.
+async fn send_upstream_request(
+    request: reqwest::RequestBuilder,
+    timeout: Option<Duration>,
+) -> Result<reqwest::Response, String> {
+    // Only waiting for the response to start is bounded, bodies such as downloads and
+    // streams may take as long as they need.
+    let result = match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, request.send()).await {
+            Ok(result) => result,
+            Err(_elapsed) => {
+                return Err(format!("No response after {} seconds", timeout.as_secs()))
+            }
+        },
+        None => request.send().await,
+    };
+
+    result.map_err(|err| err.to_string())
+}
+
+/// What blocked a request before it was matched against filters.
+enum EarlyBlock {
+    Threat(ThreatMatch),
+    Category(String),
+    Script(ScriptMatch),
+}
+
+/// Records a request blocked before it was matched against filters in the decision log,
+/// the events stream and the statistics.
+fn record_block(
+    req: &Request<Body>,
+    early_block: EarlyBlock,
+    client_identity: String,
+    attributed_client: &str,
+    user: Option<String>,
+    statistics: &Statistics,
+    broadcast_sender: &broadcast::Sender<Event>,
+) {
+    let decision = RequestDecision {
+        blocked: true,
+        ..RequestDecision::default()
+    };
+    let request_id =
+        statistics.record_decision(req.method().to_string(), req.uri().to_string(), decision);
+    crate::logging::set_request_id(request_id);
+
+    let (threat, category, script) = match early_block {
+        EarlyBlock::Threat(threat) => {
+            statistics.increment_security_blocks(threat.severity);
+            (Some(threat), None, None)
+        }
+        EarlyBlock::Category(category) => {
+            statistics.increment_category_blocks(&category);
+            (None, Some(category), None)
+        }
+        EarlyBlock::Script(script_match) => (None, None, Some(script_match)),
+    };
+
+    let _result = broadcast_sender.send(Event {
+        id: request_id,
+        now: chrono::Utc::now(),
+        method: req.method().to_string(),
+        url: req.uri().to_string(),
+        is_request_blocked: true,
+        client: client_identity.clone(),
+        user,
+        filter: None,
+        threat,
+        category,
+        script,
+    });
+
+    let host = req.uri().host().unwrap();
+
+    statistics.increment_blocked_requests();
+    statistics.increment_blocked_clients(client_identity);
+    statistics.increment_client_blocked_requests(attributed_client, host);
+    statistics.increment_blocked_domains(host);
+}
+
+/// Fails the body of `response` once a chunk of it takes longer than `read_timeout` to
+/// arrive, so that stalled servers don't hold connections forever.
+fn with_read_timeout(response: reqwest::Response, read_timeout: Duration) -> reqwest::Response {
+    let mut timed_response = Response::new(Body::empty());
+    *timed_response.status_mut() = response.status();
+    *timed_response.version_mut() = response.version();
+    *timed_response.headers_mut() = response.headers().clone();
+
+    let chunks =
+        futures_util::stream::unfold(Some(response.bytes_stream()), move |chunks| async move {
+            let mut chunks = chunks?;
+
+            match tokio::time::timeout(read_timeout, chunks.next()).await {
+                Ok(chunk) => {
+                    let chunk = chunk?.map_err(Box::<dyn std::error::Error + Send + Sync>::from);
+                    Some((chunk, Some(chunks)))
+                }
+                Err(_elapsed) => {
+                    let error = format!("No data after {} seconds", read_timeout.as_secs());
+                    Some((Err(error.into()), None))
+                }
+            }
+        });
+    *timed_response.body_mut() = Body::wrap_stream(chunks);
+
+    reqwest::Response::from(timed_response)
+}
+
+/// Keeps the encodings of an `Accept-Encoding` header that the client decodes, so that
+/// servers don't answer with encodings such as `zstd` we can't rewrite pages from.
+/// Returns `None` when none is left, the client then asks for the ones it supports.
+fn get_decodable_accept_encoding(accept_encoding: &http::HeaderValue) -> Option<http::HeaderValue> {
+    let accept_encoding = accept_encoding.to_str().ok()?;
+
+    let encodings = accept_encoding
+        .split(',')
+        .map(str::trim)
+        .filter(|encoding| {
+            let name = encoding.split(';').next().unwrap_or("").trim();
+
+            DECODABLE_CONTENT_ENCODINGS
+                .iter()
+                .any(|decodable| name.eq_ignore_ascii_case(decodable))
+        })
+        .collect::<Vec<_>>();
+
+    if encodings.is_empty() {
+        return None;
+    }
+
+    http::HeaderValue::from_str(&encodings.join(", ")).ok()
+}
+
+fn get_informative_error_response(reason: &str) -> Response<Body> {
+    let mut response_body = String::from(include_str!("../../resources/head.html"));
+    response_body +=
+        &include_str!("../../resources/error.html").replace("#{request_error_reson}#", reason);
+
+    let mut response = Response::new(Body::from(response_body));
+    *response.status_mut() = http::StatusCode::BAD_GATEWAY;
+
+    response
+}
+
+/// Explains that the request failed because the certificate of `host` was rejected.
+fn get_upstream_certificate_error_response(host: &str, certificate_error: &str) -> Response<Body> {
+    let mut response_body = String::from(include_str!("../../resources/head.html"));
+    response_body += &include_str!("../../resources/upstream_certificate_error.html")
+        .replace("#{host}#", &escape_html(host))
+        .replace("#{certificate_error}#", &escape_html(certificate_error));
+
+    let mut response = Response::new(Body::from(response_body));
+    *response.status_mut() = http::StatusCode::BAD_GATEWAY;
+
+    response
+}
+
+/// Pages are warned about, other resources are refused.
+fn get_threat_response(
+    url: &Uri,
+    threat: &ThreatMatch,
+    resource_type: ResourceType,
+) -> Response<Body> {
+    if resource_type != ResourceType::Document {
+        return get_empty_response(StatusCode::FORBIDDEN);
+    }
+
+    let mut response_body = String::from(include_str!("../../resources/head.html"));
+    response_body += &include_str!("../../resources/threat.html")
+        .replace("#{url}#", &escape_html(&url.to_string()))
+        .replace("#{feed}#", &escape_html(&threat.feed));
+
+    let mut response = Response::new(Body::from(response_body));
+    *response.status_mut() = StatusCode::FORBIDDEN;
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+
+    response
+}
+
+/// Pages explain which category they were blocked for, other resources are refused.
+fn get_category_response(url: &Uri, category: &str, resource_type: ResourceType) -> Response<Body> {
+    if resource_type != ResourceType::Document {
+        return get_empty_response(StatusCode::FORBIDDEN);
+    }
+
+    let mut response_body = String::from(include_str!("../../resources/head.html"));
+    response_body += &include_str!("../../resources/category.html")
+        .replace("#{url}#", &escape_html(&url.to_string()))
+        .replace("#{category}#", &escape_html(category));
+
+    let mut response = Response::new(Body::from(response_body));
+    *response.status_mut() = StatusCode::FORBIDDEN;
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+
+    response
+}
+
+/// What `forbidden` and `interstitial` block responses are made of.
+struct BlockPage<'a> {
+    status_code: u16,
+    template: Option<String>,
+    url: &'a Uri,
+    filter: Option<&'a MatchedFilter>,
+    /// Link visiting the page anyway, when an interstitial page can be offered.
+    proceed_url: Option<String>,
+}
+
+fn get_blocked_response(
+    blocker_result: BlockerResult,
+    resource_type: ResourceType,
+    block_response: BlockResponse,
+    block_page: BlockPage,
+) -> Result<Response<Body>, ConnectionClosed> {
+    // Resources requested by filters through `$redirect` take precedence over the configured
+    // response. We don't redirect to network urls due to security concerns.
+    if let Some(response) = blocker_result
+        .redirect
+        .as_deref()
+        .and_then(get_data_url_response)
+    {
+        return Ok(response);
+    }
+
+    match block_response {
+        BlockResponse::Interstitial if block_page.proceed_url.is_some() => {
+            Ok(get_interstitial_response(blocker_result, block_page))
+        }
+        BlockResponse::Forbidden | BlockResponse::Interstitial => Ok(get_forbidden_response(
+            blocker_result,
+            resource_type,
+            block_page,
+        )),
+        BlockResponse::Empty => Ok(get_empty_response(http::StatusCode::OK)),
+        BlockResponse::CloseConnection => Err(ConnectionClosed),
+        BlockResponse::Resource => Ok(get_noop_resource_response(resource_type)),
+    }
+}
+
+/// Serves the content of a `data:` url, which is how the blocking engine hands out
+/// redirect resources.
+fn get_data_url_response(data_url: &str) -> Option<Response<Body>> {
+    let (metadata, data) = data_url.strip_prefix("data:")?.split_once(',')?;
+
+    let (content_type, body) = match metadata.strip_suffix(";base64") {
+        Some(content_type) => (content_type, general_purpose::STANDARD.decode(data).ok()?),
+        None => (metadata, data.as_bytes().to_vec()),
+    };
+
+    let mut response = Response::new(Body::from(body));
+    if let Ok(content_type) = http::HeaderValue::from_str(content_type) {
+        response
+            .headers_mut()
+            .insert(http::header::CONTENT_TYPE, content_type);
+    }
+
+    Some(response)
+}
+
+fn get_noop_resource_response(resource_type: ResourceType) -> Response<Body> {
+    let (content_type, body): (&str, &'static [u8]) = match resource_type {
+        ResourceType::Image => (
+            "image/gif",
+            include_bytes!("../../resources/vendor/ublock/web_accessible_resources/1x1.gif"),
+        ),
+        ResourceType::Script => (
+            "application/javascript",
+            include_bytes!("../../resources/vendor/ublock/web_accessible_resources/noop.js"),
+        ),
+        ResourceType::Stylesheet => (
+            "text/css",
+            include_bytes!("../../resources/vendor/ublock/web_accessible_resources/noop.css"),
+        ),
+        ResourceType::Document | ResourceType::Subdocument => (
+            "text/html",
+            include_bytes!("../../resources/vendor/ublock/web_accessible_resources/noop.html"),
+        ),
+        ResourceType::Media => (
+            "audio/mpeg",
+            include_bytes!("../../resources/vendor/ublock/web_accessible_resources/noop-0.1s.mp3"),
+        ),
+        ResourceType::Font | ResourceType::Xhr | ResourceType::Other => (
+            "text/plain",
+            include_bytes!("../../resources/vendor/ublock/web_accessible_resources/noop.txt"),
+        ),
+    };
+
+    let mut response = Response::new(Body::from(body));
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static(content_type),
+    );
+
+    response
+}
+
+fn get_forbidden_response(
+    blocker_result: BlockerResult,
+    resource_type: ResourceType,
+    block_page: BlockPage,
+) -> Response<Body> {
+    // Validated when the configuration is changed.
+    let status_code = StatusCode::from_u16(block_page.status_code).unwrap_or(StatusCode::FORBIDDEN);
+
+    if status_code == StatusCode::NO_CONTENT {
+        return get_empty_response(status_code);
+    }
+
+    let response_body = match block_page.template {
+        Some(template) if resource_type == ResourceType::Document => {
+            get_block_page_from_template(&template, block_page.url, block_page.filter)
+        }
+        _ => get_blocked_by_privaxy_page(blocker_result, status_code),
+    };
+
+    let mut response = Response::new(Body::from(response_body));
+    *response.status_mut() = status_code;
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+
+    response
+}
+
+fn get_interstitial_response(
+    blocker_result: BlockerResult,
+    block_page: BlockPage,
+) -> Response<Body> {
+    // Unlike `forbidden` responses, a page is always sent.
+    let status_code = match StatusCode::from_u16(block_page.status_code) {
+        Ok(status_code) if status_code != StatusCode::NO_CONTENT => status_code,
+        _ => StatusCode::FORBIDDEN,
+    };
+
+    let filter_information = match blocker_result.filter {
+        Some(filter) => filter,
+        None => "No information".to_string(),
+    };
+
+    let mut response_body = String::from(include_str!("../../resources/head.html"));
+    response_body += &include_str!("../../resources/interstitial.html")
+        .replace("#{status_code}#", status_code.as_str())
+        .replace(
+            "#{proceed_url}#",
+            &escape_html(&block_page.proceed_url.unwrap_or_default()),
+        )
+        .replace("#{matching_filter}#", &escape_html(&filter_information))
+        .replace("#{url}#", &escape_html(&block_page.url.to_string()));
+
+    let mut response = Response::new(Body::from(response_body));
+    *response.status_mut() = status_code;
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        http::HeaderValue::from_static("text/html; charset=utf-8"),
+    );
+
+    response
+}
+
+fn get_blocked_by_privaxy_page(blocker_result: BlockerResult, status_code: StatusCode) -> String {
+    let filter_information = match blocker_result.filter {
+        Some(filter) => filter,
+        None => "No information".to_string(),
+    };
+
+    let mut response_body = String::from(include_str!("../../resources/head.html"));
+    response_body += &include_str!("../../resources/blocked_by_privaxy.html")
+        .replace("#{status_code}#", status_code.as_str())
+        .replace("#{matching_filter}#", &escape_html(&filter_information));
+
+    response_body
+}
+
+/// Renders a user provided block page, `{{url}}`, `{{rule}}` and `{{list}}` are
+/// replaced with the blocked url, the rule that matched and the list it comes from.
+fn get_block_page_from_template(
+    template: &str,
+    url: &Uri,
+    filter: Option<&MatchedFilter>,
+) -> String {
+    let (rule, list) = match filter {
+        Some(filter) => (filter.rule.as_str(), filter.list.as_deref().unwrap_or("")),
+        None => ("", ""),
+    };
+
+    template
+        .replace("{{url}}", &escape_html(&url.to_string()))
+        .replace("{{rule}}", &escape_html(rule))
+        .replace("{{list}}", &escape_html(list))
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            character => escaped.push(character),
+        }
+    }
+
+    escaped
+}
+
+fn get_redirect_response(url: &str) -> Response<Body> {
+    let mut response = get_empty_response(StatusCode::SEE_OTHER);
+    if let Ok(location) = http::HeaderValue::from_str(url) {
+        response
+            .headers_mut()
+            .insert(http::header::LOCATION, location);
+    }
+
+    response
+}
+
+fn get_empty_response(status_code: http::StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status_code;
+
+    response
+}
+
+/// Relays the body of `response`, after `body_start` when part of it was already read.
+async fn write_proxied_body(
+    body_start: Option<Bytes>,
+    mut response: reqwest::Response,
+    mut sender: hyper::body::Sender,
+    statistics: Statistics,
+) {
+    if let Some(body_start) = body_start {
+        statistics.add_response_bytes(body_start.len() as u64);
+
+        // The other end is broken, no need to read the rest.
+        if let Err(_err) = sender.send_data(body_start).await {
+            return;
+        }
+    }
+
+    while let Ok(Some(chunk)) = response.chunk().await {
+        statistics.add_response_bytes(chunk.len() as u64);
+
+        // The other end is broken, let's abort immediately.
+        if let Err(_err) = sender.send_data(chunk).await {
+            break;
+        }
+    }
+}
+
+/// When we receive a request to perform an upgrade, we need to initiate a bidirectional tunnel.
+/// We upgrade the request towards the target server, towards the proxy end and we connect both through a duplex stream.
+async fn perform_two_ends_upgrade(
+    request: Request<Body>,
+    uri: Uri,
+    hyper_client: HyperClient,
+) -> Response<Body> {
+    let (mut duplex_client, mut duplex_server) = tokio::io::duplex(32);
+
+    let mut new_request = Request::new(Body::empty());
+    *new_request.headers_mut() = request.headers().clone();
+    *new_request.uri_mut() = uri;
+
+    tokio::spawn(async move {
+        match hyper::upgrade::on(request).await {
+            Ok(mut upgraded_client) => {
+                let _result =
+                    tokio::io::copy_bidirectional(&mut upgraded_client, &mut duplex_client).await;
+            }
+            Err(e) => {
+                log::debug!("Unable to upgrade: {}", e)
+            }
+        }
+    });
+
+    let response = match hyper_client.request(new_request).await {
+        Ok(response) => response,
+        Err(_err) => return get_empty_response(http::StatusCode::BAD_REQUEST),
+    };
+
+    let mut new_response = get_empty_response(StatusCode::SWITCHING_PROTOCOLS);
+    *new_response.headers_mut() = response.headers().clone();
+
+    match hyper::upgrade::on(response).await {
+        Ok(mut upgraded_server) => {
+            tokio::spawn(async move {
+                let _result =
+                    tokio::io::copy_bidirectional(&mut upgraded_server, &mut duplex_server).await;
+            });
+        }
+        Err(e) => {
+            log::debug!("Unable to upgrade: {}", e)
+        }
+    }
+
+    new_response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_accept_encoding(accept_encoding: &'static str) -> Option<http::HeaderValue> {
+        get_decodable_accept_encoding(&http::HeaderValue::from_static(accept_encoding))
+    }
+
+    #[test]
+    fn zstd_is_stripped() {
+        assert_eq!(
+            get_accept_encoding("gzip, deflate, br, zstd"),
+            Some(http::HeaderValue::from_static("gzip, deflate, br"))
+        );
+    }
+
+    #[test]
+    fn quality_values_are_kept() {
+        assert_eq!(
+            get_accept_encoding("zstd;q=1.0, br;q=0.9, gzip ; q=0.5"),
+            Some(http::HeaderValue::from_static("br;q=0.9, gzip ; q=0.5"))
+        );
+    }
+
+    #[test]
+    fn identity_is_kept() {
+        assert_eq!(
+            get_accept_encoding("identity"),
+            Some(http::HeaderValue::from_static("identity"))
+        );
+    }
+
+    #[test]
+    fn wildcard_is_stripped() {
+        assert_eq!(
+            get_accept_encoding("GZIP, *;q=0.1"),
+            Some(http::HeaderValue::from_static("GZIP"))
+        );
+        assert_eq!(get_accept_encoding("*"), None);
+    }
+
+    #[test]
+    fn nothing_decodable_is_removed() {
+        assert_eq!(get_accept_encoding("zstd, compress"), None);
+    }
+}
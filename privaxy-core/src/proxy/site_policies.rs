@@ -0,0 +1,94 @@
+use crate::configuration::{ResourceType, SitePolicy, SitePolicyAction, ANY_SITE};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// A site policy that applies to a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SitePolicyMatch {
+    pub(crate) site: String,
+    /// `None` when the action is the one applying to all resource types.
+    pub(crate) resource_type: Option<ResourceType>,
+    pub(crate) action: SitePolicyAction,
+}
+
+impl SitePolicyMatch {
+    /// Describes the policy in the `site destination type action` syntax of
+    /// uBlock Origin's dynamic filtering rules.
+    pub(crate) fn rule(&self) -> String {
+        let action = match self.action {
+            SitePolicyAction::Default => "noop",
+            SitePolicyAction::Block => "block",
+            SitePolicyAction::Allow => "allow",
+        };
+
+        format!(
+            "{} * {} {}",
+            self.site,
+            self.resource_type
+                .map(|resource_type| resource_type.as_adblock_str())
+                .unwrap_or("*"),
+            action
+        )
+    }
+}
+
+/// Site policies, keyed by site.
+#[derive(Debug, Clone)]
+pub struct SitePolicyStore(Arc<RwLock<HashMap<String, SitePolicy>>>);
+
+impl SitePolicyStore {
+    pub fn new(site_policies: Vec<SitePolicy>) -> Self {
+        let store = Self(Arc::new(RwLock::new(HashMap::new())));
+        store.replace(site_policies);
+        store
+    }
+
+    pub fn replace(&self, site_policies: Vec<SitePolicy>) {
+        *self.0.write().unwrap() = site_policies
+            .into_iter()
+            .map(|policy| (policy.site.clone(), policy))
+            .collect();
+    }
+
+    /// Returns the most specific policy deciding what happens to a request of the
+    /// given type made by a page of `host`.
+    ///
+    /// Policies of `host` come first, then those of its parent domains and finally
+    /// the policy of every site. Within a policy, the action of the resource type
+    /// comes before the action applying to all types.
+    pub(crate) fn get_match(
+        &self,
+        host: &str,
+        resource_type: ResourceType,
+    ) -> Option<SitePolicyMatch> {
+        let site_policies = self.0.read().unwrap();
+
+        if site_policies.is_empty() {
+            return None;
+        }
+
+        let host = host.to_lowercase();
+        let parent_domains = host.match_indices('.').map(|(index, _)| &host[index + 1..]);
+
+        std::iter::once(host.as_str())
+            .chain(parent_domains)
+            .chain(std::iter::once(ANY_SITE))
+            .filter_map(|site| site_policies.get(site))
+            .find_map(|policy| {
+                let (resource_type, action) = match policy.get(resource_type) {
+                    SitePolicyAction::Default => (None, policy.all),
+                    action => (Some(resource_type), action),
+                };
+
+                if action == SitePolicyAction::Default {
+                    None
+                } else {
+                    Some(SitePolicyMatch {
+                        site: policy.site.clone(),
+                        resource_type,
+                        action,
+                    })
+                }
+            })
+    }
+}
@@ -0,0 +1,84 @@
+use crate::blocker::{AdblockRequester, RequestDecision};
+use crate::configuration::ResourceType;
+use rustls::server::Acceptor;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Clients of protocols where the server speaks first never send a ClientHello.
+const CLIENT_HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+/// ClientHellos are much smaller, a client sending more is not speaking TLS.
+const MAX_CLIENT_HELLO_SIZE: usize = 64 * 1024;
+
+/// Start of a tunneled connection, read to find the server name it is for.
+pub(crate) struct ClientHello {
+    /// Bytes read from the client, that still have to be relayed to the server.
+    pub bytes: Vec<u8>,
+    /// Server name indication, if the client sent a TLS ClientHello with one.
+    pub server_name: Option<String>,
+}
+
+/// Reads the TLS ClientHello a client starts a connection with, without answering it.
+/// Gives up after a while, returning what was read so far.
+pub(crate) async fn read_client_hello<R: AsyncRead + Unpin>(
+    stream: &mut R,
+) -> io::Result<ClientHello> {
+    let mut acceptor = Acceptor::default();
+    let mut client_hello = ClientHello {
+        bytes: Vec::new(),
+        server_name: None,
+    };
+    let mut buffer = [0; 4096];
+    let deadline = tokio::time::Instant::now() + CLIENT_HELLO_TIMEOUT;
+
+    while client_hello.bytes.len() < MAX_CLIENT_HELLO_SIZE {
+        let read = match tokio::time::timeout_at(deadline, stream.read(&mut buffer)).await {
+            Ok(read) => read?,
+            Err(_elapsed) => break,
+        };
+        if read == 0 {
+            break;
+        }
+        client_hello.bytes.extend_from_slice(&buffer[..read]);
+
+        let mut reader = &buffer[..read];
+        while !reader.is_empty() {
+            // Records larger than a ClientHello may not fit in the buffer of the acceptor.
+            if let Ok(0) | Err(_) = acceptor.read_tls(&mut reader) {
+                return Ok(client_hello);
+            }
+        }
+
+        match acceptor.accept() {
+            Ok(Some(accepted)) => {
+                client_hello.server_name =
+                    accepted.client_hello().server_name().map(str::to_string);
+                break;
+            }
+            Ok(None) => continue,
+            // Not TLS, the connection is tunneled as is.
+            Err(_err) => break,
+        }
+    }
+
+    Ok(client_hello)
+}
+
+/// Evaluates a connection to `server_name` as a request for its root, so that
+/// rules blocking the whole host apply.
+pub(crate) async fn get_decision(
+    adblock_requester: &AdblockRequester,
+    server_name: &str,
+) -> RequestDecision {
+    let url = get_url(server_name);
+
+    let (_blocker_result, decision) = adblock_requester
+        .is_network_url_blocked(url.clone(), url, ResourceType::Other)
+        .await;
+
+    decision
+}
+
+pub(crate) fn get_url(server_name: &str) -> String {
+    format!("https://{}/", server_name)
+}
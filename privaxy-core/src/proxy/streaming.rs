@@ -0,0 +1,172 @@
+use crate::configuration::ResourceType;
+use hyper::header::HeaderMap;
+use hyper::{http, StatusCode, Uri};
+
+/// Content types of playlists and segments used by adaptive streaming.
+const STREAMING_CONTENT_TYPES: [&str; 5] = [
+    "application/vnd.apple.mpegurl",
+    "application/x-mpegurl",
+    "application/dash+xml",
+    "application/vnd.ms-sstr+xml",
+    "video/mp2t",
+];
+
+/// Hosts serving media segments, whose responses never need adaptation.
+const STREAMING_HOSTS: [&str; 7] = [
+    "googlevideo.com",
+    "nflxvideo.net",
+    "ttvnw.net",
+    "dssott.com",
+    "aiv-cdn.net",
+    "vimeocdn.com",
+    "video.twimg.com",
+];
+
+/// Why a response is relayed as it comes instead of being inspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PassthroughKind {
+    Media,
+    /// Server-sent events, which stay open for as long as the page is.
+    EventStream,
+    /// gRPC and gRPC-web calls, whose messages must reach the client as they are sent.
+    Grpc,
+    LargeBody,
+}
+
+/// Tells whether a response carries media or another stream that must be relayed
+/// without going through the HTML rewriter, so that playback isn't slowed down and
+/// long lived streams don't hang.
+///
+/// Responses announcing a body larger than `max_inspected_body_size` bytes are relayed
+/// as well, unless it is `0`. Pages never are, whatever their size, as they need
+/// cosmetic filtering and script injection.
+pub(crate) fn get_passthrough_kind(
+    uri: &Uri,
+    resource_type: ResourceType,
+    status: StatusCode,
+    headers: &HeaderMap,
+    max_inspected_body_size: u64,
+) -> Option<PassthroughKind> {
+    let content_type = headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    // Checked first, these streams are recorded apart whatever else the response is.
+    if content_type.starts_with("text/event-stream") {
+        return Some(PassthroughKind::EventStream);
+    }
+
+    // Covers `application/grpc-web`, `application/grpc-web-text` and their variants.
+    if content_type.starts_with("application/grpc") {
+        return Some(PassthroughKind::Grpc);
+    }
+
+    if resource_type == ResourceType::Media || status == StatusCode::PARTIAL_CONTENT {
+        return Some(PassthroughKind::Media);
+    }
+
+    if let Some(host) = uri.host() {
+        if STREAMING_HOSTS
+            .iter()
+            .any(|streaming_host| is_same_or_subdomain(host, streaming_host))
+        {
+            return Some(PassthroughKind::Media);
+        }
+    }
+
+    if content_type.contains("text/html") {
+        return None;
+    }
+
+    let is_too_large = headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|content_length| content_length.to_str().ok())
+        .and_then(|content_length| content_length.parse::<u64>().ok())
+        .is_some_and(|content_length| {
+            max_inspected_body_size != 0 && content_length > max_inspected_body_size
+        });
+
+    if is_too_large {
+        return Some(PassthroughKind::LargeBody);
+    }
+
+    let is_media = content_type.starts_with("video/")
+        || content_type.starts_with("audio/")
+        || STREAMING_CONTENT_TYPES
+            .iter()
+            .any(|streaming_content_type| content_type.starts_with(streaming_content_type));
+
+    is_media.then_some(PassthroughKind::Media)
+}
+
+fn is_same_or_subdomain(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_INSPECTED_BODY_SIZE: u64 = 8 * 1024 * 1024;
+
+    fn get_headers(content_type: &str, content_length: u64) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::CONTENT_TYPE, content_type.parse().unwrap());
+        headers.insert(http::header::CONTENT_LENGTH, content_length.into());
+
+        headers
+    }
+
+    #[test]
+    fn large_pages_are_inspected() {
+        let uri = Uri::from_static("https://example.com/");
+        let headers = get_headers("text/html; charset=utf-8", 64 * 1024 * 1024);
+
+        assert_eq!(
+            get_passthrough_kind(
+                &uri,
+                ResourceType::Document,
+                StatusCode::OK,
+                &headers,
+                MAX_INSPECTED_BODY_SIZE,
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn large_downloads_are_relayed() {
+        let uri = Uri::from_static("https://example.com/archive.zip");
+        let headers = get_headers("application/zip", 64 * 1024 * 1024);
+
+        assert_eq!(
+            get_passthrough_kind(
+                &uri,
+                ResourceType::Other,
+                StatusCode::OK,
+                &headers,
+                MAX_INSPECTED_BODY_SIZE,
+            ),
+            Some(PassthroughKind::LargeBody)
+        );
+    }
+
+    #[test]
+    fn small_binary_bodies_are_inspected() {
+        let uri = Uri::from_static("https://example.com/app.js");
+        let headers = get_headers("application/octet-stream", 64 * 1024);
+
+        assert_eq!(
+            get_passthrough_kind(
+                &uri,
+                ResourceType::Script,
+                StatusCode::OK,
+                &headers,
+                MAX_INSPECTED_BODY_SIZE,
+            ),
+            None
+        );
+    }
+}
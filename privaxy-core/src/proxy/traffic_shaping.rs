@@ -0,0 +1,296 @@
+//! Token buckets holding tunnels and the bodies of proxied requests and responses to
+//! the rates of the traffic shaping settings.
+//!
+//! Buckets hold up to a second worth of their rate, so that short bursts go through
+//! right away. Bytes are taken from every bucket traffic matches, and relayed once the
+//! emptiest one has them. Both ways of a tunnel share its buckets.
+
+use crate::configuration::TrafficShaping;
+use futures_util::{Stream, StreamExt};
+use hyper::body::Bytes;
+use hyper::{Body, Response};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Bytes of a tunnel relayed at once, so that rates are checked often enough for
+/// traffic to flow evenly.
+const SHAPED_TUNNEL_CHUNK_SIZE: usize = 16 * 1024;
+
+/// What a bucket held back since the settings were last changed.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct ShapingCounters {
+    pub bytes: u64,
+    /// Time traffic was held for, added up over every tunnel and body.
+    pub delayed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ShapingRuleStatus {
+    pub host: String,
+    pub bytes_per_second: u64,
+    #[serde(flatten)]
+    pub counters: ShapingCounters,
+    /// Counters of each client, for rules giving every client a rate of its own.
+    pub per_client: BTreeMap<String, ShapingCounters>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TrafficShapingStatus {
+    pub enabled: bool,
+    pub global: Option<ShapingCounters>,
+    /// In the order of the rules.
+    pub rules: Vec<ShapingRuleStatus>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    bytes_per_second: u64,
+    /// Negative once bytes were taken ahead of the rate, they are then waited for.
+    tokens: f64,
+    updated_at: Instant,
+    counters: ShapingCounters,
+}
+
+impl TokenBucket {
+    fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            tokens: bytes_per_second as f64,
+            updated_at: Instant::now(),
+            counters: ShapingCounters::default(),
+        }
+    }
+
+    /// Takes `bytes`, returning how long to wait before relaying them.
+    fn take(&mut self, bytes: usize) -> Duration {
+        let now = Instant::now();
+        let rate = self.bytes_per_second as f64;
+
+        self.tokens = (self.tokens + now.duration_since(self.updated_at).as_secs_f64() * rate)
+            .min(rate)
+            - bytes as f64;
+        self.updated_at = now;
+
+        let delay = if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / rate)
+        } else {
+            Duration::ZERO
+        };
+
+        self.counters.bytes += bytes as u64;
+        self.counters.delayed_ms += delay.as_millis() as u64;
+
+        delay
+    }
+}
+
+type SharedTokenBucket = Arc<Mutex<TokenBucket>>;
+
+fn create_bucket(bytes_per_second: u64) -> SharedTokenBucket {
+    Arc::new(Mutex::new(TokenBucket::new(bytes_per_second)))
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    traffic_shaping: TrafficShaping,
+    global: Option<SharedTokenBucket>,
+    /// Buckets shared by the clients of each rule, in the order of the rules.
+    rules: Vec<SharedTokenBucket>,
+    /// Buckets of rules giving every client a rate of its own, keyed by the index of
+    /// their rule and by client.
+    per_client: HashMap<(usize, String), SharedTokenBucket>,
+}
+
+/// Rates a tunnel or a body is held to.
+#[derive(Debug, Clone)]
+pub(crate) struct Shaper {
+    buckets: Vec<SharedTokenBucket>,
+}
+
+impl Shaper {
+    /// Waits until `bytes` can be relayed.
+    pub(crate) async fn throttle(&self, bytes: usize) {
+        let delay = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.lock().unwrap().take(bytes))
+            .max()
+            .unwrap_or_default();
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Holds the chunks of a body to the rates.
+    pub(crate) fn shape_body<E>(
+        self,
+        body: impl Stream<Item = Result<Bytes, E>>,
+    ) -> impl Stream<Item = Result<Bytes, E>> {
+        body.then(move |chunk| {
+            let shaper = self.clone();
+            async move {
+                let bytes = chunk.as_ref().map_or(0, |chunk| chunk.len());
+                shaper.throttle(bytes).await;
+                chunk
+            }
+        })
+    }
+
+    pub(crate) fn shape_response(self, response: reqwest::Response) -> reqwest::Response {
+        let mut shaped_response = Response::new(Body::empty());
+        *shaped_response.status_mut() = response.status();
+        *shaped_response.version_mut() = response.version();
+        *shaped_response.headers_mut() = response.headers().clone();
+        *shaped_response.body_mut() = Body::wrap_stream(self.shape_body(response.bytes_stream()));
+
+        reqwest::Response::from(shaped_response)
+    }
+
+    /// Relays a tunnel both ways until both ends are closed, like
+    /// [`tokio::io::copy_bidirectional`].
+    pub(crate) async fn copy_bidirectional<A, B>(&self, a: &mut A, b: &mut B) -> std::io::Result<()>
+    where
+        A: AsyncRead + AsyncWrite + Unpin,
+        B: AsyncRead + AsyncWrite + Unpin,
+    {
+        let (mut a_reader, mut a_writer) = tokio::io::split(a);
+        let (mut b_reader, mut b_writer) = tokio::io::split(b);
+
+        tokio::try_join!(
+            self.copy(&mut a_reader, &mut b_writer),
+            self.copy(&mut b_reader, &mut a_writer)
+        )?;
+
+        Ok(())
+    }
+
+    async fn copy<R, W>(&self, reader: &mut R, writer: &mut W) -> std::io::Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut buffer = vec![0; SHAPED_TUNNEL_CHUNK_SIZE];
+
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                return writer.shutdown().await;
+            }
+
+            self.throttle(read).await;
+            writer.write_all(&buffer[..read]).await?;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrafficShapingStore {
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl TrafficShapingStore {
+    pub fn new(traffic_shaping: TrafficShaping) -> Self {
+        let store = Self {
+            buckets: Arc::new(Mutex::new(Buckets::default())),
+        };
+        store.replace(traffic_shaping);
+        store
+    }
+
+    pub fn replace(&self, traffic_shaping: TrafficShaping) {
+        *self.buckets.lock().unwrap() = Buckets {
+            global: traffic_shaping.global_bytes_per_second.map(create_bucket),
+            rules: traffic_shaping
+                .rules
+                .iter()
+                .map(|rule| create_bucket(rule.bytes_per_second))
+                .collect(),
+            per_client: HashMap::new(),
+            traffic_shaping,
+        };
+    }
+
+    /// Returns the rates traffic of a host and client is held to, if any.
+    pub(crate) fn get_shaper(
+        &self,
+        host: &str,
+        client_identity: &str,
+        user: Option<&str>,
+    ) -> Option<Shaper> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if !buckets.traffic_shaping.enabled {
+            return None;
+        }
+
+        let host = host.to_lowercase();
+        let buckets = &mut *buckets;
+
+        let mut shaper_buckets = buckets.global.iter().cloned().collect::<Vec<_>>();
+        for (index, rule) in buckets.traffic_shaping.rules.iter().enumerate() {
+            if !rule.applies_to(&host, client_identity, user) {
+                continue;
+            }
+
+            if rule.per_client {
+                // Authenticated proxy users get their rate wherever they connect from.
+                let client = user.unwrap_or(client_identity).to_string();
+                let bucket = buckets
+                    .per_client
+                    .entry((index, client))
+                    .or_insert_with(|| create_bucket(rule.bytes_per_second));
+                shaper_buckets.push(bucket.clone());
+            } else {
+                shaper_buckets.push(buckets.rules[index].clone());
+            }
+        }
+
+        (!shaper_buckets.is_empty()).then_some(Shaper {
+            buckets: shaper_buckets,
+        })
+    }
+
+    pub(crate) fn get_status(&self) -> TrafficShapingStatus {
+        let buckets = self.buckets.lock().unwrap();
+        let get_counters = |bucket: &SharedTokenBucket| bucket.lock().unwrap().counters.clone();
+
+        TrafficShapingStatus {
+            enabled: buckets.traffic_shaping.enabled,
+            global: buckets.global.as_ref().map(get_counters),
+            rules: buckets
+                .traffic_shaping
+                .rules
+                .iter()
+                .zip(&buckets.rules)
+                .enumerate()
+                .map(|(index, (rule, bucket))| {
+                    let per_client = buckets
+                        .per_client
+                        .iter()
+                        .filter(|((rule_index, _client), _bucket)| *rule_index == index)
+                        .map(|((_rule_index, client), bucket)| {
+                            (client.clone(), get_counters(bucket))
+                        })
+                        .collect::<BTreeMap<_, _>>();
+
+                    let mut counters = get_counters(bucket);
+                    for client_counters in per_client.values() {
+                        counters.bytes += client_counters.bytes;
+                        counters.delayed_ms += client_counters.delayed_ms;
+                    }
+
+                    ShapingRuleStatus {
+                        host: rule.host.clone(),
+                        bytes_per_second: rule.bytes_per_second,
+                        counters,
+                        per_client,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Clients requests are forwarded to upstream servers with, one for each local address
+//! connections are made from.
+
+use super::ip_rules::IpRuleStore;
+use super::upstream_tls::UpstreamTlsStore;
+use crate::configuration::UpstreamConnections;
+use hyper::client::HttpConnector;
+use hyper_rustls::HttpsConnector;
+use reqwest::redirect::Policy;
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::{TcpSocket, TcpStream};
+
+pub(crate) type HyperClient = hyper::Client<HttpsConnector<HttpConnector<IpRuleStore>>>;
+
+#[derive(Clone)]
+pub(crate) struct UpstreamClient {
+    pub(crate) client: reqwest::Client,
+    /// Only used to perform upgrades, their bodies don't need to be decoded.
+    pub(crate) hyper_client: HyperClient,
+    local_address: Option<IpAddr>,
+    upstream_connections: Arc<UpstreamConnections>,
+}
+
+impl UpstreamClient {
+    fn new(
+        upstream_tls_store: &UpstreamTlsStore,
+        ip_rule_store: &IpRuleStore,
+        upstream_connections: Arc<UpstreamConnections>,
+        local_address: Option<IpAddr>,
+    ) -> Self {
+        Self {
+            client: build_client(
+                upstream_tls_store,
+                ip_rule_store,
+                &upstream_connections,
+                local_address,
+            ),
+            hyper_client: build_hyper_client(
+                upstream_tls_store,
+                ip_rule_store,
+                &upstream_connections,
+                local_address,
+            ),
+            local_address,
+            upstream_connections,
+        }
+    }
+
+    /// Connects to the first of `socket_addresses` accepting the connection, like
+    /// [`TcpStream::connect`].
+    pub(crate) async fn connect(&self, socket_addresses: &[SocketAddr]) -> io::Result<TcpStream> {
+        let mut last_error = None;
+
+        for socket_address in socket_addresses {
+            let connection = tokio::time::timeout(
+                self.upstream_connections.connect_timeout(),
+                self.connect_address(*socket_address),
+            )
+            .await
+            .unwrap_or_else(|_elapsed| {
+                Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("Unable to connect to {} in time", socket_address),
+                ))
+            });
+
+            match connection {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "No address to connect to")
+        }))
+    }
+
+    async fn connect_address(&self, socket_address: SocketAddr) -> io::Result<TcpStream> {
+        let socket = if socket_address.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+
+        // As hyper does, addresses of the other family are connected to from any address.
+        if let Some(local_address) = self
+            .local_address
+            .filter(|local_address| local_address.is_ipv4() == socket_address.is_ipv4())
+        {
+            socket.bind(SocketAddr::new(local_address, 0))?;
+        }
+
+        socket.connect(socket_address).await
+    }
+}
+
+/// Requests are forwarded by clients of their own, which verify the certificates of
+/// servers according to the upstream TLS settings and refuse to connect to addresses
+/// blocked by an IP rule.
+fn build_client(
+    upstream_tls_store: &UpstreamTlsStore,
+    ip_rule_store: &IpRuleStore,
+    upstream_connections: &UpstreamConnections,
+    local_address: Option<IpAddr>,
+) -> reqwest::Client {
+    let mut tls_config = upstream_tls_store.get_client_config();
+    tls_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    let mut builder = reqwest::Client::builder()
+        .use_preconfigured_tls(tls_config)
+        .redirect(Policy::none())
+        .no_proxy()
+        .dns_resolver(Arc::new(ip_rule_store.clone()))
+        .gzip(true)
+        .brotli(true)
+        .deflate(true)
+        .connect_timeout(upstream_connections.connect_timeout())
+        .pool_idle_timeout(upstream_connections.pool_idle_timeout())
+        .tcp_keepalive(upstream_connections.tcp_keepalive())
+        .local_address(local_address);
+    if let Some(max_idle_connections_per_host) = upstream_connections.max_idle_connections_per_host
+    {
+        builder = builder.pool_max_idle_per_host(max_idle_connections_per_host);
+    }
+
+    builder.build().unwrap()
+}
+
+/// Hyper's client don't follow redirects, which is what we want, nothing to disable here.
+fn build_hyper_client(
+    upstream_tls_store: &UpstreamTlsStore,
+    ip_rule_store: &IpRuleStore,
+    upstream_connections: &UpstreamConnections,
+    local_address: Option<IpAddr>,
+) -> HyperClient {
+    let mut http_connector = HttpConnector::new_with_resolver(ip_rule_store.clone());
+    http_connector.enforce_http(false);
+    http_connector.set_connect_timeout(Some(upstream_connections.connect_timeout()));
+    http_connector.set_keepalive(upstream_connections.tcp_keepalive());
+    http_connector.set_local_address(local_address);
+    let https_connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(upstream_tls_store.get_client_config())
+        .https_or_http()
+        .enable_http1()
+        .wrap_connector(http_connector);
+
+    let mut builder = hyper::Client::builder();
+    builder.pool_idle_timeout(upstream_connections.pool_idle_timeout());
+    if let Some(max_idle_connections_per_host) = upstream_connections.max_idle_connections_per_host
+    {
+        builder.pool_max_idle_per_host(max_idle_connections_per_host);
+    }
+
+    builder.build(https_connector)
+}
+
+#[derive(Clone)]
+pub(crate) struct UpstreamClients {
+    upstream_connections: Arc<UpstreamConnections>,
+    clients: Arc<HashMap<Option<IpAddr>, UpstreamClient>>,
+}
+
+impl UpstreamClients {
+    pub(crate) fn new(
+        upstream_tls_store: &UpstreamTlsStore,
+        ip_rule_store: &IpRuleStore,
+        upstream_connections: UpstreamConnections,
+    ) -> Self {
+        let upstream_connections = Arc::new(upstream_connections);
+
+        let mut clients = HashMap::new();
+        let local_addresses = std::iter::once(upstream_connections.local_address).chain(
+            upstream_connections
+                .local_address_rules
+                .iter()
+                .map(|rule| Some(rule.local_address)),
+        );
+        for local_address in local_addresses {
+            clients.entry(local_address).or_insert_with(|| {
+                UpstreamClient::new(
+                    upstream_tls_store,
+                    ip_rule_store,
+                    upstream_connections.clone(),
+                    local_address,
+                )
+            });
+        }
+
+        Self {
+            upstream_connections,
+            clients: Arc::new(clients),
+        }
+    }
+
+    /// Returns the client connecting to `host` from the local address set for it.
+    pub(crate) fn get(&self, host: &str) -> &UpstreamClient {
+        &self.clients[&self.upstream_connections.get_local_address(host)]
+    }
+
+    /// Returns the client of hosts no local address rule applies to.
+    pub(crate) fn get_default(&self) -> &UpstreamClient {
+        &self.clients[&self.upstream_connections.local_address]
+    }
+}
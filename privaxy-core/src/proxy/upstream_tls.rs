@@ -0,0 +1,326 @@
+use crate::configuration::{UpstreamCertificatePolicy, UpstreamTls};
+use chrono::{DateTime, Utc};
+use openssl::asn1::{Asn1Time, Asn1TimeRef};
+use openssl::hash::MessageDigest;
+use openssl::x509::{X509NameRef, X509};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+use uluru::LRUCache;
+
+const MAX_CAPTURED_CERTIFICATE_CHAINS: usize = 1_000;
+
+/// Hosts are compared the way they are written in the settings.
+fn normalize_host(host: &str) -> String {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase()
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpstreamCertificate {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+    /// DNS names and IP addresses the certificate is valid for.
+    pub subject_alternative_names: Vec<String>,
+    pub sha256_fingerprint: String,
+}
+
+impl UpstreamCertificate {
+    fn from_der(der: &[u8]) -> Result<Self, openssl::error::ErrorStack> {
+        let certificate = X509::from_der(der)?;
+
+        let subject_alternative_names = certificate
+            .subject_alt_names()
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| {
+                        name.dnsname().map(str::to_string).or_else(|| {
+                            name.ipaddress().and_then(|ip_address| {
+                                match <[u8; 4]>::try_from(ip_address) {
+                                    Ok(ipv4) => Some(std::net::IpAddr::from(ipv4).to_string()),
+                                    Err(_) => <[u8; 16]>::try_from(ip_address)
+                                        .ok()
+                                        .map(|ipv6| std::net::IpAddr::from(ipv6).to_string()),
+                                }
+                            })
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let sha256_fingerprint = certificate
+            .digest(MessageDigest::sha256())?
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect::<Vec<_>>()
+            .join(":");
+
+        Ok(Self {
+            subject: format_name(certificate.subject_name()),
+            issuer: format_name(certificate.issuer_name()),
+            not_before: to_date_time(certificate.not_before()),
+            not_after: to_date_time(certificate.not_after()),
+            subject_alternative_names,
+            sha256_fingerprint,
+        })
+    }
+}
+
+/// Formats names the way browsers show them, such as `CN=example.com, O=Example`.
+fn format_name(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("?");
+            let value = String::from_utf8_lossy(entry.data().as_slice());
+
+            format!("{key}={value}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn to_date_time(time: &Asn1TimeRef) -> Option<DateTime<Utc>> {
+    let diff = Asn1Time::from_unix(0).ok()?.diff(time).ok()?;
+
+    DateTime::from_timestamp(i64::from(diff.days) * 86_400 + i64::from(diff.secs), 0)
+}
+
+#[derive(Debug, Serialize)]
+/// Certificates a server presented the last time a connection was made to it.
+pub struct UpstreamCertificateChain {
+    pub host: String,
+    /// The certificate of the server first, followed by the intermediates it sent.
+    pub certificates: Vec<UpstreamCertificate>,
+    /// Why the chain is invalid, it may have been accepted anyway.
+    pub verification_error: Option<String>,
+    pub captured_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct CapturedCertificateChain {
+    host: String,
+    certificates: Vec<Certificate>,
+    verification_error: Option<String>,
+    captured_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct UpstreamTlsState {
+    upstream_tls: UpstreamTls,
+    /// Why the certificate of a host was rejected, until one is accepted again.
+    certificate_errors: HashMap<String, String>,
+    certificate_chains: LRUCache<CapturedCertificateChain, MAX_CAPTURED_CERTIFICATE_CHAINS>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpstreamTlsStore(Arc<RwLock<UpstreamTlsState>>);
+
+impl UpstreamTlsStore {
+    pub fn new(upstream_tls: UpstreamTls) -> Self {
+        Self(Arc::new(RwLock::new(UpstreamTlsState {
+            upstream_tls,
+            certificate_errors: HashMap::new(),
+            certificate_chains: LRUCache::default(),
+        })))
+    }
+
+    pub fn replace(&self, upstream_tls: UpstreamTls) {
+        let mut state = self.0.write().unwrap();
+
+        state.upstream_tls = upstream_tls;
+        state.certificate_errors.clear();
+    }
+
+    /// Why the certificate of `host` was rejected, if it was.
+    pub(crate) fn get_certificate_error(&self, host: &str) -> Option<String> {
+        self.0
+            .read()
+            .unwrap()
+            .certificate_errors
+            .get(&normalize_host(host))
+            .cloned()
+    }
+
+    /// Certificates presented by `host` the last time a connection was made to it.
+    pub(crate) fn get_certificate_chain(&self, host: &str) -> Option<UpstreamCertificateChain> {
+        let host = normalize_host(host);
+
+        let (certificates, verification_error, captured_at) = {
+            let mut state = self.0.write().unwrap();
+            let captured = state
+                .certificate_chains
+                .find(|captured| captured.host == host)?;
+
+            (
+                captured.certificates.clone(),
+                captured.verification_error.clone(),
+                captured.captured_at,
+            )
+        };
+
+        let certificates = certificates
+            .iter()
+            .filter_map(
+                |certificate| match UpstreamCertificate::from_der(&certificate.0) {
+                    Ok(certificate) => Some(certificate),
+                    Err(err) => {
+                        log::warn!("Failed to parse certificate of {host}: {err}");
+                        None
+                    }
+                },
+            )
+            .collect();
+
+        Some(UpstreamCertificateChain {
+            host,
+            certificates,
+            verification_error,
+            captured_at,
+        })
+    }
+
+    /// TLS configuration of the connections to servers. Their certificates are verified
+    /// according to the settings of the store at the time of the handshake.
+    pub(crate) fn get_client_config(&self) -> ClientConfig {
+        let mut root_store = RootCertStore::empty();
+        root_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|trust_anchor| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                trust_anchor.subject,
+                trust_anchor.spki,
+                trust_anchor.name_constraints,
+            )
+        }));
+
+        let verifier = UpstreamCertificateVerifier {
+            verifier: WebPkiVerifier::new(root_store, None),
+            upstream_tls_store: self.clone(),
+        };
+
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth()
+    }
+
+    /// Certificates are kept as received, they are only parsed when looked at.
+    fn capture_certificate_chain(
+        &self,
+        host: &str,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        verification_error: Option<String>,
+    ) {
+        let mut certificates = Vec::with_capacity(intermediates.len() + 1);
+        certificates.push(end_entity.clone());
+        certificates.extend_from_slice(intermediates);
+
+        let mut state = self.0.write().unwrap();
+
+        if let Some(captured) = state
+            .certificate_chains
+            .find(|captured| captured.host == host)
+        {
+            captured.certificates = certificates;
+            captured.verification_error = verification_error;
+            captured.captured_at = Utc::now();
+        } else {
+            state.certificate_chains.insert(CapturedCertificateChain {
+                host: host.to_string(),
+                certificates,
+                verification_error,
+                captured_at: Utc::now(),
+            });
+        }
+    }
+
+    fn accept_certificate(&self, host: &str) {
+        if self.0.read().unwrap().certificate_errors.contains_key(host) {
+            self.0.write().unwrap().certificate_errors.remove(host);
+        }
+    }
+
+    /// Decides whether an invalid certificate is used anyway.
+    fn reject_certificate(&self, host: String, error: rustls::Error) -> Result<(), rustls::Error> {
+        let mut state = self.0.write().unwrap();
+
+        if state.upstream_tls.overrides.contains(&host) {
+            log::debug!("Accepted invalid certificate of overridden host {host}: {error}");
+
+            return Ok(());
+        }
+
+        match state.upstream_tls.policy {
+            UpstreamCertificatePolicy::Warn => {
+                log::warn!("Accepted invalid certificate of {host}: {error}");
+
+                Ok(())
+            }
+            UpstreamCertificatePolicy::Strict => {
+                log::warn!("Rejected invalid certificate of {host}: {error}");
+                state.certificate_errors.insert(host, error.to_string());
+
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Verifies certificates as browsers do, accepting invalid ones when the settings allow it.
+struct UpstreamCertificateVerifier {
+    verifier: WebPkiVerifier,
+    upstream_tls_store: UpstreamTlsStore,
+}
+
+impl ServerCertVerifier for UpstreamCertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let host = match server_name {
+            ServerName::DnsName(dns_name) => normalize_host(dns_name.as_ref()),
+            ServerName::IpAddress(ip_address) => ip_address.to_string(),
+            _ => String::new(),
+        };
+
+        let verification = self.verifier.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        );
+
+        self.upstream_tls_store.capture_certificate_chain(
+            &host,
+            end_entity,
+            intermediates,
+            verification.as_ref().err().map(ToString::to_string),
+        );
+
+        match verification {
+            Ok(verified) => {
+                self.upstream_tls_store.accept_certificate(&host);
+
+                Ok(verified)
+            }
+            Err(error) => self
+                .upstream_tls_store
+                .reject_certificate(host, error)
+                .map(|()| ServerCertVerified::assertion()),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::configuration::{UrlRewrite, UrlRewriteAction};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use url::Url;
+
+/// How often a rule applied, since Privaxy started.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct UrlRewriteHits {
+    pub pattern: String,
+    pub hits: u64,
+    pub last_hit_at: Option<DateTime<Utc>>,
+}
+
+/// URL a request is rewritten to, and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RewrittenUrl {
+    Redirect(String),
+    Rewrite(String),
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// Normalized URL, so that `https://example.com` matches `https://example.com/`.
+    Exact(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+struct CompiledUrlRewrite {
+    url_rewrite: UrlRewrite,
+    matcher: Matcher,
+}
+
+impl CompiledUrlRewrite {
+    fn compile(url_rewrite: UrlRewrite) -> Option<Self> {
+        let matcher = if url_rewrite.regex {
+            url_rewrite
+                .build_regex()
+                .map(Matcher::Regex)
+                .map_err(|err| err.to_string())
+        } else {
+            Url::parse(&url_rewrite.pattern)
+                .map(|url| Matcher::Exact(url.to_string()))
+                .map_err(|err| err.to_string())
+        };
+
+        match matcher {
+            Ok(matcher) => Some(Self {
+                url_rewrite,
+                matcher,
+            }),
+            Err(err) => {
+                log::warn!("Ignoring URL rewrite {:?}: {}", url_rewrite.pattern, err);
+                None
+            }
+        }
+    }
+
+    fn rewrite(&self, url: &str) -> Option<String> {
+        let rewritten_url = match &self.matcher {
+            Matcher::Exact(exact_url) if url == exact_url => self.url_rewrite.replacement.clone(),
+            Matcher::Regex(regex) if regex.is_match(url) => regex
+                .replace(url, self.url_rewrite.replacement.as_str())
+                .into_owned(),
+            _ => return None,
+        };
+
+        // A rule rewriting a URL to itself, such as one upgrading to https a URL that
+        // already is, doesn't apply.
+        (rewritten_url != url).then_some(rewritten_url)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Hits {
+    hits: u64,
+    last_hit_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UrlRewriteStore {
+    url_rewrites: Arc<RwLock<Vec<CompiledUrlRewrite>>>,
+    /// Keyed by pattern, so that they are kept when other rules are edited.
+    hits: Arc<Mutex<HashMap<String, Hits>>>,
+}
+
+impl UrlRewriteStore {
+    pub fn new(url_rewrites: Vec<UrlRewrite>) -> Self {
+        let store = Self {
+            url_rewrites: Arc::new(RwLock::new(Vec::new())),
+            hits: Arc::new(Mutex::new(HashMap::new())),
+        };
+        store.replace(url_rewrites);
+        store
+    }
+
+    pub fn replace(&self, url_rewrites: Vec<UrlRewrite>) {
+        let url_rewrites = url_rewrites
+            .into_iter()
+            .filter_map(CompiledUrlRewrite::compile)
+            .collect::<Vec<_>>();
+
+        self.hits.lock().unwrap().retain(|pattern, _hits| {
+            url_rewrites
+                .iter()
+                .any(|compiled| &compiled.url_rewrite.pattern == pattern)
+        });
+        *self.url_rewrites.write().unwrap() = url_rewrites;
+    }
+
+    /// Returns the URL the first enabled rule matching `url` rewrites it to.
+    pub(crate) fn apply(&self, url: &str) -> Option<RewrittenUrl> {
+        let url_rewrites = self.url_rewrites.read().unwrap();
+
+        let (compiled, rewritten_url) = url_rewrites
+            .iter()
+            .filter(|compiled| compiled.url_rewrite.enabled)
+            .find_map(|compiled| Some((compiled, compiled.rewrite(url)?)))?;
+
+        let mut hits = self.hits.lock().unwrap();
+        let hits = hits
+            .entry(compiled.url_rewrite.pattern.clone())
+            .or_default();
+        hits.hits += 1;
+        hits.last_hit_at = Some(Utc::now());
+
+        Some(match compiled.url_rewrite.action {
+            UrlRewriteAction::Redirect => RewrittenUrl::Redirect(rewritten_url),
+            UrlRewriteAction::Rewrite => RewrittenUrl::Rewrite(rewritten_url),
+        })
+    }
+
+    /// Hits of every rule, in the order rules apply.
+    pub(crate) fn get_hits(&self) -> Vec<UrlRewriteHits> {
+        let url_rewrites = self.url_rewrites.read().unwrap();
+        let hits = self.hits.lock().unwrap();
+
+        url_rewrites
+            .iter()
+            .map(|compiled| {
+                let pattern = &compiled.url_rewrite.pattern;
+                let rule_hits = hits.get(pattern);
+
+                UrlRewriteHits {
+                    pattern: pattern.clone(),
+                    hits: rule_hits.map_or(0, |rule_hits| rule_hits.hits),
+                    last_hit_at: rule_hits.and_then(|rule_hits| rule_hits.last_hit_at),
+                }
+            })
+            .collect()
+    }
+}
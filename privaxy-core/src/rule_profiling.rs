@@ -0,0 +1,304 @@
+//! Opt-in profiling of the time filter lists, and their regex rules, take to match
+//! requests. Regex rules can't be indexed by the engine and are tried against every
+//! request, so a few of them are enough to slow a list down on low-power hardware.
+//!
+//! Requests are matched again on a thread of their own, against an engine built for
+//! each list and each regex rule, so that profiling doesn't delay proxied requests.
+
+use crate::configuration::{FilterContent, ResourceType};
+use adblock::lists::{FilterSet, ParseOptions, ParsedFilter};
+use adblock::request::Request;
+use adblock::Engine;
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Requests waiting to be profiled, requests beyond that are skipped.
+const QUEUED_REQUESTS_CAPACITY: usize = 1_024;
+/// The most expensive rules reported.
+const REPORTED_RULES: usize = 50;
+
+pub(crate) enum ProfiledRequest {
+    Network {
+        url: String,
+        referer: String,
+        resource_type: ResourceType,
+    },
+    Cosmetic {
+        url: String,
+    },
+}
+
+/// Time spent matching requests against a filter list or a rule.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RuleCost {
+    /// Title of the filter list, or the rule.
+    pub name: String,
+    /// Title of the filter list the rule comes from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list: Option<String>,
+    pub total_us: u64,
+    pub mean_us: f64,
+    pub max_us: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct RulePerformance {
+    pub enabled: bool,
+    /// Whether the engines of each list and rule are still being built, requests are
+    /// profiled once they are ready.
+    pub preparing: bool,
+    /// Network requests matched against every list and regex rule since profiling
+    /// started.
+    pub network_requests: u64,
+    /// Pages whose cosmetic filters were looked up in every list.
+    pub cosmetic_requests: u64,
+    /// Requests left out because profiling couldn't keep up with them.
+    pub skipped_requests: u64,
+    /// Filter lists, most expensive first, over network and cosmetic requests.
+    pub lists: Vec<RuleCost>,
+    /// Regex network rules, most expensive first.
+    pub rules: Vec<RuleCost>,
+}
+
+#[derive(Debug, Clone)]
+struct Cost {
+    name: String,
+    list: Option<String>,
+    total: Duration,
+    max: Duration,
+}
+
+impl Cost {
+    fn new(name: String, list: Option<String>) -> Self {
+        Self {
+            name,
+            list,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+
+    fn add(&mut self, elapsed: Duration) {
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    fn get_rule_cost(&self, requests: u64) -> RuleCost {
+        RuleCost {
+            name: self.name.clone(),
+            list: self.list.clone(),
+            total_us: self.total.as_micros() as u64,
+            mean_us: if requests == 0 {
+                0.
+            } else {
+                self.total.as_micros() as f64 / requests as f64
+            },
+            max_us: self.max.as_micros() as u64,
+        }
+    }
+}
+
+/// Costs of a profiling, in the same order as the engines of its thread.
+#[derive(Debug, Default)]
+struct Profile {
+    preparing: bool,
+    network_requests: u64,
+    cosmetic_requests: u64,
+    skipped_requests: u64,
+    lists: Vec<Cost>,
+    rules: Vec<Cost>,
+}
+
+#[derive(Default)]
+struct ProfilerState {
+    /// Queue of the profiling thread, while profiling.
+    sender: Option<Sender<ProfiledRequest>>,
+    /// Kept once profiling stops, for its results to remain available.
+    profile: Arc<Mutex<Profile>>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct RuleProfiler(Arc<RwLock<ProfilerState>>);
+
+impl RuleProfiler {
+    /// Starts profiling `filters` over, replacing any previous profiling.
+    pub(crate) fn start(&self, filters: Vec<FilterContent>) {
+        let (sender, receiver) = crossbeam_channel::bounded(QUEUED_REQUESTS_CAPACITY);
+        let profile = Arc::new(Mutex::new(Profile {
+            preparing: true,
+            ..Default::default()
+        }));
+
+        // Replacing the sender disconnects the thread of the previous profiling.
+        *self.0.write().unwrap() = ProfilerState {
+            sender: Some(sender),
+            profile: profile.clone(),
+        };
+
+        std::thread::spawn(move || profile_requests(filters, receiver, profile));
+    }
+
+    pub(crate) fn stop(&self) {
+        self.0.write().unwrap().sender = None;
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.0.read().unwrap().sender.is_some()
+    }
+
+    /// Queues a request to be profiled, when profiling.
+    pub(crate) fn profile(&self, request: ProfiledRequest) {
+        let state = self.0.read().unwrap();
+
+        if let Some(sender) = &state.sender {
+            if let Err(TrySendError::Full(_request)) = sender.try_send(request) {
+                state.profile.lock().unwrap().skipped_requests += 1;
+            }
+        }
+    }
+
+    pub(crate) fn get_performance(&self) -> RulePerformance {
+        let state = self.0.read().unwrap();
+        let profile = state.profile.lock().unwrap();
+
+        let get_rule_costs = |costs: &[Cost], requests: u64| {
+            let mut rule_costs = costs
+                .iter()
+                .map(|cost| cost.get_rule_cost(requests))
+                .collect::<Vec<_>>();
+            rule_costs.sort_by_key(|rule_cost| Reverse(rule_cost.total_us));
+            rule_costs
+        };
+
+        let mut rules = get_rule_costs(&profile.rules, profile.network_requests);
+        rules.truncate(REPORTED_RULES);
+
+        RulePerformance {
+            enabled: state.sender.is_some(),
+            preparing: profile.preparing,
+            network_requests: profile.network_requests,
+            cosmetic_requests: profile.cosmetic_requests,
+            skipped_requests: profile.skipped_requests,
+            lists: get_rule_costs(
+                &profile.lists,
+                profile.network_requests + profile.cosmetic_requests,
+            ),
+            rules,
+        }
+    }
+}
+
+/// Regex rules are those with a `/.../` pattern, exceptions included.
+fn is_regex_rule(rule: &str) -> bool {
+    match adblock::lists::parse_filter(rule, false, ParseOptions::default()) {
+        Ok(ParsedFilter::Network(network_filter)) => network_filter.is_regex(),
+        _ => false,
+    }
+}
+
+fn build_engine(rules: impl IntoIterator<Item = impl AsRef<str>>) -> Engine {
+    let mut filter_set = FilterSet::new(false);
+    filter_set.add_filters(rules, ParseOptions::default());
+
+    Engine::from_filter_set(filter_set, true)
+}
+
+/// Profiles queued requests until profiling stops.
+fn profile_requests(
+    filters: Vec<FilterContent>,
+    receiver: Receiver<ProfiledRequest>,
+    profile: Arc<Mutex<Profile>>,
+) {
+    let started_at = Instant::now();
+
+    let mut list_engines = Vec::with_capacity(filters.len());
+    let mut list_costs = Vec::with_capacity(filters.len());
+    let mut rule_engines = Vec::new();
+    let mut rule_costs = Vec::new();
+
+    for filter in &filters {
+        list_engines.push(build_engine(filter.content.lines()));
+        list_costs.push(Cost::new(filter.source.clone(), None));
+
+        for rule in filter.content.lines().map(str::trim) {
+            if is_regex_rule(rule) {
+                rule_engines.push(build_engine([rule]));
+                rule_costs.push(Cost::new(rule.to_string(), Some(filter.source.clone())));
+            }
+        }
+    }
+
+    log::debug!(
+        "Profiling {} filter lists and {} regex rules, built in {:?}",
+        list_engines.len(),
+        rule_engines.len(),
+        started_at.elapsed()
+    );
+
+    {
+        let mut profile = profile.lock().unwrap();
+        profile.preparing = false;
+        profile.lists = list_costs;
+        profile.rules = rule_costs;
+    }
+
+    let measure = |engines: &[Engine], check: &dyn Fn(&Engine)| {
+        engines
+            .iter()
+            .map(|engine| {
+                let started_at = Instant::now();
+                check(engine);
+                started_at.elapsed()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for request in receiver {
+        let is_network_request = matches!(request, ProfiledRequest::Network { .. });
+        let (list_durations, rule_durations) = match request {
+            ProfiledRequest::Network {
+                url,
+                referer,
+                resource_type,
+            } => {
+                let request = match Request::new(&url, &referer, resource_type.as_adblock_str()) {
+                    Ok(request) => request,
+                    Err(_err) => continue,
+                };
+                let check = |engine: &Engine| {
+                    engine.check_network_request(&request);
+                };
+
+                (
+                    measure(&list_engines, &check),
+                    measure(&rule_engines, &check),
+                )
+            }
+            ProfiledRequest::Cosmetic { url } => {
+                let check = |engine: &Engine| {
+                    engine.url_cosmetic_resources(&url);
+                };
+
+                (measure(&list_engines, &check), Vec::new())
+            }
+        };
+
+        let mut profile = profile.lock().unwrap();
+        if is_network_request {
+            profile.network_requests += 1;
+        } else {
+            profile.cosmetic_requests += 1;
+        }
+
+        for (cost, elapsed) in profile.lists.iter_mut().zip(list_durations) {
+            cost.add(elapsed);
+        }
+        for (cost, elapsed) in profile.rules.iter_mut().zip(rule_durations) {
+            cost.add(elapsed);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::bench::BenchOptions;
+use crate::logging::{LogFormat, LogSink};
+use clap::{Parser, Subcommand};
+use once_cell::sync::OnceCell;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+static STARTUP_OPTIONS: OnceCell<StartupOptions> = OnceCell::new();
+
+/// Settings given on the command line or through `PRIVAXY_` environment variables.
+///
+/// They take precedence over the configuration file and are never saved to it, so
+/// container and systemd deployments can be configured without editing it.
+#[derive(Debug, Clone, Default, Parser)]
+#[command(name = "privaxy", version, about)]
+pub struct StartupOptions {
+    /// Directory of the configuration file, the filters and the other files privaxy keeps.
+    #[arg(long, env = "PRIVAXY_BASE_PATH")]
+    pub base_path: Option<PathBuf>,
+    /// Directory of the downloaded filters, relative to the base path.
+    #[arg(long, env = "PRIVAXY_FILTER_PATH")]
+    pub filter_path: Option<PathBuf>,
+    /// Path or URL of the managed configuration, `managed.toml` in the base path by default.
+    #[arg(long, env = "PRIVAXY_MANAGED_CONFIGURATION")]
+    pub managed_configuration: Option<String>,
+    /// Address the proxy and the web interface listen on.
+    #[arg(long, env = "PRIVAXY_IP_ADDRESS")]
+    pub ip_address: Option<IpAddr>,
+    #[arg(long, env = "PRIVAXY_PROXY_PORT")]
+    pub proxy_port: Option<u16>,
+    #[arg(long, env = "PRIVAXY_WEB_PORT")]
+    pub web_port: Option<u16>,
+    /// Path the web interface is served under, such as `/privaxy/` behind a reverse proxy.
+    #[arg(long, env = "PRIVAXY_WEB_BASE_PATH")]
+    pub web_base_path: Option<String>,
+    /// Path of a Unix socket the web interface and the API are also served on.
+    #[arg(long, env = "PRIVAXY_ADMIN_SOCKET")]
+    pub admin_socket: Option<PathBuf>,
+    /// Serve the web interface over HTTPS.
+    #[arg(long, env = "PRIVAXY_TLS")]
+    pub tls: Option<bool>,
+    /// Log filter, such as `info` or `privaxy=debug`. Defaults to `RUST_LOG`, then to
+    /// `privaxy=info`.
+    #[arg(long, env = "PRIVAXY_LOG_LEVEL")]
+    pub log_level: Option<String>,
+    /// Format of the logs, `text` or `json`.
+    #[arg(long, env = "PRIVAXY_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    /// Where logs are written, `stdout`, `file` or `syslog`, which journald also reads.
+    /// Defaults to `stdout`.
+    #[arg(long = "log-sink", env = "PRIVAXY_LOG_SINKS", value_delimiter = ',')]
+    pub log_sinks: Vec<LogSink>,
+    /// File the `file` sink writes to, `privaxy.log` in the base path by default. It is
+    /// rotated as it grows.
+    #[arg(long, env = "PRIVAXY_LOG_FILE")]
+    pub log_file: Option<PathBuf>,
+    /// Start with blocking disabled.
+    #[arg(long, env = "PRIVAXY_DISABLE_BLOCKING")]
+    pub disable_blocking: bool,
+    /// Don't apply the edits made to the configuration file while privaxy runs.
+    #[arg(long, env = "PRIVAXY_NO_CONFIGURATION_WATCH")]
+    pub no_configuration_watch: bool,
+    /// Serve the API without the web interface, as builds without the `web-frontend`
+    /// feature do.
+    #[arg(long, env = "PRIVAXY_NO_WEB_INTERFACE")]
+    pub no_web_interface: bool,
+    /// Directory to serve the web interface from instead of the built-in one, such as
+    /// the `dist` directory of a frontend rebuilt with `trunk build`.
+    #[arg(long, env = "PRIVAXY_FRONTEND_DIRECTORY")]
+    pub frontend_directory: Option<PathBuf>,
+    /// Don't start the web server, leaving the admin socket as the only way to reach the
+    /// API, when one is set.
+    #[arg(long, env = "PRIVAXY_NO_WEB_SERVER")]
+    pub no_web_server: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Runs something else than the proxy.
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Measures how fast requests of a corpus are matched by the blocking engine and
+    /// proxied, for performance to be compared between releases.
+    Bench(BenchOptions),
+}
+
+/// Sets the options read when privaxy starts. Only the first call has an effect.
+pub fn set_startup_options(startup_options: StartupOptions) {
+    if !try_set_startup_options(startup_options) {
+        log::warn!("Startup options were already set");
+    }
+}
+
+/// Sets the options unless they were already set or read, returning whether they were
+/// set.
+pub(crate) fn try_set_startup_options(startup_options: StartupOptions) -> bool {
+    STARTUP_OPTIONS.set(startup_options).is_ok()
+}
+
+/// Returns the startup options, read from the environment alone when they were not set.
+pub(crate) fn get_startup_options() -> &'static StartupOptions {
+    STARTUP_OPTIONS.get_or_init(|| {
+        StartupOptions::try_parse_from(["privaxy"]).unwrap_or_else(|err| {
+            log::error!("Ignoring invalid environment variables: {}", err);
+            StartupOptions::default()
+        })
+    })
+}
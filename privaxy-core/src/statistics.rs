@@ -0,0 +1,1124 @@
+use crate::blocker::{MatchedFilter, RequestDecision};
+use crate::configuration::{self, Filter, ThreatSeverity, CUSTOM_FILTERS_SOURCE};
+use crate::timeseries::TimeSeries;
+use crate::tracker_entities::TrackerEntities;
+use chrono::{DateTime, DurationRound, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use uluru::LRUCache;
+use uuid::Uuid;
+
+const ENTRIES_PER_STATISTICS_TABLE: u8 = 50;
+/// Number of recent requests whose decision can be looked up.
+const RECORDED_DECISIONS: usize = 1_000;
+/// History is kept for five weeks, enough to compare a week to the previous one
+/// with some margin.
+const HISTORY_BUCKETS: usize = 24 * 7 * 5;
+/// Blocked and allowed domains of a bucket past this number only count towards its
+/// totals.
+const HISTORY_DOMAINS_PER_BUCKET: usize = 100;
+/// Blocked domains returned for each client.
+const CLIENT_TOP_BLOCKED_DOMAINS: usize = 10;
+/// Statistics since the last save are lost when Privaxy stops.
+const HISTORY_SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Upper bounds of the buckets latencies are counted in, in microseconds. Slower
+/// latencies are counted in a last bucket of their own.
+pub const LATENCY_BUCKETS: [u64; 16] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000,
+];
+
+/// Requests proxied during an hour.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryBucket {
+    pub start: DateTime<Utc>,
+    pub proxied_requests: u64,
+    pub blocked_requests: u64,
+    pub blocked_domains: HashMap<String, u64>,
+    /// Domains of proxied requests.
+    #[serde(default)]
+    pub allowed_domains: HashMap<String, u64>,
+}
+
+impl HistoryBucket {
+    fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            proxied_requests: 0,
+            blocked_requests: 0,
+            blocked_domains: HashMap::new(),
+            allowed_domains: HashMap::new(),
+        }
+    }
+}
+
+/// What [`Statistics::get_top`] ranks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TopKind {
+    /// Domains of blocked requests.
+    Blocked,
+    /// Domains of proxied requests.
+    Allowed,
+    /// Companies owning the trackers of blocked requests.
+    Companies,
+}
+
+/// A domain, or a company, and its number of requests.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TopEntry {
+    pub name: String,
+    pub requests: u64,
+    /// Company owning the domain, when it's a known tracker.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+}
+
+/// Requests of a client since statistics were last reset.
+#[derive(Debug, Clone)]
+pub struct ClientActivity {
+    pub requests: u64,
+    pub blocked_requests: u64,
+    pub blocked_domains: HashMap<String, u64>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl ClientActivity {
+    fn new() -> Self {
+        Self {
+            requests: 0,
+            blocked_requests: 0,
+            blocked_domains: HashMap::new(),
+            last_seen: Utc::now(),
+        }
+    }
+}
+
+/// Requests of a device, or of an authenticated proxy user.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ClientStatistics {
+    /// Authenticated proxy user, or the client as identified by the client
+    /// identification settings, such as its IP address.
+    pub client: String,
+    pub requests: u64,
+    pub blocked_requests: u64,
+    /// Domains blocked the most for this client.
+    pub top_blocked_domains: Vec<TopEntry>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Decision taken for a proxied request.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RecordedDecision {
+    pub id: u64,
+    pub now: DateTime<Utc>,
+    pub method: String,
+    pub url: String,
+    #[serde(flatten)]
+    pub decision: RequestDecision,
+}
+
+/// Requests blocked by the rules of a filter list.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FilterListStatistics {
+    /// Identifier of the filter, for sources that aren't configured filters such as
+    /// custom filters.
+    pub id: Option<Uuid>,
+    pub title: String,
+    pub blocked_requests: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct FilterStatistics {
+    /// Enabled filter lists, including those that didn't block anything, and the
+    /// other sources of rules, most blocking first.
+    pub lists: Vec<FilterListStatistics>,
+    /// Requests blocked by each custom filter, most blocking first.
+    #[serde(with = "tuple_vec_map")]
+    #[schemars(with = "HashMap<String, u64>")]
+    pub custom_rules: Vec<(String, u64)>,
+}
+
+/// Parts of serving requests that add to their latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyStage {
+    FilterMatch,
+    /// Handshakes with clients, for HTTPS connections.
+    TlsHandshake,
+    /// Everything done before a request is sent upstream, filter matching included.
+    RequestProcessing,
+    /// Hiding elements of pages and applying content rewrite rules.
+    Rewrite,
+}
+
+impl LatencyStage {
+    pub const ALL: [LatencyStage; 4] = [
+        LatencyStage::FilterMatch,
+        LatencyStage::TlsHandshake,
+        LatencyStage::RequestProcessing,
+        LatencyStage::Rewrite,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            LatencyStage::FilterMatch => "filter_match",
+            LatencyStage::TlsHandshake => "tls_handshake",
+            LatencyStage::RequestProcessing => "request_processing",
+            LatencyStage::Rewrite => "rewrite",
+        }
+    }
+}
+
+/// Latencies counted in the buckets of [`LATENCY_BUCKETS`].
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    bucket_counts: [u64; LATENCY_BUCKETS.len() + 1],
+    pub count: u64,
+    pub sum_us: u64,
+    max_us: u64,
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, latency: Duration) {
+        let latency_us = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|upper_bound| latency_us <= *upper_bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.bucket_counts[bucket] += 1;
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(latency_us);
+        self.max_us = self.max_us.max(latency_us);
+    }
+
+    /// Number of latencies up to each bucket upper bound, the last one having none.
+    pub fn get_cumulative_counts(&self) -> Vec<(Option<u64>, u64)> {
+        let upper_bounds = LATENCY_BUCKETS.iter().copied().map(Some).chain([None]);
+
+        upper_bounds
+            .zip(
+                self.bucket_counts
+                    .iter()
+                    .scan(0, |cumulative_count, count| {
+                        *cumulative_count += count;
+                        Some(*cumulative_count)
+                    }),
+            )
+            .collect()
+    }
+
+    /// Estimates the latency under which `quantile` of latencies are, interpolating
+    /// within the bucket it falls in like Prometheus does.
+    fn get_quantile_us(&self, quantile: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+
+        let rank = quantile * self.count as f64;
+        let mut lower_bound = 0;
+        let mut previous_count = 0;
+
+        for (upper_bound, cumulative_count) in self.get_cumulative_counts() {
+            let upper_bound = upper_bound.unwrap_or(self.max_us).min(self.max_us);
+
+            if cumulative_count as f64 >= rank && cumulative_count > previous_count {
+                let bucket_count = (cumulative_count - previous_count) as f64;
+                let position = (rank - previous_count as f64) / bucket_count;
+
+                let quantile_us =
+                    lower_bound as f64 + upper_bound.saturating_sub(lower_bound) as f64 * position;
+
+                return quantile_us.round();
+            }
+
+            lower_bound = upper_bound;
+            previous_count = cumulative_count;
+        }
+
+        self.max_us as f64
+    }
+
+    fn get_summary(&self) -> LatencySummary {
+        let mean_us = if self.count == 0 {
+            0.0
+        } else {
+            self.sum_us as f64 / self.count as f64
+        };
+
+        LatencySummary {
+            count: self.count,
+            mean_ms: mean_us / 1_000.0,
+            p50_ms: self.get_quantile_us(0.5) / 1_000.0,
+            p90_ms: self.get_quantile_us(0.9) / 1_000.0,
+            p99_ms: self.get_quantile_us(0.99) / 1_000.0,
+            max_ms: self.max_us as f64 / 1_000.0,
+        }
+    }
+}
+
+/// Latencies of a stage since Privaxy started. Percentiles are estimated from the
+/// buckets latencies are counted in.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Latency added by Privaxy to requests, to tell whether filter lists are too large
+/// for the hardware they run on.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PerformanceStatistics {
+    pub filter_match: LatencySummary,
+    pub tls_handshake: LatencySummary,
+    pub request_processing: LatencySummary,
+    pub rewrite: LatencySummary,
+    /// Time taken to build the blocking engine of the filter lists, which happens in
+    /// the background while requests are checked against the previous engine.
+    pub engine_build: LatencySummary,
+    /// Time taken to match requests, by engine shard. Shards busier than others are
+    /// given the requests of more or heavier sites.
+    pub engine_shards: Vec<LatencySummary>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SerializableStatistics {
+    pub proxied_requests: u64,
+    pub blocked_requests: u64,
+    pub modified_responses: u64,
+    /// Media and large responses relayed without being inspected.
+    pub streamed_responses: u64,
+    pub event_stream_responses: u64,
+    pub grpc_responses: u64,
+    /// Cross-site referers reduced to their origin.
+    pub trimmed_referers: u64,
+    pub stripped_referers: u64,
+    /// Requests and responses whose cookies were removed by cookie rules.
+    pub stripped_cookies: u64,
+    /// Requests answered from the response cache without contacting the server.
+    pub cache_hits: u64,
+    /// Requests answered from the response cache once the server confirmed the cached
+    /// response was still valid.
+    pub cache_revalidations: u64,
+    /// Requests the response cache had no usable response for, while it was enabled.
+    pub cache_misses: u64,
+    /// Requests blocked as malware or phishing by threat feeds, by severity. They are
+    /// counted in `blocked_requests` as well.
+    pub security_blocks: HashMap<ThreatSeverity, u64>,
+    /// Requests blocked by content categories, by category. They are counted in
+    /// `blocked_requests` as well.
+    pub category_blocks: HashMap<String, u64>,
+    #[serde(with = "tuple_vec_map")]
+    #[schemars(with = "HashMap<String, u64>")]
+    pub top_blocked_paths: Vec<(String, u64)>,
+    /// Filter that last blocked each of the top blocked paths.
+    pub top_blocked_paths_filters: HashMap<String, MatchedFilter>,
+    #[serde(with = "tuple_vec_map")]
+    #[schemars(with = "HashMap<String, u64>")]
+    pub top_clients: Vec<(String, u64)>,
+    #[serde(with = "tuple_vec_map")]
+    #[schemars(with = "HashMap<String, u64>")]
+    pub top_users: Vec<(String, u64)>,
+}
+
+/// Blocked path, how many times it was blocked and the filter blocking it last.
+pub type BlockedPath = (String, u64, Option<MatchedFilter>);
+
+/// Client connection counted in [`Statistics::open_connections`] while it lives.
+#[derive(Debug)]
+pub struct OpenConnection(Arc<Mutex<u64>>);
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap() -= 1;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Statistics {
+    pub proxied_requests: Arc<Mutex<u64>>,
+    pub blocked_requests: Arc<Mutex<u64>>,
+    pub modified_responses: Arc<Mutex<u64>>,
+    pub streamed_responses: Arc<Mutex<u64>>,
+    pub event_stream_responses: Arc<Mutex<u64>>,
+    pub grpc_responses: Arc<Mutex<u64>>,
+    pub trimmed_referers: Arc<Mutex<u64>>,
+    pub stripped_referers: Arc<Mutex<u64>>,
+    pub stripped_cookies: Arc<Mutex<u64>>,
+    pub cache_hits: Arc<Mutex<u64>>,
+    pub cache_revalidations: Arc<Mutex<u64>>,
+    pub cache_misses: Arc<Mutex<u64>>,
+    pub security_blocks: Arc<Mutex<HashMap<ThreatSeverity, u64>>>,
+    pub category_blocks: Arc<Mutex<HashMap<String, u64>>>,
+    pub top_blocked_paths: Arc<Mutex<LRUCache<BlockedPath, 1_000>>>,
+    /// Keyed by logical client, as identified by the client identification settings.
+    pub top_clients: Arc<Mutex<HashMap<String, u64>>>,
+    /// Blocked requests, keyed like `top_clients`.
+    pub blocked_clients: Arc<Mutex<HashMap<String, u64>>>,
+    pub top_users: Arc<Mutex<HashMap<String, u64>>>,
+    /// Keyed by authenticated proxy user, or by logical client for requests of no user.
+    pub clients: Arc<Mutex<HashMap<String, ClientActivity>>>,
+    /// Blocked requests, keyed by the source of the rule that blocked them.
+    pub blocked_by_lists: Arc<Mutex<HashMap<String, u64>>>,
+    /// Requests blocked by custom filters, keyed by rule.
+    pub blocked_by_custom_rules: Arc<Mutex<HashMap<String, u64>>>,
+    /// Origins of proxied requests, used to warm up connections.
+    pub top_origins: Arc<Mutex<LRUCache<(String, u64), 1_000>>>,
+    pub last_request_id: Arc<Mutex<u64>>,
+    /// Most recent decisions, oldest first.
+    pub recent_decisions: Arc<Mutex<VecDeque<RecordedDecision>>>,
+    /// Hourly statistics, oldest first.
+    pub history: Arc<Mutex<VecDeque<HistoryBucket>>>,
+    /// Indexed by stage, in the order of [`LatencyStage::ALL`].
+    pub latencies: Arc<Mutex<[LatencyHistogram; LatencyStage::ALL.len()]>>,
+    pub engine_builds: Arc<Mutex<LatencyHistogram>>,
+    /// Indexed by engine shard.
+    pub engine_shard_matches: Arc<Mutex<Vec<LatencyHistogram>>>,
+    /// Not a counter, left alone when statistics are cleared.
+    pub open_connections: Arc<Mutex<u64>>,
+    /// Companies blocked domains are attributed to.
+    pub tracker_entities: TrackerEntities,
+    /// Request counters at several resolutions, for charts.
+    pub timeseries: TimeSeries,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Self {
+            proxied_requests: Arc::new(Mutex::new(0)),
+            blocked_requests: Arc::new(Mutex::new(0)),
+            modified_responses: Arc::new(Mutex::new(0)),
+            streamed_responses: Arc::new(Mutex::new(0)),
+            event_stream_responses: Arc::new(Mutex::new(0)),
+            grpc_responses: Arc::new(Mutex::new(0)),
+            trimmed_referers: Arc::new(Mutex::new(0)),
+            stripped_referers: Arc::new(Mutex::new(0)),
+            stripped_cookies: Arc::new(Mutex::new(0)),
+            cache_hits: Arc::new(Mutex::new(0)),
+            cache_revalidations: Arc::new(Mutex::new(0)),
+            cache_misses: Arc::new(Mutex::new(0)),
+            security_blocks: Arc::new(Mutex::new(HashMap::new())),
+            category_blocks: Arc::new(Mutex::new(HashMap::new())),
+            top_blocked_paths: Arc::new(Mutex::new(LRUCache::default())),
+            top_clients: Arc::new(Mutex::new(HashMap::new())),
+            blocked_clients: Arc::new(Mutex::new(HashMap::new())),
+            top_users: Arc::new(Mutex::new(HashMap::new())),
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            blocked_by_lists: Arc::new(Mutex::new(HashMap::new())),
+            blocked_by_custom_rules: Arc::new(Mutex::new(HashMap::new())),
+            top_origins: Arc::new(Mutex::new(LRUCache::default())),
+            last_request_id: Arc::new(Mutex::new(0)),
+            recent_decisions: Arc::new(Mutex::new(VecDeque::with_capacity(RECORDED_DECISIONS))),
+            history: Arc::new(Mutex::new(VecDeque::with_capacity(HISTORY_BUCKETS))),
+            latencies: Arc::new(Mutex::new(Default::default())),
+            engine_builds: Arc::new(Mutex::new(LatencyHistogram::default())),
+            engine_shard_matches: Arc::new(Mutex::new(Vec::new())),
+            open_connections: Arc::new(Mutex::new(0)),
+            tracker_entities: TrackerEntities::default(),
+            timeseries: TimeSeries::default(),
+        }
+    }
+
+    /// Starts counting over. Recent decisions are kept, as they explain requests rather
+    /// than count them.
+    pub fn clear(&self) {
+        for counter in [
+            &self.proxied_requests,
+            &self.blocked_requests,
+            &self.modified_responses,
+            &self.streamed_responses,
+            &self.event_stream_responses,
+            &self.grpc_responses,
+            &self.trimmed_referers,
+            &self.stripped_referers,
+            &self.stripped_cookies,
+            &self.cache_hits,
+            &self.cache_revalidations,
+            &self.cache_misses,
+        ] {
+            *counter.lock().unwrap() = 0;
+        }
+
+        for counts in [
+            &self.top_clients,
+            &self.blocked_clients,
+            &self.top_users,
+            &self.blocked_by_lists,
+            &self.blocked_by_custom_rules,
+        ] {
+            counts.lock().unwrap().clear();
+        }
+
+        self.security_blocks.lock().unwrap().clear();
+        self.category_blocks.lock().unwrap().clear();
+        self.clients.lock().unwrap().clear();
+        *self.top_blocked_paths.lock().unwrap() = LRUCache::default();
+        self.history.lock().unwrap().clear();
+        *self.latencies.lock().unwrap() = Default::default();
+        *self.engine_builds.lock().unwrap() = LatencyHistogram::default();
+        self.engine_shard_matches.lock().unwrap().clear();
+        self.timeseries.clear();
+    }
+
+    /// Calls `f` with the bucket of the current hour, creating it when needed.
+    fn update_current_history_bucket(&self, f: impl FnOnce(&mut HistoryBucket)) {
+        let start = Utc::now()
+            .duration_trunc(chrono::Duration::hours(1))
+            .unwrap();
+
+        let mut history = self.history.lock().unwrap();
+
+        if history.back().map(|bucket| bucket.start) != Some(start) {
+            if history.len() == HISTORY_BUCKETS {
+                history.pop_front();
+            }
+            history.push_back(HistoryBucket::new(start));
+        }
+
+        f(history.back_mut().unwrap())
+    }
+
+    pub fn increment_blocked_domains(&self, domain: &str) {
+        self.update_current_history_bucket(|bucket| {
+            increment_domain(&mut bucket.blocked_domains, domain)
+        });
+    }
+
+    pub fn increment_allowed_domains(&self, domain: &str) {
+        self.update_current_history_bucket(|bucket| {
+            increment_domain(&mut bucket.allowed_domains, domain)
+        });
+    }
+
+    /// Returns the `limit` domains or companies with the most requests in the buckets
+    /// starting from `from`, most requested first.
+    pub fn get_top(&self, kind: TopKind, from: DateTime<Utc>, limit: usize) -> Vec<TopEntry> {
+        let from = from.duration_trunc(chrono::Duration::hours(1)).unwrap();
+        let mut domains = HashMap::<String, u64>::new();
+
+        for bucket in self
+            .history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|bucket| bucket.start >= from)
+        {
+            let bucket_domains = match kind {
+                TopKind::Blocked | TopKind::Companies => &bucket.blocked_domains,
+                TopKind::Allowed => &bucket.allowed_domains,
+            };
+
+            for (domain, count) in bucket_domains {
+                *domains.entry(domain.clone()).or_default() += count;
+            }
+        }
+
+        let mut top = match kind {
+            TopKind::Blocked | TopKind::Allowed => domains
+                .into_iter()
+                .map(|(domain, requests)| TopEntry {
+                    entity: self.tracker_entities.get_entity(&domain),
+                    name: domain,
+                    requests,
+                })
+                .collect::<Vec<_>>(),
+            TopKind::Companies => {
+                let mut entities = HashMap::<String, u64>::new();
+
+                // Domains of no known company are left out.
+                for (domain, count) in domains {
+                    if let Some(entity) = self.tracker_entities.get_entity(&domain) {
+                        *entities.entry(entity).or_default() += count;
+                    }
+                }
+
+                entities
+                    .into_iter()
+                    .map(|(entity, requests)| TopEntry {
+                        name: entity,
+                        requests,
+                        entity: None,
+                    })
+                    .collect()
+            }
+        };
+
+        top.sort_by(|a, b| {
+            b.requests
+                .cmp(&a.requests)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        top.truncate(limit);
+
+        top
+    }
+
+    /// Returns the hourly statistics of buckets starting between `from` and `to`.
+    pub fn get_history(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Vec<HistoryBucket> {
+        self.history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|bucket| from.is_none_or(|from| bucket.start >= from))
+            .filter(|bucket| to.is_none_or(|to| bucket.start < to))
+            .cloned()
+            .collect()
+    }
+
+    /// Restores the history saved by a previous run, if any.
+    pub async fn restore_history(&self, path: &Path) {
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Unable to read statistics history: {}", err);
+                }
+                return;
+            }
+        };
+
+        match serde_json::from_slice::<VecDeque<HistoryBucket>>(&bytes) {
+            Ok(mut saved_history) => {
+                let mut history = self.history.lock().unwrap();
+
+                // Requests may have been counted before the history was restored.
+                saved_history.retain(|saved_bucket| {
+                    history
+                        .front()
+                        .is_none_or(|bucket| saved_bucket.start < bucket.start)
+                });
+                saved_history.extend(history.drain(..));
+
+                while saved_history.len() > HISTORY_BUCKETS {
+                    saved_history.pop_front();
+                }
+
+                *history = saved_history;
+            }
+            Err(err) => log::warn!("Unable to decode statistics history: {}", err),
+        }
+    }
+
+    async fn save_history(&self, path: &Path) {
+        let serialized = serde_json::to_vec(&*self.history.lock().unwrap()).unwrap();
+
+        if let Err(err) = tokio::fs::write(path, serialized).await {
+            log::warn!("Unable to save statistics history: {}", err);
+        }
+    }
+
+    pub fn increment_top_blocked_paths(&self, path_: String, filter_: Option<MatchedFilter>) {
+        let mut top_blocked_paths = self.top_blocked_paths.lock().unwrap();
+
+        match top_blocked_paths.find(|(path, _count, _filter)| path == &path_) {
+            Some((_path, count, filter)) => {
+                *count += 1;
+                *filter = filter_;
+            }
+            None => {
+                top_blocked_paths.insert((path_, 1, filter_));
+            }
+        }
+    }
+
+    /// Counts a request blocked by `filter` towards its filter list.
+    pub fn increment_blocking_filter(&self, filter: &MatchedFilter) {
+        let list = match &filter.list {
+            Some(list) => list,
+            None => return,
+        };
+
+        if list == CUSTOM_FILTERS_SOURCE {
+            *self
+                .blocked_by_custom_rules
+                .lock()
+                .unwrap()
+                .entry(filter.rule.clone())
+                .or_default() += 1;
+        }
+
+        *self
+            .blocked_by_lists
+            .lock()
+            .unwrap()
+            .entry(list.clone())
+            .or_default() += 1;
+    }
+
+    /// Returns the requests blocked by each of the given filters and custom filters.
+    pub fn get_filter_statistics(
+        &self,
+        filters: &[Filter],
+        custom_filters: &[String],
+    ) -> FilterStatistics {
+        let mut blocked_by_lists = self.blocked_by_lists.lock().unwrap().clone();
+
+        let mut lists = filters
+            .iter()
+            .filter(|filter| filter.enabled)
+            .map(|filter| FilterListStatistics {
+                id: Some(filter.id),
+                title: filter.title.clone(),
+                blocked_requests: blocked_by_lists.remove(&filter.title).unwrap_or(0),
+            })
+            .collect::<Vec<_>>();
+
+        lists.extend(
+            blocked_by_lists
+                .into_iter()
+                .map(|(title, blocked_requests)| FilterListStatistics {
+                    id: None,
+                    title,
+                    blocked_requests,
+                }),
+        );
+        lists.sort_by_key(|list| Reverse(list.blocked_requests));
+
+        let blocked_by_custom_rules = self.blocked_by_custom_rules.lock().unwrap();
+
+        let mut custom_rules = custom_filters
+            .iter()
+            .map(|rule| rule.trim())
+            .filter(|rule| !rule.is_empty() && !rule.starts_with('!'))
+            .map(|rule| {
+                let count = blocked_by_custom_rules.get(rule).copied().unwrap_or(0);
+                (rule.to_string(), count)
+            })
+            .collect::<Vec<_>>();
+        custom_rules.sort_by_key(|(_rule, count)| Reverse(*count));
+
+        FilterStatistics {
+            lists,
+            custom_rules,
+        }
+    }
+
+    /// Records the decision taken for a request, returning the id of the request.
+    pub fn record_decision(&self, method: String, url: String, decision: RequestDecision) -> u64 {
+        let id = {
+            let mut last_request_id = self.last_request_id.lock().unwrap();
+
+            *last_request_id += 1;
+            *last_request_id
+        };
+
+        let mut recent_decisions = self.recent_decisions.lock().unwrap();
+        if recent_decisions.len() == RECORDED_DECISIONS {
+            recent_decisions.pop_front();
+        }
+        recent_decisions.push_back(RecordedDecision {
+            id,
+            now: Utc::now(),
+            method,
+            url,
+            decision,
+        });
+
+        id
+    }
+
+    pub fn get_decision(&self, id: u64) -> Option<RecordedDecision> {
+        let recent_decisions = self.recent_decisions.lock().unwrap();
+
+        // Ids are sequential, so the position of a decision follows from the oldest one.
+        let oldest_id = recent_decisions.front()?.id;
+        let index = id.checked_sub(oldest_id)?;
+
+        recent_decisions.get(index as usize).cloned()
+    }
+
+    pub fn increment_top_clients(&self, client: String) {
+        *self.top_clients.lock().unwrap().entry(client).or_insert(0) += 1;
+    }
+
+    pub fn increment_blocked_clients(&self, client: String) {
+        *self
+            .blocked_clients
+            .lock()
+            .unwrap()
+            .entry(client)
+            .or_insert(0) += 1;
+    }
+
+    pub fn increment_top_users(&self, user: String) {
+        *self.top_users.lock().unwrap().entry(user).or_insert(0) += 1;
+    }
+
+    pub fn increment_client_requests(&self, client: &str) {
+        let mut clients = self.clients.lock().unwrap();
+        let activity = clients
+            .entry(client.to_string())
+            .or_insert_with(ClientActivity::new);
+
+        activity.requests += 1;
+        activity.last_seen = Utc::now();
+    }
+
+    pub fn increment_client_blocked_requests(&self, client: &str, domain: &str) {
+        let mut clients = self.clients.lock().unwrap();
+        let activity = clients
+            .entry(client.to_string())
+            .or_insert_with(ClientActivity::new);
+
+        activity.blocked_requests += 1;
+        increment_domain(&mut activity.blocked_domains, domain);
+    }
+
+    /// Returns the requests of each client, those with the most blocked requests first.
+    pub fn get_clients(&self) -> Vec<ClientStatistics> {
+        let mut clients = self
+            .clients
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(client, activity)| {
+                let mut top_blocked_domains = activity
+                    .blocked_domains
+                    .iter()
+                    .map(|(domain, requests)| TopEntry {
+                        name: domain.clone(),
+                        requests: *requests,
+                        entity: self.tracker_entities.get_entity(domain),
+                    })
+                    .collect::<Vec<_>>();
+
+                top_blocked_domains.sort_by(|a, b| {
+                    b.requests
+                        .cmp(&a.requests)
+                        .then_with(|| a.name.cmp(&b.name))
+                });
+                top_blocked_domains.truncate(CLIENT_TOP_BLOCKED_DOMAINS);
+
+                ClientStatistics {
+                    client: client.clone(),
+                    requests: activity.requests,
+                    blocked_requests: activity.blocked_requests,
+                    top_blocked_domains,
+                    last_seen: activity.last_seen,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        clients.sort_by(|a, b| {
+            b.blocked_requests
+                .cmp(&a.blocked_requests)
+                .then_with(|| a.client.cmp(&b.client))
+        });
+
+        clients
+    }
+
+    pub fn increment_top_origins(&self, origin_: String) {
+        let mut top_origins = self.top_origins.lock().unwrap();
+
+        match top_origins.find(|(origin, _count)| origin == &origin_) {
+            Some((_origin, count)) => {
+                *count += 1;
+            }
+            None => {
+                top_origins.insert((origin_, 1));
+            }
+        }
+    }
+
+    /// Returns the most requested origins, most requested first.
+    pub fn get_top_origins(&self, count: usize) -> Vec<String> {
+        let mut top_origins = self
+            .top_origins
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        top_origins.sort_by_key(|(_origin, count)| Reverse(*count));
+
+        top_origins
+            .into_iter()
+            .take(count)
+            .map(|(origin, _count)| origin)
+            .collect()
+    }
+
+    pub fn increment_proxied_requests(&self) -> u64 {
+        self.update_current_history_bucket(|bucket| bucket.proxied_requests += 1);
+        self.timeseries.increment_allowed_requests();
+
+        let mut proxied_requests = self.proxied_requests.lock().unwrap();
+
+        *proxied_requests += 1;
+        *proxied_requests
+    }
+
+    pub fn increment_blocked_requests(&self) -> u64 {
+        self.update_current_history_bucket(|bucket| bucket.blocked_requests += 1);
+        self.timeseries.increment_blocked_requests();
+
+        let mut blocked_requests = self.blocked_requests.lock().unwrap();
+
+        *blocked_requests += 1;
+        *blocked_requests
+    }
+
+    pub fn add_response_bytes(&self, bytes: u64) {
+        self.timeseries.add_response_bytes(bytes);
+    }
+
+    pub fn increment_modified_responses(&self) -> u64 {
+        let mut modified_responses = self.modified_responses.lock().unwrap();
+
+        *modified_responses += 1;
+        *modified_responses
+    }
+
+    pub fn increment_streamed_responses(&self) -> u64 {
+        let mut streamed_responses = self.streamed_responses.lock().unwrap();
+
+        *streamed_responses += 1;
+        *streamed_responses
+    }
+
+    pub fn increment_event_stream_responses(&self) -> u64 {
+        let mut event_stream_responses = self.event_stream_responses.lock().unwrap();
+
+        *event_stream_responses += 1;
+        *event_stream_responses
+    }
+
+    pub fn increment_grpc_responses(&self) -> u64 {
+        let mut grpc_responses = self.grpc_responses.lock().unwrap();
+
+        *grpc_responses += 1;
+        *grpc_responses
+    }
+
+    pub fn increment_trimmed_referers(&self) -> u64 {
+        let mut trimmed_referers = self.trimmed_referers.lock().unwrap();
+
+        *trimmed_referers += 1;
+        *trimmed_referers
+    }
+
+    pub fn increment_stripped_referers(&self) -> u64 {
+        let mut stripped_referers = self.stripped_referers.lock().unwrap();
+
+        *stripped_referers += 1;
+        *stripped_referers
+    }
+
+    pub fn increment_stripped_cookies(&self) -> u64 {
+        let mut stripped_cookies = self.stripped_cookies.lock().unwrap();
+
+        *stripped_cookies += 1;
+        *stripped_cookies
+    }
+
+    pub fn increment_cache_hits(&self) -> u64 {
+        let mut cache_hits = self.cache_hits.lock().unwrap();
+
+        *cache_hits += 1;
+        *cache_hits
+    }
+
+    pub fn increment_cache_revalidations(&self) -> u64 {
+        let mut cache_revalidations = self.cache_revalidations.lock().unwrap();
+
+        *cache_revalidations += 1;
+        *cache_revalidations
+    }
+
+    pub fn increment_cache_misses(&self) -> u64 {
+        let mut cache_misses = self.cache_misses.lock().unwrap();
+
+        *cache_misses += 1;
+        *cache_misses
+    }
+
+    pub fn increment_security_blocks(&self, severity: ThreatSeverity) -> u64 {
+        let mut security_blocks = self.security_blocks.lock().unwrap();
+        let count = security_blocks.entry(severity).or_insert(0);
+
+        *count += 1;
+        *count
+    }
+
+    pub fn increment_category_blocks(&self, category: &str) -> u64 {
+        let mut category_blocks = self.category_blocks.lock().unwrap();
+        let count = category_blocks.entry(category.to_string()).or_insert(0);
+
+        *count += 1;
+        *count
+    }
+
+    pub fn record_latency(&self, stage: LatencyStage, latency: Duration) {
+        self.latencies.lock().unwrap()[stage as usize].record(latency);
+    }
+
+    /// Counts a client connection as open until the returned guard is dropped.
+    pub fn open_connection(&self) -> OpenConnection {
+        *self.open_connections.lock().unwrap() += 1;
+
+        OpenConnection(self.open_connections.clone())
+    }
+
+    pub fn record_engine_build(&self, duration: Duration) {
+        self.engine_builds.lock().unwrap().record(duration);
+    }
+
+    pub fn record_engine_shard_match(&self, shard: usize, latency: Duration) {
+        let mut engine_shard_matches = self.engine_shard_matches.lock().unwrap();
+
+        if engine_shard_matches.len() <= shard {
+            engine_shard_matches.resize_with(shard + 1, LatencyHistogram::default);
+        }
+        engine_shard_matches[shard].record(latency);
+    }
+
+    pub fn get_performance(&self) -> PerformanceStatistics {
+        let latencies = self.latencies.lock().unwrap();
+        let get_summary = |stage: LatencyStage| latencies[stage as usize].get_summary();
+
+        PerformanceStatistics {
+            filter_match: get_summary(LatencyStage::FilterMatch),
+            tls_handshake: get_summary(LatencyStage::TlsHandshake),
+            request_processing: get_summary(LatencyStage::RequestProcessing),
+            rewrite: get_summary(LatencyStage::Rewrite),
+            engine_build: self.engine_builds.lock().unwrap().get_summary(),
+            engine_shards: self
+                .engine_shard_matches
+                .lock()
+                .unwrap()
+                .iter()
+                .map(LatencyHistogram::get_summary)
+                .collect(),
+        }
+    }
+
+    pub fn get_serialized(&self) -> SerializableStatistics {
+        SerializableStatistics {
+            proxied_requests: *self.proxied_requests.lock().unwrap(),
+            blocked_requests: *self.blocked_requests.lock().unwrap(),
+            modified_responses: *self.modified_responses.lock().unwrap(),
+            streamed_responses: *self.streamed_responses.lock().unwrap(),
+            event_stream_responses: *self.event_stream_responses.lock().unwrap(),
+            grpc_responses: *self.grpc_responses.lock().unwrap(),
+            trimmed_referers: *self.trimmed_referers.lock().unwrap(),
+            stripped_referers: *self.stripped_referers.lock().unwrap(),
+            stripped_cookies: *self.stripped_cookies.lock().unwrap(),
+            cache_hits: *self.cache_hits.lock().unwrap(),
+            cache_revalidations: *self.cache_revalidations.lock().unwrap(),
+            cache_misses: *self.cache_misses.lock().unwrap(),
+            security_blocks: self.security_blocks.lock().unwrap().clone(),
+            category_blocks: self.category_blocks.lock().unwrap().clone(),
+            top_blocked_paths: {
+                let top_blocked_paths = self.top_blocked_paths.lock().unwrap();
+                let mut top_blocked_paths_iterator = top_blocked_paths.iter();
+
+                let mut top_blocked_paths = (0..=ENTRIES_PER_STATISTICS_TABLE)
+                    .into_iter()
+                    .filter_map(|_| {
+                        let (path, count, _filter) = top_blocked_paths_iterator.next()?;
+
+                        Some((path.clone(), *count))
+                    })
+                    .collect::<Vec<_>>();
+
+                top_blocked_paths.sort_by(|a, b| b.1.cmp(&a.1));
+
+                top_blocked_paths
+            },
+            top_blocked_paths_filters: {
+                let top_blocked_paths = self.top_blocked_paths.lock().unwrap();
+
+                top_blocked_paths
+                    .iter()
+                    .take(ENTRIES_PER_STATISTICS_TABLE as usize + 1)
+                    .filter_map(|(path, _count, filter)| Some((path.clone(), filter.clone()?)))
+                    .collect()
+            },
+            top_clients: {
+                let top_clients = self.top_clients.lock().unwrap();
+                let mut top_clients_iter = top_clients.iter();
+
+                let mut top_clients = (0..=ENTRIES_PER_STATISTICS_TABLE)
+                    .into_iter()
+                    .filter_map(|_| {
+                        let (client, count) = top_clients_iter.next()?;
+
+                        Some((client.clone(), *count))
+                    })
+                    .collect::<Vec<_>>();
+
+                top_clients.sort_by(|a, b| b.1.cmp(&a.1));
+
+                top_clients
+            },
+            top_users: {
+                let top_users = self.top_users.lock().unwrap();
+
+                let mut top_users = top_users
+                    .iter()
+                    .map(|(user, count)| (user.clone(), *count))
+                    .collect::<Vec<_>>();
+
+                top_users.sort_by_key(|(_user, count)| Reverse(*count));
+                top_users.truncate(ENTRIES_PER_STATISTICS_TABLE as usize);
+
+                top_users
+            },
+        }
+    }
+}
+
+/// Counts a request to `domain`, unless the bucket already counts too many domains.
+fn increment_domain(domains: &mut HashMap<String, u64>, domain: &str) {
+    if let Some(count) = domains.get_mut(domain) {
+        *count += 1;
+    } else if domains.len() < HISTORY_DOMAINS_PER_BUCKET {
+        domains.insert(domain.to_string(), 1);
+    }
+}
+
+/// Resets statistics, along with the saved ones so that they aren't restored on the next
+/// start.
+pub(crate) async fn reset(statistics: &Statistics) {
+    statistics.clear();
+
+    statistics
+        .save_history(&configuration::get_statistics_history_file())
+        .await;
+    statistics
+        .timeseries
+        .save(&configuration::get_statistics_timeseries_file())
+        .await;
+}
+
+/// Periodically saves the statistics history, so that it survives restarts.
+pub(crate) async fn save_history_periodically(statistics: Statistics, path: PathBuf) {
+    loop {
+        tokio::time::sleep(HISTORY_SAVE_INTERVAL).await;
+
+        statistics.save_history(&path).await;
+    }
+}
@@ -0,0 +1,58 @@
+//! Resources Privaxy uses, for capacity planning on small devices.
+
+use crate::blocker::EngineStatus;
+use once_cell::sync::OnceCell;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::time::Instant;
+
+static STARTED_AT: OnceCell<Instant> = OnceCell::new();
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SystemStatus {
+    pub uptime_secs: u64,
+    /// Resident set size of the process, on platforms reporting it.
+    pub resident_memory_bytes: Option<u64>,
+    /// Client connections to the proxy, tunnels and intercepted TLS sessions included.
+    pub open_connections: u64,
+    /// Worker threads of the async runtime.
+    pub runtime_workers: usize,
+    /// Tasks of the async runtime that didn't complete yet, such as connections being
+    /// served and background jobs.
+    pub runtime_tasks: usize,
+    pub engine: EngineStatus,
+}
+
+/// Uptime is counted from the first call.
+pub(crate) fn set_started() {
+    let _ = STARTED_AT.set(Instant::now());
+}
+
+pub(crate) fn get_uptime_secs() -> u64 {
+    STARTED_AT
+        .get()
+        .map(|started_at| started_at.elapsed().as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn get_resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+
+    // Such as `VmRSS:     51200 kB`.
+    let resident_memory_kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(resident_memory_kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn get_resident_memory_bytes() -> Option<u64> {
+    None
+}
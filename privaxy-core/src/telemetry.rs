@@ -0,0 +1,414 @@
+use crate::configuration::Telemetry;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+
+/// Spans are posted to the collector in batches of at most this many spans.
+const MAX_BATCH_SIZE: usize = 512;
+/// Finished spans are posted at least this often.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Spans that finish while this many are waiting to be posted are dropped.
+const MAX_QUEUED_SPANS: usize = 4096;
+
+const SPAN_KIND_INTERNAL: u8 = 1;
+const SPAN_KIND_SERVER: u8 = 2;
+
+static EXPORTER: RwLock<Option<Exporter>> = RwLock::new(None);
+static INSTALL_TRACER: Once = Once::new();
+
+thread_local! {
+    /// Spans entered on this thread, the last one being the current span.
+    static ENTERED_SPANS: RefCell<Vec<Id>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Exporter {
+    sample_percent: u8,
+    sender: mpsc::Sender<FinishedSpan>,
+}
+
+#[derive(Debug, Clone)]
+enum AttributeValue {
+    String(String),
+    Int(i64),
+    Bool(bool),
+}
+
+struct SpanData {
+    name: &'static str,
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    is_sampled: bool,
+    start_time: SystemTime,
+    attributes: Vec<(&'static str, AttributeValue)>,
+    /// Handles to the span still alive, it ends when the last one is dropped.
+    references: usize,
+}
+
+struct FinishedSpan {
+    span: SpanData,
+    end_time: SystemTime,
+}
+
+struct AttributeVisitor<'a>(&'a mut Vec<(&'static str, AttributeValue)>);
+
+impl AttributeVisitor<'_> {
+    fn set(&mut self, field: &Field, value: AttributeValue) {
+        self.0.retain(|(name, _value)| *name != field.name());
+        self.0.push((field.name(), value));
+    }
+}
+
+impl Visit for AttributeVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field, AttributeValue::String(format!("{:?}", value)));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, AttributeValue::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.set(field, AttributeValue::Int(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.set(
+            field,
+            AttributeValue::Int(i64::try_from(value).unwrap_or(i64::MAX)),
+        );
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.set(field, AttributeValue::Bool(value));
+    }
+}
+
+/// Keeps track of the spans of privaxy while they are open and hands them to the
+/// exporter once they end. Spans of other crates are ignored.
+struct Tracer {
+    spans: Mutex<HashMap<u64, SpanData>>,
+    next_id: AtomicU64,
+}
+
+impl Tracer {
+    fn new() -> Self {
+        Self {
+            spans: Mutex::new(HashMap::new()),
+            // Span ids can't be 0.
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    fn is_traced(metadata: &Metadata<'_>) -> bool {
+        metadata.is_span() && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+}
+
+fn get_random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    openssl::rand::rand_bytes(&mut bytes).unwrap();
+
+    bytes
+}
+
+/// Whether the trace is exported, decided once for all of its spans.
+fn is_trace_sampled(trace_id: &[u8; 16]) -> bool {
+    let sample = u64::from_be_bytes(trace_id[8..].try_into().unwrap()) % 100;
+
+    EXPORTER
+        .read()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|exporter| sample < u64::from(exporter.sample_percent))
+}
+
+fn get_current_span() -> Option<Id> {
+    ENTERED_SPANS
+        .try_with(|spans| spans.borrow().last().cloned())
+        .ok()
+        .flatten()
+}
+
+impl Subscriber for Tracer {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if Self::is_traced(metadata) {
+            // Whether spans are recorded changes with the configuration.
+            Interest::sometimes()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        Self::is_traced(metadata) && EXPORTER.read().unwrap().is_some()
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let parent_id = if attributes.is_root() {
+            None
+        } else if let Some(parent_id) = attributes.parent() {
+            Some(parent_id.clone())
+        } else {
+            get_current_span()
+        };
+
+        let mut span_attributes = Vec::new();
+        attributes.record(&mut AttributeVisitor(&mut span_attributes));
+
+        let mut spans = self.spans.lock().unwrap();
+
+        let (trace_id, parent_span_id, is_sampled) =
+            match parent_id.and_then(|parent_id| spans.get(&parent_id.into_u64())) {
+                Some(parent) => (parent.trace_id, Some(parent.span_id), parent.is_sampled),
+                None => {
+                    let trace_id = get_random_bytes();
+                    (trace_id, None, is_trace_sampled(&trace_id))
+                }
+            };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        spans.insert(
+            id,
+            SpanData {
+                name: attributes.metadata().name(),
+                trace_id,
+                span_id: get_random_bytes(),
+                parent_span_id,
+                is_sampled,
+                start_time: SystemTime::now(),
+                attributes: span_attributes,
+                references: 1,
+            },
+        );
+
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            values.record(&mut AttributeVisitor(&mut span.attributes));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        let _result = ENTERED_SPANS.try_with(|spans| spans.borrow_mut().push(span.clone()));
+    }
+
+    fn exit(&self, span: &Id) {
+        let _result = ENTERED_SPANS.try_with(|spans| {
+            let mut spans = spans.borrow_mut();
+            if let Some(index) = spans.iter().rposition(|id| id == span) {
+                spans.remove(index);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(span) = self.spans.lock().unwrap().get_mut(&span.into_u64()) {
+            span.references += 1;
+        }
+
+        span.clone()
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        let span = {
+            let mut spans = self.spans.lock().unwrap();
+
+            match spans.get_mut(&id.into_u64()) {
+                Some(span) => span.references -= 1,
+                None => return false,
+            }
+
+            if spans[&id.into_u64()].references > 0 {
+                return false;
+            }
+
+            spans.remove(&id.into_u64()).unwrap()
+        };
+
+        if span.is_sampled {
+            if let Some(exporter) = EXPORTER.read().unwrap().as_ref() {
+                // Spans are dropped rather than slowing requests down when the collector
+                // can't keep up.
+                let _result = exporter.sender.try_send(FinishedSpan {
+                    span,
+                    end_time: SystemTime::now(),
+                });
+            }
+        }
+
+        true
+    }
+}
+
+fn get_unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn get_attribute_json(key: &str, value: &AttributeValue) -> Value {
+    let value = match value {
+        AttributeValue::String(value) => json!({ "stringValue": value }),
+        // 64 bits integers are strings in the JSON encoding of OTLP.
+        AttributeValue::Int(value) => json!({ "intValue": value.to_string() }),
+        AttributeValue::Bool(value) => json!({ "boolValue": value }),
+    };
+
+    json!({ "key": key, "value": value })
+}
+
+fn get_span_json(finished_span: &FinishedSpan) -> Value {
+    let span = &finished_span.span;
+
+    let mut span_json = json!({
+        "traceId": hex::encode(span.trace_id),
+        "spanId": hex::encode(span.span_id),
+        "name": span.name,
+        // Root spans are the connections and requests privaxy serves.
+        "kind": if span.parent_span_id.is_none() { SPAN_KIND_SERVER } else { SPAN_KIND_INTERNAL },
+        "startTimeUnixNano": get_unix_nanos(span.start_time),
+        "endTimeUnixNano": get_unix_nanos(finished_span.end_time),
+        "attributes": span
+            .attributes
+            .iter()
+            .map(|(key, value)| get_attribute_json(key, value))
+            .collect::<Vec<_>>(),
+    });
+
+    if let Some(parent_span_id) = span.parent_span_id {
+        span_json["parentSpanId"] = json!(hex::encode(parent_span_id));
+    }
+
+    span_json
+}
+
+/// Builds an OTLP/HTTP export request, in its JSON encoding.
+fn get_export_request(service_name: &str, spans: &[FinishedSpan]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    get_attribute_json("service.name", &AttributeValue::String(service_name.to_string())),
+                ],
+            },
+            "scopeSpans": [{
+                "scope": {
+                    "name": env!("CARGO_CRATE_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "spans": spans.iter().map(get_span_json).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+async fn post_spans(
+    client: &reqwest::Client,
+    traces_url: &str,
+    service_name: &str,
+    spans: Vec<FinishedSpan>,
+) {
+    let result = client
+        .post(traces_url)
+        .timeout(EXPORT_TIMEOUT)
+        .json(&get_export_request(service_name, &spans))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    if let Err(err) = result {
+        log::warn!("Unable to export {} spans: {}", spans.len(), err);
+    }
+}
+
+async fn export_spans(
+    client: reqwest::Client,
+    traces_url: String,
+    service_name: String,
+    mut receiver: mpsc::Receiver<FinishedSpan>,
+) {
+    let mut spans = Vec::new();
+    let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            span = receiver.recv() => match span {
+                Some(span) => {
+                    spans.push(span);
+
+                    if spans.len() < MAX_BATCH_SIZE {
+                        continue;
+                    }
+                }
+                // The exporter was replaced, what is left is still sent.
+                None => {
+                    if !spans.is_empty() {
+                        post_spans(&client, &traces_url, &service_name, spans).await;
+                    }
+
+                    return;
+                }
+            },
+            _ = interval.tick() => {
+                if spans.is_empty() {
+                    continue;
+                }
+            }
+        }
+
+        post_spans(
+            &client,
+            &traces_url,
+            &service_name,
+            std::mem::take(&mut spans),
+        )
+        .await;
+    }
+}
+
+/// Starts exporting the spans of the proxy according to `telemetry`, or stops when it
+/// has no endpoint. Called again when the configuration is reloaded.
+pub(crate) fn configure(telemetry: &Telemetry, client: reqwest::Client) {
+    let exporter = telemetry.get_traces_url().map(|traces_url| {
+        // The subscriber is only installed once tracing is wanted.
+        INSTALL_TRACER.call_once(|| {
+            if let Err(err) = tracing::subscriber::set_global_default(Tracer::new()) {
+                log::warn!("Unable to set up tracing: {}", err);
+            }
+        });
+
+        let (sender, receiver) = mpsc::channel(MAX_QUEUED_SPANS);
+        tokio::spawn(export_spans(
+            client,
+            traces_url,
+            telemetry.service_name.clone(),
+            receiver,
+        ));
+
+        Exporter {
+            sample_percent: telemetry.sample_percent,
+            sender,
+        }
+    });
+
+    // The previous exporter stops once the spans it was given are sent.
+    *EXPORTER.write().unwrap() = exporter;
+}
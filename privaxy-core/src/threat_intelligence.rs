@@ -0,0 +1,329 @@
+use crate::configuration::{ThreatFeed, ThreatFeedFormat, ThreatIntelligence, ThreatSeverity};
+use crate::web_gui::events::StatusEvent;
+use chrono::{DateTime, Utc};
+use http::Uri;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Notify};
+use url::Url;
+
+/// How often feeds are checked for being due for an update.
+const FEEDS_UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Feed listing the address of a blocked request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ThreatMatch {
+    pub feed: String,
+    pub severity: ThreatSeverity,
+}
+
+/// Download state of a feed.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ThreatFeedStatus {
+    pub title: String,
+    pub enabled: bool,
+    /// Listed URLs or hosts.
+    pub entries: usize,
+    /// When the feed was last downloaded successfully.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Why the last download failed, the entries of the previous one are kept.
+    pub error: Option<String>,
+}
+
+/// Entries of a feed, as last downloaded.
+#[derive(Debug, Default)]
+struct DownloadedFeed {
+    /// Without their scheme, see [`get_url_key`].
+    urls: HashSet<String>,
+    hosts: HashSet<String>,
+    updated_at: Option<DateTime<Utc>>,
+    checked_at: Option<Instant>,
+    error: Option<String>,
+}
+
+impl DownloadedFeed {
+    fn len(&self) -> usize {
+        self.urls.len() + self.hosts.len()
+    }
+}
+
+#[derive(Debug)]
+struct ThreatFeeds {
+    configuration: ThreatIntelligence,
+    /// Keyed by feed URL.
+    downloaded: HashMap<Url, DownloadedFeed>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ThreatStore {
+    feeds: Arc<RwLock<ThreatFeeds>>,
+    configuration_changed: Arc<Notify>,
+}
+
+impl ThreatStore {
+    pub fn new(threat_intelligence: ThreatIntelligence) -> Self {
+        Self {
+            feeds: Arc::new(RwLock::new(ThreatFeeds {
+                configuration: threat_intelligence,
+                downloaded: HashMap::new(),
+            })),
+            configuration_changed: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Feeds that were added or enabled are downloaded right away.
+    pub fn replace(&self, threat_intelligence: ThreatIntelligence) {
+        let mut feeds = self.feeds.write().unwrap();
+
+        let urls = threat_intelligence
+            .feeds
+            .iter()
+            .map(|feed| feed.url.clone())
+            .collect::<HashSet<_>>();
+        feeds.downloaded.retain(|url, _feed| urls.contains(url));
+        feeds.configuration = threat_intelligence;
+
+        self.configuration_changed.notify_one();
+    }
+
+    /// Returns the first enabled feed listing the URL or the host of `uri`.
+    pub(crate) fn get_match(&self, uri: &Uri) -> Option<ThreatMatch> {
+        let feeds = self.feeds.read().unwrap();
+
+        if !feeds.configuration.enabled {
+            return None;
+        }
+
+        let host = uri.host()?.to_lowercase();
+        let url_key = get_url_key(
+            uri.scheme_str().unwrap_or("http"),
+            &host,
+            uri.port_u16(),
+            uri.path_and_query()
+                .map_or("/", |path_and_query| path_and_query.as_str()),
+        );
+
+        feeds
+            .configuration
+            .feeds
+            .iter()
+            .filter(|feed| feed.enabled)
+            .find(|feed| match feeds.downloaded.get(&feed.url) {
+                Some(downloaded) => {
+                    downloaded.urls.contains(&url_key) || is_host_listed(&downloaded.hosts, &host)
+                }
+                None => false,
+            })
+            .map(|feed| ThreatMatch {
+                feed: feed.title.clone(),
+                severity: feed.severity,
+            })
+    }
+
+    pub(crate) fn get_statuses(&self) -> Vec<ThreatFeedStatus> {
+        let feeds = self.feeds.read().unwrap();
+
+        feeds
+            .configuration
+            .feeds
+            .iter()
+            .map(|feed| {
+                let downloaded = feeds.downloaded.get(&feed.url);
+
+                ThreatFeedStatus {
+                    title: feed.title.clone(),
+                    enabled: feed.enabled,
+                    entries: downloaded.map_or(0, DownloadedFeed::len),
+                    updated_at: downloaded.and_then(|downloaded| downloaded.updated_at),
+                    error: downloaded.and_then(|downloaded| downloaded.error.clone()),
+                }
+            })
+            .collect()
+    }
+
+    /// Downloads the enabled feeds that are due for an update, or all of them when
+    /// `force` is set.
+    pub(crate) async fn update(
+        &self,
+        http_client: &reqwest::Client,
+        status_sender: &broadcast::Sender<StatusEvent>,
+        force: bool,
+    ) {
+        let due_feeds = {
+            let feeds = self.feeds.read().unwrap();
+            let update_interval = feeds.configuration.update_interval();
+
+            if !feeds.configuration.enabled {
+                return;
+            }
+
+            feeds
+                .configuration
+                .feeds
+                .iter()
+                .filter(|feed| feed.enabled)
+                .filter(|feed| {
+                    force
+                        || feeds
+                            .downloaded
+                            .get(&feed.url)
+                            .and_then(|downloaded| downloaded.checked_at)
+                            .is_none_or(|checked_at| checked_at.elapsed() >= update_interval)
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        for feed in due_feeds {
+            let result = download_feed(http_client, &feed).await;
+
+            let mut feeds = self.feeds.write().unwrap();
+            let downloaded = feeds.downloaded.entry(feed.url.clone()).or_default();
+            downloaded.checked_at = Some(Instant::now());
+
+            match result {
+                Ok((urls, hosts)) => {
+                    downloaded.urls = urls;
+                    downloaded.hosts = hosts;
+                    downloaded.updated_at = Some(Utc::now());
+                    downloaded.error = None;
+
+                    log::info!(
+                        "Updated threat feed {} with {} entries",
+                        feed.title,
+                        downloaded.len()
+                    );
+
+                    let _result = status_sender.send(StatusEvent::ThreatFeedUpdated {
+                        now: Utc::now(),
+                        title: feed.title,
+                        entries: downloaded.len(),
+                    });
+                }
+                Err(err) => {
+                    log::error!("Unable to update threat feed {}: {}", feed.title, err);
+
+                    downloaded.error = Some(err.clone());
+
+                    let _result = status_sender.send(StatusEvent::ThreatFeedUpdateFailed {
+                        now: Utc::now(),
+                        title: feed.title,
+                        error: err,
+                    });
+                }
+            }
+        }
+    }
+}
+
+async fn download_feed(
+    http_client: &reqwest::Client,
+    feed: &ThreatFeed,
+) -> Result<(HashSet<String>, HashSet<String>), String> {
+    let content = http_client
+        .get(feed.url.clone())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?
+        .text()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    Ok(match feed.format {
+        ThreatFeedFormat::Urls => (parse_urls(&content), HashSet::new()),
+        ThreatFeedFormat::Hosts => (HashSet::new(), parse_hosts(&content)),
+    })
+}
+
+/// Identifies a URL whatever its scheme, as feeds list malware URLs with the scheme
+/// they were seen with.
+fn get_url_key(scheme: &str, host: &str, port: Option<u16>, path_and_query: &str) -> String {
+    let port = match (scheme, port) {
+        ("http", Some(80)) | ("https", Some(443)) | (_, None) => String::new(),
+        (_, Some(port)) => format!(":{}", port),
+    };
+
+    format!("{}{}{}", host, port, path_and_query)
+}
+
+fn parse_urls(content: &str) -> HashSet<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| Url::parse(line).ok())
+        .filter_map(|url| {
+            let path_and_query = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+
+            Some(get_url_key(
+                url.scheme(),
+                url.host_str()?,
+                url.port(),
+                &path_and_query,
+            ))
+        })
+        .collect()
+}
+
+/// Reads hosts, one per line or as hosts file entries such as `0.0.0.0 example.com`.
+pub(crate) fn parse_hosts(content: &str) -> HashSet<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(|line| {
+            let hosts = line.split_whitespace().collect::<Vec<_>>();
+
+            match hosts.as_slice() {
+                // Hosts file entries start with the address the hosts resolve to.
+                [address, hosts @ ..] if !hosts.is_empty() && address.parse::<IpAddr>().is_ok() => {
+                    hosts.to_vec()
+                }
+                hosts => hosts.to_vec(),
+            }
+        })
+        .map(|host| host.trim_end_matches('.').to_lowercase())
+        .filter(|host| !matches!(host.as_str(), "localhost" | "localhost.localdomain"))
+        .collect()
+}
+
+/// Hosts are listed along with their subdomains.
+pub(crate) fn is_host_listed(hosts: &HashSet<String>, host: &str) -> bool {
+    let mut domain = host;
+
+    loop {
+        if hosts.contains(domain) {
+            return true;
+        }
+
+        match domain.split_once('.') {
+            Some((_label, parent)) => domain = parent,
+            None => return false,
+        }
+    }
+}
+
+/// Downloads the feeds as they become due, and as soon as they are added or enabled.
+pub(crate) async fn update_feeds_periodically(
+    threat_store: ThreatStore,
+    http_client: reqwest::Client,
+    status_sender: broadcast::Sender<StatusEvent>,
+) {
+    loop {
+        threat_store
+            .update(&http_client, &status_sender, false)
+            .await;
+
+        tokio::select! {
+            _ = tokio::time::sleep(FEEDS_UPDATE_CHECK_INTERVAL) => {}
+            _ = threat_store.configuration_changed.notified() => {}
+        }
+    }
+}
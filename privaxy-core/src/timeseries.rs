@@ -0,0 +1,227 @@
+use crate::configuration::StatisticsRetention;
+use chrono::{DateTime, DurationRound, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Counters since the last save are lost when Privaxy stops.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl Resolution {
+    const ALL: [Resolution; 3] = [Resolution::Minute, Resolution::Hour, Resolution::Day];
+
+    fn get_duration(&self) -> chrono::Duration {
+        match self {
+            Resolution::Minute => chrono::Duration::minutes(1),
+            Resolution::Hour => chrono::Duration::hours(1),
+            Resolution::Day => chrono::Duration::days(1),
+        }
+    }
+
+    /// How long buckets of this resolution are kept.
+    fn get_retention(&self, retention: &StatisticsRetention) -> chrono::Duration {
+        let count = match self {
+            Resolution::Minute => retention.minutes,
+            Resolution::Hour => retention.hours,
+            Resolution::Day => retention.days,
+        };
+
+        self.get_duration() * count as i32
+    }
+}
+
+/// Requests served during a minute, an hour or a day.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TimeSeriesBucket {
+    pub start: DateTime<Utc>,
+    pub blocked_requests: u64,
+    pub allowed_requests: u64,
+    /// Bytes of the response bodies relayed to clients.
+    pub response_bytes: u64,
+}
+
+impl TimeSeriesBucket {
+    fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            blocked_requests: 0,
+            allowed_requests: 0,
+            response_bytes: 0,
+        }
+    }
+}
+
+/// Buckets of each resolution, oldest first. Minutes and hours without requests have
+/// no bucket.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Series {
+    minute: VecDeque<TimeSeriesBucket>,
+    hour: VecDeque<TimeSeriesBucket>,
+    day: VecDeque<TimeSeriesBucket>,
+}
+
+impl Series {
+    fn get(&self, resolution: Resolution) -> &VecDeque<TimeSeriesBucket> {
+        match resolution {
+            Resolution::Minute => &self.minute,
+            Resolution::Hour => &self.hour,
+            Resolution::Day => &self.day,
+        }
+    }
+
+    fn get_mut(&mut self, resolution: Resolution) -> &mut VecDeque<TimeSeriesBucket> {
+        match resolution {
+            Resolution::Minute => &mut self.minute,
+            Resolution::Hour => &mut self.hour,
+            Resolution::Day => &mut self.day,
+        }
+    }
+}
+
+/// Request counters over time. Every request is counted at each resolution, so that
+/// coarser resolutions can be kept for longer than finer ones.
+#[derive(Debug, Clone, Default)]
+pub struct TimeSeries {
+    series: Arc<Mutex<Series>>,
+    retention: Arc<Mutex<StatisticsRetention>>,
+}
+
+impl TimeSeries {
+    pub fn set_retention(&self, retention: StatisticsRetention) {
+        *self.retention.lock().unwrap() = retention;
+    }
+
+    /// Calls `f` with the current bucket of each resolution, creating them when needed
+    /// and dropping the buckets that are past their retention.
+    fn update(&self, f: impl Fn(&mut TimeSeriesBucket)) {
+        let now = Utc::now();
+        let retention = self.retention.lock().unwrap().clone();
+        let mut series = self.series.lock().unwrap();
+
+        for resolution in Resolution::ALL {
+            let start = now.duration_trunc(resolution.get_duration()).unwrap();
+            let buckets = series.get_mut(resolution);
+
+            if buckets.back().map(|bucket| bucket.start) != Some(start) {
+                let oldest_start = start - resolution.get_retention(&retention);
+
+                while buckets
+                    .front()
+                    .is_some_and(|bucket| bucket.start <= oldest_start)
+                {
+                    buckets.pop_front();
+                }
+
+                buckets.push_back(TimeSeriesBucket::new(start));
+            }
+
+            f(buckets.back_mut().unwrap());
+        }
+    }
+
+    pub fn increment_blocked_requests(&self) {
+        self.update(|bucket| bucket.blocked_requests += 1);
+    }
+
+    pub fn increment_allowed_requests(&self) {
+        self.update(|bucket| bucket.allowed_requests += 1);
+    }
+
+    pub fn add_response_bytes(&self, bytes: u64) {
+        self.update(|bucket| bucket.response_bytes += bytes);
+    }
+
+    pub fn clear(&self) {
+        *self.series.lock().unwrap() = Series::default();
+    }
+
+    /// Returns the buckets of `resolution` starting between `from` and `to`.
+    pub fn get(
+        &self,
+        resolution: Resolution,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Vec<TimeSeriesBucket> {
+        self.series
+            .lock()
+            .unwrap()
+            .get(resolution)
+            .iter()
+            .filter(|bucket| from.is_none_or(|from| bucket.start >= from))
+            .filter(|bucket| to.is_none_or(|to| bucket.start < to))
+            .cloned()
+            .collect()
+    }
+
+    /// Restores the time series saved by a previous run, if any.
+    pub async fn restore(&self, path: &Path) {
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Unable to read statistics time series: {}", err);
+                }
+                return;
+            }
+        };
+
+        match serde_json::from_slice::<Series>(&bytes) {
+            Ok(mut saved_series) => {
+                let mut series = self.series.lock().unwrap();
+
+                // Requests may have been counted before the time series were restored.
+                for resolution in Resolution::ALL {
+                    let buckets = series.get_mut(resolution);
+                    let saved_buckets = saved_series.get_mut(resolution);
+
+                    if let Some(bucket) = buckets.front_mut() {
+                        while saved_buckets
+                            .back()
+                            .is_some_and(|saved_bucket| saved_bucket.start >= bucket.start)
+                        {
+                            let saved_bucket = saved_buckets.pop_back().unwrap();
+
+                            if saved_bucket.start == bucket.start {
+                                bucket.blocked_requests += saved_bucket.blocked_requests;
+                                bucket.allowed_requests += saved_bucket.allowed_requests;
+                                bucket.response_bytes += saved_bucket.response_bytes;
+                            }
+                        }
+                    }
+                    saved_buckets.extend(buckets.drain(..));
+
+                    *buckets = std::mem::take(saved_buckets);
+                }
+            }
+            Err(err) => log::warn!("Unable to decode statistics time series: {}", err),
+        }
+    }
+
+    pub(crate) async fn save(&self, path: &Path) {
+        let serialized = serde_json::to_vec(&*self.series.lock().unwrap()).unwrap();
+
+        if let Err(err) = tokio::fs::write(path, serialized).await {
+            log::warn!("Unable to save statistics time series: {}", err);
+        }
+    }
+}
+
+/// Periodically saves the time series, so that they survive restarts.
+pub(crate) async fn save_periodically(timeseries: TimeSeries, path: PathBuf) {
+    loop {
+        tokio::time::sleep(SAVE_INTERVAL).await;
+
+        timeseries.save(&path).await;
+    }
+}
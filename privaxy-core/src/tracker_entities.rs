@@ -0,0 +1,139 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+/// Tracker data set DuckDuckGo builds from its Tracker Radar, which maps the domains of
+/// trackers to the companies that own them.
+const TRACKER_DATA_SET_URL: &str =
+    "https://staticcdn.duckduckgo.com/trackerblocking/v5/current/web_tds.json";
+/// The mapping is downloaded again once it's older than this.
+const MAX_ENTITIES_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Deserialize)]
+struct TrackerDataSetEntity {
+    #[serde(rename = "displayName")]
+    display_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrackerDataSet {
+    entities: HashMap<String, TrackerDataSetEntity>,
+    /// Keyed by domain, the values being keys of `entities`.
+    domains: HashMap<String, String>,
+}
+
+impl TrackerDataSet {
+    /// Returns the name of the company owning each domain.
+    fn into_entities(self) -> HashMap<String, String> {
+        let entities = self.entities;
+
+        self.domains
+            .into_iter()
+            .map(|(domain, entity)| {
+                let name = entities
+                    .get(&entity)
+                    .and_then(|entity| entity.display_name.clone())
+                    .unwrap_or(entity);
+
+                (domain, name)
+            })
+            .collect()
+    }
+}
+
+/// Companies owning the domains of trackers, such as `Google` for
+/// `google-analytics.com`.
+#[derive(Debug, Clone, Default)]
+pub struct TrackerEntities(Arc<RwLock<HashMap<String, String>>>);
+
+impl TrackerEntities {
+    /// Returns the company owning `domain` or one of its parent domains.
+    pub fn get_entity(&self, domain: &str) -> Option<String> {
+        let entities = self.0.read().unwrap();
+        let mut domain = domain.trim_end_matches('.');
+
+        loop {
+            if let Some(entity) = entities.get(domain) {
+                return Some(entity.clone());
+            }
+
+            domain = domain.split_once('.')?.1;
+        }
+    }
+
+    /// Restores the mapping saved by a previous run, if any.
+    pub async fn restore(&self, path: &Path) {
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Unable to read tracker entities: {}", err);
+                }
+                return;
+            }
+        };
+
+        match serde_json::from_slice::<HashMap<String, String>>(&bytes) {
+            Ok(entities) => *self.0.write().unwrap() = entities,
+            Err(err) => log::warn!("Unable to decode tracker entities: {}", err),
+        }
+    }
+
+    async fn update(&self, client: &reqwest::Client, path: &Path) -> Result<(), reqwest::Error> {
+        let tracker_data_set = client
+            .get(TRACKER_DATA_SET_URL)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<TrackerDataSet>()
+            .await?;
+
+        let entities = tracker_data_set.into_entities();
+        let serialized = serde_json::to_vec(&entities).unwrap();
+
+        log::info!(
+            "Updated the companies of {} tracker domains",
+            entities.len()
+        );
+
+        *self.0.write().unwrap() = entities;
+
+        if let Err(err) = tokio::fs::write(path, serialized).await {
+            log::warn!("Unable to save tracker entities: {}", err);
+        }
+
+        Ok(())
+    }
+}
+
+async fn is_outdated(path: &Path) -> bool {
+    let modified = match tokio::fs::metadata(path).await {
+        Ok(metadata) => metadata.modified(),
+        Err(_err) => return true,
+    };
+
+    modified
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_none_or(|age| age > MAX_ENTITIES_AGE)
+}
+
+/// Downloads the tracker entities whenever the saved ones get old.
+pub(crate) async fn update_periodically(
+    tracker_entities: TrackerEntities,
+    client: reqwest::Client,
+    path: PathBuf,
+) {
+    loop {
+        if is_outdated(&path).await {
+            if let Err(err) = tracker_entities.update(&client, &path).await {
+                log::warn!("Unable to update tracker entities: {}", err);
+            }
+        }
+
+        tokio::time::sleep(UPDATE_CHECK_INTERVAL).await;
+    }
+}
@@ -0,0 +1,254 @@
+use super::get_error_response;
+use super::sessions::{session_token, SessionStore};
+use crate::configuration::get_config_file;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use warp::filters::BoxedFilter;
+use warp::http::{Method, Response};
+use warp::path::FullPath;
+use warp::{Filter as RouteFilter, Rejection, Reply};
+
+const DEFAULT_AUDIT_ENTRIES_LIMIT: usize = 100;
+const MAX_AUDIT_ENTRIES_LIMIT: usize = 1000;
+
+/// Routes that don't change anything by themselves, they are only logged when the
+/// configuration file changed, like imports that aren't dry runs.
+const READ_ONLY_ROUTES: [&str; 3] = ["/api/filters/preview", "/api/import", "/api/test-request"];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// Who made the change. Devices are not told apart by user, this is the id of the
+    /// session that made the request when web GUI authentication is enabled.
+    pub principal: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// Top level sections of the configuration file that changed. Values are left out as
+    /// they may contain credentials.
+    pub changed_sections: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AuditQuery {
+    /// Only returns entries made after this time.
+    since: Option<DateTime<Utc>>,
+    /// Only returns entries of paths starting with this prefix.
+    path: Option<String>,
+    /// Only returns entries that changed this section of the configuration file.
+    section: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Append-only log of the changes made through the API, kept next to the configuration
+/// file.
+#[derive(Debug, Clone)]
+pub(super) struct AuditLog {
+    path: PathBuf,
+    write_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl AuditLog {
+    pub(super) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            write_lock: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    async fn append(&self, entry: &AuditEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry).unwrap();
+        line.push('\n');
+
+        let _guard = self.write_lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        file.write_all(line.as_bytes()).await
+    }
+
+    /// Returns matching entries, most recent first.
+    async fn query(&self, audit_query: &AuditQuery) -> std::io::Result<Vec<AuditEntry>> {
+        let content = match tokio::fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+
+        let limit = audit_query
+            .limit
+            .unwrap_or(DEFAULT_AUDIT_ENTRIES_LIMIT)
+            .min(MAX_AUDIT_ENTRIES_LIMIT);
+
+        Ok(content
+            .lines()
+            .rev()
+            // An interrupted write can leave a truncated last line.
+            .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+            .filter(|entry| {
+                audit_query
+                    .since
+                    .is_none_or(|since| entry.timestamp > since)
+                    && audit_query
+                        .path
+                        .as_ref()
+                        .is_none_or(|path| entry.path.starts_with(path.as_str()))
+                    && audit_query
+                        .section
+                        .as_ref()
+                        .is_none_or(|section| entry.changed_sections.contains(section))
+            })
+            .take(limit)
+            .collect())
+    }
+}
+
+/// What is known about a request before it reaches its route.
+pub(super) struct PendingAuditEntry {
+    method: Method,
+    path: String,
+    principal: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    /// The configuration file before the request, only read for requests that may change
+    /// it.
+    configuration: Option<toml::Table>,
+}
+
+fn is_mutating(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+async fn read_configuration_table() -> toml::Table {
+    match tokio::fs::read_to_string(get_config_file()).await {
+        Ok(content) => toml::from_str(&content).unwrap_or_default(),
+        Err(_) => toml::Table::new(),
+    }
+}
+
+fn get_changed_sections(before: &toml::Table, after: &toml::Table) -> Vec<String> {
+    before
+        .keys()
+        .chain(after.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|section| before.get(*section) != after.get(*section))
+        .cloned()
+        .collect()
+}
+
+/// Captures the state needed to log the request once its route replied.
+pub(super) fn begin_audit_entry(
+    session_store: SessionStore,
+) -> impl RouteFilter<Extract = (PendingAuditEntry,), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(session_token())
+        .and(warp::header::optional::<String>("user-agent"))
+        .and(warp::addr::remote())
+        .and(super::with_session_store(session_store))
+        .then(
+            |method: Method,
+             path: FullPath,
+             token: Option<String>,
+             user_agent: Option<String>,
+             remote_addr: Option<SocketAddr>,
+             session_store: SessionStore| async move {
+                let configuration = if is_mutating(&method) {
+                    Some(read_configuration_table().await)
+                } else {
+                    None
+                };
+
+                PendingAuditEntry {
+                    path: path.as_str().to_string(),
+                    method,
+                    principal: token.and_then(|token| session_store.get_session_id(&token)),
+                    ip_address: remote_addr.map(|addr| addr.ip().to_string()),
+                    user_agent,
+                    configuration,
+                }
+            },
+        )
+}
+
+/// Logs mutating requests, with the sections of the configuration file they changed.
+pub(super) async fn finish_audit_entry(
+    pending_audit_entry: PendingAuditEntry,
+    reply: impl Reply,
+    audit_log: AuditLog,
+) -> Result<warp::reply::Response, Infallible> {
+    let response = reply.into_response();
+
+    let configuration = match pending_audit_entry.configuration {
+        Some(configuration) => configuration,
+        None => return Ok(response),
+    };
+
+    let changed_sections = get_changed_sections(&configuration, &read_configuration_table().await);
+
+    if changed_sections.is_empty() && READ_ONLY_ROUTES.contains(&pending_audit_entry.path.as_str())
+    {
+        return Ok(response);
+    }
+
+    let entry = AuditEntry {
+        timestamp: Utc::now(),
+        method: pending_audit_entry.method.to_string(),
+        path: pending_audit_entry.path,
+        status: response.status().as_u16(),
+        principal: pending_audit_entry.principal,
+        ip_address: pending_audit_entry.ip_address,
+        user_agent: pending_audit_entry.user_agent,
+        changed_sections,
+    };
+
+    if let Err(err) = audit_log.append(&entry).await {
+        log::error!("Failed to write audit log entry: {err}");
+    }
+
+    Ok(response)
+}
+
+async fn get_audit_entries(
+    audit_query: AuditQuery,
+    audit_log: AuditLog,
+) -> Result<impl warp::Reply, Infallible> {
+    match audit_log.query(&audit_query).await {
+        Ok(entries) => Ok(Response::builder()
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(&entries).unwrap())
+            .unwrap()),
+        Err(err) => {
+            log::error!("Failed to read audit log: {err}");
+            Ok(get_error_response(err))
+        }
+    }
+}
+
+pub(super) fn with_audit_log(
+    audit_log: AuditLog,
+) -> impl RouteFilter<Extract = (AuditLog,), Error = Infallible> + Clone {
+    warp::any().map(move || audit_log.clone())
+}
+
+pub(super) fn create_routes(audit_log: AuditLog) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::path::end()
+        .and(warp::get())
+        .and(warp::query::<AuditQuery>())
+        .and(with_audit_log(audit_log))
+        .and_then(self::get_audit_entries)
+        .boxed()
+}
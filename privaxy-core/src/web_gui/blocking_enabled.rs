@@ -0,0 +1,113 @@
+use super::ApiError;
+use crate::blocker::BlockingDisabledStore;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::time::Duration;
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+#[derive(Deserialize)]
+pub struct BlockingEnabled(bool);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlockingPauseRequest {
+    /// Seconds until blocking is enabled again.
+    pub duration: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BlockingPauseResponse {
+    /// When blocking is enabled again, none when blocking isn't paused.
+    pub resume_at: Option<DateTime<Utc>>,
+}
+
+fn get_bad_request_response(error: &str) -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder().status(StatusCode::BAD_REQUEST).body(
+            serde_json::to_string(&ApiError {
+                error: error.to_string(),
+            })
+            .unwrap(),
+        ),
+    )
+}
+
+pub async fn get_blocking_enabled(
+    blocking_disabled_store: BlockingDisabledStore,
+) -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&blocking_disabled_store.is_enabled()))
+}
+
+pub async fn put_blocking_enabled(
+    blocking_enabled: BlockingEnabled,
+    blocking_disabled_store: BlockingDisabledStore,
+) -> Result<impl warp::Reply, Infallible> {
+    blocking_disabled_store.set(blocking_enabled.0);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_blocking_pause(
+    blocking_disabled_store: BlockingDisabledStore,
+) -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&BlockingPauseResponse {
+        resume_at: blocking_disabled_store.get_resume_at(),
+    }))
+}
+
+async fn post_blocking_pause(
+    blocking_pause: BlockingPauseRequest,
+    blocking_disabled_store: BlockingDisabledStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if blocking_pause.duration == 0 {
+        return Ok(get_bad_request_response("The duration must be positive"));
+    }
+
+    let resume_at = match chrono::Duration::from_std(Duration::from_secs(blocking_pause.duration))
+        .ok()
+        .and_then(|duration| Utc::now().checked_add_signed(duration))
+    {
+        Some(resume_at) => resume_at,
+        None => return Ok(get_bad_request_response("The duration is too long")),
+    };
+
+    blocking_disabled_store.pause_until(resume_at);
+
+    log::info!("Blocking paused until {}", resume_at);
+
+    Ok(Box::new(warp::reply::json(&BlockingPauseResponse {
+        resume_at: Some(resume_at),
+    })))
+}
+
+pub(super) fn create_routes(
+    blocking_disabled_store: BlockingDisabledStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let block_store = super::with_blocking_disabled_store(blocking_disabled_store);
+
+    let pause_routes = warp::path("pause").and(warp::path::end()).and(
+        warp::get()
+            .and(block_store.clone())
+            .and_then(self::get_blocking_pause)
+            .or(warp::post()
+                .and(warp::body::json())
+                .and(block_store.clone())
+                .and_then(self::post_blocking_pause)),
+    );
+
+    warp::path::end()
+        .and(
+            warp::get()
+                .and(block_store.clone())
+                .and_then(self::get_blocking_enabled)
+                .or(warp::put()
+                    .and(warp::body::json())
+                    .and(block_store)
+                    .and_then(self::put_blocking_enabled)),
+        )
+        .or(pause_routes)
+        .boxed()
+}
@@ -0,0 +1,315 @@
+use super::{get_error_response, ApiError};
+use crate::cert::CertCache;
+use crate::configuration::{Ca, Configuration};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkcs12::Pkcs12;
+use serde::{Deserialize, Serialize};
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+/// Replaces the CA with a new one and returns its certificate, which clients have to
+/// trust instead of the previous one.
+async fn rotate_ca(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    cert_cache: CertCache,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to rotate CA: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    let (ca_certificate, ca_private_key) =
+        match tokio::task::spawn_blocking(crate::ca::make_ca_certificate).await {
+            Ok(ca) => ca,
+            Err(err) => {
+                log::error!("Failed to rotate CA: {err}");
+                return Ok(Box::new(get_error_response(err)));
+            }
+        };
+
+    if let Err(err) = configuration.set_ca(&ca_certificate, &ca_private_key).await {
+        log::error!("Failed to rotate CA: {err}");
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    let ca_certificate_pem = ca_certificate.to_pem().unwrap();
+
+    cert_cache.replace_ca(ca_certificate, ca_private_key).await;
+
+    log::info!("Rotated CA");
+
+    Ok(Box::new(
+        Response::builder()
+            .header(
+                http::header::CONTENT_DISPOSITION,
+                "attachment; filename=privaxy-ca-certificate.pem;",
+            )
+            .header(http::header::CONTENT_TYPE, "application/x-pem-file")
+            .body(ca_certificate_pem),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CaImportRequest {
+    /// PEM encoded certificate.
+    pub certificate: String,
+    /// PEM encoded private key, which must not be encrypted.
+    pub private_key: String,
+}
+
+/// Replaces the CA with one trusted by clients already, such as an internal CA.
+async fn import_ca(
+    ca_import_request: CaImportRequest,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    cert_cache: CertCache,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let ca = Ca::from_pem(ca_import_request.certificate, ca_import_request.private_key);
+
+    if let Err(err) = ca.validate().await {
+        return Ok(Box::new(
+            Response::builder().status(StatusCode::BAD_REQUEST).body(
+                serde_json::to_string(&ApiError {
+                    error: err.to_string(),
+                })
+                .unwrap(),
+            ),
+        ));
+    }
+
+    let (ca_certificate, ca_private_key) =
+        match tokio::try_join!(ca.get_ca_certificate(), ca.get_ca_private_key()) {
+            Ok(ca) => ca,
+            Err(err) => return Ok(Box::new(get_error_response(err))),
+        };
+
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to import CA: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration.set_ca(&ca_certificate, &ca_private_key).await {
+        log::error!("Failed to import CA: {err}");
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    cert_cache.replace_ca(ca_certificate, ca_private_key).await;
+
+    log::info!("Imported CA");
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+/// Builds a download of the CA certificate in use, in one of the formats offered to clients.
+fn ca_certificate_attachment(
+    file_name: &str,
+    content_type: &str,
+    body: Vec<u8>,
+) -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder()
+            .header(
+                http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename={file_name};"),
+            )
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(body),
+    )
+}
+
+async fn get_ca_certificate_pem(cert_cache: CertCache) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let ca_certificate = cert_cache.get_ca_certificate().await;
+
+    match ca_certificate.to_pem() {
+        Ok(pem) => Ok(ca_certificate_attachment(
+            "privaxy-ca-certificate.pem",
+            "application/x-pem-file",
+            pem,
+        )),
+        Err(err) => Ok(Box::new(get_error_response(err))),
+    }
+}
+
+async fn get_ca_certificate_der(cert_cache: CertCache) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let ca_certificate = cert_cache.get_ca_certificate().await;
+
+    match ca_certificate.to_der() {
+        Ok(der) => Ok(ca_certificate_attachment(
+            "privaxy-ca-certificate.crt",
+            "application/x-x509-ca-cert",
+            der,
+        )),
+        Err(err) => Ok(Box::new(get_error_response(err))),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Pkcs12Request {
+    pub password: String,
+}
+
+/// The PKCS#12 file holds the certificate only: the private key of the CA never leaves
+/// Privaxy.
+async fn get_ca_certificate_pkcs12(
+    pkcs12_request: Pkcs12Request,
+    cert_cache: CertCache,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if pkcs12_request.password.is_empty() {
+        return Ok(Box::new(
+            Response::builder().status(StatusCode::BAD_REQUEST).body(
+                serde_json::to_string(&ApiError {
+                    error: "A password is required".to_string(),
+                })
+                .unwrap(),
+            ),
+        ));
+    }
+
+    let ca_certificate = cert_cache.get_ca_certificate().await;
+
+    // Windows and Apple platforms that predate OpenSSL 3 can't read its default
+    // AES based encryption.
+    let pkcs12 = Pkcs12::builder()
+        .name("Privaxy CA")
+        .cert(&ca_certificate)
+        .cert_algorithm(Nid::PBE_WITHSHA1AND3_KEY_TRIPLEDES_CBC)
+        .mac_md(MessageDigest::sha1())
+        .build2(&pkcs12_request.password)
+        .and_then(|pkcs12| pkcs12.to_der());
+
+    match pkcs12 {
+        Ok(der) => Ok(ca_certificate_attachment(
+            "privaxy-ca-certificate.p12",
+            "application/x-pkcs12",
+            der,
+        )),
+        Err(err) => {
+            log::error!("Failed to export CA to PKCS#12: {err}");
+            Ok(Box::new(get_error_response(err)))
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstallInstructions {
+    platform: &'static str,
+    /// Path of the download, relative to `/api/ca`.
+    download: &'static str,
+    /// Whether the download requires a password, sent as the `password` form field.
+    requires_password: bool,
+    steps: &'static [&'static str],
+}
+
+const INSTALL_INSTRUCTIONS: &[InstallInstructions] = &[
+    InstallInstructions {
+        platform: "Windows",
+        download: "certificate.p12",
+        requires_password: true,
+        steps: &[
+            "Open the downloaded file and choose the \"Local Machine\" store location.",
+            "Enter the password the file was downloaded with.",
+            "Place the certificate in the \"Trusted Root Certification Authorities\" store.",
+        ],
+    },
+    InstallInstructions {
+        platform: "macOS",
+        download: "certificate.pem",
+        requires_password: false,
+        steps: &[
+            "Open the downloaded file to add it to the System keychain in Keychain Access.",
+            "Open the \"Privaxy\" certificate and set \"When using this certificate\" to \"Always Trust\".",
+        ],
+    },
+    InstallInstructions {
+        platform: "iOS",
+        download: "certificate.pem",
+        requires_password: false,
+        steps: &[
+            "Download the file with Safari and allow the configuration profile to be downloaded.",
+            "Install the profile from Settings > General > VPN & Device Management.",
+            "Enable full trust for \"Privaxy\" in Settings > General > About > Certificate Trust Settings.",
+        ],
+    },
+    InstallInstructions {
+        platform: "Android",
+        download: "certificate.der",
+        requires_password: false,
+        steps: &[
+            "Open Settings > Security > Encryption & credentials > Install a certificate > CA certificate.",
+            "Select the downloaded file.",
+            "Most apps other than Chrome only trust certificates installed by the user if they opt in to it.",
+        ],
+    },
+    InstallInstructions {
+        platform: "Linux",
+        download: "certificate.pem",
+        requires_password: false,
+        steps: &[
+            "Copy the downloaded file to /usr/local/share/ca-certificates/privaxy.crt (Debian, Ubuntu) or /etc/pki/ca-trust/source/anchors/ (Fedora).",
+            "Run update-ca-certificates (Debian, Ubuntu) or update-ca-trust (Fedora).",
+        ],
+    },
+    InstallInstructions {
+        platform: "Firefox",
+        download: "certificate.pem",
+        requires_password: false,
+        steps: &[
+            "Open Settings > Privacy & Security > Certificates > View Certificates.",
+            "Import the downloaded file in the \"Authorities\" tab and trust it to identify websites.",
+        ],
+    },
+];
+
+async fn get_install_instructions() -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&INSTALL_INSTRUCTIONS)))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    cert_cache: CertCache,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_cert_cache = warp::any().map(move || cert_cache.clone());
+
+    warp::post()
+        .and(warp::path!("rotate"))
+        .and(super::with_configuration_save_lock(
+            configuration_save_lock.clone(),
+        ))
+        .and(with_cert_cache.clone())
+        .and_then(self::rotate_ca)
+        .or(warp::post()
+            .and(warp::path!("import"))
+            .and(warp::body::json())
+            .and(super::with_configuration_save_lock(configuration_save_lock))
+            .and(with_cert_cache.clone())
+            .and_then(self::import_ca))
+        .or(warp::get()
+            .and(warp::path!("certificate.pem"))
+            .and(with_cert_cache.clone())
+            .and_then(self::get_ca_certificate_pem))
+        .or(warp::get()
+            .and(warp::path!("certificate.der"))
+            .and(with_cert_cache.clone())
+            .and_then(self::get_ca_certificate_der))
+        .or(warp::post()
+            .and(warp::path!("certificate.p12"))
+            .and(warp::body::form())
+            .and(with_cert_cache)
+            .and_then(self::get_ca_certificate_pkcs12))
+        .or(warp::get()
+            .and(warp::path!("install-instructions"))
+            .and_then(self::get_install_instructions))
+        .boxed()
+}
@@ -0,0 +1,89 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::categories::CategoryStore;
+use crate::configuration::{Configuration, ContentCategories};
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_categories() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get categories: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.categories)))
+}
+
+async fn put_categories(
+    categories: ContentCategories,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    category_store: CategoryStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put categories: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_categories(categories, category_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_lists(category_store: CategoryStore) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&category_store.get_statuses())))
+}
+
+/// Downloads the list of every enabled category right away, without waiting for them
+/// to be due.
+async fn update_lists(
+    category_store: CategoryStore,
+    http_client: reqwest::Client,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    category_store.update(&http_client, true).await;
+
+    Ok(Box::new(warp::reply::json(&category_store.get_statuses())))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    category_store: CategoryStore,
+    http_client: reqwest::Client,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_category_store = warp::any().map(move || category_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_categories)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_category_store.clone())
+            .and_then(self::put_categories))
+        .or(warp::get()
+            .and(warp::path("lists"))
+            .and(warp::path::end())
+            .and(with_category_store.clone())
+            .and_then(self::get_lists))
+        .or(warp::post()
+            .and(warp::path("update"))
+            .and(warp::path::end())
+            .and(with_category_store)
+            .and(warp::any().map(move || http_client.clone()))
+            .and_then(self::update_lists))
+        .boxed()
+}
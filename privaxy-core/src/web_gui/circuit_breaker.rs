@@ -0,0 +1,44 @@
+use crate::proxy::circuit_breaker::CircuitBreakerStore;
+use std::convert::Infallible;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_circuit_breaker_status(
+    circuit_breaker_store: CircuitBreakerStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(
+        &circuit_breaker_store.get_status(),
+    )))
+}
+
+async fn delete_circuit(
+    domain: String,
+    circuit_breaker_store: CircuitBreakerStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if circuit_breaker_store.reset(&domain) {
+        Ok(Box::new(StatusCode::NO_CONTENT))
+    } else {
+        Ok(Box::new(StatusCode::NOT_FOUND))
+    }
+}
+
+pub(super) fn create_routes(
+    circuit_breaker_store: CircuitBreakerStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_circuit_breaker_store = {
+        let circuit_breaker_store = circuit_breaker_store.clone();
+        warp::any().map(move || circuit_breaker_store.clone())
+    };
+
+    warp::get()
+        .and(warp::path::end())
+        .and(with_circuit_breaker_store.clone())
+        .and_then(self::get_circuit_breaker_status)
+        .or(warp::delete()
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(with_circuit_breaker_store)
+            .and_then(self::delete_circuit))
+        .boxed()
+}
@@ -0,0 +1,129 @@
+use super::ApiError;
+use crate::proxy::client_grants::{ClientGrantLevel, ClientGrantStore};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+#[derive(Debug, Deserialize)]
+pub struct ClientGrantRequest {
+    /// IP address, network or name of a client.
+    pub client: String,
+    pub level: ClientGrantLevel,
+    /// Seconds until the grant expires.
+    pub duration: u64,
+    pub note: Option<String>,
+}
+
+fn get_bad_request_response(error: &str) -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder().status(StatusCode::BAD_REQUEST).body(
+            serde_json::to_string(&ApiError {
+                error: error.to_string(),
+            })
+            .unwrap(),
+        ),
+    )
+}
+
+async fn get_client_grants(
+    client_grant_store: ClientGrantStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(
+        &client_grant_store.get_grants(),
+    )))
+}
+
+async fn get_audit_trail(
+    client_grant_store: ClientGrantStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(
+        &client_grant_store.get_audit_trail(),
+    )))
+}
+
+async fn post_client_grant(
+    client_grant: ClientGrantRequest,
+    remote_address: Option<SocketAddr>,
+    client_grant_store: ClientGrantStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let client = client_grant.client.trim();
+
+    if client.is_empty() {
+        return Ok(get_bad_request_response("A client is required"));
+    }
+
+    if client_grant.duration == 0 {
+        return Ok(get_bad_request_response("The duration must be positive"));
+    }
+
+    let expires_at = match chrono::Duration::from_std(Duration::from_secs(client_grant.duration))
+        .ok()
+        .and_then(|duration| chrono::Utc::now().checked_add_signed(duration))
+    {
+        Some(expires_at) => expires_at,
+        None => return Ok(get_bad_request_response("The duration is too long")),
+    };
+
+    let grant = client_grant_store.add(
+        client.to_string(),
+        client_grant.level,
+        client_grant.note.filter(|note| !note.trim().is_empty()),
+        expires_at,
+        remote_address.map(|address| address.ip().to_string()),
+    );
+
+    Ok(Box::new(warp::reply::with_status(
+        warp::reply::json(&grant),
+        StatusCode::CREATED,
+    )))
+}
+
+async fn delete_client_grant(
+    id: u64,
+    remote_address: Option<SocketAddr>,
+    client_grant_store: ClientGrantStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let by = remote_address.map(|address| address.ip().to_string());
+
+    if client_grant_store.revoke(id, by) {
+        Ok(Box::new(StatusCode::NO_CONTENT))
+    } else {
+        Ok(Box::new(StatusCode::NOT_FOUND))
+    }
+}
+
+pub(super) fn create_routes(
+    client_grant_store: ClientGrantStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_client_grant_store = {
+        let client_grant_store = client_grant_store.clone();
+        warp::any().map(move || client_grant_store.clone())
+    };
+
+    warp::get()
+        .and(warp::path::end())
+        .and(with_client_grant_store.clone())
+        .and_then(self::get_client_grants)
+        .or(warp::get()
+            .and(warp::path("audit"))
+            .and(warp::path::end())
+            .and(with_client_grant_store.clone())
+            .and_then(self::get_audit_trail))
+        .or(warp::post()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(warp::addr::remote())
+            .and(with_client_grant_store.clone())
+            .and_then(self::post_client_grant))
+        .or(warp::delete()
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(warp::addr::remote())
+            .and(with_client_grant_store)
+            .and_then(self::delete_client_grant))
+        .boxed()
+}
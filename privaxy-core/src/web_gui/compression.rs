@@ -0,0 +1,148 @@
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder};
+use async_compression::Level;
+use futures_util::TryStreamExt;
+use hyper::body::HttpBody;
+use hyper::Body;
+use tokio_util::io::{ReaderStream, StreamReader};
+use warp::filters::BoxedFilter;
+use warp::http::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, VARY};
+use warp::http::{HeaderValue, StatusCode};
+use warp::Filter as RouteFilter;
+use warp::Reply;
+
+/// Responses smaller than this don't get smaller enough to be worth compressing.
+const MIN_COMPRESSED_LENGTH: u64 = 1024;
+
+/// Brotli is much slower at its default quality than gzip, this one compresses JSON
+/// better than gzip at about the same speed.
+const BROTLI_QUALITY: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn get_name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// Picks the encoding to compress a response with from the `Accept-Encoding` header of
+/// its request, brotli when both are accepted.
+fn negotiate_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let accepted = accept_encoding
+        .split(',')
+        .filter_map(|coding| {
+            let mut parameters = coding.split(';').map(str::trim);
+            let name = parameters.next()?.to_lowercase();
+            let quality = parameters
+                .find_map(|parameter| parameter.strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.parse::<f32>().ok())?;
+
+            Some((name, quality))
+        })
+        .filter(|(_, quality)| *quality > 0.0)
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+
+    [Encoding::Brotli, Encoding::Gzip]
+        .into_iter()
+        .find(|encoding| accepted.iter().any(|name| name == encoding.get_name()))
+}
+
+/// Upgrades to websockets, event streams and responses that are already compressed are
+/// sent as they are.
+fn is_compressible(response: &warp::reply::Response) -> bool {
+    if response.status() == StatusCode::SWITCHING_PROTOCOLS
+        || response.status() == StatusCode::NO_CONTENT
+        || response.status() == StatusCode::NOT_MODIFIED
+        || response.headers().contains_key(CONTENT_ENCODING)
+    {
+        return false;
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .unwrap_or_default();
+
+    if content_type.starts_with("text/event-stream")
+        || content_type.starts_with("application/gzip")
+        || content_type.starts_with("application/zip")
+    {
+        return false;
+    }
+
+    response
+        .body()
+        .size_hint()
+        .exact()
+        .is_none_or(|length| length >= MIN_COMPRESSED_LENGTH)
+}
+
+fn compress(
+    response: warp::reply::Response,
+    accept_encoding: Option<&str>,
+) -> warp::reply::Response {
+    if !is_compressible(&response) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+
+    // Caches must not serve a compressed response to clients that didn't accept it.
+    parts
+        .headers
+        .append(VARY, HeaderValue::from_static("accept-encoding"));
+
+    let encoding = match accept_encoding.and_then(negotiate_encoding) {
+        Some(encoding) => encoding,
+        None => return warp::reply::Response::from_parts(parts, body),
+    };
+
+    let reader = StreamReader::new(TryStreamExt::map_err(body, std::io::Error::other));
+    let body = match encoding {
+        Encoding::Brotli => Body::wrap_stream(ReaderStream::new(BrotliEncoder::with_quality(
+            reader,
+            Level::Precise(BROTLI_QUALITY),
+        ))),
+        Encoding::Gzip => Body::wrap_stream(ReaderStream::new(GzipEncoder::new(reader))),
+    };
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.insert(
+        CONTENT_ENCODING,
+        HeaderValue::from_static(encoding.get_name()),
+    );
+
+    warp::reply::Response::from_parts(parts, body)
+}
+
+/// Compresses the responses of the given routes with gzip or brotli, for clients that
+/// accept either, unless compression is turned off.
+pub(super) fn with_compression<R>(
+    routes: BoxedFilter<(R,)>,
+    compress_responses: bool,
+) -> BoxedFilter<(warp::reply::Response,)>
+where
+    R: Reply + 'static,
+{
+    warp::header::optional::<String>("accept-encoding")
+        .and(routes)
+        .map(move |accept_encoding: Option<String>, reply: R| {
+            let response = reply.into_response();
+
+            if compress_responses {
+                compress(response, accept_encoding.as_deref())
+            } else {
+                response
+            }
+        })
+        .boxed()
+}
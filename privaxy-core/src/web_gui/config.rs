@@ -0,0 +1,106 @@
+use super::{get_error_response, ApiError};
+use crate::configuration::{Configuration, ConfigurationError, ConfigurationExport};
+use crate::proxy::exclusions::LocalExclusionStore;
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Notify;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+async fn export_configuration(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let guard = configuration_save_lock.lock().await;
+    let export = Configuration::export().await;
+    drop(guard);
+
+    match export {
+        Ok(export) => Ok(Response::builder()
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(
+                http::header::CONTENT_DISPOSITION,
+                "attachment; filename=\"privaxy-configuration.json\"",
+            )
+            .body(serde_json::to_string_pretty(&export).unwrap())
+            .unwrap()),
+        Err(err) => {
+            log::error!("Failed to export configuration: {err}");
+            Ok(get_error_response(err))
+        }
+    }
+}
+
+async fn import_configuration(
+    export: ConfigurationExport,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    mut local_exclusions_store: LocalExclusionStore,
+    notify_reload: Arc<Notify>,
+) -> Result<impl warp::Reply, Infallible> {
+    let guard = configuration_save_lock.lock().await;
+
+    let configuration = match Configuration::import(export).await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to import configuration: {err}");
+
+            let status = match err {
+                ConfigurationError::FileSystemError(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
+                _ => http::StatusCode::BAD_REQUEST,
+            };
+
+            return Ok(Response::builder()
+                .status(status)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(
+                    serde_json::to_string(&ApiError {
+                        error: err.to_string(),
+                    })
+                    .unwrap(),
+                )
+                .unwrap());
+        }
+    };
+
+    local_exclusions_store.replace_exclusions(configuration.get_exclusions());
+
+    if let Err(err) = configuration_updater_sender.send(configuration).await {
+        log::error!("Failed to send updated configuration: {err}");
+        return Ok(get_error_response(err));
+    }
+    drop(guard);
+
+    // Most settings are only read when the servers start.
+    notify_reload.notify_waiters();
+
+    Ok(Response::builder()
+        .status(http::StatusCode::NO_CONTENT)
+        .body("".to_string())
+        .unwrap())
+}
+
+pub(super) fn create_routes(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    local_exclusions_store: LocalExclusionStore,
+    notify_reload: Arc<Notify>,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::path!("export")
+        .and(warp::get())
+        .and(super::with_configuration_save_lock(
+            configuration_save_lock.clone(),
+        ))
+        .and_then(self::export_configuration)
+        .or(warp::path!("import")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender,
+            ))
+            .and(super::with_configuration_save_lock(configuration_save_lock))
+            .and(super::with_local_exclusions_store(local_exclusions_store))
+            .and(super::with_notify_reload(notify_reload))
+            .and_then(self::import_configuration))
+        .boxed()
+}
@@ -0,0 +1,64 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, ContentRewriteRule};
+use crate::proxy::content_rewrite::ContentRewriteStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_content_rewrite_rules() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get content rewrite rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(
+        &configuration.content_rewrite_rules,
+    )))
+}
+
+async fn put_content_rewrite_rules(
+    content_rewrite_rules: Vec<ContentRewriteRule>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    content_rewrite_store: ContentRewriteStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put content rewrite rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_content_rewrite_rules(content_rewrite_rules, content_rewrite_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    content_rewrite_store: ContentRewriteStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_content_rewrite_store = warp::any().map(move || content_rewrite_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_content_rewrite_rules)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_content_rewrite_store)
+            .and_then(self::put_content_rewrite_rules))
+        .boxed()
+}
@@ -0,0 +1,62 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, CookieRules};
+use crate::proxy::cookie_rules::CookieRulesStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_cookie_rules() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get cookie rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.cookie_rules)))
+}
+
+async fn put_cookie_rules(
+    cookie_rules: CookieRules,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    cookie_rules_store: CookieRulesStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put cookie rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_cookie_rules(cookie_rules, cookie_rules_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    cookie_rules_store: CookieRulesStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_cookie_rules_store = warp::any().map(move || cookie_rules_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_cookie_rules)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_cookie_rules_store)
+            .and_then(self::put_cookie_rules))
+        .boxed()
+}
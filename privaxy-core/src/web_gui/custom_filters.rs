@@ -1,7 +1,7 @@
 use super::get_error_response;
+use crate::blocker::AdblockRequester;
 use crate::configuration::Configuration;
 use std::{convert::Infallible, sync::Arc};
-use tokio::sync::mpsc::Sender;
 use warp::filters::BoxedFilter;
 use warp::http::StatusCode;
 use warp::Filter as RouteFilter;
@@ -22,7 +22,7 @@ async fn get_custom_filters() -> Result<Box<dyn warp::Reply>, Infallible> {
 
 async fn put_custom_filters(
     custom_filters: String,
-    configuration_updater_sender: Sender<Configuration>,
+    adblock_requester: AdblockRequester,
     configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
 ) -> Result<Box<dyn warp::Reply>, Infallible> {
     let _guard = configuration_save_lock.lock().await;
@@ -40,25 +40,24 @@ async fn put_custom_filters(
         return Ok(Box::new(get_error_response(err)));
     }
 
-    configuration_updater_sender
-        .send(configuration.clone())
-        .await
-        .unwrap();
+    // Only the custom filters engine needs to be rebuilt, which is much faster than
+    // going through the configuration updater.
+    adblock_requester
+        .replace_custom_engine(configuration.custom_filters.clone())
+        .await;
 
     Ok(Box::new(StatusCode::ACCEPTED))
 }
 
 pub(super) fn create_routes(
-    configuration_updater_sender: Sender<Configuration>,
+    adblock_requester: AdblockRequester,
     configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
 ) -> BoxedFilter<(impl warp::Reply,)> {
     warp::get()
         .and_then(self::get_custom_filters)
         .or(warp::put()
             .and(warp::body::json())
-            .and(super::with_configuration_updater_sender(
-                configuration_updater_sender.clone(),
-            ))
+            .and(super::with_adblock_requester(adblock_requester))
             .and(super::with_configuration_save_lock(
                 configuration_save_lock.clone(),
             ))
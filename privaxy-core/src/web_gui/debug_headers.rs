@@ -0,0 +1,38 @@
+use crate::proxy::debug_headers::DebugHeadersStore;
+use serde::Deserialize;
+use std::convert::Infallible;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+#[derive(Deserialize)]
+pub struct DebugHeadersEnabled(bool);
+
+pub async fn get_debug_headers_enabled(
+    debug_headers_store: DebugHeadersStore,
+) -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&debug_headers_store.is_enabled()))
+}
+
+pub async fn put_debug_headers_enabled(
+    debug_headers_enabled: DebugHeadersEnabled,
+    debug_headers_store: DebugHeadersStore,
+) -> Result<impl warp::Reply, Infallible> {
+    debug_headers_store.set(debug_headers_enabled.0);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) fn create_routes(
+    debug_headers_store: DebugHeadersStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let store = warp::any().map(move || debug_headers_store.clone());
+    warp::get()
+        .and(store.clone())
+        .and_then(self::get_debug_headers_enabled)
+        .or(warp::put()
+            .and(warp::body::json())
+            .and(store)
+            .and_then(self::put_debug_headers_enabled))
+        .boxed()
+}
@@ -0,0 +1,503 @@
+use super::ApiError;
+use crate::blocker::MatchedFilter;
+use crate::configuration::ThreatSeverity;
+use crate::event_history::{EventHistory, RecordedEvent, TaggedEvent};
+use crate::proxy::scripts::ScriptMatch;
+use crate::threat_intelligence::ThreatMatch;
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::ws::{Message, WebSocket};
+use warp::Filter as RouteFilter;
+
+const DEFAULT_EVENT_HISTORY_LIMIT: usize = 100;
+const MAX_EVENT_HISTORY_LIMIT: usize = 1000;
+
+/// Clients are pinged this often, and disconnected when they haven't answered for
+/// twice as long.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Event {
+    /// Identifies the request, its decision can be looked up with `/requests/{id}/decision`.
+    pub id: u64,
+    pub now: DateTime<Utc>,
+    pub method: String,
+    pub url: String,
+    pub is_request_blocked: bool,
+    /// Client that issued the request, as identified by the client identification
+    /// settings, such as its IP address.
+    #[serde(default)]
+    pub client: String,
+    /// Authenticated proxy user that issued the request, if any.
+    pub user: Option<String>,
+    /// Filter that blocked the request.
+    pub filter: Option<MatchedFilter>,
+    /// Threat feed listing the address of the request, when it was blocked as malware
+    /// or phishing.
+    #[serde(default)]
+    pub threat: Option<ThreatMatch>,
+    /// Content category listing the host of the request, when it was blocked as such.
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Lua script that blocked the request.
+    #[serde(default)]
+    pub script: Option<ScriptMatch>,
+}
+
+/// Events about the state of Privaxy itself, as opposed to proxied requests.
+/// They are tagged with a `kind` to tell them apart from request events.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StatusEvent {
+    /// The blocking engine is being built from the active filter lists.
+    EngineLoading { now: DateTime<Utc> },
+    /// A filter list was parsed while building the blocking engine. Requests keep being
+    /// checked against the previous engine until the new one is ready.
+    EngineProgress {
+        now: DateTime<Utc>,
+        parsed_lists: usize,
+        lists: usize,
+    },
+    /// The blocking engine is ready to filter requests.
+    EngineReady {
+        now: DateTime<Utc>,
+        /// Whether the engine was restored from the on disk cache rather than compiled.
+        from_cache: bool,
+        duration_ms: u64,
+    },
+    /// A filter list started downloading.
+    FilterUpdateStarted {
+        now: DateTime<Utc>,
+        id: Uuid,
+        file_name: String,
+        title: String,
+    },
+    /// A filter list was checked for updates. `modified` is false when the
+    /// remote list didn't change since it was last downloaded.
+    FilterUpdateFinished {
+        now: DateTime<Utc>,
+        id: Uuid,
+        file_name: String,
+        title: String,
+        modified: bool,
+    },
+    /// A filter list could not be updated, the previous version is kept.
+    FilterUpdateFailed {
+        now: DateTime<Utc>,
+        id: Uuid,
+        file_name: String,
+        title: String,
+        error: String,
+    },
+    /// The configuration file was edited by another program and its changes applied.
+    ConfigurationReloaded { now: DateTime<Utc> },
+    /// The configuration file was edited by another program but can't be read, the
+    /// current configuration is kept.
+    ConfigurationReloadFailed { now: DateTime<Utc>, error: String },
+    /// The CA certificate expires soon, it can be replaced with `/ca/rotate`.
+    CaExpiring {
+        now: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    },
+    /// A schedule switched to its profile.
+    ScheduleApplied {
+        now: DateTime<Utc>,
+        rule: String,
+        profile: String,
+    },
+    /// A client repeatedly failed to complete TLS handshakes with a host, which is
+    /// listed by `/suggested-exclusions`.
+    ExclusionSuggested {
+        now: DateTime<Utc>,
+        host: String,
+        client: IpAddr,
+        failures: u32,
+    },
+    /// A threat feed was downloaded.
+    ThreatFeedUpdated {
+        now: DateTime<Utc>,
+        title: String,
+        entries: usize,
+    },
+    /// A threat feed could not be downloaded, its previous entries are kept.
+    ThreatFeedUpdateFailed {
+        now: DateTime<Utc>,
+        title: String,
+        error: String,
+    },
+    /// Scripts were loaded from the scripts directory, after it changed.
+    ScriptsReloaded { now: DateTime<Utc>, loaded: usize },
+    /// A script could not be loaded, or failed while called for a request. Failures
+    /// are only reported when they differ from the previous one of the script.
+    ScriptFailed {
+        now: DateTime<Utc>,
+        name: String,
+        error: String,
+    },
+}
+
+impl Event {
+    pub(crate) fn get_severity(&self) -> Severity {
+        match &self.threat {
+            Some(threat) if threat.severity == ThreatSeverity::Low => Severity::Info,
+            Some(_threat) => Severity::Warning,
+            None if self.is_request_blocked => Severity::Info,
+            None => Severity::Debug,
+        }
+    }
+
+    pub(crate) fn get_category(&self) -> Category {
+        if self.threat.is_some() {
+            Category::Security
+        } else {
+            Category::Block
+        }
+    }
+
+    pub(crate) fn get_host(&self) -> Option<String> {
+        url::Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+    }
+}
+
+impl StatusEvent {
+    /// The `kind` the event is tagged with.
+    pub(crate) fn get_kind(&self) -> String {
+        let serialized = serde_json::to_value(self).unwrap();
+
+        serialized["kind"].as_str().unwrap_or_default().to_string()
+    }
+
+    pub(crate) fn get_now(&self) -> DateTime<Utc> {
+        match self {
+            StatusEvent::EngineLoading { now }
+            | StatusEvent::EngineProgress { now, .. }
+            | StatusEvent::EngineReady { now, .. }
+            | StatusEvent::FilterUpdateStarted { now, .. }
+            | StatusEvent::FilterUpdateFinished { now, .. }
+            | StatusEvent::FilterUpdateFailed { now, .. }
+            | StatusEvent::ConfigurationReloaded { now }
+            | StatusEvent::ConfigurationReloadFailed { now, .. }
+            | StatusEvent::CaExpiring { now, .. }
+            | StatusEvent::ScheduleApplied { now, .. }
+            | StatusEvent::ExclusionSuggested { now, .. }
+            | StatusEvent::ThreatFeedUpdated { now, .. }
+            | StatusEvent::ThreatFeedUpdateFailed { now, .. }
+            | StatusEvent::ScriptsReloaded { now, .. }
+            | StatusEvent::ScriptFailed { now, .. } => *now,
+        }
+    }
+
+    pub(crate) fn get_severity(&self) -> Severity {
+        match self {
+            StatusEvent::EngineProgress { .. } => Severity::Debug,
+            StatusEvent::EngineLoading { .. }
+            | StatusEvent::EngineReady { .. }
+            | StatusEvent::FilterUpdateStarted { .. }
+            | StatusEvent::FilterUpdateFinished { .. }
+            | StatusEvent::ConfigurationReloaded { .. }
+            | StatusEvent::ScheduleApplied { .. }
+            | StatusEvent::ThreatFeedUpdated { .. }
+            | StatusEvent::ScriptsReloaded { .. } => Severity::Info,
+            StatusEvent::CaExpiring { .. } | StatusEvent::ExclusionSuggested { .. } => {
+                Severity::Warning
+            }
+            StatusEvent::FilterUpdateFailed { .. }
+            | StatusEvent::ConfigurationReloadFailed { .. }
+            | StatusEvent::ThreatFeedUpdateFailed { .. }
+            | StatusEvent::ScriptFailed { .. } => Severity::Error,
+        }
+    }
+
+    pub(crate) fn get_category(&self) -> Category {
+        match self {
+            StatusEvent::EngineLoading { .. }
+            | StatusEvent::EngineProgress { .. }
+            | StatusEvent::EngineReady { .. } => Category::System,
+            StatusEvent::FilterUpdateStarted { .. }
+            | StatusEvent::FilterUpdateFinished { .. }
+            | StatusEvent::FilterUpdateFailed { .. } => Category::FilterUpdate,
+            StatusEvent::ConfigurationReloaded { .. }
+            | StatusEvent::ConfigurationReloadFailed { .. }
+            | StatusEvent::ScheduleApplied { .. } => Category::Config,
+            StatusEvent::CaExpiring { .. } | StatusEvent::ExclusionSuggested { .. } => {
+                Category::Tls
+            }
+            StatusEvent::ThreatFeedUpdated { .. } | StatusEvent::ThreatFeedUpdateFailed { .. } => {
+                Category::Security
+            }
+            StatusEvent::ScriptsReloaded { .. } | StatusEvent::ScriptFailed { .. } => {
+                Category::Script
+            }
+        }
+    }
+
+    pub(crate) fn get_host(&self) -> Option<String> {
+        match self {
+            StatusEvent::ExclusionSuggested { host, .. } => Some(host.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Requests that were allowed.
+    #[default]
+    Debug,
+    /// Blocked requests and changes to the state of Privaxy.
+    Info,
+    /// Something needs looking into, such as a CA certificate about to expire.
+    Warning,
+    /// Something failed, such as a filter list update.
+    Error,
+}
+
+/// What part of Privaxy an event is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// Proxied requests, whether they were blocked or not.
+    Block,
+    /// Configuration reloads and schedules.
+    Config,
+    FilterUpdate,
+    /// The CA certificate and TLS interception.
+    Tls,
+    /// The blocking engine.
+    System,
+    /// Requests blocked as malware or phishing, and threat feed updates.
+    Security,
+    /// Lua scripts being loaded, and failing.
+    Script,
+}
+
+/// Sent by clients over `/events` to only receive the events they are interested in.
+/// Clients receive every event until they send one, a new one replaces the previous.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub(super) struct Subscription {
+    /// `request` for proxied requests, or the `kind` of status events. Every type of
+    /// event is sent when empty.
+    pub types: Vec<String>,
+    /// Hosts, along with their subdomains, to send the events of. Events that are not
+    /// about a host are sent regardless.
+    pub hosts: Vec<String>,
+    /// Categories to send the events of, every category when empty.
+    pub categories: Vec<Category>,
+    pub min_severity: Severity,
+}
+
+impl Subscription {
+    fn matches(&self, event: &RecordedEvent) -> bool {
+        if !self.types.is_empty() && !self.types.contains(&event.get_type()) {
+            return false;
+        }
+
+        if !self.categories.is_empty() && !self.categories.contains(&event.get_category()) {
+            return false;
+        }
+
+        if event.get_severity() < self.min_severity {
+            return false;
+        }
+
+        match event.get_host() {
+            Some(host) if !self.hosts.is_empty() => self.hosts.iter().any(|subscribed_host| {
+                host == *subscribed_host || host.ends_with(&format!(".{}", subscribed_host))
+            }),
+            _ => true,
+        }
+    }
+}
+
+/// Messages about the connection itself, tagged with a `kind` like status events.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ControlMessage {
+    /// The subscription sent by the client is applied.
+    Subscribed { subscription: Subscription },
+    /// The message sent by the client is not a subscription, the previous one is kept.
+    InvalidSubscription { error: String },
+    /// Events were dropped because they were produced faster than the client
+    /// received them.
+    Lagged { skipped: u64 },
+}
+
+pub(super) async fn events(
+    websocket: WebSocket,
+    events_sender: broadcast::Sender<Event>,
+    status_sender: broadcast::Sender<StatusEvent>,
+) {
+    let mut events_receiver = events_sender.subscribe();
+    let mut status_receiver = status_sender.subscribe();
+
+    let (mut tx, mut rx) = websocket.split();
+
+    let mut subscription = Subscription::default();
+    let mut keepalive = tokio::time::interval(KEEPALIVE_INTERVAL);
+    let mut last_seen_at = Instant::now();
+
+    loop {
+        let serialized = tokio::select! {
+            message = rx.next() => {
+                let message = match message {
+                    Some(Ok(message)) if !message.is_close() => message,
+                    _ => break,
+                };
+                last_seen_at = Instant::now();
+
+                // Pongs only keep the connection alive.
+                let text = match message.to_str() {
+                    Ok(text) => text,
+                    Err(()) => continue,
+                };
+
+                let control_message = match serde_json::from_str::<Subscription>(text) {
+                    Ok(new_subscription) => {
+                        subscription = new_subscription;
+                        ControlMessage::Subscribed { subscription: subscription.clone() }
+                    }
+                    Err(err) => ControlMessage::InvalidSubscription { error: err.to_string() },
+                };
+
+                serde_json::to_string(&control_message).unwrap()
+            },
+            _ = keepalive.tick() => {
+                if last_seen_at.elapsed() > KEEPALIVE_INTERVAL * 2 {
+                    log::debug!("Closing events socket of an unresponsive client");
+                    break;
+                }
+
+                if let Err(_err) = tx.send(Message::ping(Vec::new())).await {
+                    break;
+                }
+
+                continue;
+            },
+            event = events_receiver.recv() => match event {
+                Ok(event) => match RecordedEvent::Request(event) {
+                    event if subscription.matches(&event) => {
+                        serde_json::to_string(&TaggedEvent::from(event)).unwrap()
+                    }
+                    _event => continue,
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    serde_json::to_string(&ControlMessage::Lagged { skipped }).unwrap()
+                }
+                Err(RecvError::Closed) => break,
+            },
+            status_event = status_receiver.recv() => match status_event {
+                Ok(status_event) => match RecordedEvent::Status(status_event) {
+                    event if subscription.matches(&event) => {
+                        serde_json::to_string(&TaggedEvent::from(event)).unwrap()
+                    }
+                    _event => continue,
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    serde_json::to_string(&ControlMessage::Lagged { skipped }).unwrap()
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if let Err(_err) = tx.send(Message::text(serialized)).await {
+            break;
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EventHistoryQuery {
+    /// Only returns events that happened after this time.
+    since: Option<DateTime<Utc>>,
+    /// Comma separated types of the events to return, `request` for blocked requests or
+    /// the `kind` of status events.
+    #[serde(rename = "type")]
+    event_type: Option<String>,
+    /// Comma separated categories of the events to return.
+    category: Option<String>,
+    min_severity: Option<Severity>,
+    /// Only returns the requests of this client or authenticated proxy user.
+    client: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Splits a comma separated query parameter, ignoring empty values.
+fn split_list(list: &Option<String>) -> impl Iterator<Item = &str> {
+    list.iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+async fn get_event_history(
+    query: EventHistoryQuery,
+    event_history: EventHistory,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let categories = match split_list(&query.category)
+        .map(|category| serde_json::from_value(serde_json::Value::from(category)))
+        .collect::<Result<Vec<Category>, _>>()
+    {
+        Ok(categories) => categories,
+        Err(err) => {
+            return Ok(Box::new(warp::reply::with_status(
+                warp::reply::json(&ApiError {
+                    error: format!("Invalid category: {}", err),
+                }),
+                http::StatusCode::BAD_REQUEST,
+            )))
+        }
+    };
+    // Events are filtered the same way as they are over `/events`, except that they are
+    // not about a particular host.
+    let subscription = Subscription {
+        types: split_list(&query.event_type).map(str::to_string).collect(),
+        hosts: Vec::new(),
+        categories,
+        min_severity: query.min_severity.unwrap_or_default(),
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_EVENT_HISTORY_LIMIT)
+        .min(MAX_EVENT_HISTORY_LIMIT);
+
+    Ok(Box::new(warp::reply::json(&event_history.query(
+        query.since,
+        |event| {
+            subscription.matches(event)
+                && query.client.as_ref().is_none_or(|client| match event {
+                    RecordedEvent::Request(event) => {
+                        &event.client == client || event.user.as_ref() == Some(client)
+                    }
+                    RecordedEvent::Status(_status_event) => false,
+                })
+        },
+        limit,
+    ))))
+}
+
+/// Blocked requests and status events that happened recently, most recent first.
+pub(super) fn create_history_routes(
+    event_history: EventHistory,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(warp::query::<EventHistoryQuery>())
+        .and(warp::any().map(move || event_history.clone()))
+        .and_then(get_event_history)
+        .boxed()
+}
@@ -0,0 +1,173 @@
+use super::{get_error_response, ApiError};
+use crate::{configuration::Configuration, proxy::exclusions::LocalExclusionStore};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{convert::Infallible, sync::Arc, time::Duration};
+use tokio::sync::mpsc::Sender;
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TemporaryExclusionRequest {
+    pub host: String,
+    /// Seconds until the exclusion expires.
+    pub duration: u64,
+}
+
+fn get_bad_request_response(error: &str) -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder().status(StatusCode::BAD_REQUEST).body(
+            serde_json::to_string(&ApiError {
+                error: error.to_string(),
+            })
+            .unwrap(),
+        ),
+    )
+}
+
+async fn get_exclusions() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get exclusions: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    let exclusions = Vec::from_iter(configuration.exclusions.into_iter()).join("\n");
+
+    Ok(Box::new(warp::reply::json(&exclusions)))
+}
+
+async fn put_exclusions(
+    exclusions: String,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    local_exclusions_store: LocalExclusionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put exclusions: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_exclusions(&exclusions, local_exclusions_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    configuration_updater_sender
+        .send(configuration.clone())
+        .await
+        .unwrap();
+
+    Ok(Box::new(StatusCode::ACCEPTED))
+}
+
+async fn get_temporary_exclusions(
+    local_exclusions_store: LocalExclusionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(
+        &local_exclusions_store.get_temporary_exclusions(),
+    )))
+}
+
+async fn post_temporary_exclusion(
+    temporary_exclusion: TemporaryExclusionRequest,
+    local_exclusions_store: LocalExclusionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let host = temporary_exclusion.host.trim();
+
+    if host.is_empty() {
+        return Ok(get_bad_request_response("A host is required"));
+    }
+
+    if temporary_exclusion.duration == 0 {
+        return Ok(get_bad_request_response("The duration must be positive"));
+    }
+
+    let expires_at =
+        match chrono::Duration::from_std(Duration::from_secs(temporary_exclusion.duration))
+            .ok()
+            .and_then(|duration| chrono::Utc::now().checked_add_signed(duration))
+        {
+            Some(expires_at) => expires_at,
+            None => return Ok(get_bad_request_response("The duration is too long")),
+        };
+
+    local_exclusions_store.add_temporary_exclusion(host, expires_at);
+
+    log::info!("Excluding {} until {}", host, expires_at);
+
+    Ok(Box::new(warp::reply::with_status(
+        warp::reply::json(&local_exclusions_store.get_temporary_exclusions()),
+        StatusCode::CREATED,
+    )))
+}
+
+async fn delete_temporary_exclusion(
+    host: String,
+    local_exclusions_store: LocalExclusionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if local_exclusions_store.remove_temporary_exclusion(&host) {
+        Ok(Box::new(StatusCode::NO_CONTENT))
+    } else {
+        Ok(Box::new(StatusCode::NOT_FOUND))
+    }
+}
+
+/// Exclusions that are not saved to the configuration file and expire on their own.
+fn create_temporary_routes(
+    local_exclusions_store: LocalExclusionStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::path("temporary")
+        .and(
+            warp::get()
+                .and(warp::path::end())
+                .and(super::with_local_exclusions_store(
+                    local_exclusions_store.clone(),
+                ))
+                .and_then(self::get_temporary_exclusions)
+                .or(warp::post()
+                    .and(warp::path::end())
+                    .and(warp::body::json())
+                    .and(super::with_local_exclusions_store(
+                        local_exclusions_store.clone(),
+                    ))
+                    .and_then(self::post_temporary_exclusion))
+                .or(warp::delete()
+                    .and(warp::path::param())
+                    .and(warp::path::end())
+                    .and(super::with_local_exclusions_store(local_exclusions_store))
+                    .and_then(self::delete_temporary_exclusion)),
+        )
+        .boxed()
+}
+
+pub fn create_routes(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    local_exclusions_store: LocalExclusionStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    // Matched first, the routes below don't check that the path ends.
+    create_temporary_routes(local_exclusions_store.clone())
+        .or(warp::get().and_then(self::get_exclusions))
+        .or(warp::put()
+            .and(warp::body::json())
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender.clone(),
+            ))
+            .and(super::with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and(super::with_local_exclusions_store(local_exclusions_store))
+            .and_then(self::put_exclusions))
+        .boxed()
+}
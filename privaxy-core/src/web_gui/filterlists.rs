@@ -0,0 +1,395 @@
+use filterlists_api::{FilterDetails, FilterLanguage, FilterLicense, FilterListError, FilterTag};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use warp::filters::BoxedFilter;
+use warp::Filter as RouteFilter;
+
+/// How long data fetched from filterlists.com is served before being fetched again.
+const CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+/// Minimum delay between two requests to filterlists.com, to stay within its rate
+/// limits however many browsers are searching.
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_RESULTS_PER_PAGE: usize = 10;
+const MAX_RESULTS_PER_PAGE: usize = 100;
+
+struct CachedEntry<T> {
+    value: T,
+    fetched_at: Instant,
+}
+
+#[derive(Default)]
+struct FilterListsCacheState {
+    filters: Option<CachedEntry<Arc<Vec<filterlists_api::Filter>>>>,
+    details: HashMap<u32, Option<CachedEntry<Arc<FilterDetails>>>>,
+    syntaxes: Option<CachedEntry<Arc<Vec<filterlists_api::Filter>>>>,
+    languages: Option<CachedEntry<Arc<Vec<FilterLanguage>>>>,
+    tags: Option<CachedEntry<Arc<Vec<FilterTag>>>>,
+    licenses: Option<CachedEntry<Arc<Vec<FilterLicense>>>>,
+    last_request_at: Option<Instant>,
+}
+
+/// Responses of filterlists.com, shared by every session of the web GUI.
+///
+/// Requests are made one at a time, so concurrent searches wait for the same
+/// response instead of each querying filterlists.com. Cached data outlives its TTL
+/// when filterlists.com can't be reached.
+#[derive(Clone, Default)]
+pub(super) struct FilterListsCache(Arc<Mutex<FilterListsCacheState>>);
+
+async fn get_cached<T, F, Fut>(
+    entry: &mut Option<CachedEntry<Arc<T>>>,
+    last_request_at: &mut Option<Instant>,
+    fetch: F,
+) -> Result<Arc<T>, FilterListError>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, FilterListError>>,
+{
+    if let Some(cached) = entry
+        .as_ref()
+        .filter(|cached| cached.fetched_at.elapsed() < CACHE_TTL)
+    {
+        return Ok(cached.value.clone());
+    }
+
+    if let Some(elapsed) = last_request_at.map(|instant| instant.elapsed()) {
+        if elapsed < MIN_REQUEST_INTERVAL {
+            tokio::time::sleep(MIN_REQUEST_INTERVAL - elapsed).await;
+        }
+    }
+    *last_request_at = Some(Instant::now());
+
+    match fetch().await {
+        Ok(value) => {
+            let value = Arc::new(value);
+            *entry = Some(CachedEntry {
+                value: value.clone(),
+                fetched_at: Instant::now(),
+            });
+            Ok(value)
+        }
+        Err(err) => match entry {
+            Some(cached) => {
+                log::warn!("Unable to refresh filterlists.com data, serving cached data: {err}");
+                Ok(cached.value.clone())
+            }
+            None => Err(err),
+        },
+    }
+}
+
+impl FilterListsCache {
+    async fn get_filters(&self) -> Result<Arc<Vec<filterlists_api::Filter>>, FilterListError> {
+        let mut state = self.0.lock().await;
+        let state = &mut *state;
+
+        get_cached(
+            &mut state.filters,
+            &mut state.last_request_at,
+            filterlists_api::get_filters,
+        )
+        .await
+    }
+
+    async fn get_filter_information(&self, id: u32) -> Result<Arc<FilterDetails>, FilterListError> {
+        let mut state = self.0.lock().await;
+        let state = &mut *state;
+
+        get_cached(
+            state.details.entry(id).or_default(),
+            &mut state.last_request_at,
+            || filterlists_api::get_filter_information(filterlists_api::FilterArgs::U32(id)),
+        )
+        .await
+    }
+
+    async fn get_syntaxes(&self) -> Result<Arc<Vec<filterlists_api::Filter>>, FilterListError> {
+        let mut state = self.0.lock().await;
+        let state = &mut *state;
+
+        get_cached(
+            &mut state.syntaxes,
+            &mut state.last_request_at,
+            filterlists_api::get_syntaxes,
+        )
+        .await
+    }
+
+    async fn get_languages(&self) -> Result<Arc<Vec<FilterLanguage>>, FilterListError> {
+        let mut state = self.0.lock().await;
+        let state = &mut *state;
+
+        get_cached(
+            &mut state.languages,
+            &mut state.last_request_at,
+            filterlists_api::get_languages,
+        )
+        .await
+    }
+
+    async fn get_tags(&self) -> Result<Arc<Vec<FilterTag>>, FilterListError> {
+        let mut state = self.0.lock().await;
+        let state = &mut *state;
+
+        get_cached(
+            &mut state.tags,
+            &mut state.last_request_at,
+            filterlists_api::get_tags,
+        )
+        .await
+    }
+
+    async fn get_licenses(&self) -> Result<Arc<Vec<FilterLicense>>, FilterListError> {
+        let mut state = self.0.lock().await;
+        let state = &mut *state;
+
+        get_cached(
+            &mut state.licenses,
+            &mut state.last_request_at,
+            filterlists_api::get_licenses,
+        )
+        .await
+    }
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_per_page() -> usize {
+    DEFAULT_RESULTS_PER_PAGE
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub(super) struct SearchQuery {
+    /// Matched against the name and description of the filter lists.
+    #[serde(default)]
+    query: String,
+    /// Name of a tag the filter lists must have.
+    tag: Option<String>,
+    /// Name or ISO 639-1 code of a language the filter lists must target.
+    language: Option<String>,
+    #[serde(default = "default_page")]
+    page: usize,
+    #[serde(default = "default_per_page")]
+    per_page: usize,
+}
+
+/// A filter list of filterlists.com, with the names of its languages, license and
+/// tags resolved.
+#[derive(Debug, Serialize)]
+struct FilterListSearchResult {
+    id: u32,
+    name: String,
+    description: Option<String>,
+    primary_view_url: Option<String>,
+    languages: Vec<String>,
+    license: Option<String>,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct FilterListSearchResponse {
+    results: Vec<FilterListSearchResult>,
+    /// Number of filter lists matching the search, across every page.
+    total: usize,
+    page: usize,
+    per_page: usize,
+}
+
+async fn search_filters(
+    search_query: SearchQuery,
+    cache: FilterListsCache,
+) -> Result<FilterListSearchResponse, FilterListError> {
+    let filters = cache.get_filters().await?;
+    let languages = cache.get_languages().await?;
+    let tags = cache.get_tags().await?;
+    let licenses = cache.get_licenses().await?;
+
+    let query = search_query.query.trim().to_lowercase();
+    let tag_id = match &search_query.tag {
+        Some(tag) => match tags.iter().find(|t| t.name.eq_ignore_ascii_case(tag)) {
+            Some(tag) => Some(tag.id),
+            None => return Ok(empty_search_response(&search_query)),
+        },
+        None => None,
+    };
+    let language_id = match &search_query.language {
+        Some(language) => match languages.iter().find(|l| {
+            l.name.eq_ignore_ascii_case(language) || l.iso6391.eq_ignore_ascii_case(language)
+        }) {
+            Some(language) => Some(language.id),
+            None => return Ok(empty_search_response(&search_query)),
+        },
+        None => None,
+    };
+
+    let matching_filters = filters
+        .iter()
+        .filter(|filter| {
+            query.is_empty()
+                || filter.name.to_lowercase().contains(&query)
+                || filter
+                    .description
+                    .as_ref()
+                    .is_some_and(|description| description.to_lowercase().contains(&query))
+        })
+        .filter(|filter| tag_id.is_none_or(|id| filter.tag_ids.contains(&id)))
+        .filter(|filter| language_id.is_none_or(|id| filter.language_ids.contains(&id)))
+        .collect::<Vec<_>>();
+
+    let per_page = search_query.per_page.clamp(1, MAX_RESULTS_PER_PAGE);
+    let page = search_query.page.max(1);
+
+    let results = matching_filters
+        .iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .map(|filter| FilterListSearchResult {
+            id: filter.id,
+            name: filter.name.clone(),
+            description: filter.description.clone(),
+            primary_view_url: filter.primary_view_url.clone(),
+            languages: languages
+                .iter()
+                .filter(|language| filter.language_ids.contains(&language.id))
+                .map(|language| language.name.clone())
+                .collect(),
+            license: licenses
+                .iter()
+                .find(|license| license.id == filter.license_id)
+                .map(|license| license.name.clone()),
+            tags: tags
+                .iter()
+                .filter(|tag| filter.tag_ids.contains(&tag.id))
+                .map(|tag| tag.name.clone())
+                .collect(),
+        })
+        .collect();
+
+    Ok(FilterListSearchResponse {
+        results,
+        total: matching_filters.len(),
+        page,
+        per_page,
+    })
+}
+
+fn empty_search_response(search_query: &SearchQuery) -> FilterListSearchResponse {
+    FilterListSearchResponse {
+        results: Vec::new(),
+        total: 0,
+        page: search_query.page.max(1),
+        per_page: search_query.per_page.clamp(1, MAX_RESULTS_PER_PAGE),
+    }
+}
+
+async fn search(
+    search_query: SearchQuery,
+    cache: FilterListsCache,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Searching filters: {search_query:?}");
+    match search_filters(search_query, cache).await {
+        Ok(response) => Ok(Box::new(warp::reply::json(&response))),
+        Err(err) => {
+            log::error!("Failed to search filters: {err}");
+            Ok(Box::new(super::get_error_response(err)))
+        }
+    }
+}
+
+async fn get_filters(cache: FilterListsCache) -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting filters");
+    match cache.get_filters().await {
+        Ok(filters) => Ok(Box::new(warp::reply::json(&*filters))),
+        Err(err) => {
+            log::error!("Failed to get filters: {err}");
+            Ok(Box::new(super::get_error_response(err)))
+        }
+    }
+}
+
+async fn get_filter(id: u32, cache: FilterListsCache) -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting filter {id}");
+    match cache.get_filter_information(id).await {
+        Ok(filter) => Ok(Box::new(warp::reply::json(&*filter))),
+        Err(err) => {
+            log::error!("Failed to get filter: {err}");
+            Ok(Box::new(super::get_error_response(err)))
+        }
+    }
+}
+
+async fn get_syntaxes(cache: FilterListsCache) -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting syntaxes");
+    match cache.get_syntaxes().await {
+        Ok(syntaxes) => Ok(Box::new(warp::reply::json(&*syntaxes))),
+        Err(err) => Ok(Box::new(super::get_error_response(err))),
+    }
+}
+
+async fn get_languages(cache: FilterListsCache) -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting languages");
+    match cache.get_languages().await {
+        Ok(languages) => Ok(Box::new(warp::reply::json(&*languages))),
+        Err(err) => Ok(Box::new(super::get_error_response(err))),
+    }
+}
+
+async fn get_tags(cache: FilterListsCache) -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting tags");
+    match cache.get_tags().await {
+        Ok(tags) => Ok(Box::new(warp::reply::json(&*tags))),
+        Err(err) => Ok(Box::new(super::get_error_response(err))),
+    }
+}
+
+async fn get_licenses(cache: FilterListsCache) -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting licenses");
+    match cache.get_licenses().await {
+        Ok(licenses) => Ok(Box::new(warp::reply::json(&*licenses))),
+        Err(err) => Ok(Box::new(super::get_error_response(err))),
+    }
+}
+
+pub(super) fn create_routes(cache: FilterListsCache) -> BoxedFilter<(impl warp::Reply,)> {
+    let cache = warp::any().map(move || cache.clone());
+
+    warp::path("search")
+        .and(warp::get())
+        .and(warp::query::<SearchQuery>())
+        .and(cache.clone())
+        .and_then(self::search)
+        .or(warp::path("list")
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(cache.clone())
+            .and_then(self::get_filters))
+        .or(warp::path!("list" / u32)
+            .and(warp::get())
+            .and(cache.clone())
+            .and_then(self::get_filter))
+        .or(warp::path("syntaxes")
+            .and(warp::get())
+            .and(cache.clone())
+            .and_then(self::get_syntaxes))
+        .or(warp::path("languages")
+            .and(warp::get())
+            .and(cache.clone())
+            .and_then(self::get_languages))
+        .or(warp::path("tags")
+            .and(warp::get())
+            .and(cache.clone())
+            .and_then(self::get_tags))
+        .or(warp::path("licenses")
+            .and(warp::get())
+            .and(cache)
+            .and_then(self::get_licenses))
+        .boxed()
+}
@@ -0,0 +1,651 @@
+use super::get_error_response;
+use crate::configuration::{
+    calc_filter_filename, refresh_filters, Configuration, ConfigurationError, Filter,
+    FilterBasicAuth, FilterGroup,
+};
+use crate::web_gui::events::StatusEvent;
+use crate::web_gui::ApiError;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_with::{serde_as, DisplayFromStr};
+
+use std::collections::BTreeMap;
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::Sender;
+use url::Url;
+use uuid::Uuid;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+use warp::filters::BoxedFilter;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FilterStatusChangeRequest {
+    enabled: bool,
+    id: Uuid,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FilterUpdateQuery {
+    /// Only update the filter with this identifier. All enabled filters are updated when omitted.
+    id: Option<Uuid>,
+}
+
+/// Operation of a `POST /filters/batch` request.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FilterBatchOperation {
+    Add(FilterRequest),
+    Enable { id: Uuid },
+    Disable { id: Uuid },
+    Delete { id: Uuid },
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FilterEditRequest {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    url: Option<Url>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct FilterRequest {
+    pub enabled: bool,
+    pub title: String,
+    pub group: FilterGroup,
+    #[serde_as(as = "DisplayFromStr")]
+    pub url: Url,
+    /// Headers sent when downloading the filter, such as `Authorization`.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub basic_auth: Option<FilterBasicAuth>,
+}
+
+/// List to download and describe by `POST /filters/preview`, before subscribing to it.
+#[serde_as]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FilterPreviewRequest {
+    #[serde_as(as = "DisplayFromStr")]
+    url: Url,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    basic_auth: Option<FilterBasicAuth>,
+}
+
+impl From<FilterRequest> for Filter {
+    fn from(filter_request: FilterRequest) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            enabled: filter_request.enabled,
+            title: filter_request.title,
+            group: filter_request.group,
+            file_name: calc_filter_filename(filter_request.url.as_str()),
+            url: filter_request.url,
+            update_interval_secs: None,
+            headers: filter_request.headers,
+            basic_auth: filter_request.basic_auth,
+            managed: false,
+        }
+    }
+}
+
+fn get_filter_conflict_response(url: &Url) -> Response<String> {
+    log::warn!("Filter with URL {} already exists", url);
+
+    Response::builder()
+        .status(http::StatusCode::CONFLICT)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(
+            serde_json::to_string(&ApiError {
+                error: format!("Filter with URL {} already exists", url),
+            })
+            .unwrap(),
+        )
+        .unwrap()
+}
+
+fn get_invalid_preview_url_response(url: &Url) -> Response<String> {
+    log::warn!("Refusing to preview filter with URL {}", url);
+
+    Response::builder()
+        .status(http::StatusCode::BAD_REQUEST)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(
+            serde_json::to_string(&ApiError {
+                error: format!("Only http and https filter lists can be previewed: {}", url),
+            })
+            .unwrap(),
+        )
+        .unwrap()
+}
+
+fn get_filter_error_response(err: ConfigurationError) -> Response<String> {
+    let status = match err {
+        ConfigurationError::Managed(_) => http::StatusCode::FORBIDDEN,
+        ConfigurationError::FilterNotFound(_) => http::StatusCode::NOT_FOUND,
+        err => return get_error_response(err),
+    };
+
+    Response::builder()
+        .status(status)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(
+            serde_json::to_string(&ApiError {
+                error: err.to_string(),
+            })
+            .unwrap(),
+        )
+        .unwrap()
+}
+
+async fn change_filter_status(
+    filter_status_change_request: Vec<FilterStatusChangeRequest>,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to change filter status: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    for filter in filter_status_change_request {
+        if let Err(err) = configuration
+            .set_filter_enabled_status(filter.id, filter.enabled)
+            .await
+        {
+            log::error!("Failed to change filter status: {err}");
+            return Ok(get_filter_error_response(err));
+        }
+    }
+    let guard = configuration_save_lock.lock().await;
+
+    configuration_updater_sender
+        .send(configuration.clone())
+        .await
+        .unwrap();
+    drop(guard);
+    Ok(Response::builder()
+        .status(http::StatusCode::ACCEPTED)
+        .body("".to_string())
+        .unwrap())
+}
+
+async fn get_filters_configuration() -> Result<impl warp::Reply, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get filters configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    let filters = configuration.filters;
+    log::debug!("Filters: {:?}", filters);
+    Ok(Response::builder()
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(&filters).unwrap())
+        .unwrap())
+}
+
+async fn get_filter_status(id: Uuid) -> Result<impl warp::Reply, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to read configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    let filter = match configuration.filters.iter().find(|filter| filter.id == id) {
+        Some(filter) => filter,
+        None => {
+            return Ok(get_filter_error_response(
+                ConfigurationError::FilterNotFound(id),
+            ))
+        }
+    };
+
+    Ok(Response::builder()
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(&filter.get_status().await).unwrap())
+        .unwrap())
+}
+
+async fn preview_filter(
+    filter_preview_request: FilterPreviewRequest,
+    http_client: reqwest::Client,
+) -> Result<impl warp::Reply, Infallible> {
+    // Previews are returned verbatim, so local file sources must never be read here.
+    if !matches!(filter_preview_request.url.scheme(), "http" | "https") {
+        return Ok(get_invalid_preview_url_response(
+            &filter_preview_request.url,
+        ));
+    }
+
+    let filter = Filter::from(FilterRequest {
+        enabled: false,
+        title: filter_preview_request.url.to_string(),
+        group: FilterGroup::Regional,
+        url: filter_preview_request.url,
+        headers: filter_preview_request.headers,
+        basic_auth: filter_preview_request.basic_auth,
+    });
+
+    match filter.get_preview(&http_client).await {
+        Ok(preview) => Ok(Response::builder()
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(&preview).unwrap())
+            .unwrap()),
+        Err(err) => {
+            log::error!("Failed to preview filter: {err}");
+            Ok(get_error_response(err))
+        }
+    }
+}
+
+async fn add_filter(
+    filter_request: FilterRequest,
+    http_client: reqwest::Client,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<impl warp::Reply, Infallible> {
+    // Read the current configuration
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to read configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    if configuration
+        .filters
+        .iter()
+        .any(|filter| filter.url == filter_request.url)
+    {
+        return Ok(get_filter_conflict_response(&filter_request.url));
+    }
+
+    // Add the new filter to the configuration
+    let mut new_filter = Filter::from(filter_request);
+
+    match configuration
+        .add_filter(&mut new_filter, &http_client)
+        .await
+    {
+        Ok(_) => {}
+        Err(err) => {
+            log::error!("Failed to add filter: {err}");
+            return Ok(get_error_response(err));
+        }
+    }
+    let guard = configuration_save_lock.lock().await;
+    configuration_updater_sender
+        .send(configuration.clone())
+        .await
+        .unwrap();
+    drop(guard);
+    // Save the updated configuration
+    if let Err(err) = configuration.save().await {
+        log::error!("Failed to save configuration: {err}");
+        return Ok(get_error_response(err));
+    }
+
+    // Send the updated configuration to the updater
+    if let Err(err) = configuration_updater_sender
+        .send(configuration.clone())
+        .await
+    {
+        log::error!("Failed to send updated configuration: {err}");
+        return Ok(get_error_response(err));
+    }
+
+    Ok(Response::builder()
+        .status(http::StatusCode::CREATED)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(&new_filter).unwrap())
+        .unwrap())
+}
+
+async fn edit_filter(
+    id: Uuid,
+    filter_edit_request: FilterEditRequest,
+    http_client: reqwest::Client,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to read configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    if let Some(url) = &filter_edit_request.url {
+        if configuration
+            .filters
+            .iter()
+            .any(|filter| filter.id != id && &filter.url == url)
+        {
+            return Ok(get_filter_conflict_response(url));
+        }
+    }
+
+    if let Err(err) = configuration
+        .edit_filter(
+            id,
+            filter_edit_request.title,
+            filter_edit_request.url,
+            &http_client,
+        )
+        .await
+    {
+        log::error!("Failed to edit filter: {err}");
+        return Ok(get_filter_error_response(err));
+    }
+
+    if let Err(err) = configuration_updater_sender
+        .send(configuration.clone())
+        .await
+    {
+        log::error!("Failed to send updated configuration: {err}");
+        return Ok(get_error_response(err));
+    }
+
+    Ok(Response::builder()
+        .status(http::StatusCode::NO_CONTENT)
+        .body("".to_string())
+        .unwrap())
+}
+
+/// Applies all the operations or none of them, returning the added filters.
+async fn apply_filter_batch(
+    operations: Vec<FilterBatchOperation>,
+    http_client: reqwest::Client,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to read configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    let mut new_filters: Vec<Filter> = Vec::new();
+
+    for operation in operations {
+        let result = match operation {
+            FilterBatchOperation::Add(filter_request) => {
+                if configuration
+                    .filters
+                    .iter()
+                    .chain(new_filters.iter())
+                    .any(|filter| filter.url == filter_request.url)
+                {
+                    return Ok(get_filter_conflict_response(&filter_request.url));
+                }
+
+                new_filters.push(Filter::from(filter_request));
+                Ok(())
+            }
+            FilterBatchOperation::Enable { id } => configuration
+                .get_filter_mut(id)
+                .map(|filter| filter.enabled = true),
+            FilterBatchOperation::Disable { id } => {
+                configuration.get_filter_mut(id).and_then(|filter| {
+                    if filter.managed {
+                        return Err(ConfigurationError::Managed(filter.title.clone()));
+                    }
+
+                    filter.enabled = false;
+                    Ok(())
+                })
+            }
+            FilterBatchOperation::Delete { id } => configuration
+                .get_filter_mut(id)
+                .and_then(|filter| {
+                    if filter.managed {
+                        return Err(ConfigurationError::Managed(filter.title.clone()));
+                    }
+
+                    Ok(())
+                })
+                .map(|()| configuration.filters.retain(|filter| filter.id != id)),
+        };
+
+        if let Err(err) = result {
+            log::error!("Failed to apply filter operation: {err}");
+            return Ok(get_filter_error_response(err));
+        }
+    }
+
+    if let Err(err) = configuration
+        .add_filters(new_filters.clone(), &http_client)
+        .await
+    {
+        log::error!("Failed to add filters: {err}");
+        return Ok(get_error_response(err));
+    }
+
+    if let Err(err) = configuration.save().await {
+        log::error!("Failed to save configuration: {err}");
+        return Ok(get_error_response(err));
+    }
+
+    if let Err(err) = configuration_updater_sender
+        .send(configuration.clone())
+        .await
+    {
+        log::error!("Failed to send updated configuration: {err}");
+        return Ok(get_error_response(err));
+    }
+
+    Ok(Response::builder()
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(&new_filters).unwrap())
+        .unwrap())
+}
+
+async fn delete_filter(
+    id: Uuid,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<impl warp::Reply, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to read configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    match configuration.filters.iter().find(|filter| filter.id == id) {
+        Some(filter) if filter.managed => {
+            return Ok(get_filter_error_response(ConfigurationError::Managed(
+                filter.title.clone(),
+            )));
+        }
+        Some(_) => {}
+        None => {
+            return Ok(get_filter_error_response(
+                ConfigurationError::FilterNotFound(id),
+            ))
+        }
+    }
+
+    let mut new_configuration = configuration.clone();
+    new_configuration.filters.retain(|filter| filter.id != id);
+
+    if let Err(err) = new_configuration.save().await {
+        log::error!("Failed to save configuration: {err}");
+        return Ok(get_error_response(err));
+    }
+
+    if let Err(err) = configuration_updater_sender
+        .send(new_configuration.clone())
+        .await
+    {
+        log::error!("Failed to send updated configuration: {err}");
+        return Ok(get_error_response(err));
+    }
+    Ok(Response::builder()
+        .status(http::StatusCode::NO_CONTENT)
+        .body("".to_string())
+        .unwrap())
+}
+
+async fn update_filters(
+    filter_update_query: FilterUpdateQuery,
+    http_client: reqwest::Client,
+    configuration_updater_sender: Sender<Configuration>,
+    status_sender: broadcast::Sender<StatusEvent>,
+) -> Result<impl warp::Reply, Infallible> {
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to read configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    if let Some(id) = filter_update_query.id {
+        if !configuration.filters.iter().any(|filter| filter.id == id) {
+            return Ok(get_filter_error_response(
+                ConfigurationError::FilterNotFound(id),
+            ));
+        }
+    }
+
+    // Downloading filters can take a while, progress is reported on the events channel.
+    tokio::spawn(async move {
+        let filters = match filter_update_query.id {
+            Some(id) => configuration
+                .filters
+                .iter_mut()
+                .filter(|filter| filter.id == id)
+                .collect::<Vec<_>>(),
+            None => configuration.get_enabled_filters().collect::<Vec<_>>(),
+        };
+
+        let outcome = refresh_filters(filters, &http_client, &status_sender).await;
+        if outcome.failed > 0 {
+            log::warn!("{} filter(s) failed to update", outcome.failed);
+        }
+
+        if outcome.modified == 0 {
+            return;
+        }
+
+        // Rebuilds the engine from the freshly downloaded filters.
+        if let Err(err) = configuration_updater_sender.send(configuration).await {
+            log::error!("Failed to send updated configuration: {err}");
+        }
+    });
+
+    Ok(Response::builder()
+        .status(http::StatusCode::ACCEPTED)
+        .body("".to_string())
+        .unwrap())
+}
+
+pub(super) fn create_routes(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    http_client: reqwest::Client,
+    status_sender: broadcast::Sender<StatusEvent>,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::path("update")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::query::<FilterUpdateQuery>())
+        .and(super::with_http_client(http_client.clone()))
+        .and(super::with_configuration_updater_sender(
+            configuration_updater_sender.clone(),
+        ))
+        .and(super::with_status_sender(status_sender))
+        .and_then(self::update_filters)
+        .or(warp::path("batch")
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(super::with_http_client(http_client.clone()))
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender.clone(),
+            ))
+            .and(super::with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and_then(self::apply_filter_batch))
+        .or(warp::path("preview")
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::body::json())
+            .and(super::with_http_client(http_client.clone()))
+            .and_then(self::preview_filter))
+        .or(warp::path::param::<Uuid>()
+            .and(warp::path("status"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and_then(self::get_filter_status))
+        .or(warp::path::param::<Uuid>()
+            .and(warp::path::end())
+            .and(warp::patch())
+            .and(warp::body::json())
+            .and(super::with_http_client(http_client.clone()))
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender.clone(),
+            ))
+            .and(super::with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and_then(self::edit_filter))
+        .or(warp::path::param::<Uuid>()
+            .and(warp::path::end())
+            .and(warp::delete())
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender.clone(),
+            ))
+            .and(super::with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and_then(self::delete_filter))
+        .or(warp::get().and_then(self::get_filters_configuration))
+        .or(warp::put()
+            .and(warp::body::json())
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender.clone(),
+            ))
+            .and(super::with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and_then(self::change_filter_status))
+        .or(warp::post()
+            .and(warp::body::json())
+            .and(super::with_http_client(http_client.clone()))
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender.clone(),
+            ))
+            .and(super::with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and_then(self::add_filter))
+        .boxed()
+}
@@ -0,0 +1,166 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, HeaderRule, RefererTrimming, UserAgentRule};
+use crate::proxy::header_rules::HeaderRulesStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_header_rules() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get header rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.header_rules)))
+}
+
+async fn put_header_rules(
+    header_rules: Vec<HeaderRule>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    header_rules_store: HeaderRulesStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put header rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_header_rules(header_rules, header_rules_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_referer_trimming() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get referer trimming: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.referer_trimming)))
+}
+
+async fn put_referer_trimming(
+    referer_trimming: RefererTrimming,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    header_rules_store: HeaderRulesStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put referer trimming: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_referer_trimming(referer_trimming, header_rules_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_user_agent_rules() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get user agent rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.user_agent_rules)))
+}
+
+async fn put_user_agent_rules(
+    user_agent_rules: Vec<UserAgentRule>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    header_rules_store: HeaderRulesStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put user agent rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_user_agent_rules(user_agent_rules, header_rules_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    header_rules_store: HeaderRulesStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_header_rules_store = warp::any().map(move || header_rules_store.clone());
+
+    let referer_route = warp::path("referer").and(
+        warp::get()
+            .and(warp::path::end())
+            .and_then(self::get_referer_trimming)
+            .or(warp::put()
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_configuration_save_lock(
+                    configuration_save_lock.clone(),
+                ))
+                .and(with_header_rules_store.clone())
+                .and_then(self::put_referer_trimming)),
+    );
+
+    let user_agent_route = warp::path("user-agent").and(
+        warp::get()
+            .and(warp::path::end())
+            .and_then(self::get_user_agent_rules)
+            .or(warp::put()
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_configuration_save_lock(
+                    configuration_save_lock.clone(),
+                ))
+                .and(with_header_rules_store.clone())
+                .and_then(self::put_user_agent_rules)),
+    );
+
+    referer_route
+        .or(user_agent_route)
+        .or(warp::get()
+            .and(warp::path::end())
+            .and_then(self::get_header_rules))
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_header_rules_store)
+            .and_then(self::put_header_rules))
+        .boxed()
+}
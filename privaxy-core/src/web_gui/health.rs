@@ -0,0 +1,51 @@
+use crate::health::HealthStore;
+use std::convert::Infallible;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_health() -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&serde_json::json!({
+        "status": "ok"
+    }))))
+}
+
+async fn get_readiness(health_store: HealthStore) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let readiness = health_store.get_readiness();
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok(Box::new(warp::reply::with_status(
+        warp::reply::json(&readiness),
+        status,
+    )))
+}
+
+async fn get_version(health_store: HealthStore) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&health_store.get_version())))
+}
+
+/// Probes of container orchestrators, which can't sign in. They are served next to the
+/// web GUI rather than under `/api`.
+pub(super) fn create_routes(health_store: HealthStore) -> BoxedFilter<(impl warp::Reply,)> {
+    let health_store = warp::any().map(move || health_store.clone());
+
+    warp::get()
+        .and(warp::path("healthz"))
+        .and(warp::path::end())
+        .and_then(self::get_health)
+        .or(warp::get()
+            .and(warp::path("readyz"))
+            .and(warp::path::end())
+            .and(health_store.clone())
+            .and_then(self::get_readiness))
+        .or(warp::get()
+            .and(warp::path("version"))
+            .and(warp::path::end())
+            .and(health_store)
+            .and_then(self::get_version))
+        .boxed()
+}
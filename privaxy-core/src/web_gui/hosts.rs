@@ -0,0 +1,40 @@
+use super::ApiError;
+use crate::proxy::upstream_tls::UpstreamTlsStore;
+use std::convert::Infallible;
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+async fn get_certificate(
+    host: String,
+    upstream_tls_store: UpstreamTlsStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let host = percent_encoding::percent_decode_str(&host)
+        .decode_utf8_lossy()
+        .to_string();
+
+    match upstream_tls_store.get_certificate_chain(&host) {
+        Some(certificate_chain) => Ok(Box::new(warp::reply::json(&certificate_chain))),
+        // Only the most recently contacted hosts are kept.
+        None => Ok(Box::new(
+            Response::builder().status(StatusCode::NOT_FOUND).body(
+                serde_json::to_string(&ApiError {
+                    error: format!("No certificate recorded for {host}"),
+                })
+                .unwrap(),
+            ),
+        )),
+    }
+}
+
+pub(super) fn create_routes(
+    upstream_tls_store: UpstreamTlsStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::param())
+        .and(warp::path("certificate"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || upstream_tls_store.clone()))
+        .and_then(self::get_certificate)
+        .boxed()
+}
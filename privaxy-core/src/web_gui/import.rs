@@ -0,0 +1,101 @@
+use super::get_error_response;
+use crate::configuration::{Configuration, ImportFormat};
+use crate::proxy::exclusions::LocalExclusionStore;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::mpsc::Sender;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+fn default_dry_run() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportQuery {
+    format: ImportFormat,
+    /// Only return the changes the import would make. Imports have to be applied
+    /// explicitly with `dry_run=false`.
+    #[serde(default = "default_dry_run")]
+    dry_run: bool,
+}
+
+async fn import(
+    import_query: ImportQuery,
+    content: String,
+    http_client: reqwest::Client,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    mut local_exclusions_store: LocalExclusionStore,
+) -> Result<impl warp::Reply, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to read configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    };
+
+    let diff = match configuration.get_import_diff(import_query.format, &content) {
+        Ok(diff) => diff,
+        Err(err) => {
+            log::error!("Failed to import configuration: {err}");
+            return Ok(Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(
+                    serde_json::to_string(&super::ApiError {
+                        error: err.to_string(),
+                    })
+                    .unwrap(),
+                )
+                .unwrap());
+        }
+    };
+
+    if !import_query.dry_run {
+        if let Err(err) = configuration.apply_import_diff(&diff, &http_client).await {
+            log::error!("Failed to apply import: {err}");
+            return Ok(get_error_response(err));
+        }
+
+        local_exclusions_store.replace_exclusions(configuration.get_exclusions());
+
+        if let Err(err) = configuration_updater_sender
+            .send(configuration.clone())
+            .await
+        {
+            log::error!("Failed to send updated configuration: {err}");
+            return Ok(get_error_response(err));
+        }
+    }
+
+    Ok(Response::builder()
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_string(&diff).unwrap())
+        .unwrap())
+}
+
+pub(super) fn create_routes(
+    http_client: reqwest::Client,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    local_exclusions_store: LocalExclusionStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::path::end()
+        .and(warp::post())
+        .and(warp::query::<ImportQuery>())
+        .and(warp::body::json())
+        .and(super::with_http_client(http_client))
+        .and(super::with_configuration_updater_sender(
+            configuration_updater_sender,
+        ))
+        .and(super::with_configuration_save_lock(configuration_save_lock))
+        .and(super::with_local_exclusions_store(local_exclusions_store))
+        .and_then(self::import)
+        .boxed()
+}
@@ -0,0 +1,62 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, Injection};
+use crate::proxy::injections::InjectionStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_injections() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get injections: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.injections)))
+}
+
+async fn put_injections(
+    injections: Vec<Injection>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    injection_store: InjectionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put injections: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_injections(injections, injection_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    injection_store: InjectionStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_injection_store = warp::any().map(move || injection_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_injections)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_injection_store)
+            .and_then(self::put_injections))
+        .boxed()
+}
@@ -0,0 +1,59 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, IpRule};
+use crate::proxy::ip_rules::IpRuleStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_ip_rules() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get IP rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.ip_rules)))
+}
+
+async fn put_ip_rules(
+    ip_rules: Vec<IpRule>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    ip_rule_store: IpRuleStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put IP rules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration.set_ip_rules(ip_rules, ip_rule_store).await {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    ip_rule_store: IpRuleStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_ip_rule_store = warp::any().map(move || ip_rule_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_ip_rules)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_ip_rule_store)
+            .and_then(self::put_ip_rules))
+        .boxed()
+}
@@ -0,0 +1,47 @@
+use super::ApiError;
+use crate::logging;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+pub struct LogLevel {
+    /// Log filter, such as `info` or `privaxy=debug,hyper=warn`.
+    level: String,
+}
+
+async fn get_log_level() -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&LogLevel {
+        level: logging::get_log_level(),
+    })))
+}
+
+/// Changes the log level until privaxy restarts, `--log-level` is used again then.
+async fn put_log_level(log_level: LogLevel) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if let Err(err) = logging::set_log_level(log_level.level.trim()) {
+        return Ok(Box::new(warp::reply::with_status(
+            warp::reply::json(&ApiError {
+                error: err.to_string(),
+            }),
+            StatusCode::BAD_REQUEST,
+        )));
+    }
+
+    log::info!("Log level changed to {}", log_level.level.trim());
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes() -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_log_level)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and_then(self::put_log_level))
+        .boxed()
+}
@@ -0,0 +1,15 @@
+use crate::configuration::get_managed_configuration;
+use std::convert::Infallible;
+use warp::filters::BoxedFilter;
+use warp::Filter as RouteFilter;
+
+async fn get_managed() -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&get_managed_configuration()))
+}
+
+pub(super) fn create_routes() -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_managed)
+        .boxed()
+}
@@ -0,0 +1,307 @@
+use super::sessions::{has_session, SessionStore};
+use super::{get_error_response, ApiError};
+use crate::configuration::{Configuration, Metrics};
+use crate::statistics::{LatencyStage, Statistics};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+/// Label value of the series aggregating clients and users beyond the configured limits.
+const OTHERS_LABEL_VALUE: &str = "(other)";
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_header(output: &mut String, name: &str, help: &str, metric_type: &str) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, metric_type);
+}
+
+fn write_counter(output: &mut String, name: &str, help: &str, value: u64) {
+    write_header(output, name, help, "counter");
+    let _ = writeln!(output, "{} {}", name, value);
+}
+
+/// Writes one series per key, for the `max_labeled` most frequent keys of `ranking`.
+/// Remaining keys are summed into a single series.
+fn write_labeled_counters(
+    output: &mut String,
+    label: &str,
+    ranking: &HashMap<String, u64>,
+    counters: &[(&str, &str, &HashMap<String, u64>)],
+    max_labeled: usize,
+) {
+    let mut keys = ranking.iter().collect::<Vec<_>>();
+    keys.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    let (labeled_keys, other_keys) = keys.split_at(keys.len().min(max_labeled));
+
+    for (name, help, values) in counters {
+        write_header(output, name, help, "counter");
+
+        for (key, _count) in labeled_keys {
+            let _ = writeln!(
+                output,
+                "{}{{{}=\"{}\"}} {}",
+                name,
+                label,
+                escape_label_value(key),
+                values.get(*key).copied().unwrap_or(0)
+            );
+        }
+
+        if !other_keys.is_empty() {
+            let others = other_keys
+                .iter()
+                .map(|(key, _count)| values.get(*key).copied().unwrap_or(0))
+                .sum::<u64>();
+
+            let _ = writeln!(
+                output,
+                "{}{{{}=\"{}\"}} {}",
+                name, label, OTHERS_LABEL_VALUE, others
+            );
+        }
+    }
+}
+
+/// Writes a histogram of the latencies of each stage, in seconds.
+fn write_latency_histograms(output: &mut String, statistics: &Statistics) {
+    let name = "privaxy_latency_seconds";
+    let latencies = statistics.latencies.lock().unwrap().clone();
+
+    write_header(
+        output,
+        name,
+        "Latency added to requests, by stage of proxying them.",
+        "histogram",
+    );
+
+    for stage in LatencyStage::ALL {
+        let histogram = &latencies[stage as usize];
+
+        for (upper_bound, count) in histogram.get_cumulative_counts() {
+            let le = match upper_bound {
+                Some(upper_bound) => (upper_bound as f64 / 1_000_000.0).to_string(),
+                None => "+Inf".to_string(),
+            };
+
+            let _ = writeln!(
+                output,
+                "{}_bucket{{stage=\"{}\",le=\"{}\"}} {}",
+                name,
+                stage.get_name(),
+                le,
+                count
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "{}_sum{{stage=\"{}\"}} {}",
+            name,
+            stage.get_name(),
+            histogram.sum_us as f64 / 1_000_000.0
+        );
+        let _ = writeln!(
+            output,
+            "{}_count{{stage=\"{}\"}} {}",
+            name,
+            stage.get_name(),
+            histogram.count
+        );
+    }
+}
+
+fn render_metrics(statistics: &Statistics, metrics: &Metrics) -> String {
+    let mut output = String::new();
+
+    write_counter(
+        &mut output,
+        "privaxy_proxied_requests_total",
+        "Requests forwarded to upstream servers.",
+        *statistics.proxied_requests.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_blocked_requests_total",
+        "Requests blocked by filters.",
+        *statistics.blocked_requests.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_modified_responses_total",
+        "Responses modified to hide elements or inject scripts.",
+        *statistics.modified_responses.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_streamed_responses_total",
+        "Media and large responses relayed without being inspected.",
+        *statistics.streamed_responses.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_event_stream_responses_total",
+        "Server-sent event streams relayed without being inspected.",
+        *statistics.event_stream_responses.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_grpc_responses_total",
+        "gRPC and gRPC-web responses relayed without being inspected.",
+        *statistics.grpc_responses.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_trimmed_referers_total",
+        "Cross-site referers reduced to their origin.",
+        *statistics.trimmed_referers.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_stripped_referers_total",
+        "Referers removed from requests.",
+        *statistics.stripped_referers.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_stripped_cookies_total",
+        "Requests and responses whose cookies were removed by cookie rules.",
+        *statistics.stripped_cookies.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_cache_hits_total",
+        "Requests answered from the response cache without contacting the server.",
+        *statistics.cache_hits.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_cache_revalidations_total",
+        "Requests answered from the response cache once revalidated with the server.",
+        *statistics.cache_revalidations.lock().unwrap(),
+    );
+    write_counter(
+        &mut output,
+        "privaxy_cache_misses_total",
+        "Requests the response cache had no usable response for.",
+        *statistics.cache_misses.lock().unwrap(),
+    );
+
+    let top_clients = statistics.top_clients.lock().unwrap().clone();
+    let blocked_clients = statistics.blocked_clients.lock().unwrap().clone();
+    write_labeled_counters(
+        &mut output,
+        "client",
+        &top_clients,
+        &[
+            (
+                "privaxy_client_requests_total",
+                "Requests per client.",
+                &top_clients,
+            ),
+            (
+                "privaxy_client_blocked_requests_total",
+                "Blocked requests per client.",
+                &blocked_clients,
+            ),
+        ],
+        metrics.max_labeled_clients,
+    );
+
+    let top_users = statistics.top_users.lock().unwrap().clone();
+    write_labeled_counters(
+        &mut output,
+        "user",
+        &top_users,
+        &[(
+            "privaxy_user_requests_total",
+            "Requests per authenticated proxy user.",
+            &top_users,
+        )],
+        metrics.max_labeled_users,
+    );
+
+    write_latency_histograms(&mut output, statistics);
+
+    output
+}
+
+fn get_error(status: http::StatusCode, error: &str) -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder().status(status).body(
+            serde_json::to_string(&ApiError {
+                error: error.to_string(),
+            })
+            .unwrap(),
+        ),
+    )
+}
+
+async fn get_metrics(
+    has_session: bool,
+    authorization: Option<String>,
+    statistics: Statistics,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get metrics settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+    let metrics = configuration.metrics;
+
+    if !metrics.enabled {
+        return Ok(get_error(
+            http::StatusCode::NOT_FOUND,
+            "Metrics are disabled",
+        ));
+    }
+
+    // Digests are compared rather than tokens, for the time taken not to tell how much
+    // of a token was right.
+    let is_bearer_token_valid = match (&metrics.bearer_token, &authorization) {
+        (Some(bearer_token), Some(authorization)) => authorization
+            .strip_prefix("Bearer ")
+            .is_some_and(|token| Sha256::digest(token) == Sha256::digest(bearer_token)),
+        _ => false,
+    };
+
+    if !is_bearer_token_valid && !has_session {
+        return Ok(get_error(
+            http::StatusCode::UNAUTHORIZED,
+            "Signing in is required",
+        ));
+    }
+
+    Ok(Box::new(
+        Response::builder()
+            .header(http::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(render_metrics(&statistics, &metrics)),
+    ))
+}
+
+/// Scrapers can't sign in, so this route is not behind the session requirement of the
+/// rest of the API and authorizes requests itself.
+pub(super) fn create_routes(
+    session_store: SessionStore,
+    statistics: Statistics,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(has_session(session_store))
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(get_metrics)
+        .boxed()
+}
@@ -0,0 +1,756 @@
+use crate::proxy::authentication::ProxyAuthenticationStore;
+use crate::proxy::block_responses::BlockResponsesStore;
+use crate::proxy::client_identification::ClientIdentificationStore;
+use crate::proxy::exclusions::LocalExclusionStore;
+use crate::proxy::plugins::PluginStore;
+use crate::proxy::site_policies::SitePolicyStore;
+use crate::AppState;
+#[cfg(feature = "web-frontend")]
+use crate::WEBAPP_FRONTEND_DIR;
+use crate::{
+    blocker::{AdblockRequester, BlockingDisabledStore},
+    configuration::{Configuration, NetworkConfig},
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use sessions::SessionStore;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::sync::{broadcast, mpsc::Sender};
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::path::{FullPath, Tail};
+use warp::{http, Filter, Reply};
+
+mod audit;
+pub(crate) mod blocking_enabled;
+mod ca;
+mod categories;
+mod circuit_breaker;
+mod client_grants;
+mod compression;
+mod config;
+mod content_rewrite_rules;
+mod cookie_rules;
+pub(crate) mod custom_filters;
+pub(crate) mod debug_headers;
+pub(crate) mod events;
+pub(crate) mod exclusions;
+mod filterlists;
+pub(crate) mod filters;
+mod header_rules;
+mod health;
+mod hosts;
+mod import;
+mod injections;
+mod ip_rules;
+mod log_level;
+mod managed;
+mod metrics;
+mod notifications;
+mod openapi;
+mod origins;
+mod plugins;
+mod profiles;
+mod requests;
+mod response_cache;
+mod safe_search;
+mod schedules;
+mod scripts;
+pub(crate) mod sessions;
+pub(crate) mod settings;
+mod site_policies;
+pub(crate) mod statistics;
+mod suggested_exclusions;
+mod system;
+mod threat_intelligence;
+mod tls_overrides;
+mod traffic_shaping;
+mod url_rewrites;
+mod versions;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub(crate) struct ApiError {
+    error: String,
+}
+pub(crate) fn get_frontend(
+    state: &AppState,
+    network_config: &NetworkConfig,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let web_base_path = network_config.get_web_base_path();
+
+    let static_files_routes = create_static_routes(network_config, &web_base_path);
+
+    let cors = warp::cors()
+        .allow_origins(network_config.allowed_origins.iter().map(String::as_str))
+        .allow_methods(vec!["GET", "PUT", "POST", "DELETE"])
+        .allow_headers(vec![
+            http::header::CONTENT_TYPE,
+            http::header::CONTENT_LENGTH,
+            http::header::DATE,
+        ]);
+
+    let http_client = reqwest::Client::new();
+
+    let api_routes = create_api_routes(state, http_client, &web_base_path);
+
+    let api_routes =
+        compression::with_compression(api_routes, network_config.compress_api_responses);
+
+    let health_routes = health::create_routes(state.health_store.clone());
+
+    let routes = with_base_path(&web_base_path)
+        .and(health_routes.or(api_routes).or(static_files_routes))
+        .boxed();
+
+    origins::validate_host(network_config.get_allowed_hosts())
+        .and(
+            origins::same_origin()
+                .and(routes.clone())
+                .or(routes.with(cors)),
+        )
+        .recover(origins::recover_host_not_allowed)
+        .boxed()
+}
+
+/// Matches the segments of the base path the web GUI is served under.
+fn with_base_path(web_base_path: &str) -> BoxedFilter<()> {
+    web_base_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(warp::any().boxed(), |filter, segment| {
+            filter.and(warp::path(segment.to_string())).boxed()
+        })
+}
+
+/// The frontend requests the API and its assets relative to the base path, which Trunk
+/// links with absolute paths.
+fn rewrite_index_html(index_html: &[u8], web_base_path: &str) -> Vec<u8> {
+    String::from_utf8_lossy(index_html)
+        .replace("\"/", &format!("\"{web_base_path}"))
+        .replace("'/", &format!("'{web_base_path}"))
+        .replacen(
+            "<head>",
+            &format!("<head><base href=\"{web_base_path}\">"),
+            1,
+        )
+        .into_bytes()
+}
+
+fn create_static_routes(
+    network_config: &NetworkConfig,
+    web_base_path: &str,
+) -> BoxedFilter<(Box<dyn Reply>,)> {
+    if crate::startup::get_startup_options().no_web_interface {
+        return create_missing_static_routes();
+    }
+
+    match network_config.get_frontend_directory() {
+        Some(frontend_directory) => {
+            create_frontend_directory_routes(frontend_directory, web_base_path)
+        }
+        None => create_built_in_static_routes(web_base_path),
+    }
+}
+
+#[cfg(feature = "web-frontend")]
+fn create_built_in_static_routes(web_base_path: &str) -> BoxedFilter<(Box<dyn Reply>,)> {
+    let index_html = rewrite_index_html(
+        WEBAPP_FRONTEND_DIR
+            .get_file("index.html")
+            .unwrap()
+            .contents(),
+        web_base_path,
+    );
+
+    warp::get()
+        .and(warp::path::tail())
+        .map(move |tail: Tail| {
+            let tail_str = tail.as_str();
+
+            let file_contents = match WEBAPP_FRONTEND_DIR.get_file(tail_str) {
+                Some(file) if tail_str != "index.html" => file.contents().to_vec(),
+                _ => index_html.clone(),
+            };
+
+            let mime = mime_guess::from_path(tail_str).first_raw().unwrap_or("");
+
+            Box::new(
+                Response::builder()
+                    .header(http::header::CONTENT_TYPE, mime)
+                    .body(file_contents),
+            ) as Box<dyn Reply>
+        })
+        .boxed()
+}
+
+#[cfg(not(feature = "web-frontend"))]
+fn create_built_in_static_routes(_web_base_path: &str) -> BoxedFilter<(Box<dyn Reply>,)> {
+    create_missing_static_routes()
+}
+
+/// Serves the web GUI from a directory, reading its files on every request so that a
+/// frontend can be rebuilt while Privaxy runs.
+fn create_frontend_directory_routes(
+    frontend_directory: PathBuf,
+    web_base_path: &str,
+) -> BoxedFilter<(Box<dyn Reply>,)> {
+    if !frontend_directory.join("index.html").is_file() {
+        log::warn!(
+            "Frontend directory has no index.html: {}",
+            frontend_directory.display()
+        );
+    }
+    log::info!(
+        "Serving the web interface from {}",
+        frontend_directory.display()
+    );
+
+    let web_base_path = web_base_path.to_string();
+
+    warp::get()
+        .and(warp::path::tail())
+        .then(move |tail: Tail| {
+            let frontend_directory = frontend_directory.clone();
+            let web_base_path = web_base_path.clone();
+
+            async move {
+                get_frontend_directory_file(&frontend_directory, tail.as_str(), &web_base_path)
+                    .await
+            }
+        })
+        .boxed()
+}
+
+/// Paths that aren't files of the directory get its `index.html`, for the routes of the
+/// frontend to load.
+async fn get_frontend_directory_file(
+    frontend_directory: &Path,
+    tail: &str,
+    web_base_path: &str,
+) -> Box<dyn Reply> {
+    let file_path = Path::new(tail);
+    // Only plain segments, so that files outside of the directory can't be requested.
+    let is_within_directory = file_path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+
+    let file_contents = if is_within_directory && !tail.is_empty() && tail != "index.html" {
+        tokio::fs::read(frontend_directory.join(file_path))
+            .await
+            .ok()
+    } else {
+        None
+    };
+
+    let (file_contents, mime) = match file_contents {
+        Some(file_contents) => (
+            file_contents,
+            mime_guess::from_path(tail).first_raw().unwrap_or(""),
+        ),
+        None => match tokio::fs::read(frontend_directory.join("index.html")).await {
+            Ok(index_html) => (rewrite_index_html(&index_html, web_base_path), "text/html"),
+            Err(err) => {
+                log::error!(
+                    "Failed to read index.html from {}: {err}",
+                    frontend_directory.display()
+                );
+
+                return Box::new(
+                    Response::builder()
+                        .status(http::StatusCode::NOT_FOUND)
+                        .body(Vec::new()),
+                );
+            }
+        },
+    };
+
+    Box::new(
+        Response::builder()
+            .header(http::header::CONTENT_TYPE, mime)
+            .body(file_contents),
+    )
+}
+
+/// Without the web interface, paths outside of the API are not found.
+fn create_missing_static_routes() -> BoxedFilter<(Box<dyn Reply>,)> {
+    warp::any()
+        .and_then(|| async { Err::<Box<dyn Reply>, _>(warp::reject::not_found()) })
+        .boxed()
+}
+
+fn create_api_routes(
+    state: &AppState,
+    http_client: reqwest::Client,
+    web_base_path: &str,
+) -> BoxedFilter<(impl Reply,)> {
+    let def_headers =
+        warp::filters::reply::default_header(http::header::CONTENT_TYPE, "application/json");
+    // Routes are served under `/api/v1`, and under `/api` for clients written before the
+    // API was versioned. Other paths fall through to the static files of the frontend.
+    let api_path = versions::with_api_version().and(warp::path::full());
+
+    let events_sender = state.events_sender.clone();
+    let events_status_sender = state.status_sender.clone();
+    let events_route = warp::path("events").and(
+        warp::path("history")
+            .and(events::create_history_routes(state.event_history.clone()))
+            .or(warp::path::end()
+                .and(warp::ws())
+                .map(move |ws: warp::ws::Ws| {
+                    let events_sender = events_sender.clone();
+                    let status_sender = events_status_sender.clone();
+                    ws.on_upgrade(move |websocket| {
+                        events::events(websocket, events_sender, status_sender)
+                    })
+                })),
+    );
+
+    let requests_route = warp::path("requests").and(requests::create_routes(
+        state.statistics.clone(),
+        state.adblock_requester.clone(),
+        state.blocking_disabled_store.clone(),
+    ));
+
+    let metrics_route = warp::path("metrics").and(metrics::create_routes(
+        state.session_store.clone(),
+        state.statistics.clone(),
+    ));
+
+    let openapi_route = openapi::create_routes(web_base_path);
+
+    let test_request_route = warp::path("test-request").and(requests::create_test_routes(
+        state.adblock_requester.clone(),
+        state.blocking_disabled_store.clone(),
+    ));
+
+    let system_route = warp::path("system").and(system::create_routes(
+        state.adblock_requester.clone(),
+        state.statistics.clone(),
+    ));
+
+    let statistics_history_route = warp::path!("statistics" / "history" / ..)
+        .and(statistics::create_history_routes(state.statistics.clone()));
+
+    let statistics_filters_route = warp::path!("statistics" / "filters" / ..)
+        .and(statistics::create_filter_routes(state.statistics.clone()));
+
+    let statistics_performance_route = warp::path!("statistics" / "performance" / ..).and(
+        statistics::create_performance_routes(state.statistics.clone()),
+    );
+
+    let statistics_rule_performance_route = warp::path!("statistics" / "rule-performance" / ..)
+        .and(statistics::create_rule_performance_routes(
+            state.adblock_requester.clone(),
+        ));
+
+    let statistics_top_route = warp::path!("statistics" / "top" / ..)
+        .and(statistics::create_top_routes(state.statistics.clone()));
+
+    let statistics_timeseries_route = warp::path!("statistics" / "timeseries" / ..).and(
+        statistics::create_timeseries_routes(state.statistics.clone()),
+    );
+
+    let statistics_clients_route = warp::path!("statistics" / "clients" / ..)
+        .and(statistics::create_client_routes(state.statistics.clone()));
+
+    let statistics_export_route = warp::path!("statistics" / "export" / ..)
+        .and(statistics::create_export_routes(state.statistics.clone()));
+
+    let statistics_reset_route = warp::path!("statistics" / "reset" / ..)
+        .and(statistics::create_reset_routes(state.statistics.clone()));
+
+    let statistics = state.statistics.clone();
+    let statistics_route = warp::path("statistics")
+        .and(warp::ws())
+        .map(move |ws: warp::ws::Ws| {
+            let statistics = statistics.clone();
+            ws.on_upgrade(move |websocket| statistics::statistics(websocket, statistics))
+        });
+
+    let filters_route = warp::path("filters").and(filters::create_routes(
+        state.configuration_updater_sender.clone(),
+        state.configuration_save_lock.clone(),
+        http_client.clone(),
+        state.status_sender.clone(),
+    ));
+
+    let custom_filters_route = warp::path("custom-filters").and(custom_filters::create_routes(
+        state.adblock_requester.clone(),
+        state.configuration_save_lock.clone(),
+    ));
+
+    let exclusions_route = warp::path("exclusions").and(exclusions::create_routes(
+        state.configuration_updater_sender.clone(),
+        state.configuration_save_lock.clone(),
+        state.local_exclusion_store.clone(),
+    ));
+
+    let suggested_exclusions_route =
+        warp::path("suggested-exclusions").and(suggested_exclusions::create_routes(
+            state.configuration_updater_sender.clone(),
+            state.configuration_save_lock.clone(),
+            state.local_exclusion_store.clone(),
+            state.handshake_failure_store.clone(),
+        ));
+
+    let settings_route = warp::path("settings").and(settings::create_routes(
+        state.configuration_updater_sender.clone(),
+        state.configuration_save_lock.clone(),
+        state.proxy_authentication_store.clone(),
+        state.block_responses_store.clone(),
+        state.client_identification_store.clone(),
+        state.session_store.clone(),
+        state.notify_reload.clone(),
+    ));
+
+    let blocking_enabled_route = warp::path("blocking-enabled").and(
+        blocking_enabled::create_routes(state.blocking_disabled_store.clone()),
+    );
+
+    let log_level_route = warp::path("log-level").and(log_level::create_routes());
+
+    let debug_headers_route = warp::path("debug-headers").and(debug_headers::create_routes(
+        state.debug_headers_store.clone(),
+    ));
+
+    let site_policies_route = warp::path("site-policies").and(site_policies::create_routes(
+        state.configuration_save_lock.clone(),
+        state.site_policy_store.clone(),
+    ));
+
+    let client_grants_route = warp::path("client-grants").and(client_grants::create_routes(
+        state.client_grant_store.clone(),
+    ));
+
+    let circuit_breaker_route = warp::path("circuit-breaker").and(circuit_breaker::create_routes(
+        state.circuit_breaker_store.clone(),
+    ));
+
+    let header_rules_route = warp::path("header-rules").and(header_rules::create_routes(
+        state.configuration_save_lock.clone(),
+        state.header_rules_store.clone(),
+    ));
+
+    let cookie_rules_route = warp::path("cookie-rules").and(cookie_rules::create_routes(
+        state.configuration_save_lock.clone(),
+        state.cookie_rules_store.clone(),
+    ));
+
+    let ip_rules_route = warp::path("ip-rules").and(ip_rules::create_routes(
+        state.configuration_save_lock.clone(),
+        state.ip_rule_store.clone(),
+    ));
+
+    let threat_intelligence_route =
+        warp::path("threat-intelligence").and(threat_intelligence::create_routes(
+            state.configuration_save_lock.clone(),
+            state.threat_store.clone(),
+            http_client.clone(),
+            state.status_sender.clone(),
+        ));
+
+    let categories_route = warp::path("categories").and(categories::create_routes(
+        state.configuration_save_lock.clone(),
+        state.category_store.clone(),
+        http_client.clone(),
+    ));
+
+    let safe_search_route = warp::path("safe-search").and(safe_search::create_routes(
+        state.configuration_save_lock.clone(),
+        state.safe_search_store.clone(),
+    ));
+
+    let url_rewrites_route = warp::path("rewrites").and(url_rewrites::create_routes(
+        state.configuration_save_lock.clone(),
+        state.url_rewrite_store.clone(),
+    ));
+
+    let plugins_route = warp::path("plugins").and(plugins::create_routes(
+        state.configuration_save_lock.clone(),
+        state.plugin_store.clone(),
+    ));
+
+    let scripts_route = warp::path("scripts").and(scripts::create_routes(
+        state.configuration_save_lock.clone(),
+        state.script_store.clone(),
+    ));
+
+    let response_cache_route = warp::path("response-cache").and(response_cache::create_routes(
+        state.configuration_save_lock.clone(),
+        state.response_cache_store.clone(),
+    ));
+
+    let traffic_shaping_route = warp::path("shaping").and(traffic_shaping::create_routes(
+        state.configuration_save_lock.clone(),
+        state.traffic_shaping_store.clone(),
+    ));
+
+    let content_rewrite_rules_route =
+        warp::path("content-rewrite-rules").and(content_rewrite_rules::create_routes(
+            state.configuration_save_lock.clone(),
+            state.content_rewrite_store.clone(),
+        ));
+
+    let injections_route = warp::path("injections").and(injections::create_routes(
+        state.configuration_save_lock.clone(),
+        state.injection_store.clone(),
+    ));
+
+    let notifications_route = warp::path("notifications").and(notifications::create_routes(
+        state.configuration_save_lock.clone(),
+        state.notification_store.clone(),
+    ));
+
+    let config_route = warp::path("config").and(config::create_routes(
+        state.configuration_updater_sender.clone(),
+        state.configuration_save_lock.clone(),
+        state.local_exclusion_store.clone(),
+        state.notify_reload.clone(),
+    ));
+
+    let import_route = warp::path("import").and(import::create_routes(
+        http_client.clone(),
+        state.configuration_updater_sender.clone(),
+        state.configuration_save_lock.clone(),
+        state.local_exclusion_store.clone(),
+    ));
+
+    let options_route = warp::options().map(|| "");
+
+    let filterlists_route = warp::path("filterlists").and(filterlists::create_routes(
+        filterlists::FilterListsCache::default(),
+    ));
+
+    let managed_route = warp::path("managed").and(managed::create_routes());
+
+    let profiles_route = warp::path("profiles").and(profiles::create_routes(
+        state.configuration_updater_sender.clone(),
+        state.configuration_save_lock.clone(),
+        state.local_exclusion_store.clone(),
+    ));
+
+    let ca_route = warp::path("ca").and(ca::create_routes(
+        state.configuration_save_lock.clone(),
+        state.cert_cache.clone(),
+    ));
+
+    let schedules_route = warp::path("schedules").and(schedules::create_routes(
+        state.configuration_save_lock.clone(),
+    ));
+
+    let tls_overrides_route = warp::path("tls-overrides").and(tls_overrides::create_routes(
+        state.configuration_save_lock.clone(),
+        state.upstream_tls_store.clone(),
+    ));
+
+    let hosts_route =
+        warp::path("hosts").and(hosts::create_routes(state.upstream_tls_store.clone()));
+
+    let audit_log = audit::AuditLog::new(crate::configuration::get_audit_log_file());
+
+    let audit_route = warp::path("audit").and(audit::create_routes(audit_log.clone()));
+
+    let session_route =
+        warp::path("session").and(sessions::create_session_routes(state.session_store.clone()));
+
+    let sessions_route =
+        warp::path("sessions").and(sessions::create_routes(state.session_store.clone()));
+
+    let not_found = warp::path::tail()
+        .map(move |tail: Tail| {
+            let tail_str = tail.as_str();
+            log::warn!("Path not found: /api/{}", tail_str);
+            Response::builder()
+                .status(http::StatusCode::NOT_FOUND)
+                .body(
+                    serde_json::to_string(&ApiError {
+                        error: format!("Path not found: /api/{}", tail_str),
+                    })
+                    .unwrap(),
+                )
+                .unwrap()
+        })
+        .boxed();
+
+    // Everything but signing in requires a session when web GUI authentication is enabled.
+    // Changes are logged once they are applied.
+    let protected_routes = sessions::require_session(state.session_store.clone())
+        .and(audit::begin_audit_entry(state.session_store.clone()))
+        .and(
+            events_route
+                .or(statistics_history_route)
+                .or(statistics_filters_route)
+                .or(statistics_performance_route)
+                .or(statistics_rule_performance_route)
+                .or(statistics_top_route)
+                .or(statistics_timeseries_route)
+                .or(statistics_clients_route)
+                .or(statistics_export_route)
+                .or(statistics_reset_route)
+                .or(statistics_route)
+                .or(filters_route)
+                .or(custom_filters_route)
+                .or(exclusions_route)
+                .or(suggested_exclusions_route)
+                .or(blocking_enabled_route)
+                .or(debug_headers_route)
+                .or(log_level_route)
+                .or(site_policies_route)
+                .or(client_grants_route)
+                .or(circuit_breaker_route)
+                .boxed()
+                .or(header_rules_route)
+                .or(cookie_rules_route)
+                .or(ip_rules_route)
+                .or(threat_intelligence_route)
+                .or(safe_search_route)
+                .or(url_rewrites_route)
+                .or(plugins_route)
+                .or(scripts_route)
+                .or(response_cache_route)
+                .or(traffic_shaping_route)
+                .or(categories_route)
+                .or(content_rewrite_rules_route)
+                .or(injections_route)
+                .or(notifications_route)
+                // Keeps the type of the routes within the compiler's recursion limit.
+                .boxed()
+                .or(import_route)
+                .or(config_route)
+                .or(settings_route)
+                .or(filterlists_route)
+                .or(managed_route)
+                .or(profiles_route)
+                .or(schedules_route)
+                .or(ca_route)
+                .or(tls_overrides_route)
+                .or(hosts_route)
+                .or(audit_route)
+                .or(sessions_route)
+                .or(requests_route)
+                .or(test_request_route)
+                .or(system_route)
+                .or(not_found),
+        )
+        .and(audit::with_audit_log(audit_log))
+        .and_then(audit::finish_audit_entry)
+        .recover(sessions::recover_unauthorized);
+
+    let web_base_path = web_base_path.to_string();
+
+    api_path
+        .and(
+            options_route
+                .or(session_route)
+                .or(metrics_route)
+                .or(openapi_route)
+                .or(protected_routes),
+        )
+        .map(move |version, full_path: FullPath, reply| {
+            versions::with_deprecation_headers(reply, version, &web_base_path, full_path.as_str())
+        })
+        .with(def_headers)
+        .boxed()
+}
+
+pub(crate) fn with_local_exclusions_store(
+    local_exclusions_store: LocalExclusionStore,
+) -> impl Filter<Extract = (LocalExclusionStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || local_exclusions_store.clone())
+}
+
+pub(crate) fn with_proxy_authentication_store(
+    proxy_authentication_store: ProxyAuthenticationStore,
+) -> impl Filter<Extract = (ProxyAuthenticationStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || proxy_authentication_store.clone())
+}
+
+pub(crate) fn with_block_responses_store(
+    block_responses_store: BlockResponsesStore,
+) -> impl Filter<Extract = (BlockResponsesStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || block_responses_store.clone())
+}
+
+pub(crate) fn with_client_identification_store(
+    client_identification_store: ClientIdentificationStore,
+) -> impl Filter<Extract = (ClientIdentificationStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || client_identification_store.clone())
+}
+
+pub(crate) fn with_session_store(
+    session_store: SessionStore,
+) -> impl Filter<Extract = (SessionStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || session_store.clone())
+}
+
+pub(crate) fn with_configuration_save_lock(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> impl Filter<Extract = (Arc<tokio::sync::Mutex<()>>,), Error = std::convert::Infallible> + Clone
+{
+    warp::any().map(move || configuration_save_lock.clone())
+}
+
+fn with_site_policy_store(
+    site_policy_store: SitePolicyStore,
+) -> impl Filter<Extract = (SitePolicyStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || site_policy_store.clone())
+}
+
+fn with_adblock_requester(
+    adblock_requester: AdblockRequester,
+) -> impl Filter<Extract = (AdblockRequester,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || adblock_requester.clone())
+}
+
+fn with_plugin_store(
+    plugin_store: PluginStore,
+) -> impl Filter<Extract = (PluginStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || plugin_store.clone())
+}
+
+fn with_blocking_disabled_store(
+    blocking_disabled: BlockingDisabledStore,
+) -> impl Filter<Extract = (BlockingDisabledStore,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || blocking_disabled.clone())
+}
+
+pub(self) fn with_configuration_updater_sender(
+    sender: Sender<Configuration>,
+) -> impl Filter<Extract = (Sender<Configuration>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || sender.clone())
+}
+
+pub(self) fn with_http_client(
+    http_client: reqwest::Client,
+) -> impl Filter<Extract = (reqwest::Client,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || http_client.clone())
+}
+
+fn with_status_sender(
+    status_sender: broadcast::Sender<events::StatusEvent>,
+) -> impl Filter<Extract = (broadcast::Sender<events::StatusEvent>,), Error = std::convert::Infallible>
+       + Clone {
+    warp::any().map(move || status_sender.clone())
+}
+
+pub(self) fn with_notify_reload(
+    notify_reload: Arc<Notify>,
+) -> impl Filter<Extract = (Arc<Notify>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || notify_reload.clone())
+}
+
+pub(crate) fn get_error_response(err: impl std::error::Error) -> Response<String> {
+    log::debug!("Building error response: {:?}", err);
+    Response::builder()
+        .status(http::StatusCode::INTERNAL_SERVER_ERROR)
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .body(
+            serde_json::to_string(&ApiError {
+                error: format!("{:?}", err),
+            })
+            .unwrap(),
+        )
+        .unwrap()
+}
@@ -0,0 +1,62 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, NotificationTarget};
+use crate::notifications::NotificationStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_notifications() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get notifications: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.notifications)))
+}
+
+async fn put_notifications(
+    notifications: Vec<NotificationTarget>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    notification_store: NotificationStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put notifications: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_notifications(notifications, notification_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    notification_store: NotificationStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_notification_store = warp::any().map(move || notification_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_notifications)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_notification_store)
+            .and_then(self::put_notifications))
+        .boxed()
+}
@@ -0,0 +1,1272 @@
+use super::audit::AuditQuery;
+use super::blocking_enabled::{BlockingPauseRequest, BlockingPauseResponse};
+use super::events::{EventHistoryQuery, Subscription};
+use super::exclusions::TemporaryExclusionRequest;
+use super::filterlists::SearchQuery;
+use super::filters::{
+    FilterBatchOperation, FilterEditRequest, FilterPreviewRequest, FilterRequest,
+    FilterStatusChangeRequest, FilterUpdateQuery,
+};
+use super::import::ImportQuery;
+use super::log_level::LogLevel;
+use super::requests::{ReplayRequest, ReplayResponse};
+use super::response_cache::PurgeQuery;
+use super::statistics::{
+    ExportQuery, ExportedBucket, HistoryQuery, RuleProfilingRequest, StatisticsClientMessage,
+    StatisticsMessage, TimeSeriesQuery, TopQuery,
+};
+use super::ApiError;
+use crate::categories::CategoryStatus;
+use crate::configuration::{Filter, FilterPreview, FilterStatus};
+use crate::event_history::TaggedEvent;
+use crate::proxy::exclusions::TemporaryExclusionResponse;
+use crate::proxy::handshake_failures::SuggestedExclusion;
+use crate::proxy::plugins::PluginStatus;
+use crate::proxy::response_cache::{PurgedResponses, ResponseCacheStatus};
+use crate::proxy::scripts::ScriptStatus;
+use crate::proxy::traffic_shaping::TrafficShapingStatus;
+use crate::proxy::url_rewrites::UrlRewriteHits;
+use crate::rule_profiling::RulePerformance;
+use crate::statistics::{
+    ClientStatistics, FilterStatistics, HistoryBucket, PerformanceStatistics, RecordedDecision,
+    TopEntry,
+};
+use crate::system::SystemStatus;
+use crate::threat_intelligence::ThreatFeedStatus;
+use crate::timeseries::TimeSeriesBucket;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+use std::convert::Infallible;
+use uuid::Uuid;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// Body of a request or a response.
+#[derive(Clone, Copy)]
+enum Content {
+    Empty,
+    /// JSON, of any shape when it isn't described.
+    Json(Option<SchemaFn>),
+    PlainText,
+    Binary(&'static str),
+    Form,
+    /// The connection is upgraded to a WebSocket, whose messages are JSON. As a request,
+    /// the messages clients send over it.
+    WebSocket(Option<SchemaFn>),
+}
+
+impl Content {
+    /// Media types of the body, none when there is no body.
+    fn to_media_types(self, generator: &mut SchemaGenerator) -> Option<Value> {
+        let media_types = match self {
+            Content::Empty | Content::WebSocket(_) => return None,
+            Content::Json(schema) => json!({
+                "application/json": {
+                    "schema": schema.map_or_else(|| json!({}), |schema| json!(schema(generator)))
+                }
+            }),
+            Content::PlainText => json!({ "text/plain": { "schema": { "type": "string" } } }),
+            Content::Binary(media_type) => json!({
+                media_type: { "schema": { "type": "string", "format": "binary" } }
+            }),
+            Content::Form => json!({ "application/x-www-form-urlencoded": { "schema": {} } }),
+        };
+
+        Some(media_types)
+    }
+}
+
+/// A route of the API, as described in the OpenAPI document.
+struct Operation {
+    method: &'static str,
+    /// Path relative to `/api/v1`, with its parameters between braces.
+    path: &'static str,
+    operation_id: &'static str,
+    summary: &'static str,
+    /// Path parameters are strings unless their schema is given.
+    path_parameters: Vec<(&'static str, SchemaFn)>,
+    query: Option<SchemaFn>,
+    request: Content,
+    response: Content,
+}
+
+impl Operation {
+    fn new(
+        method: &'static str,
+        path: &'static str,
+        operation_id: &'static str,
+        summary: &'static str,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            operation_id,
+            summary,
+            path_parameters: Vec::new(),
+            query: None,
+            request: Content::Empty,
+            response: Content::Json(None),
+        }
+    }
+
+    fn path_parameter<T: JsonSchema>(mut self, name: &'static str) -> Self {
+        self.path_parameters
+            .push((name, |generator| generator.subschema_for::<T>()));
+        self
+    }
+
+    /// Fields of `T` are the query parameters of the route.
+    fn query<T: JsonSchema>(mut self) -> Self {
+        self.query = Some(|generator| generator.root_schema_for::<T>().schema.into());
+        self
+    }
+
+    fn request<T: JsonSchema>(self) -> Self {
+        self.request_content(Content::Json(Some(|generator| {
+            generator.subschema_for::<T>()
+        })))
+    }
+
+    fn request_content(mut self, content: Content) -> Self {
+        self.request = content;
+        self
+    }
+
+    fn response<T: JsonSchema>(self) -> Self {
+        self.response_content(Content::Json(Some(|generator| {
+            generator.subschema_for::<T>()
+        })))
+    }
+
+    fn response_content(mut self, content: Content) -> Self {
+        self.response = content;
+        self
+    }
+
+    fn get_parameters(&self, generator: &mut SchemaGenerator) -> Vec<Value> {
+        let mut parameters = Vec::new();
+
+        for name in self
+            .path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        {
+            let schema = match self.path_parameters.iter().find(|(n, _)| *n == name) {
+                Some((_name, schema)) => schema(generator),
+                None => generator.subschema_for::<String>(),
+            };
+
+            parameters.push(json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": schema,
+            }));
+        }
+
+        let query = match self.query {
+            Some(query) => query(generator).into_object(),
+            None => return parameters,
+        };
+
+        if let Some(object) = query.object {
+            for (name, schema) in object.properties {
+                let mut parameter = json!({
+                    "name": name,
+                    "in": "query",
+                    "required": object.required.contains(&name),
+                    "schema": schema,
+                });
+
+                if let Some(description) = schema
+                    .into_object()
+                    .metadata
+                    .and_then(|metadata| metadata.description)
+                {
+                    parameter["description"] = json!(description);
+                }
+
+                parameters.push(parameter);
+            }
+        }
+
+        parameters
+    }
+
+    fn to_json(&self, generator: &mut SchemaGenerator) -> Value {
+        let tag = self.path.split('/').nth(1).unwrap_or_default();
+
+        let mut operation = json!({
+            "operationId": self.operation_id,
+            "summary": self.summary,
+            "tags": [tag],
+            "parameters": self.get_parameters(generator),
+            "responses": {
+                "default": {
+                    "description": "Error",
+                    "content": Content::Json(Some(|generator| {
+                        generator.subschema_for::<ApiError>()
+                    })).to_media_types(generator),
+                },
+            },
+        });
+
+        if let Some(media_types) = self.request.to_media_types(generator) {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": media_types,
+            });
+        }
+
+        let status = self.get_response_status();
+        operation["responses"][status] = json!({ "description": self.get_response_description() });
+
+        if let Some(media_types) = self.response.to_media_types(generator) {
+            operation["responses"][status]["content"] = media_types;
+        }
+
+        if let Content::WebSocket(Some(schema)) = self.response {
+            operation["x-websocket-message"] = json!(schema(generator));
+        }
+
+        if let Content::WebSocket(Some(schema)) = self.request {
+            operation["x-websocket-client-message"] = json!(schema(generator));
+        }
+
+        operation
+    }
+
+    fn get_response_status(&self) -> &'static str {
+        match self.response {
+            Content::WebSocket(_) => "101",
+            _ => "2XX",
+        }
+    }
+
+    fn get_response_description(&self) -> &'static str {
+        match self.response {
+            Content::WebSocket(_) => "Switching to the WebSocket protocol",
+            Content::Empty => "Success, with no content",
+            _ => "Success",
+        }
+    }
+}
+
+fn get_operations() -> Vec<Operation> {
+    vec![
+        Operation::new("get", "/openapi.json", "get_openapi", "Describes the API")
+            .response_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/events",
+            "events",
+            "Streams proxied requests and status events",
+        )
+        .request_content(Content::WebSocket(Some(|generator| {
+            generator.subschema_for::<Subscription>()
+        })))
+        .response_content(Content::WebSocket(None)),
+        Operation::new(
+            "get",
+            "/events/history",
+            "get_event_history",
+            "Lists recent blocked requests and status events",
+        )
+        .query::<EventHistoryQuery>()
+        .response::<Vec<TaggedEvent>>(),
+        Operation::new(
+            "get",
+            "/session",
+            "get_session_status",
+            "Returns whether the session is signed in",
+        ),
+        Operation::new("post", "/session", "sign_in", "Signs in to the web GUI")
+            .request_content(Content::Json(None)),
+        Operation::new("delete", "/session", "sign_out", "Signs out of the web GUI")
+            .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/sessions",
+            "get_sessions",
+            "Lists the signed in sessions",
+        ),
+        Operation::new(
+            "delete",
+            "/sessions",
+            "revoke_other_sessions",
+            "Signs out every other session",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "delete",
+            "/sessions/{id}",
+            "revoke_session",
+            "Signs out a session",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/metrics",
+            "get_metrics",
+            "Exports metrics in the Prometheus format",
+        )
+        .response_content(Content::PlainText),
+        Operation::new(
+            "get",
+            "/statistics",
+            "statistics",
+            "Streams the statistics as they change",
+        )
+        .request_content(Content::WebSocket(Some(|generator| {
+            generator.subschema_for::<StatisticsClientMessage>()
+        })))
+        .response_content(Content::WebSocket(Some(|generator| {
+            generator.subschema_for::<StatisticsMessage>()
+        }))),
+        Operation::new(
+            "get",
+            "/statistics/history",
+            "get_history",
+            "Returns hourly statistics",
+        )
+        .query::<HistoryQuery>()
+        .response::<Vec<HistoryBucket>>(),
+        Operation::new(
+            "get",
+            "/statistics/filters",
+            "get_filter_statistics",
+            "Returns the requests blocked by each filter",
+        )
+        .response::<FilterStatistics>(),
+        Operation::new(
+            "get",
+            "/statistics/performance",
+            "get_performance",
+            "Returns the latency added to requests by each stage of proxying them",
+        )
+        .response::<PerformanceStatistics>(),
+        Operation::new(
+            "get",
+            "/statistics/rule-performance",
+            "get_rule_performance",
+            "Returns the time each filter list and its most expensive regex rules take to match requests, while rule profiling is or was enabled",
+        )
+        .response::<RulePerformance>(),
+        Operation::new(
+            "put",
+            "/statistics/rule-performance",
+            "put_rule_profiling",
+            "Starts profiling filter rules over, or stops profiling them",
+        )
+        .request::<RuleProfilingRequest>(),
+        Operation::new(
+            "get",
+            "/system/status",
+            "get_system_status",
+            "Returns the memory, connections and tasks of Privaxy and the state of its blocking engines",
+        )
+        .response::<SystemStatus>(),
+        Operation::new(
+            "get",
+            "/statistics/top",
+            "get_top",
+            "Returns the most blocked or allowed domains, or the most blocked tracking companies",
+        )
+        .query::<TopQuery>()
+        .response::<Vec<TopEntry>>(),
+        Operation::new(
+            "get",
+            "/statistics/timeseries",
+            "get_timeseries",
+            "Returns requests and response bytes per minute, hour or day",
+        )
+        .query::<TimeSeriesQuery>()
+        .response::<Vec<TimeSeriesBucket>>(),
+        Operation::new(
+            "get",
+            "/statistics/clients",
+            "get_client_statistics",
+            "Returns the requests of each client, most blocked first",
+        )
+        .response::<Vec<ClientStatistics>>(),
+        Operation::new(
+            "get",
+            "/statistics/export",
+            "export_statistics",
+            "Exports hourly statistics as JSON or CSV",
+        )
+        .query::<ExportQuery>()
+        .response::<Vec<ExportedBucket>>(),
+        Operation::new(
+            "post",
+            "/statistics/reset",
+            "reset_statistics",
+            "Resets statistics",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/requests/{id}/decision",
+            "get_decision",
+            "Explains why a recent request was blocked or allowed",
+        )
+        .path_parameter::<u64>("id")
+        .response::<RecordedDecision>(),
+        Operation::new(
+            "post",
+            "/requests/{id}/replay",
+            "replay_request",
+            "Sends a recent request again, checked against the filters as they are now",
+        )
+        .path_parameter::<u64>("id")
+        .request::<ReplayRequest>()
+        .response::<ReplayResponse>(),
+        Operation::new(
+            "post",
+            "/test-request",
+            "test_request",
+            "Tests whether a request would be blocked",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/filters",
+            "get_filters_configuration",
+            "Lists the filters",
+        )
+        .response::<Vec<Filter>>(),
+        Operation::new("post", "/filters", "add_filter", "Subscribes to a filter")
+            .request::<FilterRequest>()
+            .response::<Filter>(),
+        Operation::new(
+            "put",
+            "/filters",
+            "change_filter_status",
+            "Enables or disables filters",
+        )
+        .request::<Vec<FilterStatusChangeRequest>>()
+        .response_content(Content::Empty),
+        Operation::new(
+            "post",
+            "/filters/update",
+            "update_filters",
+            "Downloads the filters again",
+        )
+        .query::<FilterUpdateQuery>()
+        .response_content(Content::Empty),
+        Operation::new(
+            "post",
+            "/filters/batch",
+            "apply_filter_batch",
+            "Applies several operations to the filters at once",
+        )
+        .request::<Vec<FilterBatchOperation>>()
+        .response::<Vec<Filter>>(),
+        Operation::new(
+            "post",
+            "/filters/preview",
+            "preview_filter",
+            "Describes a filter before subscribing to it",
+        )
+        .request::<FilterPreviewRequest>()
+        .response::<FilterPreview>(),
+        Operation::new(
+            "get",
+            "/filters/{id}/status",
+            "get_filter_status",
+            "Returns the health of a filter",
+        )
+        .path_parameter::<Uuid>("id")
+        .response::<FilterStatus>(),
+        Operation::new(
+            "patch",
+            "/filters/{id}",
+            "edit_filter",
+            "Renames a filter or changes its URL",
+        )
+        .path_parameter::<Uuid>("id")
+        .request::<FilterEditRequest>()
+        .response_content(Content::Empty),
+        Operation::new(
+            "delete",
+            "/filters/{id}",
+            "delete_filter",
+            "Unsubscribes from a filter",
+        )
+        .path_parameter::<Uuid>("id")
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/custom-filters",
+            "get_custom_filters",
+            "Returns the custom filters, one per line",
+        )
+        .response::<String>(),
+        Operation::new(
+            "put",
+            "/custom-filters",
+            "put_custom_filters",
+            "Replaces the custom filters",
+        )
+        .request::<String>()
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/exclusions",
+            "get_exclusions",
+            "Returns the excluded hosts, one per line",
+        )
+        .response::<String>(),
+        Operation::new(
+            "put",
+            "/exclusions",
+            "put_exclusions",
+            "Replaces the excluded hosts",
+        )
+        .request::<String>()
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/exclusions/temporary",
+            "get_temporary_exclusions",
+            "Lists the hosts excluded for a while",
+        )
+        .response::<Vec<TemporaryExclusionResponse>>(),
+        Operation::new(
+            "post",
+            "/exclusions/temporary",
+            "post_temporary_exclusion",
+            "Excludes a host for a while",
+        )
+        .request::<TemporaryExclusionRequest>()
+        .response::<TemporaryExclusionResponse>(),
+        Operation::new(
+            "delete",
+            "/exclusions/temporary/{host}",
+            "delete_temporary_exclusion",
+            "Ends the temporary exclusion of a host",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/suggested-exclusions",
+            "get_suggested_exclusions",
+            "Lists the hosts clients repeatedly failed handshakes with",
+        )
+        .response::<Vec<SuggestedExclusion>>(),
+        Operation::new(
+            "post",
+            "/suggested-exclusions/{host}",
+            "apply_suggested_exclusion",
+            "Excludes a suggested host",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "delete",
+            "/suggested-exclusions/{host}",
+            "dismiss_suggested_exclusion",
+            "Dismisses the suggestion to exclude a host",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/settings/network",
+            "get_network_settings",
+            "Returns the network settings",
+        ),
+        Operation::new(
+            "put",
+            "/settings/network",
+            "put_network_settings",
+            "Changes the network settings",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/settings/ca-certificate",
+            "get_ca_certificates",
+            "Returns the CA certificate and key",
+        ),
+        Operation::new(
+            "put",
+            "/settings/ca-certificate",
+            "put_ca_certificates",
+            "Replaces the CA certificate and key",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "post",
+            "/settings/ca-certificate/validate",
+            "validate_ca_certificates",
+            "Checks a CA certificate and key without saving them",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/settings/proxy-authentication",
+            "get_proxy_authentication",
+            "Returns the proxy authentication settings",
+        ),
+        Operation::new(
+            "put",
+            "/settings/proxy-authentication",
+            "put_proxy_authentication",
+            "Changes the proxy authentication settings",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/settings/block-responses",
+            "get_block_responses",
+            "Returns how blocked requests are answered",
+        ),
+        Operation::new(
+            "put",
+            "/settings/block-responses",
+            "put_block_responses",
+            "Changes how blocked requests are answered",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/settings/client-identification",
+            "get_client_identification",
+            "Returns how clients are identified",
+        ),
+        Operation::new(
+            "put",
+            "/settings/client-identification",
+            "put_client_identification",
+            "Changes how clients are identified",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/settings/web-authentication",
+            "get_web_authentication",
+            "Returns the web GUI authentication settings",
+        ),
+        Operation::new(
+            "put",
+            "/settings/web-authentication",
+            "put_web_authentication",
+            "Changes the web GUI authentication settings",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/blocking-enabled",
+            "get_blocking_enabled",
+            "Returns whether blocking is enabled",
+        ),
+        Operation::new(
+            "put",
+            "/blocking-enabled",
+            "put_blocking_enabled",
+            "Enables or disables blocking",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/blocking-enabled/pause",
+            "get_blocking_pause",
+            "Returns when paused blocking is enabled again",
+        )
+        .response::<BlockingPauseResponse>(),
+        Operation::new(
+            "post",
+            "/blocking-enabled/pause",
+            "post_blocking_pause",
+            "Disables blocking for a while",
+        )
+        .request::<BlockingPauseRequest>()
+        .response::<BlockingPauseResponse>(),
+        Operation::new(
+            "get",
+            "/debug-headers",
+            "get_debug_headers_enabled",
+            "Returns whether debug headers are added to responses",
+        ),
+        Operation::new(
+            "put",
+            "/debug-headers",
+            "put_debug_headers_enabled",
+            "Enables or disables debug headers",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/log-level",
+            "get_log_level",
+            "Returns the filter logs are written with",
+        )
+        .response::<LogLevel>(),
+        Operation::new(
+            "put",
+            "/log-level",
+            "put_log_level",
+            "Changes the filter logs are written with until privaxy restarts",
+        )
+        .request::<LogLevel>(),
+        Operation::new(
+            "get",
+            "/site-policies",
+            "get_site_policies",
+            "Lists the site policies",
+        ),
+        Operation::new(
+            "put",
+            "/site-policies/{site}",
+            "put_site_policy",
+            "Sets the policy of a site",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "delete",
+            "/site-policies/{site}",
+            "delete_site_policy",
+            "Removes the policy of a site",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/client-grants",
+            "get_client_grants",
+            "Lists the clients allowed to bypass blocking",
+        ),
+        Operation::new(
+            "get",
+            "/client-grants/audit",
+            "get_audit_trail",
+            "Lists the changes made to client grants",
+        ),
+        Operation::new(
+            "post",
+            "/client-grants",
+            "post_client_grant",
+            "Allows a client to bypass blocking",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "delete",
+            "/client-grants/{id}",
+            "delete_client_grant",
+            "Revokes a client grant",
+        )
+        .path_parameter::<u64>("id")
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/circuit-breaker",
+            "get_circuit_breaker_status",
+            "Lists the domains whose requests are failing",
+        ),
+        Operation::new(
+            "delete",
+            "/circuit-breaker/{domain}",
+            "delete_circuit",
+            "Closes the circuit of a domain",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "get",
+            "/header-rules",
+            "get_header_rules",
+            "Lists the header rules",
+        ),
+        Operation::new(
+            "put",
+            "/header-rules",
+            "put_header_rules",
+            "Replaces the header rules",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/header-rules/referer",
+            "get_referer_trimming",
+            "Returns how referers are trimmed",
+        ),
+        Operation::new(
+            "put",
+            "/header-rules/referer",
+            "put_referer_trimming",
+            "Changes how referers are trimmed",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/header-rules/user-agent",
+            "get_user_agent_rules",
+            "Lists the user agent rules",
+        ),
+        Operation::new(
+            "put",
+            "/header-rules/user-agent",
+            "put_user_agent_rules",
+            "Replaces the user agent rules",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/cookie-rules",
+            "get_cookie_rules",
+            "Lists the cookie rules",
+        ),
+        Operation::new(
+            "put",
+            "/cookie-rules",
+            "put_cookie_rules",
+            "Replaces the cookie rules",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new("get", "/ip-rules", "get_ip_rules", "Lists the IP rules"),
+        Operation::new("put", "/ip-rules", "put_ip_rules", "Replaces the IP rules")
+            .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/threat-intelligence",
+            "get_threat_intelligence",
+            "Returns the threat intelligence settings and feeds",
+        ),
+        Operation::new(
+            "put",
+            "/threat-intelligence",
+            "put_threat_intelligence",
+            "Replaces the threat intelligence settings and feeds",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/threat-intelligence/feeds",
+            "get_threat_feeds",
+            "Lists the threat feeds along with their download state",
+        )
+        .response::<Vec<ThreatFeedStatus>>(),
+        Operation::new(
+            "post",
+            "/threat-intelligence/update",
+            "update_threat_feeds",
+            "Downloads the enabled threat feeds right away",
+        )
+        .response::<Vec<ThreatFeedStatus>>(),
+        Operation::new(
+            "get",
+            "/safe-search",
+            "get_safe_search",
+            "Returns the search engines SafeSearch is enforced on",
+        ),
+        Operation::new(
+            "put",
+            "/safe-search",
+            "put_safe_search",
+            "Replaces the search engines SafeSearch is enforced on",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/categories",
+            "get_categories",
+            "Returns the content categories and how often their lists are updated",
+        ),
+        Operation::new(
+            "put",
+            "/categories",
+            "put_categories",
+            "Replaces the content categories and how often their lists are updated",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/categories/lists",
+            "get_category_lists",
+            "Lists the content categories along with the download state of their lists",
+        )
+        .response::<Vec<CategoryStatus>>(),
+        Operation::new(
+            "post",
+            "/categories/update",
+            "update_category_lists",
+            "Downloads the lists of the enabled content categories right away",
+        )
+        .response::<Vec<CategoryStatus>>(),
+        Operation::new(
+            "get",
+            "/rewrites",
+            "get_url_rewrites",
+            "Lists the rules redirecting or rewriting requested URLs",
+        ),
+        Operation::new(
+            "put",
+            "/rewrites",
+            "put_url_rewrites",
+            "Replaces the rules redirecting or rewriting requested URLs",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/rewrites/hits",
+            "get_url_rewrite_hits",
+            "Returns how often each URL rewrite applied since Privaxy started",
+        )
+        .response::<Vec<UrlRewriteHits>>(),
+        Operation::new(
+            "get",
+            "/plugins",
+            "get_plugins",
+            "Lists the plugins and the limits their hooks run within",
+        ),
+        Operation::new(
+            "put",
+            "/plugins",
+            "put_plugins",
+            "Replaces the plugins and the limits their hooks run within",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/plugins/status",
+            "get_plugin_statuses",
+            "Lists the plugins along with their hooks and how their calls fared",
+        )
+        .response::<Vec<PluginStatus>>(),
+        Operation::new(
+            "put",
+            "/plugins/{name}/module",
+            "put_plugin_module",
+            "Installs or replaces the WebAssembly module of a plugin",
+        )
+        .path_parameter::<String>("name")
+        .request_content(Content::Binary("application/wasm")),
+        Operation::new(
+            "delete",
+            "/plugins/{name}",
+            "delete_plugin",
+            "Removes a plugin along with its module",
+        )
+        .path_parameter::<String>("name"),
+        Operation::new(
+            "get",
+            "/scripts",
+            "get_scripts",
+            "Returns whether scripts are enabled and the limits their calls run within",
+        ),
+        Operation::new(
+            "put",
+            "/scripts",
+            "put_scripts",
+            "Replaces the script settings, loading scripts again",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/scripts/status",
+            "get_script_statuses",
+            "Lists the scripts of the scripts directory along with how their calls fared",
+        )
+        .response::<Vec<ScriptStatus>>(),
+        Operation::new(
+            "get",
+            "/response-cache",
+            "get_response_cache",
+            "Returns whether the response cache is enabled and its size limits",
+        ),
+        Operation::new(
+            "put",
+            "/response-cache",
+            "put_response_cache",
+            "Replaces the response cache settings, evicting responses past its new size",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/response-cache/status",
+            "get_response_cache_status",
+            "Returns how many responses are cached and the space they take",
+        )
+        .response::<ResponseCacheStatus>(),
+        Operation::new(
+            "delete",
+            "/response-cache/entries",
+            "purge_response_cache",
+            "Removes the cached responses of a host, or every cached response",
+        )
+        .query::<PurgeQuery>()
+        .response::<PurgedResponses>(),
+        Operation::new(
+            "get",
+            "/shaping",
+            "get_traffic_shaping",
+            "Returns the bandwidth limits of tunnels and proxied bodies",
+        ),
+        Operation::new(
+            "put",
+            "/shaping",
+            "put_traffic_shaping",
+            "Replaces the bandwidth limits, starting their rates and counters over",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/shaping/status",
+            "get_traffic_shaping_status",
+            "Returns how much traffic each limit carried and for how long it was held",
+        )
+        .response::<TrafficShapingStatus>(),
+        Operation::new(
+            "get",
+            "/content-rewrite-rules",
+            "get_content_rewrite_rules",
+            "Lists the content rewrite rules",
+        ),
+        Operation::new(
+            "put",
+            "/content-rewrite-rules",
+            "put_content_rewrite_rules",
+            "Replaces the content rewrite rules",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/injections",
+            "get_injections",
+            "Lists the injections",
+        ),
+        Operation::new(
+            "put",
+            "/injections",
+            "put_injections",
+            "Replaces the injections",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/notifications",
+            "get_notifications",
+            "Lists the webhooks events are posted to",
+        ),
+        Operation::new(
+            "put",
+            "/notifications",
+            "put_notifications",
+            "Replaces the webhooks events are posted to",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/config/export",
+            "export_configuration",
+            "Exports the configuration",
+        ),
+        Operation::new(
+            "post",
+            "/config/import",
+            "import_configuration",
+            "Replaces the configuration",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "post",
+            "/import",
+            "import",
+            "Imports the configuration of another blocker",
+        )
+        .query::<ImportQuery>()
+        .request::<String>(),
+        Operation::new(
+            "get",
+            "/filterlists/search",
+            "search",
+            "Searches the filter lists of filterlists.com",
+        )
+        .query::<SearchQuery>(),
+        Operation::new(
+            "get",
+            "/filterlists/list",
+            "get_filters",
+            "Lists the filter lists of filterlists.com",
+        ),
+        Operation::new(
+            "get",
+            "/filterlists/list/{id}",
+            "get_filter",
+            "Describes a filter list of filterlists.com",
+        )
+        .path_parameter::<u32>("id"),
+        Operation::new(
+            "get",
+            "/filterlists/syntaxes",
+            "get_syntaxes",
+            "Lists the syntaxes of filterlists.com",
+        ),
+        Operation::new(
+            "get",
+            "/filterlists/languages",
+            "get_languages",
+            "Lists the languages of filterlists.com",
+        ),
+        Operation::new(
+            "get",
+            "/filterlists/tags",
+            "get_tags",
+            "Lists the tags of filterlists.com",
+        ),
+        Operation::new(
+            "get",
+            "/filterlists/licenses",
+            "get_licenses",
+            "Lists the licenses of filterlists.com",
+        ),
+        Operation::new(
+            "get",
+            "/managed",
+            "get_managed",
+            "Returns the settings enforced by the managed configuration",
+        ),
+        Operation::new("get", "/profiles", "get_profiles", "Lists the profiles"),
+        Operation::new(
+            "post",
+            "/profiles",
+            "post_profile",
+            "Saves the configuration as a profile",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "put",
+            "/profiles/active",
+            "put_active_profile",
+            "Switches to a profile",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "delete",
+            "/profiles/{name}",
+            "delete_profile",
+            "Removes a profile",
+        )
+        .response_content(Content::Empty),
+        Operation::new(
+            "post",
+            "/ca/rotate",
+            "rotate_ca",
+            "Replaces the CA with a new one",
+        ),
+        Operation::new(
+            "post",
+            "/ca/import",
+            "import_ca",
+            "Replaces the CA with an existing one",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/ca/certificate.pem",
+            "get_ca_certificate_pem",
+            "Downloads the CA certificate in the PEM format",
+        )
+        .response_content(Content::Binary("application/x-pem-file")),
+        Operation::new(
+            "get",
+            "/ca/certificate.der",
+            "get_ca_certificate_der",
+            "Downloads the CA certificate in the DER format",
+        )
+        .response_content(Content::Binary("application/x-x509-ca-cert")),
+        Operation::new(
+            "post",
+            "/ca/certificate.p12",
+            "get_ca_certificate_p12",
+            "Downloads the CA certificate in the PKCS #12 format",
+        )
+        .request_content(Content::Form)
+        .response_content(Content::Binary("application/x-pkcs12")),
+        Operation::new(
+            "get",
+            "/ca/install-instructions",
+            "get_install_instructions",
+            "Explains how to trust the CA on each platform",
+        ),
+        Operation::new("get", "/schedules", "get_schedules", "Lists the schedules"),
+        Operation::new(
+            "put",
+            "/schedules",
+            "put_schedules",
+            "Replaces the schedules",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/tls-overrides",
+            "get_tls_overrides",
+            "Returns how the certificates of servers are verified",
+        ),
+        Operation::new(
+            "put",
+            "/tls-overrides",
+            "put_tls_overrides",
+            "Changes the hosts whose invalid certificates are accepted",
+        )
+        .request_content(Content::Json(None)),
+        Operation::new(
+            "get",
+            "/hosts/{host}/certificate",
+            "get_certificate",
+            "Returns the certificates a server last presented",
+        ),
+        Operation::new(
+            "get",
+            "/audit",
+            "get_audit_entries",
+            "Lists the changes made through the API",
+        )
+        .query::<AuditQuery>(),
+    ]
+}
+
+/// OpenAPI 3 description of the API, for clients to be generated from.
+fn get_openapi_document(server_url: &str) -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+
+    for operation in get_operations() {
+        let path_item = paths.entry(operation.path).or_insert_with(|| json!({}));
+
+        path_item[operation.method] = operation.to_json(&mut generator);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Privaxy",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": server_url }],
+        "paths": paths,
+        "components": { "schemas": generator.definitions() },
+    })
+}
+
+async fn get_openapi(openapi_document: String) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(
+        Response::builder()
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(openapi_document),
+    ))
+}
+
+/// The document is built once, routes don't change while Privaxy runs.
+pub(super) fn create_routes(web_base_path: &str) -> BoxedFilter<(impl warp::Reply,)> {
+    let openapi_document = get_openapi_document(&format!("{web_base_path}api/v1")).to_string();
+
+    warp::get()
+        .and(warp::path("openapi.json"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || openapi_document.clone()))
+        .and_then(self::get_openapi)
+        .boxed()
+}
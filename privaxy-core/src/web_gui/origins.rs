@@ -0,0 +1,160 @@
+use super::ApiError;
+use std::net::IpAddr;
+use std::sync::Arc;
+use warp::http::Response;
+use warp::{Filter as RouteFilter, Rejection};
+
+#[derive(Debug)]
+struct HostNotAllowed;
+
+impl warp::reject::Reject for HostNotAllowed {}
+
+/// Removes the port of a `Host` header, and the brackets of IPv6 addresses.
+fn get_host_name(host: &str) -> &str {
+    match host.strip_prefix('[') {
+        Some(host) => host.split(']').next().unwrap_or(host),
+        None => host.split(':').next().unwrap_or(host),
+    }
+}
+
+fn is_allowed_host(host: &str, allowed_hosts: &[String]) -> bool {
+    let host_name = get_host_name(host).to_lowercase();
+
+    // Pages of other sites can't be made to resolve to an IP address or to `localhost`.
+    host_name.parse::<IpAddr>().is_ok()
+        || host_name == "localhost"
+        || host_name.ends_with(".localhost")
+        || allowed_hosts.contains(&host_name)
+}
+
+/// Refuses requests for hosts the web GUI isn't served at, against DNS rebinding: a site
+/// pointing its own domain at the address Privaxy listens on would otherwise be able to
+/// call the API from browsers as if it was the web GUI.
+pub(super) fn validate_host(
+    allowed_hosts: Vec<String>,
+) -> impl RouteFilter<Extract = (), Error = Rejection> + Clone {
+    let allowed_hosts = Arc::new(allowed_hosts);
+
+    warp::header::optional::<String>("host")
+        .and_then(move |host: Option<String>| {
+            let allowed_hosts = allowed_hosts.clone();
+
+            async move {
+                match host {
+                    Some(host) if !is_allowed_host(&host, &allowed_hosts) => {
+                        log::warn!("Refused web GUI request for host {host}");
+
+                        Err(warp::reject::custom(HostNotAllowed))
+                    }
+                    _ => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Matches requests issued by the web GUI itself, whose origin is the host they are sent
+/// to. Requests from other origins are left to the CORS policy.
+pub(super) fn same_origin() -> impl RouteFilter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("origin")
+        .and(warp::header::optional::<String>("host"))
+        .and_then(|origin: Option<String>, host: Option<String>| async move {
+            let origin = match origin {
+                Some(origin) => origin,
+                None => return Ok(()),
+            };
+
+            let is_same_origin = url::Url::parse(&origin).ok().is_some_and(|origin| {
+                let origin_host = match (origin.host_str(), origin.port()) {
+                    (Some(host), Some(port)) => format!("{host}:{port}"),
+                    (Some(host), None) => host.to_string(),
+                    (None, _) => return false,
+                };
+
+                host.is_some_and(|host| host.eq_ignore_ascii_case(&origin_host))
+            });
+
+            if is_same_origin {
+                Ok(())
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .untuple_one()
+}
+
+pub(super) async fn recover_host_not_allowed(
+    rejection: Rejection,
+) -> Result<Response<String>, Rejection> {
+    if rejection.find::<HostNotAllowed>().is_none() {
+        return Err(rejection);
+    }
+
+    Ok(Response::builder()
+        .status(http::StatusCode::FORBIDDEN)
+        .body(
+            serde_json::to_string(&ApiError {
+                error: "Host not allowed, add it to the allowed hosts of the network settings"
+                    .to_string(),
+            })
+            .unwrap(),
+        )
+        .unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_allowed_hosts() -> Vec<String> {
+        vec!["privaxy.lan".to_string()]
+    }
+
+    #[test]
+    fn ip_addresses_are_allowed() {
+        assert!(is_allowed_host("192.168.1.10:8200", &[]));
+        assert!(is_allowed_host("[::1]:8200", &[]));
+        assert!(is_allowed_host("[fe80::1]", &[]));
+    }
+
+    #[test]
+    fn localhost_is_allowed() {
+        assert!(is_allowed_host("localhost:8200", &[]));
+        assert!(is_allowed_host("LOCALHOST", &[]));
+        assert!(is_allowed_host("privaxy.localhost:8200", &[]));
+        assert!(!is_allowed_host("localhost.example.com", &[]));
+        assert!(!is_allowed_host("notlocalhost", &[]));
+    }
+
+    #[test]
+    fn allowed_hosts_ignore_the_port_and_case() {
+        assert!(is_allowed_host("privaxy.lan:8200", &get_allowed_hosts()));
+        assert!(is_allowed_host("Privaxy.LAN", &get_allowed_hosts()));
+        assert!(!is_allowed_host(
+            "attacker.example.com",
+            &get_allowed_hosts()
+        ));
+        assert!(!is_allowed_host("sub.privaxy.lan", &get_allowed_hosts()));
+    }
+
+    #[tokio::test]
+    async fn requests_for_other_hosts_are_rejected() {
+        let filter = validate_host(get_allowed_hosts());
+
+        assert!(warp::test::request()
+            .header("host", "privaxy.lan:8200")
+            .filter(&filter)
+            .await
+            .is_ok());
+        assert!(warp::test::request()
+            .header("host", "[::1]:8200")
+            .filter(&filter)
+            .await
+            .is_ok());
+        assert!(warp::test::request()
+            .header("host", "attacker.example.com")
+            .filter(&filter)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use super::{get_error_response, with_configuration_save_lock, with_plugin_store, ApiError};
+use crate::configuration::{
+    get_plugins_directory, is_valid_plugin_name, Configuration, Plugin, Plugins,
+};
+use crate::proxy::plugins::{get_plugin_file, PluginStore};
+use hyper::body::Bytes;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+/// Largest module that can be installed.
+const MAX_PLUGIN_MODULE_SIZE: u64 = 16 * 1024 * 1024;
+
+fn get_bad_request_response(error: &str) -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder().status(StatusCode::BAD_REQUEST).body(
+            serde_json::to_string(&ApiError {
+                error: error.to_string(),
+            })
+            .unwrap(),
+        ),
+    )
+}
+
+async fn get_plugins() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get plugins: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.plugins)))
+}
+
+async fn put_plugins(
+    plugins: Plugins,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    plugin_store: PluginStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put plugins: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration.set_plugins(plugins, plugin_store).await {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_statuses(plugin_store: PluginStore) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&plugin_store.get_statuses())))
+}
+
+/// Installs or replaces the module of a plugin, adding the plugin to the enabled ones
+/// when it is new.
+async fn put_module(
+    name: String,
+    module: Bytes,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    plugin_store: PluginStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if !is_valid_plugin_name(&name) {
+        return Ok(get_bad_request_response(&format!(
+            "invalid plugin name: {:?}",
+            name
+        )));
+    }
+
+    if let Err(err) = plugin_store.validate_module(&module) {
+        return Ok(get_bad_request_response(&format!(
+            "invalid module: {}",
+            err
+        )));
+    }
+
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to install plugin: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    let written = match tokio::fs::create_dir_all(get_plugins_directory()).await {
+        Ok(()) => tokio::fs::write(get_plugin_file(&name), &module).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = written {
+        log::error!("Failed to install plugin {name}: {err}");
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    let mut plugins = configuration.plugins.clone();
+    if !plugins.plugins.iter().any(|plugin| plugin.name == name) {
+        plugins.plugins.push(Plugin {
+            name,
+            enabled: true,
+        });
+    }
+
+    // Saving reloads the modules of enabled plugins, picking up the new one.
+    if let Err(err) = configuration.set_plugins(plugins, plugin_store).await {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn delete_plugin(
+    name: String,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    plugin_store: PluginStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if !is_valid_plugin_name(&name) {
+        return Ok(Box::new(StatusCode::NOT_FOUND));
+    }
+
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to remove plugin: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    let mut plugins = configuration.plugins.clone();
+    plugins.plugins.retain(|plugin| plugin.name != name);
+    if plugins == configuration.plugins {
+        return Ok(Box::new(StatusCode::NOT_FOUND));
+    }
+
+    if let Err(err) = configuration.set_plugins(plugins, plugin_store).await {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    match tokio::fs::remove_file(get_plugin_file(&name)).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => log::warn!("Unable to remove the module of plugin {name}: {err}"),
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    plugin_store: PluginStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_plugins)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and(with_plugin_store(plugin_store.clone()))
+            .and_then(self::put_plugins))
+        .or(warp::get()
+            .and(warp::path("status"))
+            .and(warp::path::end())
+            .and(with_plugin_store(plugin_store.clone()))
+            .and_then(self::get_statuses))
+        .or(warp::put()
+            .and(warp::path::param())
+            .and(warp::path("module"))
+            .and(warp::path::end())
+            .and(warp::body::content_length_limit(MAX_PLUGIN_MODULE_SIZE))
+            .and(warp::body::bytes())
+            .and(with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and(with_plugin_store(plugin_store.clone()))
+            .and_then(self::put_module))
+        .or(warp::delete()
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_plugin_store(plugin_store))
+            .and_then(self::delete_plugin))
+        .boxed()
+}
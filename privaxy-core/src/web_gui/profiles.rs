@@ -0,0 +1,172 @@
+use super::{get_error_response, ApiError};
+use crate::configuration::{Configuration, ConfigurationError, Profile};
+use crate::proxy::exclusions::LocalExclusionStore;
+use serde::{Deserialize, Serialize};
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::mpsc::Sender;
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+#[derive(Debug, Serialize)]
+pub struct ProfilesResponse {
+    pub active_profile: Option<String>,
+    pub profiles: Vec<Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProfileRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActiveProfileRequest {
+    /// `None` keeps the current settings without any profile being active.
+    pub name: Option<String>,
+}
+
+fn get_profile_error_response(err: ConfigurationError) -> Box<dyn warp::Reply> {
+    match err {
+        ConfigurationError::ProfileError(_) => Box::new(
+            Response::builder().status(StatusCode::BAD_REQUEST).body(
+                serde_json::to_string(&ApiError {
+                    error: err.to_string(),
+                })
+                .unwrap(),
+            ),
+        ),
+        err => Box::new(get_error_response(err)),
+    }
+}
+
+async fn get_profiles() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get profiles: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&ProfilesResponse {
+        profiles: configuration.get_profiles(),
+        active_profile: configuration.active_profile,
+    })))
+}
+
+async fn post_profile(
+    profile_request: ProfileRequest,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to add profile: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    match configuration.add_profile(&profile_request.name).await {
+        Ok(profile) => Ok(Box::new(warp::reply::with_status(
+            warp::reply::json(&profile),
+            StatusCode::CREATED,
+        ))),
+        Err(err) => Ok(get_profile_error_response(err)),
+    }
+}
+
+async fn delete_profile(
+    name: String,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to delete profile: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    // Names are percent-encoded in the path.
+    let name = percent_encoding::percent_decode_str(&name)
+        .decode_utf8_lossy()
+        .to_string();
+
+    match configuration.remove_profile(&name).await {
+        Ok(true) => Ok(Box::new(StatusCode::NO_CONTENT)),
+        Ok(false) => Ok(Box::new(StatusCode::NOT_FOUND)),
+        Err(err) => Ok(get_profile_error_response(err)),
+    }
+}
+
+async fn put_active_profile(
+    active_profile_request: ActiveProfileRequest,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    mut local_exclusions_store: LocalExclusionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to switch profile: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_active_profile(active_profile_request.name.as_deref())
+        .await
+    {
+        return Ok(get_profile_error_response(err));
+    }
+
+    local_exclusions_store.replace_exclusions(configuration.get_exclusions());
+
+    // Recompiles the blocking engine with the filters of the profile.
+    if let Err(err) = configuration_updater_sender.send(configuration).await {
+        log::error!("Failed to send updated configuration: {err}");
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    local_exclusions_store: LocalExclusionStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_profiles)
+        .or(warp::post()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(super::with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and_then(self::post_profile))
+        .or(warp::put()
+            .and(warp::path!("active"))
+            .and(warp::body::json())
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender,
+            ))
+            .and(super::with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and(super::with_local_exclusions_store(local_exclusions_store))
+            .and_then(self::put_active_profile))
+        .or(warp::delete()
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(super::with_configuration_save_lock(configuration_save_lock))
+            .and_then(self::delete_profile))
+        .boxed()
+}
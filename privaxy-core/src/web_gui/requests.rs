@@ -0,0 +1,323 @@
+use super::ApiError;
+use crate::blocker::{AdblockRequester, BlockingDisabledStore, RequestDecision};
+use crate::configuration::ResourceType;
+use crate::proxy::block_responses::get_resource_type;
+use crate::statistics::Statistics;
+use hyper::body::Bytes;
+use hyper::Body;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+/// Largest body a request can be replayed with.
+const MAX_REPLAY_BODY_SIZE: u64 = 1024 * 1024;
+
+/// Bytes of the body of a replayed response that are returned.
+const REPLAY_RESPONSE_PREVIEW_SIZE: usize = 1024;
+
+/// Bytes of the body of a replayed response that are read, the rest is left unread.
+const MAX_REPLAY_RESPONSE_SIZE: u64 = 16 * 1024 * 1024;
+
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+pub struct TestRequest {
+    pub url: String,
+    /// Url of the frame issuing the request, defaults to the requested url.
+    pub source_url: Option<String>,
+    pub request_type: ResourceType,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestRequestResponse {
+    #[serde(flatten)]
+    pub decision: RequestDecision,
+    /// The decision is that of the blocking engine, requests are not blocked while
+    /// blocking is disabled.
+    pub blocking_enabled: bool,
+}
+
+/// Edits to a recorded request before it is replayed. The request log doesn't keep the
+/// headers and bodies of requests, as they hold cookies and credentials, the ones to
+/// send are given here.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct ReplayRequest {
+    /// Method to send rather than the recorded one.
+    pub method: Option<String>,
+    /// Headers to send. The resource type of the request is told from them as it is for
+    /// proxied requests, such as from `Sec-Fetch-Dest`, and `Referer` is the page the
+    /// request is matched against filters as coming from.
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ReplayResponse {
+    /// Decision of the blocking engine, with the filters as they are now.
+    #[serde(flatten)]
+    pub decision: RequestDecision,
+    /// Requests are not blocked while blocking is disabled.
+    pub blocking_enabled: bool,
+    /// As understood by filter `$`-options, such as `document` or `script`.
+    pub resource_type: String,
+    /// Response of the server, not sent blocked requests.
+    pub response: Option<ReplayedResponse>,
+    /// Why the request could not be sent.
+    pub error: Option<String>,
+}
+
+/// Summary of the response to a replayed request. Redirects are not followed.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ReplayedResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    /// Size of the body, as decoded. Bodies are read up to 16 MiB.
+    pub body_size: u64,
+    pub body_truncated: bool,
+    /// Beginning of the body, as text.
+    pub body_preview: String,
+    pub duration_ms: u64,
+}
+
+fn get_error_response(status: http::StatusCode, error: String) -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder()
+            .status(status)
+            .body(serde_json::to_string(&ApiError { error }).unwrap()),
+    )
+}
+
+async fn get_decision(id: u64, statistics: Statistics) -> Result<Box<dyn warp::Reply>, Infallible> {
+    match statistics.get_decision(id) {
+        Some(decision) => Ok(Box::new(warp::reply::json(&decision))),
+        // Only the most recent decisions are kept.
+        None => Ok(get_error_response(
+            http::StatusCode::NOT_FOUND,
+            format!("No decision recorded for request {}", id),
+        )),
+    }
+}
+
+async fn test_request(
+    test_request: TestRequest,
+    adblock_requester: AdblockRequester,
+    blocking_disabled_store: BlockingDisabledStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let source_url = match test_request.source_url {
+        Some(source_url) if !source_url.is_empty() => source_url,
+        // Like proxied requests without a referer, so that they are not seen as third party.
+        _ => test_request.url.clone(),
+    };
+
+    match adblock_requester
+        .test_network_url(test_request.url, source_url, test_request.request_type)
+        .await
+    {
+        Ok(decision) => Ok(Box::new(warp::reply::json(&TestRequestResponse {
+            decision,
+            blocking_enabled: blocking_disabled_store.is_enabled(),
+        }))),
+        Err(err) => Ok(get_error_response(http::StatusCode::BAD_REQUEST, err)),
+    }
+}
+
+/// Sends a recorded request again, once checked against the filters as they are now.
+async fn replay_request(
+    id: u64,
+    replay_request: Bytes,
+    statistics: Statistics,
+    adblock_requester: AdblockRequester,
+    blocking_disabled_store: BlockingDisabledStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    // Edits are optional, the request is replayed as it was recorded without a body.
+    let replay_request = if replay_request.is_empty() {
+        ReplayRequest::default()
+    } else {
+        match serde_json::from_slice::<ReplayRequest>(&replay_request) {
+            Ok(replay_request) => replay_request,
+            Err(err) => {
+                return Ok(get_error_response(
+                    http::StatusCode::BAD_REQUEST,
+                    err.to_string(),
+                ))
+            }
+        }
+    };
+
+    let recorded_decision = match statistics.get_decision(id) {
+        Some(recorded_decision) => recorded_decision,
+        None => {
+            return Ok(get_error_response(
+                http::StatusCode::NOT_FOUND,
+                format!("No decision recorded for request {}", id),
+            ))
+        }
+    };
+
+    let method = replay_request
+        .method
+        .unwrap_or(recorded_decision.method)
+        .to_ascii_uppercase();
+    // Tunnels are recorded with their host alone, there is no request to send.
+    if method == http::Method::CONNECT.as_str() {
+        return Ok(get_error_response(
+            http::StatusCode::BAD_REQUEST,
+            "tunnels can't be replayed".to_string(),
+        ));
+    }
+
+    let mut request_builder = http::Request::builder()
+        .method(method.as_str())
+        .uri(recorded_decision.url.as_str());
+    for (name, value) in &replay_request.headers {
+        request_builder = request_builder.header(name, value);
+    }
+    let request = match request_builder.body(Body::empty()) {
+        Ok(request) => request,
+        Err(err) => {
+            return Ok(get_error_response(
+                http::StatusCode::BAD_REQUEST,
+                format!("invalid request: {}", err),
+            ))
+        }
+    };
+
+    let resource_type = get_resource_type(&request);
+    let referer = match request.headers().get(http::header::REFERER) {
+        Some(referer) => referer.to_str().unwrap_or_default().to_string(),
+        // Like proxied requests without a referer, so that they are not seen as third party.
+        None => recorded_decision.url.clone(),
+    };
+
+    let decision = match adblock_requester
+        .test_network_url(recorded_decision.url.clone(), referer, resource_type)
+        .await
+    {
+        Ok(decision) => decision,
+        Err(err) => return Ok(get_error_response(http::StatusCode::BAD_REQUEST, err)),
+    };
+    let blocking_enabled = blocking_disabled_store.is_enabled();
+
+    let (response, error) = if decision.blocked && blocking_enabled {
+        (None, None)
+    } else {
+        match send_replayed_request(request, replay_request.body).await {
+            Ok(response) => (Some(response), None),
+            Err(err) => (None, Some(err.to_string())),
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&ReplayResponse {
+        decision,
+        blocking_enabled,
+        resource_type: resource_type.as_adblock_str().to_string(),
+        response,
+        error,
+    })))
+}
+
+async fn send_replayed_request(
+    request: http::Request<Body>,
+    body: Option<String>,
+) -> Result<ReplayedResponse, reqwest::Error> {
+    // Redirects are part of the response being looked into.
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(REPLAY_TIMEOUT)
+        .build()?;
+
+    let method = reqwest::Method::from_bytes(request.method().as_str().as_bytes())
+        .unwrap_or(reqwest::Method::GET);
+    let mut request_builder = client.request(method, request.uri().to_string());
+    for (name, value) in request.headers() {
+        request_builder = request_builder.header(name.as_str(), value.as_bytes());
+    }
+    if let Some(body) = body {
+        request_builder = request_builder.body(body);
+    }
+
+    let started_at = Instant::now();
+    let mut response = request_builder.send().await?;
+
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+
+    let mut body_size = 0;
+    let mut body_truncated = false;
+    let mut body_preview = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body_size += chunk.len() as u64;
+
+        let preview_size = (REPLAY_RESPONSE_PREVIEW_SIZE - body_preview.len()).min(chunk.len());
+        body_preview.extend_from_slice(&chunk[..preview_size]);
+
+        if body_size > MAX_REPLAY_RESPONSE_SIZE {
+            body_truncated = true;
+            break;
+        }
+    }
+
+    Ok(ReplayedResponse {
+        status,
+        headers,
+        body_size,
+        body_truncated,
+        body_preview: String::from_utf8_lossy(&body_preview).into_owned(),
+        duration_ms: started_at.elapsed().as_millis() as u64,
+    })
+}
+
+pub(super) fn create_routes(
+    statistics: Statistics,
+    adblock_requester: AdblockRequester,
+    blocking_disabled_store: BlockingDisabledStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_statistics = warp::any().map(move || statistics.clone());
+
+    warp::get()
+        .and(warp::path::param())
+        .and(warp::path("decision"))
+        .and(warp::path::end())
+        .and(with_statistics.clone())
+        .and_then(get_decision)
+        .or(warp::post()
+            .and(warp::path::param())
+            .and(warp::path("replay"))
+            .and(warp::path::end())
+            .and(warp::body::content_length_limit(MAX_REPLAY_BODY_SIZE))
+            .and(warp::body::bytes())
+            .and(with_statistics)
+            .and(super::with_adblock_requester(adblock_requester))
+            .and(super::with_blocking_disabled_store(blocking_disabled_store))
+            .and_then(replay_request))
+        .boxed()
+}
+
+/// Routes checking hypothetical requests against the blocking engine.
+pub(super) fn create_test_routes(
+    adblock_requester: AdblockRequester,
+    blocking_disabled_store: BlockingDisabledStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(super::with_adblock_requester(adblock_requester))
+        .and(super::with_blocking_disabled_store(blocking_disabled_store))
+        .and_then(test_request)
+        .boxed()
+}
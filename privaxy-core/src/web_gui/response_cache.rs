@@ -0,0 +1,100 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, ResponseCache};
+use crate::proxy::response_cache::{PurgedResponses, ResponseCacheStore};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PurgeQuery {
+    /// Only purge the responses of this host, rather than every one of them.
+    host: Option<String>,
+}
+
+async fn get_response_cache() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get response cache: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.response_cache)))
+}
+
+async fn put_response_cache(
+    response_cache: ResponseCache,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    response_cache_store: ResponseCacheStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put response cache: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_response_cache(response_cache, response_cache_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_status(
+    response_cache_store: ResponseCacheStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(
+        &response_cache_store.get_status(),
+    )))
+}
+
+async fn purge(
+    query: PurgeQuery,
+    response_cache_store: ResponseCacheStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let purged = response_cache_store.purge(query.host.as_deref());
+
+    log::info!("Purged {} cached responses", purged);
+
+    Ok(Box::new(warp::reply::json(&PurgedResponses { purged })))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    response_cache_store: ResponseCacheStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_response_cache_store = warp::any().map(move || response_cache_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_response_cache)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_response_cache_store.clone())
+            .and_then(self::put_response_cache))
+        .or(warp::get()
+            .and(warp::path("status"))
+            .and(warp::path::end())
+            .and(with_response_cache_store.clone())
+            .and_then(self::get_status))
+        .or(warp::delete()
+            .and(warp::path("entries"))
+            .and(warp::path::end())
+            .and(warp::query::<PurgeQuery>())
+            .and(with_response_cache_store)
+            .and_then(self::purge))
+        .boxed()
+}
@@ -0,0 +1,62 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, SafeSearch};
+use crate::proxy::safe_search::SafeSearchStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_safe_search() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get SafeSearch settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.safe_search)))
+}
+
+async fn put_safe_search(
+    safe_search: SafeSearch,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    safe_search_store: SafeSearchStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put SafeSearch settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_safe_search(safe_search, safe_search_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    safe_search_store: SafeSearchStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_safe_search_store = warp::any().map(move || safe_search_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_safe_search)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_safe_search_store)
+            .and_then(self::put_safe_search))
+        .boxed()
+}
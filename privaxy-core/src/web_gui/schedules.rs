@@ -0,0 +1,60 @@
+use super::{get_error_response, with_configuration_save_lock, ApiError};
+use crate::configuration::{Configuration, ConfigurationError, Schedule};
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+async fn get_schedules() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get schedules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.schedules)))
+}
+
+async fn put_schedules(
+    schedules: Vec<Schedule>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put schedules: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    match configuration.set_schedules(schedules).await {
+        Ok(()) => Ok(Box::new(StatusCode::NO_CONTENT)),
+        Err(err @ ConfigurationError::ScheduleError(_)) => Ok(Box::new(
+            Response::builder().status(StatusCode::BAD_REQUEST).body(
+                serde_json::to_string(&ApiError {
+                    error: err.to_string(),
+                })
+                .unwrap(),
+            ),
+        )),
+        Err(err) => Ok(Box::new(get_error_response(err))),
+    }
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_schedules)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and_then(self::put_schedules))
+        .boxed()
+}
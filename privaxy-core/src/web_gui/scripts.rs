@@ -0,0 +1,68 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, Scripts};
+use crate::proxy::scripts::ScriptStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_scripts() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get scripts: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.scripts)))
+}
+
+async fn put_scripts(
+    scripts: Scripts,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    script_store: ScriptStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put scripts: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration.set_scripts(scripts, script_store).await {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_statuses(script_store: ScriptStore) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&script_store.get_statuses())))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    script_store: ScriptStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_script_store = warp::any().map(move || script_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_scripts)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_script_store.clone())
+            .and_then(self::put_scripts))
+        .or(warp::get()
+            .and(warp::path("status"))
+            .and(warp::path::end())
+            .and(with_script_store)
+            .and_then(self::get_statuses))
+        .boxed()
+}
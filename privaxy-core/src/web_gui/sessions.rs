@@ -0,0 +1,549 @@
+use super::{with_session_store, ApiError};
+use crate::configuration::WebAuthentication;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::{Filter as RouteFilter, Rejection};
+
+pub(crate) const SESSION_COOKIE_NAME: &str = "privaxy_session";
+
+/// Sign-in attempts a client may make per `SIGN_IN_ATTEMPTS_WINDOW`, whether they succeed
+/// or not. Attempts are counted before the password is checked, so that guesses made in
+/// parallel are limited too.
+const MAX_SIGN_IN_ATTEMPTS: u32 = 5;
+const SIGN_IN_ATTEMPTS_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub(crate) enum SignInError {
+    InvalidPassword,
+    /// The client made too many attempts, it may try again after the given duration.
+    TooManyAttempts(Duration),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SignInAttempts {
+    count: u32,
+    window_started_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct Session {
+    /// Identifies the session in the API. Unlike the session token, it is not a credential.
+    id: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    last_seen_at: DateTime<Utc>,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    /// Whether this is the session of the device making the request.
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionStatusResponse {
+    pub authentication_required: bool,
+    pub authorized: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignInRequest {
+    pub password: String,
+}
+
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+/// Sessions only live in memory, restarting Privaxy signs every device out.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    web_authentication: Arc<RwLock<WebAuthentication>>,
+    /// Sessions keyed by their token.
+    sessions: Arc<RwLock<HashMap<String, Session>>>,
+    /// Recent sign-in attempts, keyed by the address of the client that made them.
+    /// Requests made through the admin socket have no address and share an entry.
+    sign_in_attempts: Arc<Mutex<HashMap<Option<IpAddr>, SignInAttempts>>>,
+    /// Whether the web GUI is served over TLS, so that session cookies are only sent
+    /// over HTTPS.
+    secure_cookies: Arc<AtomicBool>,
+}
+
+impl SessionStore {
+    pub fn new(web_authentication: WebAuthentication) -> Self {
+        Self {
+            web_authentication: Arc::new(RwLock::new(web_authentication)),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            sign_in_attempts: Arc::new(Mutex::new(HashMap::new())),
+            secure_cookies: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn set_secure_cookies(&self, secure_cookies: bool) {
+        self.secure_cookies.store(secure_cookies, Ordering::Relaxed);
+    }
+
+    pub fn replace(&self, web_authentication: WebAuthentication) {
+        let mut current_web_authentication = self.web_authentication.write().unwrap();
+
+        // Changing the password signs every device out.
+        if current_web_authentication.password_hash != web_authentication.password_hash {
+            self.sessions.write().unwrap().clear();
+        }
+
+        *current_web_authentication = web_authentication;
+    }
+
+    fn is_authentication_required(&self) -> bool {
+        self.web_authentication.read().unwrap().enabled
+    }
+
+    /// Returns whether a request carrying the given session token may use the API.
+    pub(crate) fn authorize(&self, token: Option<&str>) -> bool {
+        if !self.is_authentication_required() {
+            return true;
+        }
+
+        let token = match token {
+            Some(token) => token,
+            None => return false,
+        };
+
+        let now = Utc::now();
+        let mut sessions = self.sessions.write().unwrap();
+
+        match sessions.get_mut(token) {
+            Some(session) if session.expires_at > now => {
+                session.last_seen_at = now;
+                true
+            }
+            Some(_) => {
+                sessions.remove(token);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the id of the session the given token belongs to.
+    pub(crate) fn get_session_id(&self, token: &str) -> Option<String> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(token)
+            .map(|session| session.id.clone())
+    }
+
+    /// Opens a new session when the password is valid, returning its token and lifetime.
+    pub(crate) async fn sign_in(
+        &self,
+        password: String,
+        user_agent: Option<String>,
+        remote_address: Option<IpAddr>,
+    ) -> Result<(String, Duration), SignInError> {
+        self.count_sign_in_attempt(remote_address)?;
+
+        let web_authentication = self.web_authentication.read().unwrap().clone();
+
+        // Argon2 is slow on purpose, it must not hold up the other requests of a worker.
+        let is_valid_password =
+            tokio::task::spawn_blocking(move || web_authentication.verify_password(&password))
+                .await
+                .unwrap();
+
+        if !is_valid_password {
+            return Err(SignInError::InvalidPassword);
+        }
+
+        self.sign_in_attempts
+            .lock()
+            .unwrap()
+            .remove(&remote_address);
+
+        Ok(self.open_session(
+            user_agent,
+            remote_address.map(|address| address.to_string()),
+        ))
+    }
+
+    /// Opens a new session without checking a password, returning its token and lifetime.
+    pub(crate) fn open_session(
+        &self,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> (String, Duration) {
+        let session_ttl = self.web_authentication.read().unwrap().session_ttl();
+        let now = Utc::now();
+        let token = get_random_hex_string(32);
+
+        self.sessions.write().unwrap().insert(
+            token.clone(),
+            Session {
+                id: get_random_hex_string(16),
+                created_at: now,
+                expires_at: now + chrono::Duration::from_std(session_ttl).unwrap(),
+                last_seen_at: now,
+                user_agent,
+                ip_address,
+            },
+        );
+
+        (token, session_ttl)
+    }
+
+    fn count_sign_in_attempt(&self, remote_address: Option<IpAddr>) -> Result<(), SignInError> {
+        let now = Instant::now();
+        let mut sign_in_attempts = self.sign_in_attempts.lock().unwrap();
+
+        sign_in_attempts.retain(|_remote_address, attempts| {
+            now.duration_since(attempts.window_started_at) < SIGN_IN_ATTEMPTS_WINDOW
+        });
+
+        let attempts = sign_in_attempts
+            .entry(remote_address)
+            .or_insert(SignInAttempts {
+                count: 0,
+                window_started_at: now,
+            });
+
+        if attempts.count >= MAX_SIGN_IN_ATTEMPTS {
+            return Err(SignInError::TooManyAttempts(
+                SIGN_IN_ATTEMPTS_WINDOW - now.duration_since(attempts.window_started_at),
+            ));
+        }
+        attempts.count += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_session_cookie(&self, token: &str, max_age: Duration) -> String {
+        get_session_cookie(token, max_age, self.secure_cookies.load(Ordering::Relaxed))
+    }
+
+    fn sign_out(&self, token: &str) {
+        self.sessions.write().unwrap().remove(token);
+    }
+
+    fn list(&self, current_token: Option<&str>) -> Vec<SessionResponse> {
+        let now = Utc::now();
+        let mut sessions = self.sessions.write().unwrap();
+
+        sessions.retain(|_token, session| session.expires_at > now);
+
+        let mut sessions = sessions
+            .iter()
+            .map(|(token, session)| SessionResponse {
+                id: session.id.clone(),
+                created_at: session.created_at,
+                expires_at: session.expires_at,
+                last_seen_at: session.last_seen_at,
+                user_agent: session.user_agent.clone(),
+                ip_address: session.ip_address.clone(),
+                current: Some(token.as_str()) == current_token,
+            })
+            .collect::<Vec<_>>();
+
+        sessions.sort_by_key(|session| Reverse(session.last_seen_at));
+
+        sessions
+    }
+
+    /// Returns whether a session with the given id existed.
+    fn revoke(&self, id: &str) -> bool {
+        let mut sessions = self.sessions.write().unwrap();
+        let sessions_count = sessions.len();
+
+        sessions.retain(|_token, session| session.id != id);
+
+        sessions.len() != sessions_count
+    }
+
+    fn revoke_others(&self, current_token: Option<&str>) {
+        self.sessions
+            .write()
+            .unwrap()
+            .retain(|token, _session| Some(token.as_str()) == current_token);
+    }
+}
+
+fn get_random_hex_string(bytes_count: usize) -> String {
+    let mut bytes = vec![0; bytes_count];
+    openssl::rand::rand_bytes(&mut bytes).unwrap();
+
+    hex::encode(bytes)
+}
+
+fn get_session_cookie(token: &str, max_age: Duration, secure: bool) -> String {
+    format!(
+        "{}={}; Max-Age={}; Path=/; HttpOnly; SameSite=Strict{}",
+        SESSION_COOKIE_NAME,
+        token,
+        max_age.as_secs(),
+        if secure { "; Secure" } else { "" }
+    )
+}
+
+/// Token of the session a request is made with, from the session cookie or, for API
+/// clients such as `privaxy-cli`, from an `Authorization: Bearer` header.
+pub(super) fn session_token(
+) -> impl RouteFilter<Extract = (Option<String>,), Error = Rejection> + Clone {
+    warp::cookie::optional(SESSION_COOKIE_NAME)
+        .and(warp::header::optional::<String>("authorization"))
+        .map(|cookie: Option<String>, authorization: Option<String>| {
+            cookie.or_else(|| {
+                authorization
+                    .as_deref()
+                    .and_then(|authorization| authorization.strip_prefix("Bearer "))
+                    .map(|token| token.trim().to_string())
+            })
+        })
+}
+
+/// Whether the request is made with a valid session, or doesn't need one.
+///
+/// Requests made through the admin socket have no remote address. The permissions of
+/// the socket already decide who can make them.
+pub(super) fn has_session(
+    session_store: SessionStore,
+) -> impl RouteFilter<Extract = (bool,), Error = Rejection> + Clone {
+    session_token().and(warp::addr::remote()).map(
+        move |token: Option<String>, remote_address: Option<SocketAddr>| {
+            remote_address.is_none() || session_store.authorize(token.as_deref())
+        },
+    )
+}
+
+/// Rejects requests without a valid session when web GUI authentication is enabled.
+pub(super) fn require_session(
+    session_store: SessionStore,
+) -> impl RouteFilter<Extract = (), Error = Rejection> + Clone {
+    has_session(session_store)
+        .and_then(|has_session: bool| async move {
+            if has_session {
+                Ok(())
+            } else {
+                Err(warp::reject::custom(Unauthorized))
+            }
+        })
+        .untuple_one()
+}
+
+pub(super) async fn recover_unauthorized(
+    rejection: Rejection,
+) -> Result<Response<String>, Rejection> {
+    if rejection.find::<Unauthorized>().is_none() {
+        return Err(rejection);
+    }
+
+    Ok(Response::builder()
+        .status(http::StatusCode::UNAUTHORIZED)
+        .body(
+            serde_json::to_string(&ApiError {
+                error: "Signing in is required".to_string(),
+            })
+            .unwrap(),
+        )
+        .unwrap())
+}
+
+async fn get_session_status(
+    token: Option<String>,
+    session_store: SessionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&SessionStatusResponse {
+        authentication_required: session_store.is_authentication_required(),
+        authorized: session_store.authorize(token.as_deref()),
+    })))
+}
+
+async fn sign_in(
+    sign_in_request: SignInRequest,
+    user_agent: Option<String>,
+    remote_address: Option<SocketAddr>,
+    session_store: SessionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let sign_in = session_store
+        .sign_in(
+            sign_in_request.password,
+            user_agent,
+            remote_address.map(|address| address.ip()),
+        )
+        .await;
+
+    match sign_in {
+        Ok((token, session_ttl)) => Ok(Box::new(
+            Response::builder()
+                .status(http::StatusCode::NO_CONTENT)
+                .header(
+                    http::header::SET_COOKIE,
+                    session_store.get_session_cookie(&token, session_ttl),
+                )
+                .body("".to_string()),
+        )),
+        Err(SignInError::InvalidPassword) => {
+            log::warn!("Failed web GUI sign in from {:?}", remote_address);
+
+            Ok(Box::new(
+                Response::builder()
+                    .status(http::StatusCode::UNAUTHORIZED)
+                    .body(
+                        serde_json::to_string(&ApiError {
+                            error: "Invalid password".to_string(),
+                        })
+                        .unwrap(),
+                    ),
+            ))
+        }
+        Err(SignInError::TooManyAttempts(retry_after)) => {
+            log::warn!(
+                "Too many web GUI sign in attempts from {:?}",
+                remote_address
+            );
+
+            Ok(Box::new(
+                Response::builder()
+                    .status(http::StatusCode::TOO_MANY_REQUESTS)
+                    .header(http::header::RETRY_AFTER, retry_after.as_secs().max(1))
+                    .body(
+                        serde_json::to_string(&ApiError {
+                            error: "Too many sign in attempts, try again later".to_string(),
+                        })
+                        .unwrap(),
+                    ),
+            ))
+        }
+    }
+}
+
+async fn sign_out(
+    token: Option<String>,
+    session_store: SessionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if let Some(token) = &token {
+        session_store.sign_out(token);
+    }
+
+    Ok(Box::new(
+        Response::builder()
+            .status(http::StatusCode::NO_CONTENT)
+            .header(
+                http::header::SET_COOKIE,
+                session_store.get_session_cookie("", Duration::ZERO),
+            )
+            .body("".to_string()),
+    ))
+}
+
+async fn get_sessions(
+    token: Option<String>,
+    session_store: SessionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(
+        &session_store.list(token.as_deref()),
+    )))
+}
+
+async fn revoke_session(
+    id: String,
+    session_store: SessionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if !session_store.revoke(&id) {
+        return Ok(Box::new(
+            Response::builder()
+                .status(http::StatusCode::NOT_FOUND)
+                .body(
+                    serde_json::to_string(&ApiError {
+                        error: format!("Session {} does not exist", id),
+                    })
+                    .unwrap(),
+                ),
+        ));
+    }
+
+    Ok(Box::new(
+        Response::builder()
+            .status(http::StatusCode::NO_CONTENT)
+            .body("".to_string()),
+    ))
+}
+
+async fn revoke_other_sessions(
+    token: Option<String>,
+    session_store: SessionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    session_store.revoke_others(token.as_deref());
+
+    Ok(Box::new(
+        Response::builder()
+            .status(http::StatusCode::NO_CONTENT)
+            .body("".to_string()),
+    ))
+}
+
+/// Routes used to sign in and out. They don't require a session.
+pub(super) fn create_session_routes(
+    session_store: SessionStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let get_route = warp::get()
+        .and(warp::path::end())
+        .and(session_token())
+        .and(with_session_store(session_store.clone()))
+        .and_then(get_session_status);
+
+    let post_route = warp::post()
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(warp::header::optional::<String>("user-agent"))
+        .and(warp::addr::remote())
+        .and(with_session_store(session_store.clone()))
+        .and_then(sign_in);
+
+    let delete_route = warp::delete()
+        .and(warp::path::end())
+        .and(session_token())
+        .and(with_session_store(session_store))
+        .and_then(sign_out);
+
+    get_route.or(post_route).or(delete_route).boxed()
+}
+
+/// Routes listing and revoking the sessions of signed-in devices.
+pub(super) fn create_routes(session_store: SessionStore) -> BoxedFilter<(impl warp::Reply,)> {
+    let get_route = warp::get()
+        .and(warp::path::end())
+        .and(session_token())
+        .and(with_session_store(session_store.clone()))
+        .and_then(get_sessions);
+
+    let delete_route = warp::delete()
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(with_session_store(session_store.clone()))
+        .and_then(revoke_session);
+
+    let delete_others_route = warp::delete()
+        .and(warp::path::end())
+        .and(session_token())
+        .and(with_session_store(session_store))
+        .and_then(revoke_other_sessions);
+
+    get_route.or(delete_route).or(delete_others_route).boxed()
+}
@@ -0,0 +1,75 @@
+use super::get_error_response;
+use crate::configuration::{BlockResponses, Configuration};
+use crate::proxy::block_responses::BlockResponsesStore;
+use crate::web_gui::with_block_responses_store;
+use crate::web_gui::with_configuration_save_lock;
+use std::convert::Infallible;
+use std::sync::Arc;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+async fn get_block_responses() -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting block responses settings");
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get block responses settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.block_responses)))
+}
+
+async fn put_block_responses(
+    block_responses: BlockResponses,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    block_responses_store: BlockResponsesStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put block responses settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    // Block responses don't affect the blocking engine, there is no need to
+    // notify the configuration updater.
+    if let Err(err) = configuration
+        .set_block_responses(block_responses, block_responses_store)
+        .await
+    {
+        log::error!("Failed to set block responses settings: {err}");
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(
+        Response::builder()
+            .status(http::StatusCode::NO_CONTENT)
+            .body("".to_string()),
+    ))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    block_responses_store: BlockResponsesStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let get_route = warp::get()
+        .and(warp::path::end())
+        .and_then(get_block_responses);
+
+    let put_route = warp::put()
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_configuration_save_lock(
+            configuration_save_lock.clone(),
+        ))
+        .and(with_block_responses_store(block_responses_store))
+        .and_then(put_block_responses);
+
+    get_route.or(put_route).boxed()
+}
@@ -0,0 +1,95 @@
+use super::get_error_response;
+use crate::configuration::{ClientIdentification, Configuration, ConfigurationError};
+use crate::proxy::client_identification::ClientIdentificationStore;
+use crate::web_gui::with_client_identification_store;
+use crate::web_gui::with_configuration_save_lock;
+use crate::web_gui::ApiError;
+use std::convert::Infallible;
+use std::sync::Arc;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+async fn get_client_identification() -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting client identification settings");
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get client identification settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(
+        &configuration.client_identification,
+    )))
+}
+
+async fn put_client_identification(
+    client_identification: ClientIdentification,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    client_identification_store: ClientIdentificationStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put client identification settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    // Client identification only affects statistics, there is no need to
+    // notify the configuration updater.
+    match configuration
+        .set_client_identification(client_identification, client_identification_store)
+        .await
+    {
+        Ok(()) => {}
+        Err(err @ ConfigurationError::ClientIdentificationError(_)) => {
+            return Ok(Box::new(
+                Response::builder()
+                    .status(http::StatusCode::BAD_REQUEST)
+                    .body(
+                        serde_json::to_string(&ApiError {
+                            error: err.to_string(),
+                        })
+                        .unwrap(),
+                    ),
+            ));
+        }
+        Err(err) => {
+            log::error!("Failed to set client identification settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    }
+
+    Ok(Box::new(
+        Response::builder()
+            .status(http::StatusCode::NO_CONTENT)
+            .body("".to_string()),
+    ))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    client_identification_store: ClientIdentificationStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let get_route = warp::get()
+        .and(warp::path::end())
+        .and_then(get_client_identification);
+
+    let put_route = warp::put()
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_configuration_save_lock(
+            configuration_save_lock.clone(),
+        ))
+        .and(with_client_identification_store(
+            client_identification_store,
+        ))
+        .and_then(put_client_identification);
+
+    get_route.or(put_route).boxed()
+}
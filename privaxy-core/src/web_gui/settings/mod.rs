@@ -0,0 +1,70 @@
+use super::get_error_response;
+use crate::configuration::Configuration;
+use crate::proxy::authentication::ProxyAuthenticationStore;
+use crate::proxy::block_responses::BlockResponsesStore;
+use crate::proxy::client_identification::ClientIdentificationStore;
+use crate::web_gui::sessions::SessionStore;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Notify;
+use warp::filters::BoxedFilter;
+use warp::Filter as RouteFilter;
+
+mod block_responses;
+mod ca_certificate;
+mod client_identification;
+mod network;
+mod proxy_authentication;
+mod web_authentication;
+
+pub(crate) fn create_routes(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    proxy_authentication_store: ProxyAuthenticationStore,
+    block_responses_store: BlockResponsesStore,
+    client_identification_store: ClientIdentificationStore,
+    session_store: SessionStore,
+    notify_reload: Arc<Notify>,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let network_settings_route = warp::path("network").and(network::create_routes(
+        configuration_updater_sender.clone(),
+        configuration_save_lock.clone(),
+        notify_reload.clone(),
+    ));
+
+    let ca_cert_route = warp::path("ca-certificate").and(ca_certificate::create_routes(
+        configuration_updater_sender.clone(),
+        configuration_save_lock.clone(),
+        notify_reload.clone(),
+    ));
+
+    let proxy_authentication_route =
+        warp::path("proxy-authentication").and(proxy_authentication::create_routes(
+            configuration_updater_sender.clone(),
+            configuration_save_lock.clone(),
+            proxy_authentication_store,
+        ));
+
+    let block_responses_route = warp::path("block-responses").and(block_responses::create_routes(
+        configuration_save_lock.clone(),
+        block_responses_store,
+    ));
+
+    let client_identification_route =
+        warp::path("client-identification").and(client_identification::create_routes(
+            configuration_save_lock.clone(),
+            client_identification_store,
+        ));
+
+    let web_authentication_route = warp::path("web-authentication").and(
+        web_authentication::create_routes(configuration_save_lock.clone(), session_store),
+    );
+
+    network_settings_route
+        .or(ca_cert_route)
+        .or(proxy_authentication_route)
+        .or(block_responses_route)
+        .or(client_identification_route)
+        .or(web_authentication_route)
+        .boxed()
+}
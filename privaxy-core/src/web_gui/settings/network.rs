@@ -39,6 +39,13 @@ impl Into<NetworkConfig> for NetworkConfigRequest {
             tls_cert_path: None,
             tls_key_path: None,
             listen_url: None,
+            web_base_path: None,
+            allowed_origins: Vec::new(),
+            allowed_hosts: Vec::new(),
+            admin_socket_path: None,
+            admin_socket_mode: None,
+            compress_api_responses: true,
+            frontend_directory: None,
         }
     }
 }
@@ -76,6 +83,13 @@ async fn put_network_settings(
     net_cfg.tls_cert_path = current_cfg.tls_cert_path;
     net_cfg.tls_key_path = current_cfg.tls_key_path;
     net_cfg.listen_url = current_cfg.listen_url;
+    net_cfg.web_base_path = current_cfg.web_base_path;
+    net_cfg.allowed_origins = current_cfg.allowed_origins;
+    net_cfg.allowed_hosts = current_cfg.allowed_hosts;
+    net_cfg.admin_socket_path = current_cfg.admin_socket_path;
+    net_cfg.admin_socket_mode = current_cfg.admin_socket_mode;
+    net_cfg.compress_api_responses = current_cfg.compress_api_responses;
+    net_cfg.frontend_directory = current_cfg.frontend_directory;
     if let Err(err) = &net_cfg.validate().await {
         log::error!("Invalid network settings: {}", err);
         return Ok(Box::new(get_error_response(err)));
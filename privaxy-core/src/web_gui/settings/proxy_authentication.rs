@@ -0,0 +1,168 @@
+use super::get_error_response;
+use crate::configuration::{Configuration, ProxyAuthentication, ProxyUser};
+use crate::proxy::authentication::ProxyAuthenticationStore;
+use crate::web_gui::with_configuration_save_lock;
+use crate::web_gui::with_configuration_updater_sender;
+use crate::web_gui::with_proxy_authentication_store;
+use crate::web_gui::ApiError;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProxyUserRequest {
+    pub username: String,
+    /// When omitted, the password of an existing user is kept.
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProxyAuthenticationRequest {
+    pub enabled: bool,
+    pub users: Vec<ProxyUserRequest>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProxyAuthenticationResponse {
+    pub enabled: bool,
+    pub users: Vec<String>,
+}
+
+async fn get_proxy_authentication() -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting proxy authentication settings");
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get proxy authentication settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    // Password hashes never leave the server.
+    Ok(Box::new(warp::reply::json(&ProxyAuthenticationResponse {
+        enabled: configuration.proxy_authentication.enabled,
+        users: configuration
+            .proxy_authentication
+            .users
+            .into_iter()
+            .map(|user| user.username)
+            .collect(),
+    })))
+}
+
+async fn put_proxy_authentication(
+    proxy_authentication_request: ProxyAuthenticationRequest,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    proxy_authentication_store: ProxyAuthenticationStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put proxy authentication settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    let mut users = Vec::with_capacity(proxy_authentication_request.users.len());
+
+    for user_request in proxy_authentication_request.users {
+        let user = match user_request.password {
+            Some(password) => ProxyUser::new(user_request.username, &password),
+            None => match configuration
+                .proxy_authentication
+                .users
+                .iter()
+                .find(|user| user.username == user_request.username)
+            {
+                Some(user) => user.clone(),
+                None => {
+                    return Ok(Box::new(
+                        Response::builder()
+                            .status(http::StatusCode::BAD_REQUEST)
+                            .body(
+                                serde_json::to_string(&ApiError {
+                                    error: format!(
+                                        "A password is required for new user {}",
+                                        user_request.username
+                                    ),
+                                })
+                                .unwrap(),
+                            ),
+                    ));
+                }
+            },
+        };
+
+        users.push(user);
+    }
+
+    if proxy_authentication_request.enabled && users.is_empty() {
+        return Ok(Box::new(
+            Response::builder()
+                .status(http::StatusCode::BAD_REQUEST)
+                .body(
+                    serde_json::to_string(&ApiError {
+                        error: "At least one user is required to enable proxy authentication"
+                            .to_string(),
+                    })
+                    .unwrap(),
+                ),
+        ));
+    }
+
+    if let Err(err) = configuration
+        .set_proxy_authentication(
+            ProxyAuthentication {
+                enabled: proxy_authentication_request.enabled,
+                users,
+            },
+            proxy_authentication_store,
+        )
+        .await
+    {
+        log::error!("Failed to set proxy authentication settings: {err}");
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    configuration_updater_sender
+        .send(configuration.clone())
+        .await
+        .unwrap();
+
+    Ok(Box::new(
+        Response::builder()
+            .status(http::StatusCode::NO_CONTENT)
+            .body("".to_string()),
+    ))
+}
+
+pub(super) fn create_routes(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    proxy_authentication_store: ProxyAuthenticationStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let get_route = warp::get()
+        .and(warp::path::end())
+        .and_then(get_proxy_authentication);
+
+    let put_route = warp::put()
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(with_configuration_updater_sender(
+            configuration_updater_sender.clone(),
+        ))
+        .and(with_configuration_save_lock(
+            configuration_save_lock.clone(),
+        ))
+        .and(with_proxy_authentication_store(proxy_authentication_store))
+        .and_then(put_proxy_authentication);
+
+    get_route.or(put_route).boxed()
+}
@@ -0,0 +1,152 @@
+use super::get_error_response;
+use crate::configuration::Configuration;
+use crate::web_gui::sessions::SessionStore;
+use crate::web_gui::with_configuration_save_lock;
+use crate::web_gui::with_session_store;
+use crate::web_gui::ApiError;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::Filter as RouteFilter;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebAuthenticationRequest {
+    pub enabled: bool,
+    /// When omitted, the current password is kept.
+    pub password: Option<String>,
+    pub session_ttl_secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebAuthenticationResponse {
+    pub enabled: bool,
+    pub password_set: bool,
+    pub session_ttl_secs: u64,
+}
+
+fn get_bad_request_response(error: &str) -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder()
+            .status(http::StatusCode::BAD_REQUEST)
+            .body(
+                serde_json::to_string(&ApiError {
+                    error: error.to_string(),
+                })
+                .unwrap(),
+            ),
+    )
+}
+
+async fn get_web_authentication() -> Result<Box<dyn warp::Reply>, Infallible> {
+    log::debug!("Getting web authentication settings");
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get web authentication settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    // The password hash never leaves the server.
+    Ok(Box::new(warp::reply::json(&WebAuthenticationResponse {
+        enabled: configuration.web_authentication.enabled,
+        password_set: configuration.web_authentication.password_hash.is_some(),
+        session_ttl_secs: configuration.web_authentication.session_ttl_secs,
+    })))
+}
+
+async fn put_web_authentication(
+    web_authentication_request: WebAuthenticationRequest,
+    user_agent: Option<String>,
+    remote_address: Option<SocketAddr>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    session_store: SessionStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put web authentication settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if web_authentication_request.session_ttl_secs == 0 {
+        return Ok(get_bad_request_response(
+            "Sign-ins must last at least one second",
+        ));
+    }
+
+    let mut web_authentication = configuration.web_authentication.clone();
+    web_authentication.enabled = web_authentication_request.enabled;
+    web_authentication.session_ttl_secs = web_authentication_request.session_ttl_secs;
+
+    if let Some(password) = &web_authentication_request.password {
+        if password.is_empty() {
+            return Ok(get_bad_request_response("The password cannot be empty"));
+        }
+
+        web_authentication.set_password(password);
+    }
+
+    if web_authentication.enabled && web_authentication.password_hash.is_none() {
+        return Ok(get_bad_request_response(
+            "A password is required to enable web GUI authentication",
+        ));
+    }
+
+    // Web GUI authentication doesn't affect the blocking engine, there is no need to
+    // notify the configuration updater.
+    if let Err(err) = configuration
+        .set_web_authentication(web_authentication, session_store.clone())
+        .await
+    {
+        log::error!("Failed to set web authentication settings: {err}");
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    let response = Response::builder().status(http::StatusCode::NO_CONTENT);
+
+    // Setting a password signs every device out. The device that set it stays signed in.
+    let response = if web_authentication_request.password.is_some() {
+        let (token, session_ttl) = session_store.open_session(
+            user_agent,
+            remote_address.map(|address| address.ip().to_string()),
+        );
+
+        response.header(
+            http::header::SET_COOKIE,
+            session_store.get_session_cookie(&token, session_ttl),
+        )
+    } else {
+        response
+    };
+
+    Ok(Box::new(response.body("".to_string())))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    session_store: SessionStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let get_route = warp::get()
+        .and(warp::path::end())
+        .and_then(get_web_authentication);
+
+    let put_route = warp::put()
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(warp::header::optional::<String>("user-agent"))
+        .and(warp::addr::remote())
+        .and(with_configuration_save_lock(
+            configuration_save_lock.clone(),
+        ))
+        .and(with_session_store(session_store))
+        .and_then(put_web_authentication);
+
+    get_route.or(put_route).boxed()
+}
@@ -0,0 +1,98 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, SitePolicy};
+use crate::proxy::site_policies::SitePolicyStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_site_policies() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get site policies: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.site_policies)))
+}
+
+async fn put_site_policy(
+    site: String,
+    mut site_policy: SitePolicy,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    site_policy_store: SitePolicyStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put site policy: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    // The site in the path takes precedence, so that it doesn't need repeating in the body.
+    site_policy.site = site;
+
+    if let Err(err) = configuration
+        .set_site_policy(site_policy, site_policy_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn delete_site_policy(
+    site: String,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    site_policy_store: SitePolicyStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to delete site policy: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    match configuration
+        .remove_site_policy(&site, site_policy_store)
+        .await
+    {
+        Ok(true) => Ok(Box::new(StatusCode::NO_CONTENT)),
+        Ok(false) => Ok(Box::new(StatusCode::NOT_FOUND)),
+        Err(err) => Ok(Box::new(get_error_response(err))),
+    }
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    site_policy_store: SitePolicyStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_site_policies)
+        .or(warp::put()
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(
+                configuration_save_lock.clone(),
+            ))
+            .and(super::with_site_policy_store(site_policy_store.clone()))
+            .and_then(self::put_site_policy))
+        .or(warp::delete()
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(super::with_site_policy_store(site_policy_store))
+            .and_then(self::delete_site_policy))
+        .boxed()
+}
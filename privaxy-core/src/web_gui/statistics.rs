@@ -0,0 +1,530 @@
+use chrono::{DateTime, TimeZone, Utc};
+use futures::{SinkExt, StreamExt};
+use log;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::convert::Infallible;
+use std::time::Duration;
+use warp::filters::BoxedFilter;
+use warp::http::Response;
+use warp::ws::{Message, WebSocket};
+use warp::Filter as RouteFilter;
+
+use super::{get_error_response, ApiError};
+use crate::blocker::AdblockRequester;
+use crate::configuration::Configuration;
+use crate::statistics::{HistoryBucket, SerializableStatistics, Statistics, TopKind};
+use crate::timeseries::Resolution;
+
+/// Statistics are compared this often, their changes are sent right away.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+const DEFAULT_TOP_PERIOD: &str = "24h";
+const DEFAULT_TOP_LIMIT: usize = 50;
+const MAX_TOP_LIMIT: usize = 1_000;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HistoryQuery {
+    /// Unix timestamp, in seconds, of the start of the period.
+    pub from: Option<i64>,
+    /// Unix timestamp, in seconds, of the end of the period, excluded.
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TopQuery {
+    pub kind: TopKind,
+    /// Period ending now, in hours or days such as `24h` or `7d`. Defaults to `24h`.
+    pub period: Option<String>,
+    /// Number of entries returned, 50 by default.
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TimeSeriesQuery {
+    /// Duration of each bucket, `hour` by default.
+    pub resolution: Option<Resolution>,
+    /// Unix timestamp, in seconds, of the start of the period.
+    pub from: Option<i64>,
+    /// Unix timestamp, in seconds, of the end of the period, excluded.
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RuleProfilingRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportQuery {
+    /// `json` by default.
+    pub format: Option<ExportFormat>,
+    /// Period ending now, in hours or days such as `24h` or `7d`. The whole history is
+    /// exported by default.
+    pub period: Option<String>,
+}
+
+/// Hour of `/statistics/export`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ExportedBucket {
+    pub start: DateTime<Utc>,
+    pub proxied_requests: u64,
+    pub blocked_requests: u64,
+    /// Domain blocked the most during the hour.
+    pub top_blocked_domain: Option<String>,
+}
+
+impl From<HistoryBucket> for ExportedBucket {
+    fn from(bucket: HistoryBucket) -> Self {
+        let top_blocked_domain = bucket
+            .blocked_domains
+            .into_iter()
+            .max_by(|(domain_a, count_a), (domain_b, count_b)| {
+                count_a.cmp(count_b).then(domain_b.cmp(domain_a))
+            })
+            .map(|(domain, _count)| domain);
+
+        Self {
+            start: bucket.start,
+            proxied_requests: bucket.proxied_requests,
+            blocked_requests: bucket.blocked_requests,
+            top_blocked_domain,
+        }
+    }
+}
+
+/// Sent over `/statistics`. Each message is numbered, one after the other, so that
+/// clients can tell when they missed one.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(super) enum StatisticsMessage {
+    /// Every statistic, sent first and whenever the client asks to resync.
+    Snapshot {
+        sequence: u64,
+        statistics: Box<SerializableStatistics>,
+    },
+    /// Statistics that changed since the previous message, as a JSON merge patch
+    /// (RFC 7386) of its statistics.
+    Delta { sequence: u64, changes: Value },
+}
+
+/// Sent by clients over `/statistics`.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(super) enum StatisticsClientMessage {
+    /// Asks for a snapshot, when a message was missed.
+    Resync,
+}
+
+/// Returns the merge patch turning `previous` into `current`, none when they are equal.
+fn get_merge_patch(previous: &Value, current: &Value) -> Option<Value> {
+    let (previous, current) = match (previous, current) {
+        (Value::Object(previous), Value::Object(current)) => (previous, current),
+        _ if previous == current => return None,
+        _ => return Some(current.clone()),
+    };
+
+    let mut patch = Map::new();
+
+    for (key, value) in current {
+        match previous.get(key) {
+            Some(previous_value) => {
+                if let Some(value_patch) = get_merge_patch(previous_value, value) {
+                    patch.insert(key.clone(), value_patch);
+                }
+            }
+            None => {
+                patch.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    for key in previous.keys().filter(|key| !current.contains_key(*key)) {
+        patch.insert(key.clone(), Value::Null);
+    }
+
+    (!patch.is_empty()).then_some(Value::Object(patch))
+}
+
+pub(super) async fn statistics(websocket: WebSocket, statistics: Statistics) {
+    let (mut tx, mut rx) = websocket.split();
+
+    let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+    let mut sequence = 0;
+    // Statistics as of the last message, none until a snapshot is sent.
+    let mut last_statistics: Option<Value> = None;
+
+    loop {
+        let message = tokio::select! {
+            message = rx.next() => {
+                let message = match message {
+                    Some(Ok(message)) if !message.is_close() => message,
+                    _ => break,
+                };
+
+                let client_message = message
+                    .to_str()
+                    .ok()
+                    .and_then(|text| serde_json::from_str::<StatisticsClientMessage>(text).ok());
+
+                if let Some(StatisticsClientMessage::Resync) = client_message {
+                    last_statistics = None;
+                    refresh.reset_immediately();
+                }
+
+                continue;
+            },
+            _ = refresh.tick() => {
+                let current = statistics.get_serialized();
+                let current_value = serde_json::to_value(&current).unwrap();
+
+                let message = match &last_statistics {
+                    None => StatisticsMessage::Snapshot {
+                        sequence: sequence + 1,
+                        statistics: Box::new(current),
+                    },
+                    Some(previous) => match get_merge_patch(previous, &current_value) {
+                        Some(changes) => StatisticsMessage::Delta {
+                            sequence: sequence + 1,
+                            changes,
+                        },
+                        // Let's not send the same statistics over and over again.
+                        None => continue,
+                    },
+                };
+
+                sequence += 1;
+                last_statistics = Some(current_value);
+
+                message
+            },
+        };
+
+        let serialized = serde_json::to_string(&message).unwrap();
+
+        if tx.send(Message::text(serialized)).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn parse_timestamp(timestamp: Option<i64>) -> Result<Option<DateTime<Utc>>, ()> {
+    match timestamp {
+        Some(timestamp) => Utc.timestamp_opt(timestamp, 0).single().map(Some).ok_or(()),
+        None => Ok(None),
+    }
+}
+
+fn get_invalid_timestamp_response() -> Box<dyn warp::Reply> {
+    Box::new(
+        Response::builder()
+            .status(http::StatusCode::BAD_REQUEST)
+            .body(
+                serde_json::to_string(&ApiError {
+                    error: "Invalid timestamp".to_string(),
+                })
+                .unwrap(),
+            ),
+    )
+}
+
+async fn get_history(
+    query: HistoryQuery,
+    statistics: Statistics,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    match (parse_timestamp(query.from), parse_timestamp(query.to)) {
+        (Ok(from), Ok(to)) => Ok(Box::new(warp::reply::json(
+            &statistics.get_history(from, to),
+        ))),
+        _ => Ok(get_invalid_timestamp_response()),
+    }
+}
+
+/// Hourly statistics, to compare periods with each other.
+pub(super) fn create_history_routes(statistics: Statistics) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(warp::query::<HistoryQuery>())
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(get_history)
+        .boxed()
+}
+
+async fn get_clients(statistics: Statistics) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&statistics.get_clients())))
+}
+
+/// Requests of each device or authenticated proxy user.
+pub(super) fn create_client_routes(statistics: Statistics) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(get_clients)
+        .boxed()
+}
+
+async fn get_timeseries(
+    query: TimeSeriesQuery,
+    statistics: Statistics,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let resolution = query.resolution.unwrap_or(Resolution::Hour);
+
+    match (parse_timestamp(query.from), parse_timestamp(query.to)) {
+        (Ok(from), Ok(to)) => Ok(Box::new(warp::reply::json(
+            &statistics.timeseries.get(resolution, from, to),
+        ))),
+        _ => Ok(get_invalid_timestamp_response()),
+    }
+}
+
+/// Requests per minute, hour or day, for charts.
+pub(super) fn create_timeseries_routes(statistics: Statistics) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(warp::query::<TimeSeriesQuery>())
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(get_timeseries)
+        .boxed()
+}
+
+async fn get_filter_statistics(statistics: Statistics) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to read configuration: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(
+        &statistics.get_filter_statistics(&configuration.filters, &configuration.custom_filters),
+    )))
+}
+
+/// Requests blocked by each filter list and custom filter.
+pub(super) fn create_filter_routes(statistics: Statistics) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(get_filter_statistics)
+        .boxed()
+}
+
+async fn get_performance(statistics: Statistics) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&statistics.get_performance())))
+}
+
+/// Latency added to requests by filter matching, TLS handshakes and rewriting.
+pub(super) fn create_performance_routes(
+    statistics: Statistics,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(get_performance)
+        .boxed()
+}
+
+async fn get_rule_performance(
+    adblock_requester: AdblockRequester,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(
+        &adblock_requester.get_rule_performance().await,
+    )))
+}
+
+async fn put_rule_profiling(
+    rule_profiling_request: RuleProfilingRequest,
+    adblock_requester: AdblockRequester,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    adblock_requester
+        .set_rule_profiling(rule_profiling_request.enabled)
+        .await;
+
+    log::info!(
+        "Rule profiling {}",
+        if rule_profiling_request.enabled {
+            "started"
+        } else {
+            "stopped"
+        }
+    );
+
+    Ok(Box::new(http::StatusCode::NO_CONTENT))
+}
+
+/// Time filter lists and their regex rules take to match requests. Profiling is off
+/// until enabled, as it matches every request once more for each list and rule.
+pub(super) fn create_rule_performance_routes(
+    adblock_requester: AdblockRequester,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(super::with_adblock_requester(adblock_requester.clone()))
+        .and_then(get_rule_performance)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(super::with_adblock_requester(adblock_requester))
+            .and_then(put_rule_profiling))
+        .boxed()
+}
+
+/// Parses a period such as `24h` or `7d`.
+fn parse_period(period: &str) -> Option<chrono::Duration> {
+    let (count, hours_per_unit) = if let Some(count) = period.strip_suffix('h') {
+        (count, 1)
+    } else if let Some(count) = period.strip_suffix('d') {
+        (count, 24)
+    } else {
+        return None;
+    };
+
+    let hours = count
+        .parse::<i64>()
+        .ok()
+        .filter(|count| *count > 0)?
+        .checked_mul(hours_per_unit)?;
+
+    chrono::Duration::try_hours(hours)
+}
+
+async fn get_top(
+    query: TopQuery,
+    statistics: Statistics,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let period = query.period.as_deref().unwrap_or(DEFAULT_TOP_PERIOD);
+    let limit = query.limit.unwrap_or(DEFAULT_TOP_LIMIT);
+
+    let error = if limit > MAX_TOP_LIMIT {
+        format!("limit can't be more than {}", MAX_TOP_LIMIT)
+    } else if let Some(period) = parse_period(period) {
+        return Ok(Box::new(warp::reply::json(&statistics.get_top(
+            query.kind,
+            Utc::now() - period,
+            limit,
+        ))));
+    } else {
+        format!(
+            "Invalid period {:?}, expected hours or days such as 24h or 7d",
+            period
+        )
+    };
+
+    Ok(Box::new(
+        Response::builder()
+            .status(http::StatusCode::BAD_REQUEST)
+            .body(serde_json::to_string(&ApiError { error }).unwrap()),
+    ))
+}
+
+/// Most blocked or allowed domains, and most blocked tracking companies, over a period.
+pub(super) fn create_top_routes(statistics: Statistics) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(warp::query::<TopQuery>())
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(get_top)
+        .boxed()
+}
+
+fn get_csv(buckets: &[ExportedBucket]) -> String {
+    let mut csv = String::from("start,proxied_requests,blocked_requests,top_blocked_domain\n");
+
+    for bucket in buckets {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            bucket.start.to_rfc3339(),
+            bucket.proxied_requests,
+            bucket.blocked_requests,
+            bucket.top_blocked_domain.as_deref().unwrap_or_default()
+        ));
+    }
+
+    csv
+}
+
+async fn export_statistics(
+    query: ExportQuery,
+    statistics: Statistics,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let from = match query.period.as_deref().map(parse_period) {
+        Some(Some(period)) => Some(Utc::now() - period),
+        Some(None) => {
+            return Ok(Box::new(
+                Response::builder()
+                    .status(http::StatusCode::BAD_REQUEST)
+                    .body(
+                        serde_json::to_string(&ApiError {
+                            error: format!(
+                                "Invalid period {:?}, expected hours or days such as 24h or 7d",
+                                query.period.unwrap()
+                            ),
+                        })
+                        .unwrap(),
+                    ),
+            ))
+        }
+        None => None,
+    };
+
+    let buckets = statistics
+        .get_history(from, None)
+        .into_iter()
+        .map(ExportedBucket::from)
+        .collect::<Vec<_>>();
+
+    let (body, content_type, extension) = match query.format.unwrap_or_default() {
+        ExportFormat::Json => (
+            serde_json::to_string(&buckets).unwrap(),
+            "application/json",
+            "json",
+        ),
+        ExportFormat::Csv => (get_csv(&buckets), "text/csv", "csv"),
+    };
+
+    Ok(Box::new(
+        Response::builder()
+            .header(
+                http::header::CONTENT_DISPOSITION,
+                format!("attachment; filename=privaxy-statistics.{extension};"),
+            )
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(body),
+    ))
+}
+
+/// Hourly statistics of a period, as JSON or CSV, for reports.
+pub(super) fn create_export_routes(statistics: Statistics) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path::end())
+        .and(warp::query::<ExportQuery>())
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(export_statistics)
+        .boxed()
+}
+
+async fn reset_statistics(statistics: Statistics) -> Result<Box<dyn warp::Reply>, Infallible> {
+    crate::statistics::reset(&statistics).await;
+
+    log::info!("Reset statistics");
+
+    Ok(Box::new(http::StatusCode::NO_CONTENT))
+}
+
+/// Starts counting over, like `statistics_reset` does on a schedule.
+pub(super) fn create_reset_routes(statistics: Statistics) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::post()
+        .and(warp::path::end())
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(reset_statistics)
+        .boxed()
+}
@@ -0,0 +1,115 @@
+use super::get_error_response;
+use crate::configuration::Configuration;
+use crate::proxy::exclusions::LocalExclusionStore;
+use crate::proxy::handshake_failures::HandshakeFailureStore;
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::mpsc::Sender;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_suggested_exclusions(
+    local_exclusions_store: LocalExclusionStore,
+    handshake_failure_store: HandshakeFailureStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    // Hosts may have been excluded since they were suggested.
+    let suggested_exclusions = handshake_failure_store
+        .get_suggested_exclusions()
+        .into_iter()
+        .filter(|suggested_exclusion| !local_exclusions_store.contains(&suggested_exclusion.host))
+        .collect::<Vec<_>>();
+
+    Ok(Box::new(warp::reply::json(&suggested_exclusions)))
+}
+
+/// Adds a suggested host to the exclusions.
+async fn apply_suggested_exclusion(
+    host: String,
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    local_exclusions_store: LocalExclusionStore,
+    handshake_failure_store: HandshakeFailureStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let host = host.to_lowercase();
+
+    if !handshake_failure_store
+        .get_suggested_exclusions()
+        .iter()
+        .any(|suggested_exclusion| suggested_exclusion.host == host)
+    {
+        return Ok(Box::new(StatusCode::NOT_FOUND));
+    }
+
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to apply suggested exclusion: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .add_exclusion(&host, local_exclusions_store)
+        .await
+    {
+        log::error!("Failed to apply suggested exclusion: {err}");
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    handshake_failure_store.remove_suggestion(&host);
+
+    configuration_updater_sender
+        .send(configuration.clone())
+        .await
+        .unwrap();
+
+    log::info!("Excluded suggested host {host}");
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn dismiss_suggested_exclusion(
+    host: String,
+    handshake_failure_store: HandshakeFailureStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    if handshake_failure_store.remove_suggestion(&host) {
+        Ok(Box::new(StatusCode::NO_CONTENT))
+    } else {
+        Ok(Box::new(StatusCode::NOT_FOUND))
+    }
+}
+
+pub(super) fn create_routes(
+    configuration_updater_sender: Sender<Configuration>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    local_exclusions_store: LocalExclusionStore,
+    handshake_failure_store: HandshakeFailureStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_handshake_failure_store = warp::any().map(move || handshake_failure_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and(super::with_local_exclusions_store(
+            local_exclusions_store.clone(),
+        ))
+        .and(with_handshake_failure_store.clone())
+        .and_then(self::get_suggested_exclusions)
+        .or(warp::post()
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(super::with_configuration_updater_sender(
+                configuration_updater_sender,
+            ))
+            .and(super::with_configuration_save_lock(configuration_save_lock))
+            .and(super::with_local_exclusions_store(local_exclusions_store))
+            .and(with_handshake_failure_store.clone())
+            .and_then(self::apply_suggested_exclusion))
+        .or(warp::delete()
+            .and(warp::path::param())
+            .and(warp::path::end())
+            .and(with_handshake_failure_store)
+            .and_then(self::dismiss_suggested_exclusion))
+        .boxed()
+}
@@ -0,0 +1,36 @@
+use crate::blocker::AdblockRequester;
+use crate::statistics::Statistics;
+use crate::system::{self, SystemStatus};
+use std::convert::Infallible;
+use warp::filters::BoxedFilter;
+use warp::Filter as RouteFilter;
+
+async fn get_status(
+    adblock_requester: AdblockRequester,
+    statistics: Statistics,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let engine = adblock_requester.get_engine_status().await;
+    let runtime_metrics = tokio::runtime::Handle::current().metrics();
+
+    Ok(Box::new(warp::reply::json(&SystemStatus {
+        uptime_secs: system::get_uptime_secs(),
+        resident_memory_bytes: system::get_resident_memory_bytes(),
+        open_connections: *statistics.open_connections.lock().unwrap(),
+        runtime_workers: runtime_metrics.num_workers(),
+        runtime_tasks: runtime_metrics.num_alive_tasks(),
+        engine,
+    })))
+}
+
+pub(super) fn create_routes(
+    adblock_requester: AdblockRequester,
+    statistics: Statistics,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    warp::get()
+        .and(warp::path("status"))
+        .and(warp::path::end())
+        .and(super::with_adblock_requester(adblock_requester))
+        .and(warp::any().map(move || statistics.clone()))
+        .and_then(self::get_status)
+        .boxed()
+}
@@ -0,0 +1,97 @@
+use super::events::StatusEvent;
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, ThreatIntelligence};
+use crate::threat_intelligence::ThreatStore;
+use std::{convert::Infallible, sync::Arc};
+use tokio::sync::broadcast;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_threat_intelligence() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get threat intelligence settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(
+        &configuration.threat_intelligence,
+    )))
+}
+
+async fn put_threat_intelligence(
+    threat_intelligence: ThreatIntelligence,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    threat_store: ThreatStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put threat intelligence settings: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_threat_intelligence(threat_intelligence, threat_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_feeds(threat_store: ThreatStore) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&threat_store.get_statuses())))
+}
+
+/// Downloads every enabled feed right away, without waiting for them to be due.
+async fn update_feeds(
+    threat_store: ThreatStore,
+    http_client: reqwest::Client,
+    status_sender: broadcast::Sender<StatusEvent>,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    threat_store
+        .update(&http_client, &status_sender, true)
+        .await;
+
+    Ok(Box::new(warp::reply::json(&threat_store.get_statuses())))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    threat_store: ThreatStore,
+    http_client: reqwest::Client,
+    status_sender: broadcast::Sender<StatusEvent>,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_threat_store = warp::any().map(move || threat_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_threat_intelligence)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_threat_store.clone())
+            .and_then(self::put_threat_intelligence))
+        .or(warp::get()
+            .and(warp::path("feeds"))
+            .and(warp::path::end())
+            .and(with_threat_store.clone())
+            .and_then(self::get_feeds))
+        .or(warp::post()
+            .and(warp::path("update"))
+            .and(warp::path::end())
+            .and(with_threat_store)
+            .and(warp::any().map(move || http_client.clone()))
+            .and(warp::any().map(move || status_sender.clone()))
+            .and_then(self::update_feeds))
+        .boxed()
+}
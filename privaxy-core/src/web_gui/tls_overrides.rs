@@ -0,0 +1,71 @@
+use super::{get_error_response, with_configuration_save_lock, ApiError};
+use crate::configuration::{Configuration, ConfigurationError};
+use crate::proxy::upstream_tls::UpstreamTlsStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::{Response, StatusCode};
+use warp::Filter as RouteFilter;
+
+async fn get_tls_overrides() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get TLS overrides: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(
+        &configuration.upstream_tls.overrides,
+    )))
+}
+
+async fn put_tls_overrides(
+    overrides: Vec<String>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    upstream_tls_store: UpstreamTlsStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put TLS overrides: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    match configuration
+        .set_tls_overrides(overrides, upstream_tls_store)
+        .await
+    {
+        Ok(()) => Ok(Box::new(StatusCode::NO_CONTENT)),
+        Err(err @ ConfigurationError::TlsOverrideError(_)) => Ok(Box::new(
+            Response::builder().status(StatusCode::BAD_REQUEST).body(
+                serde_json::to_string(&ApiError {
+                    error: err.to_string(),
+                })
+                .unwrap(),
+            ),
+        )),
+        Err(err) => Ok(Box::new(get_error_response(err))),
+    }
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    upstream_tls_store: UpstreamTlsStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_upstream_tls_store = warp::any().map(move || upstream_tls_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_tls_overrides)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_upstream_tls_store)
+            .and_then(self::put_tls_overrides))
+        .boxed()
+}
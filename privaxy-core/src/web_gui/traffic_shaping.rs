@@ -0,0 +1,75 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, TrafficShaping};
+use crate::proxy::traffic_shaping::TrafficShapingStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_traffic_shaping() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get traffic shaping: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.traffic_shaping)))
+}
+
+async fn put_traffic_shaping(
+    traffic_shaping: TrafficShaping,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    traffic_shaping_store: TrafficShapingStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put traffic shaping: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_traffic_shaping(traffic_shaping, traffic_shaping_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_status(
+    traffic_shaping_store: TrafficShapingStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(
+        &traffic_shaping_store.get_status(),
+    )))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    traffic_shaping_store: TrafficShapingStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_traffic_shaping_store = warp::any().map(move || traffic_shaping_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_traffic_shaping)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_traffic_shaping_store.clone())
+            .and_then(self::put_traffic_shaping))
+        .or(warp::get()
+            .and(warp::path("status"))
+            .and(warp::path::end())
+            .and(with_traffic_shaping_store)
+            .and_then(self::get_status))
+        .boxed()
+}
@@ -0,0 +1,71 @@
+use super::{get_error_response, with_configuration_save_lock};
+use crate::configuration::{Configuration, UrlRewrite};
+use crate::proxy::url_rewrites::UrlRewriteStore;
+use std::{convert::Infallible, sync::Arc};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Filter as RouteFilter;
+
+async fn get_url_rewrites() -> Result<Box<dyn warp::Reply>, Infallible> {
+    let configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to get URL rewrites: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    Ok(Box::new(warp::reply::json(&configuration.url_rewrites)))
+}
+
+async fn put_url_rewrites(
+    url_rewrites: Vec<UrlRewrite>,
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    url_rewrite_store: UrlRewriteStore,
+) -> Result<Box<dyn warp::Reply>, Infallible> {
+    let _guard = configuration_save_lock.lock().await;
+
+    let mut configuration = match Configuration::read_from_home().await {
+        Ok(configuration) => configuration,
+        Err(err) => {
+            log::error!("Failed to put URL rewrites: {err}");
+            return Ok(Box::new(get_error_response(err)));
+        }
+    };
+
+    if let Err(err) = configuration
+        .set_url_rewrites(url_rewrites, url_rewrite_store)
+        .await
+    {
+        return Ok(Box::new(get_error_response(err)));
+    }
+
+    Ok(Box::new(StatusCode::NO_CONTENT))
+}
+
+async fn get_hits(url_rewrite_store: UrlRewriteStore) -> Result<Box<dyn warp::Reply>, Infallible> {
+    Ok(Box::new(warp::reply::json(&url_rewrite_store.get_hits())))
+}
+
+pub(super) fn create_routes(
+    configuration_save_lock: Arc<tokio::sync::Mutex<()>>,
+    url_rewrite_store: UrlRewriteStore,
+) -> BoxedFilter<(impl warp::Reply,)> {
+    let with_url_rewrite_store = warp::any().map(move || url_rewrite_store.clone());
+
+    warp::get()
+        .and(warp::path::end())
+        .and_then(self::get_url_rewrites)
+        .or(warp::put()
+            .and(warp::path::end())
+            .and(warp::body::json())
+            .and(with_configuration_save_lock(configuration_save_lock))
+            .and(with_url_rewrite_store.clone())
+            .and_then(self::put_url_rewrites))
+        .or(warp::get()
+            .and(warp::path("hits"))
+            .and(warp::path::end())
+            .and(with_url_rewrite_store)
+            .and_then(self::get_hits))
+        .boxed()
+}
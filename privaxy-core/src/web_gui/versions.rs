@@ -0,0 +1,107 @@
+use chrono::{DateTime, TimeZone, Utc};
+use warp::filters::BoxedFilter;
+use warp::http::HeaderValue;
+use warp::Filter as RouteFilter;
+use warp::Reply;
+
+/// Version of the API a request is made to, from the prefix of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ApiVersion {
+    /// `/api`, as requested by clients written before the API was versioned.
+    Unversioned,
+    /// `/api/v1`
+    V1,
+}
+
+/// Breaking changes to the API are made in a new version. Previous versions keep being
+/// served for a while, clients are told to move on with the `Deprecation`, `Sunset` and
+/// `Link` headers of RFC 9745 and RFC 8594.
+struct Deprecation {
+    deprecated_at: DateTime<Utc>,
+    /// When requests to the version stop being served, once it is decided.
+    sunset_at: Option<DateTime<Utc>>,
+    successor: ApiVersion,
+}
+
+impl ApiVersion {
+    fn get_path_prefix(self) -> &'static str {
+        match self {
+            ApiVersion::Unversioned => "api",
+            ApiVersion::V1 => "api/v1",
+        }
+    }
+
+    fn get_deprecation(self) -> Option<Deprecation> {
+        match self {
+            ApiVersion::Unversioned => Some(Deprecation {
+                deprecated_at: Utc.timestamp_opt(1_792_108_800, 0).unwrap(),
+                sunset_at: None,
+                successor: ApiVersion::V1,
+            }),
+            ApiVersion::V1 => None,
+        }
+    }
+}
+
+/// Matches the prefix of the routes of the API, `/api` followed by its version when
+/// it is given.
+pub(super) fn with_api_version() -> BoxedFilter<(ApiVersion,)> {
+    warp::path("api")
+        .and(
+            warp::path("v1")
+                .map(|| ApiVersion::V1)
+                .or(warp::any().map(|| ApiVersion::Unversioned))
+                .unify(),
+        )
+        .boxed()
+}
+
+/// Tells clients of a deprecated version of the API where the route they requested is
+/// served in its successor.
+pub(super) fn with_deprecation_headers(
+    reply: impl Reply,
+    version: ApiVersion,
+    web_base_path: &str,
+    full_path: &str,
+) -> warp::reply::Response {
+    let mut response = reply.into_response();
+
+    let deprecation = match version.get_deprecation() {
+        Some(deprecation) => deprecation,
+        None => return response,
+    };
+
+    let route = full_path
+        .strip_prefix(web_base_path)
+        .and_then(|path| path.strip_prefix(version.get_path_prefix()))
+        .unwrap_or_default();
+    let successor_path = format!(
+        "{web_base_path}{}{route}",
+        deprecation.successor.get_path_prefix()
+    );
+
+    log::debug!("Deprecated API route requested: {full_path}, use {successor_path} instead");
+
+    let headers = response.headers_mut();
+
+    headers.insert(
+        "deprecation",
+        HeaderValue::from_str(&format!("@{}", deprecation.deprecated_at.timestamp())).unwrap(),
+    );
+
+    if let Some(sunset_at) = deprecation.sunset_at {
+        headers.insert(
+            "sunset",
+            HeaderValue::from_str(&sunset_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+                .unwrap(),
+        );
+    }
+
+    if let Ok(link) =
+        HeaderValue::from_str(&format!("<{successor_path}>; rel=\"successor-version\""))
+    {
+        headers.insert(http::header::LINK, link);
+    }
+
+    response
+}
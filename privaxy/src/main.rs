@@ -0,0 +1,31 @@
+use clap::Parser;
+use privaxy_core::startup::{Command, StartupOptions};
+use privaxy_core::ProxyBuilder;
+
+#[tokio::main]
+async fn main() {
+    let mut startup_options = StartupOptions::parse();
+
+    if let Some(Command::Bench(bench_options)) = startup_options.command.take() {
+        if let Err(err) = privaxy_core::bench::run(startup_options, bench_options).await {
+            eprintln!("Benchmark failed: {}", err);
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    let privaxy_server = match ProxyBuilder::from_startup_options(startup_options)
+        .handle_signals(true)
+        .init_logging(true)
+        .start()
+        .await
+    {
+        Ok(privaxy_server) => privaxy_server,
+        Err(err) => {
+            println!("Unable to start privaxy: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    privaxy_server.shutdown_requested().await;
+}
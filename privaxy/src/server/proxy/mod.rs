@@ -1,5 +0,0 @@
-pub(crate) mod mitm;
-pub(crate) mod serve;
-pub(crate) use mitm::serve_mitm_session;
-pub(crate) mod exclusions;
-pub(crate) mod html_rewriter;
@@ -0,0 +1,78 @@
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Version {
+    version: String,
+    build_hash: Option<String>,
+    filter_lists: usize,
+    custom_filters: usize,
+}
+
+pub enum Message {
+    Loaded(Version),
+}
+
+/// Version of Privaxy and what its blocking engine was built from.
+pub struct About {
+    version: Option<Version>,
+}
+
+impl Component for About {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let link = ctx.link().clone();
+        spawn_local(async move {
+            // Served next to the web GUI, outside of the API.
+            if let Ok(response) = Request::get("version").send().await {
+                if let Ok(version) = response.json::<Version>().await {
+                    link.send_message(Message::Loaded(version));
+                }
+            }
+        });
+
+        Self { version: None }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Loaded(version) => {
+                self.version = Some(version);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        let version = match &self.version {
+            Some(version) => version,
+            None => return html! {},
+        };
+
+        let render_row = |label: &str, value: String| {
+            html! {
+                <div class="py-3 sm:grid sm:grid-cols-3 sm:gap-4">
+                    <dt class="text-sm font-medium text-gray-500">{ label }</dt>
+                    <dd class="mt-1 text-sm text-gray-900 sm:mt-0 sm:col-span-2">{ value }</dd>
+                </div>
+            }
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "About" }</h1>
+                <dl class="mt-4 divide-y divide-gray-200">
+                    { render_row("Version", version.version.clone()) }
+                    { render_row("Build", version.build_hash.clone().unwrap_or_else(|| "Unknown".to_string())) }
+                    { render_row("Filter lists", version.filter_lists.to_string()) }
+                    { render_row("Custom filters", version.custom_filters.to_string()) }
+                </dl>
+            </>
+        }
+    }
+}
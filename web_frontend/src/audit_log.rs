@@ -0,0 +1,157 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct AuditEntry {
+    timestamp: String,
+    method: String,
+    path: String,
+    status: u16,
+    principal: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    changed_sections: Vec<String>,
+}
+
+pub enum Message {
+    Load,
+    LoadSuccess(Vec<AuditEntry>),
+    UpdateSection(String),
+}
+
+pub(crate) struct AuditLog {
+    entries: Option<Vec<AuditEntry>>,
+    section: String,
+}
+
+impl Component for AuditLog {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            entries: None,
+            section: String::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let section = self.section.trim();
+                let url = if section.is_empty() {
+                    "api/v1/audit".to_string()
+                } else {
+                    format!(
+                        "api/v1/audit?{}",
+                        url::form_urlencoded::Serializer::new(String::new())
+                            .append_pair("section", section)
+                            .finish()
+                    )
+                };
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    match Request::get(&url).send().await {
+                        Ok(response) => {
+                            if let Ok(entries) = response.json::<Vec<AuditEntry>>().await {
+                                link.send_message(Message::LoadSuccess(entries));
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Request error: {:?}", err);
+                        }
+                    }
+                });
+            }
+            Message::LoadSuccess(entries) => {
+                self.entries = Some(entries);
+            }
+            Message::UpdateSection(section) => {
+                self.section = section;
+                return false;
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_entry = |entry: &AuditEntry| {
+            let status_class = if entry.status < 400 {
+                "text-gray-500"
+            } else {
+                "text-red-600"
+            };
+            let changed_sections = if entry.changed_sections.is_empty() {
+                "No change to the configuration file".to_string()
+            } else {
+                format!("Changed {}", entry.changed_sections.join(", "))
+            };
+            let principal = match &entry.principal {
+                Some(principal) => format!("Session {}", &principal[..principal.len().min(8)]),
+                None => "Unauthenticated".to_string(),
+            };
+
+            html! {
+                <div class="py-4 text-sm">
+                    <p class="text-gray-900">
+                        <span class="font-mono">{ format!("{} {}", entry.method, entry.path) }</span>
+                        <span class={classes!("ml-2", status_class)}>{ entry.status }</span>
+                    </p>
+                    <p class="text-gray-600">{ changed_sections }</p>
+                    <p class="text-gray-500 truncate">
+                        { format!("{} · {}", entry.timestamp, principal) }
+                        { entry.ip_address.as_ref().map(|ip_address| format!(" · {}", ip_address)).unwrap_or_default() }
+                        { entry.user_agent.as_ref().map(|user_agent| format!(" · {}", user_agent)).unwrap_or_default() }
+                    </p>
+                </div>
+            }
+        };
+
+        let input_css = "shadow appearance-none border rounded w-80 py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline";
+
+        html! {
+            <>
+            <div class="pt-1.5 mb-4">
+                <h1 class="text-2xl font-bold text-gray-900">{ "Audit Log" }</h1>
+            </div>
+            <p class="text-gray-600">
+                {"Changes made through this web interface and the API, most recent first. Sessions identify devices once signing in is required."}
+            </p>
+            <div class="mt-4 flex items-center space-x-2">
+                <input value={self.section.clone()} type="text" class={input_css}
+                    placeholder="Configuration section, like filters"
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                        Message::UpdateSection(input.value())
+                    })} />
+                <PrivaxyButton
+                    color={ButtonColor::Gray}
+                    state={ButtonState::Enabled}
+                    onclick={ctx.link().callback(|_| Message::Load)}
+                    button_text={"Refresh"}
+                />
+            </div>
+            {
+                match &self.entries {
+                    None => html! { <div class="mt-4">{"Loading..."}</div> },
+                    Some(entries) if entries.is_empty() => html! {
+                        <p class="mt-4 text-gray-500 text-sm">{ "No change was recorded." }</p>
+                    },
+                    Some(entries) => html! {
+                        <div class="mt-4 border-t border-b border-gray-200 divide-y divide-gray-200">
+                            { for entries.iter().map(render_entry) }
+                        </div>
+                    },
+                }
+            }
+            </>
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use crate::button::ButtonState;
+use crate::failure_banner;
+use crate::success_banner;
+use crate::{save_button, ApiError};
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+/// Resource types as named by the API, along with a label.
+pub(crate) const RESOURCE_TYPES: [(&str, &str); 9] = [
+    ("document", "Pages"),
+    ("subdocument", "Frames"),
+    ("script", "Scripts"),
+    ("stylesheet", "Stylesheets"),
+    ("image", "Images"),
+    ("media", "Audio and video"),
+    ("font", "Fonts"),
+    ("xhr", "XHR and fetch"),
+    ("other", "Other"),
+];
+
+const BLOCK_RESPONSES: [(&str, &str); 5] = [
+    ("forbidden", "Block page, with the status code below"),
+    (
+        "interstitial",
+        "Warning page with a button to visit anyway, for pages",
+    ),
+    ("empty", "Empty 200 response"),
+    ("close_connection", "Close the connection"),
+    ("resource", "Harmless resource of the same kind"),
+];
+
+const STATUS_CODES: [(u16, &str); 3] = [
+    (403, "403 Forbidden"),
+    (204, "204 No Content, without a page"),
+    (451, "451 Unavailable For Legal Reasons"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockResponsesConfig {
+    /// Block response of each resource type.
+    #[serde(flatten)]
+    responses: BTreeMap<String, String>,
+    status_code: u16,
+    block_page_template: Option<String>,
+}
+
+pub enum Message {
+    Load,
+    LoadSuccess(BlockResponsesConfig),
+    Update(&'static str, String),
+    UpdateStatusCode(u16),
+    UpdateTemplate(String),
+    Save,
+    SaveSuccess,
+    SaveFailed(ApiError),
+    AcknowledgeError,
+    AcknowledgeSuccess,
+}
+
+pub(crate) struct BlockResponses {
+    current_config: Option<BlockResponsesConfig>,
+    remote_config: Option<BlockResponsesConfig>,
+    show_error: bool,
+    show_success: bool,
+    err_msg: String,
+}
+
+impl Component for BlockResponses {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            current_config: None,
+            remote_config: None,
+            show_error: false,
+            show_success: false,
+            err_msg: String::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/settings/block-responses");
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                if let Ok(config) = response.json::<BlockResponsesConfig>().await {
+                                    link.send_message(Message::LoadSuccess(config));
+                                }
+                            } else {
+                                log::error!(
+                                    "Failed to load block responses: {:?}",
+                                    response.status()
+                                );
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Request error: {:?}", err);
+                        }
+                    }
+                });
+            }
+            Message::LoadSuccess(config) => {
+                self.current_config = Some(config.clone());
+                self.remote_config = Some(config);
+            }
+            Message::Update(resource_type, block_response) => {
+                if let Some(config) = &mut self.current_config {
+                    config
+                        .responses
+                        .insert(resource_type.to_string(), block_response);
+                }
+            }
+            Message::UpdateStatusCode(status_code) => {
+                if let Some(config) = &mut self.current_config {
+                    config.status_code = status_code;
+                }
+            }
+            Message::UpdateTemplate(template) => {
+                if let Some(config) = &mut self.current_config {
+                    config.block_page_template = if template.is_empty() {
+                        None
+                    } else {
+                        Some(template)
+                    };
+                }
+            }
+            Message::Save => {
+                let config = match &self.current_config {
+                    Some(config) => config.clone(),
+                    None => return false,
+                };
+                let link = ctx.link().clone();
+
+                spawn_local(async move {
+                    let request = Request::put("api/v1/settings/block-responses")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&config).unwrap());
+
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                link.send_message(Message::LoadSuccess(config));
+                                link.send_message(Message::SaveSuccess);
+                            } else {
+                                link.send_message(Message::SaveFailed(
+                                    response.json::<ApiError>().await.unwrap(),
+                                ));
+                            }
+                        }
+                        Err(err) => link.send_message(Message::SaveFailed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+            }
+            Message::SaveSuccess => {
+                self.show_success = true;
+                self.show_error = false;
+                self.err_msg = String::new();
+            }
+            Message::SaveFailed(err) => {
+                self.show_success = false;
+                self.show_error = true;
+                self.err_msg = err.error;
+            }
+            Message::AcknowledgeSuccess => {
+                self.show_success = false;
+            }
+            Message::AcknowledgeError => {
+                self.show_error = false;
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_setting = |resource_type: &'static str, label: &str, value: &str| {
+            let onchange = ctx.link().callback(move |e: Event| {
+                let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+                Message::Update(resource_type, select.value())
+            });
+
+            html! {
+                <div class="py-4" style="display: flex; align-items: center; width: 100%;">
+                    <div class="text-gray-500" style="width: 200px; text-align: left; padding-right: 4px;">{ label }</div>
+                    <div style="flex-grow: 1;">
+                        <select {onchange} class="shadow border rounded w-80 py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline">
+                            { for BLOCK_RESPONSES.iter().map(|(block_response, description)| html! {
+                                <option value={*block_response} selected={*block_response == value}>{ *description }</option>
+                            }) }
+                        </select>
+                    </div>
+                </div>
+            }
+        };
+
+        let on_status_code_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdateStatusCode(select.value().parse().unwrap_or(403))
+        });
+
+        let on_template_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
+            Message::UpdateTemplate(input.value())
+        });
+
+        let save_button_state = if self.current_config != self.remote_config {
+            ButtonState::Enabled
+        } else {
+            ButtonState::Disabled
+        };
+
+        let success_banner_html = if self.show_success {
+            success_banner!(true, ctx.link().callback(|_| Message::AcknowledgeSuccess))
+        } else {
+            html! {}
+        };
+        let failure_banner_html = if self.show_error {
+            failure_banner!(
+                true,
+                ctx.link().callback(|_| Message::AcknowledgeError),
+                self.err_msg.clone()
+            )
+        } else {
+            html! {}
+        };
+
+        let save_callback = ctx.link().callback(|_| Message::Save);
+
+        html! {
+            <>
+            <div class="pt-1.5 mb-4">
+                <h1 class="text-2xl font-bold text-gray-900">{ "Block responses" }</h1>
+            </div>
+            <p class="text-gray-600">
+                {"Choose how blocked requests are answered. Some sites hang or break with the default answer. "}
+                {"Filters requesting a specific resource with "}<span class="font-mono bg-gray-100">{"$redirect"}</span>{" always take precedence."}
+            </p>
+            { success_banner_html }
+            { failure_banner_html }
+            if let Some(config) = &self.current_config {
+                <div class="mt-4 border-t border-b border-gray-200 divide-y divide-gray-200">
+                    { for RESOURCE_TYPES.iter().map(|(resource_type, label)| {
+                        let value = config.responses.get(*resource_type).map(String::as_str).unwrap_or("forbidden");
+                        render_setting(resource_type, label, value)
+                    }) }
+                </div>
+                <div class="pt-6 mb-4">
+                    <h2 class="text-xl font-bold text-gray-900">{ "Block page" }</h2>
+                </div>
+                <div class="py-4" style="display: flex; align-items: center; width: 100%;">
+                    <div class="text-gray-500" style="width: 200px; text-align: left; padding-right: 4px;">{ "Status code" }</div>
+                    <div style="flex-grow: 1;">
+                        <select onchange={on_status_code_change} class="shadow border rounded w-80 py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline">
+                            { for STATUS_CODES.iter().map(|(status_code, description)| html! {
+                                <option value={status_code.to_string()} selected={*status_code == config.status_code}>{ *description }</option>
+                            }) }
+                        </select>
+                    </div>
+                </div>
+                <p class="text-gray-600">
+                    {"Page shown in place of blocked pages. "}
+                    <span class="font-mono bg-gray-100">{"{{url}}"}</span>{", "}
+                    <span class="font-mono bg-gray-100">{"{{rule}}"}</span>{" and "}
+                    <span class="font-mono bg-gray-100">{"{{list}}"}</span>
+                    {" are replaced with the blocked url, the rule that matched and the filter list it comes from. Leave empty to use the default page."}
+                </p>
+                <textarea
+                    class="mt-4 shadow border rounded w-full h-48 py-2 px-3 font-mono text-sm text-gray-700 leading-tight focus:outline-none focus:shadow-outline"
+                    value={config.block_page_template.clone().unwrap_or_default()}
+                    oninput={on_template_input}
+                    placeholder="<html><body>Blocked {{url}}</body></html>"
+                />
+            } else {
+                <div>{"Loading..."}</div>
+            }
+            {save_button!(save_callback, save_button_state)}
+            </>
+        }
+    }
+}
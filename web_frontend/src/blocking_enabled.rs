@@ -36,7 +36,7 @@ impl Component for BlockingEnabled {
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         let base_request =
-            Request::put("/api/blocking-enabled").header("Content-Type", "application/json");
+            Request::put("api/v1/blocking-enabled").header("Content-Type", "application/json");
 
         let message_callback = ctx.link().callback(|message: Message| message);
 
@@ -83,7 +83,7 @@ impl Component for BlockingEnabled {
                 self.blocking_enabled = false;
             }
             Message::SetCurrentBlockingState => {
-                let request = Request::get("/api/blocking-enabled");
+                let request = Request::get("api/v1/blocking-enabled");
 
                 spawn_local(async move {
                     if let Ok(response) = request.send().await {
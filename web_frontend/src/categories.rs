@@ -0,0 +1,333 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentCategory {
+    name: String,
+    url: String,
+    enabled: bool,
+    clients: Vec<String>,
+    schedule: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentCategories {
+    update_interval_secs: u64,
+    categories: Vec<ContentCategory>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct CategoryStatus {
+    name: String,
+    active: bool,
+    entries: usize,
+    updated_at: Option<String>,
+    error: Option<String>,
+}
+
+pub enum Message {
+    Load,
+    Loaded(ContentCategories),
+    LoadLists,
+    LoadedLists(Vec<CategoryStatus>),
+    SetEnabled(String, bool),
+    SetClients(String, String),
+    SetSchedule(String, String),
+    Save(ContentCategories),
+    Update,
+    Failed(ApiError),
+}
+
+/// Parental controls, blocking whole categories of sites for some clients and at some
+/// times.
+pub struct Categories {
+    categories: Option<ContentCategories>,
+    statuses: Vec<CategoryStatus>,
+    is_updating: bool,
+    err_msg: Option<String>,
+}
+
+impl Categories {
+    /// Saves the categories with one of them changed.
+    fn edit(&self, ctx: &Context<Self>, name: &str, edit: impl FnOnce(&mut ContentCategory)) {
+        if let Some(categories) = &self.categories {
+            let mut categories = categories.clone();
+            if let Some(category) = categories
+                .categories
+                .iter_mut()
+                .find(|category| category.name == name)
+            {
+                edit(category);
+            }
+            ctx.link().send_message(Message::Save(categories));
+        }
+    }
+}
+
+impl Component for Categories {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            categories: None,
+            statuses: Vec::new(),
+            is_updating: false,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/categories");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<ContentCategories>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(categories) => {
+                self.categories = Some(categories);
+                ctx.link().send_message(Message::LoadLists);
+
+                true
+            }
+            Message::LoadLists => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/categories/lists");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::LoadedLists(
+                            response.json::<Vec<CategoryStatus>>().await.unwrap(),
+                        )),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::LoadedLists(statuses) => {
+                self.is_updating = false;
+                self.statuses = statuses;
+
+                true
+            }
+            Message::SetEnabled(name, enabled) => {
+                self.edit(ctx, &name, |category| category.enabled = enabled);
+
+                false
+            }
+            Message::SetClients(name, clients) => {
+                self.edit(ctx, &name, |category| {
+                    category.clients = clients
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|client| !client.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                });
+
+                false
+            }
+            Message::SetSchedule(name, schedule) => {
+                self.edit(ctx, &name, |category| {
+                    let schedule = schedule.trim();
+                    category.schedule = (!schedule.is_empty()).then(|| schedule.to_string());
+                });
+
+                false
+            }
+            Message::Save(categories) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/categories")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&categories).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Update => {
+                self.is_updating = true;
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::post("api/v1/categories/update");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::LoadedLists(
+                            response.json::<Vec<CategoryStatus>>().await.unwrap(),
+                        )),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Failed(err) => {
+                self.is_updating = false;
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_row = |category: &ContentCategory| {
+            let status = self
+                .statuses
+                .iter()
+                .find(|status| status.name == category.name);
+
+            let name = category.name.clone();
+            let on_enabled_change = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::SetEnabled(name.clone(), input.checked())
+            });
+            let name = category.name.clone();
+            let on_clients_change = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::SetClients(name.clone(), input.value())
+            });
+            let name = category.name.clone();
+            let on_schedule_change = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::SetSchedule(name.clone(), input.value())
+            });
+
+            let list_state = match status {
+                Some(CategoryStatus {
+                    error: Some(error), ..
+                }) => html! { <span class="text-red-500">{ error }</span> },
+                Some(CategoryStatus {
+                    updated_at: Some(updated_at),
+                    entries,
+                    ..
+                }) => html! { <>{ format!("{} hosts, updated {}", entries, updated_at) }</> },
+                _ if category.enabled => html! { <>{ "Downloading" }</> },
+                _ => html! { <>{ "Not downloaded" }</> },
+            };
+
+            let (label, css) = match status {
+                Some(status) if status.active => ("Blocked", "bg-red-100 text-red-800"),
+                _ if category.enabled => ("Off schedule", "bg-gray-100 text-gray-800"),
+                _ => ("Allowed", "bg-green-100 text-green-800"),
+            };
+
+            html! {
+                <tr>
+                    <td class="px-2 py-1 text-sm font-medium text-gray-900 whitespace-nowrap">
+                        <label class="flex items-center">
+                            <input onchange={on_enabled_change} type="checkbox" checked={category.enabled}
+                                class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                            { &category.name }
+                        </label>
+                    </td>
+                    <td class="px-2 py-1">
+                        <span class={classes!("rounded", "px-2", "py-1", "text-xs", "font-medium", css)}>{ label }</span>
+                    </td>
+                    <td class="px-2 py-1">
+                        <input onchange={on_clients_change} value={category.clients.join(", ")} type="text" placeholder="Every client"
+                            class="shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    </td>
+                    <td class="px-2 py-1">
+                        <input onchange={on_schedule_change} value={category.schedule.clone().unwrap_or_default()} type="text" placeholder="At all times"
+                            class="shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline font-mono" />
+                    </td>
+                    <td class="px-2 py-1 text-sm text-gray-500">{ list_state }</td>
+                </tr>
+            }
+        };
+
+        let update_button_state = if self.is_updating {
+            ButtonState::Loading
+        } else if self.categories.is_none() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Categories" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "Block whole categories of sites, whether blocking is enabled or not." }</p>
+                    <p>
+                        { "Categories apply to the comma separated clients, addresses, networks or proxy users they list, and to every client otherwise. " }
+                        { "A schedule is a cron expression matching the minutes during which the category is blocked, such as " }
+                        <span class="font-mono bg-gray-100">{ "* 21-23,0-6 * * *" }</span>{ " for nights." }
+                    </p>
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                <div class="mt-4 overflow-x-auto">
+                    <table class="min-w-full">
+                        <thead>
+                            <tr>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Category" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Now" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Clients" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Schedule" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "List" }</th>
+                            </tr>
+                        </thead>
+                        <tbody class="divide-y divide-gray-200">
+                            { for self.categories.iter().flat_map(|categories| categories.categories.iter()).map(render_row) }
+                        </tbody>
+                    </table>
+                </div>
+                <div class="mt-4">
+                    <PrivaxyButton color={ButtonColor::Blue} state={update_button_state} onclick={ctx.link().callback(|_| Message::Update)} button_text="Update lists" />
+                </div>
+            </>
+        }
+    }
+}
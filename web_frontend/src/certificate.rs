@@ -0,0 +1,115 @@
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct InstallInstructions {
+    platform: String,
+    download: String,
+    requires_password: bool,
+    steps: Vec<String>,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<InstallInstructions>),
+}
+
+/// Downloads of the CA certificate in the format each platform expects, along with the
+/// steps to trust it.
+pub struct CertificateInstall {
+    install_instructions: Vec<InstallInstructions>,
+}
+
+impl Component for CertificateInstall {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            install_instructions: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/ca/install-instructions");
+
+                    if let Ok(response) = request.send().await {
+                        if response.ok() {
+                            if let Ok(install_instructions) =
+                                response.json::<Vec<InstallInstructions>>().await
+                            {
+                                link.send_message(Message::Loaded(install_instructions));
+                            }
+                        }
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(install_instructions) => {
+                self.install_instructions = install_instructions;
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        let download_class = "inline-flex items-center px-4 py-2 border border-transparent text-sm font-medium rounded-md shadow-sm text-white bg-gray-800 hover:bg-gray-900 focus:outline-none focus:ring-2 focus:ring-offset-2 focus:ring-gray-500";
+
+        let render_instructions = |instructions: &InstallInstructions| {
+            let download_url = format!("api/v1/ca/{}", instructions.download);
+
+            // The password protected download is a form submission so that the browser
+            // saves the response as it does for links.
+            let download = if instructions.requires_password {
+                html! {
+                    <form method="post" action={download_url} class="flex items-center space-x-4">
+                        <input name="password" type="password" required=true placeholder="Password"
+                            class="shadow appearance-none border rounded w-56 py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                        <button type="submit" class={download_class}>{ "Download" }</button>
+                    </form>
+                }
+            } else {
+                html! {
+                    <a href={download_url} class={download_class}>{ "Download" }</a>
+                }
+            };
+
+            html! {
+                <div class="mt-6">
+                    <h2 class="text-lg font-medium text-gray-900">{ &instructions.platform }</h2>
+                    <ol class="mt-2 mb-4 list-decimal list-inside text-gray-600">
+                        { for instructions.steps.iter().map(|step| html! { <li>{ step }</li> }) }
+                    </ol>
+                    { download }
+                </div>
+            }
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Certificate" }</h1>
+                <div class="text-gray-600">
+                    <p>
+                        { "Devices have to trust the Privaxy CA certificate for HTTPS requests to be filtered. " }
+                        { "The certificate is available as " }
+                        <a href="api/v1/ca/certificate.pem" class="text-blue-600 hover:text-blue-800">{ "PEM" }</a>
+                        { " and " }
+                        <a href="api/v1/ca/certificate.der" class="text-blue-600 hover:text-blue-800">{ "DER" }</a>
+                        { ", or as a password protected PKCS#12 file." }
+                    </p>
+                </div>
+                { for self.install_instructions.iter().map(render_instructions) }
+            </>
+        }
+    }
+}
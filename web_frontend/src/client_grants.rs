@@ -0,0 +1,301 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+const LEVELS: [(&str, &str); 2] = [
+    ("relaxed", "No HTTPS interception, no certificate needed"),
+    ("unfiltered", "Nothing is blocked"),
+];
+
+const DURATIONS: [(u64, &str); 4] = [
+    (60 * 60, "1 hour"),
+    (4 * 60 * 60, "4 hours"),
+    (24 * 60 * 60, "24 hours"),
+    (7 * 24 * 60 * 60, "7 days"),
+];
+
+/// Number of audit trail entries shown.
+const AUDIT_TRAIL_SHOWN: usize = 20;
+
+#[derive(Debug, Serialize)]
+struct ClientGrantRequest {
+    client: String,
+    level: String,
+    duration: u64,
+    note: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClientGrant {
+    id: u64,
+    client: String,
+    level: String,
+    note: Option<String>,
+    expires_at: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClientGrantAuditEntry {
+    now: String,
+    action: String,
+    by: Option<String>,
+    grant: ClientGrant,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<ClientGrant>, Vec<ClientGrantAuditEntry>),
+    UpdateClient(String),
+    UpdateLevel(String),
+    UpdateDuration(u64),
+    UpdateNote(String),
+    Grant,
+    Revoke(u64),
+    Failed(ApiError),
+}
+
+/// Grants visitors relaxed filtering for a while, with an audit trail of changes.
+pub struct ClientGrants {
+    grants: Vec<ClientGrant>,
+    audit_trail: Vec<ClientGrantAuditEntry>,
+    client: String,
+    level: String,
+    duration: u64,
+    note: String,
+    err_msg: Option<String>,
+}
+
+fn level_label(level: &str) -> &str {
+    match level {
+        "relaxed" => "Relaxed",
+        "unfiltered" => "Unfiltered",
+        level => level,
+    }
+}
+
+impl Component for ClientGrants {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            grants: Vec::new(),
+            audit_trail: Vec::new(),
+            client: String::new(),
+            level: "relaxed".to_string(),
+            duration: 24 * 60 * 60,
+            note: String::new(),
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let grants = match Request::get("api/v1/client-grants").send().await {
+                        Ok(response) if response.ok() => response.json::<Vec<ClientGrant>>().await,
+                        _ => return,
+                    };
+                    let audit_trail = match Request::get("api/v1/client-grants/audit").send().await
+                    {
+                        Ok(response) if response.ok() => {
+                            response.json::<Vec<ClientGrantAuditEntry>>().await
+                        }
+                        _ => return,
+                    };
+
+                    if let (Ok(grants), Ok(audit_trail)) = (grants, audit_trail) {
+                        link.send_message(Message::Loaded(grants, audit_trail));
+                    }
+                });
+
+                return false;
+            }
+            Message::Loaded(grants, audit_trail) => {
+                self.grants = grants;
+                self.audit_trail = audit_trail;
+            }
+            Message::UpdateClient(client) => {
+                self.client = client;
+            }
+            Message::UpdateLevel(level) => {
+                self.level = level;
+            }
+            Message::UpdateDuration(duration) => {
+                self.duration = duration;
+            }
+            Message::UpdateNote(note) => {
+                self.note = note;
+            }
+            Message::Grant => {
+                if self.client.trim().is_empty() {
+                    return false;
+                }
+
+                self.err_msg = None;
+
+                let grant_request = ClientGrantRequest {
+                    client: self.client.trim().to_string(),
+                    level: self.level.clone(),
+                    duration: self.duration,
+                    note: Some(self.note.clone()).filter(|note| !note.trim().is_empty()),
+                };
+                self.client = String::new();
+                self.note = String::new();
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::post("api/v1/client-grants")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&grant_request).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+            }
+            Message::Revoke(id) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::delete(&format!("api/v1/client-grants/{}", id));
+
+                    let _result = request.send().await;
+
+                    link.send_message(Message::Load);
+                });
+
+                return false;
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let on_client_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateClient(input.value())
+        });
+        let on_note_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNote(input.value())
+        });
+        let on_level_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdateLevel(select.value())
+        });
+        let on_duration_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdateDuration(select.value().parse().unwrap_or(60 * 60))
+        });
+
+        let button_state = if self.client.trim().is_empty() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        let input_css = "shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline";
+
+        let render_grant = |grant: &ClientGrant| {
+            let id = grant.id;
+            let onclick = ctx.link().callback(move |_| Message::Revoke(id));
+
+            html! {
+                <li class="py-3 flex justify-between items-center">
+                    <div>
+                        <p class="text-sm font-medium text-gray-900">
+                            { &grant.client }
+                            <span class="ml-2 text-xs text-gray-500">{ level_label(&grant.level) }</span>
+                        </p>
+                        <p class="text-sm text-gray-500">
+                            { format!("Until {}", grant.expires_at) }
+                            if let Some(note) = &grant.note {
+                                { format!(" - {}", note) }
+                            }
+                        </p>
+                    </div>
+                    <button {onclick} class="text-sm text-red-600 hover:text-red-800">{ "Revoke" }</button>
+                </li>
+            }
+        };
+
+        let render_audit_entry = |entry: &ClientGrantAuditEntry| {
+            html! {
+                <li class="py-1 text-xs text-gray-500">
+                    { format!(
+                        "{}: {} access for {} {}{}",
+                        entry.now,
+                        level_label(&entry.grant.level),
+                        entry.grant.client,
+                        entry.action,
+                        entry.by.as_ref().map(|by| format!(" by {}", by)).unwrap_or_default(),
+                    ) }
+                </li>
+            }
+        };
+
+        html! {
+            <div class="mt-8 bg-white overflow-hidden shadow rounded-lg divide-y divide-gray-200">
+                <div class="px-4 py-5 sm:px-6">
+                    <h3 class="text-lg font-medium">{ "Visitor access" }</h3>
+                    <p class="text-sm text-gray-500">
+                        { "Relax filtering for a device for a while, such as a visitor's phone that doesn't trust the Privaxy certificate. " }
+                        { "Devices are identified by IP address, network or client name." }
+                    </p>
+                </div>
+                <div class="px-4 py-5 sm:p-6">
+                    <div class="grid grid-cols-1 gap-4 md:grid-cols-4">
+                        <input oninput={on_client_input} value={self.client.clone()} type="text" placeholder="192.168.1.42" class={input_css} />
+                        <select onchange={on_level_change} class={input_css}>
+                            { for LEVELS.iter().map(|(level, label)| html! {
+                                <option value={*level} selected={*level == self.level}>{ *label }</option>
+                            }) }
+                        </select>
+                        <select onchange={on_duration_change} class={input_css}>
+                            { for DURATIONS.iter().map(|(duration, label)| html! {
+                                <option value={duration.to_string()} selected={*duration == self.duration}>{ *label }</option>
+                            }) }
+                        </select>
+                        <input oninput={on_note_input} value={self.note.clone()} type="text" placeholder="Note (optional)" class={input_css} />
+                    </div>
+                    <div class="mt-4">
+                        <PrivaxyButton color={ButtonColor::Blue} state={button_state} onclick={ctx.link().callback(|_| Message::Grant)} button_text="Grant access" />
+                    </div>
+                    if let Some(err_msg) = &self.err_msg {
+                        <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                    }
+                    if !self.grants.is_empty() {
+                        <ul class="mt-4 divide-y divide-gray-200">
+                            { for self.grants.iter().map(render_grant) }
+                        </ul>
+                    }
+                    if !self.audit_trail.is_empty() {
+                        <details class="mt-4">
+                            <summary class="text-sm text-gray-700 cursor-pointer">{ "History" }</summary>
+                            <ul class="mt-2">
+                                { for self.audit_trail.iter().take(AUDIT_TRAIL_SHOWN).map(render_audit_entry) }
+                            </ul>
+                        </details>
+                    }
+                </div>
+            </div>
+        }
+    }
+}
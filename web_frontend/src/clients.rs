@@ -0,0 +1,173 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::ApiError;
+use num_format::{Locale, ToFormattedString};
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TopEntry {
+    name: String,
+    requests: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ClientStatistics {
+    client: String,
+    requests: u64,
+    blocked_requests: u64,
+    top_blocked_domains: Vec<TopEntry>,
+    last_seen: String,
+}
+
+impl ClientStatistics {
+    fn get_blocked_percent(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+
+        self.blocked_requests as f64 * 100.0 / self.requests as f64
+    }
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<ClientStatistics>),
+    Failed(ApiError),
+}
+
+/// Requests of each device, or of each authenticated proxy user, most blocked first.
+pub struct Clients {
+    clients: Option<Vec<ClientStatistics>>,
+    err_msg: Option<String>,
+}
+
+impl Component for Clients {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            clients: None,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+
+                spawn_local(async move {
+                    match Request::get("api/v1/statistics/clients").send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<Vec<ClientStatistics>>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(clients) => {
+                self.clients = Some(clients);
+                self.err_msg = None;
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_client = |client: &ClientStatistics| {
+            html! {
+                <tr key={client.client.clone()}>
+                    <td class="px-6 py-4 whitespace-nowrap text-sm font-medium text-gray-900">
+                        { &client.client }
+                        <p class="mt-1 text-xs text-gray-500">{ format!("Last seen {}", client.last_seen) }</p>
+                    </td>
+                    <td class="px-6 py-4 whitespace-nowrap text-sm text-gray-500">
+                        { client.requests.to_formatted_string(&Locale::en) }
+                    </td>
+                    <td class="px-6 py-4 whitespace-nowrap text-sm text-gray-500">
+                        { client.blocked_requests.to_formatted_string(&Locale::en) }
+                        <span class="ml-2 text-xs text-gray-400">{ format!("{:.1}%", client.get_blocked_percent()) }</span>
+                    </td>
+                    <td class="px-6 py-4 text-sm text-gray-500">
+                        <ol role="list">
+                            { for client.top_blocked_domains.iter().map(|domain| html! {
+                                <li class="flex justify-between space-x-3">
+                                    <span class="truncate">{ &domain.name }</span>
+                                    <span class="whitespace-nowrap">{ domain.requests.to_formatted_string(&Locale::en) }</span>
+                                </li>
+                            }) }
+                        </ol>
+                    </td>
+                </tr>
+            }
+        };
+
+        let header_css =
+            "px-6 py-3 text-left text-xs font-medium text-gray-500 uppercase tracking-wider";
+
+        html! {
+            <>
+            <div class="md:flex md:justify-between md:items-center">
+                <h1 class="text-2xl font-bold text-gray-900 pt-1.5">{ "Clients" }</h1>
+                <PrivaxyButton
+                    color={ButtonColor::Gray}
+                    state={ButtonState::Enabled}
+                    onclick={ctx.link().callback(|_| Message::Load)}
+                    button_text={"Refresh"}
+                />
+            </div>
+            <p class="mt-2 text-gray-600">
+                {"Requests of each device since statistics were last reset. Requests of authenticated proxy users are counted for the user rather than for their device."}
+            </p>
+            if let Some(err_msg) = &self.err_msg {
+                <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+            }
+            {
+                match &self.clients {
+                    None => html! { <div class="mt-4">{"Loading..."}</div> },
+                    Some(clients) if clients.is_empty() => html! {
+                        <p class="mt-4 text-gray-500 text-sm">{ "No request was proxied yet." }</p>
+                    },
+                    Some(clients) => html! {
+                        <div class="mt-4 shadow overflow-hidden border-b border-gray-200 sm:rounded-lg">
+                            <table class="min-w-full divide-y divide-gray-200">
+                                <thead class="bg-gray-50">
+                                    <tr>
+                                        <th scope="col" class={header_css}>{"Client"}</th>
+                                        <th scope="col" class={header_css}>{"Requests"}</th>
+                                        <th scope="col" class={header_css}>{"Blocked"}</th>
+                                        <th scope="col" class={header_css}>{"Top blocked domains"}</th>
+                                    </tr>
+                                </thead>
+                                <tbody class="bg-white divide-y divide-gray-200">
+                                    { for clients.iter().map(render_client) }
+                                </tbody>
+                            </table>
+                        </div>
+                    },
+                }
+            }
+            </>
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentRewriteRule {
+    host: String,
+    pattern: String,
+    replacement: String,
+    case_insensitive: bool,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<ContentRewriteRule>),
+    UpdateNewHost(String),
+    UpdateNewPattern(String),
+    UpdateNewReplacement(String),
+    UpdateNewCaseInsensitive(bool),
+    Add,
+    Delete(usize),
+    Save(Vec<ContentRewriteRule>),
+    Failed(ApiError),
+}
+
+/// Regular expression replacements made in the text responses of hosts.
+pub struct ContentRewriteRules {
+    rules: Option<Vec<ContentRewriteRule>>,
+    new_host: String,
+    new_pattern: String,
+    new_replacement: String,
+    new_case_insensitive: bool,
+    err_msg: Option<String>,
+}
+
+impl Component for ContentRewriteRules {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            rules: None,
+            new_host: String::new(),
+            new_pattern: String::new(),
+            new_replacement: String::new(),
+            new_case_insensitive: false,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/content-rewrite-rules");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<Vec<ContentRewriteRule>>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(rules) => {
+                self.rules = Some(rules);
+
+                true
+            }
+            Message::UpdateNewHost(new_host) => {
+                self.new_host = new_host;
+
+                true
+            }
+            Message::UpdateNewPattern(new_pattern) => {
+                self.new_pattern = new_pattern;
+
+                true
+            }
+            Message::UpdateNewReplacement(new_replacement) => {
+                self.new_replacement = new_replacement;
+
+                true
+            }
+            Message::UpdateNewCaseInsensitive(new_case_insensitive) => {
+                self.new_case_insensitive = new_case_insensitive;
+
+                true
+            }
+            Message::Add => {
+                let host = self.new_host.trim().to_lowercase();
+                if host.is_empty() || self.new_pattern.is_empty() {
+                    return false;
+                }
+
+                if let Some(rules) = &self.rules {
+                    let mut rules = rules.clone();
+                    rules.push(ContentRewriteRule {
+                        host,
+                        pattern: self.new_pattern.clone(),
+                        replacement: self.new_replacement.clone(),
+                        case_insensitive: self.new_case_insensitive,
+                    });
+
+                    self.new_pattern = String::new();
+                    self.new_replacement = String::new();
+                    ctx.link().send_message(Message::Save(rules));
+                }
+
+                true
+            }
+            Message::Delete(index) => {
+                if let Some(rules) = &self.rules {
+                    let mut rules = rules.clone();
+                    rules.remove(index);
+                    ctx.link().send_message(Message::Save(rules));
+                }
+
+                false
+            }
+            Message::Save(rules) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/content-rewrite-rules")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&rules).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_row = |(index, rule): (usize, &ContentRewriteRule)| {
+            let on_delete = ctx.link().callback(move |_| Message::Delete(index));
+
+            html! {
+                <tr>
+                    <td class="px-2 py-1 text-sm font-medium text-gray-900 whitespace-nowrap">{ &rule.host }</td>
+                    <td class="px-2 py-1 text-sm font-mono text-gray-700 break-all">
+                        { &rule.pattern }
+                        if rule.case_insensitive {
+                            <span class="ml-2 rounded px-2 py-1 text-xs font-medium bg-gray-100 text-gray-800">{ "i" }</span>
+                        }
+                    </td>
+                    <td class="px-2 py-1 text-sm font-mono text-gray-700 break-all">{ &rule.replacement }</td>
+                    <td class="px-2 py-1 text-right">
+                        <button onclick={on_delete} class="text-sm text-red-600 hover:text-red-800">{ "Remove" }</button>
+                    </td>
+                </tr>
+            }
+        };
+
+        let on_new_host_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewHost(input.value())
+        });
+
+        let on_new_pattern_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewPattern(input.value())
+        });
+
+        let on_new_replacement_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewReplacement(input.value())
+        });
+
+        let on_new_case_insensitive_change = ctx.link().callback(|e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewCaseInsensitive(input.checked())
+        });
+
+        let add_button_state = if self.new_host.trim().is_empty()
+            || self.new_pattern.is_empty()
+            || self.rules.is_none()
+        {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Content rewriting" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "Replace text in the pages, scripts and other text responses of a host and its subdomains. Use " }<span class="font-mono bg-gray-100">{ "*" }</span>{ " to rewrite responses of every host." }</p>
+                    <p>{ "Patterns are regular expressions, their groups can be used in replacements as " }<span class="font-mono bg-gray-100">{ "$1" }</span>{ ". " }<span class="font-mono bg-gray-100">{ "$replace" }</span>{ " rules of filters are applied as well." }</p>
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                <div class="mt-4 overflow-x-auto">
+                    <table class="min-w-full">
+                        <thead>
+                            <tr>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Host" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Pattern" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Replacement" }</th>
+                                <th scope="col"></th>
+                            </tr>
+                        </thead>
+                        <tbody class="divide-y divide-gray-200">
+                            { for self.rules.iter().flat_map(|rules| rules.iter().enumerate()).map(render_row) }
+                        </tbody>
+                    </table>
+                </div>
+                <div class="mt-4 flex flex-wrap items-center gap-3">
+                    <input oninput={on_new_host_input} value={self.new_host.clone()} type="text" placeholder="example.com"
+                        class="shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <input oninput={on_new_pattern_input} value={self.new_pattern.clone()} type="text" placeholder="Pattern"
+                        class="shadow appearance-none border rounded py-2 px-3 font-mono text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <input oninput={on_new_replacement_input} value={self.new_replacement.clone()} type="text" placeholder="Replacement"
+                        class="shadow appearance-none border rounded py-2 px-3 font-mono text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <label class="flex items-center text-sm text-gray-700">
+                        <input onchange={on_new_case_insensitive_change} type="checkbox" checked={self.new_case_insensitive}
+                            class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                        { "Case insensitive" }
+                    </label>
+                    <PrivaxyButton color={ButtonColor::Blue} state={add_button_state} onclick={ctx.link().callback(|_| Message::Add)} button_text="Add rule" />
+                </div>
+            </>
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CookieRule {
+    host: String,
+    action: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CookieRulesConfig {
+    block_third_party: bool,
+    strip_tracker_cookies: bool,
+    rules: Vec<CookieRule>,
+}
+
+pub enum Message {
+    Load,
+    Loaded(CookieRulesConfig),
+    SetBlockThirdParty(bool),
+    SetStripTrackerCookies(bool),
+    UpdateNewHost(String),
+    UpdateNewAction(String),
+    Add,
+    Delete(String),
+    Save(CookieRulesConfig),
+    Failed(ApiError),
+}
+
+/// Third-party and tracker cookie blocking, and per host allow and deny rules.
+pub struct CookieRules {
+    cookie_rules: Option<CookieRulesConfig>,
+    new_host: String,
+    new_action: String,
+    err_msg: Option<String>,
+}
+
+impl Component for CookieRules {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            cookie_rules: None,
+            new_host: String::new(),
+            new_action: "deny".to_string(),
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/cookie-rules");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<CookieRulesConfig>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(cookie_rules) => {
+                self.cookie_rules = Some(cookie_rules);
+
+                true
+            }
+            Message::SetBlockThirdParty(block_third_party) => {
+                if let Some(cookie_rules) = &self.cookie_rules {
+                    let mut cookie_rules = cookie_rules.clone();
+                    cookie_rules.block_third_party = block_third_party;
+                    ctx.link().send_message(Message::Save(cookie_rules));
+                }
+
+                false
+            }
+            Message::SetStripTrackerCookies(strip_tracker_cookies) => {
+                if let Some(cookie_rules) = &self.cookie_rules {
+                    let mut cookie_rules = cookie_rules.clone();
+                    cookie_rules.strip_tracker_cookies = strip_tracker_cookies;
+                    ctx.link().send_message(Message::Save(cookie_rules));
+                }
+
+                false
+            }
+            Message::UpdateNewHost(new_host) => {
+                self.new_host = new_host;
+
+                true
+            }
+            Message::UpdateNewAction(new_action) => {
+                self.new_action = new_action;
+
+                true
+            }
+            Message::Add => {
+                let host = self.new_host.trim().to_lowercase();
+                if host.is_empty() {
+                    return false;
+                }
+
+                if let Some(cookie_rules) = &self.cookie_rules {
+                    let mut cookie_rules = cookie_rules.clone();
+                    cookie_rules.rules.retain(|rule| rule.host != host);
+                    cookie_rules.rules.push(CookieRule {
+                        host,
+                        action: self.new_action.clone(),
+                    });
+
+                    self.new_host = String::new();
+                    ctx.link().send_message(Message::Save(cookie_rules));
+                }
+
+                true
+            }
+            Message::Delete(host) => {
+                if let Some(cookie_rules) = &self.cookie_rules {
+                    let mut cookie_rules = cookie_rules.clone();
+                    cookie_rules.rules.retain(|rule| rule.host != host);
+                    ctx.link().send_message(Message::Save(cookie_rules));
+                }
+
+                false
+            }
+            Message::Save(cookie_rules) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/cookie-rules")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&cookie_rules).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_checkbox = |label: &'static str, checked: bool, message: fn(bool) -> Message| {
+            let onchange = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                message(input.checked())
+            });
+
+            html! {
+                <label class="flex items-center text-sm text-gray-700 mt-2">
+                    <input {onchange} type="checkbox" {checked} disabled={self.cookie_rules.is_none()}
+                        class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                    { label }
+                </label>
+            }
+        };
+
+        let render_row = |rule: &CookieRule| {
+            let host = rule.host.clone();
+            let on_delete = ctx.link().callback(move |_| Message::Delete(host.clone()));
+            let (label, css) = match rule.action.as_str() {
+                "allow" => ("Allow", "bg-green-100 text-green-800"),
+                _ => ("Deny", "bg-red-100 text-red-800"),
+            };
+
+            html! {
+                <tr>
+                    <td class="px-2 py-1 text-sm font-medium text-gray-900 whitespace-nowrap">{ &rule.host }</td>
+                    <td class="px-2 py-1">
+                        <span class={classes!("rounded", "px-2", "py-1", "text-xs", "font-medium", css)}>{ label }</span>
+                    </td>
+                    <td class="px-2 py-1 text-right">
+                        <button onclick={on_delete} class="text-sm text-red-600 hover:text-red-800">{ "Remove" }</button>
+                    </td>
+                </tr>
+            }
+        };
+
+        let on_new_host_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewHost(input.value())
+        });
+
+        let on_new_action_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdateNewAction(select.value())
+        });
+
+        let add_button_state = if self.new_host.trim().is_empty() || self.cookie_rules.is_none() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        let (block_third_party, strip_tracker_cookies) =
+            self.cookie_rules
+                .as_ref()
+                .map_or((false, false), |cookie_rules| {
+                    (
+                        cookie_rules.block_third_party,
+                        cookie_rules.strip_tracker_cookies,
+                    )
+                });
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Cookies" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "Remove cookies sent to, or set by, hosts that don't need them." }</p>
+                    <p>{ "Rules of a host apply to its subdomains and take precedence over the options below." }</p>
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                <div class="mt-4">
+                    { render_checkbox("Block third-party cookies", block_third_party, Message::SetBlockThirdParty) }
+                    { render_checkbox("Strip cookies set by known trackers", strip_tracker_cookies, Message::SetStripTrackerCookies) }
+                </div>
+                <div class="mt-4 overflow-x-auto">
+                    <table class="min-w-full">
+                        <thead>
+                            <tr>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Host" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Cookies" }</th>
+                                <th scope="col"></th>
+                            </tr>
+                        </thead>
+                        <tbody class="divide-y divide-gray-200">
+                            { for self.cookie_rules.iter().flat_map(|cookie_rules| cookie_rules.rules.iter()).map(render_row) }
+                        </tbody>
+                    </table>
+                </div>
+                <div class="mt-4 flex space-x-3">
+                    <input oninput={on_new_host_input} value={self.new_host.clone()} type="text" placeholder="example.com"
+                        class="shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <select onchange={on_new_action_change} class="shadow border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline">
+                        <option value="deny" selected={self.new_action == "deny"}>{ "Deny" }</option>
+                        <option value="allow" selected={self.new_action == "allow"}>{ "Allow" }</option>
+                    </select>
+                    <PrivaxyButton color={ButtonColor::Blue} state={add_button_state} onclick={ctx.link().callback(|_| Message::Add)} button_text="Add rule" />
+                </div>
+            </>
+        }
+    }
+}
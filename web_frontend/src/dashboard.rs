@@ -1,11 +1,17 @@
 use crate::blocking_enabled::BlockingEnabled;
+use crate::client_grants::ClientGrants;
+use crate::get_websocket_url;
+use crate::requests::MatchedFilter;
+use crate::statistics_comparison::StatisticsComparison;
+use crate::top_companies::TopCompanies;
 use futures::future::{AbortHandle, Abortable};
-use futures::StreamExt;
+use futures::{SinkExt, StreamExt};
 use gloo_timers::future::TimeoutFuture;
 use num_format::{Locale, ToFormattedString};
 use reqwasm::websocket::futures::WebSocket;
 use serde::Deserialize;
-use std::io::Cursor;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
 use wasm_bindgen_futures::spawn_local;
 use yew::{html, Component, Context, Html};
 
@@ -16,8 +22,48 @@ pub struct Message {
     modified_responses: Option<u64>,
     #[serde(with = "tuple_vec_map")]
     top_blocked_paths: Vec<(String, u64)>,
+    #[serde(default)]
+    top_blocked_paths_filters: HashMap<String, MatchedFilter>,
     #[serde(with = "tuple_vec_map")]
     top_clients: Vec<(String, u64)>,
+    #[serde(default, with = "tuple_vec_map")]
+    top_users: Vec<(String, u64)>,
+}
+
+/// Sent over the statistics socket, a snapshot first and then the changes made to it.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StatisticsMessage {
+    Snapshot { sequence: u64, statistics: Value },
+    Delta { sequence: u64, changes: Value },
+}
+
+/// Applies a JSON merge patch (RFC 7386) to `target`.
+fn apply_merge_patch(target: &mut Value, patch: Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        patch => {
+            *target = patch;
+            return;
+        }
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let target = target.as_object_mut().unwrap();
+
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            apply_merge_patch(target.entry(key).or_insert(Value::Null), value);
+        }
+    }
+}
+
+fn sort_by_count(list: &mut [(String, u64)]) {
+    list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
 }
 
 pub struct Dashboard {
@@ -36,7 +82,7 @@ impl Component for Dashboard {
         let future = Abortable::new(
             async move {
                 loop {
-                    let ws = match WebSocket::open("/api/statistics") {
+                    let ws = match WebSocket::open(&get_websocket_url("api/v1/statistics")) {
                         Ok(ws) => ws,
                         Err(_err) => {
                             log::warn!("Unable to connect to websocket, trying again.");
@@ -47,35 +93,77 @@ impl Component for Dashboard {
                         }
                     };
 
-                    let (_write, mut read) = ws.split();
+                    let (mut write, mut read) = ws.split();
+
+                    // Statistics as of the last message, which deltas are applied to.
+                    let mut statistics = Value::Null;
+                    let mut last_sequence = 0;
+                    let mut is_resyncing = false;
 
                     while let Some(result) = read.next().await {
                         match result {
                             Ok(msg) => {
-                                let message = match msg {
+                                let statistics_message = match msg {
                                     reqwasm::websocket::Message::Text(s) => {
-                                        let cursor = Cursor::new(s.as_bytes());
-                                        let mut deserializer =
-                                            serde_json::Deserializer::from_reader(cursor)
-                                                .into_iter::<Message>();
-
-                                        match deserializer.next() {
-                                            Some(Ok(message)) => message,
-                                            Some(Err(e)) => {
+                                        match serde_json::from_str::<StatisticsMessage>(&s) {
+                                            Ok(statistics_message) => statistics_message,
+                                            Err(e) => {
                                                 log::error!(
                                                     "Failed to deserialize message: {:?}",
                                                     e
                                                 );
                                                 continue;
                                             }
-                                            None => {
-                                                log::warn!("No message received");
-                                                continue;
-                                            }
                                         }
                                     }
                                     reqwasm::websocket::Message::Bytes(_) => unreachable!(),
                                 };
+
+                                match statistics_message {
+                                    StatisticsMessage::Snapshot {
+                                        sequence,
+                                        statistics: snapshot,
+                                    } => {
+                                        statistics = snapshot;
+                                        last_sequence = sequence;
+                                        is_resyncing = false;
+                                    }
+                                    StatisticsMessage::Delta { .. } if is_resyncing => continue,
+                                    StatisticsMessage::Delta { sequence, changes } => {
+                                        // The statistics can't be patched once a message
+                                        // was missed.
+                                        if sequence != last_sequence + 1 {
+                                            is_resyncing = true;
+                                            let _result = write
+                                                .send(reqwasm::websocket::Message::Text(
+                                                    r#"{"type":"resync"}"#.to_string(),
+                                                ))
+                                                .await;
+                                            continue;
+                                        }
+
+                                        apply_merge_patch(&mut statistics, changes);
+                                        last_sequence = sequence;
+                                    }
+                                }
+
+                                let mut message =
+                                    match serde_json::from_value::<Message>(statistics.clone()) {
+                                        Ok(message) => message,
+                                        Err(e) => {
+                                            log::error!(
+                                                "Failed to deserialize statistics: {:?}",
+                                                e
+                                            );
+                                            continue;
+                                        }
+                                    };
+
+                                // Lists lose their order once patched.
+                                sort_by_count(&mut message.top_blocked_paths);
+                                sort_by_count(&mut message.top_clients);
+                                sort_by_count(&mut message.top_users);
+
                                 message_callback.emit(message);
                             }
                             Err(e) => {
@@ -103,7 +191,9 @@ impl Component for Dashboard {
                 blocked_requests: None,
                 modified_responses: None,
                 top_blocked_paths: Vec::new(),
+                top_blocked_paths_filters: HashMap::new(),
                 top_clients: Vec::new(),
+                top_users: Vec::new(),
             },
         }
     }
@@ -124,12 +214,23 @@ impl Component for Dashboard {
         }
 
         fn render_list_element(key: &str, count: u64) -> Html {
+            render_list_element_with_details(key, None, count)
+        }
+
+        fn render_list_element_with_details(
+            key: &str,
+            details: Option<String>,
+            count: u64,
+        ) -> Html {
             html! {
             <li class="relative bg-white py-5 px-4">
                 <div class="flex justify-between space-x-3">
                     <div class="min-w-0 flex-1">
 
                         <p class="text-sm font-medium text-gray-900 truncate">{ key }</p>
+                        if let Some(details) = details {
+                            <p class="mt-1 text-xs text-gray-500 truncate" title={details.clone()}>{ details }</p>
+                        }
                     </div>
                     <div class="flex-shrink-0 whitespace-nowrap text-sm text-gray-500">{ count.to_formatted_string(&Locale::en) }</div>
                 </div>
@@ -147,7 +248,7 @@ impl Component for Dashboard {
                     </div>
                     <div
                         class="mt-6 flex flex-col-reverse justify-stretch space-y-4 space-y-reverse sm:flex-row-reverse sm:justify-end sm:space-x-reverse sm:space-y-0 sm:space-x-3 md:mt-0 md:flex-row md:space-x-3">
-                        <a href="/api/settings/ca-certificate"
+                        <a href="api/v1/settings/ca-certificate"
                         class="inline-flex items-center justify-center px-4 py-2 border border-gray-300 shadow-sm text-sm font-medium rounded-md text-white bg-gray-800 hover:bg-gray-900 focus:outline-none focus:ring-2 focus:ring-offset-2 focus:ring-offset-gray-100 focus:ring-gray-500">
                         <svg xmlns="http://www.w3.org/2000/svg" class="ml-0.5 mr-2 h-5 w-5" fill="none"
                             viewBox="0 0 24 24" stroke="currentColor">
@@ -203,7 +304,11 @@ impl Component for Dashboard {
                         <div class="px-4 py-5 sm:p-6">
                             <ol role="list" class="divide-y divide-gray-200">
                                 { for self.message.top_blocked_paths.iter().map(|(path,
-                                count)|render_list_element(path, *count)) }
+                                count)|render_list_element_with_details(
+                                    path,
+                                    self.message.top_blocked_paths_filters.get(path).map(MatchedFilter::describe),
+                                    *count,
+                                )) }
                             </ol>
 
                         </div>
@@ -219,7 +324,23 @@ impl Component for Dashboard {
                             </ol>
                         </div>
                     </div>
+                    if !self.message.top_users.is_empty() {
+                        <div class="mt-4 bg-white overflow-hidden shadow rounded-lg divide-y divide-gray-200">
+                            <div class="px-4 py-5 sm:px-6">
+                                <h3 class="text-lg font-medium">{"Top users"}</h3>
+                            </div>
+                            <div class="px-4 py-5 sm:p-6">
+                                <ol role="list" class="divide-y divide-gray-200">
+                                    { for self.message.top_users.iter().map(|(user,
+                                    count)|render_list_element(user, *count)) }
+                                </ol>
+                            </div>
+                        </div>
+                    }
                 </div>
+                <TopCompanies />
+                <StatisticsComparison />
+                <ClientGrants />
             </>
         }
     }
@@ -0,0 +1,90 @@
+use reqwasm::http::Request;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+pub struct DebugHeaders {
+    enabled: Option<bool>,
+}
+
+pub enum Message {
+    Load,
+    Loaded(bool),
+    Set(bool),
+}
+
+/// Toggles annotating proxied responses with `X-Privaxy-*` headers explaining the
+/// decision taken for them.
+impl Component for DebugHeaders {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self { enabled: None }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let message_callback = ctx.link().callback(|message: Message| message);
+
+        match msg {
+            Message::Load => {
+                let request = Request::get("api/v1/debug-headers");
+
+                spawn_local(async move {
+                    if let Ok(response) = request.send().await {
+                        if response.ok() {
+                            if let Ok(value) = response.json::<bool>().await {
+                                message_callback.emit(Message::Loaded(value));
+                            }
+                        }
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(enabled) => {
+                self.enabled = Some(enabled);
+
+                true
+            }
+            Message::Set(enabled) => {
+                self.enabled = None;
+
+                let request = Request::put("api/v1/debug-headers")
+                    .header("Content-Type", "application/json")
+                    .body(serde_json::to_string(&enabled).unwrap());
+
+                spawn_local(async move {
+                    if let Ok(response) = request.send().await {
+                        if response.ok() {
+                            message_callback.emit(Message::Loaded(enabled));
+                            return;
+                        }
+                    }
+
+                    message_callback.emit(Message::Load);
+                });
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let onchange = ctx.link().callback(|e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::Set(input.checked())
+        });
+
+        html! {
+            <label class="inline-flex items-center text-sm text-gray-700"
+                title="Adds X-Privaxy-Decision and X-Privaxy-Rule headers to proxied responses, visible in browser developer tools">
+                <input {onchange} type="checkbox" checked={self.enabled.unwrap_or(false)} disabled={self.enabled.is_none()}
+                    class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                { "Annotate responses with decision headers" }
+            </label>
+        }
+    }
+}
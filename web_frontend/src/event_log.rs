@@ -0,0 +1,204 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+const SEVERITIES: [(&str, &str); 3] = [
+    ("info", "Info and above"),
+    ("warning", "Warnings and errors"),
+    ("error", "Errors only"),
+];
+
+const CATEGORIES: [(&str, &str); 8] = [
+    ("", "All categories"),
+    ("block", "Blocked requests"),
+    ("config", "Configuration"),
+    ("filter_update", "Filter updates"),
+    ("tls", "TLS"),
+    ("system", "System"),
+    ("security", "Security"),
+    ("script", "Scripts"),
+];
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct EventEntry {
+    now: String,
+    severity: String,
+    category: String,
+    /// `request`, or `status` for events about Privaxy itself.
+    #[serde(rename = "type")]
+    event_type: String,
+    kind: Option<String>,
+    url: Option<String>,
+    title: Option<String>,
+    host: Option<String>,
+    /// Script a `script_failed` event is about.
+    name: Option<String>,
+    error: Option<String>,
+}
+
+impl EventEntry {
+    fn describe(&self) -> String {
+        if self.event_type == "request" {
+            return format!("Blocked {}", self.url.as_deref().unwrap_or_default());
+        }
+
+        let kind = self.kind.as_deref().unwrap_or_default().replace('_', " ");
+        let subject = self
+            .title
+            .as_ref()
+            .or(self.host.as_ref())
+            .or(self.name.as_ref());
+
+        match (subject, &self.error) {
+            (Some(subject), Some(error)) => format!("{}: {} ({})", kind, subject, error),
+            (Some(subject), None) => format!("{}: {}", kind, subject),
+            (None, Some(error)) => format!("{} ({})", kind, error),
+            (None, None) => kind,
+        }
+    }
+}
+
+pub enum Message {
+    Load,
+    LoadSuccess(Vec<EventEntry>),
+    UpdateSeverity(String),
+    UpdateCategory(String),
+}
+
+pub(crate) struct EventLog {
+    events: Option<Vec<EventEntry>>,
+    min_severity: String,
+    category: String,
+}
+
+impl Component for EventLog {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            events: None,
+            min_severity: "info".to_string(),
+            category: String::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let mut query = url::form_urlencoded::Serializer::new(String::new());
+                query.append_pair("min_severity", &self.min_severity);
+                if !self.category.is_empty() {
+                    query.append_pair("category", &self.category);
+                }
+                let url = format!("api/v1/events/history?{}", query.finish());
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    match Request::get(&url).send().await {
+                        Ok(response) => {
+                            if let Ok(events) = response.json::<Vec<EventEntry>>().await {
+                                link.send_message(Message::LoadSuccess(events));
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Request error: {:?}", err);
+                        }
+                    }
+                });
+            }
+            Message::LoadSuccess(events) => {
+                self.events = Some(events);
+            }
+            Message::UpdateSeverity(min_severity) => {
+                self.min_severity = min_severity;
+                ctx.link().send_message(Message::Load);
+                return false;
+            }
+            Message::UpdateCategory(category) => {
+                self.category = category;
+                ctx.link().send_message(Message::Load);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_event = |event: &EventEntry| {
+            let severity_class = match event.severity.as_str() {
+                "error" => "text-red-600",
+                "warning" => "text-yellow-600",
+                _ => "text-gray-500",
+            };
+
+            html! {
+                <div class="py-4 text-sm">
+                    <p class="text-gray-900 truncate">{ event.describe() }</p>
+                    <p class="text-gray-500">
+                        <span class={classes!("font-medium", severity_class)}>{ &event.severity }</span>
+                        { format!(" · {} · {}", event.category.replace('_', " "), event.now) }
+                    </p>
+                </div>
+            }
+        };
+
+        let on_severity_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdateSeverity(select.value())
+        });
+        let on_category_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdateCategory(select.value())
+        });
+
+        let input_css = "shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline";
+
+        html! {
+            <>
+            <div class="pt-1.5 mb-4">
+                <h1 class="text-2xl font-bold text-gray-900">{ "Events" }</h1>
+            </div>
+            <p class="text-gray-600">
+                {"Blocked requests and changes to the state of Privaxy, most recent first. They are kept across restarts."}
+            </p>
+            <div class="mt-4 flex items-center space-x-2">
+                <select onchange={on_severity_change} class={input_css}>
+                    { for SEVERITIES.iter().map(|(severity, label)| html! {
+                        <option value={*severity} selected={*severity == self.min_severity}>{ *label }</option>
+                    }) }
+                </select>
+                <select onchange={on_category_change} class={input_css}>
+                    { for CATEGORIES.iter().map(|(category, label)| html! {
+                        <option value={*category} selected={*category == self.category}>{ *label }</option>
+                    }) }
+                </select>
+                <PrivaxyButton
+                    color={ButtonColor::Gray}
+                    state={ButtonState::Enabled}
+                    onclick={ctx.link().callback(|_| Message::Load)}
+                    button_text={"Refresh"}
+                />
+            </div>
+            {
+                match &self.events {
+                    None => html! { <div class="mt-4">{"Loading..."}</div> },
+                    Some(events) if events.is_empty() => html! {
+                        <p class="mt-4 text-gray-500 text-sm">{ "No event was recorded." }</p>
+                    },
+                    Some(events) => html! {
+                        <div class="mt-4 border-t border-b border-gray-200 divide-y divide-gray-200">
+                            { for events.iter().map(render_event) }
+                        </div>
+                    },
+                }
+            }
+            </>
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::block_responses::RESOURCE_TYPES;
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::requests::MatchedFilter;
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Serialize)]
+struct TestRequest {
+    url: String,
+    source_url: Option<String>,
+    request_type: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TestRequestResponse {
+    blocked: bool,
+    important: bool,
+    redirect: Option<String>,
+    filter: Option<MatchedFilter>,
+    exception: Option<MatchedFilter>,
+    blocking_enabled: bool,
+}
+
+pub enum Message {
+    UpdateUrl(String),
+    UpdateSourceUrl(String),
+    UpdateRequestType(String),
+    Test,
+    TestSuccess(TestRequestResponse),
+    TestFailed(ApiError),
+}
+
+/// Tells whether a request would be blocked, and by which rule.
+pub struct FilterTest {
+    url: String,
+    source_url: String,
+    request_type: String,
+    testing: bool,
+    response: Option<TestRequestResponse>,
+    err_msg: Option<String>,
+}
+
+impl Component for FilterTest {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            url: String::new(),
+            source_url: String::new(),
+            request_type: "document".to_string(),
+            testing: false,
+            response: None,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::UpdateUrl(url) => {
+                self.url = url;
+            }
+            Message::UpdateSourceUrl(source_url) => {
+                self.source_url = source_url;
+            }
+            Message::UpdateRequestType(request_type) => {
+                self.request_type = request_type;
+            }
+            Message::Test => {
+                if self.testing || self.url.is_empty() {
+                    return false;
+                }
+
+                self.testing = true;
+                self.err_msg = None;
+
+                let test_request = TestRequest {
+                    url: self.url.clone(),
+                    source_url: Some(self.source_url.clone()).filter(|url| !url.is_empty()),
+                    request_type: self.request_type.clone(),
+                };
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::post("api/v1/test-request")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&test_request).unwrap());
+
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                link.send_message(Message::TestSuccess(
+                                    response.json::<TestRequestResponse>().await.unwrap(),
+                                ));
+                            } else {
+                                link.send_message(Message::TestFailed(
+                                    response.json::<ApiError>().await.unwrap(),
+                                ));
+                            }
+                        }
+                        Err(err) => link.send_message(Message::TestFailed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+            }
+            Message::TestSuccess(response) => {
+                self.testing = false;
+                self.response = Some(response);
+            }
+            Message::TestFailed(err) => {
+                self.testing = false;
+                self.response = None;
+                self.err_msg = Some(err.error);
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let on_url_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateUrl(input.value())
+        });
+        let on_source_url_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateSourceUrl(input.value())
+        });
+        let on_request_type_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdateRequestType(select.value())
+        });
+
+        let button_state = if self.testing {
+            ButtonState::Loading
+        } else if self.url.is_empty() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        let input_css = "shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline";
+
+        html! {
+            <div class="mt-8">
+                <h2 class="text-lg font-medium text-gray-900">{ "Test a request" }</h2>
+                <p class="text-gray-600">
+                    { "Check whether a request would be blocked by the current filters, and by which rule." }
+                </p>
+                <div class="mt-4 grid grid-cols-1 gap-4 md:grid-cols-2">
+                    <div>
+                        <label class="block text-sm font-medium text-gray-700">{ "Request URL" }</label>
+                        <input oninput={on_url_input} value={self.url.clone()} type="text" placeholder="https://ads.example.com/banner.js" class={input_css} />
+                    </div>
+                    <div>
+                        <label class="block text-sm font-medium text-gray-700">{ "Page URL (optional)" }</label>
+                        <input oninput={on_source_url_input} value={self.source_url.clone()} type="text" placeholder="https://example.com/" class={input_css} />
+                    </div>
+                    <div>
+                        <label class="block text-sm font-medium text-gray-700">{ "Request type" }</label>
+                        <select onchange={on_request_type_change} class="shadow border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline">
+                            { for RESOURCE_TYPES.iter().map(|(request_type, label)| html! {
+                                <option value={*request_type} selected={*request_type == self.request_type}>{ *label }</option>
+                            }) }
+                        </select>
+                    </div>
+                </div>
+                <div class="mt-4">
+                    <PrivaxyButton color={ButtonColor::Blue} state={button_state} onclick={ctx.link().callback(|_| Message::Test)} button_text="Test request" />
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                if let Some(response) = &self.response {
+                    { render_response(response) }
+                }
+            </div>
+        }
+    }
+}
+
+fn render_response(response: &TestRequestResponse) -> Html {
+    let (verdict, verdict_css) = if response.blocked {
+        ("Blocked", "text-red-700")
+    } else {
+        ("Allowed", "text-green-700")
+    };
+
+    let render_filter = |label: &str, filter: &Option<MatchedFilter>| match filter {
+        Some(filter) => html! {
+            <div class="mt-2">
+                <dt class="text-sm font-medium text-gray-500">{ label }</dt>
+                <dd class="text-sm text-gray-900 font-mono break-all">{ &filter.rule }</dd>
+                <dd class="text-sm text-gray-500">{ filter.list.as_deref().unwrap_or("Unknown list") }</dd>
+            </div>
+        },
+        None => html! {},
+    };
+
+    html! {
+        <div class="mt-4 bg-white shadow rounded-lg px-4 py-5 sm:p-6">
+            <p class={classes!("text-lg", "font-semibold", verdict_css)}>
+                { verdict }
+                if response.important {
+                    <span class="ml-2 text-sm font-normal text-gray-500">{ "(important rule)" }</span>
+                }
+            </p>
+            if !response.blocking_enabled {
+                <p class="text-sm text-gray-500">{ "Blocking is currently disabled, requests are not blocked." }</p>
+            }
+            <dl>
+                { render_filter("Matching rule", &response.filter) }
+                { render_filter("Exception", &response.exception) }
+                if let Some(redirect) = &response.redirect {
+                    <div class="mt-2">
+                        <dt class="text-sm font-medium text-gray-500">{ "Redirected to resource" }</dt>
+                        <dd class="text-sm text-gray-900 font-mono">{ redirect }</dd>
+                    </div>
+                }
+            </dl>
+        </div>
+    }
+}
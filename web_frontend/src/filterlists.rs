@@ -2,40 +2,88 @@ use crate::button;
 use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
 use crate::filters::{AddFilterRequest, Filter, FilterConfiguration, FilterGroup};
 use crate::save_button::BASE_BUTTON_CSS;
+use crate::ApiError;
 use crate::{save_button, submit_banner};
-use filterlists_api;
 use reqwasm::http::Request;
+use serde::Deserialize;
 use url::Url;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 use yew::InputEvent;
 use yew::{html, Component, Context, Html};
+
+/// A filter list of filterlists.com, as found by the search of the backend.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FilterListSearchResult {
+    pub id: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub primary_view_url: Option<String>,
+    pub languages: Vec<String>,
+    pub license: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FilterListSearchResponse {
+    pub results: Vec<FilterListSearchResult>,
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct FilterRuleCounts {
+    pub network: usize,
+    pub network_exception: usize,
+    pub cosmetic: usize,
+    pub cosmetic_exception: usize,
+    pub scriptlet: usize,
+    pub html: usize,
+}
+
+/// Content of a filter list, as described by the backend before subscribing to it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FilterPreview {
+    pub title: Option<String>,
+    pub homepage: Option<String>,
+    pub expires: Option<String>,
+    pub dialect: String,
+    pub rule_count: usize,
+    pub rule_counts: FilterRuleCounts,
+    pub parse_error_count: usize,
+    pub parse_errors: Vec<String>,
+    pub sample_rules: Vec<String>,
+}
+
 pub enum SearchFilterMessage {
     Open,
     Close,
     FilterChanged(String),
-    AddFilter(filterlists_api::Filter),
-    RemoveFilter(filterlists_api::Filter),
-    LoadFilters,
-    FiltersLoaded(Vec<filterlists_api::Filter>),
+    AddFilter(FilterListSearchResult),
+    FilterAdded(Filter),
+    RemoveFilter(FilterListSearchResult),
+    Search,
+    SearchResultsLoaded(String, FilterListSearchResponse),
+    Preview(FilterListSearchResult),
+    PreviewLoaded(u32, FilterPreview),
+    PreviewFailed(u32, String),
+    ClosePreview,
     Error(String),
     NextPage,
     PreviousPage,
-    LanguagesLoaded(Vec<filterlists_api::FilterLanguage>),
-    LicensesLoaded(Vec<filterlists_api::FilterLicense>),
-    TagsLoaded(Vec<filterlists_api::FilterTag>),
 }
 
 pub struct SearchFilterList {
     link: yew::html::Scope<Self>,
     is_open: bool,
-    filters: Vec<filterlists_api::Filter>,
+    results: Vec<FilterListSearchResult>,
+    total_results: usize,
+    previewed_filter: Option<FilterListSearchResult>,
+    preview: Option<FilterPreview>,
+    preview_error: Option<String>,
     filter_query: String,
-    loading: bool,
-    languages: Vec<filterlists_api::FilterLanguage>,
-    licenses: Vec<filterlists_api::FilterLicense>,
-    tags: Vec<filterlists_api::FilterTag>,
     current_page: usize,
     results_per_page: usize,
     active_filters: FilterConfiguration,
@@ -56,12 +104,12 @@ impl Component for SearchFilterList {
         Self {
             link: _ctx.link().clone(),
             is_open: false,
-            filters: Vec::<filterlists_api::Filter>::new(),
-            languages: Vec::<filterlists_api::FilterLanguage>::new(),
-            licenses: Vec::<filterlists_api::FilterLicense>::new(),
-            tags: Vec::<filterlists_api::FilterTag>::new(),
+            results: Vec::new(),
+            total_results: 0,
+            previewed_filter: None,
+            preview: None,
+            preview_error: None,
             filter_query: String::new(),
-            loading: true,
             current_page: 1,
             results_per_page: 10,
             active_filters: _ctx.props().filter_configuration.clone(),
@@ -72,10 +120,17 @@ impl Component for SearchFilterList {
         match msg {
             SearchFilterMessage::Open => {
                 self.is_open = true;
-                self.link.send_message(SearchFilterMessage::LoadFilters);
+                self.link.send_message(SearchFilterMessage::Search);
+            }
+            SearchFilterMessage::Close => {
+                self.is_open = false;
+                self.link.send_message(SearchFilterMessage::ClosePreview);
+            }
+            SearchFilterMessage::FilterChanged(query) => {
+                self.filter_query = query;
+                self.current_page = 1;
+                self.link.send_message(SearchFilterMessage::Search);
             }
-            SearchFilterMessage::Close => self.is_open = false,
-            SearchFilterMessage::FilterChanged(query) => self.filter_query = query,
             SearchFilterMessage::AddFilter(filter) => {
                 let parsed_url =
                     match Url::parse(&filter.primary_view_url.clone().unwrap_or_default()) {
@@ -85,15 +140,11 @@ impl Component for SearchFilterList {
                             return false;
                         }
                     };
-                let group: FilterGroup = self
+                let group: FilterGroup = filter
                     .tags
-                    .clone()
-                    .into_iter()
-                    .filter(|tag| {
-                        filter.tag_ids.contains(&tag.id)
-                            && FILTER_TAG_GROUPS.contains(&tag.name.as_str())
-                    })
-                    .map(|tag| match tag.name.as_str() {
+                    .iter()
+                    .filter(|tag| FILTER_TAG_GROUPS.contains(&tag.as_str()))
+                    .map(|tag| match tag.as_str() {
                         "ads" => FilterGroup::Ads,
                         "privacy" => FilterGroup::Privacy,
                         "malware" => FilterGroup::Malware,
@@ -105,19 +156,19 @@ impl Component for SearchFilterList {
 
                 let request_body: AddFilterRequest =
                     AddFilterRequest::new(filter.name.clone(), group, parsed_url);
-                let request = Request::post("/api/filters")
+                let request = Request::post("api/v1/filters")
                     .header("Content-Type", "application/json")
                     .body(serde_json::to_string(&request_body).unwrap());
-                self.active_filters.push(Filter::new(
-                    filter.name.clone(),
-                    FilterGroup::Malware,
-                    "".to_string(),
-                ));
+                let link = self.link.clone();
                 spawn_local(async move {
                     match request.send().await {
                         Ok(response) => {
                             if response.ok() {
                                 log::info!("Filter added successfully");
+                                // The created filter carries the identifier it can be removed with.
+                                if let Ok(filter) = response.json::<Filter>().await {
+                                    link.send_message(SearchFilterMessage::FilterAdded(filter));
+                                }
                             } else {
                                 log::error!("Failed to add filter: {:?}", response.status());
                             }
@@ -128,19 +179,23 @@ impl Component for SearchFilterList {
                     }
                 })
             }
+            SearchFilterMessage::FilterAdded(filter) => {
+                self.active_filters.push(filter);
+                self.link.send_message(SearchFilterMessage::ClosePreview);
+            }
             SearchFilterMessage::RemoveFilter(filter) => {
-                let parsed_url = match Url::parse(&filter.primary_view_url.clone().unwrap()) {
-                    Ok(url) => url,
-                    Err(err) => {
-                        log::error!("Failed to parse URL: {}", err);
-                        return false;
-                    }
+                let active_filter_id = match self
+                    .active_filters
+                    .iter()
+                    .find(|active_filter| active_filter.title == filter.name)
+                {
+                    Some(active_filter) => active_filter.id.clone(),
+                    None => return false,
                 };
-                let request_body: AddFilterRequest =
-                    AddFilterRequest::new(filter.name.clone(), FilterGroup::Malware, parsed_url);
-                let request = Request::delete("/api/filters")
-                    .header("Content-Type", "application/json")
-                    .body(serde_json::to_string(&request_body).unwrap());
+                self.active_filters
+                    .retain(|active_filter| active_filter.id != active_filter_id);
+
+                let request = Request::delete(&format!("api/v1/filters/{}", active_filter_id));
                 spawn_local(async move {
                     match request.send().await {
                         Ok(response) => {
@@ -156,134 +211,118 @@ impl Component for SearchFilterList {
                     }
                 })
             }
-            SearchFilterMessage::LoadFilters => {
-                if self.loading {
-                    let link = self.link.clone();
-                    spawn_local(async move {
-                        let request = Request::get("/api/filterlists/list");
-                        match request.send().await {
-                            Ok(response) => {
-                                if response.ok() {
-                                    if let Ok(filters) =
-                                        response.json::<Vec<filterlists_api::Filter>>().await
-                                    {
-                                        link.send_message(SearchFilterMessage::FiltersLoaded(
-                                            filters,
-                                        ))
-                                    }
-                                } else {
-                                    log::error!("Failed to load filters: {:?}", response.status());
-                                    link.send_message(SearchFilterMessage::Error(
-                                        response.status().to_string(),
+            SearchFilterMessage::Search => {
+                let query = self.filter_query.clone();
+                let parameters = url::form_urlencoded::Serializer::new(String::new())
+                    .append_pair("query", &query)
+                    .append_pair("page", &self.current_page.to_string())
+                    .append_pair("per_page", &self.results_per_page.to_string())
+                    .finish();
+                let link = self.link.clone();
+                spawn_local(async move {
+                    let request =
+                        Request::get(&format!("api/v1/filterlists/search?{}", parameters));
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                if let Ok(search_response) =
+                                    response.json::<FilterListSearchResponse>().await
+                                {
+                                    link.send_message(SearchFilterMessage::SearchResultsLoaded(
+                                        query,
+                                        search_response,
                                     ))
                                 }
-                            }
-                            Err(err) => {
-                                link.send_message(SearchFilterMessage::Error(err.to_string()))
+                            } else {
+                                log::error!("Failed to search filters: {:?}", response.status());
+                                link.send_message(SearchFilterMessage::Error(
+                                    response.status().to_string(),
+                                ))
                             }
                         }
-                        let request = Request::get("/api/filterlists/languages");
-                        match request.send().await {
-                            Ok(response) => {
-                                if response.ok() {
-                                    if let Ok(langs) = response
-                                        .json::<Vec<filterlists_api::FilterLanguage>>()
-                                        .await
-                                    {
-                                        link.send_message(SearchFilterMessage::LanguagesLoaded(
-                                            langs,
-                                        ))
-                                    }
-                                } else {
-                                    log::error!(
-                                        "Failed to load languages: {:?}",
-                                        response.status()
-                                    );
-                                    link.send_message(SearchFilterMessage::Error(
-                                        response.status().to_string(),
-                                    ))
-                                }
-                            }
-                            Err(err) => {
-                                link.send_message(SearchFilterMessage::Error(err.to_string()))
-                            }
-                        };
-                        let request = Request::get("/api/filterlists/licenses");
-                        match request.send().await {
-                            Ok(response) => {
-                                if response.ok() {
-                                    if let Ok(licenses) =
-                                        response.json::<Vec<filterlists_api::FilterLicense>>().await
-                                    {
-                                        link.send_message(SearchFilterMessage::LicensesLoaded(
-                                            licenses,
-                                        ))
-                                    }
-                                } else {
-                                    log::error!("Failed to load licenses: {:?}", response.status());
-                                    link.send_message(SearchFilterMessage::Error(
-                                        response.status().to_string(),
-                                    ))
-                                }
-                            }
-                            Err(err) => {
-                                link.send_message(SearchFilterMessage::Error(err.to_string()))
-                            }
-                        };
-                        let request = Request::get("/api/filterlists/tags");
-                        match request.send().await {
-                            Ok(response) => {
-                                if response.ok() {
-                                    if let Ok(tags) =
-                                        response.json::<Vec<filterlists_api::FilterTag>>().await
-                                    {
-                                        link.send_message(SearchFilterMessage::TagsLoaded(tags))
-                                    }
-                                } else {
-                                    log::error!("Failed to load tags: {:?}", response.status());
-                                    link.send_message(SearchFilterMessage::Error(
-                                        response.status().to_string(),
-                                    ))
-                                }
-                            }
-                            Err(err) => {
-                                link.send_message(SearchFilterMessage::Error(err.to_string()))
-                            }
-                        };
-                    });
-                }
+                        Err(err) => link.send_message(SearchFilterMessage::Error(err.to_string())),
+                    }
+                });
             }
-            SearchFilterMessage::FiltersLoaded(filters) => {
+            SearchFilterMessage::SearchResultsLoaded(query, search_response) => {
+                // Results of a search made before the query or page changed are outdated.
+                if query != self.filter_query || search_response.page != self.current_page {
+                    return false;
+                }
                 log::info!("Filters loaded successfully");
-                self.filters = filters.clone();
-                self.loading = false;
+                self.results = search_response.results;
+                self.total_results = search_response.total;
+            }
+            SearchFilterMessage::Preview(filter) => {
+                let url = match &filter.primary_view_url {
+                    Some(url) => url.clone(),
+                    None => return false,
+                };
+                let id = filter.id;
+                self.previewed_filter = Some(filter);
+                self.preview = None;
+                self.preview_error = None;
+
+                let link = self.link.clone();
+                spawn_local(async move {
+                    let request = Request::post("api/v1/filters/preview")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::json!({ "url": url }).to_string());
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            match response.json::<FilterPreview>().await {
+                                Ok(preview) => link
+                                    .send_message(SearchFilterMessage::PreviewLoaded(id, preview)),
+                                Err(err) => link.send_message(SearchFilterMessage::PreviewFailed(
+                                    id,
+                                    err.to_string(),
+                                )),
+                            }
+                        }
+                        Ok(response) => {
+                            let error = match response.json::<ApiError>().await {
+                                Ok(err) => err.error,
+                                Err(_) => response.status().to_string(),
+                            };
+                            link.send_message(SearchFilterMessage::PreviewFailed(id, error))
+                        }
+                        Err(err) => link
+                            .send_message(SearchFilterMessage::PreviewFailed(id, err.to_string())),
+                    }
+                });
             }
-            SearchFilterMessage::LanguagesLoaded(langs) => {
-                log::info!("Languages loaded successfully");
-                self.languages = langs.clone();
+            SearchFilterMessage::PreviewLoaded(id, preview) => {
+                // The preview of another list was requested in the meantime.
+                if self.previewed_filter.as_ref().map(|filter| filter.id) != Some(id) {
+                    return false;
+                }
+                self.preview = Some(preview);
             }
-            SearchFilterMessage::LicensesLoaded(licenses) => {
-                log::info!("Licenses loaded successfully");
-                self.licenses = licenses.clone();
+            SearchFilterMessage::PreviewFailed(id, error) => {
+                if self.previewed_filter.as_ref().map(|filter| filter.id) != Some(id) {
+                    return false;
+                }
+                log::error!("Failed to preview filter: {}", error);
+                self.preview_error = Some(error);
             }
-            SearchFilterMessage::TagsLoaded(tags) => {
-                log::info!("Tags loaded successfully");
-                self.tags = tags.clone();
+            SearchFilterMessage::ClosePreview => {
+                self.previewed_filter = None;
+                self.preview = None;
+                self.preview_error = None;
             }
             SearchFilterMessage::Error(error) => {
                 log::error!("Error loading filters: {}", error.to_string());
-                self.loading = false;
             }
             SearchFilterMessage::NextPage => {
-                if self.current_page
-                    < (self.filters.len() as f64 / self.results_per_page as f64).ceil() as usize
-                {
+                if self.current_page < self.get_total_pages() {
                     self.current_page += 1;
+                    self.link.send_message(SearchFilterMessage::Search);
                 }
             }
             SearchFilterMessage::PreviousPage => {
                 if self.current_page > 1 {
                     self.current_page -= 1;
+                    self.link.send_message(SearchFilterMessage::Search);
                 }
             }
         }
@@ -291,23 +330,7 @@ impl Component for SearchFilterList {
     }
 
     fn view(&self, _ctx: &Context<Self>) -> Html {
-        let filtered_filters: Vec<&filterlists_api::Filter> = self
-            .filters
-            .iter()
-            .filter(|filter| {
-                filter
-                    .name
-                    .to_lowercase()
-                    .contains(&self.filter_query.to_lowercase())
-            })
-            .collect();
-        let total_pages =
-            (filtered_filters.len() as f64 / self.results_per_page as f64).ceil() as usize;
-        let start_index = (self.current_page - 1) * self.results_per_page;
-        let paginated_filters = filtered_filters
-            .into_iter()
-            .skip(start_index)
-            .take(self.results_per_page);
+        let total_pages = self.get_total_pages();
 
         let prev_button = html! {
         <PrivaxyButton
@@ -320,7 +343,7 @@ impl Component for SearchFilterList {
         let next_button = html! {
         <PrivaxyButton
             color={ButtonColor::Gray}
-            state={if self.current_page == total_pages {ButtonState::Disabled} else {ButtonState::Enabled}}
+            state={if self.current_page >= total_pages {ButtonState::Disabled} else {ButtonState::Enabled}}
             onclick={self.link.callback(|_| SearchFilterMessage::NextPage)}
             button_text={"Next"}
         />
@@ -367,6 +390,11 @@ impl Component for SearchFilterList {
                         <div class="fixed inset-0 bg-gray-600 bg-opacity-75 flex items-center justify-center z-50">
                             <div class="bg-white p-6 rounded-lg shadow-lg z-60" style="width: 50vw; height: 80vh; overflow: hidden;">
                                 <div class="flex flex-col space-y-4" style="height: 100%;">
+                                { if let Some(filter) = &self.previewed_filter {
+                                    self.view_preview(filter, _ctx)
+                                } else {
+                                    html! {
+                                    <>
                                     <input type="text" placeholder="Search by name" class="border border-gray-300 p-2 rounded"
                                         value={self.filter_query.clone()}
                                         oninput={_ctx.link().callback(|e: InputEvent| {
@@ -386,7 +414,7 @@ impl Component for SearchFilterList {
                                                 </tr>
                                             </thead>
                                             <tbody>
-                                                { for paginated_filters.map(|filter| self.view_filter_row(filter, _ctx)) }
+                                                { for self.results.iter().map(|filter| self.view_filter_row(filter, _ctx)) }
                                             </tbody>
                                         </table>
                                     </div>
@@ -395,6 +423,9 @@ impl Component for SearchFilterList {
                                         <span>{"Page "} {self.current_page} {" of "} {total_pages}</span>
                                        {next_button}
                                     </div>
+                                    </>
+                                    }
+                                }}
                                     {cancel_button}
                                 </div>
                             </div>
@@ -409,8 +440,80 @@ impl Component for SearchFilterList {
 }
 
 impl SearchFilterList {
-    fn view_filter_row(&self, filter: &filterlists_api::Filter, ctx: &Context<Self>) -> Html {
+    fn get_total_pages(&self) -> usize {
+        ((self.total_results as f64 / self.results_per_page as f64).ceil() as usize).max(1)
+    }
+
+    fn view_preview(&self, filter: &FilterListSearchResult, ctx: &Context<Self>) -> Html {
         let filter_clone = filter.clone();
+        let add_button = html! {
+            <PrivaxyButton state={ButtonState::Enabled} onclick={ctx.link().callback(move |_| SearchFilterMessage::AddFilter(filter_clone.clone()))} color={ButtonColor::Green} button_text={"Add"}/>
+        };
+
+        let content = if let Some(preview) = &self.preview {
+            let rule_count_row = |name: &str, count: usize| {
+                html! {
+                    <tr>
+                        <td class="px-4 py-1 text-gray-600">{ name.to_string() }</td>
+                        <td class="px-4 py-1 text-right">{ count }</td>
+                    </tr>
+                }
+            };
+            html! {
+                <>
+                    <p class="text-sm text-gray-600">
+                        { format!("{} rules, written for {}", preview.rule_count, preview.dialect.replace('_', " ")) }
+                        { if let Some(expires) = &preview.expires {
+                            html! { <span>{ format!(", updated every {}", expires) }</span> }
+                        } else {
+                            html! {}
+                        }}
+                    </p>
+                    <table class="table-auto text-sm">
+                        <tbody>
+                            { rule_count_row("Network", preview.rule_counts.network) }
+                            { rule_count_row("Network exceptions", preview.rule_counts.network_exception) }
+                            { rule_count_row("Cosmetic", preview.rule_counts.cosmetic) }
+                            { rule_count_row("Cosmetic exceptions", preview.rule_counts.cosmetic_exception) }
+                            { rule_count_row("Scriptlets", preview.rule_counts.scriptlet) }
+                            { rule_count_row("HTML filters", preview.rule_counts.html) }
+                        </tbody>
+                    </table>
+                    { if preview.parse_error_count > 0 {
+                        html! {
+                            <p class="text-sm text-yellow-700">
+                                { format!("{} rules can't be parsed, such as {}", preview.parse_error_count, preview.parse_errors.join(", ")) }
+                            </p>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    <pre class="bg-gray-100 p-2 rounded text-xs overflow-auto" style="flex-grow: 1;">
+                        { preview.sample_rules.join("\n") }
+                    </pre>
+                </>
+            }
+        } else if let Some(error) = &self.preview_error {
+            html! { <p class="text-sm text-red-500">{ format!("Unable to preview the filter: {}", error) }</p> }
+        } else {
+            html! { <p class="text-sm text-gray-600">{ "Downloading the filter..." }</p> }
+        };
+
+        html! {
+            <>
+                <h3 class="text-lg font-medium text-gray-900">{ &filter.name }</h3>
+                { content }
+                <div class="flex space-x-4">
+                    <PrivaxyButton state={ButtonState::Enabled} onclick={ctx.link().callback(|_| SearchFilterMessage::ClosePreview)} color={ButtonColor::Gray} button_text={"Back"}/>
+                    { add_button }
+                </div>
+            </>
+        }
+    }
+
+    fn view_filter_row(&self, filter: &FilterListSearchResult, ctx: &Context<Self>) -> Html {
+        let filter_clone = filter.clone();
+        let previewed_filter = filter.clone();
         let existing_filter = self
             .active_filters
             .clone()
@@ -438,33 +541,16 @@ impl SearchFilterList {
                     { &filter.description.clone().unwrap_or_default() }
                 </td>
                 <td class="border px-4 py-2 overflow-hidden" style="height: 5vh; white-space: nowrap; text-overflow: ellipsis;">
-                    { self.get_language_name(filter.language_ids.clone()) }
+                    { filter.languages.join(", ") }
                 </td>
                 <td class="border px-4 py-2 overflow-hidden" style="height: 5vh; white-space: nowrap; text-overflow: ellipsis;">
-                    { self.get_license_name(filter.license_id) }
+                    { filter.license.clone().unwrap_or_default() }
                 </td>
                 <td class="border px-4 py-2 text-center overflow-hidden" style="height: 5vh; white-space: nowrap; text-overflow: ellipsis;">
                 { button }
+                <PrivaxyButton state={if filter.primary_view_url.is_some() {ButtonState::Enabled} else {ButtonState::Disabled}} onclick={ctx.link().callback(move |_| SearchFilterMessage::Preview(previewed_filter.clone()))} color={ButtonColor::Gray} button_text={"Preview"}/>
                 </td>
             </tr>
         }
     }
-
-    fn get_language_name(&self, language_ids: Vec<u32>) -> String {
-        self.languages
-            .iter()
-            .filter(|lang| language_ids.contains(&lang.id))
-            .map(|lang| lang.name.clone())
-            .collect::<Vec<String>>()
-            .join(", ")
-    }
-
-    fn get_license_name(&self, license_id: u32) -> String {
-        self.licenses
-            .iter()
-            .filter(|license| license.id == license_id)
-            .map(|license| license.name.clone())
-            .collect::<Vec<String>>()
-            .join(", ")
-    }
 }
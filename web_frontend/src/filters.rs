@@ -1,4 +1,4 @@
-use crate::button::ButtonState;
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
 use crate::filterlists::SearchFilterList;
 use crate::{save_button, submit_banner};
 use reqwasm::http::Request;
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::de::IoRead;
 use serde_json::StreamDeserializer;
 use serde_with::{serde_as, DisplayFromStr};
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::Cursor;
 use url::Url;
@@ -61,6 +62,16 @@ pub enum AddFilterMessage {
     CategoryChanged(FilterGroup),
     UrlChanged(String),
     TitleChanged(String),
+    UsernameChanged(String),
+    PasswordChanged(String),
+}
+
+/// Credentials of a private filter list.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FilterBasicAuth {
+    username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
 }
 
 #[serde_as]
@@ -71,6 +82,8 @@ pub struct AddFilterRequest {
     group: FilterGroup,
     #[serde_as(as = "DisplayFromStr")]
     url: Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    basic_auth: Option<FilterBasicAuth>,
 }
 
 impl AddFilterRequest {
@@ -80,6 +93,7 @@ impl AddFilterRequest {
             title,
             group,
             url,
+            basic_auth: None,
         }
     }
 }
@@ -90,6 +104,8 @@ pub struct AddFilterComponent {
     category: FilterGroup,
     title: String,
     url: String,
+    username: String,
+    password: String,
     changes_saved: bool,
 }
 
@@ -104,6 +120,8 @@ impl Component for AddFilterComponent {
             category: FilterGroup::Default,
             url: String::new(),
             title: String::new(),
+            username: String::new(),
+            password: String::new(),
             changes_saved: false,
         }
     }
@@ -123,9 +141,18 @@ impl Component for AddFilterComponent {
                         },
                         group: category,
                         url: parsed_url,
+                        basic_auth: if self.username.is_empty() {
+                            None
+                        } else {
+                            Some(FilterBasicAuth {
+                                username: self.username.clone(),
+                                password: Some(self.password.clone())
+                                    .filter(|password| !password.is_empty()),
+                            })
+                        },
                     };
 
-                    let request = Request::post("/api/filters")
+                    let request = Request::post("api/v1/filters")
                         .header("Content-Type", "application/json")
                         .body(serde_json::to_string(&request_body).unwrap());
 
@@ -154,6 +181,8 @@ impl Component for AddFilterComponent {
             AddFilterMessage::TitleChanged(title) => {
                 self.title = title;
             }
+            AddFilterMessage::UsernameChanged(username) => self.username = username,
+            AddFilterMessage::PasswordChanged(password) => self.password = password,
         }
         true
     }
@@ -262,12 +291,42 @@ impl Component for AddFilterComponent {
                                             type="text"
                                             class="flex-1 bg-white border border-gray-300 text-gray-700 py-2 px-4 rounded leading-tight focus:outline-none focus:bg-white focus:border-gray-500"
                                             value={self.url.clone()}
+                                            placeholder="https://example.com/list.txt or file:///path/to/list.txt"
                                             oninput={_ctx.link().callback(|e: InputEvent| {
                                                 let input = e.target_dyn_into::<HtmlInputElement>().expect("event target should be an input element");
                                                 AddFilterMessage::UrlChanged(input.value())
                                             })}
                                         />
                                     </div>
+                                    <div class="flex items-center">
+                                        <div class="w-32">
+                                            <label class="font-bold">{"Username"}</label>
+                                        </div>
+                                        <input
+                                            type="text"
+                                            class="flex-1 bg-white border border-gray-300 text-gray-700 py-2 px-4 rounded leading-tight focus:outline-none focus:bg-white focus:border-gray-500"
+                                            value={self.username.clone()}
+                                            placeholder="Only for private lists"
+                                            oninput={_ctx.link().callback(|e: InputEvent| {
+                                                let input = e.target_dyn_into::<HtmlInputElement>().expect("event target should be an input element");
+                                                AddFilterMessage::UsernameChanged(input.value())
+                                            })}
+                                        />
+                                    </div>
+                                    <div class="flex items-center">
+                                        <div class="w-32">
+                                            <label class="font-bold">{"Password"}</label>
+                                        </div>
+                                        <input
+                                            type="password"
+                                            class="flex-1 bg-white border border-gray-300 text-gray-700 py-2 px-4 rounded leading-tight focus:outline-none focus:bg-white focus:border-gray-500"
+                                            value={self.password.clone()}
+                                            oninput={_ctx.link().callback(|e: InputEvent| {
+                                                let input = e.target_dyn_into::<HtmlInputElement>().expect("event target should be an input element");
+                                                AddFilterMessage::PasswordChanged(input.value())
+                                            })}
+                                        />
+                                    </div>
                                     <div class="flex space-x-4">
                                         <button onclick={_ctx.link().callback(move |_| AddFilterMessage::Save(url.clone(), title.clone(), category.clone()))} class="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded z-60">{"Save"}</button>
                                         <button onclick={_ctx.link().callback(|_| AddFilterMessage::Close)} class="bg-gray-500 hover:bg-gray-700 text-white font-bold py-2 px-4 rounded z-60">{"Cancel"}</button>
@@ -286,55 +345,81 @@ impl Component for AddFilterComponent {
 
 #[derive(Deserialize, Clone, PartialEq, Eq)]
 pub struct Filter {
+    pub id: String,
     enabled: bool,
     pub title: String,
     group: FilterGroup,
     file_name: String,
-}
-
-impl Filter {
-    pub fn new(title: String, group: FilterGroup, file_name: String) -> Self {
-        Self {
-            enabled: true,
-            title,
-            group,
-            file_name,
-        }
-    }
+    /// Set by the managed configuration, it can't be disabled.
+    #[serde(default)]
+    managed: bool,
 }
 
 impl std::fmt::Display for Filter {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "Filter(Enabled={}, Title={}, Group={:?}, File_name={})",
-            self.enabled, self.title, self.group, self.file_name
+            "Filter(Id={}, Enabled={}, Title={}, Group={:?}, File_name={})",
+            self.id, self.enabled, self.title, self.group, self.file_name
         )
     }
 }
 
-#[allow(non_snake_case)]
+/// Operation of a `POST /api/filters/batch` request.
 #[derive(Serialize)]
-pub struct FilterStatusChangeRequest {
-    enabled: bool,
-    file_name: String,
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FilterBatchOperation {
+    Enable { id: String },
+    Disable { id: String },
 }
 
 pub type FilterConfiguration = Vec<Filter>;
 
+/// Health of a filter list.
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+pub struct FilterStatus {
+    id: String,
+    rule_count: usize,
+    parse_error_count: usize,
+    parse_errors: Vec<String>,
+    last_error: Option<String>,
+    stale: bool,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+pub struct FilterListStatistics {
+    id: Option<String>,
+    blocked_requests: u64,
+}
+
+/// Requests blocked by each filter list and custom filter.
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+pub struct FilterStatistics {
+    lists: Vec<FilterListStatistics>,
+    #[serde(with = "tuple_vec_map")]
+    custom_rules: Vec<(String, u64)>,
+}
+
 pub enum Message {
     Load,
     Display(FilterConfiguration),
+    StatusLoaded(FilterStatus),
+    StatisticsLoaded(FilterStatistics),
     UpdateFilterSelection((String, bool)),
     Save,
     ChangesSaved,
     AckChanges,
+    UpdateLists,
+    UpdateListsRequested,
 }
 
 pub struct Filters {
     filter_configuration: Option<FilterConfiguration>,
     filter_configuration_before_changes: Option<FilterConfiguration>,
+    filter_statuses: HashMap<String, FilterStatus>,
+    filter_statistics: Option<FilterStatistics>,
     changes_saved: bool,
+    update_requested: bool,
 }
 
 impl Filters {
@@ -350,7 +435,7 @@ impl Filters {
             .as_ref()
             .unwrap()
             .into_iter()
-            .any(|f| f.file_name == filter.file_name)
+            .any(|f| f.id == filter.id)
     }
 }
 
@@ -364,7 +449,10 @@ impl Component for Filters {
         Self {
             filter_configuration: None,
             filter_configuration_before_changes: None,
+            filter_statuses: HashMap::new(),
+            filter_statistics: None,
             changes_saved: false,
+            update_requested: false,
         }
     }
 
@@ -372,12 +460,55 @@ impl Component for Filters {
         match msg {
             Message::Display(filter_configuration) => {
                 log::debug!("Displaying");
+                for filter in &filter_configuration {
+                    let request = Request::get(&format!("api/v1/filters/{}/status", filter.id));
+                    let message_callback = ctx.link().callback(|message: Message| message);
+
+                    spawn_local(async move {
+                        match request.send().await {
+                            Ok(response) if response.ok() => {
+                                if let Ok(filter_status) = response.json::<FilterStatus>().await {
+                                    message_callback.emit(Message::StatusLoaded(filter_status))
+                                }
+                            }
+                            Ok(response) => {
+                                log::error!("Failed to get filter status: {:?}", response.status())
+                            }
+                            Err(err) => log::error!("Request error: {:?}", err),
+                        }
+                    });
+                }
+
+                let request = Request::get("api/v1/statistics/filters");
+                let message_callback = ctx.link().callback(|message: Message| message);
+                spawn_local(async move {
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            if let Ok(filter_statistics) = response.json::<FilterStatistics>().await
+                            {
+                                message_callback.emit(Message::StatisticsLoaded(filter_statistics))
+                            }
+                        }
+                        Ok(response) => {
+                            log::error!("Failed to get filter statistics: {:?}", response.status())
+                        }
+                        Err(err) => log::error!("Request error: {:?}", err),
+                    }
+                });
+
                 self.filter_configuration = Some(filter_configuration.clone());
                 self.filter_configuration_before_changes = Some(filter_configuration);
             }
+            Message::StatisticsLoaded(filter_statistics) => {
+                self.filter_statistics = Some(filter_statistics);
+            }
+            Message::StatusLoaded(filter_status) => {
+                self.filter_statuses
+                    .insert(filter_status.id.clone(), filter_status);
+            }
             Message::Load => {
                 log::debug!("Retrieving filters..");
-                let request = Request::get("/api/filters");
+                let request = Request::get("api/v1/filters");
                 log::debug!("Request: {:?}", request);
                 let message_callback = ctx.link().callback(|message: Message| message);
                 log::debug!("Message callback: {:?}", message_callback);
@@ -406,18 +537,27 @@ impl Component for Filters {
                 if !self.configuration_has_changed() {
                     return false;
                 }
+                let filters_before_changes =
+                    self.filter_configuration_before_changes.as_ref().unwrap();
+
+                // Only filters whose status changed are sent, in a single transaction.
                 let request_body = self
                     .filter_configuration
                     .as_ref()
                     .unwrap()
                     .iter()
-                    .map(|filter| FilterStatusChangeRequest {
-                        enabled: filter.enabled,
-                        file_name: filter.file_name.clone(),
+                    .filter(|filter| !filters_before_changes.contains(filter))
+                    .map(|filter| {
+                        let id = filter.id.clone();
+                        if filter.enabled {
+                            FilterBatchOperation::Enable { id }
+                        } else {
+                            FilterBatchOperation::Disable { id }
+                        }
                     })
                     .collect::<Vec<_>>();
 
-                let request = Request::put("/api/filters")
+                let request = Request::post("api/v1/filters/batch")
                     .header("Content-Type", "application/json")
                     .body(serde_json::to_string(&request_body).unwrap());
 
@@ -438,14 +578,14 @@ impl Component for Filters {
 
                 log::info!("Save")
             }
-            Message::UpdateFilterSelection((filter_name, enabled)) => {
+            Message::UpdateFilterSelection((filter_id, enabled)) => {
                 self.changes_saved = false;
 
                 self.filter_configuration
                     .as_mut()
                     .unwrap()
                     .iter_mut()
-                    .find(|filter| filter.file_name == filter_name)
+                    .find(|filter| filter.id == filter_id)
                     .and_then(|filter| {
                         filter.enabled = enabled;
 
@@ -457,6 +597,25 @@ impl Component for Filters {
                 self.filter_configuration_before_changes = self.filter_configuration.clone();
             }
             Message::AckChanges => self.changes_saved = false,
+            Message::UpdateLists => {
+                self.update_requested = true;
+
+                let request = Request::post("api/v1/filters/update");
+                let callback = ctx.link().callback(|message: Message| message);
+
+                // Lists are downloaded in the background, progress is reported on the events channel.
+                spawn_local(async move {
+                    match request.send().await {
+                        Ok(response) if !response.ok() => {
+                            log::error!("Failed to request filters update: {:?}", response.status())
+                        }
+                        Err(err) => log::error!("Request error: {:?}", err),
+                        _ => {}
+                    }
+                    callback.emit(Message::UpdateListsRequested);
+                });
+            }
+            Message::UpdateListsRequested => self.update_requested = false,
         };
 
         true
@@ -471,29 +630,72 @@ impl Component for Filters {
         };
         log::debug!("Retrieving callback..");
 
-        let callback = ctx
-            .link()
-            .callback(|(filter_file_name, enabled): (String, bool)| {
-                Message::UpdateFilterSelection((filter_file_name, enabled))
-            });
+        let callback = ctx.link().callback(|(filter_id, enabled): (String, bool)| {
+            Message::UpdateFilterSelection((filter_id, enabled))
+        });
         log::debug!("Retrieved callback.");
         let save_callback = ctx.link().callback(|_| Message::Save);
+        let update_lists_state = if self.update_requested {
+            ButtonState::Loading
+        } else {
+            ButtonState::Enabled
+        };
         let render_category_filter = |filter: &Filter| {
-            let filter_file_name = filter.file_name.clone();
+            let filter_id = filter.id.clone();
             let filter_enabled = filter.enabled;
             let callback_clone = callback.clone();
 
             let checkbox_callback = Callback::from(move |_| {
-                callback_clone.emit((filter_file_name.to_string(), !filter_enabled))
+                callback_clone.emit((filter_id.to_string(), !filter_enabled))
             });
+            let blocked_requests = self
+                .filter_statistics
+                .as_ref()
+                .and_then(|filter_statistics| {
+                    filter_statistics
+                        .lists
+                        .iter()
+                        .find(|list| list.id.as_ref() == Some(&filter.id))
+                        .map(|list| list.blocked_requests)
+                });
+            let blocked_requests_html = match blocked_requests {
+                Some(blocked_requests) => html! {
+                    <span class="ml-2 text-xs text-gray-500">{ format!("{} blocked", blocked_requests) }</span>
+                },
+                None => html! {},
+            };
+            let filter_status_html = match self.filter_statuses.get(&filter.id) {
+                Some(filter_status) => html! {
+                    <>
+                        <span class="ml-2 text-xs text-gray-500">{ format!("{} rules", filter_status.rule_count) }</span>
+                        if filter_status.stale {
+                            <span title={filter_status.last_error.clone().unwrap_or_default()}
+                                class="ml-2 inline-flex items-center px-2 py-0.5 rounded text-xs font-medium bg-red-100 text-red-800">{ "Stale" }</span>
+                        }
+                        if filter_status.parse_error_count > 0 {
+                            <span title={filter_status.parse_errors.join("\n")}
+                                class="ml-2 inline-flex items-center px-2 py-0.5 rounded text-xs font-medium bg-yellow-100 text-yellow-800">
+                                { format!("{} invalid rules", filter_status.parse_error_count) }
+                            </span>
+                        }
+                    </>
+                },
+                None => html! {},
+            };
             log::debug!("Returning category filter.");
             html! {
             <div class="relative flex items-start py-4">
                 <div class="min-w-0 flex-1 text-sm">
-                    <label for={filter.file_name.clone()} class="select-none">{&filter.title}</label>
+                    <label for={filter.id.clone()} class="select-none">{&filter.title}</label>
+                    if filter.managed {
+                        <span class="ml-2 inline-flex items-center px-2 py-0.5 rounded text-xs font-medium bg-gray-100 text-gray-800">{ "Managed" }</span>
+                    }
+                    { filter_status_html }
+                    { blocked_requests_html }
                 </div>
                 <div class="ml-3 flex items-center h-5">
-                    <input checked={filter.enabled} onchange={checkbox_callback} name={filter.file_name.clone()} type="checkbox"
+                    <input checked={filter.enabled} onchange={checkbox_callback} name={filter.id.clone()} id={filter.id.clone()} type="checkbox"
+                        disabled={filter.managed}
                         class="focus:ring-blue-500 h-4 w-4 text-blue-600 border-gray-300 rounded" />
                 </div>
             </div>
@@ -527,6 +729,23 @@ impl Component for Filters {
             }
         };
 
+        let custom_rules_html = match &self.filter_statistics {
+            Some(filter_statistics) if !filter_statistics.custom_rules.is_empty() => html! {
+                <fieldset class="mb-8">
+                    <legend class="text-lg font-medium text-gray-900">{ "Custom filters" }</legend>
+                    <div class="mt-4 border-t border-b border-gray-200 divide-y divide-gray-200">
+                        { for filter_statistics.custom_rules.iter().map(|(rule, blocked_requests)| html! {
+                            <div class="flex items-center justify-between py-2 text-sm">
+                                <span class="font-mono truncate">{ rule }</span>
+                                <span class="ml-3 text-xs text-gray-500">{ format!("{} blocked", blocked_requests) }</span>
+                            </div>
+                        }) }
+                    </div>
+                </fieldset>
+            },
+            _ => html! {},
+        };
+
         let success_banner = if self.changes_saved {
             let icon = html! {
                 <svg xmlns="http://www.w3.org/2000/svg" class="h-6 w-6 text-white" fill="none"
@@ -561,6 +780,12 @@ impl Component for Filters {
                                 <AddFilterComponent state={save_button::SaveButtonState::Enabled}/>
                                 <SearchFilterList filter_configuration={filter_configuration.clone()}/>
                                 {save_button!(save_callback, save_button_state)}
+                                <PrivaxyButton
+                                    color={ButtonColor::Gray}
+                                    state={update_lists_state}
+                                    onclick={ctx.link().callback(|_| Message::UpdateLists)}
+                                    button_text={"Update lists"}
+                                />
                             </div>
                             { render_category(FilterGroup::Default, filter_configuration) }
                             { render_category(FilterGroup::Ads, filter_configuration) }
@@ -568,6 +793,7 @@ impl Component for Filters {
                             { render_category(FilterGroup::Malware, filter_configuration) }
                             { render_category(FilterGroup::Social, filter_configuration) }
                             { render_category(FilterGroup::Regional, filter_configuration) }
+                            { custom_rules_html }
                         </>
                 }
             }
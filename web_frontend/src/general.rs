@@ -31,6 +31,8 @@ pub enum Message {
     UpdateCaKey(String),
     UploadCaCert(web_sys::File),
     UploadCaKey(web_sys::File),
+    UploadConfiguration(web_sys::File),
+    ImportConfiguration(String),
     ValidateCertificates,
     ValidationFailed(String),
     UpdateTls(bool),
@@ -94,7 +96,7 @@ impl NetworkSettings {
     }
     async fn save(&mut self) -> Result<(), ApiError> {
         let body = serde_json::to_string(&self.current_config).unwrap();
-        let req = reqwasm::http::Request::put("/api/settings/network")
+        let req = reqwasm::http::Request::put("api/v1/settings/network")
             .body(body)
             .header("Content-Type", "application/json");
         match req.send().await {
@@ -183,7 +185,7 @@ impl Component for GeneralSettings {
             Message::Load => {
                 let link = ctx.link().clone();
                 spawn_local(async move {
-                    let request = Request::get("/api/settings/network");
+                    let request = Request::get("api/v1/settings/network");
                     match request.send().await {
                         Ok(response) => {
                             if response.ok() {
@@ -337,6 +339,39 @@ impl Component for GeneralSettings {
                     }),
                 );
             }
+            Message::UploadConfiguration(file) => {
+                let link = ctx.link().clone();
+                read_file(
+                    file,
+                    Callback::from(move |result: Result<String, String>| match result {
+                        Ok(text) => link.send_message(Message::ImportConfiguration(text)),
+                        Err(e) => log::error!("Failed to read configuration file: {}", e),
+                    }),
+                );
+            }
+            Message::ImportConfiguration(export) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::post("api/v1/config/import")
+                        .header("Content-Type", "application/json")
+                        .body(export);
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::SaveSuccess);
+                            link.send_message(Message::Load);
+                        }
+                        Ok(response) => link.send_message(Message::SaveFailed(
+                            response.json::<ApiError>().await.unwrap_or(ApiError {
+                                error: response.status_text(),
+                            }),
+                        )),
+                        Err(err) => link.send_message(Message::SaveFailed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+            }
             Message::ValidateCertificates => {
                 let cert_pem = self.ca_config.ca_cert_pem.clone();
                 let key_pem = self.ca_config.private_key_pem.clone();
@@ -534,6 +569,24 @@ impl Component for GeneralSettings {
                     <div>{"Loading..."}</div>
                 }
                     {render_category("Certificate", SettingCategories::Certificate(self.ca_config.clone()))}
+                <fieldset class="mb-8" style="width: 100%;">
+                    <legend class="text-lg font-medium text-gray-900">{"Backup"}</legend>
+                    <div class="mt-4 border-t border-b border-gray-200 py-4">
+                        <p class="text-gray-400 text-sm mb-4">{"The whole configuration, including the CA private key and credentials, as a single file to back it up or move it to another machine. Importing a configuration replaces the current one and restarts the proxy."}</p>
+                        <div class="flex space-x-4">
+                            <a href="api/v1/config/export" download="privaxy-configuration.json" class={ get_css(ButtonColor::Blue) }>{"Export configuration"}</a>
+                            <input
+                                type="file"
+                                accept=".json"
+                                class={ get_css(ButtonColor::Gray) }
+                                onchange={ctx.link().batch_callback(|e: Event| {
+                                    let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                                    input.files().and_then(|files| files.get(0)).map(Message::UploadConfiguration)
+                                })}
+                            />
+                        </div>
+                    </div>
+                </fieldset>
 
             {save_button!(save_callback, save_button_state)}
             </>
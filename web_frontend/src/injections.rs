@@ -0,0 +1,276 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::save_button::{SaveButton, SaveButtonState};
+use crate::success_banner;
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Injection {
+    host: String,
+    enabled: bool,
+    css: String,
+    js: String,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<Injection>),
+    UpdateNewHost(String),
+    Add,
+    Delete(usize),
+    SetEnabled(usize, bool),
+    UpdateCss(usize, String),
+    UpdateJs(usize, String),
+    Save,
+    Saved,
+    AckChanges,
+    Failed(ApiError),
+}
+
+/// Editor of the styles and scripts added to the pages of hosts.
+pub struct Injections {
+    injections: Option<Vec<Injection>>,
+    saved_injections: Option<Vec<Injection>>,
+    new_host: String,
+    changes_saved: bool,
+    err_msg: Option<String>,
+}
+
+impl Injections {
+    fn update_injection(&mut self, index: usize, update: impl FnOnce(&mut Injection)) -> bool {
+        match self
+            .injections
+            .as_mut()
+            .and_then(|injections| injections.get_mut(index))
+        {
+            Some(injection) => {
+                update(injection);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Component for Injections {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            injections: None,
+            saved_injections: None,
+            new_host: String::new(),
+            changes_saved: false,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/injections");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<Vec<Injection>>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(injections) => {
+                self.saved_injections = Some(injections.clone());
+                self.injections = Some(injections);
+
+                true
+            }
+            Message::UpdateNewHost(new_host) => {
+                self.new_host = new_host;
+
+                true
+            }
+            Message::Add => {
+                let host = self.new_host.trim().to_lowercase();
+                if host.is_empty() {
+                    return false;
+                }
+
+                if let Some(injections) = &mut self.injections {
+                    injections.push(Injection {
+                        host,
+                        enabled: true,
+                        css: String::new(),
+                        js: String::new(),
+                    });
+                    self.new_host = String::new();
+                }
+
+                true
+            }
+            Message::Delete(index) => {
+                if let Some(injections) = &mut self.injections {
+                    if index < injections.len() {
+                        injections.remove(index);
+                    }
+                }
+
+                true
+            }
+            Message::SetEnabled(index, enabled) => {
+                self.update_injection(index, |injection| injection.enabled = enabled)
+            }
+            Message::UpdateCss(index, css) => {
+                self.update_injection(index, |injection| injection.css = css)
+            }
+            Message::UpdateJs(index, js) => {
+                self.update_injection(index, |injection| injection.js = js)
+            }
+            Message::Save => {
+                let injections = match &self.injections {
+                    Some(injections) => injections.clone(),
+                    None => return false,
+                };
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/injections")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&injections).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Saved),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Saved => {
+                self.changes_saved = true;
+                ctx.link().send_message(Message::Load);
+
+                true
+            }
+            Message::AckChanges => {
+                self.changes_saved = false;
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_injection = |(index, injection): (usize, &Injection)| {
+            let on_delete = ctx.link().callback(move |_| Message::Delete(index));
+
+            let on_enabled_change = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::SetEnabled(index, input.checked())
+            });
+
+            let on_css_input = ctx.link().callback(move |e: InputEvent| {
+                let textarea: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
+                Message::UpdateCss(index, textarea.value())
+            });
+
+            let on_js_input = ctx.link().callback(move |e: InputEvent| {
+                let textarea: web_sys::HtmlTextAreaElement = e.target_unchecked_into();
+                Message::UpdateJs(index, textarea.value())
+            });
+
+            html! {
+                <div class="mt-4 border border-gray-200 rounded-md p-4">
+                    <div class="flex items-center justify-between">
+                        <span class="text-sm font-medium text-gray-900">{ &injection.host }</span>
+                        <div class="flex items-center space-x-4">
+                            <label class="flex items-center text-sm text-gray-700">
+                                <input onchange={on_enabled_change} type="checkbox" checked={injection.enabled}
+                                    class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                                { "Enabled" }
+                            </label>
+                            <button onclick={on_delete} class="text-sm text-red-600 hover:text-red-800">{ "Remove" }</button>
+                        </div>
+                    </div>
+                    <label class="block mt-3 text-sm font-medium text-gray-700">{ "CSS" }</label>
+                    <textarea oninput={on_css_input} value={injection.css.clone()} rows="6"
+                        class="mt-1 shadow-sm focus:ring-blue-500 focus:border-blue-500 block w-full font-mono sm:text-sm border-gray-300 rounded-md"></textarea>
+                    <label class="block mt-3 text-sm font-medium text-gray-700">{ "JavaScript" }</label>
+                    <textarea oninput={on_js_input} value={injection.js.clone()} rows="6"
+                        class="mt-1 shadow-sm focus:ring-blue-500 focus:border-blue-500 block w-full font-mono sm:text-sm border-gray-300 rounded-md"></textarea>
+                </div>
+            }
+        };
+
+        let on_new_host_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewHost(input.value())
+        });
+
+        let add_button_state = if self.new_host.trim().is_empty() || self.injections.is_none() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        let save_button_state = if self.injections == self.saved_injections {
+            SaveButtonState::Disabled
+        } else {
+            SaveButtonState::Enabled
+        };
+
+        let success_banner_html = if self.changes_saved {
+            success_banner!(true, ctx.link().callback(|_| Message::AckChanges))
+        } else {
+            html! {}
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Injections" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "Add styles and scripts to the pages of a host and its subdomains, such as dark themes or fixes for broken sites. Use " }<span class="font-mono bg-gray-100">{ "*" }</span>{ " to add them to every page." }</p>
+                </div>
+                { success_banner_html }
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                { for self.injections.iter().flat_map(|injections| injections.iter().enumerate()).map(render_injection) }
+                <div class="mt-4 flex space-x-3">
+                    <input oninput={on_new_host_input} value={self.new_host.clone()} type="text" placeholder="example.com"
+                        class="shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <PrivaxyButton color={ButtonColor::Blue} state={add_button_state} onclick={ctx.link().callback(|_| Message::Add)} button_text="Add host" />
+                </div>
+                <SaveButton state={save_button_state} onclick={ctx.link().callback(|_| Message::Save)} />
+            </>
+        }
+    }
+}
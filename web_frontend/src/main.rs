@@ -3,17 +3,46 @@ use yew::functional::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+mod about;
+mod audit_log;
+mod block_responses;
 mod blocking_enabled;
 mod button;
+mod categories;
+mod certificate;
+mod client_grants;
+mod clients;
+mod content_rewrite_rules;
+mod cookie_rules;
 mod dashboard;
+mod debug_headers;
+mod event_log;
+mod filter_test;
 mod filterlists;
 mod filters;
 mod general;
+mod injections;
+mod managed;
+mod notifications;
+mod plugins;
+mod profiles;
 mod requests;
+mod response_cache;
+mod safe_search;
 mod save_button;
+mod schedules;
+mod sessions;
 mod settings;
 mod settings_textarea;
+mod sign_in;
+mod site_policies;
+mod statistics_comparison;
 mod submit_banner;
+mod suggested_exclusions;
+mod system;
+mod temporary_exclusions;
+mod top_companies;
+mod url_rewrites;
 
 #[derive(Debug, Deserialize, Clone)]
 pub(crate) struct ApiError {
@@ -26,6 +55,10 @@ enum Route {
     Dashboard,
     #[at("/requests")]
     Requests,
+    #[at("/clients")]
+    Clients,
+    #[at("/system")]
+    System,
     #[at("/settings/:s")]
     Settings,
     #[not_found]
@@ -86,14 +119,17 @@ fn switch(route: &Route) -> Html {
         <div class="flex items-center justify-between h-16">
           <div class="flex items-center">
             <div class="flex-shrink-0">
-              <img class="h-8 w-auto text-white" src="/logo.svg" alt="Logo" />
+              <img class="h-8 w-auto text-white" src="logo.svg" alt="Logo" />
             </div>
               <div class="flex ml-6 space-x-4">
               <Link<Route> classes={ get_classes(*route, Route::Dashboard) } to={Route::Dashboard}>{ "Dashboard" }</Link<Route>>
                <Link<Route> classes={ get_classes(*route, Route::Requests) } to={Route::Requests}>{ "Requests" }</Link<Route>>
+               <Link<Route> classes={ get_classes(*route, Route::Clients) } to={Route::Clients}>{ "Clients" }</Link<Route>>
+               <Link<Route> classes={ get_classes(*route, Route::System) } to={Route::System}>{ "System" }</Link<Route>>
                <Link<settings::SettingsRoute> classes={ get_classes(*route, Route::Settings) } to={settings::SettingsRoute::Filters}>{ "Settings" }</Link<settings::SettingsRoute>>
                </div>
           </div>
+          <profiles::ProfileSwitcher />
         </div>
       </div>
     </nav> };
@@ -108,6 +144,14 @@ fn switch(route: &Route) -> Html {
             set_title("Requests");
             html! { <>{navigation} <div class={"container mt-4 mb-10 mx-auto px-4 sm:px-6 lg:px-8"}> <requests::Requests /> </div></> }
         }
+        Route::Clients => {
+            set_title("Clients");
+            html! { <>{navigation} <div class={"container mt-4 mb-10 mx-auto px-4 sm:px-6 lg:px-8"}> <clients::Clients /> </div></> }
+        }
+        Route::System => {
+            set_title("System");
+            html! { <>{navigation} <div class={"container mt-4 mb-10 mx-auto px-4 sm:px-6 lg:px-8"}> <system::System /> </div></> }
+        }
         Route::Settings => {
             html! {<>{navigation} <div class={"container mt-4 mb-10 mx-auto px-4 sm:px-6 lg:px-8"}> <Switch<settings::SettingsRoute> render={Switch::render(settings::switch_settings)} /> </div> </>}
         }
@@ -121,9 +165,11 @@ fn switch(route: &Route) -> Html {
 #[function_component(App)]
 fn app() -> Html {
     html! {
-        <BrowserRouter>
-            <Switch<Route> render={Switch::render(switch)} />
-        </BrowserRouter>
+        <sign_in::SignInGate>
+            <BrowserRouter>
+                <Switch<Route> render={Switch::render(switch)} />
+            </BrowserRouter>
+        </sign_in::SignInGate>
     }
 }
 
@@ -131,6 +177,19 @@ fn set_title(title: &str) {
     gloo_utils::document().set_title(&format!("Privaxy | {}", title));
 }
 
+/// Url of an API WebSocket, relative to the base path the web GUI is served under. It
+/// uses TLS when the web GUI is served over HTTPS.
+fn get_websocket_url(path: &str) -> String {
+    let base_uri = gloo_utils::document()
+        .base_uri()
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    // `https://` becomes `wss://` and `http://` becomes `ws://`.
+    format!("ws{}{}", base_uri.trim_start_matches("http"), path)
+}
+
 fn main() {
     wasm_logger::init(wasm_logger::Config::default());
 
@@ -0,0 +1,83 @@
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::{html, Component, Context, Html};
+
+/// Baseline policy pushed by an administrator, only the exclusions are shown here,
+/// managed filter lists are flagged on the filters page.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ManagedConfiguration {
+    #[serde(default)]
+    exclusions: Vec<String>,
+}
+
+pub enum Message {
+    Load,
+    Loaded(ManagedConfiguration),
+}
+
+/// Lists the exclusions of the managed configuration, which can't be edited locally.
+pub struct ManagedExclusions {
+    managed_configuration: ManagedConfiguration,
+}
+
+impl Component for ManagedExclusions {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            managed_configuration: ManagedConfiguration::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/managed");
+
+                    if let Ok(response) = request.send().await {
+                        if response.ok() {
+                            if let Ok(managed_configuration) =
+                                response.json::<ManagedConfiguration>().await
+                            {
+                                link.send_message(Message::Loaded(managed_configuration));
+                            }
+                        }
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(managed_configuration) => {
+                self.managed_configuration = managed_configuration;
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        if self.managed_configuration.exclusions.is_empty() {
+            return html! {};
+        }
+
+        html! {
+            <div class="mt-8">
+                <h2 class="text-lg font-medium text-gray-900">{ "Managed exclusions" }</h2>
+                <p class="text-gray-600">
+                    { "These exclusions are set by your administrator and apply in addition to the ones above." }
+                </p>
+                <ul class="mt-2 divide-y divide-gray-200">
+                    { for self.managed_configuration.exclusions.iter().map(|exclusion| html! {
+                        <li class="py-3 text-sm font-medium text-gray-900">{ exclusion }</li>
+                    }) }
+                </ul>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::save_button::{SaveButton, SaveButtonState};
+use crate::success_banner;
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+const FORMATS: [(&str, &str); 4] = [
+    ("json", "JSON"),
+    ("slack", "Slack"),
+    ("discord", "Discord"),
+    ("ntfy", "ntfy"),
+];
+
+const SEVERITIES: [(&str, &str); 3] = [
+    ("info", "Info and above"),
+    ("warning", "Warnings and errors"),
+    ("error", "Errors only"),
+];
+
+const CATEGORIES: [(&str, &str); 7] = [
+    ("block", "Blocked requests"),
+    ("config", "Configuration"),
+    ("filter_update", "Filter updates"),
+    ("tls", "TLS"),
+    ("system", "System"),
+    ("security", "Security"),
+    ("script", "Scripts"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationTarget {
+    name: String,
+    enabled: bool,
+    format: String,
+    url: String,
+    /// Not editable here, kept as they are.
+    #[serde(default)]
+    types: Vec<String>,
+    categories: Vec<String>,
+    min_severity: String,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<NotificationTarget>),
+    UpdateNewName(String),
+    Add,
+    Delete(usize),
+    SetEnabled(usize, bool),
+    UpdateFormat(usize, String),
+    UpdateUrl(usize, String),
+    UpdateMinSeverity(usize, String),
+    SetCategory(usize, String, bool),
+    Save,
+    Saved,
+    AckChanges,
+    Failed(ApiError),
+}
+
+/// Editor of the webhooks events are posted to.
+pub struct Notifications {
+    targets: Option<Vec<NotificationTarget>>,
+    saved_targets: Option<Vec<NotificationTarget>>,
+    new_name: String,
+    changes_saved: bool,
+    err_msg: Option<String>,
+}
+
+impl Notifications {
+    fn update_target(
+        &mut self,
+        index: usize,
+        update: impl FnOnce(&mut NotificationTarget),
+    ) -> bool {
+        match self
+            .targets
+            .as_mut()
+            .and_then(|targets| targets.get_mut(index))
+        {
+            Some(target) => {
+                update(target);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Component for Notifications {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            targets: None,
+            saved_targets: None,
+            new_name: String::new(),
+            changes_saved: false,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/notifications");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<Vec<NotificationTarget>>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(targets) => {
+                self.saved_targets = Some(targets.clone());
+                self.targets = Some(targets);
+
+                true
+            }
+            Message::UpdateNewName(new_name) => {
+                self.new_name = new_name;
+
+                true
+            }
+            Message::Add => {
+                let name = self.new_name.trim().to_string();
+                if name.is_empty() {
+                    return false;
+                }
+
+                if let Some(targets) = &mut self.targets {
+                    targets.push(NotificationTarget {
+                        name,
+                        enabled: true,
+                        format: "json".to_string(),
+                        url: String::new(),
+                        types: Vec::new(),
+                        categories: Vec::new(),
+                        min_severity: "warning".to_string(),
+                    });
+                    self.new_name = String::new();
+                }
+
+                true
+            }
+            Message::Delete(index) => {
+                if let Some(targets) = &mut self.targets {
+                    if index < targets.len() {
+                        targets.remove(index);
+                    }
+                }
+
+                true
+            }
+            Message::SetEnabled(index, enabled) => {
+                self.update_target(index, |target| target.enabled = enabled)
+            }
+            Message::UpdateFormat(index, format) => {
+                self.update_target(index, |target| target.format = format)
+            }
+            Message::UpdateUrl(index, url) => self.update_target(index, |target| target.url = url),
+            Message::UpdateMinSeverity(index, min_severity) => {
+                self.update_target(index, |target| target.min_severity = min_severity)
+            }
+            Message::SetCategory(index, category, is_set) => self.update_target(index, |target| {
+                target.categories.retain(|c| *c != category);
+                if is_set {
+                    target.categories.push(category);
+                }
+            }),
+            Message::Save => {
+                let targets = match &self.targets {
+                    Some(targets) => targets.clone(),
+                    None => return false,
+                };
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/notifications")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&targets).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Saved),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Saved => {
+                self.changes_saved = true;
+                ctx.link().send_message(Message::Load);
+
+                true
+            }
+            Message::AckChanges => {
+                self.changes_saved = false;
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let input_css = "shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline";
+
+        let render_target = |(index, target): (usize, &NotificationTarget)| {
+            let on_delete = ctx.link().callback(move |_| Message::Delete(index));
+
+            let on_enabled_change = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::SetEnabled(index, input.checked())
+            });
+
+            let on_format_change = ctx.link().callback(move |e: Event| {
+                let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+                Message::UpdateFormat(index, select.value())
+            });
+
+            let on_url_input = ctx.link().callback(move |e: InputEvent| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::UpdateUrl(index, input.value())
+            });
+
+            let on_severity_change = ctx.link().callback(move |e: Event| {
+                let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+                Message::UpdateMinSeverity(index, select.value())
+            });
+
+            let render_category = |(category, label): &(&str, &str)| {
+                let category = category.to_string();
+                let is_set = target.categories.contains(&category);
+                let on_change = ctx.link().callback(move |e: Event| {
+                    let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                    Message::SetCategory(index, category.clone(), input.checked())
+                });
+
+                html! {
+                    <label class="flex items-center text-sm text-gray-700">
+                        <input onchange={on_change} type="checkbox" checked={is_set}
+                            class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                        { *label }
+                    </label>
+                }
+            };
+
+            html! {
+                <div class="mt-4 border border-gray-200 rounded-md p-4">
+                    <div class="flex items-center justify-between">
+                        <span class="text-sm font-medium text-gray-900">{ &target.name }</span>
+                        <div class="flex items-center space-x-4">
+                            <label class="flex items-center text-sm text-gray-700">
+                                <input onchange={on_enabled_change} type="checkbox" checked={target.enabled}
+                                    class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                                { "Enabled" }
+                            </label>
+                            <button onclick={on_delete} class="text-sm text-red-600 hover:text-red-800">{ "Remove" }</button>
+                        </div>
+                    </div>
+                    <div class="mt-3 flex items-center space-x-2">
+                        <select onchange={on_format_change} class={input_css}>
+                            { for FORMATS.iter().map(|(format, label)| html! {
+                                <option value={*format} selected={*format == target.format}>{ *label }</option>
+                            }) }
+                        </select>
+                        <input oninput={on_url_input} value={target.url.clone()} type="text" placeholder="https://ntfy.sh/my-topic"
+                            class={classes!(input_css, "flex-1")} />
+                        <select onchange={on_severity_change} class={input_css}>
+                            { for SEVERITIES.iter().map(|(severity, label)| html! {
+                                <option value={*severity} selected={*severity == target.min_severity}>{ *label }</option>
+                            }) }
+                        </select>
+                    </div>
+                    <div class="mt-3 flex flex-wrap items-center space-x-4">
+                        { for CATEGORIES.iter().map(render_category) }
+                    </div>
+                </div>
+            }
+        };
+
+        let on_new_name_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewName(input.value())
+        });
+
+        let add_button_state = if self.new_name.trim().is_empty() || self.targets.is_none() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        let save_button_state = if self.targets == self.saved_targets {
+            SaveButtonState::Disabled
+        } else {
+            SaveButtonState::Enabled
+        };
+
+        let success_banner_html = if self.changes_saved {
+            success_banner!(true, ctx.link().callback(|_| Message::AckChanges))
+        } else {
+            html! {}
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Notifications" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "Post events to webhooks as they happen, such as failed filter updates or an expiring CA certificate. Targets receive every category of event when none is checked." }</p>
+                </div>
+                { success_banner_html }
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                { for self.targets.iter().flat_map(|targets| targets.iter().enumerate()).map(render_target) }
+                <div class="mt-4 flex space-x-3">
+                    <input oninput={on_new_name_input} value={self.new_name.clone()} type="text" placeholder="Name, like phone"
+                        class={input_css} />
+                    <PrivaxyButton color={ButtonColor::Blue} state={add_button_state} onclick={ctx.link().callback(|_| Message::Add)} button_text="Add target" />
+                </div>
+                <SaveButton state={save_button_state} onclick={ctx.link().callback(|_| Message::Save)} />
+            </>
+        }
+    }
+}
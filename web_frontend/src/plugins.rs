@@ -0,0 +1,274 @@
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plugin {
+    name: String,
+    enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginsConfiguration {
+    plugins: Vec<Plugin>,
+    max_fuel: u64,
+    max_memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PluginStatus {
+    name: String,
+    hooks: Vec<String>,
+    load_error: Option<String>,
+    calls: u64,
+    failures: u64,
+    last_error: Option<String>,
+    last_error_at: Option<String>,
+}
+
+pub enum Message {
+    Load,
+    Loaded(PluginsConfiguration),
+    LoadStatuses,
+    LoadedStatuses(Vec<PluginStatus>),
+    SetEnabled(usize, bool),
+    Delete(String),
+    Save(PluginsConfiguration),
+    Failed(ApiError),
+}
+
+/// WebAssembly plugins, along with how their hooks fared.
+pub struct Plugins {
+    configuration: Option<PluginsConfiguration>,
+    statuses: Vec<PluginStatus>,
+    err_msg: Option<String>,
+}
+
+impl Component for Plugins {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            configuration: None,
+            statuses: Vec::new(),
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/plugins");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<PluginsConfiguration>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(configuration) => {
+                self.configuration = Some(configuration);
+                ctx.link().send_message(Message::LoadStatuses);
+
+                true
+            }
+            Message::LoadStatuses => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/plugins/status");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::LoadedStatuses(
+                                response.json::<Vec<PluginStatus>>().await.unwrap(),
+                            ))
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::LoadedStatuses(statuses) => {
+                self.statuses = statuses;
+
+                true
+            }
+            Message::SetEnabled(index, enabled) => {
+                if let Some(configuration) = &self.configuration {
+                    let mut configuration = configuration.clone();
+                    if let Some(plugin) = configuration.plugins.get_mut(index) {
+                        plugin.enabled = enabled;
+                    }
+                    ctx.link().send_message(Message::Save(configuration));
+                }
+
+                false
+            }
+            Message::Delete(name) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::delete(&format!("api/v1/plugins/{}", name));
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Save(configuration) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/plugins")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&configuration).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_row = |(index, plugin): (usize, &Plugin)| {
+            let on_enabled_change = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::SetEnabled(index, input.checked())
+            });
+            let name = plugin.name.clone();
+            let on_delete = ctx.link().callback(move |_| Message::Delete(name.clone()));
+
+            let status = self
+                .statuses
+                .iter()
+                .find(|status| status.name == plugin.name);
+
+            let hooks = status.map_or_else(String::new, |status| status.hooks.join(", "));
+            let calls = status.map_or_else(String::new, |status| {
+                format!("{} ({} failed)", status.calls, status.failures)
+            });
+            let error = match status {
+                Some(PluginStatus {
+                    load_error: Some(load_error),
+                    ..
+                }) => format!("Not loaded: {}", load_error),
+                Some(PluginStatus {
+                    last_error: Some(last_error),
+                    last_error_at: Some(last_error_at),
+                    ..
+                }) => format!("{} ({})", last_error, last_error_at),
+                _ => String::new(),
+            };
+
+            html! {
+                <tr>
+                    <td class="px-2 py-1 text-sm font-medium text-gray-900">
+                        <label class="flex items-center">
+                            <input onchange={on_enabled_change} type="checkbox" checked={plugin.enabled}
+                                class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                            <span class="font-mono">{ &plugin.name }</span>
+                        </label>
+                    </td>
+                    <td class="px-2 py-1 text-sm text-gray-500 font-mono">{ hooks }</td>
+                    <td class="px-2 py-1 text-sm text-gray-500">{ calls }</td>
+                    <td class="px-2 py-1 text-sm text-red-500 break-all">{ error }</td>
+                    <td class="px-2 py-1 text-right">
+                        <button onclick={on_delete} class="text-sm text-red-600 hover:text-red-800">{ "Remove" }</button>
+                    </td>
+                </tr>
+            }
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Plugins" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "WebAssembly modules called with the headers of requests before they are sent upstream, and of responses before they are relayed, in order." }</p>
+                    <p>
+                        { "Modules are installed with " }
+                        <span class="font-mono bg-gray-100">{ "PUT /api/v1/plugins/<name>/module" }</span>
+                        { ". A plugin failing or running out of its limits is skipped, the request goes on as if it wasn't there." }
+                    </p>
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                if let Some(configuration) = &self.configuration {
+                    <p class="mt-4 text-sm text-gray-600">
+                        { format!(
+                            "Each call runs with {} units of fuel and {} KiB of memory.",
+                            configuration.max_fuel,
+                            configuration.max_memory_bytes / 1024
+                        ) }
+                    </p>
+                }
+                <div class="mt-4 overflow-x-auto">
+                    <table class="min-w-full">
+                        <thead>
+                            <tr>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Name" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Hooks" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Calls" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Last error" }</th>
+                                <th scope="col"></th>
+                            </tr>
+                        </thead>
+                        <tbody class="divide-y divide-gray-200">
+                            { for self.configuration.iter().flat_map(|configuration| configuration.plugins.iter().enumerate()).map(render_row) }
+                        </tbody>
+                    </table>
+                </div>
+            </>
+        }
+    }
+}
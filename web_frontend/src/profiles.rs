@@ -0,0 +1,321 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::failure_banner;
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Profile {
+    name: String,
+    enabled_filters: BTreeSet<String>,
+    exclusions: BTreeSet<String>,
+    custom_filters: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ProfilesResponse {
+    active_profile: Option<String>,
+    profiles: Vec<Profile>,
+}
+
+impl ProfilesResponse {
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.profiles
+            .iter()
+            .map(|profile| profile.name.clone())
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ProfileRequest {
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ActiveProfileRequest {
+    name: Option<String>,
+}
+
+fn load_profiles<C>(ctx: &Context<C>, on_success: fn(ProfilesResponse) -> C::Message)
+where
+    C: Component,
+{
+    let link = ctx.link().clone();
+    spawn_local(async move {
+        match Request::get("api/v1/profiles").send().await {
+            Ok(response) => {
+                if let Ok(profiles) = response.json::<ProfilesResponse>().await {
+                    link.send_message(on_success(profiles));
+                }
+            }
+            Err(err) => {
+                log::error!("Request error: {:?}", err);
+            }
+        }
+    });
+}
+
+pub enum SwitcherMessage {
+    Load,
+    LoadSuccess(ProfilesResponse),
+    Switch(Option<String>),
+}
+
+/// Dropdown switching between profiles, hidden until one is created.
+pub(crate) struct ProfileSwitcher {
+    profiles: Option<ProfilesResponse>,
+}
+
+impl Component for ProfileSwitcher {
+    type Message = SwitcherMessage;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(SwitcherMessage::Load);
+
+        Self { profiles: None }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            SwitcherMessage::Load => load_profiles(ctx, SwitcherMessage::LoadSuccess),
+            SwitcherMessage::LoadSuccess(profiles) => {
+                self.profiles = Some(profiles);
+            }
+            SwitcherMessage::Switch(name) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/profiles/active")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&ActiveProfileRequest { name }).unwrap());
+
+                    match request.send().await {
+                        Ok(response) => {
+                            if !response.ok() {
+                                log::error!("Failed to switch profile: {:?}", response.status());
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Request error: {:?}", err);
+                        }
+                    }
+                    link.send_message(SwitcherMessage::Load);
+                });
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let profiles = match &self.profiles {
+            Some(profiles) if !profiles.profiles.is_empty() => profiles,
+            _ => return html! {},
+        };
+
+        let onchange = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            let value = select.value();
+            SwitcherMessage::Switch(if value.is_empty() { None } else { Some(value) })
+        });
+
+        html! {
+            <select {onchange} title="Profile" class="bg-gray-700 text-white text-sm rounded-md border-0 py-2 pl-3 pr-8 focus:outline-none">
+                <option value="" selected={profiles.active_profile.is_none()}>{ "No profile" }</option>
+                { for profiles.profiles.iter().map(|profile| html! {
+                    <option value={profile.name.clone()} selected={Some(&profile.name) == profiles.active_profile.as_ref()}>{ &profile.name }</option>
+                }) }
+            </select>
+        }
+    }
+}
+
+pub enum Message {
+    Load,
+    LoadSuccess(ProfilesResponse),
+    UpdateName(String),
+    Add,
+    Delete(String),
+    Failed(ApiError),
+    AcknowledgeError,
+}
+
+/// Settings page creating and deleting profiles.
+pub(crate) struct Profiles {
+    profiles: Option<ProfilesResponse>,
+    name: String,
+    show_error: bool,
+    err_msg: String,
+}
+
+fn send_request(ctx: &Context<Profiles>, request: Request) {
+    let link = ctx.link().clone();
+    spawn_local(async move {
+        match request.send().await {
+            Ok(response) => {
+                if response.ok() {
+                    link.send_message(Message::Load);
+                } else {
+                    link.send_message(Message::Failed(response.json::<ApiError>().await.unwrap()));
+                }
+            }
+            Err(err) => link.send_message(Message::Failed(ApiError {
+                error: format!("{:?}", err),
+            })),
+        }
+    });
+}
+
+impl Component for Profiles {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            profiles: None,
+            name: String::new(),
+            show_error: false,
+            err_msg: String::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => load_profiles(ctx, Message::LoadSuccess),
+            Message::LoadSuccess(profiles) => {
+                self.profiles = Some(profiles);
+            }
+            Message::UpdateName(name) => {
+                self.name = name;
+            }
+            Message::Add => {
+                let request = Request::post("api/v1/profiles")
+                    .header("Content-Type", "application/json")
+                    .body(
+                        serde_json::to_string(&ProfileRequest {
+                            name: self.name.trim().to_string(),
+                        })
+                        .unwrap(),
+                    );
+                self.name = String::new();
+
+                send_request(ctx, request);
+            }
+            Message::Delete(name) => {
+                let url = format!(
+                    "api/v1/profiles/{}",
+                    String::from(js_sys::encode_uri_component(&name))
+                );
+
+                send_request(ctx, Request::delete(&url));
+            }
+            Message::Failed(err) => {
+                self.show_error = true;
+                self.err_msg = err.error;
+            }
+            Message::AcknowledgeError => {
+                self.show_error = false;
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_profile = |profile: &Profile, active: bool| {
+            let name = profile.name.clone();
+            let action = if active {
+                html! { <span class="ml-3 text-sm text-gray-500">{ "Active" }</span> }
+            } else {
+                html! {
+                    <PrivaxyButton
+                        color={ButtonColor::Red}
+                        state={ButtonState::Enabled}
+                        onclick={ctx.link().callback(move |_| Message::Delete(name.clone()))}
+                        button_text={"Delete".to_string()}
+                    />
+                }
+            };
+
+            html! {
+                <div class="py-4 flex items-center justify-between">
+                    <div class="min-w-0 flex-1 text-sm">
+                        <p class="text-gray-900 truncate">{ &profile.name }</p>
+                        <p class="text-gray-500">
+                            { format!(
+                                "{} filters · {} exclusions · {} custom filters",
+                                profile.enabled_filters.len(),
+                                profile.exclusions.len(),
+                                profile.custom_filters.len()
+                            ) }
+                        </p>
+                    </div>
+                    { action }
+                </div>
+            }
+        };
+
+        let failure_banner_html = if self.show_error {
+            failure_banner!(
+                true,
+                ctx.link().callback(|_| Message::AcknowledgeError),
+                self.err_msg.clone()
+            )
+        } else {
+            html! {}
+        };
+
+        let add_button_state = if self.name.trim().is_empty() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+        let input_css = "shadow appearance-none border rounded w-80 py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline";
+
+        html! {
+            <>
+            <div class="pt-1.5 mb-4">
+                <h1 class="text-2xl font-bold text-gray-900">{ "Profiles" }</h1>
+            </div>
+            <p class="text-gray-600">
+                {"Profiles are named sets of enabled filters, exclusions and custom filters. "}
+                {"New profiles start from the current settings. Switch between them from the navigation bar, changes made while a profile is active are kept in it."}
+            </p>
+            { failure_banner_html }
+            <div class="mt-4 flex items-center space-x-2">
+                <input value={self.name.clone()} type="text" class={input_css} placeholder="Name, like work or kids"
+                    oninput={ctx.link().callback(|e: InputEvent| {
+                        let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                        Message::UpdateName(input.value())
+                    })} />
+                <PrivaxyButton
+                    color={ButtonColor::Blue}
+                    state={add_button_state}
+                    onclick={ctx.link().callback(|_| Message::Add)}
+                    button_text={"Add profile".to_string()}
+                />
+            </div>
+            {
+                match &self.profiles {
+                    None => html! { <div class="mt-4">{"Loading..."}</div> },
+                    Some(profiles) if profiles.profiles.is_empty() => html! {
+                        <p class="mt-4 text-gray-500 text-sm">{ "No profile was created." }</p>
+                    },
+                    Some(profiles) => html! {
+                        <div class="mt-4 border-t border-b border-gray-200 divide-y divide-gray-200">
+                            { for profiles.profiles.iter().map(|profile| {
+                                render_profile(profile, Some(&profile.name) == profiles.active_profile.as_ref())
+                            }) }
+                        </div>
+                    },
+                }
+            }
+            </>
+        }
+    }
+}
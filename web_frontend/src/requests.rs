@@ -1,5 +1,9 @@
+use crate::debug_headers::DebugHeaders;
+use crate::get_websocket_url;
+use crate::temporary_exclusions::PauseSite;
 use futures::future::{AbortHandle, Abortable};
-use futures::StreamExt;
+use futures::{SinkExt, StreamExt};
+use reqwasm::http::Request;
 use reqwasm::websocket::futures::WebSocket;
 use serde::Deserialize;
 use wasm_bindgen_futures::spawn_local;
@@ -7,12 +11,37 @@ use yew::{html, Component, Context, Html};
 
 const MAX_REQUESTS_SHOWN: usize = 500;
 
+/// A filter rule that matched a request.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct MatchedFilter {
+    pub rule: String,
+    pub list: Option<String>,
+}
+
+impl MatchedFilter {
+    pub fn describe(&self) -> String {
+        match &self.list {
+            Some(list) => format!("{} ({})", self.rule, list),
+            None => self.rule.clone(),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct Message {
+    #[serde(default)]
+    id: u64,
     now: String,
     method: String,
     url: String,
     is_request_blocked: bool,
+    #[serde(default)]
+    client: String,
+    #[serde(default)]
+    user: Option<String>,
+    /// Filter that blocked the request.
+    #[serde(default)]
+    filter: Option<MatchedFilter>,
 }
 
 pub struct Requests {
@@ -27,16 +56,41 @@ impl Component for Requests {
     fn create(ctx: &Context<Self>) -> Self {
         let message_callback = ctx.link().callback(|message: Message| message);
 
-        let ws = WebSocket::open("/api/events").unwrap();
-        let (_write, mut read) = ws.split();
+        let ws = WebSocket::open(&get_websocket_url("api/v1/events")).unwrap();
+        let (mut write, mut read) = ws.split();
 
         let (abort_handle, abort_registration) = AbortHandle::new_pair();
         let future = Abortable::new(
             async move {
+                // Status events are sent over the same socket, they are not requests
+                // and don't belong in this feed.
+                let _result = write
+                    .send(reqwasm::websocket::Message::Text(
+                        r#"{"types":["request"]}"#.to_string(),
+                    ))
+                    .await;
+
+                // Requests blocked before the page was opened, oldest first as each
+                // message is shown above the previous ones.
+                if let Ok(response) = Request::get("api/v1/events/history?type=request")
+                    .send()
+                    .await
+                {
+                    if let Ok(history) = response.json::<Vec<Message>>().await {
+                        for message in history.into_iter().rev() {
+                            message_callback.emit(message);
+                        }
+                    }
+                }
+
                 while let Some(Ok(msg)) = read.next().await {
                     let message = match msg {
                         reqwasm::websocket::Message::Text(s) => {
-                            serde_json::from_str::<Message>(&s).unwrap()
+                            // Acknowledgements of the subscription aren't requests either.
+                            match serde_json::from_str::<Message>(&s) {
+                                Ok(message) => message,
+                                Err(_err) => continue,
+                            }
                         }
                         reqwasm::websocket::Message::Bytes(_) => unreachable!(),
                     };
@@ -58,6 +112,12 @@ impl Component for Requests {
     }
 
     fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        // Requests made while the history was loading are both in it and sent over the
+        // socket.
+        if msg.id != 0 && self.messages.iter().any(|message| message.id == msg.id) {
+            return false;
+        }
+
         self.messages.insert(0, msg);
 
         self.messages.truncate(MAX_REQUESTS_SHOWN);
@@ -79,7 +139,7 @@ impl Component for Requests {
 
             html! {
 
-            <tr class={ background }>
+            <tr key={element.id} class={ background }>
                 <td class="w-1/12 px-6 py-4 whitespace-nowrap text-sm font-medium text-gray-900">
                     {&element.now}
                 </td>
@@ -91,6 +151,18 @@ impl Component for Requests {
                 </td>
                 <td class="px-6 py-4 whitespace-nowrap text-sm text-gray-500">
                     {&element.url}
+                    if let Some(filter) = &element.filter {
+                        <p class="mt-1 text-xs text-red-700">{ format!("Blocked by {}", filter.describe()) }</p>
+                    }
+                    <div class="mt-1">
+                        <PauseSite url={element.url.clone()} />
+                    </div>
+                </td>
+                <td class="w-1/12 px-6 py-4 whitespace-nowrap text-sm text-gray-500">
+                    {&element.client}
+                    if let Some(user) = &element.user {
+                        <p class="mt-1 text-xs text-gray-400">{ user }</p>
+                    }
                 </td>
             </tr>
                 }
@@ -98,10 +170,13 @@ impl Component for Requests {
 
         html! {
                <>
-          <h3 class="text-2xl font-bold text-gray-900 pt-1.5">
-            {"Requests feed"}
-            <div class="mt-2 ml-3 inline pulsating-circle"></div>
-          </h3>
+          <div class="md:flex md:justify-between md:items-center">
+            <h3 class="text-2xl font-bold text-gray-900 pt-1.5">
+              {"Requests feed"}
+              <div class="mt-2 ml-3 inline pulsating-circle"></div>
+            </h3>
+            <DebugHeaders />
+          </div>
           <div class="mt-4 flex flex-col">
             <div class="-my-2 overflow-x-auto sm:-mx-6 lg:-mx-8">
               <div class="py-2 align-middle inline-block min-w-full sm:px-6 lg:px-8">
@@ -121,6 +196,10 @@ impl Component for Requests {
                           class="px-6 py-3 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">
                           {"Path"}
                         </th>
+                        <th scope="col"
+                          class="px-6 py-3 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">
+                          {"Client"}
+                        </th>
                       </tr>
                     </thead>
                     <tbody class="w-full bg-white divide-y divide-gray-200">
@@ -0,0 +1,261 @@
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseCacheConfiguration {
+    enabled: bool,
+    max_size_bytes: u64,
+    max_entry_size_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ResponseCacheStatus {
+    entries: usize,
+    size_bytes: u64,
+    max_size_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PurgedResponses {
+    purged: usize,
+}
+
+pub enum Message {
+    Load,
+    Loaded(ResponseCacheConfiguration),
+    LoadStatus,
+    LoadedStatus(ResponseCacheStatus),
+    SetEnabled(bool),
+    Save(ResponseCacheConfiguration),
+    UpdateHost(String),
+    Purge,
+    Purged(usize),
+    Failed(ApiError),
+}
+
+fn format_mib(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Cache of upstream responses, how full it is, and purging it.
+pub struct ResponseCache {
+    configuration: Option<ResponseCacheConfiguration>,
+    status: Option<ResponseCacheStatus>,
+    host: String,
+    purge_message: Option<String>,
+    err_msg: Option<String>,
+}
+
+impl Component for ResponseCache {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            configuration: None,
+            status: None,
+            host: String::new(),
+            purge_message: None,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/response-cache");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<ResponseCacheConfiguration>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(configuration) => {
+                self.configuration = Some(configuration);
+                ctx.link().send_message(Message::LoadStatus);
+
+                true
+            }
+            Message::LoadStatus => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/response-cache/status");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::LoadedStatus(
+                            response.json::<ResponseCacheStatus>().await.unwrap(),
+                        )),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::LoadedStatus(status) => {
+                self.status = Some(status);
+
+                true
+            }
+            Message::SetEnabled(enabled) => {
+                if let Some(configuration) = &self.configuration {
+                    let mut configuration = configuration.clone();
+                    configuration.enabled = enabled;
+                    ctx.link().send_message(Message::Save(configuration));
+                }
+
+                false
+            }
+            Message::Save(configuration) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/response-cache")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&configuration).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::UpdateHost(host) => {
+                self.host = host;
+
+                false
+            }
+            Message::Purge => {
+                self.err_msg = None;
+                self.purge_message = None;
+
+                let host = self.host.trim().to_string();
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let url = if host.is_empty() {
+                        "api/v1/response-cache/entries".to_string()
+                    } else {
+                        format!(
+                            "api/v1/response-cache/entries?host={}",
+                            String::from(js_sys::encode_uri_component(&host))
+                        )
+                    };
+                    let request = Request::delete(&url);
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Purged(
+                            response.json::<PurgedResponses>().await.unwrap().purged,
+                        )),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Purged(purged) => {
+                self.purge_message = Some(format!("Purged {} cached responses.", purged));
+                ctx.link().send_message(Message::LoadStatus);
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let on_enabled_change = ctx.link().callback(|e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::SetEnabled(input.checked())
+        });
+        let on_host_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateHost(input.value())
+        });
+        let on_purge = ctx.link().callback(|_| Message::Purge);
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Cache" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "Responses of servers are kept on disk and served again for as long as they say they are fresh, then revalidated with the server, which saves fetching them again on slow links." }</p>
+                    <p>{ "Private responses and responses setting cookies are never cached, as the cache is shared by every client." }</p>
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                if let Some(configuration) = &self.configuration {
+                    <label class="mt-4 flex items-center">
+                        <input onchange={on_enabled_change} type="checkbox" checked={configuration.enabled}
+                            class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                        <span class="text-sm font-medium text-gray-900">{ "Cache responses" }</span>
+                    </label>
+                    <p class="mt-2 text-sm text-gray-600">
+                        { format!(
+                            "Responses larger than {} are not cached.",
+                            format_mib(configuration.max_entry_size_bytes)
+                        ) }
+                    </p>
+                }
+                if let Some(status) = &self.status {
+                    <p class="mt-2 text-sm text-gray-600">
+                        { format!(
+                            "{} responses cached, taking {} of {}.",
+                            status.entries,
+                            format_mib(status.size_bytes),
+                            format_mib(status.max_size_bytes)
+                        ) }
+                    </p>
+                }
+                <div class="mt-4 flex items-center">
+                    <input oninput={on_host_input} type="text" value={self.host.clone()} placeholder="Host, or empty for every response"
+                        class="shadow-sm focus:ring-blue-500 focus:border-blue-500 block w-72 sm:text-sm border-gray-300 rounded-md mr-2" />
+                    <button onclick={on_purge} class="text-sm text-red-600 hover:text-red-800">{ "Purge" }</button>
+                </div>
+                if let Some(purge_message) = &self.purge_message {
+                    <p class="mt-2 text-sm text-gray-600">{ purge_message }</p>
+                }
+            </>
+        }
+    }
+}
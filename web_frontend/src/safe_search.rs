@@ -0,0 +1,212 @@
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+const SEARCH_ENGINES: [(&str, &str); 4] = [
+    ("google", "Google"),
+    ("bing", "Bing"),
+    ("duckduckgo", "DuckDuckGo"),
+    ("youtube", "YouTube restricted mode"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafeSearchConfig {
+    enabled: bool,
+    overrides: BTreeMap<String, bool>,
+}
+
+pub enum Message {
+    Load,
+    Loaded(SafeSearchConfig),
+    SetEnabled(bool),
+    /// `None` follows the main toggle.
+    SetOverride(&'static str, Option<bool>),
+    Save(SafeSearchConfig),
+    Failed(ApiError),
+}
+
+/// SafeSearch enforcement, for every search engine or for some of them.
+pub struct SafeSearch {
+    safe_search: Option<SafeSearchConfig>,
+    err_msg: Option<String>,
+}
+
+impl Component for SafeSearch {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            safe_search: None,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/safe-search");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<SafeSearchConfig>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(safe_search) => {
+                self.safe_search = Some(safe_search);
+
+                true
+            }
+            Message::SetEnabled(enabled) => {
+                if let Some(safe_search) = &self.safe_search {
+                    let mut safe_search = safe_search.clone();
+                    safe_search.enabled = enabled;
+                    ctx.link().send_message(Message::Save(safe_search));
+                }
+
+                false
+            }
+            Message::SetOverride(search_engine, enforced) => {
+                if let Some(safe_search) = &self.safe_search {
+                    let mut safe_search = safe_search.clone();
+                    match enforced {
+                        Some(enforced) => {
+                            safe_search
+                                .overrides
+                                .insert(search_engine.to_string(), enforced);
+                        }
+                        None => {
+                            safe_search.overrides.remove(search_engine);
+                        }
+                    }
+                    ctx.link().send_message(Message::Save(safe_search));
+                }
+
+                false
+            }
+            Message::Save(safe_search) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/safe-search")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&safe_search).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let enabled = self
+            .safe_search
+            .as_ref()
+            .is_some_and(|safe_search| safe_search.enabled);
+
+        let on_enabled_change = ctx.link().callback(|e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::SetEnabled(input.checked())
+        });
+
+        let render_row = |(search_engine, label): &(&'static str, &'static str)| {
+            let search_engine = *search_engine;
+            let enforced = self
+                .safe_search
+                .as_ref()
+                .and_then(|safe_search| safe_search.overrides.get(search_engine).copied());
+
+            let onchange = ctx.link().callback(move |e: Event| {
+                let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+                let enforced = match select.value().as_str() {
+                    "enforced" => Some(true),
+                    "not_enforced" => Some(false),
+                    _ => None,
+                };
+                Message::SetOverride(search_engine, enforced)
+            });
+
+            html! {
+                <tr>
+                    <td class="px-2 py-1 text-sm font-medium text-gray-900 whitespace-nowrap">{ label }</td>
+                    <td class="px-2 py-1">
+                        <select {onchange} disabled={self.safe_search.is_none()} class="shadow border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline">
+                            <option value="default" selected={enforced.is_none()}>{ if enabled { "Enforced (default)" } else { "Not enforced (default)" } }</option>
+                            <option value="enforced" selected={enforced == Some(true)}>{ "Enforced" }</option>
+                            <option value="not_enforced" selected={enforced == Some(false)}>{ "Not enforced" }</option>
+                        </select>
+                    </td>
+                </tr>
+            }
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "SafeSearch" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "Have search engines filter explicit results out, whatever the settings of their users." }</p>
+                    <p>{ "Excluded hosts are tunneled and can't be rewritten." }</p>
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                <div class="mt-4">
+                    <label class="flex items-center text-sm text-gray-700 mt-2">
+                        <input onchange={on_enabled_change} type="checkbox" checked={enabled} disabled={self.safe_search.is_none()}
+                            class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                        { "Enforce SafeSearch on every search engine" }
+                    </label>
+                </div>
+                <div class="mt-4 overflow-x-auto">
+                    <table class="min-w-full">
+                        <thead>
+                            <tr>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Search engine" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "SafeSearch" }</th>
+                            </tr>
+                        </thead>
+                        <tbody class="divide-y divide-gray-200">
+                            { for SEARCH_ENGINES.iter().map(render_row) }
+                        </tbody>
+                    </table>
+                </div>
+            </>
+        }
+    }
+}
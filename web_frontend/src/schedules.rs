@@ -0,0 +1,284 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::profiles::ProfilesResponse;
+use crate::save_button::{SaveButton, SaveButtonState};
+use crate::success_banner;
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    rule: String,
+    profile: String,
+    enabled: bool,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<Schedule>, Vec<String>),
+    Add,
+    Delete(usize),
+    SetEnabled(usize, bool),
+    UpdateRule(usize, String),
+    UpdateProfile(usize, String),
+    Save,
+    Saved,
+    AckChanges,
+    Failed(ApiError),
+}
+
+/// Editor of the times at which profiles are switched to.
+pub struct Schedules {
+    schedules: Option<Vec<Schedule>>,
+    saved_schedules: Option<Vec<Schedule>>,
+    profiles: Vec<String>,
+    changes_saved: bool,
+    err_msg: Option<String>,
+}
+
+impl Schedules {
+    fn update_schedule(&mut self, index: usize, update: impl FnOnce(&mut Schedule)) -> bool {
+        match self
+            .schedules
+            .as_mut()
+            .and_then(|schedules| schedules.get_mut(index))
+        {
+            Some(schedule) => {
+                update(schedule);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Component for Schedules {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            schedules: None,
+            saved_schedules: None,
+            profiles: Vec::new(),
+            changes_saved: false,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let schedules = match Request::get("api/v1/schedules").send().await {
+                        Ok(response) if response.ok() => {
+                            response.json::<Vec<Schedule>>().await.unwrap()
+                        }
+                        Ok(response) => {
+                            return link.send_message(Message::Failed(
+                                response.json::<ApiError>().await.unwrap(),
+                            ))
+                        }
+                        Err(err) => {
+                            return link.send_message(Message::Failed(ApiError {
+                                error: format!("{:?}", err),
+                            }))
+                        }
+                    };
+
+                    let profiles = match Request::get("api/v1/profiles").send().await {
+                        Ok(response) if response.ok() => {
+                            response.json::<ProfilesResponse>().await.unwrap()
+                        }
+                        Ok(response) => {
+                            return link.send_message(Message::Failed(
+                                response.json::<ApiError>().await.unwrap(),
+                            ))
+                        }
+                        Err(err) => {
+                            return link.send_message(Message::Failed(ApiError {
+                                error: format!("{:?}", err),
+                            }))
+                        }
+                    };
+
+                    link.send_message(Message::Loaded(schedules, profiles.names()));
+                });
+
+                false
+            }
+            Message::Loaded(schedules, profiles) => {
+                self.saved_schedules = Some(schedules.clone());
+                self.schedules = Some(schedules);
+                self.profiles = profiles;
+
+                true
+            }
+            Message::Add => {
+                let profile = match self.profiles.first() {
+                    Some(profile) => profile.clone(),
+                    None => return false,
+                };
+
+                if let Some(schedules) = &mut self.schedules {
+                    schedules.push(Schedule {
+                        rule: "0 9 * * mon-fri".to_string(),
+                        profile,
+                        enabled: true,
+                    });
+                }
+
+                true
+            }
+            Message::Delete(index) => {
+                if let Some(schedules) = &mut self.schedules {
+                    if index < schedules.len() {
+                        schedules.remove(index);
+                    }
+                }
+
+                true
+            }
+            Message::SetEnabled(index, enabled) => {
+                self.update_schedule(index, |schedule| schedule.enabled = enabled)
+            }
+            Message::UpdateRule(index, rule) => {
+                self.update_schedule(index, |schedule| schedule.rule = rule)
+            }
+            Message::UpdateProfile(index, profile) => {
+                self.update_schedule(index, |schedule| schedule.profile = profile)
+            }
+            Message::Save => {
+                let schedules = match &self.schedules {
+                    Some(schedules) => schedules.clone(),
+                    None => return false,
+                };
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/schedules")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&schedules).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Saved),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Saved => {
+                self.changes_saved = true;
+                ctx.link().send_message(Message::Load);
+
+                true
+            }
+            Message::AckChanges => {
+                self.changes_saved = false;
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_schedule = |(index, schedule): (usize, &Schedule)| {
+            let on_delete = ctx.link().callback(move |_| Message::Delete(index));
+
+            let on_enabled_change = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::SetEnabled(index, input.checked())
+            });
+
+            let on_rule_input = ctx.link().callback(move |e: InputEvent| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::UpdateRule(index, input.value())
+            });
+
+            let on_profile_change = ctx.link().callback(move |e: Event| {
+                let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+                Message::UpdateProfile(index, select.value())
+            });
+
+            html! {
+                <div class="mt-4 flex items-center space-x-4">
+                    <input oninput={on_rule_input} value={schedule.rule.clone()} type="text"
+                        class="shadow appearance-none border rounded w-56 py-2 px-3 font-mono text-sm text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <select onchange={on_profile_change}
+                        class="shadow-sm focus:ring-blue-500 focus:border-blue-500 sm:text-sm border-gray-300 rounded-md">
+                        { for self.profiles.iter().map(|profile| html! {
+                            <option value={profile.clone()} selected={*profile == schedule.profile}>{ profile }</option>
+                        }) }
+                    </select>
+                    <label class="flex items-center text-sm text-gray-700">
+                        <input onchange={on_enabled_change} type="checkbox" checked={schedule.enabled}
+                            class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                        { "Enabled" }
+                    </label>
+                    <button onclick={on_delete} class="text-sm text-red-600 hover:text-red-800">{ "Remove" }</button>
+                </div>
+            }
+        };
+
+        let add_button_state = if self.profiles.is_empty() || self.schedules.is_none() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        let save_button_state = if self.schedules == self.saved_schedules {
+            SaveButtonState::Disabled
+        } else {
+            SaveButtonState::Enabled
+        };
+
+        let success_banner_html = if self.changes_saved {
+            success_banner!(true, ctx.link().callback(|_| Message::AckChanges))
+        } else {
+            html! {}
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Schedules" }</h1>
+                <div class="text-gray-600">
+                    <p>
+                        { "Switch to a profile at the times matched by a cron rule, in local time: minute, hour, day of the month, month and day of the week. " }
+                        { "For instance " }<span class="font-mono bg-gray-100">{ "0 9 * * mon-fri" }</span>{ " is every weekday at 9:00. " }
+                        { "A profile switched to by hand is kept until the next scheduled time." }
+                    </p>
+                </div>
+                { success_banner_html }
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                if self.schedules.is_some() && self.profiles.is_empty() {
+                    <p class="mt-4 text-gray-500 text-sm">{ "Create a profile to schedule it." }</p>
+                }
+                { for self.schedules.iter().flat_map(|schedules| schedules.iter().enumerate()).map(render_schedule) }
+                <div class="mt-4">
+                    <PrivaxyButton color={ButtonColor::Blue} state={add_button_state} onclick={ctx.link().callback(|_| Message::Add)} button_text="Add schedule" />
+                </div>
+                <SaveButton state={save_button_state} onclick={ctx.link().callback(|_| Message::Save)} />
+            </>
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::failure_banner;
+use crate::success_banner;
+use crate::{save_button, ApiError};
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct WebAuthentication {
+    enabled: bool,
+    password_set: bool,
+    session_ttl_secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct WebAuthenticationRequest {
+    enabled: bool,
+    password: Option<String>,
+    session_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct WebAuthenticationForm {
+    enabled: bool,
+    password: String,
+    raw_session_ttl_days: String,
+}
+
+impl WebAuthenticationForm {
+    fn session_ttl_secs(&self) -> Option<u64> {
+        match self.raw_session_ttl_days.trim().parse::<u64>() {
+            Ok(days) if days > 0 => days.checked_mul(SECONDS_PER_DAY),
+            _ => None,
+        }
+    }
+}
+
+impl From<&WebAuthentication> for WebAuthenticationForm {
+    fn from(web_authentication: &WebAuthentication) -> Self {
+        Self {
+            enabled: web_authentication.enabled,
+            password: String::new(),
+            raw_session_ttl_days: (web_authentication.session_ttl_secs / SECONDS_PER_DAY)
+                .max(1)
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct SignedInDevice {
+    id: String,
+    created_at: String,
+    expires_at: String,
+    last_seen_at: String,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+    current: bool,
+}
+
+pub enum Message {
+    Load,
+    LoadSuccess(WebAuthentication),
+    LoadDevices,
+    LoadDevicesSuccess(Vec<SignedInDevice>),
+    UpdateEnabled(bool),
+    UpdatePassword(String),
+    UpdateSessionTtl(String),
+    Save,
+    SaveSuccess,
+    SaveFailed(ApiError),
+    SignOut(String),
+    SignOutOthers,
+    AcknowledgeError,
+    AcknowledgeSuccess,
+}
+
+pub(crate) struct Sessions {
+    current_config: Option<WebAuthenticationForm>,
+    remote_config: Option<WebAuthentication>,
+    devices: Vec<SignedInDevice>,
+    show_error: bool,
+    show_success: bool,
+    err_msg: String,
+}
+
+impl Sessions {
+    fn has_changes(&self) -> bool {
+        match (&self.current_config, &self.remote_config) {
+            (Some(current_config), Some(remote_config)) => {
+                current_config != &WebAuthenticationForm::from(remote_config)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn send_delete(ctx: &Context<Sessions>, url: String) {
+    let link = ctx.link().clone();
+    spawn_local(async move {
+        match Request::delete(&url).send().await {
+            Ok(response) => {
+                if response.ok() {
+                    link.send_message(Message::LoadDevices);
+                } else {
+                    link.send_message(Message::SaveFailed(
+                        response.json::<ApiError>().await.unwrap(),
+                    ));
+                }
+            }
+            Err(err) => link.send_message(Message::SaveFailed(ApiError {
+                error: format!("{:?}", err),
+            })),
+        }
+    });
+}
+
+impl Component for Sessions {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+        ctx.link().send_message(Message::LoadDevices);
+
+        Self {
+            current_config: None,
+            remote_config: None,
+            devices: Vec::new(),
+            show_error: false,
+            show_success: false,
+            err_msg: String::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/settings/web-authentication");
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                if let Ok(config) = response.json::<WebAuthentication>().await {
+                                    link.send_message(Message::LoadSuccess(config));
+                                }
+                            } else {
+                                log::error!(
+                                    "Failed to load web authentication settings: {:?}",
+                                    response.status()
+                                );
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Request error: {:?}", err);
+                        }
+                    }
+                });
+            }
+            Message::LoadSuccess(config) => {
+                self.current_config = Some(WebAuthenticationForm::from(&config));
+                self.remote_config = Some(config);
+            }
+            Message::LoadDevices => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/sessions");
+                    match request.send().await {
+                        Ok(response) => {
+                            if let Ok(devices) = response.json::<Vec<SignedInDevice>>().await {
+                                link.send_message(Message::LoadDevicesSuccess(devices));
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Request error: {:?}", err);
+                        }
+                    }
+                });
+            }
+            Message::LoadDevicesSuccess(devices) => {
+                self.devices = devices;
+            }
+            Message::UpdateEnabled(enabled) => {
+                if let Some(config) = &mut self.current_config {
+                    config.enabled = enabled;
+                }
+            }
+            Message::UpdatePassword(password) => {
+                if let Some(config) = &mut self.current_config {
+                    config.password = password;
+                }
+            }
+            Message::UpdateSessionTtl(raw_session_ttl_days) => {
+                if let Some(config) = &mut self.current_config {
+                    config.raw_session_ttl_days = raw_session_ttl_days;
+                }
+            }
+            Message::Save => {
+                let config = match &self.current_config {
+                    Some(config) => config.clone(),
+                    None => return false,
+                };
+                let session_ttl_secs = match config.session_ttl_secs() {
+                    Some(session_ttl_secs) => session_ttl_secs,
+                    None => return false,
+                };
+
+                let request_body = WebAuthenticationRequest {
+                    enabled: config.enabled,
+                    password: if config.password.is_empty() {
+                        None
+                    } else {
+                        Some(config.password)
+                    },
+                    session_ttl_secs,
+                };
+                let link = ctx.link().clone();
+
+                spawn_local(async move {
+                    let request = Request::put("api/v1/settings/web-authentication")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&request_body).unwrap());
+
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                link.send_message(Message::Load);
+                                link.send_message(Message::LoadDevices);
+                                link.send_message(Message::SaveSuccess);
+                            } else {
+                                link.send_message(Message::SaveFailed(
+                                    response.json::<ApiError>().await.unwrap(),
+                                ));
+                            }
+                        }
+                        Err(err) => link.send_message(Message::SaveFailed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+            }
+            Message::SaveSuccess => {
+                self.show_success = true;
+                self.show_error = false;
+                self.err_msg = String::new();
+            }
+            Message::SaveFailed(err) => {
+                self.show_success = false;
+                self.show_error = true;
+                self.err_msg = err.error;
+            }
+            Message::SignOut(id) => send_delete(ctx, format!("api/v1/sessions/{}", id)),
+            Message::SignOutOthers => send_delete(ctx, "api/v1/sessions".to_string()),
+            Message::AcknowledgeSuccess => {
+                self.show_success = false;
+            }
+            Message::AcknowledgeError => {
+                self.show_error = false;
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_setting = |setting_name: &str, input: Html, description: &str| {
+            html! {
+                <div class="py-4" style="display: flex; flex-direction: column; width: 100%;">
+                    <div style="display: flex; align-items: center; width: 100%;">
+                        <div class="text-gray-500" style="width: 200px; text-align: left; padding-right: 4px;">{ setting_name }</div>
+                        <div style="flex-grow: 1;">{ input }</div>
+                    </div>
+                    <div style="margin-left: 200px;">
+                        <p class="text-gray-400 text-sm">{ description }</p>
+                    </div>
+                </div>
+            }
+        };
+
+        let render_device = |device: &SignedInDevice| {
+            let id = device.id.clone();
+            html! {
+                <div class="py-4 flex items-center justify-between">
+                    <div class="min-w-0 flex-1 text-sm">
+                        <p class="text-gray-900 truncate">{ device.user_agent.clone().unwrap_or_else(|| "Unknown device".to_string()) }</p>
+                        <p class="text-gray-500">
+                            { device.ip_address.clone().unwrap_or_default() }
+                            { format!(" · Last seen {} · Signed in {} · Expires {}", device.last_seen_at, device.created_at, device.expires_at) }
+                        </p>
+                    </div>
+                    if device.current {
+                        <span class="ml-3 text-sm text-gray-500">{ "This device" }</span>
+                    } else {
+                        <PrivaxyButton
+                            color={ButtonColor::Red}
+                            state={ButtonState::Enabled}
+                            onclick={ctx.link().callback(move |_| Message::SignOut(id.clone()))}
+                            button_text={"Sign out"}
+                        />
+                    }
+                </div>
+            }
+        };
+
+        let save_button_state = match &self.current_config {
+            Some(config) if self.has_changes() && config.session_ttl_secs().is_some() => {
+                ButtonState::Enabled
+            }
+            _ => ButtonState::Disabled,
+        };
+
+        let success_banner_html = if self.show_success {
+            success_banner!(true, ctx.link().callback(|_| Message::AcknowledgeSuccess))
+        } else {
+            html! {}
+        };
+        let failure_banner_html = if self.show_error {
+            failure_banner!(
+                true,
+                ctx.link().callback(|_| Message::AcknowledgeError),
+                self.err_msg.clone()
+            )
+        } else {
+            html! {}
+        };
+
+        let save_callback = ctx.link().callback(|_| Message::Save);
+        let input_css = "shadow appearance-none border rounded w-80 py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline";
+
+        html! {
+            <>
+            <div class="pt-1.5 mb-4">
+                <h1 class="text-2xl font-bold text-gray-900">{ "Sign-in" }</h1>
+            </div>
+            <p class="text-gray-600">
+                {"Require a password to use this web interface. Changing the password signs every other device out."}
+            </p>
+            { success_banner_html }
+            { failure_banner_html }
+            if let (Some(config), Some(remote_config)) = (&self.current_config, &self.remote_config) {
+                <div class="mt-4 border-t border-b border-gray-200 divide-y divide-gray-200">
+                    { render_setting(
+                        "Require signing in",
+                        html! {
+                            <input checked={config.enabled} type="checkbox"
+                                onclick={ctx.link().callback(|e: MouseEvent| {
+                                    let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                                    Message::UpdateEnabled(input.checked())
+                                })}
+                                class="focus:ring-blue-500 h-4 w-4 text-blue-600 border-gray-300 rounded" />
+                        },
+                        "Devices must sign in before using the dashboard, the settings and the API."
+                    ) }
+                    { render_setting(
+                        "Password",
+                        html! {
+                            <input value={config.password.clone()} type="password" class={input_css}
+                                placeholder={if remote_config.password_set { "Unchanged" } else { "" }}
+                                oninput={ctx.link().callback(|e: InputEvent| {
+                                    let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                                    Message::UpdatePassword(input.value())
+                                })} />
+                        },
+                        "Leave empty to keep the current password."
+                    ) }
+                    { render_setting(
+                        "Sign-in lifetime (days)",
+                        html! {
+                            <input value={config.raw_session_ttl_days.clone()} type="text" class={input_css}
+                                oninput={ctx.link().callback(|e: InputEvent| {
+                                    let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                                    Message::UpdateSessionTtl(input.value())
+                                })} />
+                        },
+                        "Devices have to sign in again after this many days."
+                    ) }
+                </div>
+            } else {
+                <div>{"Loading..."}</div>
+            }
+            {save_button!(save_callback, save_button_state)}
+            <div class="pt-1.5 mt-10 mb-4 flex items-center justify-between">
+                <h2 class="text-lg font-medium text-gray-900">{ "Signed-in devices" }</h2>
+                <PrivaxyButton
+                    color={ButtonColor::Red}
+                    state={if self.devices.iter().any(|device| !device.current) { ButtonState::Enabled } else { ButtonState::Disabled }}
+                    onclick={ctx.link().callback(|_| Message::SignOutOthers)}
+                    button_text={"Sign out other devices"}
+                />
+            </div>
+            if self.devices.is_empty() {
+                <p class="text-gray-500 text-sm">{ "No device is signed in." }</p>
+            } else {
+                <div class="border-t border-b border-gray-200 divide-y divide-gray-200">
+                    { for self.devices.iter().map(render_device) }
+                </div>
+            }
+            </>
+        }
+    }
+}
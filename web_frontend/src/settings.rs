@@ -1,7 +1,29 @@
+use crate::about::About;
+use crate::audit_log::AuditLog;
+use crate::block_responses::BlockResponses;
+use crate::categories::Categories;
+use crate::certificate::CertificateInstall;
+use crate::content_rewrite_rules::ContentRewriteRules;
+use crate::cookie_rules::CookieRules;
+use crate::event_log::EventLog;
+use crate::filter_test::FilterTest;
 use crate::filters::Filters;
 use crate::general::GeneralSettings;
+use crate::injections::Injections;
+use crate::managed::ManagedExclusions;
+use crate::notifications::Notifications;
+use crate::plugins::Plugins;
+use crate::profiles::Profiles;
+use crate::response_cache::ResponseCache;
+use crate::safe_search::SafeSearch;
+use crate::schedules::Schedules;
+use crate::sessions::Sessions;
 use crate::set_title;
 use crate::settings_textarea::SettingsTextarea;
+use crate::site_policies::SitePolicies;
+use crate::suggested_exclusions::SuggestedExclusions;
+use crate::temporary_exclusions::TemporaryExclusions;
+use crate::url_rewrites::UrlRewrites;
 use yew::prelude::*;
 use yew::{html, Html};
 use yew_router::prelude::*;
@@ -10,12 +32,48 @@ use yew_router::prelude::*;
 pub enum SettingsRoute {
     #[at("/settings/general")]
     General,
+    #[at("/settings/certificate")]
+    Certificate,
     #[at("/settings/filters")]
     Filters,
+    #[at("/settings/profiles")]
+    Profiles,
+    #[at("/settings/schedules")]
+    Schedules,
     #[at("/settings/exclusions")]
     Exclusions,
     #[at("/settings/custom-filters")]
     CustomFilters,
+    #[at("/settings/block-responses")]
+    BlockResponses,
+    #[at("/settings/site-policies")]
+    SitePolicies,
+    #[at("/settings/cookies")]
+    Cookies,
+    #[at("/settings/safe-search")]
+    SafeSearch,
+    #[at("/settings/categories")]
+    Categories,
+    #[at("/settings/redirects")]
+    Redirects,
+    #[at("/settings/plugins")]
+    Plugins,
+    #[at("/settings/cache")]
+    Cache,
+    #[at("/settings/content-rewriting")]
+    ContentRewriting,
+    #[at("/settings/injections")]
+    Injections,
+    #[at("/settings/notifications")]
+    Notifications,
+    #[at("/settings/sessions")]
+    Sessions,
+    #[at("/settings/audit-log")]
+    AuditLog,
+    #[at("/settings/events")]
+    Events,
+    #[at("/settings/about")]
+    About,
 }
 
 pub fn switch_settings(route: &SettingsRoute) -> Html {
@@ -54,15 +112,30 @@ pub fn switch_settings(route: &SettingsRoute) -> Html {
 
             html! { <GeneralSettings /> }
         }
+        SettingsRoute::Certificate => {
+            set_title("Settings - Certificate");
+
+            html! { <CertificateInstall /> }
+        }
         SettingsRoute::Filters => {
             set_title("Settings - Filters");
 
             html! { <Filters />}
         }
+        SettingsRoute::Profiles => {
+            set_title("Settings - Profiles");
+
+            html! { <Profiles /> }
+        }
+        SettingsRoute::Schedules => {
+            set_title("Settings - Schedules");
+
+            html! { <Schedules /> }
+        }
         SettingsRoute::Exclusions => {
             set_title("Settings - Exclusions");
 
-            let resource_url = "/api/exclusions";
+            let resource_url = "api/v1/exclusions";
 
             let description = html! {<div class="text-gray-600">
                     <p>
@@ -73,12 +146,19 @@ pub fn switch_settings(route: &SettingsRoute) -> Html {
             };
             let textarea_description = "Insert one entry per line";
 
-            html! {<SettingsTextarea h1="Exclusions" {description} input_name="exclusions" {textarea_description} {resource_url} />}
+            html! {
+                <>
+                    <SettingsTextarea h1="Exclusions" {description} input_name="exclusions" {textarea_description} {resource_url} />
+                    <SuggestedExclusions />
+                    <ManagedExclusions />
+                    <TemporaryExclusions />
+                </>
+            }
         }
         SettingsRoute::CustomFilters => {
             set_title("Settings - Custom Filters");
 
-            let resource_url = "/api/custom-filters";
+            let resource_url = "api/v1/custom-filters";
 
             let description = html! {
                 <p class="text-gray-600">
@@ -88,16 +168,114 @@ pub fn switch_settings(route: &SettingsRoute) -> Html {
 
             let textarea_description = "Insert one filter per line";
 
-            html! {<SettingsTextarea h1="Custom Filters" {description} input_name="custom_filters" {textarea_description} {resource_url} />}
+            html! {
+                <>
+                    <SettingsTextarea h1="Custom Filters" {description} input_name="custom_filters" {textarea_description} {resource_url} />
+                    <FilterTest />
+                </>
+            }
+        }
+        SettingsRoute::BlockResponses => {
+            set_title("Settings - Block Responses");
+
+            html! { <BlockResponses /> }
+        }
+        SettingsRoute::SitePolicies => {
+            set_title("Settings - Site Policies");
+
+            html! { <SitePolicies /> }
+        }
+        SettingsRoute::Cookies => {
+            set_title("Settings - Cookies");
+
+            html! { <CookieRules /> }
+        }
+        SettingsRoute::SafeSearch => {
+            set_title("Settings - SafeSearch");
+
+            html! { <SafeSearch /> }
+        }
+        SettingsRoute::Categories => {
+            set_title("Settings - Categories");
+
+            html! { <Categories /> }
+        }
+        SettingsRoute::Redirects => {
+            set_title("Settings - Redirects");
+
+            html! { <UrlRewrites /> }
+        }
+        SettingsRoute::Plugins => {
+            set_title("Settings - Plugins");
+
+            html! { <Plugins /> }
+        }
+        SettingsRoute::Cache => {
+            set_title("Settings - Cache");
+
+            html! { <ResponseCache /> }
+        }
+        SettingsRoute::ContentRewriting => {
+            set_title("Settings - Content Rewriting");
+
+            html! { <ContentRewriteRules /> }
+        }
+        SettingsRoute::Injections => {
+            set_title("Settings - Injections");
+
+            html! { <Injections /> }
+        }
+        SettingsRoute::Notifications => {
+            set_title("Settings - Notifications");
+
+            html! { <Notifications /> }
+        }
+        SettingsRoute::Sessions => {
+            set_title("Settings - Sign-in");
+
+            html! { <Sessions /> }
+        }
+        SettingsRoute::AuditLog => {
+            set_title("Settings - Audit Log");
+
+            html! { <AuditLog /> }
+        }
+        SettingsRoute::Events => {
+            set_title("Settings - Events");
+
+            html! { <EventLog /> }
+        }
+        SettingsRoute::About => {
+            set_title("Settings - About");
+
+            html! { <About /> }
         }
     };
 
     html! {<div class="md:grid md:grid-cols-8">
     <nav class="space-y-1 mt-4 lg:col-span-1 sm:col-span-2" aria-label="Sidebar">
         <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::General)} to={SettingsRoute::General}> <span class="truncate">{ "General" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Certificate)} to={SettingsRoute::Certificate}> <span class="truncate">{ "Certificate" }</span></Link<SettingsRoute>>
         <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Filters)} to={SettingsRoute::Filters}> <span class="truncate">{ "Filters" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Profiles)} to={SettingsRoute::Profiles}> <span class="truncate">{ "Profiles" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Schedules)} to={SettingsRoute::Schedules}> <span class="truncate">{ "Schedules" }</span></Link<SettingsRoute>>
         <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Exclusions)} to={SettingsRoute::Exclusions}> <span class="truncate">{ "Exclusions" }</span></Link<SettingsRoute>>
         <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::CustomFilters)} to={SettingsRoute::CustomFilters}> <span class="truncate">{ "Custom filters" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::BlockResponses)} to={SettingsRoute::BlockResponses}> <span class="truncate">{ "Block responses" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::SitePolicies)} to={SettingsRoute::SitePolicies}> <span class="truncate">{ "Site policies" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Cookies)} to={SettingsRoute::Cookies}> <span class="truncate">{ "Cookies" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::SafeSearch)} to={SettingsRoute::SafeSearch}> <span class="truncate">{ "SafeSearch" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Categories)} to={SettingsRoute::Categories}> <span class="truncate">{ "Categories" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Redirects)} to={SettingsRoute::Redirects}> <span class="truncate">{ "Redirects" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Plugins)} to={SettingsRoute::Plugins}> <span class="truncate">{ "Plugins" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Cache)} to={SettingsRoute::Cache}> <span class="truncate">{ "Cache" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::ContentRewriting)} to={SettingsRoute::ContentRewriting}> <span class="truncate">{ "Content rewriting" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Injections)} to={SettingsRoute::Injections}> <span class="truncate">{ "Injections" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Notifications)} to={SettingsRoute::Notifications}> <span class="truncate">{ "Notifications" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Sessions)} to={SettingsRoute::Sessions}> <span class="truncate">{ "Sign-in" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::AuditLog)} to={SettingsRoute::AuditLog}> <span class="truncate">{ "Audit log" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::Events)} to={SettingsRoute::Events}> <span class="truncate">{ "Events" }</span></Link<SettingsRoute>>
+        <Link<SettingsRoute> classes={get_classes(*route, SettingsRoute::About)} to={SettingsRoute::About}> <span class="truncate">{ "About" }</span></Link<SettingsRoute>>
     </nav>
         <div class="container mx-auto px-4 sm:px-6 lg:px-8 mt-4 sm:col-span-6">{ content }</div>
     </div>
@@ -0,0 +1,162 @@
+use crate::button::{get_css, ButtonColor};
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Children, Component, Context, Html, Properties};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionStatus {
+    authentication_required: bool,
+    authorized: bool,
+}
+
+#[derive(Serialize)]
+struct SignInRequest {
+    password: String,
+}
+
+pub enum Message {
+    Load,
+    LoadSuccess(SessionStatus),
+    UpdatePassword(String),
+    SignIn,
+    SignInFailed(ApiError),
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub children: Children,
+}
+
+/// Renders its children once the web GUI may be used, asking for the password
+/// first when web GUI authentication is enabled.
+pub struct SignInGate {
+    status: Option<SessionStatus>,
+    password: String,
+    signing_in: bool,
+    err_msg: Option<String>,
+}
+
+impl Component for SignInGate {
+    type Message = Message;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            status: None,
+            password: String::new(),
+            signing_in: false,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/session");
+                    match request.send().await {
+                        Ok(response) => {
+                            if let Ok(status) = response.json::<SessionStatus>().await {
+                                link.send_message(Message::LoadSuccess(status));
+                            }
+                        }
+                        Err(err) => {
+                            log::error!("Request error: {:?}", err);
+                        }
+                    }
+                });
+            }
+            Message::LoadSuccess(status) => {
+                self.status = Some(status);
+                self.signing_in = false;
+                self.password = String::new();
+            }
+            Message::UpdatePassword(password) => {
+                self.password = password;
+
+                return false;
+            }
+            Message::SignIn => {
+                self.signing_in = true;
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                let password = self.password.clone();
+                spawn_local(async move {
+                    let request = Request::post("api/v1/session")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&SignInRequest { password }).unwrap());
+
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                link.send_message(Message::Load);
+                            } else {
+                                link.send_message(Message::SignInFailed(
+                                    response.json::<ApiError>().await.unwrap(),
+                                ));
+                            }
+                        }
+                        Err(err) => link.send_message(Message::SignInFailed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+            }
+            Message::SignInFailed(err) => {
+                self.signing_in = false;
+                self.err_msg = Some(err.error);
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let status = match &self.status {
+            Some(status) => status,
+            // This loads fast enough that a loader would only add flickering.
+            None => return html! {},
+        };
+
+        if !status.authentication_required || status.authorized {
+            return html! { <>{ for ctx.props().children.iter() }</> };
+        }
+
+        let onsubmit = ctx.link().callback(|e: FocusEvent| {
+            e.prevent_default();
+            Message::SignIn
+        });
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdatePassword(input.value())
+        });
+
+        let mut button_css = get_css(ButtonColor::Blue);
+        if self.signing_in {
+            button_css.push("opacity-50");
+            button_css.push("cursor-not-allowed");
+        }
+
+        html! {
+            <div class="min-h-full flex items-center justify-center py-16 px-4">
+                <div class="max-w-sm w-full">
+                    <h1 class="text-2xl font-bold text-gray-900 mb-6">{ "Sign in to Privaxy" }</h1>
+                    <form {onsubmit}>
+                        <input {oninput} type="password" placeholder="Password" autofocus=true
+                            class="shadow appearance-none border rounded w-full py-2 px-3 mb-4 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                        if let Some(err_msg) = &self.err_msg {
+                            <p class="text-red-500 text-xs italic mb-4">{ err_msg }</p>
+                        }
+                        <button type="submit" class={button_css} disabled={self.signing_in}>{ "Sign in" }</button>
+                    </form>
+                </div>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use crate::block_responses::RESOURCE_TYPES;
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+/// Actions in the order cells cycle through them.
+const ACTIONS: [&str; 3] = ["default", "block", "allow"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SitePolicy {
+    site: String,
+    /// Action per resource type, and for all of them under `all`.
+    #[serde(flatten)]
+    actions: BTreeMap<String, String>,
+}
+
+impl SitePolicy {
+    fn get(&self, column: &str) -> &str {
+        self.actions
+            .get(column)
+            .map(|action| action.as_str())
+            .unwrap_or("default")
+    }
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<SitePolicy>),
+    UpdateNewSite(String),
+    Add,
+    /// Moves the action of a site and column to the next one.
+    Cycle(String, &'static str),
+    Delete(String),
+    Save(SitePolicy),
+    Failed(ApiError),
+}
+
+/// Per site actions overriding the filters, laid out as a site by resource type matrix.
+pub struct SitePolicies {
+    site_policies: Option<Vec<SitePolicy>>,
+    new_site: String,
+    err_msg: Option<String>,
+}
+
+impl Component for SitePolicies {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            site_policies: None,
+            new_site: String::new(),
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/site-policies");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<Vec<SitePolicy>>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(site_policies) => {
+                self.site_policies = Some(site_policies);
+
+                true
+            }
+            Message::UpdateNewSite(new_site) => {
+                self.new_site = new_site;
+
+                true
+            }
+            Message::Add => {
+                let site = self.new_site.trim().to_lowercase();
+                if site.is_empty() {
+                    return false;
+                }
+
+                self.new_site = String::new();
+                ctx.link().send_message(Message::Save(SitePolicy {
+                    site,
+                    actions: BTreeMap::new(),
+                }));
+
+                true
+            }
+            Message::Cycle(site, column) => {
+                let site_policy = self.site_policies.as_ref().and_then(|site_policies| {
+                    site_policies.iter().find(|policy| policy.site == site)
+                });
+
+                if let Some(site_policy) = site_policy {
+                    let mut site_policy = site_policy.clone();
+                    let current = ACTIONS
+                        .iter()
+                        .position(|action| *action == site_policy.get(column))
+                        .unwrap_or(0);
+                    site_policy.actions.insert(
+                        column.to_string(),
+                        ACTIONS[(current + 1) % ACTIONS.len()].to_string(),
+                    );
+
+                    ctx.link().send_message(Message::Save(site_policy));
+                }
+
+                false
+            }
+            Message::Delete(site) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::delete(&format!("api/v1/site-policies/{}", site));
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(_response) => link.send_message(Message::Failed(ApiError {
+                            error: format!("Unable to delete the policy of {}", site),
+                        })),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Save(site_policy) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request =
+                        Request::put(&format!("api/v1/site-policies/{}", site_policy.site))
+                            .header("Content-Type", "application/json")
+                            .body(serde_json::to_string(&site_policy).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let columns = std::iter::once(("all", "All"))
+            .chain(RESOURCE_TYPES.iter().copied())
+            .collect::<Vec<_>>();
+
+        let render_cell = |site_policy: &SitePolicy, column: &'static str| {
+            let (label, css) = match site_policy.get(column) {
+                "block" => ("Block", "bg-red-100 text-red-800 hover:bg-red-200"),
+                "allow" => ("Allow", "bg-green-100 text-green-800 hover:bg-green-200"),
+                _ => ("-", "bg-gray-50 text-gray-400 hover:bg-gray-100"),
+            };
+            let site = site_policy.site.clone();
+            let onclick = ctx
+                .link()
+                .callback(move |_| Message::Cycle(site.clone(), column));
+
+            html! {
+                <td class="px-1 py-1">
+                    <button {onclick} class={classes!("w-full", "rounded", "px-2", "py-1", "text-xs", "font-medium", css)}>
+                        { label }
+                    </button>
+                </td>
+            }
+        };
+
+        let render_row = |site_policy: &SitePolicy| {
+            let site = site_policy.site.clone();
+            let on_delete = ctx.link().callback(move |_| Message::Delete(site.clone()));
+
+            html! {
+                <tr>
+                    <td class="px-2 py-1 text-sm font-medium text-gray-900 whitespace-nowrap">{ &site_policy.site }</td>
+                    { for columns.iter().map(|(column, _label)| render_cell(site_policy, column)) }
+                    <td class="px-2 py-1 text-right">
+                        <button onclick={on_delete} class="text-sm text-red-600 hover:text-red-800">{ "Remove" }</button>
+                    </td>
+                </tr>
+            }
+        };
+
+        let on_new_site_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewSite(input.value())
+        });
+
+        let add_button_state = if self.new_site.trim().is_empty() {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Site policies" }</h1>
+                <div class="text-gray-600">
+                    <p>
+                        { "Block or allow the requests made by the pages of a site, per kind of resource, whatever the filters say. " }
+                        { "Policies of a site apply to its subdomains, and " }<span class="font-mono bg-gray-100">{ "*" }</span>{ " applies to every site." }
+                    </p>
+                    <p>{ "The most specific policy wins: a site before its parent domains, a kind of resource before \"All\". Click a cell to change it." }</p>
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                <div class="mt-4 overflow-x-auto">
+                    <table class="min-w-full">
+                        <thead>
+                            <tr>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Site" }</th>
+                                { for columns.iter().map(|(_column, label)| html! {
+                                    <th scope="col" class="px-1 py-2 text-center text-xs font-medium text-gray-500 uppercase tracking-wider">{ *label }</th>
+                                }) }
+                                <th scope="col"></th>
+                            </tr>
+                        </thead>
+                        <tbody class="divide-y divide-gray-200">
+                            { for self.site_policies.iter().flatten().map(render_row) }
+                        </tbody>
+                    </table>
+                </div>
+                <div class="mt-4 flex space-x-3">
+                    <input oninput={on_new_site_input} value={self.new_site.clone()} type="text" placeholder="example.com"
+                        class="shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <PrivaxyButton color={ButtonColor::Blue} state={add_button_state} onclick={ctx.link().callback(|_| Message::Add)} button_text="Add site" />
+                </div>
+            </>
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use crate::ApiError;
+use num_format::{Locale, ToFormattedString};
+use reqwasm::http::Request;
+use serde::Deserialize;
+use std::collections::HashMap;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+const HOUR_MS: f64 = 60.0 * 60.0 * 1000.0;
+const DAY_MS: f64 = 24.0 * HOUR_MS;
+const WEEK_MS: f64 = 7.0 * DAY_MS;
+/// Number of domains listed in the top domain changes.
+const DOMAIN_CHANGES_SHOWN: usize = 15;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryBucket {
+    start: String,
+    proxied_requests: u64,
+    blocked_requests: u64,
+    blocked_domains: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonMode {
+    Day,
+    Week,
+    /// A week before a point in time, such as enabling a filter list, against the week after.
+    BeforeAfter,
+}
+
+impl ComparisonMode {
+    const ALL: [(ComparisonMode, &'static str, &'static str); 3] = [
+        (
+            ComparisonMode::Day,
+            "day",
+            "Last 24 hours vs the previous 24 hours",
+        ),
+        (ComparisonMode::Week, "week", "This week vs last week"),
+        (
+            ComparisonMode::BeforeAfter,
+            "before_after",
+            "Before vs after a date",
+        ),
+    ];
+}
+
+/// Statistics of a period, summed from its hourly buckets.
+#[derive(Debug, Default)]
+struct PeriodSummary {
+    proxied_requests: u64,
+    blocked_requests: u64,
+    blocked_domains: HashMap<String, u64>,
+}
+
+impl PeriodSummary {
+    fn from_buckets<'a>(buckets: impl Iterator<Item = &'a HistoryBucket>) -> Self {
+        let mut summary = Self::default();
+
+        for bucket in buckets {
+            summary.proxied_requests += bucket.proxied_requests;
+            summary.blocked_requests += bucket.blocked_requests;
+
+            for (domain, count) in &bucket.blocked_domains {
+                *summary.blocked_domains.entry(domain.clone()).or_insert(0) += count;
+            }
+        }
+
+        summary
+    }
+
+    fn total_requests(&self) -> u64 {
+        self.proxied_requests + self.blocked_requests
+    }
+
+    /// Share of requests that were blocked, between 0 and 1.
+    fn blocked_share(&self) -> Option<f64> {
+        if self.total_requests() == 0 {
+            None
+        } else {
+            Some(self.blocked_requests as f64 / self.total_requests() as f64)
+        }
+    }
+}
+
+struct Comparison {
+    previous: PeriodSummary,
+    current: PeriodSummary,
+}
+
+pub enum Message {
+    SetMode(ComparisonMode),
+    SetPivot(String),
+    Load,
+    Loaded(Vec<HistoryBucket>),
+    Failed(String),
+}
+
+/// Compares the statistics of two periods, to see what changing filters did.
+pub struct StatisticsComparison {
+    mode: ComparisonMode,
+    /// Start of the "after" period, as entered in a `datetime-local` input.
+    pivot: String,
+    loading: bool,
+    comparison: Option<Comparison>,
+    err_msg: Option<String>,
+}
+
+impl StatisticsComparison {
+    /// Returns the previous and current periods, as `(from, to)` milliseconds since the epoch.
+    fn periods(&self) -> Option<((f64, f64), (f64, f64))> {
+        let now = js_sys::Date::now();
+
+        let (pivot, length) = match self.mode {
+            ComparisonMode::Day => (now - DAY_MS, DAY_MS),
+            ComparisonMode::Week => (now - WEEK_MS, WEEK_MS),
+            ComparisonMode::BeforeAfter => {
+                // Parsed in local time, like it was entered.
+                let pivot = js_sys::Date::parse(&self.pivot);
+                if pivot.is_nan() || pivot > now {
+                    return None;
+                }
+                (pivot, WEEK_MS)
+            }
+        };
+
+        Some(((pivot - length, pivot), (pivot, (pivot + length).min(now))))
+    }
+}
+
+impl Component for StatisticsComparison {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            mode: ComparisonMode::Week,
+            pivot: String::new(),
+            loading: false,
+            comparison: None,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::SetMode(mode) => {
+                self.mode = mode;
+                self.comparison = None;
+                ctx.link().send_message(Message::Load);
+            }
+            Message::SetPivot(pivot) => {
+                self.pivot = pivot;
+                self.comparison = None;
+                ctx.link().send_message(Message::Load);
+            }
+            Message::Load => {
+                let ((from, _), (_, to)) = match self.periods() {
+                    Some(periods) => periods,
+                    None => return true,
+                };
+
+                // Buckets are hourly, the one `from` falls in is included.
+                let from = ((from - HOUR_MS) / 1000.0).floor() as i64;
+                let to = (to / 1000.0).ceil() as i64;
+
+                self.loading = true;
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get(&format!(
+                        "api/v1/statistics/history?from={}&to={}",
+                        from, to
+                    ));
+
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                match response.json::<Vec<HistoryBucket>>().await {
+                                    Ok(history) => link.send_message(Message::Loaded(history)),
+                                    Err(err) => {
+                                        link.send_message(Message::Failed(format!("{:?}", err)))
+                                    }
+                                }
+                            } else {
+                                let err = response.json::<ApiError>().await.unwrap();
+                                link.send_message(Message::Failed(err.error));
+                            }
+                        }
+                        Err(err) => link.send_message(Message::Failed(format!("{:?}", err))),
+                    }
+                });
+            }
+            Message::Loaded(history) => {
+                self.loading = false;
+
+                let ((previous_from, previous_to), (current_from, current_to)) =
+                    match self.periods() {
+                        Some(periods) => periods,
+                        None => return true,
+                    };
+
+                // The bucket of the current hour is still being filled.
+                let current_to = if js_sys::Date::now() - current_to < HOUR_MS {
+                    current_to + HOUR_MS
+                } else {
+                    current_to
+                };
+
+                // A bucket belongs to the period its middle falls in.
+                let in_period = |from: f64, to: f64| {
+                    move |bucket: &&HistoryBucket| {
+                        let middle = js_sys::Date::parse(&bucket.start) + HOUR_MS / 2.0;
+                        middle >= from && middle < to
+                    }
+                };
+
+                self.comparison = Some(Comparison {
+                    previous: PeriodSummary::from_buckets(
+                        history.iter().filter(in_period(previous_from, previous_to)),
+                    ),
+                    current: PeriodSummary::from_buckets(
+                        history.iter().filter(in_period(current_from, current_to)),
+                    ),
+                });
+            }
+            Message::Failed(err) => {
+                self.loading = false;
+                self.err_msg = Some(err);
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let on_mode_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            let mode = ComparisonMode::ALL
+                .iter()
+                .find(|(_mode, value, _label)| *value == select.value())
+                .map(|(mode, _value, _label)| *mode)
+                .unwrap_or(ComparisonMode::Week);
+            Message::SetMode(mode)
+        });
+        let on_pivot_change = ctx.link().callback(|e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::SetPivot(input.value())
+        });
+
+        let content = match &self.comparison {
+            _ if self.mode == ComparisonMode::BeforeAfter && self.periods().is_none() => html! {
+                <p class="text-sm text-gray-500">{ "Pick a date in the past, such as when a filter list was enabled." }</p>
+            },
+            Some(comparison) => render_comparison(comparison),
+            None if self.loading => html! {
+                <p class="text-sm text-gray-500">{ "Loading..." }</p>
+            },
+            None => html! {},
+        };
+
+        html! {
+            <div class="mt-8 bg-white overflow-hidden shadow rounded-lg divide-y divide-gray-200">
+                <div class="px-4 py-5 sm:px-6 md:flex md:justify-between md:items-center">
+                    <h3 class="text-lg font-medium">{ "Comparison" }</h3>
+                    <div class="mt-2 md:mt-0 flex space-x-3">
+                        if self.mode == ComparisonMode::BeforeAfter {
+                            <input onchange={on_pivot_change} value={self.pivot.clone()} type="datetime-local"
+                                class="shadow border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                        }
+                        <select onchange={on_mode_change}
+                            class="shadow border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline">
+                            { for ComparisonMode::ALL.iter().map(|(mode, value, label)| html! {
+                                <option value={*value} selected={*mode == self.mode}>{ *label }</option>
+                            }) }
+                        </select>
+                    </div>
+                </div>
+                <div class="px-4 py-5 sm:p-6">
+                    if let Some(err_msg) = &self.err_msg {
+                        <p class="text-red-500 text-sm">{ err_msg }</p>
+                    }
+                    { content }
+                </div>
+            </div>
+        }
+    }
+}
+
+fn format_share(share: Option<f64>) -> String {
+    match share {
+        Some(share) => format!("{:.1}%", share * 100.0),
+        None => "-".to_string(),
+    }
+}
+
+fn render_delta(delta: i64) -> Html {
+    let css = if delta > 0 {
+        "text-red-700"
+    } else if delta < 0 {
+        "text-green-700"
+    } else {
+        "text-gray-500"
+    };
+
+    html! { <span class={css}>{ format!("{:+}", delta) }</span> }
+}
+
+fn render_comparison(comparison: &Comparison) -> Html {
+    let previous = &comparison.previous;
+    let current = &comparison.current;
+
+    let share_delta = match (previous.blocked_share(), current.blocked_share()) {
+        (Some(previous), Some(current)) => {
+            format!("{:+.1} points", (current - previous) * 100.0)
+        }
+        _ => "-".to_string(),
+    };
+
+    let mut domain_changes = previous
+        .blocked_domains
+        .keys()
+        .chain(current.blocked_domains.keys())
+        .collect::<std::collections::HashSet<_>>()
+        .into_iter()
+        .map(|domain| {
+            let previous_count = previous.blocked_domains.get(domain).copied().unwrap_or(0);
+            let current_count = current.blocked_domains.get(domain).copied().unwrap_or(0);
+            (domain, previous_count, current_count)
+        })
+        .collect::<Vec<_>>();
+
+    domain_changes.sort_by(|a, b| {
+        let delta = |(_domain, previous, current): &(&String, u64, u64)| {
+            (*current as i64 - *previous as i64).abs()
+        };
+        delta(b).cmp(&delta(a)).then_with(|| a.0.cmp(b.0))
+    });
+    domain_changes.truncate(DOMAIN_CHANGES_SHOWN);
+
+    let render_stat = |label: &str, previous: String, current: String, delta: Html| {
+        html! {
+            <div class="px-4 py-5 sm:p-6">
+                <dt class="text-base font-normal text-gray-900">{ label }</dt>
+                <dd class="mt-1 flex justify-between items-baseline">
+                    <div class="text-2xl font-semibold text-blue-600">
+                        { current }
+                        <span class="ml-2 text-sm font-medium text-gray-500">{ format!("from {}", previous) }</span>
+                    </div>
+                    <div class="text-sm font-medium">{ delta }</div>
+                </dd>
+            </div>
+        }
+    };
+
+    html! {
+        <>
+            <dl class="grid grid-cols-1 rounded-lg bg-gray-50 overflow-hidden divide-y divide-gray-200 md:grid-cols-3 md:divide-y-0 md:divide-x">
+                { render_stat(
+                    "Blocked share",
+                    format_share(previous.blocked_share()),
+                    format_share(current.blocked_share()),
+                    html! { <span class="text-gray-700">{ share_delta }</span> },
+                ) }
+                { render_stat(
+                    "Requests",
+                    previous.total_requests().to_formatted_string(&Locale::en),
+                    current.total_requests().to_formatted_string(&Locale::en),
+                    render_delta(current.total_requests() as i64 - previous.total_requests() as i64),
+                ) }
+                { render_stat(
+                    "Blocked requests",
+                    previous.blocked_requests.to_formatted_string(&Locale::en),
+                    current.blocked_requests.to_formatted_string(&Locale::en),
+                    render_delta(current.blocked_requests as i64 - previous.blocked_requests as i64),
+                ) }
+            </dl>
+            if !domain_changes.is_empty() {
+                <table class="mt-6 min-w-full divide-y divide-gray-200">
+                    <thead>
+                        <tr>
+                            <th scope="col" class="py-3 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Blocked domain" }</th>
+                            <th scope="col" class="py-3 text-right text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Before" }</th>
+                            <th scope="col" class="py-3 text-right text-xs font-medium text-gray-500 uppercase tracking-wider">{ "After" }</th>
+                            <th scope="col" class="py-3 text-right text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Change" }</th>
+                        </tr>
+                    </thead>
+                    <tbody class="divide-y divide-gray-200">
+                        { for domain_changes.iter().map(|(domain, previous_count, current_count)| html! {
+                            <tr>
+                                <td class="py-2 text-sm text-gray-900 truncate">{ domain }</td>
+                                <td class="py-2 text-sm text-gray-500 text-right">{ previous_count.to_formatted_string(&Locale::en) }</td>
+                                <td class="py-2 text-sm text-gray-500 text-right">{ current_count.to_formatted_string(&Locale::en) }</td>
+                                <td class="py-2 text-sm text-right">{ render_delta(*current_count as i64 - *previous_count as i64) }</td>
+                            </tr>
+                        }) }
+                    </tbody>
+                </table>
+            }
+        </>
+    }
+}
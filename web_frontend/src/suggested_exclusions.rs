@@ -0,0 +1,131 @@
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SuggestedExclusion {
+    host: String,
+    clients: Vec<String>,
+    failures: u32,
+    last_error: String,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<SuggestedExclusion>),
+    Apply(String),
+    Dismiss(String),
+}
+
+/// Lists hosts clients repeatedly failed TLS handshakes with, which likely break while
+/// being intercepted.
+pub struct SuggestedExclusions {
+    suggested_exclusions: Vec<SuggestedExclusion>,
+}
+
+impl Component for SuggestedExclusions {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            suggested_exclusions: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/suggested-exclusions");
+
+                    if let Ok(response) = request.send().await {
+                        if response.ok() {
+                            if let Ok(suggested_exclusions) =
+                                response.json::<Vec<SuggestedExclusion>>().await
+                            {
+                                link.send_message(Message::Loaded(suggested_exclusions));
+                            }
+                        }
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(suggested_exclusions) => {
+                self.suggested_exclusions = suggested_exclusions;
+
+                true
+            }
+            Message::Apply(host) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::post(&format!("api/v1/suggested-exclusions/{}", host));
+
+                    let _result = request.send().await;
+
+                    link.send_message(Message::Load);
+                });
+
+                false
+            }
+            Message::Dismiss(host) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::delete(&format!("api/v1/suggested-exclusions/{}", host));
+
+                    let _result = request.send().await;
+
+                    link.send_message(Message::Load);
+                });
+
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if self.suggested_exclusions.is_empty() {
+            return html! {};
+        }
+
+        let render_suggestion = |suggestion: &SuggestedExclusion| {
+            let host = suggestion.host.clone();
+            let apply = ctx.link().callback(move |_| Message::Apply(host.clone()));
+            let host = suggestion.host.clone();
+            let dismiss = ctx.link().callback(move |_| Message::Dismiss(host.clone()));
+
+            html! {
+                <li class="py-3 flex justify-between items-center">
+                    <div>
+                        <p class="text-sm font-medium text-gray-900">{ &suggestion.host }</p>
+                        <p class="text-sm text-gray-500">
+                            { format!("{} failed handshakes from {}", suggestion.failures, suggestion.clients.join(", ")) }
+                        </p>
+                        <p class="text-xs text-gray-400">{ &suggestion.last_error }</p>
+                    </div>
+                    <div class="space-x-4">
+                        <button onclick={apply} class="text-sm text-blue-600 hover:text-blue-800">{ "Exclude" }</button>
+                        <button onclick={dismiss} class="text-sm text-gray-600 hover:text-gray-800">{ "Dismiss" }</button>
+                    </div>
+                </li>
+            }
+        };
+
+        html! {
+            <div class="mt-8">
+                <h2 class="text-lg font-medium text-gray-900">{ "Suggested exclusions" }</h2>
+                <p class="text-gray-600">
+                    { "Clients repeatedly failed to connect to these hosts through Privaxy. They may pin their certificates or not use HTTPS, excluding them restores them." }
+                </p>
+                <ul class="mt-2 divide-y divide-gray-200">
+                    { for self.suggested_exclusions.iter().map(render_suggestion) }
+                </ul>
+            </div>
+        }
+    }
+}
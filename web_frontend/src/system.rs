@@ -0,0 +1,204 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::ApiError;
+use num_format::{Locale, ToFormattedString};
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct RuleCounts {
+    network: usize,
+    network_exceptions: usize,
+    cosmetic: usize,
+    cosmetic_exceptions: usize,
+    scriptlets: usize,
+    html: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EngineStatus {
+    shards: usize,
+    filter_lists: usize,
+    rules: RuleCounts,
+    custom_rules: RuleCounts,
+    memory_estimate_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SystemStatus {
+    uptime_secs: u64,
+    resident_memory_bytes: Option<u64>,
+    open_connections: u64,
+    runtime_workers: usize,
+    runtime_tasks: usize,
+    engine: EngineStatus,
+}
+
+pub enum Message {
+    Load,
+    Loaded(SystemStatus),
+    Failed(ApiError),
+}
+
+fn format_mib(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn format_uptime(uptime_secs: u64) -> String {
+    let days = uptime_secs / 86_400;
+    let hours = uptime_secs % 86_400 / 3_600;
+    let minutes = uptime_secs % 3_600 / 60;
+
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Resources Privaxy uses, to tell whether filter lists fit the device it runs on.
+pub struct System {
+    status: Option<SystemStatus>,
+    err_msg: Option<String>,
+}
+
+impl Component for System {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            status: None,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+
+                spawn_local(async move {
+                    match Request::get("api/v1/system/status").send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<SystemStatus>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(status) => {
+                self.status = Some(status);
+                self.err_msg = None;
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_row = |label: &str, value: String| {
+            html! {
+                <div class="py-3 sm:grid sm:grid-cols-3 sm:gap-4">
+                    <dt class="text-sm font-medium text-gray-500">{ label }</dt>
+                    <dd class="mt-1 text-sm text-gray-900 sm:mt-0 sm:col-span-2">{ value }</dd>
+                </div>
+            }
+        };
+
+        let render_rule_row = |label: &str, rules: usize, custom_rules: usize| {
+            html! {
+                <tr>
+                    <td class="px-6 py-4 whitespace-nowrap text-sm font-medium text-gray-900">{ label }</td>
+                    <td class="px-6 py-4 whitespace-nowrap text-sm text-gray-500">{ rules.to_formatted_string(&Locale::en) }</td>
+                    <td class="px-6 py-4 whitespace-nowrap text-sm text-gray-500">{ custom_rules.to_formatted_string(&Locale::en) }</td>
+                </tr>
+            }
+        };
+
+        let header_css =
+            "px-6 py-3 text-left text-xs font-medium text-gray-500 uppercase tracking-wider";
+
+        html! {
+            <>
+            <div class="md:flex md:justify-between md:items-center">
+                <h1 class="text-2xl font-bold text-gray-900 pt-1.5">{ "System" }</h1>
+                <PrivaxyButton
+                    color={ButtonColor::Gray}
+                    state={ButtonState::Enabled}
+                    onclick={ctx.link().callback(|_| Message::Load)}
+                    button_text={"Refresh"}
+                />
+            </div>
+            <p class="mt-2 text-gray-600">
+                {"Memory and connections of Privaxy, and what its blocking engine was built from. Each engine shard holds a replica of the engine."}
+            </p>
+            if let Some(err_msg) = &self.err_msg {
+                <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+            }
+            {
+                match &self.status {
+                    None => html! { <div class="mt-4">{"Loading..."}</div> },
+                    Some(status) => {
+                        let engine = &status.engine;
+                        let rules = &engine.rules;
+                        let custom_rules = &engine.custom_rules;
+
+                        html! {
+                            <>
+                            <dl class="mt-4 divide-y divide-gray-200">
+                                { render_row("Uptime", format_uptime(status.uptime_secs)) }
+                                { render_row("Memory", status.resident_memory_bytes.map(format_mib).unwrap_or_else(|| "Unknown".to_string())) }
+                                { render_row("Open connections", status.open_connections.to_formatted_string(&Locale::en)) }
+                                { render_row("Runtime tasks", format!("{} on {} workers", status.runtime_tasks.to_formatted_string(&Locale::en), status.runtime_workers)) }
+                                { render_row("Engine shards", engine.shards.to_string()) }
+                                { render_row("Engine memory", format!("About {}", format_mib(engine.memory_estimate_bytes))) }
+                                { render_row("Filter lists", engine.filter_lists.to_string()) }
+                            </dl>
+                            <div class="mt-4 shadow overflow-hidden border-b border-gray-200 sm:rounded-lg">
+                                <table class="min-w-full divide-y divide-gray-200">
+                                    <thead class="bg-gray-50">
+                                        <tr>
+                                            <th scope="col" class={header_css}>{"Rules"}</th>
+                                            <th scope="col" class={header_css}>{"Filter lists"}</th>
+                                            <th scope="col" class={header_css}>{"Custom filters"}</th>
+                                        </tr>
+                                    </thead>
+                                    <tbody class="bg-white divide-y divide-gray-200">
+                                        { render_rule_row("Network", rules.network, custom_rules.network) }
+                                        { render_rule_row("Network exceptions", rules.network_exceptions, custom_rules.network_exceptions) }
+                                        { render_rule_row("Cosmetic", rules.cosmetic, custom_rules.cosmetic) }
+                                        { render_rule_row("Cosmetic exceptions", rules.cosmetic_exceptions, custom_rules.cosmetic_exceptions) }
+                                        { render_rule_row("Scriptlets", rules.scriptlets, custom_rules.scriptlets) }
+                                        { render_rule_row("HTML filters", rules.html, custom_rules.html) }
+                                    </tbody>
+                                </table>
+                            </div>
+                            </>
+                        }
+                    }
+                }
+            }
+            </>
+        }
+    }
+}
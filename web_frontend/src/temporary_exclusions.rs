@@ -0,0 +1,234 @@
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::{html, Component, Context, Html, Properties};
+
+/// How long sites are paused for from the requests feed.
+const PAUSE_DURATION_SECS: u64 = 30 * 60;
+
+#[derive(Debug, Serialize)]
+struct TemporaryExclusionRequest {
+    host: String,
+    duration: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemporaryExclusion {
+    host: String,
+    expires_at: String,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PauseSiteProps {
+    /// Url of a request made to the site.
+    pub url: String,
+}
+
+pub enum PauseSiteMessage {
+    Pause,
+    Paused,
+    Failed(ApiError),
+}
+
+/// Excludes the host of a request for a while, for pages that break while being filtered.
+pub struct PauseSite {
+    host: Option<String>,
+    pausing: bool,
+    paused: bool,
+    err_msg: Option<String>,
+}
+
+impl Component for PauseSite {
+    type Message = PauseSiteMessage;
+    type Properties = PauseSiteProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let host = url::Url::parse(&ctx.props().url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()));
+
+        Self {
+            host,
+            pausing: false,
+            paused: false,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            PauseSiteMessage::Pause => {
+                let host = match &self.host {
+                    Some(host) if !self.pausing => host.clone(),
+                    _ => return false,
+                };
+
+                self.pausing = true;
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::post("api/v1/exclusions/temporary")
+                        .header("Content-Type", "application/json")
+                        .body(
+                            serde_json::to_string(&TemporaryExclusionRequest {
+                                host,
+                                duration: PAUSE_DURATION_SECS,
+                            })
+                            .unwrap(),
+                        );
+
+                    match request.send().await {
+                        Ok(response) => {
+                            if response.ok() {
+                                link.send_message(PauseSiteMessage::Paused);
+                            } else {
+                                link.send_message(PauseSiteMessage::Failed(
+                                    response.json::<ApiError>().await.unwrap(),
+                                ));
+                            }
+                        }
+                        Err(err) => link.send_message(PauseSiteMessage::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+            }
+            PauseSiteMessage::Paused => {
+                self.pausing = false;
+                self.paused = true;
+            }
+            PauseSiteMessage::Failed(err) => {
+                self.pausing = false;
+                self.err_msg = Some(err.error);
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let host = match &self.host {
+            Some(host) => host,
+            None => return html! {},
+        };
+
+        if self.paused {
+            return html! {
+                <span class="text-xs text-gray-500">{ format!("Paused {} for 30 minutes", host) }</span>
+            };
+        }
+
+        html! {
+            <>
+                <button
+                    onclick={ctx.link().callback(|_| PauseSiteMessage::Pause)}
+                    disabled={self.pausing}
+                    title={format!("Stop filtering {} for 30 minutes", host)}
+                    class="text-xs text-blue-600 hover:text-blue-800 disabled:opacity-50">
+                    { "Pause for 30 minutes" }
+                </button>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="text-xs text-red-500">{ err_msg }</p>
+                }
+            </>
+        }
+    }
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<TemporaryExclusion>),
+    Resume(String),
+}
+
+/// Lists temporary exclusions that did not expire yet.
+pub struct TemporaryExclusions {
+    temporary_exclusions: Vec<TemporaryExclusion>,
+}
+
+impl Component for TemporaryExclusions {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            temporary_exclusions: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/exclusions/temporary");
+
+                    if let Ok(response) = request.send().await {
+                        if response.ok() {
+                            if let Ok(temporary_exclusions) =
+                                response.json::<Vec<TemporaryExclusion>>().await
+                            {
+                                link.send_message(Message::Loaded(temporary_exclusions));
+                            }
+                        }
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(temporary_exclusions) => {
+                self.temporary_exclusions = temporary_exclusions;
+
+                true
+            }
+            Message::Resume(host) => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::delete(&format!("api/v1/exclusions/temporary/{}", host));
+
+                    let _result = request.send().await;
+
+                    link.send_message(Message::Load);
+                });
+
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if self.temporary_exclusions.is_empty() {
+            return html! {};
+        }
+
+        let render_exclusion = |exclusion: &TemporaryExclusion| {
+            let host = exclusion.host.clone();
+            let onclick = ctx.link().callback(move |_| Message::Resume(host.clone()));
+
+            html! {
+                <li class="py-3 flex justify-between items-center">
+                    <div>
+                        <p class="text-sm font-medium text-gray-900">{ &exclusion.host }</p>
+                        <p class="text-sm text-gray-500">{ format!("Until {}", exclusion.expires_at) }</p>
+                    </div>
+                    <button {onclick} class="text-sm text-blue-600 hover:text-blue-800">{ "Resume filtering" }</button>
+                </li>
+            }
+        };
+
+        html! {
+            <div class="mt-8">
+                <h2 class="text-lg font-medium text-gray-900">{ "Paused sites" }</h2>
+                <p class="text-gray-600">
+                    { "These hosts are temporarily excluded, they are filtered again once the pause expires." }
+                </p>
+                <ul class="mt-2 divide-y divide-gray-200">
+                    { for self.temporary_exclusions.iter().map(render_exclusion) }
+                </ul>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::ApiError;
+use num_format::{Locale, ToFormattedString};
+use reqwasm::http::Request;
+use serde::Deserialize;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+const PERIODS: [(&str, &str); 3] = [
+    ("24h", "Last 24 hours"),
+    ("7d", "Last 7 days"),
+    ("30d", "Last 30 days"),
+];
+
+/// Number of companies listed.
+const COMPANIES_SHOWN: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TopEntry {
+    name: String,
+    requests: u64,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<TopEntry>),
+    UpdatePeriod(String),
+    Failed(ApiError),
+}
+
+/// Tracking companies whose domains were blocked the most, according to the DuckDuckGo
+/// Tracker Radar.
+pub struct TopCompanies {
+    period: String,
+    companies: Option<Vec<TopEntry>>,
+    err_msg: Option<String>,
+}
+
+impl Component for TopCompanies {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            period: PERIODS[0].0.to_string(),
+            companies: None,
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                let url = format!(
+                    "api/v1/statistics/top?kind=companies&period={}&limit={}",
+                    self.period, COMPANIES_SHOWN
+                );
+
+                spawn_local(async move {
+                    match Request::get(&url).send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<Vec<TopEntry>>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(companies) => {
+                self.companies = Some(companies);
+                self.err_msg = None;
+
+                true
+            }
+            Message::UpdatePeriod(period) => {
+                self.period = period;
+                ctx.link().send_message(Message::Load);
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let on_period_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdatePeriod(select.value())
+        });
+
+        let render_company = |company: &TopEntry| {
+            html! {
+                <li class="relative bg-white py-5 px-4">
+                    <div class="flex justify-between space-x-3">
+                        <p class="min-w-0 flex-1 text-sm font-medium text-gray-900 truncate">{ &company.name }</p>
+                        <div class="flex-shrink-0 whitespace-nowrap text-sm text-gray-500">{ company.requests.to_formatted_string(&Locale::en) }</div>
+                    </div>
+                </li>
+            }
+        };
+
+        html! {
+            <div class="mt-8 bg-white overflow-hidden shadow rounded-lg divide-y divide-gray-200">
+                <div class="px-4 py-5 sm:px-6 flex items-center justify-between">
+                    <h3 class="text-lg font-medium">{ "Top tracking companies blocked" }</h3>
+                    <select onchange={on_period_change} class="shadow border rounded py-1 px-2 text-sm text-gray-700">
+                        { for PERIODS.iter().map(|(period, label)| html! {
+                            <option value={*period} selected={*period == self.period}>{ *label }</option>
+                        }) }
+                    </select>
+                </div>
+                <div class="px-4 py-5 sm:p-6">
+                    if let Some(err_msg) = &self.err_msg {
+                        <p class="text-red-500 text-sm">{ err_msg }</p>
+                    }
+                    {
+                        match &self.companies {
+                            Some(companies) if companies.is_empty() => html! {
+                                <p class="text-sm text-gray-500">{ "No known tracker was blocked during this period." }</p>
+                            },
+                            Some(companies) => html! {
+                                <ol role="list" class="divide-y divide-gray-200">
+                                    { for companies.iter().map(render_company) }
+                                </ol>
+                            },
+                            None => html! {},
+                        }
+                    }
+                </div>
+            </div>
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use crate::button::{ButtonColor, ButtonState, PrivaxyButton};
+use crate::ApiError;
+use reqwasm::http::Request;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{html, Component, Context, Html};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlRewrite {
+    pattern: String,
+    regex: bool,
+    replacement: String,
+    action: String,
+    enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct UrlRewriteHits {
+    pattern: String,
+    hits: u64,
+    last_hit_at: Option<String>,
+}
+
+pub enum Message {
+    Load,
+    Loaded(Vec<UrlRewrite>),
+    LoadHits,
+    LoadedHits(Vec<UrlRewriteHits>),
+    UpdateNewPattern(String),
+    UpdateNewRegex(bool),
+    UpdateNewReplacement(String),
+    UpdateNewAction(String),
+    SetEnabled(usize, bool),
+    Add,
+    Delete(usize),
+    Save(Vec<UrlRewrite>),
+    Failed(ApiError),
+}
+
+/// Redirects and rewrites of requested URLs, such as to the old Reddit design or to
+/// an Invidious instance.
+pub struct UrlRewrites {
+    url_rewrites: Option<Vec<UrlRewrite>>,
+    hits: Vec<UrlRewriteHits>,
+    new_pattern: String,
+    new_regex: bool,
+    new_replacement: String,
+    new_action: String,
+    err_msg: Option<String>,
+}
+
+impl Component for UrlRewrites {
+    type Message = Message;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Message::Load);
+
+        Self {
+            url_rewrites: None,
+            hits: Vec::new(),
+            new_pattern: String::new(),
+            new_regex: false,
+            new_replacement: String::new(),
+            new_action: "redirect".to_string(),
+            err_msg: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Message::Load => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/rewrites");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => {
+                            link.send_message(Message::Loaded(
+                                response.json::<Vec<UrlRewrite>>().await.unwrap(),
+                            ));
+                        }
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::Loaded(url_rewrites) => {
+                self.url_rewrites = Some(url_rewrites);
+                ctx.link().send_message(Message::LoadHits);
+
+                true
+            }
+            Message::LoadHits => {
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::get("api/v1/rewrites/hits");
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::LoadedHits(
+                            response.json::<Vec<UrlRewriteHits>>().await.unwrap(),
+                        )),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                false
+            }
+            Message::LoadedHits(hits) => {
+                self.hits = hits;
+
+                true
+            }
+            Message::UpdateNewPattern(new_pattern) => {
+                self.new_pattern = new_pattern;
+
+                true
+            }
+            Message::UpdateNewRegex(new_regex) => {
+                self.new_regex = new_regex;
+
+                true
+            }
+            Message::UpdateNewReplacement(new_replacement) => {
+                self.new_replacement = new_replacement;
+
+                true
+            }
+            Message::UpdateNewAction(new_action) => {
+                self.new_action = new_action;
+
+                true
+            }
+            Message::SetEnabled(index, enabled) => {
+                if let Some(url_rewrites) = &self.url_rewrites {
+                    let mut url_rewrites = url_rewrites.clone();
+                    if let Some(url_rewrite) = url_rewrites.get_mut(index) {
+                        url_rewrite.enabled = enabled;
+                    }
+                    ctx.link().send_message(Message::Save(url_rewrites));
+                }
+
+                false
+            }
+            Message::Add => {
+                let pattern = self.new_pattern.trim().to_string();
+                let replacement = self.new_replacement.trim().to_string();
+                if pattern.is_empty() || replacement.is_empty() {
+                    return false;
+                }
+
+                if let Some(url_rewrites) = &self.url_rewrites {
+                    let mut url_rewrites = url_rewrites.clone();
+                    url_rewrites.push(UrlRewrite {
+                        pattern,
+                        regex: self.new_regex,
+                        replacement,
+                        action: self.new_action.clone(),
+                        enabled: true,
+                    });
+
+                    self.new_pattern = String::new();
+                    self.new_replacement = String::new();
+                    ctx.link().send_message(Message::Save(url_rewrites));
+                }
+
+                true
+            }
+            Message::Delete(index) => {
+                if let Some(url_rewrites) = &self.url_rewrites {
+                    let mut url_rewrites = url_rewrites.clone();
+                    if index < url_rewrites.len() {
+                        url_rewrites.remove(index);
+                    }
+                    ctx.link().send_message(Message::Save(url_rewrites));
+                }
+
+                false
+            }
+            Message::Save(url_rewrites) => {
+                self.err_msg = None;
+
+                let link = ctx.link().clone();
+                spawn_local(async move {
+                    let request = Request::put("api/v1/rewrites")
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_string(&url_rewrites).unwrap());
+
+                    match request.send().await {
+                        Ok(response) if response.ok() => link.send_message(Message::Load),
+                        Ok(response) => link.send_message(Message::Failed(
+                            response.json::<ApiError>().await.unwrap(),
+                        )),
+                        Err(err) => link.send_message(Message::Failed(ApiError {
+                            error: format!("{:?}", err),
+                        })),
+                    }
+                });
+
+                true
+            }
+            Message::Failed(err) => {
+                self.err_msg = Some(err.error);
+
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let render_row = |(index, url_rewrite): (usize, &UrlRewrite)| {
+            let on_enabled_change = ctx.link().callback(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                Message::SetEnabled(index, input.checked())
+            });
+            let on_delete = ctx.link().callback(move |_| Message::Delete(index));
+
+            let (label, css) = match url_rewrite.action.as_str() {
+                "rewrite" => ("Rewrite", "bg-gray-100 text-gray-800"),
+                _ => ("Redirect", "bg-green-100 text-green-800"),
+            };
+
+            let hits = match self
+                .hits
+                .iter()
+                .find(|hits| hits.pattern == url_rewrite.pattern)
+            {
+                Some(UrlRewriteHits {
+                    hits,
+                    last_hit_at: Some(last_hit_at),
+                    ..
+                }) => format!("{}, last {}", hits, last_hit_at),
+                _ => "0".to_string(),
+            };
+
+            html! {
+                <tr>
+                    <td class="px-2 py-1 text-sm font-medium text-gray-900">
+                        <label class="flex items-center">
+                            <input onchange={on_enabled_change} type="checkbox" checked={url_rewrite.enabled}
+                                class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                            <span class={classes!("break-all", url_rewrite.regex.then_some("font-mono"))}>{ &url_rewrite.pattern }</span>
+                        </label>
+                    </td>
+                    <td class="px-2 py-1 text-sm text-gray-900 break-all">{ &url_rewrite.replacement }</td>
+                    <td class="px-2 py-1">
+                        <span class={classes!("rounded", "px-2", "py-1", "text-xs", "font-medium", css)}>{ label }</span>
+                    </td>
+                    <td class="px-2 py-1 text-sm text-gray-500">{ hits }</td>
+                    <td class="px-2 py-1 text-right">
+                        <button onclick={on_delete} class="text-sm text-red-600 hover:text-red-800">{ "Remove" }</button>
+                    </td>
+                </tr>
+            }
+        };
+
+        let on_new_pattern_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewPattern(input.value())
+        });
+
+        let on_new_regex_change = ctx.link().callback(|e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewRegex(input.checked())
+        });
+
+        let on_new_replacement_input = ctx.link().callback(|e: InputEvent| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            Message::UpdateNewReplacement(input.value())
+        });
+
+        let on_new_action_change = ctx.link().callback(|e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            Message::UpdateNewAction(select.value())
+        });
+
+        let add_button_state = if self.new_pattern.trim().is_empty()
+            || self.new_replacement.trim().is_empty()
+            || self.url_rewrites.is_none()
+        {
+            ButtonState::Disabled
+        } else {
+            ButtonState::Enabled
+        };
+
+        html! {
+            <>
+                <h1 class="text-2xl font-bold text-gray-900">{ "Redirects" }</h1>
+                <div class="text-gray-600">
+                    <p>{ "Send requests for a URL to another one, before they reach the server. The first enabled rule matching a request applies." }</p>
+                    <p>
+                        { "Redirects are followed by the browser, rewrites fetch the other URL in place of the requested one. " }
+                        { "A regular expression only replaces the part of the URL it matches, with " }
+                        <span class="font-mono bg-gray-100">{ "$1" }</span>{ " standing for its first group, such as " }
+                        <span class="font-mono bg-gray-100">{ "^https://(www\\.)?reddit\\.com/(.*)" }</span>{ " to " }
+                        <span class="font-mono bg-gray-100">{ "https://old.reddit.com/$2" }</span>{ "." }
+                    </p>
+                </div>
+                if let Some(err_msg) = &self.err_msg {
+                    <p class="mt-4 text-red-500 text-sm">{ err_msg }</p>
+                }
+                <div class="mt-4 overflow-x-auto">
+                    <table class="min-w-full">
+                        <thead>
+                            <tr>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Pattern" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Replacement" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Action" }</th>
+                                <th scope="col" class="px-2 py-2 text-left text-xs font-medium text-gray-500 uppercase tracking-wider">{ "Hits" }</th>
+                                <th scope="col"></th>
+                            </tr>
+                        </thead>
+                        <tbody class="divide-y divide-gray-200">
+                            { for self.url_rewrites.iter().flat_map(|url_rewrites| url_rewrites.iter().enumerate()).map(render_row) }
+                        </tbody>
+                    </table>
+                </div>
+                <div class="mt-4 flex space-x-3">
+                    <input oninput={on_new_pattern_input} value={self.new_pattern.clone()} type="text" placeholder="https://www.youtube.com/"
+                        class="shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <label class="flex items-center text-sm text-gray-700">
+                        <input onchange={on_new_regex_change} type="checkbox" checked={self.new_regex}
+                            class="h-4 w-4 text-blue-600 border-gray-300 rounded mr-2" />
+                        { "Regex" }
+                    </label>
+                    <input oninput={on_new_replacement_input} value={self.new_replacement.clone()} type="text" placeholder="https://yewtu.be/"
+                        class="shadow appearance-none border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" />
+                    <select onchange={on_new_action_change} class="shadow border rounded py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline">
+                        <option value="redirect" selected={self.new_action == "redirect"}>{ "Redirect" }</option>
+                        <option value="rewrite" selected={self.new_action == "rewrite"}>{ "Rewrite" }</option>
+                    </select>
+                    <PrivaxyButton color={ButtonColor::Blue} state={add_button_state} onclick={ctx.link().callback(|_| Message::Add)} button_text="Add rule" />
+                </div>
+            </>
+        }
+    }
+}